@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::platform;
+
+/// Prefix of cached binaries and manifests
+const BINARY_PREFIX: &str = "claude-";
+const MANIFEST_PREFIX: &str = "manifest-";
+
+/// Suffix of a binary that is still being downloaded
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Marks a file being copied into the cache, followed by the copier's pid
+const TEMP_MARKER: &str = ".tmp-";
+
+/// Age after which another process's staging file is assumed abandoned
+const ORPHAN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Extensions of archives kept next to the binaries extracted from them
+const ARCHIVE_EXTENSIONS: [&str; 2] = [".tar.gz", ".zip"];
+
+/// Suffix of the detached signature cached next to a manifest
+const SIGNATURE_SUFFIX: &str = ".minisig";
+
+/// How many checksum characters go into a cached binary's file name
+const CHECKSUM_KEY_LEN: usize = 16;
+
+/// A file in the downloads cache
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub version: String,
+    /// Platform id for binaries, `None` for manifests
+    pub platform: Option<String>,
+    /// An interrupted download that the next install will resume
+    pub partial: bool,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl CacheEntry {
+    /// Time since the entry was last written
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.modified)
+            .unwrap_or_default()
+    }
+}
+
+/// Directory holding downloaded binaries and manifests
+pub fn dir() -> Result<PathBuf> {
+    Ok(platform::get_paths()?.downloads_dir)
+}
+
+/// Cache location of a verified binary, keyed by version, platform, and
+/// checksum so a re-published build never matches a stale file
+pub fn binary_path(version: &str, platform: &str, checksum: &str) -> Result<PathBuf> {
+    let key = &checksum[..checksum.len().min(CHECKSUM_KEY_LEN)];
+    Ok(dir()?.join(format!("{}{}-{}-{}", BINARY_PREFIX, version, platform, key)))
+}
+
+/// Where the archive a binary is extracted from is kept, e.g.
+/// `claude-<version>-<platform>-<key>.tar.gz`
+pub fn archive_path(binary_path: &Path, extension: &str) -> PathBuf {
+    let mut name = binary_path.as_os_str().to_os_string();
+    name.push(format!(".{}", extension));
+    PathBuf::from(name)
+}
+
+/// Where a binary is downloaded to before its checksum is verified
+pub fn partial_path(binary_path: &Path) -> PathBuf {
+    let mut name = binary_path.as_os_str().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Where this process stages a file before renaming it to `path`, so an
+/// interrupted copy never sits at the final path
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!("{}{}", TEMP_MARKER, std::process::id()));
+    PathBuf::from(name)
+}
+
+/// Remove staging files left behind by runs that crashed or were killed
+pub fn remove_orphaned_temp_files() {
+    let Some(read_dir) = dir().ok().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return;
+    };
+    let own = format!("{}{}", TEMP_MARKER, std::process::id());
+    for entry in read_dir.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.contains(TEMP_MARKER) || name.ends_with(&own) {
+            continue;
+        }
+        // A concurrent install may still be copying a recent one
+        let abandoned = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .map(|modified| modified.elapsed().unwrap_or_default() >= ORPHAN_AGE)
+            .unwrap_or(false);
+        if abandoned {
+            // Archives are extracted into staging directories
+            let removed = if entry.path().is_dir() {
+                std::fs::remove_dir_all(entry.path())
+            } else {
+                std::fs::remove_file(entry.path())
+            };
+            match removed {
+                Ok(()) => tracing::debug!("removed orphaned {}", entry.path().display()),
+                Err(e) => tracing::debug!("could not remove {}: {}", entry.path().display(), e),
+            }
+        }
+    }
+}
+
+/// Remove the staging files this process started, when it stops partway
+pub fn remove_own_temp_files() {
+    let Some(read_dir) = dir().ok().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return;
+    };
+    let own = format!("{}{}", TEMP_MARKER, std::process::id());
+    for entry in read_dir.flatten() {
+        if !entry.file_name().to_string_lossy().ends_with(&own) {
+            continue;
+        }
+        let removed = if entry.path().is_dir() {
+            std::fs::remove_dir_all(entry.path())
+        } else {
+            std::fs::remove_file(entry.path())
+        };
+        if let Err(e) = removed {
+            tracing::debug!("could not remove {}: {}", entry.path().display(), e);
+        }
+    }
+}
+
+/// Cache location of a version's manifest
+pub fn manifest_path(version: &str) -> Result<PathBuf> {
+    Ok(dir()?.join(format!("{}{}.json", MANIFEST_PREFIX, version)))
+}
+
+/// Everything in the cache, oldest first
+pub fn entries() -> Result<Vec<CacheEntry>> {
+    let dir = dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(&dir).context("Failed to read downloads directory")? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.contains(TEMP_MARKER) {
+            continue;
+        }
+        let partial = name.ends_with(PARTIAL_SUFFIX);
+        let Some((version, platform)) = parse_name(name.trim_end_matches(PARTIAL_SUFFIX)) else {
+            continue;
+        };
+
+        entries.push(CacheEntry {
+            path: entry.path(),
+            version,
+            platform,
+            partial,
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        });
+    }
+
+    entries.sort_by_key(|e| e.modified);
+    Ok(entries)
+}
+
+/// Cached binaries whose file name carries `checksum`, under any version or
+/// platform, so an identical build published again is not downloaded twice.
+/// Callers still verify the full checksum.
+pub fn binaries_with_checksum(checksum: &str) -> Vec<PathBuf> {
+    let key = &checksum[..checksum.len().min(CHECKSUM_KEY_LEN)];
+    let suffix = format!("-{}", key);
+    entries()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.platform.is_some() && !entry.partial)
+        .filter(|entry| entry.path.to_string_lossy().ends_with(&suffix))
+        .map(|entry| entry.path)
+        .collect()
+}
+
+/// Remove a cache entry, along with a manifest's signature
+pub fn remove(path: &Path) -> Result<()> {
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    let mut signature = path.as_os_str().to_os_string();
+    signature.push(SIGNATURE_SUFFIX);
+    let _ = std::fs::remove_file(signature);
+    Ok(())
+}
+
+/// Version and platform from a cache file name. Also recognizes the
+/// `claude-<version>-<platform>` files left behind by failed installs from
+/// before binaries were keyed by checksum.
+fn parse_name(name: &str) -> Option<(String, Option<String>)> {
+    if let Some(version) = name
+        .strip_prefix(MANIFEST_PREFIX)
+        .and_then(|rest| rest.strip_suffix(".json"))
+    {
+        return Some((version.to_string(), None));
+    }
+
+    let rest = name.strip_prefix(BINARY_PREFIX)?;
+    let rest = ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|extension| rest.strip_suffix(extension))
+        .unwrap_or(rest);
+    let rest = match rest.rsplit_once('-') {
+        Some((head, key))
+            if key.len() == CHECKSUM_KEY_LEN && key.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            head
+        }
+        _ => rest,
+    };
+
+    // Platform ids are `<os>-<arch>`; the version is whatever precedes them
+    let mut parts = rest.rsplitn(3, '-');
+    let arch = parts.next()?;
+    let os = parts.next()?;
+    let version = parts.next()?;
+    Some((version.to_string(), Some(format!("{}-{}", os, arch))))
+}
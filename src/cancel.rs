@@ -0,0 +1,61 @@
+//! Ctrl+C handling. The handler only sets a flag, which downloads, child
+//! processes, and deploys check at points where they can stop without
+//! leaving anything half written; a second Ctrl+C exits at once.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::error::Failure;
+use crate::output::reporter;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Changes that took effect before Ctrl+C, to list when the command stops
+static COMMITTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Catch Ctrl+C so the command can stop cleanly
+pub fn install_handler() {
+    let installed = ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            std::process::exit(Failure::Interrupted.exit_code().into());
+        }
+        eprintln!("\nStopping... press Ctrl+C again to quit immediately");
+    });
+    if let Err(e) = installed {
+        tracing::debug!("could not install the Ctrl+C handler: {}", e);
+    }
+}
+
+/// Whether Ctrl+C has been pressed
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Stop here if Ctrl+C has been pressed
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        return Err(Failure::Interrupted.into());
+    }
+    Ok(())
+}
+
+/// Note a change that has taken effect, such as a binary put in place
+pub fn committed(change: impl Into<String>) {
+    if let Ok(mut committed) = COMMITTED.lock() {
+        committed.push(change.into());
+    }
+}
+
+/// Say what, if anything, was changed before Ctrl+C stopped the command
+pub fn report_aborted() {
+    let committed = COMMITTED.lock().map(|c| c.clone()).unwrap_or_default();
+    if committed.is_empty() {
+        reporter().finish_failure("Aborted — no changes were committed");
+        return;
+    }
+    reporter().finish_failure("Aborted — these changes were already committed:");
+    for change in committed {
+        reporter().output(format!("    {}", change));
+    }
+}
@@ -1,32 +1,325 @@
-use clap::{Parser, Subcommand};
+use clap::builder::{FalseyValueParser, PossibleValue, PossibleValuesParser};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::download::Channel;
+use crate::platform::{Editor, EnvMode};
 
 #[derive(Parser)]
 #[command(name = "code-assist")]
 #[command(author, version, about = "Cross-platform CLI for installing AI coding assistants")]
 #[command(propagate_version = true)]
 pub struct Cli {
+    /// Omitted on a terminal to start the guided installer
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 
     /// Skip confirmation prompts
     #[arg(short, long, global = true)]
     pub yes: bool,
 
-    /// Enable verbose output
-    #[arg(short, long, global = true)]
-    pub verbose: bool,
+    /// Fail instead of prompting, naming the flag that answers the prompt;
+    /// on by default when stdin isn't a terminal (set the variable to 0 to
+    /// answer prompts from a pipe)
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_NON_INTERACTIVE",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub non_interactive: bool,
+
+    /// Directory with local artifacts (defaults to `local/` next to the executable)
+    #[arg(long, global = true, env = "CODE_ASSIST_LOCAL_DIR", value_name = "PATH")]
+    pub local_dir: Option<PathBuf>,
+
+    /// Skip all network access and use local artifacts only
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_OFFLINE",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub offline: bool,
+
+    /// Base URL to download releases from instead of the public bucket;
+    /// repeat to list mirrors in the order to try them (file:// works too)
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_DOWNLOAD_URL",
+        value_name = "URL",
+        value_delimiter = ','
+    )]
+    pub download_url: Vec<String>,
+
+    /// Proxy for all downloads (overrides HTTPS_PROXY and friends; NO_PROXY still applies)
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Extra root certificate to trust for downloads, PEM or DER (repeatable;
+    /// NODE_EXTRA_CA_CERTS and deployed certificates are trusted too)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub ca_cert: Vec<PathBuf>,
+
+    /// Seconds to wait for a connection or for data before giving up on the
+    /// remote (defaults to 10 to connect and 30 between reads)
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_TIMEOUT",
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timeout: Option<u64>,
+
+    /// Attempts per download before falling back to local artifacts
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_RETRIES",
+        value_name = "N",
+        default_value_t = 3,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for each one after (e.g. 500ms, 2s)
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_RETRY_DELAY",
+        value_name = "DELAY",
+        default_value = "1s",
+        value_parser = parse_duration
+    )]
+    pub retry_delay: Duration,
+
+    /// Bearer token for mirrors that require one (prefer the environment
+    /// variable, which doesn't show up in the process list)
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_DOWNLOAD_TOKEN",
+        value_name = "TOKEN",
+        hide_env_values = true
+    )]
+    pub auth_token: Option<String>,
+
+    /// Install even if the release manifest's signature is missing or invalid
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_ALLOW_UNSIGNED",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub allow_unsigned: bool,
+
+    /// Minisign public key release manifests must be signed with; without
+    /// one, signatures aren't checked
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_MANIFEST_KEY",
+        value_name = "KEY"
+    )]
+    pub manifest_key: Option<String>,
+
+    /// Download and install even if free disk space looks too low
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_SKIP_SPACE_CHECK",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub skip_space_check: bool,
+
+    /// Reinstall every extension, even ones already installed at the same
+    /// or a newer version
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_FORCE_EXTENSIONS",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub force_extensions: bool,
+
+    /// Overwrite deployed files such as snippets even if they were changed
+    /// since they were deployed, and reinstall a version that is already
+    /// installed
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_FORCE",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub force: bool,
+
+    /// Replace settings keys you changed since the last deploy with the
+    /// template's values, instead of keeping yours
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_FORCE_SETTINGS",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub force_settings: bool,
+
+    /// Ask about each settings key you changed since the last deploy
+    /// before replacing it with the template's value
+    #[arg(long, global = true, conflicts_with = "force_settings")]
+    pub interactive: bool,
+
+    /// Link deployed files that aren't merged, such as certificates,
+    /// CLAUDE.md, and snippets, to their templates instead of copying them,
+    /// so changes to a shared local directory apply without redeploying
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_LINK",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub link: bool,
+
+    /// Don't import certificates into the system trust store, for machines
+    /// whose policy forbids changing it; NODE_EXTRA_CA_CERTS is still set
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_SKIP_CERT_IMPORT",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub skip_cert_import: bool,
+
+    /// Add the install directory to the Windows user PATH even when that
+    /// makes it longer than Windows keeps whole
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_FORCE_PATH",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub force_path: bool,
+
+    /// Where to keep the environment variables set on Windows: the
+    /// registry, a marked block in the PowerShell profile, or both
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "CODE_ASSIST_ENV_MODE",
+        value_name = "MODE",
+        default_value_t
+    )]
+    pub env_mode: EnvMode,
+
+    /// When another run is already installing or configuring, wait for it
+    /// to finish instead of exiting
+    #[arg(
+        long,
+        global = true,
+        env = "CODE_ASSIST_WAIT",
+        value_parser = FalseyValueParser::new()
+    )]
+    pub wait: bool,
+
+    /// Editor to install extensions and deploy settings to (defaults to
+    /// every one installed of VS Code, VS Code Insiders, and VSCodium)
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "CODE_ASSIST_EDITOR",
+        value_name = "VARIANT"
+    )]
+    pub editor: Option<Editor>,
+
+    /// Print what would be done without changing anything
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Disable colored output and Unicode symbols (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Only print errors and the final result
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Enable verbose output (-v for debug, -vv for trace)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Check prerequisites (VS Code, Git)
-    Check,
+    /// Check prerequisites (VS Code, Git, and whatever else tools need)
+    Check {
+        /// Check only what this tool needs (e.g., claude-code)
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// Print results as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Install a tool and configure environment
+    #[command(disable_version_flag = true)]
     Install {
         /// Tool to install (e.g., claude-code)
+        #[arg(short, long, required_unless_present = "all")]
+        tool: Option<String>,
+
+        /// Install every available tool
+        #[arg(long, conflicts_with_all = ["tool", "version"])]
+        all: bool,
+
+        /// Install a specific version instead of the latest (e.g., 2.1.31)
+        #[arg(long, value_name = "X.Y.Z")]
+        version: Option<String>,
+
+        /// Release channel to install from (defaults to the installed
+        /// channel, then the configured one, then stable)
+        #[arg(long, value_enum)]
+        channel: Option<Channel>,
+
+        /// Allow --channel to move an installed tool to a different channel
+        #[arg(long, requires = "channel")]
+        switch_channel: bool,
+
+        /// Configuration profile in local/profiles/ to deploy (defaults to
+        /// the one last deployed, or `default`)
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// Install for every user of the machine, in Program Files or
+        /// /usr/local, instead of the current user's home; needs an elevated
+        /// prompt or sudo, and leaves configuration to each user
+        #[arg(long, conflicts_with = "profile")]
+        system: bool,
+
+        /// Write a JSON record of what the install did to PATH, or to stdout
+        /// for `-`, also when it fails
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+
+    /// Update installed tools to the latest version on their channel
+    Update {
+        /// Tool to update (defaults to every installed tool)
         #[arg(short, long)]
-        tool: String,
+        tool: Option<String>,
+
+        /// Release channel to update from (defaults to the installed channel)
+        #[arg(long, value_enum)]
+        channel: Option<Channel>,
+
+        /// Allow --channel to move an installed tool to a different channel
+        #[arg(long, requires = "channel")]
+        switch_channel: bool,
     },
 
     /// Uninstall a tool and remove configuration
@@ -34,6 +327,43 @@ pub enum Commands {
         /// Tool to uninstall
         #[arg(short, long)]
         tool: String,
+
+        /// Also remove deployed configuration, certificates, cached downloads,
+        /// and environment changes
+        #[arg(long)]
+        purge: bool,
+
+        /// Remove the install made with `install --system`, leaving each
+        /// user's own install alone
+        #[arg(long, conflicts_with = "purge")]
+        system: bool,
+
+        /// Write a JSON record of what was removed and what couldn't be to
+        /// PATH, or to stdout for `-`
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+    },
+
+    /// Restore a previously installed version of a tool
+    Rollback {
+        /// Tool to roll back
+        #[arg(short, long)]
+        tool: String,
+
+        /// Retained version to restore (defaults to the one before the current)
+        #[arg(long, value_name = "VERSION")]
+        to: Option<String>,
+    },
+
+    /// Restore settings files from a backup taken before a deploy changed them
+    Restore {
+        /// Tool whose settings to restore
+        #[arg(short, long)]
+        tool: String,
+
+        /// Backup to restore (defaults to the newest, or a choice when interactive)
+        #[arg(long, value_name = "TIMESTAMP")]
+        timestamp: Option<String>,
     },
 
     /// Apply/update configuration without reinstalling
@@ -41,8 +371,224 @@ pub enum Commands {
         /// Tool to configure
         #[arg(short, long)]
         tool: String,
+
+        /// Configuration profile in local/profiles/ to deploy (defaults to
+        /// the one last deployed, or `default`)
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// Report how the configuration differs from the templates without
+        /// changing anything; exits 9 if it does
+        #[arg(long)]
+        check: bool,
+
+        /// Print the --check report as JSON
+        #[arg(long, requires = "check")]
+        json: bool,
+    },
+
+    /// List the versions of a tool available to install, newest first
+    Versions {
+        /// Tool to list versions of
+        #[arg(short, long)]
+        tool: String,
+
+        /// Show at most this many versions
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Print results as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check the local artifacts directory for missing or corrupt files
+    Verify {
+        /// Print results as JSON on stdout
+        #[arg(long)]
+        json: bool,
     },
 
     /// List available tools and their installation status
+    List {
+        /// Print results as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show detailed status for each tool
+    Status {
+        /// Only show this tool
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// Print results as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Write the installed tools, their versions and what code-assist
+    /// deployed for them to a file another machine can import
+    Export {
+        /// File to write (defaults to stdout)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Install and configure tools to match a file written by `export`
+    Import {
+        /// Setup file to match
+        #[arg(value_name = "PATH")]
+        file: PathBuf,
+    },
+
+    /// Diagnose a broken installation
+    Doctor,
+
+    /// Show the environment variables and PATH entries code-assist set,
+    /// where each is saved, and whether this shell has them
+    Env {
+        /// Only show this tool
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// Print statements that bring this shell up to date instead, for
+        /// `eval "$(code-assist env --shell zsh)"`
+        #[arg(long, value_enum, value_name = "SHELL")]
+        shell: Option<crate::environment::Shell>,
+    },
+
+    /// Show recent entries from the log file every run appends to
+    Logs {
+        /// Show at most this many lines
+        #[arg(long, value_name = "N", default_value_t = 50)]
+        tail: usize,
+
+        /// Only show the most recent run
+        #[arg(long)]
+        last_run: bool,
+
+        /// Print where the log file is instead
+        #[arg(long, conflicts_with_all = ["tail", "last_run"])]
+        path: bool,
+    },
+
+    /// Replace this code-assist with the newest published build
+    SelfUpdate {
+        /// Only report whether a newer build is available
+        #[arg(long)]
+        check: bool,
+
+        /// Where to look for builds (overrides CODE_ASSIST_SELF_UPDATE_URL
+        /// and `self_update_url` in the config file)
+        #[arg(long, value_name = "URL")]
+        url: Option<String>,
+    },
+
+    /// Inspect the user config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Inspect or clear cached downloads
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the effective configuration and where each value came from
+    Show,
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// List cached binaries and manifests with their size and age
     List,
+
+    /// Delete cached downloads older than a given age
+    Clean {
+        /// Minimum age to delete, e.g. 12h, 30d, or 2w
+        #[arg(long, value_name = "AGE", default_value = "30d", value_parser = parse_duration)]
+        older_than: Duration,
+
+        /// Delete everything regardless of age
+        #[arg(long, conflicts_with = "older_than")]
+        all: bool,
+    },
+
+    /// Print the cache directory
+    Path,
+}
+
+/// Parse a duration like `30d`: a number followed by ms, s, m, h, d, or w
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a number followed by a unit, got '{}'", value))?;
+
+    let seconds = match unit {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}', expected ms, s, m, h, d, or w", unit)),
+    };
+
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("'{}' is too long", value))
+}
+
+/// Build the clap command for completion generation, offering `tool_names`
+/// as the values of every `--tool` argument
+pub fn completion_command(tool_names: &[String]) -> clap::Command {
+    let mut command = Cli::command();
+
+    let with_tool: Vec<String> = command
+        .get_subcommands()
+        .filter(|sub| sub.get_arguments().any(|arg| arg.get_id() == "tool"))
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+
+    for name in with_tool {
+        let values = PossibleValuesParser::new(tool_names.iter().map(PossibleValue::new));
+        command = command.mut_subcommand(name, |sub| {
+            sub.mut_arg("tool", |arg| arg.value_parser(values))
+        });
+    }
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_that_overflow_are_refused() {
+        assert_eq!(
+            parse_duration("2w"),
+            Ok(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("99999999999999999w"),
+            Err("'99999999999999999w' is too long".to_string())
+        );
+    }
 }
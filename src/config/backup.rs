@@ -0,0 +1,250 @@
+//! Copies of settings files taken before a deploy changes them, so a bad
+//! template can be undone with `restore`
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::utc_timestamp;
+use crate::output::reporter;
+use crate::plan;
+use crate::platform::PlatformPaths;
+use crate::state::{Backup, State};
+
+/// Backups kept when the config file doesn't say
+pub const DEFAULT_KEEP: usize = 10;
+
+/// Directory holding one directory per backup, named by its timestamp
+pub fn dir(paths: &PlatformPaths) -> PathBuf {
+    paths.backups_dir.clone()
+}
+
+/// The backup one deploy (or restore) writes to, named by when it started
+pub struct Snapshot {
+    timestamp: String,
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl Snapshot {
+    pub fn new(paths: &PlatformPaths, keep: usize) -> Self {
+        let timestamp = utc_timestamp(SystemTime::now());
+        Self {
+            dir: dir(paths).join(&timestamp),
+            timestamp,
+            keep,
+        }
+    }
+
+    /// Copy `file` into the snapshot's `label` directory before it's changed,
+    /// and record the copy in `state`. A missing file has nothing to lose, and
+    /// nothing is copied when no backups are kept.
+    pub fn save(&self, file: &Path, label: &str, state: &mut State, dry_run: bool) -> Result<()> {
+        if self.keep == 0 || !file.exists() {
+            return Ok(());
+        }
+
+        let copy = self
+            .dir
+            .join(label)
+            .join(file.file_name().unwrap_or_default());
+        if dry_run {
+            plan::step(format!("Back up {} to {}", file.display(), copy.display()));
+            return Ok(());
+        }
+        // Saved already this second, and that copy is the older one
+        if copy.exists() {
+            return Ok(());
+        }
+
+        if let Some(parent) = copy.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(file, &copy)
+            .with_context(|| format!("Failed to back up {}", file.display()))?;
+        state.record_backup(&self.timestamp, file, &copy);
+        reporter().detail(format!("Backed up to {}", copy.display()));
+        Ok(())
+    }
+}
+
+/// Delete the oldest backups until at most `keep` remain
+pub fn prune(state: &mut State, paths: &PlatformPaths, keep: usize) -> Result<()> {
+    state.backups.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    let excess = state.backups.len().saturating_sub(keep);
+    for backup in state.backups.drain(..excess) {
+        let backup_dir = dir(paths).join(&backup.timestamp);
+        if backup_dir.exists() {
+            std::fs::remove_dir_all(&backup_dir)
+                .with_context(|| format!("Failed to remove {}", backup_dir.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete every backup in `state`, and the backups directory once empty
+///
+/// With `dry_run`, print each removal instead of making it.
+pub fn remove_all(state: &State, paths: &PlatformPaths, dry_run: bool) -> Result<()> {
+    for backup in &state.backups {
+        let backup_dir = dir(paths).join(&backup.timestamp);
+        if !backup_dir.exists() {
+            continue;
+        }
+        if dry_run {
+            plan::step(format!("Remove settings backup {}", backup_dir.display()));
+            continue;
+        }
+        std::fs::remove_dir_all(&backup_dir)
+            .with_context(|| format!("Failed to remove {}", backup_dir.display()))?;
+        reporter().success(format!("Removed settings backup {}", backup.timestamp));
+    }
+    if !dry_run {
+        std::fs::remove_dir(dir(paths)).ok();
+    }
+    Ok(())
+}
+
+/// Recorded backups whose copies still exist, newest first
+pub fn list(paths: &PlatformPaths, tool: &str) -> Result<Vec<Backup>> {
+    let Some(state) = State::load(paths, tool)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut backups: Vec<Backup> = state
+        .backups
+        .into_iter()
+        .map(|mut backup| {
+            backup.files.retain(|file| file.copy.exists());
+            backup
+        })
+        .filter(|backup| !backup.files.is_empty())
+        .collect();
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(backups)
+}
+
+/// Copy `backup`'s files back over the originals. The current files are
+/// backed up first, so the restore can itself be undone.
+///
+/// With `dry_run`, print each copy instead of making it.
+pub fn restore(
+    paths: &PlatformPaths,
+    tool: &str,
+    backup: &Backup,
+    keep: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let mut state = State::load(paths, tool)?.unwrap_or_default();
+    let snapshot = Snapshot::new(paths, keep);
+
+    for file in &backup.files {
+        let label = file
+            .copy
+            .parent()
+            .and_then(Path::file_name)
+            .unwrap_or_default()
+            .to_string_lossy();
+        snapshot.save(&file.original, &label, &mut state, dry_run)?;
+
+        if dry_run {
+            plan::step(format!(
+                "Restore {} from {}",
+                file.original.display(),
+                file.copy.display()
+            ));
+            continue;
+        }
+        if let Some(parent) = file.original.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(&file.copy, &file.original)
+            .with_context(|| format!("Failed to restore {}", file.original.display()))?;
+        reporter().success(format!("Restored {}", file.original.display()));
+    }
+
+    if !dry_run {
+        prune(&mut state, paths, keep)?;
+        state.save(paths, tool)?;
+    }
+    Ok(())
+}
+
+/// A line of a diff between two versions of a file
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff turning `old` into `new`, by longest common subsequence.
+/// Settings files are small, so the quadratic table is fine.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: length of the longest common subsequence of old[i..]
+    // and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        let old = "{\n  \"a\": 1,\n  \"b\": 2\n}";
+        let new = "{\n  \"a\": 1,\n  \"b\": 3,\n  \"c\": 4\n}";
+
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                DiffLine::Same("{"),
+                DiffLine::Same("  \"a\": 1,"),
+                DiffLine::Removed("  \"b\": 2"),
+                DiffLine::Added("  \"b\": 3,"),
+                DiffLine::Added("  \"c\": 4"),
+                DiffLine::Same("}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_text_has_no_changes() {
+        let text = "one\ntwo\n";
+
+        assert!(diff_lines(text, text)
+            .iter()
+            .all(|line| matches!(line, DiffLine::Same(_))));
+    }
+}
@@ -0,0 +1,721 @@
+//! Root certificates for TLS-inspecting proxies: copied into the certs
+//! directory, imported into the system trust store, and combined into one
+//! bundle for `NODE_EXTRA_CA_CERTS`, which only takes a single file
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use super::files::{stage_copy, stage_link};
+use super::Transaction;
+use crate::output::reporter;
+use crate::plan;
+use crate::platform::{self, Environment, PlatformPaths};
+use crate::state::State;
+
+/// Every deployed certificate in one file, in the certs directory
+pub(super) const BUNDLE_FILE: &str = "ca-bundle.pem";
+
+/// Our bundle combined with the file `NODE_EXTRA_CA_CERTS` already pointed
+/// at, in the certs directory
+pub(super) const MERGED_BUNDLE_FILE: &str = "ca-bundle-merged.pem";
+
+/// Extensions certificate files are recognized by
+const CERT_EXTENSIONS: [&str; 3] = ["crt", "pem", "cer"];
+
+/// Days before a certificate expires that deploys and `doctor` start
+/// warning about it
+const EXPIRY_WARNING_DAYS: i64 = 30;
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Certificate files shipped in the platform config tree, in name order
+pub(super) fn certificate_files(config_dir: &Path) -> Result<Vec<PathBuf>> {
+    // Look for certificates in different possible locations
+    let cert_sources = [
+        config_dir.join(".continue").join("certs"),
+        config_dir.join("certs"),
+    ];
+
+    let mut files = Vec::new();
+
+    for cert_source in &cert_sources {
+        if !cert_source.exists() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(cert_source)? {
+            let path = entry?.path();
+            if is_certificate_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+/// Whether `path` is named like a certificate, skipping macOS resource forks
+/// and the bundles, which only repeat the others
+pub fn is_certificate_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if name.starts_with("._") || name == BUNDLE_FILE || name == MERGED_BUNDLE_FILE {
+        return false;
+    }
+    path.extension().is_some_and(|e| {
+        CERT_EXTENSIONS
+            .iter()
+            .any(|ext| e.eq_ignore_ascii_case(ext))
+    })
+}
+
+/// Certificates a deploy copied into the certs directory
+#[derive(Debug, Default)]
+pub(super) struct DeployedCerts {
+    /// The certificate files, without the bundle
+    pub files: Vec<DeployedCert>,
+    /// Whether any certificate in the bundle hasn't expired
+    pub any_valid: bool,
+    /// Contents of the bundle, if there were certificates to put in it
+    pub bundle: Option<String>,
+}
+
+/// A certificate file copied into the certs directory
+#[derive(Debug)]
+pub(super) struct DeployedCert {
+    pub path: PathBuf,
+    /// Thumbprints of the certificates in it, which the trust store knows
+    /// them by
+    pub thumbprints: Vec<String>,
+}
+
+/// Stage copies of the certificates in the certs directory, converting any
+/// in DER form to PEM, and a bundle of all of them. With `link`, PEM
+/// certificates are linked to their templates instead. Each certificate is
+/// checked first: one that can't be parsed fails the deploy, and expired,
+/// soon to expire, or non-CA certificates are warned about.
+pub(super) fn deploy_certificates(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    link: bool,
+    state: &mut State,
+    tx: &mut Transaction,
+    dry_run: bool,
+) -> Result<DeployedCerts> {
+    let certs = certificate_files(config_dir)?;
+
+    let mut deployed = DeployedCerts::default();
+    let mut pems = Vec::new();
+
+    for path in certs {
+        let file_name = path.file_name().unwrap_or_default();
+        let dest = paths.certs_dir.join(file_name);
+
+        let content = std::fs::read(&path)
+            .with_context(|| format!("Failed to read certificate {}", path.display()))?;
+        let infos = read_certificates(&content)
+            .with_context(|| format!("{} is not a valid certificate", path.display()))?;
+        let (pem, converted) = match pem_text(&content) {
+            Some(text) => (text.to_string(), false),
+            None => (der_to_pem(&content), true),
+        };
+        pems.push(pem.clone());
+        deployed.any_valid |= infos.iter().any(|info| info.expiry != Expiry::Expired);
+
+        let link = link && !converted;
+        if dry_run {
+            if link {
+                plan::step(format!(
+                    "Link certificate {} to {}",
+                    dest.display(),
+                    path.display()
+                ));
+            } else {
+                plan::step(format!(
+                    "Copy certificate {} to {}{}",
+                    path.display(),
+                    dest.display(),
+                    if converted { " as PEM" } else { "" }
+                ));
+            }
+            warn_about(&file_name.to_string_lossy(), &infos);
+            deployed.files.push(DeployedCert::new(dest, &infos));
+            continue;
+        }
+
+        let target = std::path::absolute(&path)?;
+        let linked = link
+            && match stage_link(&dest, &target, "Certificates", state, tx) {
+                Ok(()) => true,
+                Err(e) => {
+                    reporter().warn(format!("{:#}; copying it instead", e));
+                    false
+                }
+            };
+        if !linked {
+            let content = if converted { pem.into_bytes() } else { content };
+            stage_copy(&dest, content, "Certificates", state, tx)?;
+        }
+        state.record_file(&dest);
+
+        let note = if converted { " (converted to PEM)" } else { "" };
+        let verb = if linked { "Linked" } else { "Deployed" };
+        reporter().success(format!(
+            "{} certificate: {}{}",
+            verb,
+            file_name.to_string_lossy(),
+            note
+        ));
+        for info in &infos {
+            reporter().detail(info.describe());
+        }
+        reporter().detail(dest.display());
+        warn_about(&file_name.to_string_lossy(), &infos);
+
+        deployed.files.push(DeployedCert::new(dest, &infos));
+    }
+
+    if deployed.files.is_empty() {
+        reporter().skip("No certificates to deploy");
+        return Ok(deployed);
+    }
+
+    let bundle_path = paths.certs_dir.join(BUNDLE_FILE);
+    let (bundle, count) = bundle(&pems);
+    if dry_run {
+        plan::step(format!(
+            "Write {} certificate(s) to {}",
+            count,
+            bundle_path.display()
+        ));
+    } else {
+        tx.stage(&bundle_path, bundle.clone(), "Certificates")?;
+        state.record_file(&bundle_path);
+        reporter().success(format!("Bundled {} certificate(s)", count));
+        reporter().detail(bundle_path.display());
+    }
+    deployed.bundle = Some(bundle);
+
+    Ok(deployed)
+}
+
+impl DeployedCert {
+    fn new(path: PathBuf, infos: &[CertInfo]) -> Self {
+        Self {
+            path,
+            thumbprints: infos.iter().map(|info| info.thumbprint.clone()).collect(),
+        }
+    }
+}
+
+/// Where `NODE_EXTRA_CA_CERTS` should point after a deploy
+#[derive(Debug)]
+pub(super) struct NodeCaCerts {
+    pub path: PathBuf,
+    /// What it was set to before code-assist pointed it at our bundle
+    pub previous: Option<String>,
+}
+
+/// Decide where `NODE_EXTRA_CA_CERTS` should point, or `None` to leave it
+/// alone. If another tool already pointed it at a bundle of its own, a
+/// merged bundle of theirs and ours is staged so both keep working; a value
+/// naming a missing file is replaced, saying so.
+pub(super) fn node_extra_ca_certs(
+    paths: &PlatformPaths,
+    environment: &dyn Environment,
+    deployed: &DeployedCerts,
+    force: bool,
+    state: &mut State,
+    tx: &mut Transaction,
+    dry_run: bool,
+) -> Result<Option<NodeCaCerts>> {
+    // In a dry run nothing was written, so use the bundle that would have
+    // been rather than the one on disk
+    let bundle_path = paths.certs_dir.join(BUNDLE_FILE);
+    let Some(ours) = deployed
+        .bundle
+        .clone()
+        .or_else(|| std::fs::read_to_string(&bundle_path).ok())
+    else {
+        return Ok(None);
+    };
+    if !deployed.files.is_empty() && !deployed.any_valid && !force {
+        reporter().error(
+            "Every deployed certificate has expired, so NODE_EXTRA_CA_CERTS was not \
+             pointed at them (use --force to set it anyway)",
+        );
+        return Ok(None);
+    }
+
+    // A value that isn't one of our bundles was set by something else since
+    // we last looked; otherwise fall back to the one we found the first time
+    let merged_path = paths.certs_dir.join(MERGED_BUNDLE_FILE);
+    let foreign = environment
+        .get_user_env_var("NODE_EXTRA_CA_CERTS")
+        .filter(|value| {
+            let value = Path::new(value);
+            value != bundle_path && value != merged_path
+        });
+    let previous = foreign.clone().or_else(|| {
+        state
+            .env_var_previous("NODE_EXTRA_CA_CERTS")
+            .map(str::to_string)
+    });
+    let Some(other) = previous.as_deref().map(PathBuf::from) else {
+        return Ok(Some(NodeCaCerts {
+            path: bundle_path,
+            previous,
+        }));
+    };
+
+    let Ok(content) = std::fs::read(&other) else {
+        if foreign.is_some() {
+            reporter().warn(format!(
+                "NODE_EXTRA_CA_CERTS pointed at {}, which doesn't exist; replacing it",
+                other.display()
+            ));
+        }
+        return Ok(Some(NodeCaCerts {
+            path: bundle_path,
+            previous,
+        }));
+    };
+
+    let theirs = match pem_text(&content) {
+        Some(text) => text.to_string(),
+        None => der_to_pem(&content),
+    };
+    let (merged, count) = bundle(&[ours, theirs]);
+    if dry_run {
+        plan::step(format!(
+            "Combine {} with {} in {}",
+            bundle_path.display(),
+            other.display(),
+            merged_path.display()
+        ));
+    } else {
+        tx.stage(&merged_path, merged, "Certificates")?;
+        state.record_file(&merged_path);
+        reporter().success(format!(
+            "Combined {} certificate(s) with those NODE_EXTRA_CA_CERTS already named",
+            count
+        ));
+        reporter().detail(other.display());
+        reporter().detail(merged_path.display());
+    }
+
+    Ok(Some(NodeCaCerts {
+        path: merged_path,
+        previous,
+    }))
+}
+
+/// What a deploy would write to the certs directory: each certificate, as
+/// PEM, and then the bundle. Empty if there are no certificates.
+pub(super) fn expected_certificates(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut expected = Vec::new();
+    let mut pems = Vec::new();
+    for path in certificate_files(config_dir)? {
+        let dest = paths.certs_dir.join(path.file_name().unwrap_or_default());
+        let content = std::fs::read(&path)
+            .with_context(|| format!("Failed to read certificate {}", path.display()))?;
+        let (pem, written) = match pem_text(&content) {
+            Some(text) => (text.to_string(), content.clone()),
+            None => {
+                let pem = der_to_pem(&content);
+                (pem.clone(), pem.into_bytes())
+            }
+        };
+        pems.push(pem);
+        expected.push((dest, written));
+    }
+
+    if !pems.is_empty() {
+        let (bundle, _) = bundle(&pems);
+        expected.push((paths.certs_dir.join(BUNDLE_FILE), bundle.into_bytes()));
+    }
+    Ok(expected)
+}
+
+/// Warn about the certificates in the file `name` that have expired or soon
+/// will, or that can't act as a root
+fn warn_about(name: &str, infos: &[CertInfo]) {
+    for info in infos {
+        if let Some(problem) = info.problem() {
+            let message = format!("Certificate {} ({}) {}", name, info.subject, problem);
+            if info.expiry == Expiry::Expired {
+                reporter().error(message);
+            } else {
+                reporter().warn(message);
+            }
+        }
+    }
+}
+
+/// Try to import deployed certificates into the user's trust store,
+/// recording the ones that weren't there before so uninstalling can take
+/// them out again
+pub(super) fn import_certificates(certs: &[DeployedCert], state: &mut State, dry_run: bool) {
+    for cert in certs {
+        match platform::import_certificate(&cert.path, &cert.thumbprints, dry_run) {
+            Ok(_) if dry_run => {}
+            Ok(added) if added.is_empty() => reporter().skip(format!(
+                "{} is already trusted",
+                cert.path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+            Ok(added) => {
+                reporter().success(format!(
+                    "Imported certificate into the trust store: {}",
+                    cert.path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                for thumbprint in added {
+                    state.record_trusted_certificate(&thumbprint);
+                }
+            }
+            Err(e) => reporter().warn(format!("Certificate import: {:#}", e)),
+        }
+    }
+}
+
+/// How close a certificate is to the end of its validity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    Valid,
+    /// Expires within `EXPIRY_WARNING_DAYS`
+    Soon {
+        days: i64,
+    },
+    Expired,
+}
+
+/// What a certificate says about itself
+#[derive(Debug, Clone)]
+pub struct CertInfo {
+    pub subject: String,
+    /// SHA-256 of the DER encoding, as colon-separated hex
+    pub fingerprint: String,
+    /// SHA-1 of the DER encoding, as uppercase hex, which is how Windows
+    /// and macOS identify certificates in their stores
+    pub thumbprint: String,
+    /// Last day it's valid, as `YYYY-MM-DD` in UTC
+    pub not_after: String,
+    /// Whether it may sign other certificates, as a root must
+    pub is_ca: bool,
+    pub expiry: Expiry,
+}
+
+impl CertInfo {
+    fn parse(der: &[u8]) -> Result<Self> {
+        let (_, cert) =
+            X509Certificate::from_der(der).map_err(|e| anyhow!("invalid certificate: {}", e))?;
+
+        let validity = cert.validity();
+        let expiry = match validity.time_to_expiration() {
+            None => Expiry::Expired,
+            Some(left) if left.whole_days() < EXPIRY_WARNING_DAYS => Expiry::Soon {
+                days: left.whole_days(),
+            },
+            Some(_) => Expiry::Valid,
+        };
+        // Version 1 roots predate extensions, so being self-signed is as
+        // close as they come to saying they're a CA
+        let is_ca = match cert.basic_constraints() {
+            Ok(Some(constraints)) => constraints.value.ca,
+            _ => cert.subject() == cert.issuer(),
+        };
+
+        let fingerprint: Vec<String> = Sha256::digest(der)
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            fingerprint: fingerprint.join(":"),
+            thumbprint: hex::encode_upper(
+                ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, der).as_ref(),
+            ),
+            not_after: validity.not_after.to_datetime().date().to_string(),
+            is_ca,
+            expiry,
+        })
+    }
+
+    /// Subject, fingerprint, and expiry date on one line
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, SHA-256 {}, valid until {}",
+            self.subject, self.fingerprint, self.not_after
+        )
+    }
+
+    /// What's wrong with the certificate as a trusted root, if anything
+    pub fn problem(&self) -> Option<String> {
+        match self.expiry {
+            Expiry::Expired => Some(format!(
+                "EXPIRED on {}; TLS connections relying on it will fail",
+                self.not_after
+            )),
+            Expiry::Soon { days } => Some(format!(
+                "expires in {} day(s), on {}; ask for a renewed certificate",
+                days, self.not_after
+            )),
+            Expiry::Valid if !self.is_ca => {
+                Some("is not a CA certificate, so it can't act as a root".to_string())
+            }
+            Expiry::Valid => None,
+        }
+    }
+}
+
+/// The certificates in a PEM file, or the one in a DER file
+pub fn read_certificates(content: &[u8]) -> Result<Vec<CertInfo>> {
+    let Some(text) = pem_text(content) else {
+        return Ok(vec![CertInfo::parse(content)?]);
+    };
+
+    pem_blocks(text)
+        .iter()
+        .map(|block| {
+            let body: String = block
+                .lines()
+                .filter(|line| !line.starts_with("-----"))
+                .collect();
+            let der = STANDARD
+                .decode(body)
+                .map_err(|e| anyhow!("invalid PEM: {}", e))?;
+            CertInfo::parse(&der)
+        })
+        .collect()
+}
+
+/// `content` as text if it holds PEM certificates; anything else is taken
+/// to be a single DER certificate
+fn pem_text(content: &[u8]) -> Option<&str> {
+    std::str::from_utf8(content)
+        .ok()
+        .filter(|text| text.contains(PEM_BEGIN))
+}
+
+/// A DER certificate wrapped as PEM, in 64-character lines
+fn der_to_pem(der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = format!("{}\n", PEM_BEGIN);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(PEM_END);
+    pem.push('\n');
+    pem
+}
+
+/// The certificate blocks in PEM text, each with its markers and with the
+/// line endings normalized
+fn pem_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let Some(len) = rest[start..].find(PEM_END) else {
+            break;
+        };
+        let end = start + len + PEM_END.len();
+        let block: Vec<&str> = rest[start..end].lines().map(str::trim).collect();
+        blocks.push(block.join("\n"));
+        rest = &rest[end..];
+    }
+    blocks
+}
+
+/// Every certificate in `pems` in one PEM file, each once and in the order
+/// first seen, with how many there are
+fn bundle(pems: &[String]) -> (String, usize) {
+    let mut blocks: Vec<String> = Vec::new();
+    for block in pems.iter().flat_map(|pem| pem_blocks(pem)) {
+        if !blocks.contains(&block) {
+            blocks.push(block);
+        }
+    }
+    let mut bundle = String::new();
+    for block in &blocks {
+        bundle.push_str(block);
+        bundle.push('\n');
+    }
+    (bundle, blocks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fake::FakeEnvironment;
+
+    /// A self-signed certificate in DER, valid until `not_after`
+    fn certificate(ca: bool, not_after: time::OffsetDateTime) -> Vec<u8> {
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = if ca {
+            rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained)
+        } else {
+            rcgen::IsCa::ExplicitNoCa
+        };
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "Test Root");
+        params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+        params.not_after = not_after;
+        let key = rcgen::KeyPair::generate().unwrap();
+        params.self_signed(&key).unwrap().der().to_vec()
+    }
+
+    fn in_days(days: i64) -> time::OffsetDateTime {
+        time::OffsetDateTime::now_utc() + time::Duration::days(days)
+    }
+
+    fn pem(body: &str) -> String {
+        format!("{}\n{}\n{}\n", PEM_BEGIN, body, PEM_END)
+    }
+
+    #[test]
+    fn der_is_wrapped_as_pem() {
+        let der = vec![0x30u8; 60];
+        let pem = der_to_pem(&der);
+
+        let lines: Vec<&str> = pem.lines().collect();
+        assert_eq!(lines.first(), Some(&PEM_BEGIN));
+        assert_eq!(lines.last(), Some(&PEM_END));
+        assert_eq!(lines[1].len(), 64);
+        assert_eq!(STANDARD.decode(lines[1..3].concat()).unwrap(), der);
+        assert!(pem_text(pem.as_bytes()).is_some());
+        assert!(pem_text(&der).is_none());
+    }
+
+    #[test]
+    fn bundle_holds_each_certificate_once() {
+        let chain = format!("{}{}", pem("AAAA"), pem("BBBB"));
+        let crlf = pem("AAAA").replace('\n', "\r\n");
+
+        let (bundle, count) = bundle(&[chain, pem("CCCC"), crlf]);
+
+        assert_eq!(count, 3);
+        assert_eq!(
+            bundle,
+            format!("{}{}{}", pem("AAAA"), pem("BBBB"), pem("CCCC"))
+        );
+    }
+
+    #[test]
+    fn reads_subject_fingerprint_and_expiry() {
+        let der = certificate(true, rcgen::date_time_ymd(2090, 6, 30));
+
+        let infos = read_certificates(&der_to_pem(&der).into_bytes()).unwrap();
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].subject, "CN=Test Root");
+        assert_eq!(infos[0].not_after, "2090-06-30");
+        assert_eq!(infos[0].fingerprint.len(), 32 * 3 - 1);
+        assert_eq!(infos[0].thumbprint.len(), 40);
+        assert_eq!(infos[0].thumbprint, infos[0].thumbprint.to_uppercase());
+        assert!(infos[0].is_ca);
+        assert_eq!(infos[0].expiry, Expiry::Valid);
+        assert_eq!(infos[0].problem(), None);
+        // DER files hold the same certificate
+        assert_eq!(
+            read_certificates(&der).unwrap()[0].fingerprint,
+            infos[0].fingerprint
+        );
+    }
+
+    #[test]
+    fn flags_expired_soon_to_expire_and_leaf_certificates() {
+        let expired = read_certificates(&certificate(true, in_days(-1))).unwrap();
+        assert_eq!(expired[0].expiry, Expiry::Expired);
+        assert!(expired[0].problem().unwrap().contains("EXPIRED"));
+
+        let soon = read_certificates(&certificate(true, in_days(10))).unwrap();
+        assert!(matches!(soon[0].expiry, Expiry::Soon { days: 9 | 10 }));
+
+        let leaf = read_certificates(&certificate(false, in_days(365))).unwrap();
+        assert!(!leaf[0].is_ca);
+        assert!(leaf[0].problem().unwrap().contains("not a CA"));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_certificates() {
+        assert!(read_certificates(b"cert").is_err());
+        assert!(read_certificates(pem("AAAA").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn merges_with_the_bundle_node_already_trusted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = PlatformPaths {
+            home_dir: dir.path().to_path_buf(),
+            claude_config_dir: dir.path().join(".claude"),
+            app_data_dir: dir.path().join(".config"),
+            certs_dir: dir.path().join("certs"),
+            vscode_user_dir: None,
+            downloads_dir: dir.path().join("downloads"),
+            state_dir: dir.path().join("state"),
+            backups_dir: dir.path().join("backups"),
+        };
+        let theirs = dir.path().join("theirs.pem");
+        std::fs::write(&theirs, format!("{}{}", pem("AAAA"), pem("BBBB"))).unwrap();
+        let deployed = DeployedCerts {
+            bundle: Some(pem("BBBB") + &pem("CCCC")),
+            ..Default::default()
+        };
+        let mut state = State::default();
+        state.record_env_var(
+            "NODE_EXTRA_CA_CERTS",
+            "/elsewhere",
+            Some(theirs.to_str().unwrap()),
+        );
+        let mut tx = Transaction::new();
+        let environment = FakeEnvironment::default();
+
+        let node = node_extra_ca_certs(
+            &paths,
+            &environment,
+            &deployed,
+            false,
+            &mut state,
+            &mut tx,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        tx.commit().unwrap();
+        tx.finish();
+
+        let merged = paths.certs_dir.join(MERGED_BUNDLE_FILE);
+        assert_eq!(node.path, merged);
+        assert_eq!(node.previous.as_deref(), theirs.to_str());
+        assert_eq!(
+            std::fs::read_to_string(&merged).unwrap(),
+            format!("{}{}{}", pem("BBBB"), pem("CCCC"), pem("AAAA"))
+        );
+        assert!(state.files.contains(&merged));
+
+        // A previous value naming a missing file is replaced by our bundle
+        std::fs::remove_file(&theirs).unwrap();
+        let mut tx = Transaction::new();
+        let node = node_extra_ca_certs(
+            &paths,
+            &environment,
+            &deployed,
+            false,
+            &mut state,
+            &mut tx,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(node.path, paths.certs_dir.join(BUNDLE_FILE));
+    }
+}
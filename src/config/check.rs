@@ -0,0 +1,352 @@
+//! `configure --check`: how the machine differs from what a deploy would
+//! leave behind, found without changing anything
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::certs::{self, expected_certificates};
+use super::files::template_files;
+use super::merge::{KeyChange, MergeOptions};
+use super::placeholders::Placeholders;
+use super::{
+    describe_key, describe_value, get_platform_config_dir, list_installed_extensions, mcp,
+    plan_settings_merge, vscode, vscode_settings_template, vscode_template, wanted_extensions,
+    DeployOptions, FileStrategy,
+};
+use crate::doctor;
+use crate::platform::{Environment, PlatformPaths};
+use crate::prerequisites;
+use crate::state::State;
+
+/// One way the machine differs from the mandated configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    /// What drifted: `setting`, `keybinding`, `mcp-server`, `file`,
+    /// `certificate`, `environment`, `path`, or `extension`
+    pub kind: &'static str,
+    /// The file, variable, directory, or extension concerned
+    pub target: String,
+    /// How it differs
+    pub problem: String,
+}
+
+impl Drift {
+    fn new(kind: &'static str, target: impl fmt::Display, problem: impl Into<String>) -> Self {
+        Self {
+            kind,
+            target: target.to_string(),
+            problem: problem.into(),
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.target, self.problem)
+    }
+}
+
+/// Compare the deployed files, settings, environment, PATH, and extensions
+/// with the templates in `local_dir` and the recorded state. Nothing is
+/// written; an empty result means a deploy would have nothing to do.
+pub fn check_configs(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    options: &DeployOptions,
+) -> Result<Vec<Drift>> {
+    let config_dir = get_platform_config_dir(local_dir, &options.tool);
+    let state = State::load(paths, &options.tool)?.unwrap_or_default();
+    let placeholders = Placeholders::new(paths, &options.install_dir);
+    let environment = &*options.host.environment;
+    let editors = prerequisites::target_editors(&*options.host.runner, options.editor);
+    let mut drift = Vec::new();
+
+    if config_dir.exists() {
+        let claude_dir = config_dir.join(".claude");
+        if claude_dir.is_dir() {
+            for relative in template_files(&claude_dir)? {
+                let source = claude_dir.join(&relative);
+                let dest = paths.claude_config_dir.join(&relative);
+                if relative.extension().is_some_and(|e| e == "json") {
+                    check_settings(&source, &dest, &state, &placeholders, &mut drift)?;
+                } else {
+                    check_file(&source, &dest, &mut drift)?;
+                }
+            }
+        }
+
+        let mcp_source = config_dir.join("mcp.json");
+        if mcp_source.exists() {
+            check_mcp_servers(&mcp_source, &paths.claude_json(), &placeholders, &mut drift)?;
+        }
+
+        let certs = expected_certificates(&config_dir, paths)?;
+        for (dest, content) in &certs {
+            match std::fs::read(dest) {
+                Ok(current) if current == *content => {}
+                Ok(_) => drift.push(Drift::new(
+                    "certificate",
+                    dest.display(),
+                    "differs from the template",
+                )),
+                Err(_) => drift.push(Drift::new("certificate", dest.display(), "is missing")),
+            }
+        }
+        if !certs.is_empty() {
+            check_node_extra_ca_certs(paths, environment, &mut drift);
+        }
+
+        for editor in &editors {
+            let user_dir = paths.vscode_settings_dir(*editor);
+            if let Some(source) = vscode_settings_template(&config_dir) {
+                let dest = user_dir.join("settings.json");
+                check_settings(&source, &dest, &state, &placeholders, &mut drift)?;
+            }
+            if let Some(source) = vscode_template(&config_dir, "keybindings.json") {
+                let dest = user_dir.join("keybindings.json");
+                check_keybindings(&source, &dest, &placeholders, &mut drift)?;
+            }
+            if let Some(source) = vscode_template(&config_dir, "snippets") {
+                for relative in template_files(&source)? {
+                    let dest = user_dir.join("snippets").join(&relative);
+                    check_file(&source.join(&relative), &dest, &mut drift)?;
+                }
+            }
+        }
+    }
+
+    for file in &options.files {
+        let dest = placeholders.expand_text(&file.dest, &file.source.display().to_string());
+        let dest = Path::new(&dest);
+        match file.strategy {
+            FileStrategy::Merge => {
+                check_settings(&file.source, dest, &state, &placeholders, &mut drift)?
+            }
+            FileStrategy::Copy => check_file(&file.source, dest, &mut drift)?,
+            FileStrategy::Keep if !dest.exists() => {
+                drift.push(Drift::new("file", dest.display(), "is missing"))
+            }
+            FileStrategy::Keep => {}
+        }
+    }
+
+    // Files only the state knows about, such as a merged certificate bundle
+    for file in &state.files {
+        let target = file.display().to_string();
+        if !file.exists() && !drift.iter().any(|d| d.target == target) {
+            drift.push(Drift::new("file", target, "is missing"));
+        }
+    }
+
+    for var in &state.env_vars {
+        if environment.current_env_var(&var.name).as_deref() != Some(var.value.as_str()) {
+            drift.push(Drift::new(
+                "environment",
+                &var.name,
+                format!("is not set to {}", var.value),
+            ));
+        }
+    }
+
+    let process_path = environment.process_path();
+    for dir in &state.path_entries {
+        if !environment.is_on_user_path(dir) && !doctor::is_in(&process_path, Path::new(dir)) {
+            drift.push(Drift::new("path", dir, "is not on PATH"));
+        }
+    }
+
+    let wanted = wanted_extensions(local_dir)?;
+    if !wanted.is_empty() {
+        for editor in editors {
+            let installed = match list_installed_extensions(&*options.host.runner, editor) {
+                Ok(installed) => installed,
+                Err(e) => {
+                    drift.push(Drift::new("extension", editor, format!("{:#}", e)));
+                    continue;
+                }
+            };
+            for want in &wanted {
+                let found = installed.iter().find(|ext| ext.matches(&want.id));
+                let problem = match (found, &want.version) {
+                    (None, _) => format!("{} is not installed", want.id),
+                    (Some(ext), Some(version)) if !want.is_satisfied_by(ext) => format!(
+                        "{} is at {}, not {}",
+                        want.id,
+                        ext.version.as_deref().unwrap_or("an unknown version"),
+                        version
+                    ),
+                    _ => continue,
+                };
+                drift.push(Drift::new("extension", editor, problem));
+            }
+        }
+    }
+
+    Ok(drift)
+}
+
+/// Keys a merge of the settings template at `source` would change in `dest`
+fn check_settings(
+    source: &Path,
+    dest: &Path,
+    state: &State,
+    placeholders: &Placeholders,
+    drift: &mut Vec<Drift>,
+) -> Result<()> {
+    if !dest.exists() {
+        drift.push(Drift::new("setting", dest.display(), "is missing"));
+        return Ok(());
+    }
+    let mut merge =
+        match plan_settings_merge(source, dest, state, &MergeOptions::default(), placeholders) {
+            Ok(merge) => merge,
+            Err(e) => {
+                drift.push(Drift::new("setting", dest.display(), format!("{:#}", e)));
+                return Ok(());
+            }
+        };
+    if let Some(reason) = &merge.broken {
+        drift.push(Drift::new(
+            "setting",
+            dest.display(),
+            format!("can't be merged into ({})", reason),
+        ));
+        return Ok(());
+    }
+
+    // Keys the user changed since the last deploy are drift all the same,
+    // even though a deploy keeps them
+    for edited in std::mem::take(&mut merge.edited) {
+        merge.overwrite(edited);
+    }
+    for change in merge.changes().0 {
+        let problem = match change {
+            KeyChange::Added { key, value } => format!(
+                "{} is missing (should be {})",
+                describe_key(&key),
+                describe_value(&value)
+            ),
+            KeyChange::Changed { key, from, to } => format!(
+                "{} is {}, not {}",
+                describe_key(&key),
+                describe_value(&from),
+                describe_value(&to)
+            ),
+            KeyChange::Removed { key, .. } => {
+                format!("{} is no longer in the template", describe_key(&key))
+            }
+        };
+        drift.push(Drift::new("setting", dest.display(), problem));
+    }
+    Ok(())
+}
+
+/// Keybindings in the template at `source` that `dest` doesn't bind
+fn check_keybindings(
+    source: &Path,
+    dest: &Path,
+    placeholders: &Placeholders,
+    drift: &mut Vec<Drift>,
+) -> Result<()> {
+    let mut template = serde_json::Value::Array(
+        vscode::read_keybindings(source).context("Failed to parse keybindings template")?,
+    );
+    placeholders.expand(&mut template, source);
+    let serde_json::Value::Array(template) = template else {
+        unreachable!("placeholders only change strings")
+    };
+    let current = if dest.exists() {
+        match vscode::read_keybindings(dest) {
+            Ok(current) => current,
+            Err(e) => {
+                drift.push(Drift::new("keybinding", dest.display(), format!("{:#}", e)));
+                return Ok(());
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    for entry in template {
+        if !current
+            .iter()
+            .any(|existing| vscode::same_binding(existing, &entry))
+        {
+            drift.push(Drift::new(
+                "keybinding",
+                dest.display(),
+                format!(
+                    "{} for {} is missing",
+                    entry.get("key").unwrap_or(&serde_json::Value::Null),
+                    entry.get("command").unwrap_or(&serde_json::Value::Null)
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Servers in the template at `source` missing from Claude's config or
+/// defined differently there
+fn check_mcp_servers(
+    source: &Path,
+    dest: &Path,
+    placeholders: &Placeholders,
+    drift: &mut Vec<Drift>,
+) -> Result<()> {
+    let template = mcp::read_template(placeholders, source)?;
+    let current = mcp::read_servers(dest).unwrap_or_default();
+    for (name, definition) in template {
+        let problem = match current.get(&name) {
+            Some(existing) if *existing == definition => continue,
+            Some(_) => format!("{} differs from the template", name),
+            None => format!("{} is missing", name),
+        };
+        drift.push(Drift::new("mcp-server", dest.display(), problem));
+    }
+    Ok(())
+}
+
+/// Whether a copied template at `dest` still matches `source`
+fn check_file(source: &Path, dest: &Path, drift: &mut Vec<Drift>) -> Result<()> {
+    let expected =
+        std::fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    match std::fs::read(dest) {
+        Ok(current) if current == expected => {}
+        Ok(_) => drift.push(Drift::new(
+            "file",
+            dest.display(),
+            "differs from the template",
+        )),
+        Err(_) => drift.push(Drift::new("file", dest.display(), "is missing")),
+    }
+    Ok(())
+}
+
+/// Node must trust our bundle, or the merged one if another tool's was
+/// there first
+fn check_node_extra_ca_certs(
+    paths: &PlatformPaths,
+    environment: &dyn Environment,
+    drift: &mut Vec<Drift>,
+) {
+    let ours: [PathBuf; 2] = [
+        paths.certs_dir.join(certs::BUNDLE_FILE),
+        paths.certs_dir.join(certs::MERGED_BUNDLE_FILE),
+    ];
+    match environment.current_env_var("NODE_EXTRA_CA_CERTS") {
+        Some(value) if ours.iter().any(|path| path.as_os_str() == value.as_str()) => {}
+        Some(value) => drift.push(Drift::new(
+            "environment",
+            "NODE_EXTRA_CA_CERTS",
+            format!("is {}, not {}", value, ours[0].display()),
+        )),
+        None => drift.push(Drift::new(
+            "environment",
+            "NODE_EXTRA_CA_CERTS",
+            "is not set",
+        )),
+    }
+}
@@ -0,0 +1,953 @@
+//! Editor extensions: marketplace IDs listed in `extensions.json`, and VSIX
+//! files shipped in `VSIX/` for installs that can't reach the marketplace
+
+use anyhow::{Context, Result};
+use console::style;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::cancel;
+use crate::download;
+use crate::error::Failure;
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::plan;
+use crate::platform::{self, Editor, Host, PlatformPaths};
+use crate::prerequisites;
+use crate::process::Runner;
+use crate::state::State;
+
+/// File in the local artifacts directory listing marketplace extensions
+pub const EXTENSIONS_FILE: &str = "extensions.json";
+
+/// `extensions.json`: a list of extensions, or an object with one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExtensionsFile {
+    List(Vec<ExtensionSpec>),
+    Object { extensions: Vec<ExtensionSpec> },
+}
+
+/// `"publisher.name"`, `"publisher.name@1.2.3"`, or
+/// `{ "id": "publisher.name", "version": "1.2.3" }`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExtensionSpec {
+    Id(String),
+    Pinned { id: String, version: Option<String> },
+}
+
+impl ExtensionSpec {
+    fn into_wanted(self) -> WantedExtension {
+        let (id, version) = match self {
+            ExtensionSpec::Id(spec) => match spec.split_once('@') {
+                Some((id, version)) => (id.to_string(), Some(version.to_string())),
+                None => (spec, None),
+            },
+            ExtensionSpec::Pinned { id, version } => (id, version),
+        };
+        WantedExtension {
+            id: id.trim().to_lowercase(),
+            version: version.filter(|v| !v.is_empty()),
+            marketplace: true,
+            vsix: None,
+        }
+    }
+}
+
+/// An extension an install should leave in the editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct WantedExtension {
+    /// Lowercased marketplace ID (`publisher.name`), or only the name for
+    /// an extension that comes just as a VSIX file
+    pub id: String,
+    /// Version required; any version will do if `None`
+    pub version: Option<String>,
+    /// Listed in `extensions.json`, so it can come from the marketplace
+    pub marketplace: bool,
+    /// Local VSIX file to install from offline or if the marketplace fails
+    pub vsix: Option<PathBuf>,
+}
+
+impl WantedExtension {
+    /// Whether `installed` satisfies this, and so needs nothing done: a
+    /// version pinned in `extensions.json` must match exactly, while a VSIX
+    /// file is only worth installing over an older version
+    pub fn is_satisfied_by(&self, installed: &InstalledExtension) -> bool {
+        let Some(wanted) = &self.version else {
+            return true;
+        };
+        match &installed.version {
+            Some(current) if self.marketplace => current == wanted,
+            Some(current) => download::compare_versions(current, wanted).is_ge(),
+            None => false,
+        }
+    }
+
+    /// The argument for `--install-extension` from the marketplace
+    fn marketplace_spec(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.id, version),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// The extensions in `local_dir/extensions.json`, followed by the VSIX files
+/// in `local_dir/VSIX` that aren't among them. A VSIX file of a listed
+/// extension becomes its offline fallback, if its version fits.
+pub fn wanted_extensions(local_dir: &Path) -> Result<Vec<WantedExtension>> {
+    let mut wanted = listed_extensions(local_dir)?;
+
+    for vsix in vsix_files(&local_dir.join("VSIX"))? {
+        let Some(name) = vsix_extension_name(&vsix) else {
+            continue;
+        };
+        let version = vsix_extension_version(&vsix);
+        let listed = wanted.iter_mut().find(|want| {
+            want.marketplace
+                && extension_matches(&want.id, &name)
+                && (want.version.is_none() || want.version == version)
+        });
+        match listed {
+            Some(want) => want.vsix = want.vsix.take().or(Some(vsix)),
+            None => wanted.push(WantedExtension {
+                id: name,
+                version,
+                marketplace: false,
+                vsix: Some(vsix),
+            }),
+        }
+    }
+
+    Ok(wanted)
+}
+
+/// The marketplace extension `id`, pinned to `version` if given, with the
+/// newest VSIX file of it in `local_dir/VSIX` that fits as its offline
+/// fallback. For tools that are themselves an extension.
+pub fn marketplace_extension(
+    local_dir: &Path,
+    id: &str,
+    version: Option<&str>,
+) -> Result<WantedExtension> {
+    let mut want = ExtensionSpec::Pinned {
+        id: id.to_string(),
+        version: version.map(str::to_string),
+    }
+    .into_wanted();
+
+    let mut fallback: Option<(PathBuf, Option<String>)> = None;
+    for vsix in vsix_files(&local_dir.join("VSIX"))? {
+        let Some(name) = vsix_extension_name(&vsix) else {
+            continue;
+        };
+        let vsix_version = vsix_extension_version(&vsix);
+        if !extension_matches(&want.id, &name)
+            || (want.version.is_some() && want.version != vsix_version)
+        {
+            continue;
+        }
+        let newer = match (&fallback, &vsix_version) {
+            (None, _) => true,
+            (Some((_, Some(best))), Some(version)) => {
+                download::compare_versions(version, best).is_gt()
+            }
+            (Some((_, None)), Some(_)) => true,
+            (Some(_), None) => false,
+        };
+        if newer {
+            fallback = Some((vsix, vsix_version));
+        }
+    }
+    want.vsix = fallback.map(|(vsix, _)| vsix);
+
+    Ok(want)
+}
+
+/// Extensions listed in `local_dir/extensions.json`; none if there isn't one
+fn listed_extensions(local_dir: &Path) -> Result<Vec<WantedExtension>> {
+    let path = local_dir.join(EXTENSIONS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: ExtensionsFile = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid extension list {}", path.display()))?;
+    let specs = match file {
+        ExtensionsFile::List(specs) | ExtensionsFile::Object { extensions: specs } => specs,
+    };
+    Ok(specs.into_iter().map(ExtensionSpec::into_wanted).collect())
+}
+
+/// What installing extensions into one editor came to
+#[derive(Debug, Default)]
+struct Summary {
+    installed: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+/// Where and how extensions are installed
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionOptions {
+    /// Editor to install into, instead of every installed one
+    pub editor: Option<Editor>,
+
+    /// Install only from VSIX files, without the marketplace
+    pub offline: bool,
+
+    /// Reinstall extensions that are already up to date
+    pub force: bool,
+
+    /// Runs the editors' CLIs
+    pub host: Host,
+}
+
+/// Install the extensions listed in `local_dir/extensions.json` and the
+/// VSIX files in `local_dir/VSIX`. Extensions already up to date are
+/// skipped unless `options.force`. Listed extensions come from the
+/// marketplace, falling back to a VSIX file of the same extension when
+/// offline or when the marketplace install fails. Extensions that weren't
+/// installed before are recorded in the state file, so uninstalling removes
+/// them and only them.
+///
+/// With `dry_run`, print the extensions that would be installed instead.
+pub fn install_extensions(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    tool: &str,
+    options: &ExtensionOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let wanted = wanted_extensions(local_dir).context(Failure::Configuration(
+        "Failed to read the extensions to install".to_string(),
+    ))?;
+    if wanted.is_empty() {
+        reporter().skip("No extensions to install");
+        return Ok(());
+    }
+
+    install_extension_list(&wanted, local_dir, paths, tool, options, dry_run)
+}
+
+/// Install `wanted` into each targeted editor the way `install_extensions`
+/// does, recording the ones that weren't installed before in `tool`'s state
+pub fn install_extension_list(
+    wanted: &[WantedExtension],
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    tool: &str,
+    options: &ExtensionOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let mut state = super::load_state(local_dir, paths, tool).context(Failure::Configuration(
+        "Failed to read the deployment state".to_string(),
+    ))?;
+    let recorded = state.extensions.len();
+
+    for editor in prerequisites::target_editors(&*options.host.runner, options.editor) {
+        if cancel::is_cancelled() {
+            break;
+        }
+
+        // Under WSL, the host's VS Code is reached through the shim that
+        // Windows puts on the Linux PATH
+        if platform::is_wsl() && editor.cli_on_path().is_none() {
+            reporter().warn(format!(
+                "Skipping {} extensions: its `{}` command from the Windows host isn't on the WSL PATH",
+                editor,
+                editor.command()
+            ));
+            reporter().info(
+                "  Install them from VS Code on Windows, or check that appendWindowsPath isn't turned off in /etc/wsl.conf",
+            );
+            continue;
+        }
+
+        let summary = install_extensions_into(wanted, editor, options, &mut state, dry_run)?;
+        if dry_run {
+            continue;
+        }
+
+        let message = format!(
+            "{} extensions: {} installed, {} already up to date, {} failed",
+            editor, summary.installed, summary.skipped, summary.failed
+        );
+        if summary.failed > 0 {
+            reporter().warn(message);
+        } else {
+            reporter().success(message);
+        }
+        if summary.installed > 0 {
+            cancel::committed(format!(
+                "Installed {} {} extensions",
+                summary.installed, editor
+            ));
+        }
+    }
+
+    // Record what was installed even when Ctrl+C cut the install short
+    if state.extensions.len() != recorded {
+        state.save(paths, tool)?;
+    }
+
+    cancel::check()
+}
+
+/// The extensions of `wanted` that installing would add or change in each
+/// targeted editor, as `id` or `id@version` with the editor
+pub fn extensions_to_install(
+    wanted: &[WantedExtension],
+    options: &ExtensionOptions,
+) -> Vec<(Editor, String)> {
+    let mut missing = Vec::new();
+    for editor in prerequisites::target_editors(&*options.host.runner, options.editor) {
+        let installed =
+            list_installed_extensions(&*options.host.runner, editor).unwrap_or_else(|e| {
+                tracing::debug!("could not list {} extensions: {:#}", editor, e);
+                Vec::new()
+            });
+        for want in wanted {
+            let current = installed.iter().find(|ext| ext.matches(&want.id));
+            if current.is_none_or(|ext| options.force || !want.is_satisfied_by(ext)) {
+                missing.push((editor, want.marketplace_spec()));
+            }
+        }
+    }
+    missing
+}
+
+fn install_extensions_into(
+    wanted: &[WantedExtension],
+    editor: Editor,
+    options: &ExtensionOptions,
+    state: &mut State,
+    dry_run: bool,
+) -> Result<Summary> {
+    let vscode_cli = editor.cli();
+    let runner = &*options.host.runner;
+    let installed = list_installed_extensions(runner, editor).unwrap_or_else(|e| {
+        tracing::debug!("could not list {} extensions: {:#}", editor, e);
+        Vec::new()
+    });
+
+    // Only needed to check VSIX files against
+    let editor_version = wanted
+        .iter()
+        .any(|want| want.vsix.is_some())
+        .then(|| prerequisites::editor_version(runner, editor))
+        .flatten();
+
+    let mut summary = Summary::default();
+    let mut added = Vec::new();
+    for want in wanted {
+        if cancel::is_cancelled() {
+            break;
+        }
+
+        let current = installed.iter().find(|ext| ext.matches(&want.id));
+        if let Some(ext) = current.filter(|ext| !options.force && want.is_satisfied_by(ext)) {
+            reporter().skip(format!(
+                "{} {} is up to date",
+                want.id,
+                ext.version.as_deref().unwrap_or_default()
+            ));
+            summary.skipped += 1;
+            continue;
+        }
+
+        // Marketplace first, then the local file
+        let mut sources = Vec::new();
+        if want.marketplace && !options.offline {
+            sources.push(want.marketplace_spec());
+        }
+        let mut incompatible = None;
+        if let Some(vsix) = &want.vsix {
+            match vsix_incompatibility(runner, vsix, editor, editor_version.as_deref()) {
+                Some(reason) => incompatible = Some(reason),
+                None => sources.push(vsix.to_string_lossy().to_string()),
+            }
+        }
+        if let (true, Some(reason)) = (sources.is_empty(), &incompatible) {
+            reporter().warn(format!("Skipping {}: {}", want.id, reason));
+            summary.failed += 1;
+            continue;
+        }
+        if sources.is_empty() {
+            reporter().error(format!(
+                "Cannot install {} offline without a VSIX file in VSIX/",
+                want.id
+            ));
+            summary.failed += 1;
+            continue;
+        }
+
+        if dry_run {
+            plan::step(format!(
+                "Run '{} --install-extension {}'",
+                vscode_cli.display(),
+                sources[0]
+            ));
+            continue;
+        }
+
+        reporter().info(format!(
+            "Installing extension: {} ({})",
+            style(&want.id).cyan(),
+            editor
+        ));
+
+        let mut errors: Vec<String> = incompatible.into_iter().collect();
+        for source in &sources {
+            let mut command = std::process::Command::new(&vscode_cli);
+            command.args(["--install-extension", source]);
+            // Moving an installed extension to another version needs --force
+            if current.is_some() || options.force {
+                command.arg("--force");
+            }
+            let output = match runner.output(&mut command) {
+                Ok(output) => output,
+                Err(e) if cancel::is_cancelled() => {
+                    errors.push(e.to_string());
+                    break;
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to run the {} CLI", editor))
+                        .context(Failure::Configuration(format!(
+                            "Failed to install {} extensions",
+                            editor
+                        )))
+                }
+            };
+
+            if output.status.success() {
+                errors.clear();
+                break;
+            }
+            errors.push(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        if errors.is_empty() {
+            reporter().success(format!("Installed {}", want.id));
+            events::emit(Event::ExtensionInstalled {
+                editor: editor.to_string(),
+                id: want.id.clone(),
+            });
+            summary.installed += 1;
+            if current.is_none() {
+                added.push(want);
+            }
+        } else {
+            reporter().error(format!(
+                "Failed to install {}: {}",
+                want.id,
+                errors.join("; ")
+            ));
+            summary.failed += 1;
+        }
+    }
+
+    // A VSIX filename doesn't give the publisher, so look up the full IDs
+    if !added.is_empty() {
+        let installed = list_installed_extensions(runner, editor).unwrap_or_default();
+        for want in added {
+            let id = installed
+                .iter()
+                .find(|ext| ext.matches(&want.id))
+                .map_or(want.id.as_str(), |ext| ext.id.as_str());
+            state.record_extension(editor, id);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Uninstall the extensions recorded in the state file as installed by us,
+/// leaving any the user installed alone. Failures are reported rather than
+/// returned, and the extensions that failed stay recorded.
+///
+/// With `dry_run`, print the extensions that would be uninstalled instead.
+pub fn uninstall_extensions(
+    runner: &dyn Runner,
+    paths: &PlatformPaths,
+    tool: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let Some(mut state) = State::load(paths, tool)? else {
+        return Ok(());
+    };
+
+    let mut editors: Vec<Editor> = Vec::new();
+    for ext in &state.extensions {
+        if !editors.contains(&ext.editor) {
+            editors.push(ext.editor);
+        }
+    }
+
+    let mut failed = Vec::new();
+    for editor in editors {
+        let ids: Vec<&str> = state
+            .extensions
+            .iter()
+            .filter(|ext| ext.editor == editor)
+            .map(|ext| ext.id.as_str())
+            .collect();
+        let removal = uninstall_extensions_from(runner, editor, &ids, dry_run);
+        if dry_run {
+            continue;
+        }
+
+        let message = format!(
+            "{} extensions: {} removed, {} already gone, {} failed",
+            editor,
+            removal.removed,
+            removal.gone,
+            removal.failed.len()
+        );
+        if removal.failed.is_empty() {
+            reporter().success(message);
+        } else {
+            reporter().warn(message);
+        }
+        failed.extend(removal.failed.into_iter().map(|id| (editor, id)));
+    }
+
+    if !dry_run && !state.extensions.is_empty() {
+        state
+            .extensions
+            .retain(|ext| failed.contains(&(ext.editor, ext.id.clone())));
+        state.save(paths, tool)?;
+    }
+    Ok(())
+}
+
+/// Uninstall the extension `id` from every targeted editor it's installed
+/// in, whoever installed it, and forget it in `tool`'s state. For tools that
+/// are themselves an extension; fails if it couldn't be removed everywhere.
+///
+/// With `dry_run`, print where it would be uninstalled from instead.
+pub fn uninstall_extension(
+    runner: &dyn Runner,
+    paths: &PlatformPaths,
+    tool: &str,
+    id: &str,
+    editor: Option<Editor>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut found = false;
+    let mut failed = Vec::new();
+    for editor in prerequisites::target_editors(runner, editor) {
+        let installed = list_installed_extensions(runner, editor).unwrap_or_else(|e| {
+            tracing::debug!("could not list {} extensions: {:#}", editor, e);
+            Vec::new()
+        });
+        let Some(ext) = installed.iter().find(|ext| ext.matches(id)) else {
+            continue;
+        };
+        found = true;
+        let removal = uninstall_extensions_from(runner, editor, &[ext.id.as_str()], dry_run);
+        if !removal.failed.is_empty() {
+            failed.push(editor.to_string());
+        }
+    }
+    if !found {
+        reporter().skip(format!("{} is not installed in any editor", id));
+    }
+
+    if !dry_run {
+        if let Some(mut state) = State::load(paths, tool)? {
+            let recorded = state.extensions.len();
+            state
+                .extensions
+                .retain(|ext| !extension_matches(&ext.id, id));
+            if state.extensions.len() != recorded {
+                state.save(paths, tool)?;
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(Failure::Configuration(format!(
+            "Failed to uninstall {} from {}",
+            id,
+            failed.join(", ")
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// What uninstalling extensions from one editor came to
+#[derive(Debug, Default)]
+struct Removal {
+    removed: usize,
+    gone: usize,
+    failed: Vec<String>,
+}
+
+fn uninstall_extensions_from(
+    runner: &dyn Runner,
+    editor: Editor,
+    ids: &[&str],
+    dry_run: bool,
+) -> Removal {
+    let vscode_cli = editor.cli();
+    let mut removal = Removal::default();
+
+    let installed = match list_installed_extensions(runner, editor) {
+        Ok(installed) => installed,
+        Err(e) => {
+            reporter().error(format!("Could not list {} extensions: {:#}", editor, e));
+            removal.failed = ids.iter().map(|id| id.to_string()).collect();
+            return removal;
+        }
+    };
+
+    for &id in ids {
+        if !installed.iter().any(|ext| ext.id == id) {
+            reporter().skip(format!("{} was already removed", id));
+            removal.gone += 1;
+            continue;
+        }
+        if dry_run {
+            plan::step(format!(
+                "Run '{} --uninstall-extension {}'",
+                vscode_cli.display(),
+                id
+            ));
+            continue;
+        }
+
+        let output = runner
+            .output(std::process::Command::new(&vscode_cli).args(["--uninstall-extension", id]));
+        let error = match output {
+            Ok(output) if output.status.success() => {
+                reporter().success(format!("Removed {}", id));
+                removal.removed += 1;
+                continue;
+            }
+            Ok(output) => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            Err(e) => format!("{:#}", e),
+        };
+        reporter().error(format!("Failed to remove {}: {}", id, error));
+        removal.failed.push(id.to_string());
+    }
+
+    removal
+}
+
+/// An extension reported by `code --list-extensions --show-versions`
+#[derive(Debug, Clone)]
+pub struct InstalledExtension {
+    /// Lowercased `publisher.name` identifier
+    pub id: String,
+    pub version: Option<String>,
+}
+
+impl InstalledExtension {
+    /// Whether this is the extension packaged as `name` (with or without publisher)
+    pub fn matches(&self, name: &str) -> bool {
+        extension_matches(&self.id, name)
+    }
+}
+
+/// Whether the extension `id` is the one called `name`, which may or may not
+/// include the publisher
+fn extension_matches(id: &str, name: &str) -> bool {
+    id == name || id.ends_with(&format!(".{}", name))
+}
+
+/// Extensions installed in `editor`, with lowercased IDs
+pub fn list_installed_extensions(
+    runner: &dyn Runner,
+    editor: Editor,
+) -> Result<Vec<InstalledExtension>> {
+    let output = runner
+        .output(
+            std::process::Command::new(editor.cli())
+                .arg("--list-extensions")
+                .arg("--show-versions"),
+        )
+        .with_context(|| format!("Failed to run the {} CLI", editor))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!(
+            "{} --list-extensions failed: {}",
+            editor.command(),
+            stderr.trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('@') {
+            Some((id, version)) => InstalledExtension {
+                id: id.to_lowercase(),
+                version: Some(version.to_string()),
+            },
+            None => InstalledExtension {
+                id: line.to_lowercase(),
+                version: None,
+            },
+        })
+        .collect())
+}
+
+/// VSIX files in a directory
+pub fn vsix_files(vsix_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !vsix_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(vsix_dir)? {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "vsix").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Why `vsix` can't be installed into `editor`, which reports `version`:
+/// the `engines.vscode` range in its manifest asks for a newer one. `None`
+/// if it fits, or if either version can't be read.
+fn vsix_incompatibility(
+    runner: &dyn Runner,
+    vsix: &Path,
+    editor: Editor,
+    version: Option<&str>,
+) -> Option<String> {
+    let version = version?;
+    let range = vsix_engine(runner, vsix)?;
+    let minimum = engine_minimum(&range)?;
+    if download::compare_versions(version, &minimum) != std::cmp::Ordering::Less {
+        return None;
+    }
+    Some(format!(
+        "{} needs {} {} or later (engines.vscode is \"{}\"), but {} is installed; update {}",
+        vsix.file_name().unwrap_or_default().to_string_lossy(),
+        editor,
+        minimum,
+        range,
+        version,
+        editor
+    ))
+}
+
+/// `engines.vscode` from the `extension/package.json` inside `vsix`, which
+/// is a zip read with the system tar, as platform archives are
+fn vsix_engine(runner: &dyn Runner, vsix: &Path) -> Option<String> {
+    let mut command = std::process::Command::new("tar");
+    command.arg("-xOf").arg(vsix).arg("extension/package.json");
+    let output = runner.output(&mut command).ok()?;
+    if !output.status.success() {
+        tracing::debug!(
+            "could not read the manifest in {}: {}",
+            vsix.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    let manifest: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    manifest["engines"]["vscode"].as_str().map(str::to_string)
+}
+
+/// The oldest version an `engines.vscode` range such as `^1.85.0`,
+/// `>=1.85.0`, or `1.85.x` accepts; `None` for `*`
+fn engine_minimum(range: &str) -> Option<String> {
+    let range = range.split_whitespace().next()?;
+    let version = range.trim_start_matches(['^', '~', '>', '=']);
+    let parts: Vec<&str> = version
+        .split('.')
+        .take_while(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("."))
+}
+
+/// Extension name guessed from a VSIX filename such as `name-1.2.3.vsix`.
+/// The publisher isn't part of the filename, so match against the part of
+/// the extension ID after the dot.
+fn vsix_extension_name(vsix: &Path) -> Option<String> {
+    let stem = vsix.file_stem()?.to_string_lossy().to_lowercase();
+
+    match stem.rfind('-') {
+        Some(idx) if stem[idx + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+            Some(stem[..idx].to_string())
+        }
+        _ => Some(stem),
+    }
+}
+
+/// Extension version from a VSIX filename such as `name-1.2.3.vsix`, if it
+/// has one
+fn vsix_extension_version(vsix: &Path) -> Option<String> {
+    let stem = vsix.file_stem()?.to_string_lossy().to_string();
+    let (_, version) = stem.rsplit_once('-')?;
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then(|| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_dir(extensions: Option<&str>, vsix: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        if let Some(extensions) = extensions {
+            std::fs::write(dir.path().join(EXTENSIONS_FILE), extensions).unwrap();
+        }
+        std::fs::create_dir(dir.path().join("VSIX")).unwrap();
+        for name in vsix {
+            std::fs::write(dir.path().join("VSIX").join(name), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn reads_ids_with_optional_versions() {
+        let dir = local_dir(
+            Some(
+                r#"{ "extensions": [
+                    "ms-python.python",
+                    "Anthropic.Claude-Code@2.1.0",
+                    { "id": "redhat.vscode-yaml", "version": "1.15.0" }
+                ] }"#,
+            ),
+            &[],
+        );
+
+        let wanted = wanted_extensions(dir.path()).unwrap();
+
+        let specs: Vec<String> = wanted.iter().map(|w| w.marketplace_spec()).collect();
+        assert_eq!(
+            specs,
+            [
+                "ms-python.python",
+                "anthropic.claude-code@2.1.0",
+                "redhat.vscode-yaml@1.15.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn vsix_files_back_up_listed_extensions() {
+        let dir = local_dir(
+            Some(r#"["anthropic.claude-code@2.1.0", "ms-python.python"]"#),
+            &[
+                "claude-code-2.1.0.vsix",
+                "python-2024.1.0.vsix",
+                "helper-1.0.0.vsix",
+            ],
+        );
+
+        let wanted = wanted_extensions(dir.path()).unwrap();
+
+        assert_eq!(wanted.len(), 3);
+        assert!(wanted[0].vsix.is_some());
+        assert!(wanted[1].vsix.is_some());
+        assert_eq!(wanted[2].id, "helper");
+        assert_eq!(wanted[2].version.as_deref(), Some("1.0.0"));
+        assert!(!wanted[2].marketplace);
+    }
+
+    #[test]
+    fn vsix_of_another_version_is_not_a_fallback() {
+        let dir = local_dir(
+            Some(r#"["anthropic.claude-code@2.1.0"]"#),
+            &["claude-code-2.0.0.vsix"],
+        );
+
+        let wanted = wanted_extensions(dir.path()).unwrap();
+
+        assert_eq!(wanted.len(), 2);
+        assert_eq!(wanted[0].vsix, None);
+    }
+
+    #[test]
+    fn newest_fitting_vsix_backs_up_a_marketplace_extension() {
+        let dir = local_dir(
+            None,
+            &[
+                "continue-1.0.9.vsix",
+                "continue-1.2.0.vsix",
+                "continue-1.10.0.vsix",
+                "claude-code-2.1.0.vsix",
+            ],
+        );
+
+        let any = marketplace_extension(dir.path(), "Continue.continue", None).unwrap();
+        assert_eq!(any.id, "continue.continue");
+        assert!(any.marketplace);
+        assert_eq!(
+            any.vsix,
+            Some(dir.path().join("VSIX").join("continue-1.10.0.vsix"))
+        );
+
+        let pinned = marketplace_extension(dir.path(), "continue.continue", Some("1.2.0")).unwrap();
+        assert_eq!(pinned.marketplace_spec(), "continue.continue@1.2.0");
+        assert_eq!(
+            pinned.vsix,
+            Some(dir.path().join("VSIX").join("continue-1.2.0.vsix"))
+        );
+
+        let missing =
+            marketplace_extension(dir.path(), "continue.continue", Some("2.0.0")).unwrap();
+        assert_eq!(missing.vsix, None);
+    }
+
+    #[test]
+    fn engine_ranges_give_their_minimum() {
+        assert_eq!(engine_minimum("^1.85.0").as_deref(), Some("1.85.0"));
+        assert_eq!(engine_minimum(">=1.90.0").as_deref(), Some("1.90.0"));
+        assert_eq!(engine_minimum("1.85.x").as_deref(), Some("1.85"));
+        assert_eq!(engine_minimum("^1.94.0-insider").as_deref(), Some("1.94"));
+        assert_eq!(engine_minimum("*"), None);
+    }
+
+    #[test]
+    fn pinned_versions_must_match_exactly() {
+        let want = WantedExtension {
+            id: "ms-python.python".to_string(),
+            version: Some("2024.1.0".to_string()),
+            marketplace: true,
+            vsix: None,
+        };
+        let installed = |version: &str| InstalledExtension {
+            id: "ms-python.python".to_string(),
+            version: Some(version.to_string()),
+        };
+
+        assert!(want.is_satisfied_by(&installed("2024.1.0")));
+        assert!(!want.is_satisfied_by(&installed("2024.2.0")));
+    }
+
+    #[test]
+    fn vsix_files_only_replace_older_versions() {
+        let want = WantedExtension {
+            id: "claude-code".to_string(),
+            version: Some("2.0.10".to_string()),
+            marketplace: false,
+            vsix: Some(PathBuf::from("claude-code-2.0.10.vsix")),
+        };
+        let installed = |version: &str| InstalledExtension {
+            id: "anthropic.claude-code".to_string(),
+            version: Some(version.to_string()),
+        };
+
+        assert!(!want.is_satisfied_by(&installed("2.0.9")));
+        assert!(!want.is_satisfied_by(&installed("2.0.10-beta.1")));
+        assert!(want.is_satisfied_by(&installed("2.0.10")));
+        assert!(want.is_satisfied_by(&installed("2.1.0")));
+    }
+}
@@ -0,0 +1,215 @@
+//! Template files copied in whole rather than merged, such as snippets and
+//! Claude commands. A checksum of each copy is kept in the state file so a
+//! later deploy can tell whether the user has changed it since. With
+//! `--link` they are linked to the template instead, so updates to a shared
+//! `local/` directory reach the machine without another deploy.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use super::{SettingsDeploy, Transaction};
+use crate::output::reporter;
+use crate::plan;
+use crate::state::State;
+
+/// What to do when the destination holds something other than what was last
+/// deployed, and `--force` wasn't given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Conflict {
+    /// Leave the user's file alone
+    Keep,
+    /// Write the template next to it as `<name>.new.<ext>`
+    WriteAlongside,
+}
+
+/// Files under `dir` and its subdirectories, relative to it, skipping macOS
+/// resource forks
+pub(super) fn template_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(dir.join(&relative))
+            .with_context(|| format!("Failed to read {}", dir.join(&relative).display()))?
+        {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with("._") {
+                continue;
+            }
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// SHA-256 of `content`, hex encoded
+pub(super) fn sha256_hex(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Copy the template at `source` to `dest`, or link it there with `--link`,
+/// where `name` describes it in messages. A destination already holding the
+/// template the same way is left alone, one still holding what an earlier
+/// deploy wrote is replaced, and anything else is handled as `conflict`
+/// says, or with `force` backed up and replaced. Returns whether anything
+/// was, or in a dry run would be, written.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn copy_template(
+    settings: &SettingsDeploy,
+    source: &Path,
+    dest: &Path,
+    name: &str,
+    label: &str,
+    conflict: Conflict,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<bool> {
+    let content =
+        std::fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let checksum = sha256_hex(&content);
+    let target = std::path::absolute(source)?;
+    let linked = state
+        .linked_file(dest)
+        .is_some_and(|link| link.target == target && link.is_intact());
+
+    let (dest, replacing) = match std::fs::read(dest).ok() {
+        None => (dest.to_path_buf(), false),
+        Some(current) if current == content && linked == settings.link => {
+            // Keep tracking a file an earlier deploy wrote
+            if state.copied_file_sha256(dest).is_some() {
+                state.record_copied_file(dest, &checksum);
+            }
+            return Ok(false);
+        }
+        // Switching between a copy and a link
+        Some(current) if current == content => (dest.to_path_buf(), true),
+        Some(current) if state.copied_file_sha256(dest) == Some(sha256_hex(&current).as_str()) => {
+            (dest.to_path_buf(), true)
+        }
+        Some(_) if settings.force => {
+            settings
+                .snapshot
+                .save(dest, label, state, settings.dry_run)?;
+            (dest.to_path_buf(), true)
+        }
+        Some(_) => match conflict {
+            Conflict::Keep => {
+                reporter().warn(format!(
+                    "Left {} in place: it was changed since it was deployed \
+                     (use --force to overwrite it)",
+                    dest.display()
+                ));
+                return Ok(false);
+            }
+            Conflict::WriteAlongside => {
+                let alongside = alongside_path(dest);
+                if std::fs::read(&alongside).is_ok_and(|current| current == content) {
+                    return Ok(false);
+                }
+                if !settings.dry_run {
+                    reporter().warn(format!(
+                        "{} was changed since it was deployed; the new version is at {}",
+                        dest.display(),
+                        alongside.display()
+                    ));
+                }
+                let replacing = alongside.exists();
+                (alongside, replacing)
+            }
+        },
+    };
+
+    if settings.dry_run {
+        if settings.link {
+            plan::step(format!("Link {} to {}", dest.display(), source.display()));
+        } else if replacing {
+            plan::step(format!(
+                "Replace {} with {}",
+                dest.display(),
+                source.display()
+            ));
+        } else {
+            plan::step(format!("Copy {} to {}", source.display(), dest.display()));
+        }
+        return Ok(true);
+    }
+
+    if settings.link {
+        match stage_link(&dest, &target, name, state, tx) {
+            Ok(()) => {
+                reporter().success(format!("Linked {}", name));
+                reporter().detail(dest.display());
+                return Ok(true);
+            }
+            Err(e) => reporter().warn(format!("{:#}; copying it instead", e)),
+        }
+    }
+
+    stage_copy(&dest, content, name, state, tx)?;
+    state.record_copied_file(&dest, &checksum);
+    let verb = if replacing { "Updated" } else { "Deployed" };
+    reporter().success(format!("{} {}", verb, name));
+    reporter().detail(dest.display());
+    Ok(true)
+}
+
+/// Stage a link at `dest` to the template `target` and record it
+pub(super) fn stage_link(
+    dest: &Path,
+    target: &Path,
+    step: &str,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let kind = tx.stage_link(dest, target, step)?;
+    state.record_linked_file(dest, target, kind);
+    Ok(())
+}
+
+/// Stage `content` for `dest`, replacing rather than writing through a link
+/// an earlier `--link` deploy made there
+pub(super) fn stage_copy(
+    dest: &Path,
+    content: impl Into<Vec<u8>>,
+    step: &str,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    if state.forget_linked_file(dest) {
+        tx.stage_unlinked(dest, content, step)
+    } else {
+        tx.stage(dest, content, step)
+    }
+}
+
+/// `<name>.new.<ext>` next to `path`, for a template that can't replace it
+fn alongside_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}.new.{}", stem, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}.new", stem)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alongside_keeps_the_extension() {
+        assert_eq!(
+            alongside_path(Path::new("/h/.claude/CLAUDE.md")),
+            Path::new("/h/.claude/CLAUDE.new.md")
+        );
+        assert_eq!(
+            alongside_path(Path::new("/h/.claude/hooks/check")),
+            Path::new("/h/.claude/hooks/check.new")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! JSON with comments and trailing commas, as VS Code accepts in its
+//! settings files
+
+use serde_json::Value;
+
+/// Parse JSON that may contain `//` and `/* */` comments and trailing
+/// commas. A file with nothing but whitespace and comments is an empty
+/// object, as VS Code treats it, and a leading byte order mark, which VS
+/// Code sometimes writes on Windows, is skipped.
+pub fn parse(content: &str) -> serde_json::Result<Value> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let (json, _) = strip(content);
+    if json.trim().is_empty() {
+        return Ok(Value::Object(serde_json::Map::new()));
+    }
+    serde_json::from_str(&json)
+}
+
+/// Whether `content` has comments that rewriting it would drop
+pub fn has_comments(content: &str) -> bool {
+    strip(content).1
+}
+
+/// Blank out comments and trailing commas, keeping line breaks so parse
+/// errors point at the right line. Returns the plain JSON and whether there
+/// were comments.
+fn strip(content: &str) -> (String, bool) {
+    let mut out = String::with_capacity(content.len());
+    let mut has_comments = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                has_comments = true;
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                has_comments = true;
+                chars.next();
+                let mut previous = '\0';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            '}' | ']' => {
+                let end = out.trim_end().len();
+                if out[..end].ends_with(',') {
+                    out.replace_range(end - 1..end, " ");
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    (out, has_comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ignores_comments() {
+        let content = r#"{
+                // Line comment
+                "editor.fontSize": 14, /* block
+                comment */ "url": "https://example.com/path",
+                "pattern": "/* not a comment */"
+            }"#;
+
+        assert!(has_comments(content));
+        assert_eq!(
+            parse(content).unwrap(),
+            json!({
+                "editor.fontSize": 14,
+                "url": "https://example.com/path",
+                "pattern": "/* not a comment */",
+            })
+        );
+    }
+
+    #[test]
+    fn allows_trailing_commas() {
+        let content = "{ \"list\": [1, 2,\n], \"nested\": { \"a\": \",}\", },\n}";
+
+        assert!(!has_comments(content));
+        assert_eq!(
+            parse(content).unwrap(),
+            json!({ "list": [1, 2], "nested": { "a": ",}" } })
+        );
+    }
+
+    #[test]
+    fn empty_file_is_an_empty_object() {
+        assert_eq!(parse("").unwrap(), json!({}));
+        assert_eq!(parse("  \n// nothing yet\n").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark() {
+        assert_eq!(parse("\u{feff}{ \"a\": 1 }").unwrap(), json!({ "a": 1 }));
+        assert_eq!(parse("\u{feff}").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn reports_errors_on_the_original_line() {
+        let error = parse("{\n  // comment\n  \"a\": oops\n}").unwrap_err();
+
+        assert_eq!(error.line(), 3);
+    }
+
+    #[test]
+    fn escaped_quotes_stay_inside_strings() {
+        let content = r#"{ "a": "say \"hi\" // there" }"#;
+
+        assert!(!has_comments(content));
+        assert_eq!(
+            parse(content).unwrap(),
+            json!({ "a": "say \"hi\" // there" })
+        );
+    }
+}
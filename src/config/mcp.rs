@@ -0,0 +1,241 @@
+//! MCP servers from `mcp.json`, merged by name into the `mcpServers` map of
+//! Claude's `~/.claude.json`
+
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use std::path::Path;
+
+use super::placeholders::Placeholders;
+use super::{jsonc, plan_settings_write, preserve_comments, SettingsDeploy, Transaction};
+use crate::output::events::{self, Event};
+use crate::output::{self, reporter};
+use crate::state::State;
+
+/// Key in Claude's config holding the servers, also accepted at the top of
+/// the template
+const SERVERS_KEY: &str = "mcpServers";
+
+/// What merging the template does to one server
+enum ServerChange {
+    Added(String),
+    Updated(String),
+    /// Defined by the user under the same name, so ours is left out
+    Kept(String),
+}
+
+/// Merge the servers in the template at `source` into `dest` by name. A
+/// server the user defined under the same name wins unless `force`; one an
+/// earlier deploy wrote follows the template.
+pub(super) fn deploy_mcp_servers(
+    settings: &SettingsDeploy,
+    source: &Path,
+    dest: &Path,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let name = "MCP servers";
+    let template = read_template(&settings.placeholders, source)?;
+
+    let created = !dest.exists();
+    let mut config = if created {
+        serde_json::Map::new()
+    } else {
+        read_config(dest)?
+    };
+    let mut servers = match config.remove(SERVERS_KEY) {
+        Some(serde_json::Value::Object(servers)) => servers,
+        None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+        Some(_) => {
+            return Err(anyhow!(
+                "{} in {} is not an object",
+                SERVERS_KEY,
+                dest.display()
+            ))
+        }
+    };
+
+    let mut changes = Vec::new();
+    let mut deployed = Vec::new();
+    for (server, definition) in template {
+        match servers.get(&server) {
+            Some(current) if *current == definition => {
+                // Keep tracking a server an earlier deploy wrote
+                if state.mcp_server(dest, &server).is_some() {
+                    deployed.push((server, definition));
+                }
+                continue;
+            }
+            Some(current) => {
+                let ours = state.mcp_server(dest, &server) == Some(current);
+                if !ours && !settings.force {
+                    changes.push(ServerChange::Kept(server));
+                    continue;
+                }
+                changes.push(ServerChange::Updated(server.clone()));
+            }
+            None => changes.push(ServerChange::Added(server.clone())),
+        }
+        servers.insert(server.clone(), definition.clone());
+        deployed.push((server, definition));
+    }
+    let writes = changes
+        .iter()
+        .any(|change| !matches!(change, ServerChange::Kept(_)));
+
+    if settings.dry_run {
+        settings.snapshot.save(dest, "claude", state, true)?;
+        plan_settings_write(source, dest);
+        print_server_changes(&changes);
+        return Ok(());
+    }
+
+    if !writes {
+        record_servers(dest, &deployed, state);
+        warn_kept(&changes);
+        reporter().skip(format!("{} already up to date", name));
+        return Ok(());
+    }
+
+    if !settings.assume_yes {
+        reporter().info(format!("Changes to {}:", dest.display()));
+        print_server_changes(&changes);
+        if !output::confirm(&format!("This will update {}.", name))? {
+            reporter().skip(format!("Left {} unchanged", name));
+            return Ok(());
+        }
+    }
+
+    settings.snapshot.save(dest, "claude", state, false)?;
+    record_servers(dest, &deployed, state);
+    config.insert(SERVERS_KEY.to_string(), serde_json::Value::Object(servers));
+
+    preserve_comments(dest)?;
+    tx.stage(
+        dest,
+        serde_json::to_string_pretty(&serde_json::Value::Object(config))?,
+        name,
+    )?;
+    warn_kept(&changes);
+    events::emit(Event::SettingsChanged {
+        file: dest.to_path_buf(),
+        keys: changes
+            .iter()
+            .filter_map(|change| match change {
+                ServerChange::Added(server) | ServerChange::Updated(server) => {
+                    Some(format!("{}.{}", SERVERS_KEY, server))
+                }
+                ServerChange::Kept(_) => None,
+            })
+            .collect(),
+    });
+    for change in &changes {
+        match change {
+            ServerChange::Added(server) => {
+                reporter().success(format!("Added MCP server {}", server))
+            }
+            ServerChange::Updated(server) => {
+                reporter().success(format!("Updated MCP server {}", server))
+            }
+            ServerChange::Kept(_) => {}
+        }
+    }
+    reporter().detail(dest.display());
+
+    Ok(())
+}
+
+/// The servers in an `mcp.json` template, either under `mcpServers` or at
+/// the top level, with placeholders expanded
+pub(super) fn read_template(
+    placeholders: &Placeholders,
+    path: &Path,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut template =
+        jsonc::parse(&content).with_context(|| format!("Invalid JSON in {}", path.display()))?;
+    placeholders.expand(&mut template, path);
+    let mut template = match template {
+        serde_json::Value::Object(template) => template,
+        _ => return Err(anyhow!("{} is not an object of servers", path.display())),
+    };
+    match template.remove(SERVERS_KEY) {
+        Some(serde_json::Value::Object(servers)) => Ok(servers),
+        Some(_) => Err(anyhow!(
+            "{} in {} is not an object",
+            SERVERS_KEY,
+            path.display()
+        )),
+        None => Ok(template),
+    }
+}
+
+/// Claude's config file as an object, treating an empty file as an empty one
+fn read_config(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(serde_json::Map::new());
+    }
+    match jsonc::parse(&content).with_context(|| format!("Invalid JSON in {}", path.display()))? {
+        serde_json::Value::Object(config) => Ok(config),
+        _ => Err(anyhow!("{} is not a JSON object", path.display())),
+    }
+}
+
+/// The servers in Claude's config at `path`, or `None` if it can't be read
+pub(super) fn read_servers(path: &Path) -> Option<serde_json::Map<String, serde_json::Value>> {
+    match read_config(path).ok()?.remove(SERVERS_KEY) {
+        Some(serde_json::Value::Object(servers)) => Some(servers),
+        _ => Some(serde_json::Map::new()),
+    }
+}
+
+/// Remove `names` from the servers in Claude's config at `path`, keeping
+/// everything else in it
+pub(super) fn remove_servers(path: &Path, names: &[&str]) -> Result<()> {
+    let mut config = read_config(path)?;
+    if let Some(serde_json::Value::Object(servers)) = config.get_mut(SERVERS_KEY) {
+        servers.retain(|name, _| !names.contains(&name.as_str()));
+        if servers.is_empty() {
+            config.remove(SERVERS_KEY);
+        }
+    }
+    super::write_settings(path, &serde_json::Value::Object(config))
+}
+
+fn record_servers(dest: &Path, deployed: &[(String, serde_json::Value)], state: &mut State) {
+    for (server, definition) in deployed {
+        state.record_mcp_server(dest, server, definition);
+    }
+}
+
+fn warn_kept(changes: &[ServerChange]) {
+    for change in changes {
+        if let ServerChange::Kept(server) = change {
+            reporter().warn(format!(
+                "Kept your MCP server {}: it differs from the template \
+                 (use --force to replace it)",
+                server
+            ));
+        }
+    }
+}
+
+fn print_server_changes(changes: &[ServerChange]) {
+    let out = reporter();
+    for change in changes {
+        match change {
+            ServerChange::Added(server) => out.output(style(format!("    + {}", server)).green()),
+            ServerChange::Updated(server) => {
+                out.output(style(format!("    ~ {}", server)).yellow())
+            }
+            ServerChange::Kept(server) => {
+                out.output(style(format!("    = {} (yours, kept)", server)).dim())
+            }
+        }
+    }
+    if changes.is_empty() {
+        out.output(style("    no changes").dim());
+    }
+}
@@ -0,0 +1,284 @@
+//! Merging a settings template into settings the user already has, so
+//! entries nested inside a key we also set survive the merge
+
+use serde_json::{Map, Value};
+
+/// How an array in the template combines with one already in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The template's array replaces the existing one
+    Replace,
+    /// Existing entries are kept, followed by template entries not among them
+    Union,
+}
+
+/// Key paths whose arrays are lists the user adds to, so they union rather
+/// than replace. Each path is the chain of object keys from the top; VS Code
+/// keys like `cSpell.words` are a single key despite the dots.
+const UNION_PATHS: &[&[&str]] = &[
+    &["permissions", "allow"],
+    &["permissions", "ask"],
+    &["permissions", "deny"],
+    &["permissions", "additionalDirectories"],
+    &["cSpell.words"],
+    &["python.analysis.extraPaths"],
+    &["terminal.integrated.commandsToSkipShell"],
+];
+
+/// Array strategy for a merge: `arrays` everywhere except `union_paths`
+#[derive(Debug, Clone, Copy)]
+pub struct MergeOptions<'a> {
+    pub arrays: ArrayMerge,
+    pub union_paths: &'a [&'a [&'a str]],
+}
+
+impl Default for MergeOptions<'static> {
+    fn default() -> Self {
+        MergeOptions {
+            arrays: ArrayMerge::Replace,
+            union_paths: UNION_PATHS,
+        }
+    }
+}
+
+impl MergeOptions<'_> {
+    fn arrays_at(&self, path: &[String]) -> ArrayMerge {
+        let is_union = |union: &&[&str]| union.iter().copied().eq(path.iter().map(String::as_str));
+        if self.union_paths.iter().any(is_union) {
+            ArrayMerge::Union
+        } else {
+            self.arrays
+        }
+    }
+}
+
+/// Merge `source` into `dest`, the existing value of top-level `key`.
+/// Objects merge key by key, arrays follow `options`, and anything else
+/// (including `null` and a type mismatch) is replaced by the source value.
+pub fn merge_key(dest: &mut Value, key: &str, source: Value, options: &MergeOptions) {
+    merge(dest, source, &mut vec![key.to_string()], options);
+}
+
+fn merge(dest: &mut Value, source: Value, path: &mut Vec<String>, options: &MergeOptions) {
+    match (dest, source) {
+        (Value::Object(dest), Value::Object(source)) => {
+            for (key, value) in source {
+                match dest.get_mut(&key) {
+                    Some(existing) => {
+                        path.push(key);
+                        merge(existing, value, path, options);
+                        path.pop();
+                    }
+                    None => {
+                        dest.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(dest), Value::Array(source))
+            if options.arrays_at(path) == ArrayMerge::Union =>
+        {
+            for item in source {
+                if !dest.contains(&item) {
+                    dest.push(item);
+                }
+            }
+        }
+        (dest, source) => *dest = source,
+    }
+}
+
+/// What a merge does to one top-level key
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyChange {
+    Added { key: String, value: Value },
+    Changed { key: String, from: Value, to: Value },
+    Removed { key: String, value: Value },
+}
+
+impl KeyChange {
+    pub fn key(&self) -> &str {
+        match self {
+            KeyChange::Added { key, .. }
+            | KeyChange::Changed { key, .. }
+            | KeyChange::Removed { key, .. } => key,
+        }
+    }
+}
+
+/// Top-level keys that differ between `before` and `after`, in `after`'s
+/// order followed by removals, and how many keys were left as they were
+pub fn diff_keys(
+    before: &Map<String, Value>,
+    after: &Map<String, Value>,
+) -> (Vec<KeyChange>, usize) {
+    let mut changes = Vec::new();
+    let mut untouched = 0;
+    for (key, value) in after {
+        match before.get(key) {
+            None => changes.push(KeyChange::Added {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            Some(previous) if previous != value => changes.push(KeyChange::Changed {
+                key: key.clone(),
+                from: previous.clone(),
+                to: value.clone(),
+            }),
+            Some(_) => untouched += 1,
+        }
+    }
+    for (key, value) in before {
+        if !after.contains_key(key) {
+            changes.push(KeyChange::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    (changes, untouched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn merged(mut dest: Value, key: &str, source: Value, options: &MergeOptions) -> Value {
+        merge_key(&mut dest, key, source, options);
+        dest
+    }
+
+    #[test]
+    fn merges_nested_objects_key_by_key() {
+        let dest = json!({ "PATH": "/usr/bin", "nested": { "keep": 1, "set": 1 } });
+        let source = json!({ "NODE_EXTRA_CA_CERTS": "/certs/ca.crt", "nested": { "set": 2 } });
+
+        let result = merged(
+            dest,
+            "terminal.integrated.env.osx",
+            source,
+            &MergeOptions::default(),
+        );
+
+        assert_eq!(
+            result,
+            json!({
+                "PATH": "/usr/bin",
+                "NODE_EXTRA_CA_CERTS": "/certs/ca.crt",
+                "nested": { "keep": 1, "set": 2 },
+            })
+        );
+    }
+
+    #[test]
+    fn replaces_arrays_by_default() {
+        let result = merged(
+            json!({ "rulers": [80, 100] }),
+            "[python]",
+            json!({ "rulers": [88] }),
+            &MergeOptions::default(),
+        );
+
+        assert_eq!(result, json!({ "rulers": [88] }));
+    }
+
+    #[test]
+    fn unions_known_list_settings() {
+        let result = merged(
+            json!({ "allow": ["Bash(ls)", "Read"], "deny": ["WebFetch"] }),
+            "permissions",
+            json!({ "allow": ["Read", "Bash(git status)"], "deny": [] }),
+            &MergeOptions::default(),
+        );
+
+        assert_eq!(
+            result,
+            json!({ "allow": ["Bash(ls)", "Read", "Bash(git status)"], "deny": ["WebFetch"] })
+        );
+    }
+
+    #[test]
+    fn array_strategy_is_configurable() {
+        let options = MergeOptions {
+            arrays: ArrayMerge::Union,
+            union_paths: &[],
+        };
+
+        let result = merged(json!(["a", "b"]), "list", json!(["b", "c"]), &options);
+
+        assert_eq!(result, json!(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn source_wins_type_conflicts() {
+        let options = MergeOptions::default();
+
+        assert_eq!(
+            merged(json!("inherit"), "key", json!({ "a": 1 }), &options),
+            json!({ "a": 1 })
+        );
+        assert_eq!(
+            merged(json!({ "a": 1 }), "key", json!("inherit"), &options),
+            json!("inherit")
+        );
+        assert_eq!(
+            merged(json!([1]), "key", json!({ "a": 1 }), &options),
+            json!({ "a": 1 })
+        );
+    }
+
+    #[test]
+    fn null_is_an_ordinary_value() {
+        let options = MergeOptions::default();
+
+        assert_eq!(
+            merged(
+                json!({ "a": 1, "b": 2 }),
+                "key",
+                json!({ "a": null }),
+                &options
+            ),
+            json!({ "a": null, "b": 2 })
+        );
+        assert_eq!(
+            merged(
+                json!({ "a": null }),
+                "key",
+                json!({ "a": { "b": 1 } }),
+                &options
+            ),
+            json!({ "a": { "b": 1 } })
+        );
+        assert_eq!(merged(Value::Null, "key", json!([1]), &options), json!([1]));
+    }
+
+    #[test]
+    fn diff_keys_sorts_changes_by_kind() {
+        let before = json!({ "keep": 1, "change": "old", "drop": true });
+        let after = json!({ "keep": 1, "change": "new", "add": [1] });
+
+        let (changes, untouched) =
+            diff_keys(before.as_object().unwrap(), after.as_object().unwrap());
+
+        assert_eq!(untouched, 1);
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Added {
+                    key: "add".to_string(),
+                    value: json!([1]),
+                },
+                KeyChange::Changed {
+                    key: "change".to_string(),
+                    from: json!("old"),
+                    to: json!("new"),
+                },
+                KeyChange::Removed {
+                    key: "drop".to_string(),
+                    value: json!(true),
+                },
+            ]
+        );
+    }
+}
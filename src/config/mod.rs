@@ -1,28 +1,85 @@
+pub mod backup;
+pub mod jsonc;
+mod certs;
+mod check;
+mod extensions;
+mod files;
+mod mcp;
+mod merge;
+mod placeholders;
+mod preview;
+mod profile;
+mod transaction;
+mod user;
+mod vscode;
+mod yaml;
+
 use anyhow::{Context, Result};
 use console::style;
-use std::path::Path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::cancel;
+use crate::error::Failure;
+use crate::output::events::{self, Event};
+use crate::output::{self, reporter};
+use crate::plan;
+use crate::platform::{self, Editor, Environment, Host, PlatformPaths};
+use crate::prerequisites;
+use crate::state::{SettingsKey, State, LEGACY_TOOL};
 
-use crate::platform::{self, PlatformPaths};
+use backup::Snapshot;
+use certs::{
+    certificate_files, deploy_certificates, import_certificates, node_extra_ca_certs, NodeCaCerts,
+};
+pub use check::{check_configs, Drift};
+pub use certs::{is_certificate_file, read_certificates, Expiry};
+pub use extensions::{
+    extensions_to_install, install_extension_list, install_extensions, list_installed_extensions,
+    marketplace_extension, uninstall_extension, uninstall_extensions, vsix_files,
+    wanted_extensions, ExtensionOptions, InstalledExtension, WantedExtension, EXTENSIONS_FILE,
+};
+use files::{sha256_hex, template_files, Conflict};
+use merge::{KeyChange, MergeOptions};
+use placeholders::Placeholders;
+pub use preview::{preview_deploy, DeployPreview, PlannedFile};
+pub use profile::{list_profiles, profile_dir, DEFAULT_PROFILE};
+use transaction::Transaction;
+pub use user::{Secret, Settings, Source, ToolConfig, UserConfig};
 
-fn get_platform_config_dir(local_dir: &Path) -> std::path::PathBuf {
+/// `tool`'s platform config tree: `<tool>/<PLATFORM>/USER-DIRECTORY` in the
+/// local directory, or for the tool that predates per-tool trees, the shared
+/// `<PLATFORM>/USER-DIRECTORY` if it has none of its own. Another tool never
+/// falls back to the shared tree, so it can't deploy Claude's settings.
+fn get_platform_config_dir(local_dir: &Path, tool: &str) -> std::path::PathBuf {
+    let tool_dir = get_platform_tree(&local_dir.join(tool));
+    if tool_dir.exists() || tool != LEGACY_TOOL {
+        return tool_dir;
+    }
+    get_platform_tree(local_dir)
+}
+
+/// `<PLATFORM>/USER-DIRECTORY` under `dir`
+fn get_platform_tree(dir: &Path) -> std::path::PathBuf {
     #[cfg(target_os = "windows")]
     {
-        local_dir.join("WIN").join("USER-DIRECTORY")
+        dir.join("WIN").join("USER-DIRECTORY")
     }
 
     #[cfg(target_os = "macos")]
     {
-        local_dir.join("MACOS").join("USER-DIRECTORY")
+        dir.join("MACOS").join("USER-DIRECTORY")
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
         // Linux fallback for development - not actually supported at runtime
-        local_dir.join("LINUX").join("USER-DIRECTORY")
+        dir.join("LINUX").join("USER-DIRECTORY")
     }
 }
 
-fn get_vscode_settings_source(config_dir: &Path) -> std::path::PathBuf {
+/// VS Code's `User` directory in the platform config tree
+fn get_vscode_user_source(config_dir: &Path) -> std::path::PathBuf {
     #[cfg(target_os = "windows")]
     {
         config_dir
@@ -30,7 +87,6 @@ fn get_vscode_settings_source(config_dir: &Path) -> std::path::PathBuf {
             .join("Roaming")
             .join("Code")
             .join("User")
-            .join("settings.json")
     }
 
     #[cfg(target_os = "macos")]
@@ -40,276 +96,2094 @@ fn get_vscode_settings_source(config_dir: &Path) -> std::path::PathBuf {
             .join("Application Support")
             .join("Code")
             .join("User")
-            .join("settings.json")
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
         // Linux fallback for development
-        config_dir
-            .join(".config")
-            .join("Code")
-            .join("User")
-            .join("settings.json")
+        config_dir.join(".config").join("Code").join("User")
     }
 }
 
-/// Deploy configuration files for a tool
-pub fn deploy_configs(local_dir: &Path, paths: &PlatformPaths) -> Result<()> {
-    let platform_config_dir = get_platform_config_dir(local_dir);
+/// How a deploy treats the user's existing settings
+#[derive(Debug, Clone)]
+pub struct DeployOptions {
+    /// Settings backups to keep; zero turns them off
+    pub keep_backups: usize,
 
-    if !platform_config_dir.exists() {
-        println!(
-            "  {} No platform-specific configs found",
-            style("!").yellow().bold()
-        );
+    /// Merge settings without showing the changes and asking first
+    pub assume_yes: bool,
+
+    /// Overwrite deployed files that were changed since they were deployed
+    pub force: bool,
+
+    /// What to do with settings keys changed since the last deploy
+    pub edited_keys: EditedKeys,
+
+    /// Editor whose settings to merge into, instead of every installed one
+    pub editor: Option<Editor>,
+
+    /// Where the tool's binary is installed, for `${INSTALL_DIR}` in
+    /// templates
+    pub install_dir: PathBuf,
+
+    /// Tool being deployed, which picks its config tree and state file
+    pub tool: String,
+
+    /// Profile the templates come from, remembered in the state file
+    pub profile: String,
+
+    /// Link files that aren't merged, such as certificates and snippets, to
+    /// their templates instead of copying them
+    pub link: bool,
+
+    /// Leave the system trust store alone, for users whose policy forbids
+    /// changing it
+    pub skip_cert_import: bool,
+
+    /// Files deployed besides the platform config tree, for tools declared
+    /// in `tools.d`
+    pub files: Vec<ConfigFile>,
+
+    /// Environment variables to set, for tools declared in `tools.d`; the
+    /// values may hold placeholders
+    pub env: Vec<(String, String)>,
+
+    /// Sets and reads the user's persistent environment variables
+    pub host: Host,
+}
+
+/// A template deployed to a path of its own rather than as part of the
+/// platform config tree
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub source: PathBuf,
+    /// Where it goes, which may hold placeholders such as `${HOME}`
+    pub dest: String,
+    pub strategy: FileStrategy,
+}
+
+/// How a [`ConfigFile`] treats what's already at its destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStrategy {
+    /// Merge the template's keys into the user's JSON or YAML, like settings
+    #[default]
+    Merge,
+    /// Copy the template in, writing it alongside a copy the user changed
+    Copy,
+    /// Copy the template in only if the user has no file of their own
+    Keep,
+}
+
+/// What a deploy does with a settings key the user changed since the last
+/// deploy wrote it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditedKeys {
+    /// Leave the user's value and list the key
+    #[default]
+    Keep,
+    /// Write the template's value anyway (`--force-settings`)
+    Overwrite,
+    /// Ask about each key (`--interactive`)
+    Ask,
+}
+
+/// `text` with placeholders such as `${HOME}` filled in, warning about any
+/// that are unknown in `what`
+pub fn expand_placeholders(
+    paths: &PlatformPaths,
+    install_dir: &Path,
+    text: &str,
+    what: &str,
+) -> String {
+    Placeholders::new(paths, install_dir).expand_text(text, what)
+}
+
+/// Deploy configuration files for a tool. Changes to settings files are
+/// shown and confirmed unless `assume_yes`, and the files are backed up
+/// before they're changed. The deploy is all or nothing: if any step fails,
+/// every file it replaced is put back.
+///
+/// With `dry_run`, print each file and environment change instead of making it.
+pub fn deploy_configs(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    options: &DeployOptions,
+    dry_run: bool,
+) -> Result<()> {
+    deploy_platform_configs(local_dir, paths, options, dry_run)
+        .context(Failure::Configuration("Failed to deploy configuration".to_string()))
+}
+
+fn deploy_platform_configs(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    options: &DeployOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let platform_config_dir = get_platform_config_dir(local_dir, &options.tool);
+
+    if !platform_config_dir.exists() && options.files.is_empty() && options.env.is_empty() {
+        reporter().warn("No platform-specific configs found");
         return Ok(());
     }
 
-    // Deploy .claude/settings.json
-    deploy_claude_settings(&platform_config_dir, paths)?;
+    let mut state = load_state(local_dir, paths, &options.tool)?;
+    let snapshot = Snapshot::new(paths, options.keep_backups);
+    let settings = SettingsDeploy {
+        snapshot: &snapshot,
+        assume_yes: options.assume_yes,
+        force: options.force,
+        edited_keys: options.edited_keys,
+        link: options.link,
+        skip_cert_import: options.skip_cert_import,
+        dry_run,
+        editors: prerequisites::target_editors(&*options.host.runner, options.editor),
+        placeholders: Placeholders::new(paths, &options.install_dir),
+    };
+    let mut deployed = state.clone();
+    let result = deploy_steps(
+        &platform_config_dir,
+        paths,
+        options,
+        &settings,
+        &mut deployed,
+        dry_run,
+    );
+    if dry_run {
+        return result;
+    }
+
+    // A failed deploy was rolled back, so only its backups are worth keeping
+    match result {
+        Ok(()) => {
+            state = deployed;
+            state.profile = Some(options.profile.clone());
+        }
+        Err(_) => state.backups = deployed.backups,
+    }
+    backup::prune(&mut state, paths, options.keep_backups)?;
+    state.save(paths, &options.tool)?;
+
+    result
+}
+
+fn deploy_steps(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    options: &DeployOptions,
+    settings: &SettingsDeploy,
+    state: &mut State,
+    dry_run: bool,
+) -> Result<()> {
+    // Files are staged here and only replaced once every step has
+    // succeeded; returning early drops the transaction, which rolls it back
+    let mut tx = Transaction::new();
+
+    // Deploy the .claude tree: settings, CLAUDE.md, commands, and agents
+    deploy_claude_assets(config_dir, paths, settings, state, &mut tx)
+        .context("Failed to deploy Claude settings")?;
+    cancel::check()?;
+
+    // Deploy the .continue tree: Continue's config.yaml and prompts
+    deploy_continue_assets(config_dir, paths, settings, state, &mut tx)
+        .context("Failed to deploy Continue settings")?;
+    cancel::check()?;
+
+    // Merge MCP servers into Claude's config
+    deploy_mcp_configs(config_dir, paths, settings, state, &mut tx)
+        .context("Failed to deploy MCP servers")?;
+    cancel::check()?;
 
     // Deploy certificates
-    deploy_certificates(&platform_config_dir, paths)?;
+    let deployed_certs =
+        deploy_certificates(config_dir, paths, settings.link, state, &mut tx, dry_run)
+            .context("Failed to deploy certificates")?;
+
+    // Deploy VS Code settings, keybindings, and snippets
+    deploy_vscode_configs(config_dir, paths, settings, state, &mut tx)?;
+    cancel::check()?;
+
+    // Deploy the files a declared tool lists
+    deploy_config_files(&options.files, settings, state, &mut tx)?;
+    cancel::check()?;
+
+    // Merge with any bundle NODE_EXTRA_CA_CERTS already names
+    let node_certs = node_extra_ca_certs(
+        paths,
+        &*options.host.environment,
+        &deployed_certs,
+        settings.force,
+        state,
+        &mut tx,
+        dry_run,
+    )
+    .context("Failed to combine certificate bundles")?;
+
+    // Last chance to stop with nothing changed
+    cancel::check()?;
+    tx.commit()?;
+    if !settings.skip_cert_import {
+        import_certificates(&deployed_certs.files, state, dry_run);
+    }
+
+    // Set environment variables last, as they're the hardest to take back
+    configure_environment(
+        &*options.host.environment,
+        node_certs,
+        &options.env,
+        settings,
+        state,
+        dry_run,
+    )
+    .context("Failed to set environment variables")?;
+
+    tx.finish();
+    if !dry_run {
+        cancel::committed("Deployed configuration files");
+    }
+    Ok(())
+}
+
+/// Deploy everything under `.claude/` in the platform config tree into
+/// Claude's config dir, keeping subdirectories. JSON files are merged like
+/// settings; anything else, such as `CLAUDE.md` and the `commands` and
+/// `agents` templates, is copied unless the user changed the deployed copy,
+/// in which case the template is written alongside it.
+fn deploy_claude_assets(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    settings: &SettingsDeploy,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let tree = AssetTree {
+        source: config_dir.join(".claude"),
+        dest: paths.claude_config_dir.clone(),
+        title: "Claude",
+        label: "claude",
+        skip: None,
+    };
+    tree.deploy(settings, state, tx)
+}
+
+/// Deploy everything under `.continue/` in the platform config tree into
+/// `~/.continue` the same way, merging `config.yaml` as YAML. Its `certs`
+/// are left to the certificates step.
+fn deploy_continue_assets(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    settings: &SettingsDeploy,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let tree = AssetTree {
+        source: config_dir.join(".continue"),
+        dest: paths.home_dir.join(".continue"),
+        title: "Continue",
+        label: "continue",
+        skip: Some("certs"),
+    };
+    tree.deploy(settings, state, tx)
+}
+
+/// A directory of templates deployed into a tool's config dir
+struct AssetTree {
+    source: PathBuf,
+    dest: PathBuf,
+    /// Names the files in messages, e.g. "Claude settings"
+    title: &'static str,
+    /// Backup label, with subdirectories appended
+    label: &'static str,
+    /// Subdirectory another step deploys
+    skip: Option<&'static str>,
+}
+
+impl AssetTree {
+    fn deploy(
+        &self,
+        settings: &SettingsDeploy,
+        state: &mut State,
+        tx: &mut Transaction,
+    ) -> Result<()> {
+        if !self.source.is_dir() {
+            return Ok(());
+        }
+
+        let mut copied = false;
+        let mut templates = false;
+        for relative in template_files(&self.source)? {
+            if self.skip.is_some_and(|skip| relative.starts_with(skip)) {
+                continue;
+            }
+            let source = self.source.join(&relative);
+            let dest = self.dest.join(&relative);
+            let label = match relative.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => {
+                    format!("{}/{}", self.label, dir.display())
+                }
+                _ => self.label.to_string(),
+            };
+
+            if relative.extension().is_some_and(|e| e == "json") || yaml::is_yaml(&relative) {
+                let name = if relative == Path::new("settings.json") {
+                    format!("{} settings", self.title)
+                } else {
+                    format!("{} {}", self.title, relative.display())
+                };
+                settings
+                    .deploy(&source, &dest, &name, &label, state, tx)
+                    .with_context(|| format!("Failed to deploy {}", name))?;
+                continue;
+            }
 
-    // Deploy VS Code settings
-    deploy_vscode_settings(&platform_config_dir, paths)?;
+            templates = true;
+            let name = format!("{} {}", self.title, relative.display());
+            copied |= files::copy_template(
+                settings,
+                &source,
+                &dest,
+                &name,
+                &label,
+                Conflict::WriteAlongside,
+                state,
+                tx,
+            )
+            .with_context(|| format!("Failed to deploy {}", name))?;
+        }
 
-    // Set environment variables
-    configure_environment(paths)?;
+        if templates && !copied && !settings.dry_run {
+            reporter().skip(format!("{} templates already up to date", self.title));
+        }
+        Ok(())
+    }
+}
 
+/// Deploy each of `files` to its own destination as its strategy says
+fn deploy_config_files(
+    files: &[ConfigFile],
+    settings: &SettingsDeploy,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    for file in files {
+        let dest = PathBuf::from(settings.placeholders.expand_text(&file.dest, &file.dest));
+        let name = dest
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let label = dest
+            .parent()
+            .and_then(Path::file_name)
+            .map_or_else(|| name.clone(), |dir| dir.to_string_lossy().to_string());
+        let conflict = match file.strategy {
+            FileStrategy::Merge => {
+                settings
+                    .deploy(&file.source, &dest, &name, &label, state, tx)
+                    .with_context(|| format!("Failed to deploy {}", name))?;
+                continue;
+            }
+            FileStrategy::Copy => Conflict::WriteAlongside,
+            FileStrategy::Keep => Conflict::Keep,
+        };
+        let copied = files::copy_template(
+            settings,
+            &file.source,
+            &dest,
+            &name,
+            &label,
+            conflict,
+            state,
+            tx,
+        )
+        .with_context(|| format!("Failed to deploy {}", name))?;
+        if !copied && !settings.dry_run {
+            reporter().skip(format!("{} already up to date", name));
+        }
+    }
     Ok(())
 }
 
-fn deploy_claude_settings(config_dir: &Path, paths: &PlatformPaths) -> Result<()> {
-    let source = config_dir.join(".claude").join("settings.json");
+/// Merge the servers in `mcp.json` into Claude's config
+fn deploy_mcp_configs(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    settings: &SettingsDeploy,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let source = config_dir.join("mcp.json");
     if !source.exists() {
         return Ok(());
     }
 
-    let dest_dir = &paths.claude_config_dir;
-    std::fs::create_dir_all(dest_dir).context("Failed to create .claude directory")?;
+    mcp::deploy_mcp_servers(settings, &source, &paths.claude_json(), state, tx)
+}
 
-    let dest = dest_dir.join("settings.json");
+/// The VS Code settings template, in the platform layout or the simpler
+/// `vscode-settings.json`
+fn vscode_settings_template(config_dir: &Path) -> Option<PathBuf> {
+    vscode_template(config_dir, "settings.json")
+}
 
-    // If settings already exist, merge them
-    if dest.exists() {
-        merge_json_settings(&source, &dest)?;
-        println!(
-            "  {} Merged Claude settings",
-            style("✓").green().bold()
-        );
+/// The VS Code template file or directory `name`, in the platform layout
+/// or the simpler `vscode-<name>` at the top of the config tree
+fn vscode_template(config_dir: &Path, name: &str) -> Option<PathBuf> {
+    let platform_source = get_vscode_user_source(config_dir).join(name);
+
+    // Also check for a simpler path structure
+    let alt_source = config_dir.join(format!("vscode-{}", name));
+
+    if platform_source.exists() {
+        Some(platform_source)
+    } else if alt_source.exists() {
+        Some(alt_source)
     } else {
-        std::fs::copy(&source, &dest).context("Failed to copy Claude settings")?;
-        println!(
-            "  {} Deployed Claude settings",
-            style("✓").green().bold()
-        );
+        None
+    }
+}
+
+/// Deploy the VS Code settings, keybindings, and snippets templates into
+/// every targeted editor
+fn deploy_vscode_configs(
+    config_dir: &Path,
+    paths: &PlatformPaths,
+    settings: &SettingsDeploy,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let settings_source = vscode_settings_template(config_dir);
+    let keybindings_source = vscode_template(config_dir, "keybindings.json");
+    let snippets_source = vscode_template(config_dir, "snippets");
+    if settings_source.is_none() && keybindings_source.is_none() && snippets_source.is_none() {
+        reporter().skip("No VS Code settings to deploy");
+        return Ok(());
     }
 
+    if let Some(problem) = platform::wsl_host_problem() {
+        reporter().warn(format!(
+            "Skipping VS Code settings: VS Code runs on the Windows host, but {}",
+            problem
+        ));
+        reporter().info("  Copy these to %APPDATA%\\Code\\User on Windows by hand:");
+        for (source, name) in [
+            (&settings_source, "settings.json"),
+            (&keybindings_source, "keybindings.json"),
+            (&snippets_source, "snippets"),
+        ] {
+            if let Some(source) = source {
+                reporter().info(format!("    {} as {}", source.display(), name));
+            }
+        }
+        return Ok(());
+    }
+
+    for &editor in &settings.editors {
+        let user_dir = paths.vscode_settings_dir(editor);
+        let label = editor.backup_label();
+
+        if let Some(source) = &settings_source {
+            let name = format!("{} settings", editor);
+            settings
+                .deploy(
+                    source,
+                    &user_dir.join("settings.json"),
+                    &name,
+                    label,
+                    state,
+                    tx,
+                )
+                .with_context(|| format!("Failed to deploy {}", name))?;
+        }
+
+        if let Some(source) = &keybindings_source {
+            let name = format!("{} keybindings", editor);
+            let dest = user_dir.join("keybindings.json");
+            vscode::deploy_keybindings(settings, source, &dest, &name, label, state, tx)
+                .with_context(|| format!("Failed to deploy {}", name))?;
+        }
+
+        if let Some(source) = &snippets_source {
+            let name = format!("{} snippets", editor);
+            let label = format!("{}/snippets", label);
+            vscode::deploy_snippets(
+                settings,
+                source,
+                &user_dir.join("snippets"),
+                &name,
+                &label,
+                state,
+                tx,
+            )
+            .with_context(|| format!("Failed to deploy {}", name))?;
+        }
+    }
     Ok(())
 }
 
-fn deploy_certificates(config_dir: &Path, paths: &PlatformPaths) -> Result<()> {
-    // Look for certificates in different possible locations
-    let cert_sources = [
-        config_dir.join(".continue").join("certs"),
-        config_dir.join("certs"),
-    ];
+/// What every settings file in one deploy shares
+struct SettingsDeploy<'a> {
+    snapshot: &'a Snapshot,
+    assume_yes: bool,
+    /// Overwrite copied files the user changed since they were deployed
+    force: bool,
+    /// What to do with settings keys the user changed since the last deploy
+    edited_keys: EditedKeys,
+    /// Link files that aren't merged instead of copying them
+    link: bool,
+    /// Don't import certificates into the system trust store
+    skip_cert_import: bool,
+    dry_run: bool,
+    /// Editors whose settings the VS Code template is merged into
+    editors: Vec<Editor>,
+    /// Values for the placeholders in JSON templates
+    placeholders: Placeholders,
+}
 
-    let mut found_certs = false;
+impl SettingsDeploy<'_> {
+    /// Merge the template at `source` into `dest`, first showing what will
+    /// change and, unless `assume_yes`, asking. The write is staged in `tx`.
+    /// `name` describes the file in messages and `label` names its
+    /// directory in backups.
+    fn deploy(
+        &self,
+        source: &Path,
+        dest: &Path,
+        name: &str,
+        label: &str,
+        state: &mut State,
+        tx: &mut Transaction,
+    ) -> Result<()> {
+        let mut merge = plan_settings_merge(
+            source,
+            dest,
+            state,
+            &MergeOptions::default(),
+            &self.placeholders,
+        )?;
+        self.settle_edited(&mut merge)?;
+        let (changes, untouched) = merge.changes();
 
-    for cert_source in &cert_sources {
-        if !cert_source.exists() {
-            continue;
+        if let Some(reason) = &merge.broken {
+            reporter().warn(format!(
+                "{} can't be merged into ({}); it will be backed up and replaced by the template",
+                dest.display(),
+                reason
+            ));
         }
 
-        std::fs::create_dir_all(&paths.certs_dir).context("Failed to create certs directory")?;
+        if self.dry_run {
+            self.snapshot.save(dest, label, state, true)?;
+            if merge.broken.is_some() {
+                plan::step(format!(
+                    "Back up {} and replace it with {}",
+                    dest.display(),
+                    source.display()
+                ));
+            } else {
+                plan_settings_write(source, dest);
+            }
+            print_settings_changes(&changes, untouched);
+            return Ok(());
+        }
 
-        for entry in std::fs::read_dir(cert_source)? {
-            let entry = entry?;
-            let path = entry.path();
+        if changes.is_empty() && !merge.created && merge.broken.is_none() {
+            apply_settings_merge(merge, state, tx, name)?;
+            reporter().skip(format!("{} already up to date", name));
+            return Ok(());
+        }
 
-            // Skip macOS resource fork files
-            if path
-                .file_name()
-                .map(|n| n.to_string_lossy().starts_with("._"))
-                .unwrap_or(false)
-            {
-                continue;
+        if !self.assume_yes {
+            reporter().info(format!("Changes to {}:", dest.display()));
+            print_settings_changes(&changes, untouched);
+            if !output::confirm(&format!("This will update {}.", name))? {
+                reporter().skip(format!("Left {} unchanged", name));
+                return Ok(());
             }
+        }
+
+        self.snapshot.save(dest, label, state, false)?;
+        events::emit(Event::SettingsChanged {
+            file: dest.to_path_buf(),
+            keys: changes.iter().map(|c| c.key().to_string()).collect(),
+        });
+        if apply_settings_merge(merge, state, tx, name)? {
+            reporter().success(format!("Deployed {}", name));
+        } else {
+            reporter().success(format!("Merged {}", name));
+        }
+        reporter().detail(dest.display());
+
+        Ok(())
+    }
 
-            if path.extension().map(|e| e == "crt").unwrap_or(false) {
-                let dest = paths.certs_dir.join(entry.file_name());
-                std::fs::copy(&path, &dest).context("Failed to copy certificate")?;
-
-                println!(
-                    "  {} Deployed certificate: {}",
-                    style("✓").green().bold(),
-                    entry.file_name().to_string_lossy()
-                );
-
-                // Try to import the certificate
-                if let Err(e) = platform::import_certificate(&dest) {
-                    println!(
-                        "  {} Certificate import: {}",
-                        style("!").yellow().bold(),
-                        e
-                    );
+    /// Decide about the keys the user changed since the last deploy: kept
+    /// by default, replaced with `--force-settings`, or asked about one at a
+    /// time with `--interactive`. Those kept are listed.
+    fn settle_edited(&self, merge: &mut SettingsMerge) -> Result<()> {
+        let mut kept = Vec::new();
+        for edited in std::mem::take(&mut merge.edited) {
+            let replace = match self.edited_keys {
+                EditedKeys::Keep => false,
+                EditedKeys::Overwrite => true,
+                // A dry run asks nothing, so shows them as kept
+                EditedKeys::Ask if self.dry_run => false,
+                EditedKeys::Ask => {
+                    reporter().info(format!(
+                        "{} in {} was changed since the last deploy:",
+                        describe_key(&edited.key),
+                        merge.dest.display()
+                    ));
+                    print_edited_key(&edited);
+                    let input = output::prompt(
+                        "  Replace it with the template's value? [y/N] ",
+                        "--force-settings to replace changed keys",
+                    )?;
+                    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
                 }
+            };
+            if replace {
+                merge.overwrite(edited);
+            } else {
+                kept.push(edited);
+            }
+        }
 
-                found_certs = true;
+        if !kept.is_empty() && self.edited_keys != EditedKeys::Ask {
+            reporter().warn(format!(
+                "Keeping {} key(s) in {} changed since the last deploy \
+                 (--force-settings replaces them):",
+                kept.len(),
+                merge.dest.display()
+            ));
+            for edited in &kept {
+                print_edited_key(edited);
             }
         }
+        merge.edited = kept;
+        Ok(())
     }
+}
 
-    if !found_certs {
-        println!(
-            "  {} No certificates to deploy",
-            style("-").dim()
-        );
+/// Print a key the user changed next to what the template would write
+fn print_edited_key(edited: &EditedKey) {
+    let current = match &edited.current {
+        Some(value) => describe_value(value),
+        None => "removed".to_string(),
+    };
+    reporter().output(
+        style(format!(
+            "    ! {}: {} (template: {})",
+            describe_key(&edited.key),
+            current,
+            describe_value(&edited.template)
+        ))
+        .yellow(),
+    );
+}
+
+/// Print a settings merge's changes, one key per line
+fn print_settings_changes(changes: &[KeyChange], untouched: usize) {
+    let out = reporter();
+    for change in changes {
+        match change {
+            KeyChange::Added { key, value } => out.output(
+                style(format!(
+                    "    + {}: {}",
+                    describe_key(key),
+                    describe_value(value)
+                ))
+                .green(),
+            ),
+            KeyChange::Changed { key, from, to } => out.output(
+                style(format!(
+                    "    ~ {}: {} -> {}",
+                    describe_key(key),
+                    describe_value(from),
+                    describe_value(to)
+                ))
+                .yellow(),
+            ),
+            KeyChange::Removed { key, value } => out.output(
+                style(format!(
+                    "    - {}: {}",
+                    describe_key(key),
+                    describe_value(value)
+                ))
+                .red(),
+            ),
+        }
+    }
+    if changes.is_empty() {
+        out.output(style("    no changes").dim());
+    }
+    if untouched > 0 {
+        out.output(style(format!("    {} other key(s) left untouched", untouched)).dim());
+    }
+}
+
+fn describe_key(key: &str) -> String {
+    serde_json::Value::from(key).to_string()
+}
+
+/// A value as compact JSON, shortened to fit on a line
+fn describe_value(value: &serde_json::Value) -> String {
+    const MAX_CHARS: usize = 60;
+    let json = value.to_string();
+    if json.chars().count() <= MAX_CHARS {
+        return json;
     }
+    let short: String = json.chars().take(MAX_CHARS - 3).collect();
+    format!("{}...", short)
+}
+
+fn configure_environment(
+    environment: &dyn Environment,
+    node_certs: Option<NodeCaCerts>,
+    env: &[(String, String)],
+    settings: &SettingsDeploy,
+    state: &mut State,
+    dry_run: bool,
+) -> Result<()> {
+    for (name, value) in env {
+        let value = settings.placeholders.expand_text(value, name);
+        if environment.get_user_env_var(name).as_deref() == Some(value.as_str()) {
+            reporter().skip(format!("{} is already set", name));
+            continue;
+        }
+        // The value from before the first deploy is the one to put back
+        let previous = state
+            .env_var_previous(name)
+            .map(str::to_string)
+            .or_else(|| environment.get_user_env_var(name));
+        environment.set_user_env_var(name, &value, dry_run)?;
+        if dry_run {
+            continue;
+        }
+        state.record_env_var(name, &value, previous.as_deref());
+        events::emit(Event::EnvVarSet {
+            name: name.clone(),
+            value: value.clone(),
+        });
+        reporter().success(format!("Set {} environment variable", name));
+        reporter().detail(&value);
+    }
+
+    // Node only reads one extra CA file, so point it at the bundle of every
+    // certificate
+    let Some(node_certs) = node_certs else {
+        return Ok(());
+    };
+    let value = platform::path_str(&node_certs.path)?;
+
+    environment.set_user_env_var("NODE_EXTRA_CA_CERTS", value, dry_run)?;
+    if dry_run {
+        return Ok(());
+    }
+    state.record_env_var("NODE_EXTRA_CA_CERTS", value, node_certs.previous.as_deref());
+    events::emit(Event::EnvVarSet {
+        name: "NODE_EXTRA_CA_CERTS".to_string(),
+        value: value.to_string(),
+    });
+    reporter().success("Set NODE_EXTRA_CA_CERTS environment variable");
+    reporter().detail(node_certs.path.display());
 
     Ok(())
 }
 
-fn deploy_vscode_settings(config_dir: &Path, paths: &PlatformPaths) -> Result<()> {
-    let platform_source = get_vscode_settings_source(config_dir);
+/// The recorded deployment state, or for installs made before it was
+/// tracked, what can be inferred from the files on disk
+pub fn load_state(local_dir: &Path, paths: &PlatformPaths, tool: &str) -> Result<State> {
+    if let Some(state) = State::load(paths, tool)? {
+        return Ok(state);
+    }
 
-    // Also check for a simpler path structure
-    let alt_source = config_dir.join("vscode-settings.json");
+    let config_dir = get_platform_config_dir(local_dir, tool);
+    let mut state = State::default();
 
-    let source = if platform_source.exists() {
-        platform_source
-    } else if alt_source.exists() {
-        alt_source
+    // Claude settings that still match the template exactly were created by
+    // us; anything else may hold the user's own values
+    let claude_settings = paths.claude_config_dir.join("settings.json");
+    let claude_template = config_dir.join(".claude").join("settings.json");
+    if same_json(&claude_template, &claude_settings) {
+        state.record_created_settings(&claude_settings);
+        for (key, value) in read_json_object(&claude_settings).unwrap_or_default() {
+            state.record_key(&claude_settings, &key, value, None);
+        }
     } else {
-        println!(
-            "  {} No VS Code settings to deploy",
-            style("-").dim()
-        );
-        return Ok(());
+        infer_settings_keys(&claude_template, &claude_settings, &mut state);
+    }
+
+    // Installs from before the state file only deployed to VS Code itself
+    if let Some(template) = vscode_settings_template(&config_dir) {
+        let vscode_settings = paths
+            .vscode_settings_dir(Editor::Stable)
+            .join("settings.json");
+        infer_settings_keys(&template, &vscode_settings, &mut state);
+    }
+
+    for cert in certificate_files(&config_dir)? {
+        let dest = paths.certs_dir.join(cert.file_name().unwrap_or_default());
+        if dest.exists() {
+            state.record_file(&dest);
+        }
+    }
+
+    if let Ok(value) = std::env::var("NODE_EXTRA_CA_CERTS") {
+        if state.files.iter().any(|f| f.as_os_str() == value.as_str()) {
+            state.record_env_var("NODE_EXTRA_CA_CERTS", &value, None);
+        }
+    }
+
+    state.migrated = !state.settings.is_empty() || !state.files.is_empty();
+    Ok(state)
+}
+
+/// Record template keys that `dest` holds with the template's value. Whether
+/// we or the user set them is unknown, so they are marked as inferred.
+fn infer_settings_keys(template: &Path, dest: &Path, state: &mut State) {
+    let (Some(template_obj), Some(dest_obj)) = (read_json_object(template), read_json_object(dest))
+    else {
+        return;
     };
 
-    std::fs::create_dir_all(&paths.vscode_settings_dir)
-        .context("Failed to create VS Code settings directory")?;
+    for (key, value) in template_obj {
+        if dest_obj.get(&key) == Some(&value) {
+            state.settings.push(SettingsKey {
+                file: dest.to_path_buf(),
+                key,
+                value,
+                previous: None,
+                inferred: true,
+            });
+        }
+    }
+}
 
-    let dest = paths.vscode_settings_dir.join("settings.json");
+/// Differences between the recorded state and the machine, one line each.
+/// Environment variables are compared with their saved values in
+/// `environment`, not this process's. Empty if nothing has drifted or
+/// nothing was recorded.
+pub fn config_drift(
+    paths: &PlatformPaths,
+    tool: &str,
+    environment: &dyn Environment,
+) -> Result<Vec<String>> {
+    let Some(state) = State::load(paths, tool)? else {
+        return Ok(Vec::new());
+    };
 
-    if dest.exists() {
-        merge_json_settings(&source, &dest)?;
-        println!(
-            "  {} Merged VS Code settings",
-            style("✓").green().bold()
-        );
+    let mut drift = Vec::new();
+
+    for file in &state.files {
+        if !file.exists() {
+            drift.push(format!("{} is missing", file.display()));
+        }
+    }
+
+    let mut settings_files: Vec<&PathBuf> = state.settings.iter().map(|s| &s.file).collect();
+    settings_files.sort();
+    settings_files.dedup();
+    for file in settings_files {
+        let Some(current) = read_json_object(file) else {
+            drift.push(format!("{} is missing or not valid JSON", file.display()));
+            continue;
+        };
+        for entry in state.settings.iter().filter(|s| &s.file == file) {
+            if current.get(&entry.key) != Some(&entry.value) {
+                drift.push(format!("{}: {} was changed", file.display(), entry.key));
+            }
+        }
+    }
+
+    for binding in &state.keybindings {
+        let present = vscode::read_keybindings(&binding.file)
+            .is_ok_and(|entries| entries.contains(&binding.entry));
+        if !present {
+            drift.push(format!(
+                "{}: keybinding {} was removed or changed",
+                binding.file.display(),
+                binding.entry.get("key").unwrap_or(&serde_json::Value::Null)
+            ));
+        }
+    }
+
+    for server in &state.mcp_servers {
+        let current = mcp::read_servers(&server.file).and_then(|mut s| s.remove(&server.name));
+        match current {
+            Some(definition) if definition == server.definition => {}
+            Some(_) => drift.push(format!(
+                "{}: MCP server {} was changed",
+                server.file.display(),
+                server.name
+            )),
+            None => drift.push(format!(
+                "{}: MCP server {} is missing",
+                server.file.display(),
+                server.name
+            )),
+        }
+    }
+
+    for link in &state.linked_files {
+        if !link.is_intact() {
+            drift.push(format!(
+                "{} is no longer linked to {}",
+                link.path.display(),
+                link.target.display()
+            ));
+        } else if !link.target.exists() {
+            drift.push(format!(
+                "{} links to {}, which is missing",
+                link.path.display(),
+                link.target.display()
+            ));
+        }
+    }
+
+    for copied in &state.copied_files {
+        match std::fs::read(&copied.path) {
+            Ok(content) if sha256_hex(&content) == copied.sha256 => {}
+            Ok(_) => drift.push(format!("{} was changed", copied.path.display())),
+            Err(_) => drift.push(format!("{} is missing", copied.path.display())),
+        }
+    }
+
+    for var in &state.env_vars {
+        if environment.get_user_env_var(&var.name).as_deref() != Some(var.value.as_str()) {
+            drift.push(format!("{} is no longer set to {}", var.name, var.value));
+        }
+    }
+
+    Ok(drift)
+}
+
+/// A file a deploy copied or linked into place, for `status`
+#[derive(Debug, Serialize)]
+pub struct DeployedFile {
+    pub path: PathBuf,
+    /// Template the file links to, or `None` if it was copied
+    pub link: Option<PathBuf>,
+}
+
+/// Files the recorded deploy copied or linked into place, in path order
+pub fn deployed_files(paths: &PlatformPaths, tool: &str) -> Result<Vec<DeployedFile>> {
+    let Some(state) = State::load(paths, tool)? else {
+        return Ok(Vec::new());
+    };
+
+    let copied = state
+        .files
+        .iter()
+        .chain(state.copied_files.iter().map(|f| &f.path))
+        .filter(|path| state.linked_file(path).is_none())
+        .map(|path| DeployedFile {
+            path: path.clone(),
+            link: None,
+        });
+    let linked = state.linked_files.iter().map(|link| DeployedFile {
+        path: link.path.clone(),
+        link: Some(link.target.clone()),
+    });
+    let mut files: Vec<DeployedFile> = copied.chain(linked).collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Revert what `deploy_configs` recorded in the state file, returning a
+/// description of everything left in place because it can't be attributed
+/// to code-assist or was changed since
+///
+/// With `dry_run`, print each removal instead of making it.
+pub fn purge_configs(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    tool: &str,
+    host: &Host,
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let state = load_state(local_dir, paths, tool)?;
+    let mut left_in_place = Vec::new();
+
+    let mut settings_files: Vec<&PathBuf> = state
+        .settings
+        .iter()
+        .map(|s| &s.file)
+        .chain(&state.created_settings)
+        .collect();
+    settings_files.sort();
+    settings_files.dedup();
+
+    for file in settings_files {
+        let Some(mut current) = read_json_object(file) else {
+            continue;
+        };
+
+        let mut reverted = Vec::new();
+        for entry in state.settings.iter().filter(|s| &s.file == file) {
+            if entry.inferred {
+                left_in_place.push(format!(
+                    "{}: {} (may have been set before install)",
+                    file.display(),
+                    entry.key
+                ));
+                continue;
+            }
+            if current.get(&entry.key) != Some(&entry.value) {
+                left_in_place.push(format!(
+                    "{}: {} (changed since it was deployed)",
+                    file.display(),
+                    entry.key
+                ));
+                continue;
+            }
+
+            match &entry.previous {
+                Some(previous) => current.insert(entry.key.clone(), previous.clone()),
+                None => current.remove(&entry.key),
+            };
+            reverted.push(entry.key.as_str());
+        }
+
+        if state.created_settings.contains(file) && current.is_empty() {
+            remove_deployed_file(file, "settings file", dry_run)?;
+        } else if !reverted.is_empty() {
+            if dry_run {
+                plan::step(format!(
+                    "Revert {} in {}",
+                    reverted.join(", "),
+                    file.display()
+                ));
+                continue;
+            }
+            write_settings(file, &serde_json::Value::Object(current))?;
+            reporter().success(format!("Reverted {} setting(s)", reverted.len()));
+            reporter().detail(file.display());
+        }
+    }
+
+    let mut keybinding_files: Vec<&PathBuf> = state.keybindings.iter().map(|k| &k.file).collect();
+    keybinding_files.sort();
+    keybinding_files.dedup();
+    for file in keybinding_files {
+        let Ok(mut entries) = vscode::read_keybindings(file) else {
+            continue;
+        };
+        let ours: Vec<&serde_json::Value> = state
+            .keybindings
+            .iter()
+            .filter(|k| &k.file == file)
+            .map(|k| &k.entry)
+            .collect();
+        for entry in &ours {
+            if !entries.contains(entry) {
+                left_in_place.push(format!(
+                    "{}: keybinding {} (changed since it was deployed)",
+                    file.display(),
+                    entry.get("key").unwrap_or(&serde_json::Value::Null)
+                ));
+            }
+        }
+        let before = entries.len();
+        entries.retain(|entry| !ours.contains(&entry));
+        let reverted = before - entries.len();
+
+        if state.created_settings.contains(file) && entries.is_empty() {
+            remove_deployed_file(file, "keybindings file", dry_run)?;
+        } else if reverted > 0 {
+            if dry_run {
+                plan::step(format!(
+                    "Remove {} keybinding(s) from {}",
+                    reverted,
+                    file.display()
+                ));
+                continue;
+            }
+            write_settings(file, &serde_json::Value::Array(entries))?;
+            reporter().success(format!("Removed {} keybinding(s)", reverted));
+            reporter().detail(file.display());
+        }
+    }
+
+    let mut mcp_files: Vec<&PathBuf> = state.mcp_servers.iter().map(|s| &s.file).collect();
+    mcp_files.sort();
+    mcp_files.dedup();
+    for file in mcp_files {
+        let Some(current) = mcp::read_servers(file) else {
+            continue;
+        };
+        let mut ours = Vec::new();
+        for server in state.mcp_servers.iter().filter(|s| &s.file == file) {
+            match current.get(&server.name) {
+                Some(definition) if *definition == server.definition => {
+                    ours.push(server.name.as_str())
+                }
+                Some(_) => left_in_place.push(format!(
+                    "{}: MCP server {} (changed since it was deployed)",
+                    file.display(),
+                    server.name
+                )),
+                None => {}
+            }
+        }
+        if ours.is_empty() {
+            continue;
+        }
+        if dry_run {
+            plan::step(format!(
+                "Remove MCP server(s) {} from {}",
+                ours.join(", "),
+                file.display()
+            ));
+            continue;
+        }
+        mcp::remove_servers(file, &ours)?;
+        reporter().success(format!("Removed {} MCP server(s)", ours.len()));
+        reporter().detail(file.display());
+    }
+
+    for link in &state.linked_files {
+        if link.is_intact() {
+            remove_deployed_file(&link.path, "link", dry_run)?;
+            if let Some(parent) = link.path.parent().filter(|_| !dry_run) {
+                // Only succeeds once the directory is empty
+                std::fs::remove_dir(parent).ok();
+            }
+        } else if link.path.exists() && !state.files.contains(&link.path) {
+            left_in_place.push(format!(
+                "{} (no longer a link to {})",
+                link.path.display(),
+                link.target.display()
+            ));
+        }
+    }
+
+    for copied in &state.copied_files {
+        match std::fs::read(&copied.path) {
+            Ok(content) if sha256_hex(&content) == copied.sha256 => {
+                remove_deployed_file(&copied.path, "deployed file", dry_run)?;
+                if let Some(parent) = copied.path.parent().filter(|_| !dry_run) {
+                    // Only succeeds once the directory is empty
+                    std::fs::remove_dir(parent).ok();
+                }
+            }
+            Ok(_) => left_in_place.push(format!(
+                "{} (changed since it was deployed)",
+                copied.path.display()
+            )),
+            Err(_) => {}
+        }
+    }
+
+    for file in &state.files {
+        if file.exists() {
+            remove_deployed_file(file, "certificate", dry_run)?;
+        }
+    }
+
+    for thumbprint in &state.trusted_certificates {
+        match platform::remove_certificate(thumbprint, dry_run) {
+            Ok(()) if !dry_run => reporter().success(format!(
+                "Removed certificate {} from the trust store",
+                thumbprint
+            )),
+            Ok(()) => {}
+            Err(e) => reporter().warn(format!(
+                "Could not remove certificate {} from the trust store: {:#}",
+                thumbprint, e
+            )),
+        }
+    }
+
+    backup::remove_all(&state, paths, dry_run)?;
+    if !dry_run
+        && paths
+            .certs_dir
+            .read_dir()
+            .is_ok_and(|mut d| d.next().is_none())
+    {
+        std::fs::remove_dir(&paths.certs_dir).ok();
+    }
+
+    for var in &state.env_vars {
+        // Put back what another tool had set before we replaced it
+        if let Some(previous) = &var.previous {
+            match host
+                .environment
+                .set_user_env_var(&var.name, previous, dry_run)
+            {
+                Ok(()) if !dry_run => {
+                    reporter().success(format!("Restored {} to {}", var.name, previous))
+                }
+                Ok(()) => {}
+                Err(e) => reporter().warn(format!("Could not restore {}: {}", var.name, e)),
+            }
+            continue;
+        }
+        match host.environment.unset_user_env_var(&var.name, dry_run) {
+            Ok(()) if !dry_run => reporter().success(format!("Unset {}", var.name)),
+            Ok(()) => {}
+            Err(e) => reporter().warn(format!("Could not unset {}: {}", var.name, e)),
+        }
+    }
+
+    for dir in &state.path_entries {
+        match host.environment.remove_from_path(dir, dry_run) {
+            Ok(()) if !dry_run => reporter().success(format!("Removed {} from PATH", dir)),
+            Ok(()) => {}
+            Err(e) => reporter().warn(format!("Could not remove {} from PATH: {}", dir, e)),
+        }
+    }
+
+    if dry_run {
+        if State::exists(paths, tool) {
+            plan::step(format!("Remove {}", State::path(paths, tool).display()));
+        }
     } else {
-        std::fs::copy(&source, &dest).context("Failed to copy VS Code settings")?;
-        println!(
-            "  {} Deployed VS Code settings",
-            style("✓").green().bold()
-        );
+        State::remove(paths, tool)?;
+    }
+
+    Ok(left_in_place)
+}
+
+fn remove_deployed_file(path: &Path, label: &str, dry_run: bool) -> Result<()> {
+    if dry_run {
+        plan::step(format!("Remove {} {}", label, path.display()));
+        return Ok(());
     }
 
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    reporter().success(format!("Removed {}", label));
+    reporter().detail(path.display());
     Ok(())
 }
 
-fn configure_environment(paths: &PlatformPaths) -> Result<()> {
-    // Set NODE_EXTRA_CA_CERTS if we have certificates
-    let zscaler_cert = paths.certs_dir.join("ZscalerRootCertificate-2048-SHA256.crt");
-    let alt_cert = paths.certs_dir.join("zscaler-root.crt");
+/// Whether two files hold the same JSON value; false if either can't be read
+fn same_json(a: &Path, b: &Path) -> bool {
+    let read = |path: &Path| -> Option<serde_json::Value> {
+        parse_settings(path, &std::fs::read_to_string(path).ok()?).ok()
+    };
+    matches!((read(a), read(b)), (Some(a), Some(b)) if a == b)
+}
 
-    let cert_path = if zscaler_cert.exists() {
-        Some(zscaler_cert)
-    } else if alt_cert.exists() {
-        Some(alt_cert)
+/// A JSON object file's contents, or `None` if it's missing or not an object
+pub fn read_json_object(path: &Path) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match parse_settings(path, &content).ok()? {
+        serde_json::Value::Object(obj) => Some(obj),
+        _ => None,
+    }
+}
+
+/// Describe the settings write that a dry run skipped
+fn plan_settings_write(source: &Path, dest: &Path) {
+    if dest.exists() {
+        plan::step(format!(
+            "Merge {} into {}",
+            source.display(),
+            dest.display()
+        ));
     } else {
-        None
-    };
+        plan::step(format!("Copy {} to {}", source.display(), dest.display()));
+    }
+}
 
-    if let Some(cert) = cert_path {
-        platform::set_user_env_var("NODE_EXTRA_CA_CERTS", cert.to_str().unwrap())?;
-        println!(
-            "  {} Set NODE_EXTRA_CA_CERTS environment variable",
-            style("✓").green().bold()
-        );
+/// What merging a settings template into a file will do, worked out before
+/// anything is written so it can be shown and confirmed
+#[derive(Debug)]
+struct SettingsMerge {
+    dest: PathBuf,
+    created: bool,
+    /// Why the existing file can't be merged into, if it's to be set aside
+    /// and replaced by the template
+    broken: Option<String>,
+    before: serde_json::Value,
+    after: serde_json::Value,
+    /// Template keys to record in the state, with their merged and
+    /// previous values
+    recorded: Vec<(String, serde_json::Value, Option<serde_json::Value>)>,
+    /// Keys from an earlier deploy that the template no longer has
+    stale: Vec<SettingsKey>,
+    /// Keys the user changed since the last deploy, left as they are
+    edited: Vec<EditedKey>,
+}
+
+/// How a template key in a settings file relates to earlier deploys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyOrigin {
+    /// No deploy has written it
+    New,
+    /// Still holds what the last deploy wrote
+    Unchanged,
+    /// Changed or removed since the last deploy wrote it
+    UserModified,
+}
+
+impl KeyOrigin {
+    /// Classify `key` in `dest`, which holds `current`, against what
+    /// `state` records writing there
+    fn of(state: &State, dest: &Path, key: &str, current: Option<&serde_json::Value>) -> Self {
+        match state
+            .settings
+            .iter()
+            .find(|s| s.file == dest && s.key == key)
+        {
+            None => KeyOrigin::New,
+            Some(recorded) if current == Some(&recorded.value) => KeyOrigin::Unchanged,
+            Some(_) => KeyOrigin::UserModified,
+        }
     }
+}
 
-    Ok(())
+/// A template key the user changed since the last deploy
+#[derive(Debug, Clone)]
+struct EditedKey {
+    key: String,
+    /// The user's value; `None` where they removed the key
+    current: Option<serde_json::Value>,
+    /// What the merge would write instead
+    template: serde_json::Value,
 }
 
-fn merge_json_settings(source: &Path, dest: &Path) -> Result<()> {
+impl SettingsMerge {
+    /// Top-level keys the merge changes, and how many it leaves alone
+    fn changes(&self) -> (Vec<KeyChange>, usize) {
+        match (&self.before, &self.after) {
+            (serde_json::Value::Object(before), serde_json::Value::Object(after)) => {
+                merge::diff_keys(before, after)
+            }
+            _ => (Vec::new(), 0),
+        }
+    }
+
+    /// Write the template's value for an edited key after all
+    fn overwrite(&mut self, edited: EditedKey) {
+        if let serde_json::Value::Object(after) = &mut self.after {
+            after.insert(edited.key.clone(), edited.template.clone());
+        }
+        self.recorded
+            .push((edited.key, edited.template, edited.current));
+    }
+}
+
+/// Work out how the template at `source` merges into `dest` without writing
+/// anything. Nested objects are merged rather than replaced, and arrays
+/// follow `options`. Keys set on an earlier run that the template no longer
+/// has are reverted, and those the user changed since are set aside in
+/// `edited` rather than overwritten. Placeholders in the template are
+/// expanded first.
+fn plan_settings_merge(
+    source: &Path,
+    dest: &Path,
+    state: &State,
+    options: &MergeOptions,
+    placeholders: &Placeholders,
+) -> Result<SettingsMerge> {
     let source_content = std::fs::read_to_string(source)?;
-    let dest_content = std::fs::read_to_string(dest)?;
+    let mut source_json = parse_settings(source, &source_content)
+        .with_context(|| format!("Failed to parse source settings {}", format_name(source)))?;
+    placeholders.expand(&mut source_json, source);
+
+    // A destination that can't be merged into is replaced by the template,
+    // after being set aside, rather than failing the whole deploy
+    let created = !dest.exists();
+    let mut broken = None;
+    let before = if created {
+        serde_json::Value::Object(serde_json::Map::new())
+    } else {
+        let parsed = String::from_utf8(std::fs::read(dest)?)
+            .map_err(|_| "it isn't valid UTF-8".to_string())
+            .and_then(|content| {
+                parse_settings(dest, &content)
+                    .map_err(|e| format!("invalid {}: {}", format_name(dest), e))
+            });
+        match parsed {
+            Ok(value) if value.is_object() || !source_json.is_object() => value,
+            Ok(value) => {
+                broken = Some(format!(
+                    "it holds {} instead of an object",
+                    describe_value(&value)
+                ));
+                serde_json::Value::Object(serde_json::Map::new())
+            }
+            Err(reason) => {
+                broken = Some(reason);
+                serde_json::Value::Object(serde_json::Map::new())
+            }
+        }
+    };
 
-    let source_json: serde_json::Value = serde_json::from_str(&source_content)
-        .context("Failed to parse source settings JSON")?;
-    let mut dest_json: serde_json::Value = serde_json::from_str(&dest_content)
-        .context("Failed to parse destination settings JSON")?;
+    let mut after = before.clone();
+    let mut recorded = Vec::new();
+    let mut stale = Vec::new();
+    let mut edited = Vec::new();
 
-    // Merge source into dest (source values override dest)
+    // Merge source into dest (source values override dest, nested objects merge)
     if let (serde_json::Value::Object(source_obj), serde_json::Value::Object(dest_obj)) =
-        (source_json, &mut dest_json)
+        (source_json, &mut after)
     {
+        stale = state
+            .settings
+            .iter()
+            .filter(|s| s.file == dest && !s.inferred && !source_obj.contains_key(&s.key))
+            .cloned()
+            .collect();
+        for entry in &stale {
+            if dest_obj.get(&entry.key) == Some(&entry.value) {
+                match &entry.previous {
+                    Some(previous) => dest_obj.insert(entry.key.clone(), previous.clone()),
+                    None => dest_obj.remove(&entry.key),
+                };
+            }
+        }
+
         for (key, value) in source_obj {
-            dest_obj.insert(key, value);
+            let previous = dest_obj.get(&key).cloned();
+            let mut merged = previous.clone().unwrap_or_default();
+            merge::merge_key(&mut merged, &key, value, options);
+
+            // A file that was removed or replaced starts over from the
+            // template, so only keys in one being merged into count as edited
+            let merging = !created && broken.is_none();
+            if merging
+                && previous.as_ref() != Some(&merged)
+                && KeyOrigin::of(state, dest, &key, previous.as_ref()) == KeyOrigin::UserModified
+            {
+                edited.push(EditedKey {
+                    key,
+                    current: previous,
+                    template: merged,
+                });
+                continue;
+            }
+            dest_obj.insert(key.clone(), merged.clone());
+            recorded.push((key, merged, previous));
         }
     }
 
-    let merged = serde_json::to_string_pretty(&dest_json)?;
-    std::fs::write(dest, merged)?;
+    Ok(SettingsMerge {
+        dest: dest.to_path_buf(),
+        created,
+        broken,
+        before,
+        after,
+        recorded,
+        stale,
+        edited,
+    })
+}
+
+/// Stage a planned merge's write in `tx`, under the deploy step `step`, and
+/// record it in `state`. The file is left alone if the merge doesn't change
+/// it. Returns whether the file will be created.
+fn apply_settings_merge(
+    merge: SettingsMerge,
+    state: &mut State,
+    tx: &mut Transaction,
+    step: &str,
+) -> Result<bool> {
+    if merge.created {
+        state.record_created_settings(&merge.dest);
+    }
+    state.settings.retain(|s| {
+        !merge
+            .stale
+            .iter()
+            .any(|t| t.file == s.file && t.key == s.key)
+    });
+    for (key, value, previous) in merge.recorded {
+        state.record_key(&merge.dest, &key, value, previous);
+    }
+
+    if merge.created || merge.broken.is_some() || merge.after != merge.before {
+        if merge.broken.is_some() {
+            set_aside_broken(&merge.dest)?;
+        } else {
+            preserve_comments(&merge.dest)?;
+        }
+        tx.stage(
+            &merge.dest,
+            format_settings(&merge.dest, &merge.after)?,
+            step,
+        )?;
+    }
+    Ok(merge.created)
+}
+
+/// A settings file's contents: YAML for `.yaml` and `.yml` files, else JSON
+/// with comments
+fn parse_settings(path: &Path, content: &str) -> Result<serde_json::Value> {
+    if yaml::is_yaml(path) {
+        yaml::parse(content)
+    } else {
+        Ok(jsonc::parse(content)?)
+    }
+}
+
+/// `value` written the way `path`'s format expects
+fn format_settings(path: &Path, value: &serde_json::Value) -> Result<String> {
+    if yaml::is_yaml(path) {
+        yaml::to_string(value)
+    } else {
+        Ok(serde_json::to_string_pretty(value)?)
+    }
+}
+
+fn format_name(path: &Path) -> &'static str {
+    if yaml::is_yaml(path) {
+        "YAML"
+    } else {
+        "JSON"
+    }
+}
+
+/// Write a settings file, keeping a copy if it has comments
+fn write_settings(path: &Path, value: &serde_json::Value) -> Result<()> {
+    preserve_comments(path)?;
+    let content = format_settings(path, value)?;
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Comments in a settings file about to be replaced can't be carried over,
+/// so a file that has them is first copied to a timestamped backup next to it
+fn preserve_comments(path: &Path) -> Result<()> {
+    if let Ok(existing) = std::fs::read_to_string(path) {
+        let commented = if yaml::is_yaml(path) {
+            yaml::has_comments(&existing)
+        } else {
+            jsonc::has_comments(&existing)
+        };
+        if commented {
+            let backup = backup_path(path);
+            std::fs::write(&backup, existing)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+            reporter().warn(format!(
+                "Comments in {} could not be preserved; the original is saved as {}",
+                path.display(),
+                backup.display()
+            ));
+        }
+    }
+    Ok(())
+}
 
+/// Copy a settings file that can't be parsed to `settings.json.broken-<UTC
+/// timestamp>` next to it, before the template replaces it. The deploy has
+/// already warned about it.
+fn set_aside_broken(path: &Path) -> Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stamp = utc_timestamp(std::time::SystemTime::now());
+    let mut backup = path.with_file_name(format!("{}.broken-{}", name, stamp));
+    let mut n = 1;
+    while backup.exists() {
+        n += 1;
+        backup = path.with_file_name(format!("{}.broken-{}-{}", name, stamp, n));
+    }
+    std::fs::copy(path, &backup)
+        .with_context(|| format!("Failed to back up {}", path.display()))?;
+    reporter().info(format!("Saved the original as {}", backup.display()));
     Ok(())
 }
 
-/// Install VSIX extensions from a directory
-pub fn install_vsix_extensions(vsix_dir: &Path) -> Result<()> {
-    if !vsix_dir.exists() {
-        println!(
-            "  {} No VSIX extensions to install",
-            style("-").dim()
+/// `settings.json.<UTC timestamp>.bak` next to `path`, numbered if a backup
+/// was already made this second
+fn backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stamp = utc_timestamp(std::time::SystemTime::now());
+    let mut backup = path.with_file_name(format!("{}.{}.bak", name, stamp));
+    let mut n = 1;
+    while backup.exists() {
+        n += 1;
+        backup = path.with_file_name(format!("{}.{}-{}.bak", name, stamp, n));
+    }
+    backup
+}
+
+/// `time` as a compact UTC timestamp, e.g. `20260131T235959Z`, which sorts
+/// in time order
+pub fn utc_timestamp(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, time_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fake::FakeEnvironment;
+    use crate::process::fake::FakeRunner;
+    use serde_json::json;
+    use std::rc::Rc;
+
+    /// A template and an existing settings file in a temp directory
+    fn files(
+        template: serde_json::Value,
+        existing: Option<&str>,
+    ) -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("template.json");
+        let dest = dir.path().join("settings.json");
+        std::fs::write(&source, template.to_string()).unwrap();
+        if let Some(existing) = existing {
+            std::fs::write(&dest, existing).unwrap();
+        }
+        (dir, source, dest)
+    }
+
+    fn plan(source: &Path, dest: &Path, state: &State) -> SettingsMerge {
+        plan_settings_merge(
+            source,
+            dest,
+            state,
+            &MergeOptions::default(),
+            &Placeholders::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn planning_a_merge_writes_nothing() {
+        let existing = r#"{ "theme": "dark", "model": "opus" }"#;
+        let (_dir, source, dest) = files(json!({ "model": "sonnet", "new": 1 }), Some(existing));
+        let state = State::default();
+
+        let merge = plan(&source, &dest, &state);
+        let (changes, untouched) = merge.changes();
+
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), existing);
+        assert_eq!(untouched, 1);
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Changed {
+                    key: "model".to_string(),
+                    from: json!("opus"),
+                    to: json!("sonnet"),
+                },
+                KeyChange::Added {
+                    key: "new".to_string(),
+                    value: json!(1),
+                },
+            ]
         );
-        return Ok(());
     }
 
-    let vscode_cli = get_vscode_cli();
+    #[test]
+    fn applying_a_merge_writes_and_records_it() {
+        let (_dir, source, dest) =
+            files(json!({ "model": "sonnet" }), Some(r#"{ "model": "opus" }"#));
+        let mut state = State::default();
 
-    for entry in std::fs::read_dir(vsix_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+        let merge = plan(&source, &dest, &state);
+        let mut tx = Transaction::new();
+        let created = apply_settings_merge(merge, &mut state, &mut tx, "test").unwrap();
+        tx.commit().unwrap();
+        tx.finish();
 
-        if path.extension().map(|e| e == "vsix").unwrap_or(false) {
-            let filename = entry.file_name();
-            println!(
-                "  Installing extension: {}",
-                style(filename.to_string_lossy()).cyan()
-            );
+        assert!(!created);
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&dest).unwrap()).unwrap();
+        assert_eq!(written, json!({ "model": "sonnet" }));
+        assert_eq!(state.settings.len(), 1);
+        assert_eq!(state.settings[0].previous, Some(json!("opus")));
+    }
 
-            let output = std::process::Command::new(vscode_cli)
-                .args(["--install-extension", path.to_str().unwrap()])
-                .output()
-                .context("Failed to run VS Code CLI")?;
+    #[test]
+    fn plans_reverting_keys_the_template_dropped() {
+        let (_dir, source, dest) = files(json!({}), Some(r#"{ "old": "ours" }"#));
+        let mut state = State::default();
+        state.record_key(&dest, "old", json!("ours"), Some(json!("theirs")));
 
-            if output.status.success() {
-                println!(
-                    "  {} Installed {}",
-                    style("✓").green().bold(),
-                    filename.to_string_lossy()
-                );
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                println!(
-                    "  {} Failed to install {}: {}",
-                    style("✗").red().bold(),
-                    filename.to_string_lossy(),
-                    stderr.trim()
-                );
-            }
+        let merge = plan(&source, &dest, &state);
+
+        assert_eq!(
+            merge.changes().0,
+            vec![KeyChange::Changed {
+                key: "old".to_string(),
+                from: json!("ours"),
+                to: json!("theirs"),
+            }]
+        );
+        apply_settings_merge(merge, &mut state, &mut Transaction::new(), "test").unwrap();
+        assert!(state.settings.is_empty());
+    }
+
+    #[test]
+    fn keys_are_classified_against_what_the_last_deploy_wrote() {
+        let dest = Path::new("/home/me/.claude/settings.json");
+        let mut state = State::default();
+        state.record_key(dest, "model", json!("sonnet"), None);
+
+        let origin = |key: &str, current: Option<serde_json::Value>| {
+            KeyOrigin::of(&state, dest, key, current.as_ref())
+        };
+        assert_eq!(origin("theme", Some(json!("dark"))), KeyOrigin::New);
+        assert_eq!(origin("model", Some(json!("sonnet"))), KeyOrigin::Unchanged);
+        assert_eq!(
+            origin("model", Some(json!("opus"))),
+            KeyOrigin::UserModified
+        );
+        assert_eq!(origin("model", None), KeyOrigin::UserModified);
+    }
+
+    #[test]
+    fn keys_the_user_changed_are_left_alone() {
+        let (_dir, source, dest) = files(
+            json!({ "model": "sonnet-4", "maxTokens": 8000, "theme": "light" }),
+            Some(r#"{ "model": "sonnet", "maxTokens": 4000, "theme": "dark" }"#),
+        );
+        let mut state = State::default();
+        state.record_key(&dest, "model", json!("sonnet"), None);
+        state.record_key(&dest, "maxTokens", json!(2000), None);
+
+        let merge = plan(&source, &dest, &state);
+
+        // "model" is ours to update and "theme" is new; the user raised
+        // "maxTokens" after we set it
+        assert_eq!(
+            merge.changes().0,
+            vec![
+                KeyChange::Changed {
+                    key: "model".to_string(),
+                    from: json!("sonnet"),
+                    to: json!("sonnet-4"),
+                },
+                KeyChange::Changed {
+                    key: "theme".to_string(),
+                    from: json!("dark"),
+                    to: json!("light"),
+                },
+            ]
+        );
+        assert_eq!(merge.edited.len(), 1);
+        assert_eq!(merge.edited[0].key, "maxTokens");
+        assert_eq!(merge.edited[0].current, Some(json!(4000)));
+        assert_eq!(merge.edited[0].template, json!(8000));
+
+        apply_settings_merge(merge, &mut state, &mut Transaction::new(), "test").unwrap();
+        let recorded = state
+            .settings
+            .iter()
+            .find(|s| s.key == "maxTokens")
+            .unwrap();
+        assert_eq!(recorded.value, json!(2000));
+    }
+
+    #[test]
+    fn an_overwritten_edit_is_written_and_recorded() {
+        let (_dir, source, dest) = files(
+            json!({ "maxTokens": 8000 }),
+            Some(r#"{ "maxTokens": 4000 }"#),
+        );
+        let mut state = State::default();
+        state.record_key(&dest, "maxTokens", json!(2000), Some(json!(1000)));
+
+        let mut merge = plan(&source, &dest, &state);
+        let edited = merge.edited.pop().unwrap();
+        merge.overwrite(edited);
+        apply_settings_merge(merge, &mut state, &mut Transaction::new(), "test").unwrap();
+
+        assert_eq!(state.settings[0].value, json!(8000));
+        assert_eq!(state.settings[0].previous, Some(json!(1000)));
+    }
+
+    #[test]
+    fn a_recreated_file_takes_every_template_key() {
+        let (_dir, source, dest) = files(json!({ "maxTokens": 8000 }), None);
+        let mut state = State::default();
+        state.record_key(&dest, "maxTokens", json!(2000), None);
+
+        let merge = plan(&source, &dest, &state);
+
+        assert!(merge.edited.is_empty());
+        assert_eq!(merge.after, json!({ "maxTokens": 8000 }));
+    }
+
+    /// Plan and apply a merge, returning what was written and the files
+    /// left next to it
+    fn merge_into(template: serde_json::Value, existing: &str) -> (serde_json::Value, Vec<String>) {
+        let (dir, source, dest) = files(template, Some(existing));
+        let mut state = State::default();
+
+        let merge = plan(&source, &dest, &state);
+        let mut tx = Transaction::new();
+        apply_settings_merge(merge, &mut state, &mut tx, "test").unwrap();
+        tx.commit().unwrap();
+        tx.finish();
+
+        let written = jsonc::parse(&std::fs::read_to_string(&dest).unwrap()).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("settings.json"))
+            .collect();
+        names.sort();
+        (written, names)
+    }
+
+    #[test]
+    fn an_empty_file_is_merged_into_as_empty_settings() {
+        let (written, names) = merge_into(json!({ "model": "sonnet" }), "");
+
+        assert_eq!(written, json!({ "model": "sonnet" }));
+        assert_eq!(names, ["settings.json"]);
+    }
+
+    #[test]
+    fn a_byte_order_mark_is_ignored() {
+        let (written, names) = merge_into(
+            json!({ "model": "sonnet" }),
+            "\u{feff}{ \"theme\": \"dark\" }",
+        );
+
+        assert_eq!(written, json!({ "theme": "dark", "model": "sonnet" }));
+        assert_eq!(names, ["settings.json"]);
+    }
+
+    #[test]
+    fn truncated_settings_are_set_aside_and_replaced() {
+        let (dir, source, dest) = files(json!({ "model": "sonnet" }), Some(r#"{ "theme": "da"#));
+        let mut state = State::default();
+
+        let merge = plan(&source, &dest, &state);
+        assert!(merge.broken.as_deref().unwrap().starts_with("invalid JSON"));
+        let mut tx = Transaction::new();
+        apply_settings_merge(merge, &mut state, &mut tx, "test").unwrap();
+        tx.commit().unwrap();
+        tx.finish();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&dest).unwrap()).unwrap();
+        assert_eq!(written, json!({ "model": "sonnet" }));
+        let backup = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().contains("settings.json.broken-"))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(backup).unwrap(),
+            r#"{ "theme": "da"#
+        );
+        assert_eq!(state.settings[0].previous, None);
+    }
+
+    #[test]
+    fn settings_that_are_not_utf8_are_set_aside_byte_for_byte() {
+        let (dir, source, dest) = files(json!({ "model": "sonnet" }), None);
+        let original = b"{ \"theme\": \"\xff\" }";
+        std::fs::write(&dest, original).unwrap();
+        let mut state = State::default();
+
+        let merge = plan(&source, &dest, &state);
+        assert_eq!(merge.broken.as_deref(), Some("it isn't valid UTF-8"));
+        let mut tx = Transaction::new();
+        apply_settings_merge(merge, &mut state, &mut tx, "test").unwrap();
+        tx.commit().unwrap();
+        tx.finish();
+
+        let backup = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().contains("settings.json.broken-"))
+            .unwrap();
+        assert_eq!(std::fs::read(backup).unwrap(), original);
+    }
+
+    #[test]
+    fn null_settings_are_set_aside_and_replaced() {
+        let (written, names) = merge_into(json!({}), "null");
+
+        assert_eq!(written, json!({}));
+        assert_eq!(names.len(), 2);
+        assert!(names[1].starts_with("settings.json.broken-"), "{:?}", names);
+    }
+
+    #[test]
+    fn a_new_file_is_created_even_without_changes() {
+        let (_dir, source, dest) = files(json!({}), None);
+        let mut state = State::default();
+
+        let merge = plan(&source, &dest, &state);
+        assert!(merge.changes().0.is_empty());
+
+        let mut tx = Transaction::new();
+        assert!(apply_settings_merge(merge, &mut state, &mut tx, "test").unwrap());
+        assert!(!dest.exists());
+        tx.commit().unwrap();
+        tx.finish();
+        assert!(dest.exists());
+        assert_eq!(state.created_settings, vec![dest]);
+    }
+
+    /// Paths under `root` standing in for the user's home
+    fn temp_paths(root: &Path) -> PlatformPaths {
+        let home = root.join("home");
+        PlatformPaths {
+            claude_config_dir: home.join(".claude"),
+            app_data_dir: home.join("appdata"),
+            certs_dir: home.join("certs"),
+            vscode_user_dir: None,
+            downloads_dir: home.join("downloads"),
+            state_dir: home.join("state"),
+            backups_dir: home.join("backups"),
+            home_dir: home,
         }
     }
 
-    Ok(())
-}
+    fn deploy_options(paths: &PlatformPaths) -> DeployOptions {
+        DeployOptions {
+            keep_backups: 0,
+            assume_yes: true,
+            force: false,
+            edited_keys: EditedKeys::Keep,
+            editor: None,
+            install_dir: paths.claude_config_dir.join("bin"),
+            tool: LEGACY_TOOL.to_string(),
+            profile: "default".to_string(),
+            link: false,
+            skip_cert_import: true,
+            files: Vec::new(),
+            env: Vec::new(),
+            host: fake_host(Rc::new(FakeEnvironment::default())),
+        }
+    }
+
+    /// A host that runs nothing and keeps the environment in `environment`
+    fn fake_host(environment: Rc<FakeEnvironment>) -> Host {
+        Host {
+            runner: Rc::new(FakeRunner::new(|_| (0, String::new()))),
+            environment,
+        }
+    }
+
+    #[test]
+    fn a_deploy_emits_each_change() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let local = dir.path().join("local");
+        let templates = get_platform_tree(&local).join(".claude");
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(templates.join("settings.json"), r#"{ "model": "sonnet" }"#).unwrap();
+        std::fs::write(templates.join("CLAUDE.md"), "# Team notes\n").unwrap();
+        let paths = temp_paths(dir.path());
+
+        let (result, emitted) =
+            events::capture(|| deploy_configs(&local, &paths, &deploy_options(&paths), false));
+        result.unwrap();
+
+        let settings = paths.claude_config_dir.join("settings.json");
+        assert_eq!(
+            emitted,
+            [
+                Event::SettingsChanged {
+                    file: settings.clone(),
+                    keys: vec!["model".to_string()],
+                },
+                Event::FileDeployed(paths.claude_config_dir.join("CLAUDE.md")),
+                Event::FileDeployed(settings),
+            ]
+        );
+    }
+
+    #[test]
+    fn declared_environment_variables_are_set_for_the_user() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = temp_paths(dir.path());
+        let environment = Rc::new(FakeEnvironment::default());
+        let options = DeployOptions {
+            env: vec![("TEAM_URL".to_string(), "https://example.com".to_string())],
+            host: fake_host(environment.clone()),
+            ..deploy_options(&paths)
+        };
 
-fn get_vscode_cli() -> &'static str {
-    "code"
+        let (result, emitted) =
+            events::capture(|| deploy_configs(&dir.path().join("local"), &paths, &options, false));
+        result.unwrap();
+
+        assert_eq!(environment.changes(), ["set TEAM_URL=https://example.com"]);
+        assert_eq!(
+            emitted,
+            [Event::EnvVarSet {
+                name: "TEAM_URL".to_string(),
+                value: "https://example.com".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn a_missing_config_tree_is_a_warning() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = temp_paths(dir.path());
+
+        let (result, emitted) = events::capture(|| {
+            deploy_configs(
+                &dir.path().join("local"),
+                &paths,
+                &deploy_options(&paths),
+                false,
+            )
+        });
+        result.unwrap();
+
+        assert_eq!(
+            emitted,
+            [Event::Warning(
+                "No platform-specific configs found".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn drift_is_reported_once_per_change_against_the_saved_environment() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = temp_paths(dir.path());
+        let (a, b) = (dir.path().join("a.json"), dir.path().join("b.json"));
+        std::fs::write(&a, r#"{ "one": 1, "two": 0 }"#).unwrap();
+        std::fs::write(&b, r#"{ "three": 3 }"#).unwrap();
+        let mut state = State::default();
+        state.record_key(&a, "one", json!(1), None);
+        state.record_key(&b, "three", json!(3), None);
+        state.record_key(&a, "two", json!(2), None);
+        state.record_env_var("TEAM_URL", "https://example.com", None);
+        state.record_env_var("TEAM_NAME", "platform", None);
+        state.save(&paths, "test").unwrap();
+
+        let environment = FakeEnvironment::default();
+        environment
+            .set_user_env_var("TEAM_URL", "https://example.com", false)
+            .unwrap();
+
+        assert_eq!(
+            config_drift(&paths, "test", &environment).unwrap(),
+            [
+                format!("{}: two was changed", a.display()),
+                "TEAM_NAME is no longer set to platform".to_string(),
+            ]
+        );
+    }
 }
@@ -0,0 +1,225 @@
+//! `${...}` placeholders in JSON templates, replaced with paths and names for
+//! this machine before a template is merged, so one template serves every
+//! user
+//!
+//! | Placeholder            | Value                                     |
+//! |------------------------|-------------------------------------------|
+//! | `${HOME}`              | The user's home directory                 |
+//! | `${CLAUDE_CONFIG_DIR}` | Claude's config directory (`~/.claude`)   |
+//! | `${CERTS_DIR}`         | Where certificates are deployed           |
+//! | `${INSTALL_DIR}`       | Where the tool's binary is installed      |
+//! | `${USERNAME}`          | The user's login name                     |
+//! | `${env:NAME}`          | Environment variable `NAME`               |
+//!
+//! `$${` is written as a literal `${`.
+
+use std::path::Path;
+
+use crate::output::reporter;
+use crate::platform::PlatformPaths;
+
+/// Values for the placeholders a template may use
+#[derive(Debug, Default)]
+pub struct Placeholders {
+    vars: Vec<(&'static str, String)>,
+}
+
+impl Placeholders {
+    pub fn new(paths: &PlatformPaths, install_dir: &Path) -> Self {
+        let username = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| {
+                paths
+                    .home_dir
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            });
+        let path = |p: &Path| p.to_string_lossy().to_string();
+        Self {
+            vars: vec![
+                ("HOME", path(&paths.home_dir)),
+                ("CLAUDE_CONFIG_DIR", path(&paths.claude_config_dir)),
+                ("CERTS_DIR", path(&paths.certs_dir)),
+                ("INSTALL_DIR", path(install_dir)),
+                ("USERNAME", username),
+            ],
+        }
+    }
+
+    /// Replace the placeholders in every string in `value`, warning about
+    /// any that aren't known, which are left as they are. Keys and other
+    /// values are left alone. `source` names the template in the warning.
+    pub fn expand(&self, value: &mut serde_json::Value, source: &Path) {
+        let mut unknown = Vec::new();
+        self.expand_value(value, &mut unknown);
+        warn_unknown(unknown, &source.display());
+    }
+
+    /// `text` with its placeholders replaced, such as a path or environment
+    /// variable a tool definition names, warning about unknown ones as
+    /// `expand` does. `what` names the text in the warning.
+    pub fn expand_text(&self, text: &str, what: &str) -> String {
+        let mut unknown = Vec::new();
+        let expanded = self.expand_str(text, &mut unknown);
+        warn_unknown(unknown, &what);
+        expanded
+    }
+
+    fn expand_value(&self, value: &mut serde_json::Value, unknown: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(text) => *text = self.expand_str(text, unknown),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.expand_value(item, unknown);
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                for item in fields.values_mut() {
+                    self.expand_value(item, unknown);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `text` with its placeholders replaced, adding the names of unknown
+    /// ones to `unknown`
+    fn expand_str(&self, text: &str, unknown: &mut Vec<String>) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(after) = rest.strip_prefix("$${") {
+                expanded.push_str("${");
+                rest = after;
+                continue;
+            }
+            let Some(end) = rest.strip_prefix("${").and_then(|r| r.find('}')) else {
+                expanded.push('$');
+                rest = &rest[1..];
+                continue;
+            };
+
+            let name = &rest[2..end + 2];
+            match self.value(name) {
+                Some(value) => expanded.push_str(&value),
+                None => {
+                    unknown.push(name.to_string());
+                    expanded.push_str(&rest[..end + 3]);
+                }
+            }
+            rest = &rest[end + 3..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        if let Some(var) = name.strip_prefix("env:") {
+            return std::env::var(var).ok();
+        }
+        self.vars
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+fn warn_unknown(mut unknown: Vec<String>, what: &dyn std::fmt::Display) {
+    unknown.sort();
+    unknown.dedup();
+    for name in unknown {
+        reporter().warn(format!(
+            "Unknown placeholder ${{{}}} in {} was left as is",
+            name, what
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn placeholders() -> Placeholders {
+        let paths = PlatformPaths {
+            home_dir: PathBuf::from("/home/me"),
+            claude_config_dir: PathBuf::from("/home/me/.claude"),
+            app_data_dir: PathBuf::from("/home/me/.config"),
+            certs_dir: PathBuf::from("/home/me/certs"),
+            vscode_user_dir: None,
+            downloads_dir: PathBuf::from("/home/me/.cache/code-assist/downloads"),
+            state_dir: PathBuf::from("/home/me/.local/state/code-assist"),
+            backups_dir: PathBuf::from("/home/me/.local/state/code-assist/backups"),
+        };
+        Placeholders::new(&paths, Path::new("/home/me/.claude/bin"))
+    }
+
+    fn expand(text: &str) -> (String, Vec<String>) {
+        let mut unknown = Vec::new();
+        let expanded = placeholders().expand_str(text, &mut unknown);
+        (expanded, unknown)
+    }
+
+    #[test]
+    fn expands_known_placeholders() {
+        assert_eq!(
+            expand("${HOME}/proxy.pac").0,
+            "/home/me/proxy.pac".to_string()
+        );
+        assert_eq!(
+            expand("${CERTS_DIR}/a.crt:${INSTALL_DIR}").0,
+            "/home/me/certs/a.crt:/home/me/.claude/bin".to_string()
+        );
+    }
+
+    #[test]
+    fn expands_environment_variables() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(expand("${env:PATH}").0, path);
+        assert_eq!(
+            expand("${env:CODE_ASSIST_SURELY_UNSET}"),
+            (
+                "${env:CODE_ASSIST_SURELY_UNSET}".to_string(),
+                vec!["env:CODE_ASSIST_SURELY_UNSET".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_and_escaped_placeholders() {
+        assert_eq!(
+            expand("${TOKEN} and ${HOME"),
+            ("${TOKEN} and ${HOME".to_string(), vec!["TOKEN".to_string()])
+        );
+        assert_eq!(
+            expand("$${HOME} costs $5"),
+            ("${HOME} costs $5".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn expands_nested_strings_only() {
+        let mut value = json!({
+            "${HOME}": "${HOME}",
+            "nested": { "list": ["${CERTS_DIR}", 1, true, null] },
+            "number": 2.5
+        });
+
+        placeholders().expand(&mut value, Path::new("settings.json"));
+
+        assert_eq!(
+            value,
+            json!({
+                "${HOME}": "/home/me",
+                "nested": { "list": ["/home/me/certs", 1, true, null] },
+                "number": 2.5
+            })
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! What a deploy would touch, worked out from the templates without
+//! reading or changing the user's files beyond checking which exist, for
+//! the plan `install` shows before asking to go ahead.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use super::certs::certificate_files;
+use super::files::template_files;
+use super::placeholders::Placeholders;
+use super::{get_platform_config_dir, vscode_template, yaml, DeployOptions, FileStrategy};
+use crate::platform::{self, PlatformPaths};
+use crate::prerequisites;
+
+/// A settings file a deploy would merge into, or create if it's missing
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+impl PlannedFile {
+    fn new(path: PathBuf) -> Self {
+        let exists = path.exists();
+        PlannedFile { path, exists }
+    }
+}
+
+/// The files and environment variables a deploy would change
+#[derive(Debug, Default)]
+pub struct DeployPreview {
+    pub settings: Vec<PlannedFile>,
+    /// Where each certificate would be copied
+    pub certificates: Vec<PathBuf>,
+    /// Names of the environment variables that would be set
+    pub env_vars: Vec<String>,
+}
+
+/// What deploying `local_dir`'s templates with `options` would change
+pub fn preview_deploy(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    options: &DeployOptions,
+) -> Result<DeployPreview> {
+    let config_dir = get_platform_config_dir(local_dir, &options.tool);
+    let mut preview = DeployPreview::default();
+
+    for (source, dest, skip) in [
+        (
+            config_dir.join(".claude"),
+            paths.claude_config_dir.clone(),
+            None,
+        ),
+        (
+            config_dir.join(".continue"),
+            paths.home_dir.join(".continue"),
+            Some("certs"),
+        ),
+    ] {
+        if !source.is_dir() {
+            continue;
+        }
+        for relative in template_files(&source)? {
+            if skip.is_some_and(|skip| relative.starts_with(skip)) {
+                continue;
+            }
+            if relative.extension().is_some_and(|e| e == "json") || yaml::is_yaml(&relative) {
+                preview
+                    .settings
+                    .push(PlannedFile::new(dest.join(&relative)));
+            }
+        }
+    }
+
+    if config_dir.join("mcp.json").exists() {
+        preview.settings.push(PlannedFile::new(paths.claude_json()));
+    }
+
+    let vscode_files: Vec<&str> = ["settings.json", "keybindings.json"]
+        .into_iter()
+        .filter(|name| vscode_template(&config_dir, name).is_some())
+        .collect();
+    if !vscode_files.is_empty() && platform::wsl_host_problem().is_none() {
+        for editor in prerequisites::target_editors(&*options.host.runner, options.editor) {
+            let user_dir = paths.vscode_settings_dir(editor);
+            for name in &vscode_files {
+                preview.settings.push(PlannedFile::new(user_dir.join(name)));
+            }
+        }
+    }
+
+    let placeholders = Placeholders::new(paths, &options.install_dir);
+    for file in &options.files {
+        if file.strategy == FileStrategy::Merge {
+            let dest = placeholders.expand_text(&file.dest, &file.dest);
+            preview.settings.push(PlannedFile::new(PathBuf::from(dest)));
+        }
+    }
+
+    if config_dir.exists() {
+        for cert in certificate_files(&config_dir)? {
+            preview
+                .certificates
+                .push(paths.certs_dir.join(cert.file_name().unwrap_or_default()));
+        }
+    }
+
+    preview
+        .env_vars
+        .extend(options.env.iter().map(|(name, _)| name.clone()));
+    if !preview.certificates.is_empty() {
+        preview.env_vars.push("NODE_EXTRA_CA_CERTS".to_string());
+    }
+
+    Ok(preview)
+}
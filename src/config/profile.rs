@@ -0,0 +1,83 @@
+//! Named configuration sets. `local/profiles/<name>/` holds a profile's own
+//! platform config tree, `VSIX/`, and `extensions.json`; without a
+//! `default` profile there, the flat layout directly in `local/` is the
+//! default one.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::error::Failure;
+
+/// Profile used when none is asked for or remembered
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Subdirectory of the local artifacts directory holding the profiles
+const PROFILES_DIR: &str = "profiles";
+
+/// Directory holding `profile`'s templates and extensions
+pub fn profile_dir(local_dir: &Path, profile: &str) -> Result<PathBuf> {
+    let dir = local_dir.join(PROFILES_DIR).join(profile);
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+    if profile == DEFAULT_PROFILE {
+        return Ok(local_dir.to_path_buf());
+    }
+
+    Err(Failure::Configuration(format!(
+        "Unknown profile '{}' (available: {})",
+        profile,
+        list_profiles(local_dir).join(", ")
+    ))
+    .into())
+}
+
+/// Profiles in `local_dir`, in name order, always including the default
+pub fn list_profiles(local_dir: &Path) -> Vec<String> {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    if let Ok(entries) = std::fs::read_dir(local_dir.join(PROFILES_DIR)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && !name.starts_with('.') && !profiles.contains(&name) {
+                profiles.push(name);
+            }
+        }
+    }
+    profiles.sort();
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_falls_back_to_the_flat_layout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("profiles").join("data-science")).unwrap();
+
+        assert_eq!(profile_dir(dir.path(), "default").unwrap(), dir.path());
+        assert_eq!(
+            profile_dir(dir.path(), "data-science").unwrap(),
+            dir.path().join("profiles").join("data-science")
+        );
+
+        let error = profile_dir(dir.path(), "finance").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown profile 'finance' (available: data-science, default)"
+        );
+    }
+
+    #[test]
+    fn a_default_profile_directory_wins_over_the_flat_layout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("profiles").join("default")).unwrap();
+
+        assert_eq!(
+            profile_dir(dir.path(), "default").unwrap(),
+            dir.path().join("profiles").join("default")
+        );
+        assert_eq!(list_profiles(dir.path()), ["default"]);
+    }
+}
@@ -0,0 +1,470 @@
+//! All-or-nothing file writes for a deploy, so a step that fails partway
+//! leaves the machine as it was rather than half configured
+//!
+//! Writes are staged to temporary files next to their destinations, checked,
+//! and then moved into place with renames. Until the transaction is
+//! finished, the files they replaced are kept aside, and dropping an
+//! unfinished transaction puts them back.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+use super::{jsonc, yaml};
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::state::LinkKind;
+
+/// Suffix of a staged write waiting to be committed
+const STAGED_SUFFIX: &str = "code-assist-new";
+
+/// Suffix of a replaced file kept until the transaction finishes
+const ASIDE_SUFFIX: &str = "code-assist-old";
+
+/// A set of file writes that happen together or not at all
+#[derive(Default)]
+pub struct Transaction {
+    staged: Vec<Staged>,
+    committed: Vec<Committed>,
+    /// Directories created for staged files, outermost first
+    created_dirs: Vec<PathBuf>,
+    finished: bool,
+}
+
+/// A write staged to `temp`, to be moved to `dest` on commit
+struct Staged {
+    dest: PathBuf,
+    temp: PathBuf,
+    /// What was written, or `None` for a link
+    contents: Option<Vec<u8>>,
+    step: String,
+}
+
+/// A file moved into place, and where the file it replaced was put
+struct Committed {
+    dest: PathBuf,
+    aside: Option<PathBuf>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `contents` to a temporary file next to `dest`, creating its
+    /// directory if needed. `step` names the deploy step in errors. A
+    /// symlinked `dest` is written through, so the link is kept.
+    pub fn stage(&mut self, dest: &Path, contents: impl Into<Vec<u8>>, step: &str) -> Result<()> {
+        let dest = match std::fs::canonicalize(dest) {
+            Ok(target) if dest.is_symlink() => target,
+            _ => dest.to_path_buf(),
+        };
+        self.stage_unlinked(&dest, contents, step)
+    }
+
+    /// Like `stage`, but a symlink at `dest` is replaced rather than
+    /// written through, for links an earlier `--link` deploy made
+    pub fn stage_unlinked(
+        &mut self,
+        dest: &Path,
+        contents: impl Into<Vec<u8>>,
+        step: &str,
+    ) -> Result<()> {
+        let contents = contents.into();
+        if let Some(parent) = dest.parent() {
+            self.create_dir(parent)
+                .with_context(|| format!("{}: failed to create {}", step, parent.display()))?;
+        }
+
+        let temp = sibling(dest, STAGED_SUFFIX);
+        std::fs::write(&temp, &contents)
+            .with_context(|| format!("{}: failed to stage {}", step, dest.display()))?;
+        self.staged.push(Staged {
+            dest: dest.to_path_buf(),
+            temp,
+            contents: Some(contents),
+            step: step.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Make a link to `target` next to `dest`, to be moved into place on
+    /// commit, creating its directory if needed. Where symlinks need a
+    /// privilege the user lacks, a hard link is made instead; junctions only
+    /// link directories, so they don't help for files.
+    pub fn stage_link(&mut self, dest: &Path, target: &Path, step: &str) -> Result<LinkKind> {
+        if let Some(parent) = dest.parent() {
+            self.create_dir(parent)
+                .with_context(|| format!("{}: failed to create {}", step, parent.display()))?;
+        }
+
+        let temp = sibling(dest, STAGED_SUFFIX);
+        std::fs::remove_file(&temp).ok();
+        let kind = make_link(target, &temp).with_context(|| {
+            format!(
+                "{}: failed to link {} to {}",
+                step,
+                dest.display(),
+                target.display()
+            )
+        })?;
+        self.staged.push(Staged {
+            dest: dest.to_path_buf(),
+            temp,
+            contents: None,
+            step: step.to_string(),
+        });
+        Ok(kind)
+    }
+
+    /// Check every staged file, then move them all into place. If any of it
+    /// fails, the files already moved are put back before returning.
+    pub fn commit(&mut self) -> Result<()> {
+        let result = self.validate().and_then(|()| self.rename_all());
+        if result.is_err() {
+            self.rollback();
+        }
+        result
+    }
+
+    /// Keep the committed files and delete the ones they replaced
+    pub fn finish(mut self) {
+        for committed in &self.committed {
+            events::emit(Event::FileDeployed(committed.dest.clone()));
+            if let Some(aside) = &committed.aside {
+                std::fs::remove_file(aside).ok();
+            }
+        }
+        self.finished = true;
+    }
+
+    /// Each staged file must read back as written, and JSON files must still
+    /// parse, allowing the comments VS Code accepts, as must YAML files
+    fn validate(&self) -> Result<()> {
+        for staged in &self.staged {
+            let written = std::fs::read(&staged.temp).with_context(|| {
+                format!(
+                    "{}: failed to read back {}",
+                    staged.step,
+                    staged.temp.display()
+                )
+            })?;
+            if staged.contents.as_ref().is_some_and(|c| *c != written) {
+                return Err(anyhow!(
+                    "{}: staged copy of {} doesn't match what was written",
+                    staged.step,
+                    staged.dest.display()
+                ));
+            }
+            if staged.dest.extension().is_some_and(|e| e == "json") {
+                let text = String::from_utf8_lossy(&written);
+                jsonc::parse(&text).with_context(|| {
+                    format!(
+                        "{}: {} is not valid JSON",
+                        staged.step,
+                        staged.dest.display()
+                    )
+                })?;
+            }
+            if yaml::is_yaml(&staged.dest) {
+                yaml::parse(&String::from_utf8_lossy(&written)).with_context(|| {
+                    format!(
+                        "{}: {} is not valid YAML",
+                        staged.step,
+                        staged.dest.display()
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rename_all(&mut self) -> Result<()> {
+        // Taken one at a time, so those not reached are left for `rollback`
+        while !self.staged.is_empty() {
+            let staged = self.staged.remove(0);
+            // Anything other than a file in the way makes the rename fail
+            let aside = if staged.dest.is_file() {
+                let aside = sibling(&staged.dest, ASIDE_SUFFIX);
+                if let Err(e) = std::fs::rename(&staged.dest, &aside) {
+                    std::fs::remove_file(&staged.temp).ok();
+                    return Err(e).with_context(|| {
+                        format!(
+                            "{}: failed to replace {}",
+                            staged.step,
+                            staged.dest.display()
+                        )
+                    });
+                }
+                Some(aside)
+            } else {
+                None
+            };
+
+            // Recorded before the rename so a failure still restores the
+            // file moved aside
+            self.committed.push(Committed {
+                dest: staged.dest.clone(),
+                aside,
+            });
+            if let Err(e) = std::fs::rename(&staged.temp, &staged.dest) {
+                std::fs::remove_file(&staged.temp).ok();
+                return Err(e).with_context(|| {
+                    format!("{}: failed to write {}", staged.step, staged.dest.display())
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Put back every file the transaction replaced, and remove what it
+    /// created
+    fn rollback(&mut self) {
+        for staged in self.staged.drain(..) {
+            std::fs::remove_file(&staged.temp).ok();
+        }
+
+        for committed in self.committed.drain(..).rev() {
+            let restored = match &committed.aside {
+                Some(aside) => std::fs::rename(aside, &committed.dest),
+                None if committed.dest.is_file() || committed.dest.is_symlink() => {
+                    std::fs::remove_file(&committed.dest)
+                }
+                None => Ok(()),
+            };
+            match (restored, &committed.aside) {
+                (Ok(()), _) => reporter().warn(format!("Rolled back {}", committed.dest.display())),
+                (Err(e), Some(aside)) => reporter().error(format!(
+                    "Failed to restore {} ({}); the original is at {}",
+                    committed.dest.display(),
+                    e,
+                    aside.display()
+                )),
+                (Err(e), None) => reporter().error(format!(
+                    "Failed to remove {}: {}",
+                    committed.dest.display(),
+                    e
+                )),
+            }
+        }
+
+        for dir in self.created_dirs.drain(..).rev() {
+            std::fs::remove_dir(dir).ok();
+        }
+    }
+
+    /// Create `dir` and any missing parents, remembering which were new
+    fn create_dir(&mut self, dir: &Path) -> std::io::Result<()> {
+        let missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect();
+        std::fs::create_dir_all(dir)?;
+        self.created_dirs.extend(missing.into_iter().rev());
+        Ok(())
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.rollback();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn make_link(target: &Path, link: &Path) -> std::io::Result<LinkKind> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(LinkKind::Symlink)
+}
+
+#[cfg(windows)]
+fn make_link(target: &Path, link: &Path) -> std::io::Result<LinkKind> {
+    use windows_sys::Win32::Foundation::ERROR_PRIVILEGE_NOT_HELD;
+
+    match std::os::windows::fs::symlink_file(target, link) {
+        Ok(()) => Ok(LinkKind::Symlink),
+        // Symlinks need developer mode or an elevated process
+        Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD as i32) => {
+            std::fs::hard_link(target, link)?;
+            Ok(LinkKind::Hardlink)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// `.<name>.<suffix>` in the same directory as `path`, so a rename to or
+/// from it never crosses filesystems
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn dir() -> tempfile::TempDir {
+        tempfile::TempDir::new().unwrap()
+    }
+
+    /// Names of the files left in `dir`
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn commits_all_writes() {
+        let dir = dir();
+        let existing = dir.path().join("a.json");
+        let new = dir.path().join("nested").join("b.crt");
+        fs::write(&existing, "{}").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&existing, r#"{"a":1}"#, "first").unwrap();
+        tx.stage(&new, "cert", "second").unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "{}");
+        tx.commit().unwrap();
+        tx.finish();
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), r#"{"a":1}"#);
+        assert_eq!(fs::read_to_string(&new).unwrap(), "cert");
+        assert_eq!(entries(dir.path()), ["a.json", "nested"]);
+        assert_eq!(entries(&dir.path().join("nested")), ["b.crt"]);
+    }
+
+    #[test]
+    fn failing_to_stage_changes_nothing() {
+        let dir = dir();
+        let existing = dir.path().join("a.json");
+        fs::write(&existing, "{}").unwrap();
+        // A file where the second write needs a directory
+        fs::write(dir.path().join("blocked"), "").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&existing, r#"{"a":1}"#, "first").unwrap();
+        let error = tx
+            .stage(&dir.path().join("blocked").join("b.json"), "{}", "second")
+            .unwrap_err();
+        drop(tx);
+
+        assert!(error.to_string().starts_with("second:"), "{}", error);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "{}");
+        assert_eq!(entries(dir.path()), ["a.json", "blocked"]);
+    }
+
+    #[test]
+    fn invalid_settings_fail_validation() {
+        let dir = dir();
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, "{}").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&settings, "{ oops", "settings").unwrap();
+        let error = tx.commit().unwrap_err();
+
+        assert!(error.to_string().contains("not valid JSON"), "{}", error);
+        assert_eq!(fs::read_to_string(&settings).unwrap(), "{}");
+        assert_eq!(entries(dir.path()), ["settings.json"]);
+    }
+
+    #[test]
+    fn failed_rename_restores_earlier_files() {
+        let dir = dir();
+        let first = dir.path().join("a.json");
+        let new = dir.path().join("new").join("b.crt");
+        let blocked = dir.path().join("c.json");
+        fs::write(&first, "{}").unwrap();
+        // A directory in the way of the last file can't be replaced
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), "").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&first, r#"{"a":1}"#, "first").unwrap();
+        tx.stage(&new, "cert", "second").unwrap();
+        tx.stage(&blocked, "{}", "third").unwrap();
+        let error = tx.commit().unwrap_err();
+
+        assert!(error.to_string().starts_with("third:"), "{}", error);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "{}");
+        assert!(!new.exists());
+        assert!(blocked.join("keep").exists());
+        assert_eq!(entries(dir.path()), ["a.json", "c.json"]);
+    }
+
+    #[test]
+    fn failed_rename_removes_the_files_staged_after_it() {
+        let dir = dir();
+        let first = dir.path().join("a.json");
+        let blocked = dir.path().join("b.json");
+        let last = dir.path().join("c.json");
+        fs::write(&first, "{}").unwrap();
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), "").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&first, r#"{"a":1}"#, "first").unwrap();
+        tx.stage(&blocked, "{}", "second").unwrap();
+        tx.stage(&last, "{}", "third").unwrap();
+        let error = tx.commit().unwrap_err();
+
+        assert!(error.to_string().starts_with("second:"), "{}", error);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "{}");
+        assert!(!last.exists());
+        assert_eq!(entries(dir.path()), ["a.json", "b.json"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_replace_files_and_are_removed_on_rollback() {
+        let dir = dir();
+        let target = dir.path().join("template.md");
+        let existing = dir.path().join("a.md");
+        let new = dir.path().join("b.md");
+        fs::write(&target, "team").unwrap();
+        fs::write(&existing, "old").unwrap();
+
+        let mut tx = Transaction::new();
+        assert_eq!(
+            tx.stage_link(&existing, &target, "first").unwrap(),
+            LinkKind::Symlink
+        );
+        tx.stage_link(&new, &target, "second").unwrap();
+        tx.commit().unwrap();
+        assert_eq!(fs::read_link(&existing).unwrap(), target);
+        assert_eq!(fs::read_to_string(&new).unwrap(), "team");
+        drop(tx);
+
+        assert!(!existing.is_symlink());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!new.exists() && !new.is_symlink());
+        assert_eq!(entries(dir.path()), ["a.md", "template.md"]);
+    }
+
+    #[test]
+    fn a_later_failure_rolls_back_committed_files() {
+        let dir = dir();
+        let existing = dir.path().join("a.json");
+        let new = dir.path().join("b.crt");
+        fs::write(&existing, "{}").unwrap();
+
+        let mut tx = Transaction::new();
+        tx.stage(&existing, r#"{"a":1}"#, "first").unwrap();
+        tx.stage(&new, "cert", "second").unwrap();
+        tx.commit().unwrap();
+        assert!(new.exists());
+        // A step after the commit failed, so the transaction is never finished
+        drop(tx);
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "{}");
+        assert!(!new.exists());
+        assert_eq!(entries(dir.path()), ["a.json"]);
+    }
+}
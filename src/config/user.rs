@@ -0,0 +1,431 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cli::Cli;
+use crate::download::Channel;
+use crate::platform::PathOverrides;
+
+/// Defaults read from `config.toml` in the platform config directory
+/// (`~/.config/code-assist` on Linux, `~/Library/Application Support/code-assist`
+/// on macOS, `%APPDATA%\code-assist` on Windows). Every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserConfig {
+    pub local_dir: Option<PathBuf>,
+    pub download_base_url: Option<OneOrMany>,
+    pub proxy: Option<String>,
+    pub default_yes: Option<bool>,
+    pub no_color: Option<bool>,
+    /// Where `self-update` looks for new builds of code-assist
+    pub self_update_url: Option<String>,
+
+    /// Settings for the download client (`[download]`)
+    pub download: DownloadConfig,
+
+    /// Settings file backups (`[backups]`)
+    pub backups: BackupsConfig,
+
+    /// Minimum versions of prerequisites (`[prerequisites]`)
+    pub prerequisites: PrerequisitesConfig,
+
+    /// The log file every run appends to (`[logs]`)
+    pub logs: LogsConfig,
+
+    /// Directories to use in place of the platform's (`[paths]`)
+    pub paths: PathsConfig,
+
+    /// Per-tool overrides, keyed by tool name (`[tools.claude-code]`)
+    pub tools: BTreeMap<String, ToolConfig>,
+}
+
+/// A single value or a list of them, such as one download URL or mirrors
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// The `[download]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownloadConfig {
+    /// Bearer token for mirrors that require one
+    pub token: Option<Secret>,
+    /// Minisign public key release manifests must be signed with
+    pub manifest_key: Option<String>,
+}
+
+/// The `[backups]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupsConfig {
+    /// How many settings backups to keep; 0 turns them off
+    pub keep: Option<usize>,
+}
+
+/// The `[prerequisites]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrerequisitesConfig {
+    /// Oldest Node.js that npm-installed tools accept
+    pub node: Option<String>,
+}
+
+/// The `[logs]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Total size of the log files in megabytes; 0 turns logging off
+    pub max_size_mb: Option<u64>,
+}
+
+/// The `[paths]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    /// Claude Code's settings directory, `~/.claude` by default
+    pub claude_dir: Option<PathBuf>,
+    /// Where certificates are copied
+    pub certs_dir: Option<PathBuf>,
+    /// The VS Code `User` directory holding `settings.json`
+    pub vscode_user_dir: Option<PathBuf>,
+}
+
+/// A credential, kept out of `Debug` output so it can't end up in logs
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// Overrides that apply to a single tool
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolConfig {
+    /// Version to install when `--version` isn't given
+    pub version: Option<String>,
+
+    /// Local artifacts directory for this tool only
+    pub local_dir: Option<PathBuf>,
+
+    /// Release channel for new installs when `--channel` isn't given
+    pub channel: Option<Channel>,
+
+    /// Tools to install before this one
+    pub dependencies: Vec<String>,
+}
+
+impl UserConfig {
+    /// Location of the config file, if the platform has a config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("code-assist").join("config.toml"))
+    }
+
+    /// Load the config file, or defaults if there isn't one
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env(&'static str),
+    File,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Flag => write!(f, "flag"),
+            Source::Env(name) => write!(f, "env {}", name),
+            Source::File => write!(f, "config file"),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A setting's effective value and where it came from
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+/// Command-line flags, environment variables, and the config file merged
+/// in that order of precedence
+#[derive(Debug)]
+pub struct Settings {
+    pub local_dir: Setting<Option<PathBuf>>,
+    pub offline: Setting<bool>,
+    /// Mirrors in the order to try them; empty for the default bucket
+    pub download_base_url: Setting<Vec<String>>,
+    pub proxy: Setting<Option<String>>,
+    /// Bearer token sent to the mirrors
+    pub auth_token: Setting<Option<Secret>>,
+    pub manifest_key: Setting<Option<String>>,
+    /// Connect and read timeout in seconds; `None` for the defaults
+    pub timeout: Setting<Option<u64>>,
+    pub retries: Setting<u32>,
+    pub retry_delay: Setting<Duration>,
+    /// Settings backups to keep
+    pub keep_backups: Setting<usize>,
+    /// Oldest Node.js that npm-installed tools accept
+    pub node_version: Setting<String>,
+    /// Total size of the log files in megabytes
+    pub log_max_size_mb: Setting<u64>,
+    pub yes: Setting<bool>,
+    /// Fail instead of prompting
+    pub non_interactive: Setting<bool>,
+    pub no_color: Setting<bool>,
+    /// Where `self-update` looks for new builds of code-assist
+    pub self_update_url: Setting<String>,
+    pub claude_dir: Setting<Option<PathBuf>>,
+    pub certs_dir: Setting<Option<PathBuf>>,
+    pub vscode_user_dir: Setting<Option<PathBuf>>,
+    pub tools: BTreeMap<String, ToolConfig>,
+}
+
+impl Settings {
+    pub fn resolve(cli: &Cli, matches: &ArgMatches, file: UserConfig) -> Self {
+        // clap folds `env = ...` values into the argument, so ask it which
+        // one actually supplied the value
+        let arg_source = |id: &str, env: &'static str| match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => Some(Source::Flag),
+            Some(ValueSource::EnvVariable) => Some(Source::Env(env)),
+            _ => None,
+        };
+
+        let local_dir = match arg_source("local_dir", "CODE_ASSIST_LOCAL_DIR") {
+            Some(source) => Setting::new(cli.local_dir.clone(), source),
+            None => from_file(file.local_dir),
+        };
+
+        // A directory given on the command line or in the environment wins
+        // over the config file, including its per-tool directories
+        let mut tools = file.tools;
+        if matches!(local_dir.source, Source::Flag | Source::Env(_)) {
+            for tool in tools.values_mut() {
+                tool.local_dir = None;
+            }
+        }
+
+        let offline = match arg_source("offline", "CODE_ASSIST_OFFLINE") {
+            Some(source) => Setting::new(cli.offline, source),
+            None => Setting::new(false, Source::Default),
+        };
+
+        let download_base_url = match arg_source("download_url", "CODE_ASSIST_DOWNLOAD_URL") {
+            Some(source) => Setting::new(cli.download_url.clone(), source),
+            None => match file.download_base_url {
+                Some(urls) => Setting::new(urls.into_vec(), Source::File),
+                None => Setting::new(Vec::new(), Source::Default),
+            },
+        };
+
+        let proxy = if let Some(proxy) = &cli.proxy {
+            Setting::new(Some(proxy.clone()), Source::Flag)
+        } else if let Some((name, proxy)) = proxy_from_env() {
+            Setting::new(Some(proxy), Source::Env(name))
+        } else {
+            from_file(file.proxy)
+        };
+
+        let auth_token = match arg_source("auth_token", "CODE_ASSIST_DOWNLOAD_TOKEN") {
+            Some(source) => Setting::new(cli.auth_token.clone().map(Secret::new), source),
+            None => from_file(file.download.token),
+        };
+
+        let manifest_key = match arg_source("manifest_key", "CODE_ASSIST_MANIFEST_KEY") {
+            Some(source) => Setting::new(cli.manifest_key.clone(), source),
+            None => from_file(file.download.manifest_key),
+        };
+
+        let timeout = Setting::new(
+            cli.timeout,
+            arg_source("timeout", "CODE_ASSIST_TIMEOUT").unwrap_or(Source::Default),
+        );
+
+        let retries = Setting::new(
+            cli.retries,
+            arg_source("retries", "CODE_ASSIST_RETRIES").unwrap_or(Source::Default),
+        );
+        let retry_delay = Setting::new(
+            cli.retry_delay,
+            arg_source("retry_delay", "CODE_ASSIST_RETRY_DELAY").unwrap_or(Source::Default),
+        );
+
+        let keep_backups = match file.backups.keep {
+            Some(keep) => Setting::new(keep, Source::File),
+            None => Setting::new(super::backup::DEFAULT_KEEP, Source::Default),
+        };
+
+        let node_version = match file.prerequisites.node {
+            Some(version) => Setting::new(version, Source::File),
+            None => Setting::new(
+                crate::prerequisites::NODE_MIN_VERSION.to_string(),
+                Source::Default,
+            ),
+        };
+
+        let log_max_size_mb = match file.logs.max_size_mb {
+            Some(size) => Setting::new(size, Source::File),
+            None => Setting::new(crate::logs::DEFAULT_MAX_SIZE_MB, Source::Default),
+        };
+
+        let yes = if cli.yes {
+            Setting::new(true, Source::Flag)
+        } else if let Some(yes) = file.default_yes {
+            Setting::new(yes, Source::File)
+        } else {
+            Setting::new(false, Source::Default)
+        };
+
+        let non_interactive = match arg_source("non_interactive", "CODE_ASSIST_NON_INTERACTIVE") {
+            Some(source) => Setting::new(cli.non_interactive, source),
+            None => Setting::new(!std::io::stdin().is_terminal(), Source::Default),
+        };
+
+        let no_color = if cli.no_color {
+            Setting::new(true, Source::Flag)
+        } else if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            Setting::new(true, Source::Env("NO_COLOR"))
+        } else if let Some(no_color) = file.no_color {
+            Setting::new(no_color, Source::File)
+        } else {
+            Setting::new(false, Source::Default)
+        };
+
+        let self_update_url = match std::env::var("CODE_ASSIST_SELF_UPDATE_URL") {
+            Ok(url) if !url.is_empty() => {
+                Setting::new(url, Source::Env("CODE_ASSIST_SELF_UPDATE_URL"))
+            }
+            _ => match file.self_update_url {
+                Some(url) => Setting::new(url, Source::File),
+                None => Setting::new(crate::self_update::DEFAULT_URL.to_string(), Source::Default),
+            },
+        };
+
+        let claude_dir = path_setting("CODE_ASSIST_CLAUDE_DIR", file.paths.claude_dir);
+        let certs_dir = path_setting("CODE_ASSIST_CERTS_DIR", file.paths.certs_dir);
+        let vscode_user_dir =
+            path_setting("CODE_ASSIST_VSCODE_USER_DIR", file.paths.vscode_user_dir);
+
+        Self {
+            local_dir,
+            offline,
+            download_base_url,
+            proxy,
+            auth_token,
+            manifest_key,
+            timeout,
+            retries,
+            retry_delay,
+            keep_backups,
+            node_version,
+            log_max_size_mb,
+            yes,
+            non_interactive,
+            no_color,
+            self_update_url,
+            claude_dir,
+            certs_dir,
+            vscode_user_dir,
+            tools,
+        }
+    }
+
+    /// The directories to use in place of the platform's
+    pub fn path_overrides(&self) -> PathOverrides {
+        PathOverrides {
+            claude_dir: self.claude_dir.value.clone(),
+            certs_dir: self.certs_dir.value.clone(),
+            vscode_user_dir: self.vscode_user_dir.value.clone(),
+        }
+    }
+}
+
+/// Standard proxy variables, most specific first. Every download is over
+/// HTTPS, so HTTP_PROXY only counts when it's the only one set.
+const PROXY_ENV_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+];
+
+fn proxy_from_env() -> Option<(&'static str, String)> {
+    PROXY_ENV_VARS.iter().find_map(|&name| {
+        std::env::var(name)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| (name, value.trim().to_string()))
+    })
+}
+
+/// A directory from `env`, else the config file
+fn path_setting(env: &'static str, file: Option<PathBuf>) -> Setting<Option<PathBuf>> {
+    match std::env::var_os(env).filter(|dir| !dir.is_empty()) {
+        Some(dir) => Setting::new(Some(PathBuf::from(dir)), Source::Env(env)),
+        None => from_file(file),
+    }
+}
+
+fn from_file<T>(value: Option<T>) -> Setting<Option<T>> {
+    let source = if value.is_some() {
+        Source::File
+    } else {
+        Source::Default
+    };
+    Setting::new(value, source)
+}
@@ -0,0 +1,188 @@
+//! VS Code configuration beyond settings.json: keybindings, merged entry by
+//! entry, and snippet files, copied unless the user has changed them
+
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use std::path::Path;
+
+use super::files::{copy_template, template_files, Conflict};
+use super::{jsonc, plan_settings_write, preserve_comments, SettingsDeploy, Transaction};
+use crate::output::{self, reporter};
+use crate::state::State;
+
+/// Merge the keybindings template at `source` into `dest`, appending each
+/// entry whose `key` and `command` aren't already bound there. Entries an
+/// earlier deploy added that the template no longer has are removed.
+/// Changes are shown and confirmed the same way as for settings.
+pub(super) fn deploy_keybindings(
+    settings: &SettingsDeploy,
+    source: &Path,
+    dest: &Path,
+    name: &str,
+    label: &str,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let mut template = serde_json::Value::Array(
+        read_keybindings(source).context("Failed to parse keybindings template")?,
+    );
+    settings.placeholders.expand(&mut template, source);
+    let serde_json::Value::Array(template) = template else {
+        unreachable!("placeholders only change strings")
+    };
+    let created = !dest.exists();
+    let before = if created {
+        Vec::new()
+    } else {
+        read_keybindings(dest)?
+    };
+
+    let stale: Vec<serde_json::Value> = state
+        .keybindings
+        .iter()
+        .filter(|k| k.file == dest && !template.contains(&k.entry))
+        .map(|k| k.entry.clone())
+        .collect();
+    let mut after: Vec<serde_json::Value> = before
+        .iter()
+        .filter(|entry| !stale.contains(entry))
+        .cloned()
+        .collect();
+    let removed: Vec<&serde_json::Value> = before
+        .iter()
+        .filter(|entry| stale.contains(entry))
+        .collect();
+    let mut added = Vec::new();
+    for entry in template {
+        if !after.iter().any(|existing| same_binding(existing, &entry)) {
+            after.push(entry.clone());
+            added.push(entry);
+        }
+    }
+
+    if settings.dry_run {
+        settings.snapshot.save(dest, label, state, true)?;
+        plan_settings_write(source, dest);
+        print_keybinding_changes(&added, &removed);
+        return Ok(());
+    }
+
+    if added.is_empty() && removed.is_empty() && !created {
+        reporter().skip(format!("{} already up to date", name));
+        return Ok(());
+    }
+
+    if !settings.assume_yes {
+        reporter().info(format!("Changes to {}:", dest.display()));
+        print_keybinding_changes(&added, &removed);
+        if !output::confirm(&format!("This will update {}.", name))? {
+            reporter().skip(format!("Left {} unchanged", name));
+            return Ok(());
+        }
+    }
+
+    settings.snapshot.save(dest, label, state, false)?;
+    if created {
+        state.record_created_settings(dest);
+    }
+    state
+        .keybindings
+        .retain(|k| k.file != dest || !stale.contains(&k.entry));
+    for entry in &added {
+        state.record_keybinding(dest, entry);
+    }
+
+    preserve_comments(dest)?;
+    tx.stage(
+        dest,
+        serde_json::to_string_pretty(&serde_json::Value::Array(after))?,
+        name,
+    )?;
+    if created {
+        reporter().success(format!("Deployed {}", name));
+    } else {
+        reporter().success(format!("Merged {}", name));
+    }
+    reporter().detail(dest.display());
+
+    Ok(())
+}
+
+/// A keybindings file's entries
+pub(super) fn read_keybindings(path: &Path) -> Result<Vec<serde_json::Value>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match jsonc::parse(&content).with_context(|| format!("Invalid JSON in {}", path.display()))? {
+        serde_json::Value::Array(entries) => Ok(entries),
+        _ => Err(anyhow!("{} is not a list of keybindings", path.display())),
+    }
+}
+
+/// Whether two keybindings bind the same key to the same command
+pub(super) fn same_binding(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    let key = |entry: &serde_json::Value| {
+        entry
+            .get("key")
+            .and_then(|k| k.as_str())
+            .map(str::to_lowercase)
+    };
+    let command = |entry: &serde_json::Value| entry.get("command").cloned();
+    key(a) == key(b) && command(a) == command(b)
+}
+
+fn print_keybinding_changes(added: &[serde_json::Value], removed: &[&serde_json::Value]) {
+    let out = reporter();
+    let describe = |entry: &serde_json::Value| {
+        format!(
+            "{}: {}",
+            entry.get("key").and_then(|k| k.as_str()).unwrap_or("?"),
+            entry.get("command").and_then(|c| c.as_str()).unwrap_or("?")
+        )
+    };
+    for entry in added {
+        out.output(style(format!("    + {}", describe(entry))).green());
+    }
+    for entry in removed {
+        out.output(style(format!("    - {}", describe(entry))).red());
+    }
+    if added.is_empty() && removed.is_empty() {
+        out.output(style("    no changes").dim());
+    }
+}
+
+/// Copy the snippet files under `source` into `dest_dir`. A snippet that
+/// exists with other contents is only replaced if it still holds what an
+/// earlier deploy wrote, or with `force`, in which case it's backed up
+/// first.
+pub(super) fn deploy_snippets(
+    settings: &SettingsDeploy,
+    source: &Path,
+    dest_dir: &Path,
+    name: &str,
+    label: &str,
+    state: &mut State,
+    tx: &mut Transaction,
+) -> Result<()> {
+    let mut changed = false;
+
+    for relative in template_files(source)? {
+        changed |= copy_template(
+            settings,
+            &source.join(&relative),
+            &dest_dir.join(&relative),
+            &format!("{} {}", name, relative.display()),
+            label,
+            Conflict::Keep,
+            state,
+            tx,
+        )?;
+    }
+
+    if !changed && !settings.dry_run {
+        reporter().skip(format!("{} already up to date", name));
+    }
+    Ok(())
+}
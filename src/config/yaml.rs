@@ -0,0 +1,79 @@
+//! YAML settings files, such as Continue's `config.yaml`. They're read into
+//! the same values as JSON settings, so templates merge into them the same
+//! way, and written back as YAML.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+/// Whether `path` is a YAML file, going by its extension
+pub fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"))
+}
+
+/// Parse YAML into a JSON value. A file with nothing but whitespace and
+/// comments is an empty object, like an empty JSON settings file.
+pub fn parse(content: &str) -> Result<Value> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    match serde_yaml::from_str(content)? {
+        Value::Null => Ok(Value::Object(serde_json::Map::new())),
+        value => Ok(value),
+    }
+}
+
+/// `value` as YAML
+pub fn to_string(value: &Value) -> Result<String> {
+    serde_yaml::to_string(value).context("Failed to write YAML")
+}
+
+/// Whether `content` has comments that rewriting it would drop. A `#` inside
+/// a quoted string counts too, which only costs an unneeded backup.
+pub fn has_comments(content: &str) -> bool {
+    content.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with('#') || line.contains(" #")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::merge::{self, MergeOptions};
+    use serde_json::json;
+
+    #[test]
+    fn a_template_merges_into_existing_yaml() {
+        let existing = parse(
+            "name: My config\nmodels:\n  - name: Local\n    provider: ollama\nrequestOptions:\n  timeout: 30\n",
+        )
+        .unwrap();
+        let template = parse("requestOptions:\n  caBundlePath: /certs/ca-bundle.pem\n").unwrap();
+
+        let mut merged = existing.clone();
+        for (key, value) in template.as_object().unwrap().clone() {
+            let mut current = merged[&key].clone();
+            merge::merge_key(&mut current, &key, value, &MergeOptions::default());
+            merged[&key] = current;
+        }
+
+        assert_eq!(
+            merged["requestOptions"],
+            json!({ "timeout": 30, "caBundlePath": "/certs/ca-bundle.pem" })
+        );
+        assert_eq!(merged["models"], existing["models"]);
+        assert_eq!(parse(&to_string(&merged).unwrap()).unwrap(), merged);
+    }
+
+    #[test]
+    fn an_empty_file_is_an_empty_object() {
+        assert_eq!(parse("# nothing yet\n").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn finds_comments() {
+        assert!(has_comments("# Models\nmodels: []\n"));
+        assert!(has_comments("name: x # ours\n"));
+        assert!(!has_comments("name: x\n"));
+    }
+}
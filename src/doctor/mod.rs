@@ -0,0 +1,307 @@
+use console::style;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Source};
+use crate::download;
+use crate::output::{reporter, Symbol};
+use crate::platform::{Editor, Host, PlatformPaths};
+use crate::prerequisites::{self, Prerequisite};
+
+/// Outcome of a single diagnostic check
+#[derive(Serialize)]
+pub struct Diagnostic {
+    pub name: String,
+    pub passed: bool,
+    /// What was found when the check passed, or how to fix it when it failed
+    pub detail: String,
+}
+
+impl Diagnostic {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(name: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: hint.into(),
+        }
+    }
+
+    pub fn print(&self) {
+        if self.passed {
+            reporter().success(format!(
+                "{} {}",
+                self.name,
+                style(format!("({})", self.detail)).dim()
+            ));
+        } else {
+            reporter().error(format!(
+                "{}\n      {} {}",
+                self.name,
+                style(reporter().symbol(Symbol::Arrow)).yellow(),
+                self.detail
+            ));
+        }
+    }
+}
+
+/// Report the proxy downloads go through, and where it was configured
+pub fn proxy_diagnostic(proxy: Option<&str>, source: Source) -> Diagnostic {
+    let Some(proxy) = proxy else {
+        return Diagnostic::pass("Proxy", "none, connecting directly");
+    };
+
+    let mut detail = format!("{} from {}", download::proxy_url(proxy), source);
+    if let Some(no_proxy) = download::no_proxy() {
+        detail.push_str(&format!(", bypassed for {}", no_proxy));
+    }
+    Diagnostic::pass("Proxy", detail)
+}
+
+/// Report whether a download mirror can be reached, and if not, what went
+/// wrong, through which proxy, and what to try
+pub fn reachability_diagnostic(result: &download::Reachability) -> Diagnostic {
+    let name = "Download server";
+    let Some(problem) = result.problem else {
+        return Diagnostic::pass(name, format!("{} ({})", result.url, result.detail));
+    };
+    let via = match &result.proxy {
+        Some(proxy) => format!("through proxy {}", proxy),
+        None => "connecting directly".to_string(),
+    };
+    Diagnostic::fail(
+        name,
+        format!(
+            "{}: {}, {} ({}); {}",
+            result.url,
+            problem,
+            via,
+            result.detail,
+            problem.hint()
+        ),
+    )
+}
+
+/// Checks that don't belong to any particular tool, with `required` the
+/// prerequisites of every registered tool, run through `host`
+pub fn check_environment(
+    paths: &PlatformPaths,
+    host: &Host,
+    required: &[Prerequisite],
+) -> Vec<Diagnostic> {
+    let statuses = prerequisites::evaluate(&*host.runner, required, &paths.home_dir);
+    let mut diagnostics: Vec<Diagnostic> = statuses
+        .into_iter()
+        .map(|status| {
+            if status.installed {
+                let detail = match status.warning {
+                    Some(warning) => format!("{}; {}", status.details, warning),
+                    None => status.details,
+                };
+                Diagnostic::pass(status.name, detail)
+            } else if let Some(problem) = status.problem {
+                Diagnostic::fail(status.name, problem)
+            } else {
+                let detail = format!(
+                    "Install {} via Software Center (Windows) or Self-Service (macOS)",
+                    status.name
+                );
+                Diagnostic::fail(status.name, detail)
+            }
+        })
+        .collect();
+
+    // Node.js is worth knowing about even when no tool requires it
+    if !required
+        .iter()
+        .any(|prerequisite| matches!(prerequisite, Prerequisite::Node { .. }))
+    {
+        let node = prerequisites::node_status(&*host.runner, None);
+        let detail = if node.installed {
+            node.details
+        } else {
+            "not installed; only npm-installed tools need it".to_string()
+        };
+        diagnostics.push(Diagnostic::pass(node.name, detail));
+    }
+
+    diagnostics.push(check_node_extra_ca_certs(paths));
+    diagnostics.extend(check_certificates(paths));
+    for editor in Editor::ALL {
+        // VS Code's settings are always checked, the variants' once they exist
+        let settings = paths.vscode_settings_dir(editor).join("settings.json");
+        if editor == Editor::Stable || settings.exists() {
+            diagnostics.push(check_vscode_settings(editor, &settings));
+        }
+    }
+
+    diagnostics
+}
+
+/// Whether `dir` is on the PATH of the current process
+pub fn is_on_path(dir: &Path) -> bool {
+    let Some(path_var) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    std::env::split_paths(&path_var).any(|entry| paths_equal(&entry, dir))
+}
+
+/// Whether `dir` is one of the PATH entries `path`
+pub fn is_in(path: &[PathBuf], dir: &Path) -> bool {
+    path.iter().any(|entry| paths_equal(entry, dir))
+}
+
+fn paths_equal(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn check_node_extra_ca_certs(paths: &PlatformPaths) -> Diagnostic {
+    let name = "NODE_EXTRA_CA_CERTS";
+
+    let Some(value) = std::env::var_os(name) else {
+        let has_certs = std::fs::read_dir(&paths.certs_dir)
+            .map(|entries| entries.flatten().next().is_some())
+            .unwrap_or(false);
+
+        return if has_certs {
+            Diagnostic::fail(
+                name,
+                format!(
+                    "Not set in this shell although certificates exist in {}; \
+                     run 'code-assist configure' and open a new terminal",
+                    paths.certs_dir.display()
+                ),
+            )
+        } else {
+            Diagnostic::pass(name, "not configured, no certificates deployed")
+        };
+    };
+
+    let cert_path = Path::new(&value);
+    match std::fs::read(cert_path) {
+        Ok(bytes) if looks_like_certificate(&bytes) => {
+            Diagnostic::pass(name, cert_path.display().to_string())
+        }
+        Ok(_) => Diagnostic::fail(
+            name,
+            format!(
+                "{} is not a PEM or DER certificate; run 'code-assist configure' to redeploy it",
+                cert_path.display()
+            ),
+        ),
+        Err(_) => Diagnostic::fail(
+            name,
+            format!(
+                "Points at {} which does not exist; run 'code-assist configure' to redeploy it",
+                cert_path.display()
+            ),
+        ),
+    }
+}
+
+/// Each deployed certificate must parse, act as a root, and not be expired
+/// or about to expire
+fn check_certificates(paths: &PlatformPaths) -> Vec<Diagnostic> {
+    let Ok(entries) = std::fs::read_dir(&paths.certs_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| config::is_certificate_file(path))
+        .collect();
+    files.sort();
+
+    let mut diagnostics = Vec::new();
+    for file in files {
+        let name = format!(
+            "Certificate {}",
+            file.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let certs = match std::fs::read(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| config::read_certificates(&content))
+        {
+            Ok(certs) => certs,
+            Err(e) => {
+                diagnostics.push(Diagnostic::fail(
+                    name,
+                    format!(
+                        "{} can't be read as a certificate ({:#}); fix it in the local \
+                         directory and run 'code-assist configure'",
+                        file.display(),
+                        e
+                    ),
+                ));
+                continue;
+            }
+        };
+
+        for cert in certs {
+            diagnostics.push(match cert.problem() {
+                None => Diagnostic::pass(&name, cert.describe()),
+                Some(problem) if cert.expiry != config::Expiry::Valid => Diagnostic::fail(
+                    &name,
+                    format!(
+                        "{} {}; replace it in the local directory and run \
+                         'code-assist configure'",
+                        cert.subject, problem
+                    ),
+                ),
+                Some(problem) => Diagnostic::fail(&name, format!("{} {}", cert.subject, problem)),
+            });
+        }
+    }
+    diagnostics
+}
+
+fn check_vscode_settings(editor: Editor, settings: &Path) -> Diagnostic {
+    let name = format!("{} settings.json", editor);
+
+    let content = match std::fs::read_to_string(settings) {
+        Ok(content) => content,
+        Err(_) => return Diagnostic::pass(name, "not present"),
+    };
+
+    match config::jsonc::parse(&content) {
+        Ok(_) => Diagnostic::pass(name, settings.display().to_string()),
+        Err(e) => Diagnostic::fail(
+            name,
+            format!(
+                "{} is not valid JSON ({}); fix or remove it, then run 'code-assist configure'",
+                settings.display(),
+                e
+            ),
+        ),
+    }
+}
+
+/// Cheap structural check for a PEM bundle or a single DER certificate
+fn looks_like_certificate(bytes: &[u8]) -> bool {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if let (Some(begin), Some(end)) = (
+            text.find("-----BEGIN CERTIFICATE-----"),
+            text.find("-----END CERTIFICATE-----"),
+        ) {
+            let body = &text[begin + "-----BEGIN CERTIFICATE-----".len()..end];
+            return !body.trim().is_empty()
+                && body
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+/=\r\n ".contains(c));
+        }
+    }
+
+    // DER certificates start with an ASN.1 SEQUENCE tag
+    bytes.len() > 4 && bytes[0] == 0x30
+}
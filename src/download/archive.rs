@@ -0,0 +1,152 @@
+//! Extracting the binary from a platform archive with the system `tar`,
+//! which reads tar.gz everywhere and zip on Windows and macOS (bsdtar)
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use super::ArchiveKind;
+use crate::cache;
+use crate::process;
+
+/// Extract `binary_name` from a verified archive to `output_path`. The
+/// archive is unpacked into a staging directory next to `output_path`,
+/// which is removed afterwards whether or not extraction succeeds.
+pub fn extract_binary(
+    archive: &Path,
+    kind: ArchiveKind,
+    binary_name: &str,
+    output_path: &Path,
+) -> Result<()> {
+    let entries = list(archive)?;
+    if let Some(unsafe_entry) = entries.iter().find(|entry| !is_safe_entry(entry)) {
+        bail!(
+            "Refusing to extract {}: entry '{}' points outside the archive",
+            archive.display(),
+            unsafe_entry
+        );
+    }
+    if has_links(archive)? {
+        bail!(
+            "Refusing to extract {}: it contains links",
+            archive.display()
+        );
+    }
+
+    let staging = Staging(cache::temp_path(output_path));
+    std::fs::create_dir_all(&staging.0)
+        .with_context(|| format!("Failed to create {}", staging.0.display()))?;
+    run_tar(
+        Command::new("tar")
+            .arg("-xf")
+            .arg(archive)
+            .arg("-C")
+            .arg(&staging.0),
+    )
+    .with_context(|| format!("Failed to extract {} archive", kind.extension()))?;
+
+    let binary = find_file(&staging.0, binary_name)?
+        .ok_or_else(|| anyhow!("{} does not contain {}", archive.display(), binary_name))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    super::persist(&binary, output_path).context("Failed to move extracted binary into place")
+}
+
+/// Removes the staging directory when dropped
+struct Staging(PathBuf);
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::debug!("could not remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+fn run_tar(command: &mut Command) -> Result<String> {
+    let output = process::output(command).context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Entry names in the archive
+fn list(archive: &Path) -> Result<Vec<String>> {
+    let listing = run_tar(Command::new("tar").arg("-tf").arg(archive))
+        .with_context(|| format!("Failed to read {}", archive.display()))?;
+    Ok(listing.lines().map(str::to_string).collect())
+}
+
+/// Whether the archive has symlinks or hard links, which could redirect
+/// later entries outside the staging directory. The long listing starts each
+/// line with the entry's mode, as `ls -l` does, in both GNU tar and bsdtar.
+fn has_links(archive: &Path) -> Result<bool> {
+    let listing = run_tar(Command::new("tar").arg("-tvf").arg(archive))
+        .with_context(|| format!("Failed to read {}", archive.display()))?;
+    Ok(listing
+        .lines()
+        .any(|line| line.starts_with('l') || line.starts_with('h')))
+}
+
+/// Whether an entry stays inside the directory it's extracted to: relative,
+/// and never climbing out with `..`
+fn is_safe_entry(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+    let path = Path::new(&name);
+    let has_drive = name.as_bytes().get(1) == Some(&b':');
+    !name.starts_with('/')
+        && !has_drive
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// The first regular file named `name` under `dir`, checking each
+/// directory before its subdirectories
+fn find_file(dir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let mut subdirs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_file() && entry.file_name() == name {
+            return Ok(Some(entry.path()));
+        }
+        if file_type.is_dir() {
+            subdirs.push(entry.path());
+        }
+    }
+    subdirs.sort();
+    for subdir in subdirs {
+        if let Some(found) = find_file(&subdir, name)? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_entries_that_escape() {
+        for name in ["claude", "bin/claude", "./bin/claude", "package/"] {
+            assert!(is_safe_entry(name), "{}", name);
+        }
+        for name in [
+            "../claude",
+            "bin/../../claude",
+            "/usr/bin/claude",
+            "..\\claude",
+            "C:\\claude",
+        ] {
+            assert!(!is_safe_entry(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A release's `manifest.json`: what was built for which platform. Fields
+/// this version doesn't know about are ignored, so newer manifests still load.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    /// Version the manifest describes, if it says
+    #[serde(default)]
+    pub version: Option<String>,
+    pub platforms: BTreeMap<String, PlatformEntry>,
+}
+
+/// The build of a release for one platform
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformEntry {
+    /// SHA-256 of the binary (or of the archive, if there is one), as
+    /// lowercase hex
+    pub checksum: String,
+    /// Size of the binary or archive in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// File name of the binary; the platform's usual name if unset
+    #[serde(default, alias = "binary")]
+    pub binary_name: Option<String>,
+    /// Archive the binary ships in, along with supporting files; a raw
+    /// binary if unset
+    #[serde(default)]
+    pub archive: Option<ArchiveKind>,
+    /// Detached signature of the binary; carried along but not verified yet
+    #[serde(default)]
+    #[allow(dead_code)]
+    pub signature: Option<String>,
+    /// Where to download the binary from instead of the mirrors
+    #[serde(default)]
+    pub download_url: Option<String>,
+}
+
+/// Format of a platform archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ArchiveKind {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => "zip",
+            ArchiveKind::TarGz => "tar.gz",
+        }
+    }
+
+    /// Name of the archive that holds `binary_name`, e.g. `claude.tar.gz`
+    pub fn file_name(self, binary_name: &str) -> String {
+        let stem = binary_name.strip_suffix(".exe").unwrap_or(binary_name);
+        format!("{}.{}", stem, self.extension())
+    }
+}
+
+impl Manifest {
+    /// Parse the manifest for `version`, checking what the installer relies on
+    pub fn parse(content: &str, version: &str) -> Result<Self> {
+        let manifest: Manifest = serde_json::from_str(content)
+            .with_context(|| format!("Invalid manifest for {}", version))?;
+
+        if let Some(described) = manifest.version.as_deref() {
+            if described != version {
+                return Err(anyhow!(
+                    "Invalid manifest for {}: it describes version {}",
+                    version,
+                    described
+                ));
+            }
+        }
+
+        for (platform, entry) in &manifest.platforms {
+            let checksum = &entry.checksum;
+            if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(anyhow!(
+                    "Invalid manifest for {}: checksum for {} is not a SHA-256 hex digest",
+                    version,
+                    platform
+                ));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// The entry for `platform`, or an error listing the platforms there are
+    pub fn platform(&self, version: &str, platform: &str) -> Result<&PlatformEntry> {
+        self.platforms.get(platform).ok_or_else(|| {
+            let available: Vec<&str> = self.platforms.keys().map(String::as_str).collect();
+            anyhow!(
+                "manifest for {} has no entry for {} (available: {})",
+                version,
+                platform,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })
+    }
+}
+
+impl PlatformEntry {
+    /// File name of the binary, falling back to `default`
+    pub fn binary_name<'a>(&'a self, default: &'a str) -> &'a str {
+        self.binary_name.as_deref().unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKSUM: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn parses_current_manifest() {
+        let content = format!(
+            r#"{{
+                "version": "1.2.3",
+                "buildDate": "2025-01-01T00:00:00Z",
+                "platforms": {{
+                    "darwin-arm64": {{ "checksum": "{0}", "size": 1024 }},
+                    "win32-x64": {{ "checksum": "{0}", "size": 2048 }}
+                }}
+            }}"#,
+            CHECKSUM
+        );
+
+        let manifest = Manifest::parse(&content, "1.2.3").unwrap();
+        let entry = manifest.platform("1.2.3", "darwin-arm64").unwrap();
+        assert_eq!(manifest.version.as_deref(), Some("1.2.3"));
+        assert_eq!(entry.checksum, CHECKSUM);
+        assert_eq!(entry.size, Some(1024));
+        assert_eq!(entry.binary_name("claude"), "claude");
+        assert!(entry.signature.is_none());
+        assert!(entry.download_url.is_none());
+        assert!(entry.archive.is_none());
+    }
+
+    #[test]
+    fn parses_archive_entries() {
+        let content = format!(
+            r#"{{
+                "platforms": {{
+                    "linux-x64": {{ "checksum": "{0}", "archive": "tar.gz", "binary": "claude" }},
+                    "win32-x64": {{ "checksum": "{0}", "archive": "zip" }}
+                }}
+            }}"#,
+            CHECKSUM
+        );
+
+        let manifest = Manifest::parse(&content, "1.2.3").unwrap();
+        let linux = manifest.platform("1.2.3", "linux-x64").unwrap();
+        assert_eq!(linux.archive, Some(ArchiveKind::TarGz));
+        assert_eq!(linux.binary_name("default"), "claude");
+        let windows = manifest.platform("1.2.3", "win32-x64").unwrap();
+        assert_eq!(windows.archive, Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::Zip.file_name("claude.exe"), "claude.zip");
+
+        let unknown = content.replace("\"zip\"", "\"tar.xz\"");
+        assert!(Manifest::parse(&unknown, "1.2.3").is_err());
+    }
+
+    #[test]
+    fn tolerates_fields_from_future_versions() {
+        let content = format!(
+            r#"{{
+                "schema": 7,
+                "channels": ["stable"],
+                "platforms": {{
+                    "linux-x64": {{
+                        "checksum": "{}",
+                        "binaryName": "claude-linux",
+                        "signature": "c2lnbmF0dXJl",
+                        "downloadUrl": "https://cdn.example.com/claude-linux",
+                        "compression": {{ "zstd": true }}
+                    }}
+                }}
+            }}"#,
+            CHECKSUM
+        );
+
+        let manifest = Manifest::parse(&content, "9.0.0").unwrap();
+        let entry = manifest.platform("9.0.0", "linux-x64").unwrap();
+        assert_eq!(entry.binary_name("claude"), "claude-linux");
+        assert_eq!(entry.signature.as_deref(), Some("c2lnbmF0dXJl"));
+        assert_eq!(
+            entry.download_url.as_deref(),
+            Some("https://cdn.example.com/claude-linux")
+        );
+    }
+
+    #[test]
+    fn missing_platform_lists_available_ones() {
+        let content = format!(
+            r#"{{ "platforms": {{ "win32-x64": {{ "checksum": "{0}" }}, "darwin-x64": {{ "checksum": "{0}" }} }} }}"#,
+            CHECKSUM
+        );
+
+        let manifest = Manifest::parse(&content, "1.2.3").unwrap();
+        let error = manifest.platform("1.2.3", "darwin-arm64").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "manifest for 1.2.3 has no entry for darwin-arm64 (available: darwin-x64, win32-x64)"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        let cases = [
+            ("not json", "Invalid manifest for 1.2.3"),
+            (r#"{ "version": "1.2.3" }"#, "missing field `platforms`"),
+            (
+                r#"{ "platforms": { "linux-x64": { "size": 1 } } }"#,
+                "missing field `checksum`",
+            ),
+            (
+                r#"{ "platforms": { "linux-x64": { "checksum": 42 } } }"#,
+                "invalid type",
+            ),
+            (
+                r#"{ "platforms": { "linux-x64": { "checksum": "abc" } } }"#,
+                "checksum for linux-x64 is not a SHA-256 hex digest",
+            ),
+            (
+                r#"{ "version": "1.2.4", "platforms": {} }"#,
+                "it describes version 1.2.4",
+            ),
+        ];
+
+        for (content, expected) in cases {
+            let error = format!("{:#}", Manifest::parse(content, "1.2.3").unwrap_err());
+            assert!(error.contains(expected), "{:?}: {}", content, error);
+        }
+    }
+}
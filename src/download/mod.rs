@@ -1,37 +1,629 @@
 use anyhow::{anyhow, Context, Result};
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, ProgressBar};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
 use sha2::{Digest, Sha256};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-const GCS_BUCKET: &str = "https://storage.googleapis.com/claude-code-dist-86c565f3-f756-42ad-8dfa-d59b1c096819/claude-code-releases";
+mod archive;
+mod manifest;
+mod reachability;
+mod signature;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+pub use manifest::{ArchiveKind, Manifest, PlatformEntry};
+pub use reachability::{check as check_reachability, Reachability};
+pub use signature::{PublicKey, SignatureError};
+
+use crate::cache;
+use crate::cancel;
+use crate::config::Secret;
+use crate::error::Failure;
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::platform;
+
+/// Default base URL for versions, manifests, and binaries
+pub const GCS_BUCKET: &str = "https://storage.googleapis.com/claude-code-dist-86c565f3-f756-42ad-8dfa-d59b1c096819/claude-code-releases";
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest wait between attempts, however many there are
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How failed manifest and binary downloads are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after failed attempt number `attempt`, plus up to half again at
+    /// random so installs started together behind the same proxy don't retry
+    /// in lockstep
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_RETRY_DELAY);
+        backoff + (backoff / 2).mul_f64(jitter())
+    }
+}
+
+/// A number in [0, 1), random enough to spread out retries
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Where and how remote artifacts are fetched, decided once at startup
+struct Remote {
+    /// Mirrors in the order they're tried, never empty
+    base_urls: Vec<String>,
+    client: reqwest::blocking::Client,
+    retry: RetryPolicy,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    /// Key manifests must be signed with; signatures aren't checked without
+    /// one
+    manifest_key: Option<PublicKey>,
+    allow_unsigned: bool,
+    /// `Authorization` header for the configured mirrors, marked sensitive
+    auth_header: Option<HeaderValue>,
+    /// Proxy requests go through, as a URL
+    proxy: Option<String>,
+}
+
+static REMOTE: OnceLock<Remote> = OnceLock::new();
+
+/// How to reach the remote, passed to `init`
+#[derive(Debug, Default)]
+pub struct ClientOptions {
+    /// Mirrors to try in order; `GCS_BUCKET` if empty. `file://` URLs
+    /// read from a directory, such as a mounted share.
+    pub base_urls: Vec<String>,
+    pub proxy: Option<String>,
+    pub retry: RetryPolicy,
+    /// Longest wait for a connection to open; `DEFAULT_CONNECT_TIMEOUT` if unset
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for a response or the next chunk of one;
+    /// `DEFAULT_READ_TIMEOUT` if unset
+    pub read_timeout: Option<Duration>,
+    /// Root certificates to trust on top of the built-in ones, PEM or DER
+    pub ca_certs: Vec<PathBuf>,
+    /// Minisign key manifests must be signed with. Without one, manifests
+    /// are trusted as served and only checksums are verified.
+    pub manifest_key: Option<String>,
+    /// Accept manifests whose signature is missing or invalid, with a warning
+    pub allow_unsigned: bool,
+    /// Bearer token sent to the mirrors in `base_urls`, but never to the
+    /// public bucket or a manifest's own download URLs
+    pub auth_token: Option<Secret>,
+}
+
+/// Set up the client every request goes through. Only the first call has
+/// an effect.
+pub fn init(options: ClientOptions) -> Result<()> {
+    let connect_timeout = options.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+    let read_timeout = options.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT);
+
+    // The blocking client applies `timeout` to each read of the body rather
+    // than the whole transfer, so large downloads aren't cut off
+    let mut builder = reqwest::blocking::Client::builder()
+        .user_agent(user_agent())
+        .connect_timeout(connect_timeout)
+        .timeout(read_timeout);
+    if let Some(proxy) = options.proxy.as_deref() {
+        let url = proxy_url(proxy);
+        let proxy = reqwest::Proxy::all(&url)
+            .with_context(|| format!("Invalid proxy URL '{}'", proxy))?
+            .no_proxy(reqwest::NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    for path in &options.ca_certs {
+        match load_certificates(path) {
+            Ok(certs) => {
+                tracing::debug!(
+                    "trusting {} certificate(s) from {}",
+                    certs.len(),
+                    path.display()
+                );
+                for cert in certs {
+                    builder = builder.add_root_certificate(cert);
+                }
+            }
+            Err(e) => reporter().notice(format!(
+                "Warning: Ignoring certificate {}: {:#}",
+                path.display(),
+                e
+            )),
+        }
+    }
+    let client = builder.build().context("Failed to create HTTP client")?;
+    let manifest_key = match options.manifest_key.as_deref() {
+        Some(key) => Some(PublicKey::parse(key).context("Invalid manifest key")?),
+        None => None,
+    };
+
+    let auth_header = match &options.auth_token {
+        Some(token) => {
+            // The parse error would echo the value, token and all
+            let bearer = format!("Bearer {}", token.expose().trim());
+            let mut value = HeaderValue::from_str(&bearer).map_err(|_| {
+                anyhow!("Invalid download token: it has characters an HTTP header can't carry")
+            })?;
+            value.set_sensitive(true);
+            Some(value)
+        }
+        None => None,
+    };
+
+    let mut base_urls: Vec<String> = options
+        .base_urls
+        .iter()
+        .map(|url| url.trim_end_matches('/').to_string())
+        .collect();
+    if base_urls.is_empty() {
+        base_urls.push(GCS_BUCKET.to_string());
+    }
+
+    let _ = REMOTE.set(Remote {
+        base_urls,
+        client,
+        retry: options.retry,
+        connect_timeout,
+        read_timeout,
+        manifest_key,
+        allow_unsigned: options.allow_unsigned,
+        auth_header,
+        proxy: options.proxy.as_deref().map(proxy_url),
+    });
+    Ok(())
+}
+
+/// `code-assist/<version> (<platform id>)`, so mirrors can tell clients apart
+fn user_agent() -> String {
+    format!(
+        "code-assist/{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        platform::get_platform_id()
+    )
+}
+
+/// Extra root certificates to trust: `explicit` ones first, then
+/// NODE_EXTRA_CA_CERTS, then the `.crt` files deployed to `certs_dir`. A
+/// corporate TLS-inspecting proxy's root is usually among the latter two.
+pub fn ca_cert_files(explicit: &[PathBuf], certs_dir: &Path) -> Vec<PathBuf> {
+    let mut files = explicit.to_vec();
+
+    if let Some(path) = std::env::var_os("NODE_EXTRA_CA_CERTS").filter(|v| !v.is_empty()) {
+        files.push(PathBuf::from(path));
+    }
+
+    if let Ok(entries) = std::fs::read_dir(certs_dir) {
+        let mut deployed: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "crt"))
+            .collect();
+        deployed.sort();
+        files.extend(deployed);
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|path| seen.insert(path.clone()));
+    files
+}
+
+/// Every certificate in a PEM bundle, or the single certificate in a DER file
+fn load_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let data = std::fs::read(path).context("Failed to read file")?;
+
+    let certs = if data.windows(11).any(|w| w == b"-----BEGIN ") {
+        reqwest::Certificate::from_pem_bundle(&data).context("Invalid PEM certificate")?
+    } else {
+        vec![reqwest::Certificate::from_der(&data).context("Invalid DER certificate")?]
+    };
+
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found"));
+    }
+
+    // Certificates are only parsed when a client is built, and one bad file
+    // shouldn't stop the real client from building, so try each on its own
+    for cert in &certs {
+        reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(cert.clone())
+            .build()
+            .context("Invalid certificate")?;
+    }
+    Ok(certs)
+}
+
+/// `proxy` with a scheme, since proxy variables are often set to a bare
+/// `host:port`
+pub fn proxy_url(proxy: &str) -> String {
+    if proxy.contains("://") {
+        proxy.to_string()
+    } else {
+        format!("http://{}", proxy)
+    }
+}
+
+/// Hosts that bypass the proxy, from NO_PROXY or no_proxy
+pub fn no_proxy() -> Option<String> {
+    ["NO_PROXY", "no_proxy"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// The remote settings, with defaults if `init` was never called
+fn remote() -> &'static Remote {
+    REMOTE.get_or_init(|| Remote {
+        base_urls: vec![GCS_BUCKET.to_string()],
+        client: reqwest::blocking::Client::new(),
+        retry: RetryPolicy::default(),
+        connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        read_timeout: DEFAULT_READ_TIMEOUT,
+        manifest_key: None,
+        allow_unsigned: false,
+        auth_header: None,
+        proxy: None,
+    })
+}
+
+/// Why a request failed, and so whether trying again could help
+enum FetchError {
+    /// Connection errors, 5xx responses, and transfers cut off midway
+    Transient(anyhow::Error),
+    /// Anything else, such as a 404 or a local write failure
+    Permanent(anyhow::Error),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_builder() {
+            FetchError::Permanent(e.into())
+        } else {
+            FetchError::Transient(e.into())
+        }
+    }
+}
+
+/// The mirrors downloads are tried from, in order
+pub fn base_urls() -> &'static [String] {
+    &remote().base_urls
+}
+
+/// Say which mirror served an artifact; only worth more than a verbose
+/// detail when there was a choice
+fn report_mirror(base_url: &str) {
+    if base_urls().len() > 1 {
+        reporter().info(format!("Served by {}", base_url));
+    } else {
+        reporter().detail(format!("Served by {}", base_url));
+    }
+}
+
+/// The local path a `file://` URL points at, or `None` for other URLs
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    if !url.starts_with("file:") {
+        return None;
+    }
+    reqwest::Url::parse(url).ok()?.to_file_path().ok()
+}
+
+/// Start a request for `url`, with the download token if it's on one of the
+/// configured mirrors
+fn request(method: reqwest::Method, url: &str) -> reqwest::blocking::RequestBuilder {
+    let remote = remote();
+    let request = remote.client.request(method, url);
+    match &remote.auth_header {
+        Some(header) if is_mirror_url(url) => request.header(AUTHORIZATION, header.clone()),
+        _ => request,
+    }
+}
+
+/// Whether `url` is under one of the mirrors the user configured
+fn is_mirror_url(url: &str) -> bool {
+    base_urls()
+        .iter()
+        .filter(|base_url| *base_url != GCS_BUCKET)
+        .any(|base_url| url.starts_with(&format!("{}/", base_url)))
+}
+
+/// GET `url`, classifying non-success statuses by whether a retry could help
+fn get(url: &str) -> Result<reqwest::blocking::Response, FetchError> {
+    check_status(request(reqwest::Method::GET, url).send()?)
+}
+
+/// Fetch a small text artifact over HTTP or from a `file://` mirror
+fn get_text(url: &str) -> Result<String, FetchError> {
+    match file_url_path(url) {
+        Some(path) => std::fs::read_to_string(&path).map_err(|e| {
+            FetchError::Permanent(anyhow!("Failed to read {}: {}", path.display(), e))
+        }),
+        None => Ok(get(url)?.text()?),
+    }
+}
+
+fn check_status(
+    response: reqwest::blocking::Response,
+) -> Result<reqwest::blocking::Response, FetchError> {
+    let status = response.status();
+    if matches!(
+        status,
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+    ) {
+        let url = response.url();
+        Err(FetchError::Permanent(
+            AuthRequired {
+                status,
+                host: url.host_str().unwrap_or_default().to_string(),
+                token_sent: remote().auth_header.is_some() && is_mirror_url(url.as_str()),
+            }
+            .into(),
+        ))
+    } else if status.is_server_error() {
+        Err(FetchError::Transient(anyhow!("HTTP error: {}", status)))
+    } else if !status.is_success() {
+        Err(FetchError::Permanent(anyhow!("HTTP error: {}", status)))
+    } else {
+        Ok(response)
+    }
+}
+
+/// A mirror turned a request away for lack of a valid download token
+#[derive(Debug)]
+struct AuthRequired {
+    status: reqwest::StatusCode,
+    host: String,
+    /// Whether the request carried a token, which was then rejected
+    token_sent: bool,
+}
+
+impl std::fmt::Display for AuthRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.token_sent {
+            write!(
+                f,
+                "{} rejected the download token ({}); check --auth-token, \
+                 CODE_ASSIST_DOWNLOAD_TOKEN, or `token` under [download] in the config file",
+                self.host, self.status
+            )
+        } else {
+            write!(
+                f,
+                "{} requires a download token ({}); pass --auth-token, set \
+                 CODE_ASSIST_DOWNLOAD_TOKEN, or set `token` under [download] in the config file",
+                self.host, self.status
+            )
+        }
+    }
+}
+
+impl std::error::Error for AuthRequired {}
+
+/// The token problem behind `error`, if that's what it is
+fn auth_required(error: &anyhow::Error) -> Option<&AuthRequired> {
+    error.chain().find_map(|cause| cause.downcast_ref())
+}
+
+/// Record the error from the latest mirror, unless an earlier mirror turned
+/// the token away, which is what the user needs to hear about
+fn note_error(slot: &mut Option<anyhow::Error>, error: anyhow::Error) {
+    if slot.as_ref().and_then(auth_required).is_none() {
+        *slot = Some(error);
+    }
+}
+
+/// The timeout `error` hit, if it is one
+fn timed_out_after(error: &anyhow::Error) -> Option<Duration> {
+    error.chain().find_map(|cause| {
+        // Body reads report reqwest errors wrapped in an io::Error
+        let reqwest_error = cause.downcast_ref::<reqwest::Error>().or_else(|| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .and_then(|e| e.get_ref())
+                .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+        })?;
+
+        if !reqwest_error.is_timeout() {
+            None
+        } else if reqwest_error.is_connect() {
+            Some(remote().connect_timeout)
+        } else {
+            Some(remote().read_timeout)
+        }
+    })
+}
+
+/// Warn that a remote artifact is being replaced by the local fallback.
+/// Timeouts get their own message, since they usually point at a proxy or
+/// VPN problem rather than a missing artifact, and other connection problems
+/// are named along with what to try.
+fn warn_fallback(error: Option<&anyhow::Error>, unavailable: &str) {
+    if let Some(auth) = error.and_then(auth_required) {
+        reporter().warn(format!("{}, falling back to local", auth));
+        return;
+    }
+    match error.and_then(timed_out_after) {
+        Some(timeout) => reporter().warn(format!(
+            "Remote timed out after {}s, falling back to local",
+            timeout.as_secs()
+        )),
+        None => match error.and_then(reachability::diagnose) {
+            Some(problem) => {
+                reporter().warn(format!("{} ({}; {})", unavailable, problem, problem.hint()))
+            }
+            None => reporter().warn(unavailable),
+        },
+    }
+}
+
+/// Run `fetch` until it succeeds, fails permanently, or runs out of attempts,
+/// backing off between attempts. `fetch` gets the attempt number, from 1.
+/// Retry warnings are printed around `pb` so they don't garble it.
+fn with_retries<T>(
+    what: &str,
+    pb: &ProgressBar,
+    mut fetch: impl FnMut(u32) -> Result<T, FetchError>,
+) -> Result<T> {
+    let policy = remote().retry;
+    let mut attempt = 1;
+    loop {
+        match fetch(attempt) {
+            Ok(value) => return Ok(value),
+            Err(FetchError::Transient(e)) if attempt < policy.attempts => {
+                let delay = policy.delay(attempt);
+                pb.suspend(|| {
+                    reporter().warn(format!(
+                        "{} failed (attempt {}/{}): {:#}; retrying in {:.1}s",
+                        what,
+                        attempt,
+                        policy.attempts,
+                        e,
+                        delay.as_secs_f64()
+                    ))
+                });
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(FetchError::Transient(e) | FetchError::Permanent(e)) => return Err(e),
+        }
+    }
+}
+
+/// Progress note for attempt number `attempt`, empty for the first
+fn attempt_note(attempt: u32) -> String {
+    if attempt > 1 {
+        format!("(attempt {}/{})", attempt, remote().retry.attempts)
+    } else {
+        String::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DownloadSource {
     Remote,
+    #[serde(rename = "local")]
     LocalFallback,
+    /// Previously downloaded and verified, see `cache`
+    Cache,
 }
 
-/// Get the latest version from remote or local fallback
-pub fn get_latest_version(local_dir: &Path) -> Result<(String, DownloadSource)> {
+/// A release channel, each with its own pointer to the latest version
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 3] = [Channel::Stable, Channel::Beta, Channel::Nightly];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+
+    /// Channel by name, as recorded at install time
+    pub fn parse(name: &str) -> Option<Self> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name() == name)
+    }
+
+    /// File holding the channel's latest version, on the mirrors and in the
+    /// local fallback
+    pub fn latest_file(self) -> &'static str {
+        match self {
+            Channel::Stable => "latest",
+            Channel::Beta => "latest-beta",
+            Channel::Nightly => "latest-nightly",
+        }
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Get the latest version on `channel` from remote or local fallback
+///
+/// With `offline`, only the local fallback is consulted.
+pub fn get_latest_version(
+    local_dir: &Path,
+    channel: Channel,
+    offline: bool,
+) -> Result<(String, DownloadSource)> {
     // Try remote first
-    let url = format!("{}/latest", GCS_BUCKET);
-    match reqwest::blocking::get(&url) {
-        Ok(response) if response.status().is_success() => {
-            let version = response.text()?.trim().to_string();
-            return Ok((version, DownloadSource::Remote));
+    let mut remote_error = None;
+    if !offline {
+        match remote_latest_version(channel) {
+            Ok((version, base_url)) => {
+                report_mirror(base_url);
+                return Ok((version, DownloadSource::Remote));
+            }
+            Err(e) => remote_error = Some(e),
         }
-        _ => {}
     }
 
     // Fall back to local
-    let local_path = local_dir.join("latest");
+    let local_path = local_dir.join(channel.latest_file());
+    if offline && !local_path.exists() {
+        return Err(Failure::Download(format!(
+            "Offline mode: expected the latest version file at {}",
+            local_path.display()
+        ))
+        .into());
+    }
+
     if local_path.exists() {
-        println!(
-            "  {} Remote unavailable, using local fallback",
-            style("!").yellow().bold()
-        );
+        if !offline {
+            warn_fallback(
+                remote_error.as_ref(),
+                "Remote unavailable, using local fallback",
+            );
+        }
         let version = std::fs::read_to_string(&local_path)
             .context("Failed to read local version file")?
             .trim()
@@ -39,145 +631,1148 @@ pub fn get_latest_version(local_dir: &Path) -> Result<(String, DownloadSource)>
         return Ok((version, DownloadSource::LocalFallback));
     }
 
-    Err(anyhow!("Could not get version from remote or local fallback"))
+    if let Some(auth) = remote_error.as_ref().and_then(auth_required) {
+        return Err(Failure::Download(auth.to_string()).into());
+    }
+    Err(Failure::Download(format!(
+        "Could not get the latest {} version from remote or local fallback",
+        channel
+    ))
+    .into())
+}
+
+/// Latest version on `channel` published remotely, or `None` if no mirror
+/// can be reached
+pub fn get_remote_latest_version(channel: Channel) -> Option<String> {
+    remote_latest_version(channel)
+        .ok()
+        .map(|(version, _)| version)
+}
+
+/// Latest version on `channel` from the first mirror that has one, and that
+/// mirror, or the last mirror's error
+fn remote_latest_version(channel: Channel) -> Result<(String, &'static str)> {
+    let mut last_error = None;
+    for base_url in base_urls() {
+        match get_text(&format!("{}/{}", base_url, channel.latest_file())) {
+            Ok(text) => return Ok((text.trim().to_string(), base_url.as_str())),
+            Err(FetchError::Transient(e) | FetchError::Permanent(e)) => {
+                tracing::debug!("no latest version from {}: {:#}", base_url, e);
+                note_error(&mut last_error, e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("No download URLs configured")))
+}
+
+/// File next to `latest` listing every published version
+const VERSION_INDEX_FILE: &str = "versions.json";
+
+/// Published versions and the version each channel points at
+#[derive(Debug)]
+pub struct VersionIndex {
+    /// Every known version, newest first
+    pub versions: Vec<String>,
+    pub heads: Vec<(Channel, String)>,
+    pub source: DownloadSource,
+}
+
+/// `versions.json` is either a bare array or an object with a `versions` key
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum VersionIndexFile {
+    List(Vec<String>),
+    Object { versions: Vec<String> },
+}
+
+/// List the versions available to install, from the first mirror that
+/// publishes a `versions.json`, or else the version directories under
+/// `local_dir`
+///
+/// With `offline`, only the local fallback is consulted.
+pub fn list_versions(local_dir: &Path, offline: bool) -> Result<VersionIndex> {
+    let mut remote_error = None;
+    if !offline {
+        match remote_versions() {
+            Ok((versions, base_url)) => {
+                report_mirror(base_url);
+                let heads = Channel::ALL
+                    .iter()
+                    .filter_map(|&channel| {
+                        remote_latest_version(channel)
+                            .ok()
+                            .map(|(version, _)| (channel, version))
+                    })
+                    .collect();
+                return Ok(VersionIndex::new(versions, heads, DownloadSource::Remote));
+            }
+            Err(e) => remote_error = Some(e),
+        }
+    }
+
+    let versions = local_versions(local_dir);
+    if versions.is_empty() {
+        if let Some(auth) = remote_error.as_ref().and_then(auth_required) {
+            return Err(Failure::Download(auth.to_string()).into());
+        }
+        return Err(Failure::Download(format!(
+            "Could not list versions from remote or local fallback ({})",
+            local_dir.display()
+        ))
+        .into());
+    }
+    if !offline {
+        warn_fallback(
+            remote_error.as_ref(),
+            "Remote version list unavailable, using local fallback",
+        );
+    }
+
+    let heads = Channel::ALL
+        .iter()
+        .filter_map(|&channel| {
+            std::fs::read_to_string(local_dir.join(channel.latest_file()))
+                .ok()
+                .map(|content| (channel, content.trim().to_string()))
+                .filter(|(_, version)| !version.is_empty())
+        })
+        .collect();
+    Ok(VersionIndex::new(
+        versions,
+        heads,
+        DownloadSource::LocalFallback,
+    ))
+}
+
+impl VersionIndex {
+    /// Sort newest first, adding any channel head the list left out
+    fn new(
+        mut versions: Vec<String>,
+        heads: Vec<(Channel, String)>,
+        source: DownloadSource,
+    ) -> Self {
+        for (_, head) in &heads {
+            if !versions.contains(head) {
+                versions.push(head.clone());
+            }
+        }
+        versions.sort_by(|a, b| compare_versions(b, a));
+        versions.dedup();
+        VersionIndex {
+            versions,
+            heads,
+            source,
+        }
+    }
+
+    /// Channels whose latest version is `version`
+    pub fn channels_at(&self, version: &str) -> Vec<Channel> {
+        self.heads
+            .iter()
+            .filter(|(_, head)| head == version)
+            .map(|&(channel, _)| channel)
+            .collect()
+    }
+}
+
+/// The version index from the first mirror that has one, and that mirror
+fn remote_versions() -> Result<(Vec<String>, &'static str)> {
+    let mut last_error = None;
+    for base_url in base_urls() {
+        let url = format!("{}/{}", base_url, VERSION_INDEX_FILE);
+        let result = get_text(&url)
+            .map_err(|e| match e {
+                FetchError::Transient(e) | FetchError::Permanent(e) => e,
+            })
+            .and_then(|text| {
+                serde_json::from_str::<VersionIndexFile>(&text)
+                    .with_context(|| format!("Invalid version index at {}", url))
+            });
+        match result {
+            Ok(VersionIndexFile::List(versions) | VersionIndexFile::Object { versions }) => {
+                return Ok((versions, base_url.as_str()))
+            }
+            Err(e) => {
+                tracing::debug!("no version index from {}: {:#}", base_url, e);
+                note_error(&mut last_error, e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("No download URLs configured")))
+}
+
+/// Directories under `local_dir` that hold a release manifest
+fn local_versions(local_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(local_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("manifest.json").is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Compare versions the way semver orders them: dotted parts numerically
+/// (falling back to string order for non-numeric ones), a pre-release such
+/// as `1.2.0-beta.1` before its release, and `+build` metadata ignored
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let split = |version: &str| -> (String, Option<String>) {
+        let version = version.split('+').next().unwrap_or_default();
+        match version.split_once('-') {
+            Some((release, pre)) => (release.to_string(), Some(pre.to_string())),
+            None => (version.to_string(), None),
+        }
+    };
+    let (a_release, a_pre) = split(a);
+    let (b_release, b_pre) = split(b);
+
+    match compare_dotted(&a_release, &b_release) {
+        Ordering::Equal => match (a_pre, b_pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => compare_dotted(&a, &b),
+        },
+        ordering => ordering,
+    }
+}
+
+/// Compare dot-separated identifiers, numbers numerically and before words
+fn compare_dotted(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    _ => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
 }
 
 /// Get the manifest for a version
-pub fn get_manifest(version: &str, local_dir: &Path) -> Result<(serde_json::Value, DownloadSource)> {
+///
+/// A manifest fetched remotely before is reused from the cache, since
+/// published versions never change. With `offline`, only the cache and the
+/// local fallback are consulted.
+pub fn get_manifest(
+    version: &str,
+    local_dir: &Path,
+    offline: bool,
+) -> Result<(Manifest, DownloadSource)> {
+    let cached_path = cache::manifest_path(version)?;
+    if let Some(manifest) = std::fs::read_to_string(&cached_path)
+        .ok()
+        .filter(|content| {
+            let signature = read_signature(&cached_path);
+            remote().allow_unsigned
+                || !matches!(
+                    verify_manifest_signature(content, signature.as_deref()),
+                    Some(Err(_))
+                )
+        })
+        .and_then(|content| Manifest::parse(&content, version).ok())
+    {
+        reporter().detail(format!("Using cached manifest {}", cached_path.display()));
+        return Ok((manifest, DownloadSource::Cache));
+    }
+
     // Try remote first
-    let url = format!("{}/{}/manifest.json", GCS_BUCKET, version);
-    match reqwest::blocking::get(&url) {
-        Ok(response) if response.status().is_success() => {
-            let manifest: serde_json::Value = response.json()?;
-            return Ok((manifest, DownloadSource::Remote));
+    let urls: Vec<String> = base_urls()
+        .iter()
+        .map(|base_url| format!("{}/{}/manifest.json", base_url, version))
+        .collect();
+    let mut remote_error = None;
+    let mut signature_error = None;
+    for (base_url, url) in base_urls().iter().filter(|_| !offline).zip(&urls) {
+        let fetched = with_retries("Manifest download", &ProgressBar::hidden(), |_| {
+            get_text(url)
+        });
+        match fetched {
+            Ok(content) => {
+                let signature = fetch_signature(url);
+                let checked = check_signature(version, &content, signature.as_deref(), url)
+                    .and_then(|()| Manifest::parse(&content, version));
+                let manifest = match checked {
+                    Ok(manifest) => manifest,
+                    Err(e) => {
+                        reporter().warn(format!("Ignoring manifest from {}: {:#}", base_url, e));
+                        if matches!(e.downcast_ref(), Some(Failure::Signature(_))) {
+                            signature_error = Some(e);
+                        } else {
+                            note_error(&mut remote_error, e);
+                        }
+                        continue;
+                    }
+                };
+                report_mirror(base_url);
+                if let Err(e) = write_cached_manifest(&cached_path, &content, signature.as_deref())
+                {
+                    tracing::debug!("could not cache manifest: {:#}", e);
+                }
+                return Ok((manifest, DownloadSource::Remote));
+            }
+            Err(e) => {
+                tracing::debug!("manifest unavailable from {}: {:#}", base_url, e);
+                note_error(&mut remote_error, e);
+            }
         }
-        _ => {}
     }
 
     // Fall back to local
     let local_path = local_dir.join(version).join("manifest.json");
+    if offline && !local_path.exists() {
+        return Err(Failure::Download(format!(
+            "Offline mode: expected the manifest for version {} at {}",
+            version,
+            local_path.display()
+        ))
+        .into());
+    }
+
     if local_path.exists() {
-        println!(
-            "  {} Remote unavailable, using local manifest",
-            style("!").yellow().bold()
-        );
+        if !offline {
+            warn_fallback(
+                remote_error.as_ref().or(signature_error.as_ref()),
+                "Remote unavailable, using local manifest",
+            );
+        }
         let content = std::fs::read_to_string(&local_path)?;
-        let manifest: serde_json::Value = serde_json::from_str(&content)?;
+        let signature = read_signature(&local_path);
+        check_signature(
+            version,
+            &content,
+            signature.as_deref(),
+            &local_path.display().to_string(),
+        )?;
+        let manifest = Manifest::parse(&content, version)
+            .with_context(|| format!("Failed to load {}", local_path.display()))?;
         return Ok((manifest, DownloadSource::LocalFallback));
     }
 
-    Err(anyhow!("Could not get manifest from remote or local fallback"))
+    // A mirror that served a manifest but failed verification says more than
+    // the list of places tried
+    if let Some(e) = signature_error {
+        return Err(e);
+    }
+    if let Some(auth) = remote_error.as_ref().and_then(auth_required) {
+        return Err(Failure::Download(auth.to_string()).into());
+    }
+
+    let looked_in: Vec<String> = urls
+        .iter()
+        .cloned()
+        .chain([local_path.display().to_string()])
+        .map(|place| format!("\n  - {}", place))
+        .collect();
+    Err(Failure::Download(format!(
+        "Could not find manifest for version {}. Looked in:{}",
+        version,
+        looked_in.concat()
+    ))
+    .into())
+}
+
+/// Check a manifest against its detached signature with the key manifests
+/// must be signed with; `None` if no key is configured, so there's nothing
+/// to check against
+pub fn verify_manifest_signature(
+    content: &str,
+    signature: Option<&str>,
+) -> Option<Result<(), SignatureError>> {
+    let key = remote().manifest_key.as_ref()?;
+    Some(key.verify(content.as_bytes(), signature))
+}
+
+/// The detached signature served next to the manifest at `url`, if a key is
+/// configured to check it with and there is one
+fn fetch_signature(url: &str) -> Option<String> {
+    remote().manifest_key.as_ref()?;
+    let signature_url = format!("{}{}", url, signature::SIGNATURE_SUFFIX);
+    match with_retries("Signature download", &ProgressBar::hidden(), |_| {
+        get_text(&signature_url)
+    }) {
+        Ok(signature) => Some(signature),
+        Err(e) => {
+            tracing::debug!("no signature at {}: {:#}", signature_url, e);
+            None
+        }
+    }
+}
+
+/// Whether `--allow-unsigned` was passed
+pub fn allows_unsigned() -> bool {
+    remote().allow_unsigned
+}
+
+/// Verify a manifest against its detached signature from `location`. With
+/// a key configured, a missing or invalid signature is a
+/// `Failure::Signature` unless `--allow-unsigned` was passed, in which case
+/// it's only a warning.
+fn check_signature(
+    version: &str,
+    content: &str,
+    signature: Option<&str>,
+    location: &str,
+) -> Result<()> {
+    let remote = remote();
+    let Some(key) = &remote.manifest_key else {
+        tracing::debug!("no manifest key configured, not checking {}", location);
+        return Ok(());
+    };
+    let message = match key.verify(content.as_bytes(), signature) {
+        Ok(()) => {
+            tracing::debug!("manifest {} verified with key {}", location, key.id());
+            return Ok(());
+        }
+        Err(SignatureError::Missing) => format!(
+            "No signature found for the {} manifest (expected {}{})",
+            version,
+            location,
+            signature::SIGNATURE_SUFFIX
+        ),
+        Err(SignatureError::Invalid(reason)) => format!(
+            "Invalid signature on the {} manifest at {}: {}",
+            version, location, reason
+        ),
+    };
+
+    if remote.allow_unsigned {
+        reporter().warn(format!(
+            "{}; continuing because of --allow-unsigned",
+            message
+        ));
+        Ok(())
+    } else {
+        Err(Failure::Signature(format!(
+            "{}. Pass --allow-unsigned to install anyway.",
+            message
+        ))
+        .into())
+    }
+}
+
+/// The detached signature stored next to a manifest, if any
+fn read_signature(manifest_path: &Path) -> Option<String> {
+    let mut path = manifest_path.as_os_str().to_owned();
+    path.push(signature::SIGNATURE_SUFFIX);
+    std::fs::read_to_string(path).ok()
+}
+
+/// Cache the manifest as served, keeping fields this version doesn't know,
+/// along with its signature so it can be verified again when reused
+fn write_cached_manifest(path: &Path, content: &str, signature: Option<&str>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(signature::SIGNATURE_SUFFIX);
+    match signature {
+        Some(signature) => std::fs::write(signature_path, signature)?,
+        None => {
+            let _ = std::fs::remove_file(signature_path);
+        }
+    }
+    Ok(())
+}
+
+/// URL of the binary for a version and platform on the first mirror
+pub fn binary_url(version: &str, platform: &str, binary_name: &str) -> String {
+    mirror_binary_url(&base_urls()[0], version, platform, binary_name)
+}
+
+fn mirror_binary_url(base_url: &str, version: &str, platform: &str, binary_name: &str) -> String {
+    format!("{}/{}/{}/{}", base_url, version, platform, binary_name)
+}
+
+/// Path of the binary for a version and platform in the local fallback
+pub fn local_binary_path(
+    local_dir: &Path,
+    version: &str,
+    platform: &str,
+    binary_name: &str,
+) -> PathBuf {
+    local_dir.join(version).join(platform).join(binary_name)
+}
+
+/// How many bytes `download_binary` will fetch for `entry`: the manifest's
+/// `size`, else the Content-Length of a HEAD request to the first source that
+/// answers, else the size of the local fallback copy. `None` if nothing says.
+pub fn artifact_size(
+    version: &str,
+    platform: &str,
+    binary_name: &str,
+    entry: &PlatformEntry,
+    local_dir: &Path,
+    offline: bool,
+) -> Option<u64> {
+    if let Some(size) = entry.size {
+        return Some(size);
+    }
+
+    let file_name = match entry.archive {
+        Some(kind) => kind.file_name(binary_name),
+        None => binary_name.to_string(),
+    };
+    if !offline {
+        let urls = entry.download_url.iter().cloned().chain(
+            base_urls()
+                .iter()
+                .map(|base_url| mirror_binary_url(base_url, version, platform, &file_name)),
+        );
+        for url in urls {
+            match remote_size(&url) {
+                Ok(Some(size)) => return Some(size),
+                Ok(None) => {}
+                Err(e) => tracing::debug!("could not get the size of {}: {:#}", url, e),
+            }
+        }
+    }
+
+    let local_path = local_binary_path(local_dir, version, platform, &file_name);
+    std::fs::metadata(local_path)
+        .ok()
+        .map(|metadata| metadata.len())
+}
+
+/// Size of the file at `url`, from a HEAD request or the file itself
+fn remote_size(url: &str) -> Result<Option<u64>> {
+    if let Some(path) = file_url_path(url) {
+        return Ok(Some(std::fs::metadata(&path)?.len()));
+    }
+    let response = match check_status(request(reqwest::Method::HEAD, url).send()?) {
+        Ok(response) => response,
+        Err(FetchError::Transient(e) | FetchError::Permanent(e)) => return Err(e),
+    };
+    Ok(response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse().ok()))
 }
 
 /// Download binary with fallback to local
+///
+/// For an entry with an `archive`, the archive is fetched and verified as
+/// described below, kept in the cache next to `output_path`, and the binary
+/// is extracted from it to `output_path`.
 pub fn download_binary(
     version: &str,
     platform: &str,
     binary_name: &str,
+    entry: &PlatformEntry,
     local_dir: &Path,
     output_path: &Path,
-    expected_checksum: &str,
+    offline: bool,
+) -> Result<()> {
+    let source = match entry.archive {
+        None => fetch_verified(
+            version,
+            platform,
+            binary_name,
+            entry,
+            local_dir,
+            output_path,
+            offline,
+        )?,
+        Some(kind) => {
+            let archive_path = cache::archive_path(output_path, kind.extension());
+            let source = fetch_verified(
+                version,
+                platform,
+                &kind.file_name(binary_name),
+                entry,
+                local_dir,
+                &archive_path,
+                offline,
+            )?;
+            archive::extract_binary(&archive_path, kind, binary_name, output_path)?;
+            reporter().success(format!("Extracted {}", binary_name));
+            source
+        }
+    };
+    events::emit(Event::DownloadSource(source));
+    Ok(())
+}
+
+/// Fetch the file `file_name` from a release and verify it against the
+/// manifest entry's checksum
+///
+/// If `output_path` already holds a file with the expected checksum (see
+/// `cache::binary_path`), it is used as is, and an identical binary cached
+/// under another version is copied there. Remote downloads go to a
+/// `.part` file next to it first, which an interrupted download leaves
+/// behind for the next attempt to resume; copies are staged in a temp file.
+/// Either way, nothing reaches `output_path` before its checksum is
+/// verified. A download URL in the manifest
+/// entry is tried before the mirrors. With `offline`, the binary is copied
+/// from the local fallback only.
+fn fetch_verified(
+    version: &str,
+    platform: &str,
+    file_name: &str,
+    entry: &PlatformEntry,
+    local_dir: &Path,
+    output_path: &Path,
+    offline: bool,
 ) -> Result<DownloadSource> {
-    // Try remote first
-    let url = format!("{}/{}/{}/{}", GCS_BUCKET, version, platform, binary_name);
+    let expected_checksum = entry.checksum.as_str();
+    let local_path = local_binary_path(local_dir, version, platform, file_name);
 
-    println!("  Downloading {}...", style(binary_name).cyan());
+    if output_path.exists() && verify_checksum(output_path, expected_checksum)? {
+        reporter().success("Using cached download (verified)");
+        reporter().detail(output_path.display());
+        return Ok(DownloadSource::Cache);
+    }
+    cache::remove_orphaned_temp_files();
+    for cached in cache::binaries_with_checksum(expected_checksum) {
+        if cached == output_path || !verify_checksum(&cached, expected_checksum)? {
+            continue;
+        }
+        let temp_path = cache::temp_path(output_path);
+        std::fs::copy(&cached, &temp_path).context("Failed to copy cached binary")?;
+        if verify_checksum(&temp_path, expected_checksum)? {
+            persist(&temp_path, output_path)?;
+            reporter().success("Using identical cached download (verified)");
+            reporter().detail(cached.display());
+            return Ok(DownloadSource::Cache);
+        }
+        std::fs::remove_file(&temp_path).ok();
+    }
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("  {spinner:.cyan} {msg}")
-            .unwrap(),
-    );
-    pb.set_message("Connecting to remote server...");
+    if offline {
+        if !local_path.exists() {
+            return Err(Failure::Download(format!(
+                "Offline mode: expected the {} binary at {}",
+                platform,
+                local_path.display()
+            ))
+            .into());
+        }
+        return copy_local_binary(&local_path, output_path, expected_checksum);
+    }
 
-    let remote_result = download_from_url(&url, output_path, &pb);
+    // Try each mirror in turn
+    let part_path = cache::partial_path(output_path);
+    let mut remote_mismatch = false;
+    let mut remote_error = None;
+    let urls = entry
+        .download_url
+        .iter()
+        .map(|url| (url.as_str(), url.clone()))
+        .chain(base_urls().iter().map(|base_url| {
+            let url = mirror_binary_url(base_url, version, platform, file_name);
+            (base_url.as_str(), url)
+        }));
+    for (source, url) in urls {
+        match download_verified(&url, &part_path, output_path, entry)? {
+            MirrorOutcome::Verified => {
+                report_mirror(source);
+                return Ok(DownloadSource::Remote);
+            }
+            MirrorOutcome::Mismatch => {
+                download_failed(&url, "does not match the manifest's checksum");
+                remote_mismatch = true;
+            }
+            MirrorOutcome::Failed(e) => {
+                download_failed(&url, format!("{:#}", e));
+                note_error(&mut remote_error, e);
+            }
+        }
+    }
 
-    if remote_result.is_ok() {
-        pb.finish_and_clear();
-        // Verify checksum
-        if verify_checksum(output_path, expected_checksum)? {
-            println!(
-                "  {} Downloaded and verified",
-                style("✓").green().bold()
-            );
-            return Ok(DownloadSource::Remote);
-        } else {
-            std::fs::remove_file(output_path).ok();
-            println!(
-                "  {} Checksum verification failed, trying local fallback",
-                style("!").yellow().bold()
-            );
+    // Fall back to local
+    if local_path.exists() {
+        warn_fallback(
+            remote_error.as_ref(),
+            "Remote unavailable, trying local fallback",
+        );
+        return copy_local_binary(&local_path, output_path, expected_checksum);
+    }
+
+    if remote_mismatch {
+        return Err(Failure::ChecksumMismatch(
+            "Remote checksum verification failed and no local fallback found".to_string(),
+        )
+        .into());
+    }
+
+    if let Some(auth) = remote_error.as_ref().and_then(auth_required) {
+        return Err(Failure::Download(auth.to_string()).into());
+    }
+    if let Some(timeout) = remote_error.as_ref().and_then(timed_out_after) {
+        return Err(Failure::Download(format!(
+            "Remote timed out after {}s and no local fallback found",
+            timeout.as_secs()
+        ))
+        .into());
+    }
+
+    Err(Failure::Download("Remote unavailable and no local fallback found".to_string()).into())
+}
+
+/// Fetch a small text artifact such as a `latest` file from `url`, which
+/// isn't on the mirrors, retrying like downloads
+pub fn fetch_text(url: &str) -> Result<String> {
+    with_retries("Fetch", &ProgressBar::hidden(), |_| get_text(url))
+        .with_context(|| format!("Failed to fetch {}", url))
+}
+
+/// Fetch the manifest for `version` from `url`, which isn't on the mirrors,
+/// checking its signature the way `get_manifest` does. Nothing is cached
+/// and there is no local fallback.
+pub fn fetch_manifest(url: &str, version: &str) -> Result<Manifest> {
+    let content = fetch_text(url)?;
+    let signature = fetch_signature(url);
+    check_signature(version, &content, signature.as_deref(), url)?;
+    Manifest::parse(&content, version)
+}
+
+/// Download the build `entry` describes from `url` to `output_path`,
+/// verifying its checksum the way `download_binary` does and extracting
+/// `binary_name` if it ships in an archive
+pub fn download_entry(
+    url: &str,
+    entry: &PlatformEntry,
+    binary_name: &str,
+    output_path: &Path,
+) -> Result<()> {
+    let target = match entry.archive {
+        Some(kind) => cache::archive_path(output_path, kind.extension()),
+        None => output_path.to_path_buf(),
+    };
+    match download_verified(url, &cache::partial_path(&target), &target, entry)? {
+        MirrorOutcome::Verified => {}
+        MirrorOutcome::Mismatch => {
+            return Err(Failure::ChecksumMismatch(format!(
+                "{} does not match the manifest's checksum",
+                url
+            ))
+            .into())
         }
-    } else {
+        MirrorOutcome::Failed(e) => {
+            return Err(Failure::Download(format!("Failed to download {} ({:#})", url, e)).into())
+        }
+    }
+    if let Some(kind) = entry.archive {
+        let extracted = archive::extract_binary(&target, kind, binary_name, output_path);
+        std::fs::remove_file(&target).ok();
+        extracted?;
+    }
+    Ok(())
+}
+
+/// Download the file at `url`, which has no manifest, to `output_path`,
+/// checking its SHA-256 against `sha256` when there is one. `local_path` is
+/// the fallback when the download fails, and with `offline` the only source.
+/// Like `fetch_verified`, nothing reaches `output_path` unverified.
+pub fn download_file(
+    url: &str,
+    local_path: &Path,
+    output_path: &Path,
+    sha256: Option<&str>,
+    offline: bool,
+) -> Result<()> {
+    let source = fetch_file(url, local_path, output_path, sha256, offline)?;
+    events::emit(Event::DownloadSource(source));
+    Ok(())
+}
+
+fn fetch_file(
+    url: &str,
+    local_path: &Path,
+    output_path: &Path,
+    sha256: Option<&str>,
+    offline: bool,
+) -> Result<DownloadSource> {
+    let verified = |checksum: &str| sha256.is_none_or(|want| checksum.eq_ignore_ascii_case(want));
+
+    let mut remote_error = None;
+    if !offline {
+        let name = style(url.rsplit('/').next().unwrap_or(url)).cyan();
+        reporter().info(format!("Downloading {}...", name));
+        reporter().detail(url);
+
+        let part_path = cache::partial_path(output_path);
+        let pb = if reporter().show_progress() {
+            ProgressBar::new_spinner()
+        } else {
+            ProgressBar::hidden()
+        };
+        let result = with_retries("Download", &pb, |attempt| {
+            pb.set_style(reporter().spinner_style());
+            pb.set_position(0);
+            pb.set_message(format!(
+                "Connecting to remote server... {}",
+                attempt_note(attempt)
+            ));
+            download_from_url(url, &part_path, None, &pb, attempt)
+        });
         pb.finish_and_clear();
-        println!(
-            "  {} Remote download failed, trying local fallback",
-            style("!").yellow().bold()
+
+        match result {
+            Ok(checksum) if verified(&checksum) => {
+                persist(&part_path, output_path)
+                    .context("Failed to move the download into place")?;
+                reporter().success(match sha256 {
+                    Some(_) => "Downloaded and verified",
+                    None => "Downloaded",
+                });
+                return Ok(DownloadSource::Remote);
+            }
+            Ok(_) => {
+                std::fs::remove_file(&part_path).ok();
+                reporter().warn("Checksum verification failed");
+                download_failed(url, "does not match its sha256");
+                remote_error = Some(anyhow!("{} does not match its sha256", url));
+            }
+            Err(e) => {
+                cancel::check()?;
+                download_failed(url, format!("{:#}", e));
+                remote_error = Some(e);
+            }
+        }
+    }
+
+    if !local_path.exists() {
+        if offline {
+            return Err(Failure::Download(format!(
+                "Offline mode: expected a local copy at {}",
+                local_path.display()
+            ))
+            .into());
+        }
+        let reason = remote_error.map_or_else(String::new, |e| format!(" ({:#})", e));
+        return Err(Failure::Download(format!(
+            "Download failed and no local fallback found at {}{}",
+            local_path.display(),
+            reason
+        ))
+        .into());
+    }
+    if !offline {
+        warn_fallback(
+            remote_error.as_ref(),
+            "Remote unavailable, trying local fallback",
         );
     }
 
-    // Fall back to local
-    let local_path = local_dir.join(version).join(platform).join(binary_name);
-    if local_path.exists() {
-        std::fs::copy(&local_path, output_path)
-            .context("Failed to copy local binary")?;
+    let temp_path = cache::temp_path(output_path);
+    std::fs::copy(local_path, &temp_path).context("Failed to copy the local fallback")?;
+    if !verified(&file_checksum(&temp_path)?) {
+        std::fs::remove_file(&temp_path).ok();
+        return Err(Failure::ChecksumMismatch(format!(
+            "{} does not match its sha256",
+            local_path.display()
+        ))
+        .into());
+    }
+    persist(&temp_path, output_path).context("Failed to move the local fallback into place")?;
+    reporter().success("Using local fallback");
+    Ok(DownloadSource::LocalFallback)
+}
 
-        if verify_checksum(output_path, expected_checksum)? {
-            println!(
-                "  {} Using local fallback (verified)",
-                style("✓").green().bold()
-            );
-            return Ok(DownloadSource::LocalFallback);
+fn download_failed(url: &str, error: impl Into<String>) {
+    events::emit(Event::DownloadFailed {
+        url: url.to_string(),
+        error: error.into(),
+    });
+}
+
+/// How downloading from one mirror went
+enum MirrorOutcome {
+    Verified,
+    /// Downloaded twice, but the checksum never matched
+    Mismatch,
+    /// Unreachable or out of retries; any partial file is kept for resuming
+    Failed(anyhow::Error),
+}
+
+/// Download `url` into `part_path` and move it to `output_path` once its
+/// checksum verifies
+fn download_verified(
+    url: &str,
+    part_path: &Path,
+    output_path: &Path,
+    entry: &PlatformEntry,
+) -> Result<MirrorOutcome> {
+    let expected_checksum = entry.checksum.as_str();
+    let name = style(url.rsplit('/').next().unwrap_or(url)).cyan();
+    match entry.size {
+        Some(size) => reporter().info(format!("Downloading {} ({})...", name, HumanBytes(size))),
+        None => reporter().info(format!("Downloading {}...", name)),
+    }
+    reporter().detail(url);
+
+    // A mismatch is more often a corrupted transfer than a bad build, so
+    // download once more before giving up on the mirror
+    for pass in 1..=2 {
+        let pb = if reporter().show_progress() {
+            ProgressBar::new_spinner()
+        } else {
+            ProgressBar::hidden()
+        };
+
+        let remote_result = with_retries("Download", &pb, |attempt| {
+            pb.set_style(reporter().spinner_style());
+            pb.set_position(0);
+            pb.set_message(format!(
+                "Connecting to remote server... {}",
+                attempt_note(attempt)
+            ));
+            download_from_url(url, part_path, entry.size, &pb, attempt)
+        });
+        pb.finish_and_clear();
+
+        // The partial file stays for the next run to resume
+        let checksum = match remote_result {
+            Ok(checksum) => checksum,
+            Err(e) => {
+                // Ctrl+C isn't a reason to try the next mirror
+                cancel::check()?;
+                match timed_out_after(&e) {
+                    Some(timeout) => {
+                        reporter().warn(format!("Download timed out after {}s", timeout.as_secs()))
+                    }
+                    None => reporter().warn(format!("Download failed ({:#})", e)),
+                }
+                return Ok(MirrorOutcome::Failed(e));
+            }
+        };
+
+        if checksum.eq_ignore_ascii_case(expected_checksum) {
+            persist(part_path, output_path)
+                .context("Failed to move downloaded binary into place")?;
+            reporter().success("Downloaded and verified");
+            return Ok(MirrorOutcome::Verified);
+        }
+
+        std::fs::remove_file(part_path).ok();
+        if pass == 1 {
+            reporter().warn("Checksum verification failed, downloading again");
         } else {
-            std::fs::remove_file(output_path).ok();
-            return Err(anyhow!("Local fallback checksum verification failed"));
+            reporter().warn("Checksum verification failed again");
         }
     }
 
-    Err(anyhow!("Remote unavailable and no local fallback found"))
+    Ok(MirrorOutcome::Mismatch)
 }
 
-fn download_from_url(url: &str, output_path: &Path, pb: &ProgressBar) -> Result<()> {
-    let response = reqwest::blocking::get(url)?;
+fn copy_local_binary(
+    local_path: &Path,
+    output_path: &Path,
+    expected_checksum: &str,
+) -> Result<DownloadSource> {
+    let temp_path = cache::temp_path(output_path);
+    std::fs::copy(local_path, &temp_path).context("Failed to copy local binary")?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!("HTTP error: {}", response.status()));
+    if verify_checksum(&temp_path, expected_checksum)? {
+        persist(&temp_path, output_path).context("Failed to move local binary into place")?;
+        reporter().success("Using local fallback (verified)");
+        Ok(DownloadSource::LocalFallback)
+    } else {
+        std::fs::remove_file(&temp_path).ok();
+        Err(Failure::ChecksumMismatch(
+            "Local fallback checksum verification failed".to_string(),
+        )
+        .into())
     }
+}
 
-    let total_size = response.content_length().unwrap_or(0);
+/// Flush a verified `temp` file to disk and rename it to `path`, so `path`
+/// only ever holds a complete file
+fn persist(temp: &Path, path: &Path) -> Result<()> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(temp)?
+        .sync_all()?;
+    if let Err(e) = std::fs::rename(temp, path) {
+        // Windows can refuse to replace an existing file, so remove it first
+        if !path.exists() {
+            return Err(e.into());
+        }
+        std::fs::remove_file(path)?;
+        std::fs::rename(temp, path)?;
+    }
+    Ok(())
+}
 
-    if total_size > 0 {
-        pb.set_length(total_size);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("  {spinner:.cyan} [{bar:30.cyan/dim}] {bytes}/{total_bytes} ({eta})")
-                .unwrap()
-                .progress_chars("█▓░"),
-        );
+/// Writes a file while hashing everything in it, so a download's checksum is
+/// known without reading the file back
+struct HashingWriter {
+    file: std::fs::File,
+    hasher: Sha256,
+}
+
+impl HashingWriter {
+    fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(HashingWriter {
+            file: std::fs::File::create(path)?,
+            hasher: Sha256::new(),
+        })
+    }
+
+    /// Append to an existing file, hashing what it already holds first
+    fn append(path: &Path) -> std::io::Result<Self> {
+        let mut writer = HashingWriter {
+            file: std::fs::OpenOptions::new().append(true).open(path)?,
+            hasher: Sha256::new(),
+        };
+        std::io::copy(&mut std::fs::File::open(path)?, &mut writer.hasher)?;
+        Ok(writer)
+    }
+
+    fn write_all(&mut self, buffer: &[u8]) -> std::io::Result<()> {
+        std::io::Write::write_all(&mut self.file, buffer)?;
+        self.hasher.update(buffer);
+        Ok(())
     }
 
-    let mut file = std::fs::File::create(output_path)?;
-    let mut downloaded: u64 = 0;
+    /// SHA-256 of the whole file, as lowercase hex
+    fn finish(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+/// Download `url` into `part_path`. If the file already has data from an
+/// interrupted attempt, ask the server for just the rest; servers that
+/// ignore the range get the file written from scratch. `file://` URLs are
+/// copied whole. The bar shows progress against the response's length, or
+/// the manifest's `size` when the server doesn't send one. Returns the
+/// SHA-256 of the complete file.
+fn download_from_url(
+    url: &str,
+    part_path: &Path,
+    expected_size: Option<u64>,
+    pb: &ProgressBar,
+    attempt: u32,
+) -> Result<String, FetchError> {
+    if let Some(path) = file_url_path(url) {
+        let copy = || -> std::io::Result<String> {
+            let mut writer = HashingWriter::create(part_path)?;
+            let mut source = std::fs::File::open(&path)?;
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                if cancel::is_cancelled() {
+                    return Err(std::io::ErrorKind::Interrupted.into());
+                }
+                let bytes_read = source.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    return Ok(writer.finish());
+                }
+                writer.write_all(&buffer[..bytes_read])?;
+            }
+        };
+        return copy().map_err(|e| {
+            FetchError::Permanent(anyhow!("Failed to copy {}: {}", path.display(), e))
+        });
+    }
+
+    let existing = std::fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = request(reqwest::Method::GET, url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let response = request.send()?;
+
+    // The partial file doesn't fit the remote one, so start over
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        std::fs::remove_file(part_path).ok();
+        return Err(FetchError::Transient(anyhow!(
+            "Server rejected resuming at byte {}",
+            existing
+        )));
+    }
+    let response = check_status(response)?;
+
+    let resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let start = if resumed { existing } else { 0 };
+    let content_length = response.content_length().map(|len| start + len);
+
+    match content_length.or(expected_size).filter(|&size| size > 0) {
+        Some(total_size) => {
+            pb.set_length(total_size);
+            pb.set_style(reporter().bar_style());
+        }
+        None => pb.set_style(reporter().counter_style()),
+    }
+    pb.set_message(attempt_note(attempt));
+    if resumed {
+        pb.suspend(|| reporter().detail(format!("Resuming from {}", HumanBytes(start))));
+    }
+
+    let writer = if resumed {
+        HashingWriter::append(part_path)
+    } else {
+        HashingWriter::create(part_path)
+    };
+    let mut writer = writer.map_err(|e| FetchError::Permanent(e.into()))?;
+    let mut downloaded = start;
+    pb.set_position(downloaded);
+    // Bytes from an earlier attempt shouldn't count toward the rate
+    pb.reset_eta();
 
     let mut reader = response;
     let mut buffer = [0u8; 8192];
 
     loop {
-        let bytes_read = reader.read(&mut buffer)?;
+        // Stop at Ctrl+C, keeping what's downloaded for the next run to resume
+        if cancel::is_cancelled() {
+            return Err(FetchError::Permanent(Failure::Interrupted.into()));
+        }
+
+        // A read error means the connection dropped mid-transfer
+        let bytes_read = reader
+            .read(&mut buffer)
+            .map_err(|e| FetchError::Transient(e.into()))?;
         if bytes_read == 0 {
             break;
         }
 
-        std::io::Write::write_all(&mut file, &buffer[..bytes_read])?;
+        writer
+            .write_all(&buffer[..bytes_read])
+            .map_err(|e| FetchError::Permanent(e.into()))?;
         downloaded += bytes_read as u64;
         pb.set_position(downloaded);
     }
 
-    Ok(())
+    // Passing a truncated file on would only fail as a checksum mismatch
+    if let Some(total_size) = content_length.filter(|&size| downloaded < size) {
+        return Err(FetchError::Transient(anyhow!(
+            "Connection closed after {} of {} bytes",
+            downloaded,
+            total_size
+        )));
+    }
+
+    Ok(writer.finish())
 }
 
-fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
+/// Hash a file already on disk, for the local fallback and cache hits
+pub fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
+    Ok(file_checksum(file_path)? == expected)
+}
+
+/// SHA-256 of a file, hex encoded
+pub fn file_checksum(file_path: &Path) -> Result<String> {
     let mut file = std::fs::File::open(file_path)?;
     let mut hasher = Sha256::new();
     let mut buffer = [0u8; 8192];
@@ -190,6 +1785,48 @@ fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
         hasher.update(&buffer[..bytes_read]);
     }
 
-    let actual = hex::encode(hasher.finalize());
-    Ok(actual == expected)
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streamed_checksum_matches_file_checksum() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("claude.part");
+        // Several buffers' worth, in uneven chunks
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let (first, rest) = content.split_at(70_001);
+
+        let mut writer = HashingWriter::create(&path).unwrap();
+        for chunk in first.chunks(8192) {
+            writer.write_all(chunk).unwrap();
+        }
+        drop(writer);
+
+        // Resuming hashes what the interrupted attempt left behind
+        let mut writer = HashingWriter::append(&path).unwrap();
+        for chunk in rest.chunks(5000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let streamed = writer.finish();
+
+        assert_eq!(streamed, hex::encode(Sha256::digest(&content)));
+        assert!(verify_checksum(&path, &streamed).unwrap());
+    }
+
+    #[test]
+    fn versions_compare_as_semver() {
+        use std::cmp::Ordering::*;
+
+        assert_eq!(compare_versions("1.10.0", "1.9.3"), Greater);
+        assert_eq!(compare_versions("2.1.0", "2.1.0"), Equal);
+        assert_eq!(compare_versions("2.1.0-beta.1", "2.1.0"), Less);
+        assert_eq!(compare_versions("2.1.0-beta.2", "2.1.0-beta.10"), Less);
+        assert_eq!(compare_versions("2.1.0-alpha", "2.1.0-beta"), Less);
+        assert_eq!(compare_versions("2.1.0+build.5", "2.1.0"), Equal);
+        assert_eq!(compare_versions("2.1.1-rc.1", "2.1.0"), Greater);
+    }
 }
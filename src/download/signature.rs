@@ -0,0 +1,153 @@
+//! Minisign signatures over release manifests, so a compromised bucket
+//! can't swap in binaries along with matching checksums
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Suffix of the detached signature next to a manifest
+pub const SIGNATURE_SUFFIX: &str = ".minisig";
+
+/// Why a manifest couldn't be verified
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error("no signature found")]
+    Missing,
+
+    #[error("signature invalid: {0}")]
+    Invalid(String),
+}
+
+/// A minisign public key, and its id as minisign prints it
+#[derive(Debug, Clone)]
+pub struct PublicKey {
+    key: minisign_verify::PublicKey,
+    id: String,
+}
+
+impl PublicKey {
+    /// Parse a key as printed by `minisign -G`, with or without the
+    /// `untrusted comment:` line of a `.pub` file
+    pub fn parse(text: &str) -> Result<Self> {
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .context("Public key is empty")?;
+        let key = minisign_verify::PublicKey::from_base64(line)
+            .map_err(|e| anyhow::anyhow!("Not a minisign Ed25519 public key ({})", e))?;
+        // minisign prints the little-endian key id as a number
+        let bytes = STANDARD.decode(line)?;
+        let id = bytes[2..10]
+            .iter()
+            .rev()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        Ok(PublicKey { key, id })
+    }
+
+    /// Key id in the form minisign prints it
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Check `signature`, the contents of a `.minisig` file, over `message`.
+    /// Signatures over the message itself rather than its hash, from
+    /// minisign before 0.8, are accepted as `minisign -V` does.
+    pub fn verify(&self, message: &[u8], signature: Option<&str>) -> Result<(), SignatureError> {
+        use minisign_verify::Error;
+
+        let signature = signature.ok_or(SignatureError::Missing)?;
+        let signature = minisign_verify::Signature::decode(signature).map_err(|e| match e {
+            Error::UnsupportedAlgorithm => {
+                SignatureError::Invalid("unsupported signature algorithm".to_string())
+            }
+            _ => SignatureError::Invalid("not a minisign signature file".to_string()),
+        })?;
+        self.key
+            .verify(message, &signature, true)
+            .map_err(|e| match e {
+                Error::UnexpectedKeyId => {
+                    SignatureError::Invalid(format!("made with another key than {}", self.id))
+                }
+                // The trusted comment is signed too, so a changed one fails
+                // here along with a changed manifest
+                _ => SignatureError::Invalid(
+                    "does not match the manifest or its trusted comment".to_string(),
+                ),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Key and signatures made with minisign's format over `MANIFEST`
+    const TEST_KEY: &str = "untrusted comment: minisign public key\n\
+        RWTMZS6ScCLEBg6q8gmMkHjykfaT4JR63bHfHF3Bogz2NdGCB4HdL7ls\n";
+    const MANIFEST: &[u8] = br#"{"platforms":{}}"#;
+    const PREHASHED: &str = "untrusted comment: signature from minisign secret key
+RUTMZS6ScCLEBtMoZSiR/xHpsRwnv+M8WbE7KHjgHqE1mM8ptVTE+h6DVwxrpHHQ9vm365ZziPPBNVJNQtfBQ8C5HuUhzO2JqQA=
+trusted comment: timestamp:1700000000\tfile:manifest.json
+96mxeqNz5MtdlBDN8UxAN+SX9KBv54nNUs6z1aGSYYFN2EtGC8Zm1Onyxo5aCVM6fD7WF6s57Is7U6+OPvgXCw==
+";
+    const LEGACY: &str = "untrusted comment: signature from minisign secret key
+RWTMZS6ScCLEBrm7AmCgtjikYitiazwPUk8+AfOSe54nL8XGGE/f+scCg35fpKpRGdoyy//fBYVuuEdjvwyQ5LyF9Z0nPh96lgQ=
+trusted comment: timestamp:1700000000\tfile:manifest.json
+vfCR6mVfn58VVNlvGE0ND3xZwhs/6ErbaLMAlML9XDmgyrXJ0dY4BJcpBaMi62EHcBE+Pi3hkMJRJDHEY4ZBAQ==
+";
+
+    fn key() -> PublicKey {
+        PublicKey::parse(TEST_KEY).unwrap()
+    }
+
+    #[test]
+    fn verifies_prehashed_and_legacy_signatures() {
+        key().verify(MANIFEST, Some(PREHASHED)).unwrap();
+        key().verify(MANIFEST, Some(LEGACY)).unwrap();
+        assert_eq!(key().id(), "06C42270922E65CC");
+    }
+
+    #[test]
+    fn distinguishes_missing_from_invalid() {
+        assert!(matches!(
+            key().verify(MANIFEST, None),
+            Err(SignatureError::Missing)
+        ));
+
+        let tampered = br#"{"platforms":{"linux-x64":{}}}"#;
+        let err = key().verify(tampered, Some(PREHASHED)).unwrap_err();
+        assert!(
+            err.to_string().contains("does not match the manifest"),
+            "{}",
+            err
+        );
+
+        let comment = PREHASHED.replace("1700000000", "1800000000");
+        let err = key().verify(MANIFEST, Some(&comment)).unwrap_err();
+        assert!(err.to_string().contains("trusted comment"), "{}", err);
+
+        let err = key().verify(MANIFEST, Some("not a signature")).unwrap_err();
+        assert!(matches!(err, SignatureError::Invalid(_)));
+    }
+
+    #[test]
+    fn rejects_signatures_from_other_keys() {
+        // The test key with another key id
+        let mut other = STANDARD
+            .decode("RWTMZS6ScCLEBg6q8gmMkHjykfaT4JR63bHfHF3Bogz2NdGCB4HdL7ls")
+            .unwrap();
+        other[2] ^= 0xff;
+        let other = PublicKey::parse(&STANDARD.encode(other)).unwrap();
+
+        let err = other.verify(MANIFEST, Some(PREHASHED)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("made with another key than {}", other.id())),
+            "{}",
+            err
+        );
+        assert!(PublicKey::parse("RWQ=").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! The environment variables and PATH entries code-assist set for a tool,
+//! compared between where they're saved for new shells and the running
+//! process, for `code-assist env`. A shell opened before an install keeps
+//! the environment it started with, which is the usual reason the two
+//! disagree.
+
+use std::path::Path;
+
+use crate::doctor;
+use crate::platform;
+use crate::state::State;
+
+/// A shell `env --shell` can write statements for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    /// The shell this is most likely running under, for suggesting the
+    /// command that brings it up to date
+    pub fn detect() -> Self {
+        if cfg!(target_os = "windows") {
+            return Shell::Powershell;
+        }
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        if shell.ends_with("fish") {
+            Shell::Fish
+        } else if shell.ends_with("zsh") {
+            Shell::Zsh
+        } else {
+            Shell::Bash
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Powershell => "powershell",
+        }
+    }
+
+    /// How to run `env --shell` for this shell so it applies the output
+    pub fn eval_command(self) -> String {
+        match self {
+            Shell::Powershell => {
+                "code-assist env --shell powershell | Invoke-Expression".to_string()
+            }
+            shell => format!("eval \"$(code-assist env --shell {})\"", shell.name()),
+        }
+    }
+
+    /// `text` as a single-quoted literal
+    fn quote(self, text: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", text.replace('\'', "'\\''")),
+            Shell::Fish => format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'")),
+            Shell::Powershell => format!("'{}'", text.replace('\'', "''")),
+        }
+    }
+
+    fn set_var(self, name: &str, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={}", name, self.quote(value)),
+            Shell::Fish => format!("set -gx {} {}", name, self.quote(value)),
+            Shell::Powershell => format!("$env:{} = {}", name, self.quote(value)),
+        }
+    }
+
+    fn prepend_path(self, dir: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export PATH={}:\"$PATH\"", self.quote(dir)),
+            Shell::Fish => format!("set -gx PATH {} $PATH", self.quote(dir)),
+            Shell::Powershell => format!(
+                "$env:Path = {} + [IO.Path]::PathSeparator + $env:Path",
+                self.quote(dir)
+            ),
+        }
+    }
+}
+
+/// An environment variable code-assist set
+#[derive(Debug)]
+pub struct VarAudit {
+    pub name: String,
+    /// What code-assist set it to
+    pub recorded: String,
+    /// The value new shells get and where it's saved
+    pub saved: Option<(String, String)>,
+    /// The value in this process
+    pub current: Option<String>,
+}
+
+impl VarAudit {
+    /// Whether this process has the value new shells get
+    pub fn agrees(&self) -> bool {
+        self.saved.as_ref().map(|(value, _)| value) == self.current.as_ref()
+    }
+
+    /// Values, saved or current, naming a file that doesn't exist
+    pub fn dangling(&self) -> Vec<&str> {
+        let mut values: Vec<&str> = self
+            .saved
+            .iter()
+            .map(|(value, _)| value.as_str())
+            .chain(self.current.as_deref())
+            .filter(|value| is_missing_file(value))
+            .collect();
+        values.dedup();
+        values
+    }
+}
+
+/// A directory code-assist added to PATH
+#[derive(Debug)]
+pub struct PathAudit {
+    pub dir: String,
+    /// Where the saved PATH gets it from
+    pub saved: Option<String>,
+    /// Whether this process has it on PATH
+    pub current: bool,
+    pub exists: bool,
+}
+
+/// Everything code-assist set in the environment for one tool
+#[derive(Debug, Default)]
+pub struct Audit {
+    pub vars: Vec<VarAudit>,
+    pub path_entries: Vec<PathAudit>,
+}
+
+impl Audit {
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty() && self.path_entries.is_empty()
+    }
+
+    /// Whether anything here differs between new shells and this process
+    pub fn out_of_date(&self) -> bool {
+        self.vars
+            .iter()
+            .any(|var| var.saved.is_some() && !var.agrees())
+            || self
+                .path_entries
+                .iter()
+                .any(|entry| entry.saved.is_some() && !entry.current)
+    }
+
+    /// Statements that give a shell the values new shells get, falling back
+    /// to what code-assist set where nothing is saved; PATH entries the
+    /// shell already has are left out so running it twice adds nothing
+    pub fn exports(&self, shell: Shell) -> Vec<String> {
+        let vars = self.vars.iter().map(|var| {
+            let value = var.saved.as_ref().map_or(&var.recorded, |(value, _)| value);
+            shell.set_var(&var.name, value)
+        });
+        let path_entries = self
+            .path_entries
+            .iter()
+            .filter(|entry| !entry.current)
+            .map(|entry| shell.prepend_path(&entry.dir));
+        vars.chain(path_entries).collect()
+    }
+}
+
+/// Compare what `state` records as set against the saved and current
+/// environment
+pub fn audit(state: &State) -> Audit {
+    Audit {
+        vars: state
+            .env_vars
+            .iter()
+            .map(|var| VarAudit {
+                name: var.name.clone(),
+                recorded: var.value.clone(),
+                saved: platform::find_user_env_var(&var.name),
+                current: std::env::var(&var.name).ok(),
+            })
+            .collect(),
+        path_entries: state
+            .path_entries
+            .iter()
+            .map(|dir| PathAudit {
+                dir: dir.clone(),
+                saved: platform::find_on_user_path(dir),
+                current: doctor::is_on_path(Path::new(dir)),
+                exists: Path::new(dir).is_dir(),
+            })
+            .collect(),
+    }
+}
+
+/// Whether `value` is a path to a file that isn't there
+fn is_missing_file(value: &str) -> bool {
+    let path = Path::new(value);
+    path.is_absolute() && !path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_survive_each_shell() {
+        assert_eq!(Shell::Zsh.set_var("A", "it's"), "export A='it'\\''s'");
+        assert_eq!(Shell::Fish.set_var("A", "it's"), "set -gx A 'it\\'s'");
+        assert_eq!(Shell::Powershell.set_var("A", "it's"), "$env:A = 'it''s'");
+    }
+
+    #[test]
+    fn exports_prefer_the_saved_value_and_skip_path_already_set() {
+        let audit = Audit {
+            vars: vec![
+                VarAudit {
+                    name: "SAVED".to_string(),
+                    recorded: "old".to_string(),
+                    saved: Some(("new".to_string(), "~/.zshrc:3".to_string())),
+                    current: None,
+                },
+                VarAudit {
+                    name: "UNSAVED".to_string(),
+                    recorded: "set".to_string(),
+                    saved: None,
+                    current: None,
+                },
+            ],
+            path_entries: vec![
+                PathAudit {
+                    dir: "/opt/a".to_string(),
+                    saved: None,
+                    current: true,
+                    exists: true,
+                },
+                PathAudit {
+                    dir: "/opt/b".to_string(),
+                    saved: None,
+                    current: false,
+                    exists: true,
+                },
+            ],
+        };
+
+        assert_eq!(
+            audit.exports(Shell::Bash),
+            [
+                "export SAVED='new'",
+                "export UNSAVED='set'",
+                "export PATH='/opt/b':\"$PATH\"",
+            ]
+        );
+    }
+}
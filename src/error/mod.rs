@@ -0,0 +1,109 @@
+use thiserror::Error;
+
+/// Failures that map to a specific process exit code. Commands return these
+/// (directly or as context on another error) and `main` turns them into the
+/// exit code; anything else exits with 1.
+#[derive(Debug, Error)]
+pub enum Failure {
+    /// VS Code or Git is missing; the command has already listed what
+    #[error("Prerequisites not met")]
+    MissingPrerequisites,
+
+    /// A version, manifest, or binary could not be fetched remotely or locally
+    #[error("{0}")]
+    Download(String),
+
+    /// A binary was fetched but did not match the manifest checksum
+    #[error("{0}")]
+    ChecksumMismatch(String),
+
+    /// Deploying settings, certificates, extensions, or environment failed
+    #[error("{0}")]
+    Configuration(String),
+
+    /// A manifest's signature was missing or didn't verify
+    #[error("{0}")]
+    Signature(String),
+
+    #[error("{0}")]
+    UnknownTool(String),
+
+    /// Too little free disk space for a download and install
+    #[error("{0}")]
+    DiskSpace(String),
+
+    /// `configure --check` found the configuration differs from the
+    /// templates; the differences have already been listed
+    #[error("{0}")]
+    Drifted(String),
+
+    /// Another run holds the lock on this user's installation
+    #[error("{0}")]
+    Locked(String),
+
+    /// A prompt was needed but prompts are turned off; the message names
+    /// the flag that answers it
+    #[error("{0}")]
+    NeedsInput(String),
+
+    /// The user declined a confirmation prompt
+    #[error("Aborted")]
+    Aborted,
+
+    /// Ctrl+C stopped the command, which has said what it had changed
+    #[error("Interrupted")]
+    Interrupted,
+
+    /// A failure the command has already reported in full, such as failed
+    /// `doctor` checks
+    #[error("{0}")]
+    Reported(String),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Reported(_) => 1,
+            Failure::MissingPrerequisites => 2,
+            Failure::Download(_) => 3,
+            Failure::ChecksumMismatch(_) => 4,
+            Failure::Configuration(_) => 5,
+            Failure::UnknownTool(_) => 6,
+            Failure::Signature(_) => 7,
+            Failure::DiskSpace(_) => 8,
+            Failure::Drifted(_) => 9,
+            Failure::Locked(_) => 10,
+            Failure::NeedsInput(_) => 11,
+            Failure::Aborted | Failure::Interrupted => 130,
+        }
+    }
+
+    /// Whether the user has already seen this failure, so `main` shouldn't
+    /// print it again
+    pub fn is_reported(&self) -> bool {
+        matches!(
+            self,
+            Failure::MissingPrerequisites
+                | Failure::Aborted
+                | Failure::Interrupted
+                | Failure::Reported(_)
+                | Failure::Drifted(_)
+        )
+    }
+}
+
+/// Exit code for an error returned from a command
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    // A prompt that couldn't be asked is what a script needs to hear about,
+    // whichever step it came up in
+    let needed_input = error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<Failure>(),
+            Some(Failure::NeedsInput(_))
+        )
+    });
+    if needed_input {
+        return Failure::NeedsInput(String::new()).exit_code();
+    }
+    error.downcast_ref::<Failure>().map_or(1, Failure::exit_code)
+}
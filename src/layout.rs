@@ -0,0 +1,192 @@
+//! Older versions kept downloads, state files, and settings backups in the
+//! Claude config directory. Each now has a directory of its own (see
+//! `PlatformPaths`), and the first run after an upgrade moves whatever is
+//! still in the old place there.
+
+use anyhow::{Context, Result};
+use std::io;
+use std::path::Path;
+
+use crate::output::reporter;
+use crate::platform::PlatformPaths;
+use crate::state::State;
+
+/// Move anything left in the old layout. What can't be moved stays where it
+/// is, after a warning, and is tried again on the next run.
+pub fn migrate(paths: &PlatformPaths) {
+    let old = &paths.claude_config_dir;
+    let old_backups = old.join("backups");
+    let rebase = old_backups != paths.backups_dir && old_backups.exists();
+    let mut moves = vec![
+        (old.join("downloads"), paths.downloads_dir.clone()),
+        (old_backups.clone(), paths.backups_dir.clone()),
+    ];
+    moves.extend(State::files_in(old).into_iter().map(|file| {
+        let to = paths.state_dir.join(file.file_name().unwrap_or_default());
+        (file, to)
+    }));
+
+    for (from, to) in moves {
+        if from == to || !from.exists() {
+            continue;
+        }
+        tracing::debug!("moving {} to {}", from.display(), to.display());
+        if let Err(e) = move_entry(&from, &to) {
+            reporter().warn(format!(
+                "Couldn't move {} to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            ));
+        }
+    }
+
+    if rebase {
+        if let Err(e) = rebase_backups(&paths.state_dir, &old_backups, &paths.backups_dir) {
+            reporter().warn(format!("{:#}", e));
+        }
+    }
+}
+
+/// Rename `from` to `to`, or copy it and remove the original where they're
+/// on different filesystems. A directory is merged into one already at
+/// `to`, where a file of the same name is the newer copy and wins.
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    if !to.exists() {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if std::fs::rename(from, to).is_ok() {
+            return Ok(());
+        }
+    }
+
+    if from.symlink_metadata()?.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            move_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return std::fs::remove_dir(from);
+    }
+
+    if !to.exists() {
+        std::fs::copy(from, to)?;
+    }
+    std::fs::remove_file(from)
+}
+
+/// Point the backups each state file in `state_dir` records under `from` at
+/// `to`, where they were moved
+fn rebase_backups(state_dir: &Path, from: &Path, to: &Path) -> Result<()> {
+    for path in State::files_in(state_dir) {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let Ok(mut state) = serde_json::from_str::<State>(&content) else {
+            // Left for `State::load` to report when the file is used
+            continue;
+        };
+        if state.rebase_backups(from, to) {
+            std::fs::write(&path, serde_json::to_string_pretty(&state)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Backup, BackupFile};
+
+    fn paths(home: &Path) -> PlatformPaths {
+        PlatformPaths {
+            home_dir: home.to_path_buf(),
+            claude_config_dir: home.join(".claude"),
+            app_data_dir: home.join(".config"),
+            certs_dir: home.join("certs"),
+            vscode_user_dir: None,
+            downloads_dir: home.join(".cache/code-assist/downloads"),
+            state_dir: home.join(".local/state/code-assist"),
+            backups_dir: home.join(".local/state/code-assist/backups"),
+        }
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn the_old_layout_moves_to_the_new_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = paths(dir.path());
+        let claude = &paths.claude_config_dir;
+        let old_copy = claude.join("backups/20260101T000000Z/claude/settings.json");
+        write(&claude.join("downloads/claude-2.1.0"), "binary");
+        write(&old_copy, "{}");
+        write(&claude.join("settings.json"), "{}");
+        let mut state = State {
+            backups: vec![Backup {
+                timestamp: "20260101T000000Z".to_string(),
+                files: vec![BackupFile {
+                    original: claude.join("settings.json"),
+                    copy: old_copy,
+                }],
+            }],
+            ..State::default()
+        };
+        write(
+            &claude.join(".code-assist-state.claude-code.json"),
+            &serde_json::to_string(&state).unwrap(),
+        );
+        state.backups.clear();
+        write(
+            &claude.join(".code-assist-state.json"),
+            &serde_json::to_string(&state).unwrap(),
+        );
+
+        migrate(&paths);
+
+        assert!(paths.downloads_dir.join("claude-2.1.0").exists());
+        assert!(paths.state_dir.join(".code-assist-state.json").exists());
+        assert!(!claude.join("downloads").exists());
+        assert!(!claude.join("backups").exists());
+        assert!(State::files_in(claude).is_empty());
+        assert!(claude.join("settings.json").exists());
+
+        let state = State::load(&paths, "claude-code").unwrap().unwrap();
+        let copy = &state.backups[0].files[0].copy;
+        assert_eq!(
+            copy,
+            &paths
+                .backups_dir
+                .join("20260101T000000Z/claude/settings.json")
+        );
+        assert!(copy.exists());
+    }
+
+    #[test]
+    fn files_already_in_the_new_place_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = paths(dir.path());
+        write(
+            &paths.claude_config_dir.join("downloads/manifest.json"),
+            "old",
+        );
+        write(
+            &paths.claude_config_dir.join("downloads/claude-2.0.0"),
+            "binary",
+        );
+        write(&paths.downloads_dir.join("manifest.json"), "new");
+
+        migrate(&paths);
+
+        assert_eq!(
+            std::fs::read_to_string(paths.downloads_dir.join("manifest.json")).unwrap(),
+            "new"
+        );
+        assert!(paths.downloads_dir.join("claude-2.0.0").exists());
+        assert!(!paths.claude_config_dir.join("downloads").exists());
+    }
+}
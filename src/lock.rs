@@ -0,0 +1,113 @@
+//! One run at a time: commands that change the machine take an exclusive
+//! lock in the Claude config dir, so two installs can't interleave writes
+//! to settings.json or the downloads cache
+//!
+//! The lock is the operating system's, so it goes away with the process
+//! however that ends. The holder's PID is kept in a file next to it, for
+//! saying who holds it; one left behind by a run that crashed is stale.
+
+use anyhow::{Context, Result};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cancel;
+use crate::error::Failure;
+use crate::output::reporter;
+use crate::platform::PlatformPaths;
+
+/// File the lock is taken on
+const LOCK_FILE: &str = ".code-assist.lock";
+
+/// File holding the PID of the run that holds the lock
+const PID_FILE: &str = ".code-assist.pid";
+
+/// How often to try again while waiting for another run
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Held until dropped
+pub struct RunLock {
+    file: File,
+    pid_file: PathBuf,
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        // A PID file outliving its run is how a crash shows
+        std::fs::remove_file(&self.pid_file).ok();
+        self.file.unlock().ok();
+    }
+}
+
+/// Take the lock, waiting for another run to release it with `wait` and
+/// failing with [`Failure::Locked`] otherwise. `None` where the filesystem
+/// can't lock, after a warning, rather than refusing to run.
+pub fn acquire(paths: &PlatformPaths, wait: bool) -> Result<Option<RunLock>> {
+    let dir = &paths.claude_config_dir;
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let lock_path = dir.join(LOCK_FILE);
+    let pid_file = dir.join(PID_FILE);
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+
+    let mut waiting = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => {
+                reporter().warn(format!(
+                    "Could not lock {} ({}); not guarding against another run at the same time",
+                    lock_path.display(),
+                    e
+                ));
+                return Ok(None);
+            }
+        }
+
+        let holder = describe_holder(&pid_file);
+        if !wait {
+            return Err(Failure::Locked(format!(
+                "Another code-assist run ({}) is changing this installation; \
+                 wait for it to finish or pass --wait",
+                holder
+            ))
+            .into());
+        }
+        if !waiting {
+            reporter().info(format!(
+                "Waiting for another code-assist run ({})...",
+                holder
+            ));
+            waiting = true;
+        }
+        cancel::check()?;
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    if let Some(pid) = read_pid(&pid_file).filter(|&pid| pid != std::process::id()) {
+        reporter().detail(format!(
+            "Cleared a stale lock left by process {}, which is no longer running",
+            pid
+        ));
+    }
+    std::fs::write(&pid_file, std::process::id().to_string())
+        .with_context(|| format!("Failed to write {}", pid_file.display()))?;
+
+    Ok(Some(RunLock { file, pid_file }))
+}
+
+fn read_pid(pid_file: &Path) -> Option<u32> {
+    std::fs::read_to_string(pid_file).ok()?.trim().parse().ok()
+}
+
+fn describe_holder(pid_file: &Path) -> String {
+    match read_pid(pid_file) {
+        Some(pid) => format!("process {}", pid),
+        None => "process unknown".to_string(),
+    }
+}
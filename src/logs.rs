@@ -0,0 +1,192 @@
+//! The persistent log. Every run appends its debug-level tracing output to
+//! `~/.claude/logs/code-assist.log`, whatever the console shows, so a failed
+//! install can be looked into after the terminal is gone. When the file
+//! reaches half the size limit it becomes `code-assist.log.1`, replacing
+//! the one before, which keeps the two within the limit.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+
+use crate::platform::PlatformPaths;
+
+/// Total size of the log files, in megabytes, unless configured
+pub const DEFAULT_MAX_SIZE_MB: u64 = 10;
+
+const LOG_FILE: &str = "code-assist.log";
+
+/// Starts the line each run opens with, which `logs --last-run` looks for
+const RUN_STARTED: &str = "run started:";
+
+/// Flags whose value never goes in the log
+const SECRET_FLAGS: [&str; 1] = ["--auth-token"];
+
+/// Where the log is written
+pub fn path(paths: &PlatformPaths) -> PathBuf {
+    paths.claude_config_dir.join("logs").join(LOG_FILE)
+}
+
+/// The file before the current one, rotated out when it got too big
+fn previous_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+/// What the log records: everything of ours down to debug, which includes
+/// every external command and its exit status, and warnings from libraries
+pub fn filter() -> Targets {
+    Targets::new()
+        .with_target("code_assist", Level::DEBUG)
+        .with_default(Level::WARN)
+}
+
+/// The log file, opened for appending, or `None` when logging is turned off
+/// (`max_size_mb = 0`) or the file can't be written
+pub fn open(paths: &PlatformPaths, max_size_mb: u64) -> Option<RotatingFile> {
+    if max_size_mb == 0 {
+        return None;
+    }
+    RotatingFile::open(path(paths), max_size_mb * 1024 * 1024).ok()
+}
+
+/// Record the start of a run with its command line, secrets left out
+pub fn run_started() {
+    tracing::info!(
+        "{} code-assist {} {}",
+        RUN_STARTED,
+        env!("CARGO_PKG_VERSION"),
+        redacted(std::env::args().skip(1)).join(" ")
+    );
+}
+
+/// `args` with the values of secret flags replaced
+fn redacted(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut hide_next = false;
+    args.map(|arg| {
+        if std::mem::take(&mut hide_next) {
+            return "<redacted>".to_string();
+        }
+        for flag in SECRET_FLAGS {
+            if arg == flag {
+                hide_next = true;
+            } else if arg.starts_with(&format!("{}=", flag)) {
+                return format!("{}=<redacted>", flag);
+            }
+        }
+        arg
+    })
+    .collect()
+}
+
+/// A log file that moves itself aside to `<name>.1` once it grows past
+/// half of `max_bytes`
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            max_bytes,
+            file: Some(file),
+            size,
+        })
+    }
+
+    /// Move the file aside and start a new one. If another run got there
+    /// first, or the rename fails, carry on with whatever file is in place.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        // Let go of the file first, which Windows needs to rename it
+        self.file = None;
+        // Not logged: this runs while the log holds its lock
+        std::fs::rename(&self.path, previous_path(&self.path)).ok();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes / 2 {
+            self.rotate()?;
+        }
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| std::io::Error::other("log file is closed"))?;
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The logged lines, oldest first, across the rotated file and the current
+/// one. With `last_run`, only those from the most recent run.
+pub fn read(paths: &PlatformPaths, last_run: bool) -> Result<Vec<String>> {
+    let path = path(paths);
+    let mut lines = Vec::new();
+    for file in [previous_path(&path), path] {
+        match std::fs::read_to_string(&file) {
+            Ok(content) => lines.extend(content.lines().map(str::to_string)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", file.display())),
+        }
+    }
+    if last_run {
+        if let Some(start) = lines.iter().rposition(|line| line.contains(RUN_STARTED)) {
+            lines.drain(..start);
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_flag_values_are_redacted() {
+        let args = [
+            "install",
+            "--auth-token",
+            "s3cret",
+            "--auth-token=s3cret",
+            "-y",
+        ];
+        assert_eq!(
+            redacted(args.iter().map(|arg| arg.to_string())),
+            [
+                "install",
+                "--auth-token",
+                "<redacted>",
+                "--auth-token=<redacted>",
+                "-y"
+            ]
+        );
+    }
+}
@@ -1,191 +1,2140 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches};
+use clap_complete::Shell;
 use console::style;
-use tracing_subscriber::EnvFilter;
+use indicatif::{HumanBytes, HumanDuration};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
+mod cache;
+mod cancel;
 mod cli;
 mod config;
+mod doctor;
 mod download;
+mod environment;
+mod error;
+mod layout;
+mod lock;
+mod logs;
+mod output;
+mod plan;
 mod platform;
 mod prerequisites;
+mod process;
+mod report;
+mod self_update;
+mod setup;
+mod space;
+mod state;
 mod tools;
+mod verify;
 
-use cli::{Cli, Commands};
+use cli::{CacheAction, Cli, Commands, ConfigAction};
+use config::backup::DiffLine;
+use config::{Settings, UserConfig};
+use error::Failure;
+use output::events;
+use output::{confirm, reporter, Verbosity};
+use platform::Host;
+use prerequisites::Prerequisite;
+use tools::{ChannelChoice, ToolOptions};
 
-fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+fn main() -> ExitCode {
+    let bus = Rc::new(events::Bus::default());
+    bus.subscribe(Rc::new(output::Console));
+    events::with_bus(bus, run_main)
+}
+
+fn run_main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        // Whatever failed after Ctrl+C failed because of it
+        Err(_) if cancel::is_cancelled() => {
+            tracing::warn!("stopped by Ctrl+C");
+            cache::remove_own_temp_files();
+            cancel::report_aborted();
+            ExitCode::from(Failure::Interrupted.exit_code())
+        }
+        Err(e) => {
+            tracing::warn!("failed with exit code {}: {:#}", error::exit_code(&e), e);
+            let failure = e.downcast_ref::<Failure>();
+            if !failure.is_some_and(Failure::is_reported) {
+                eprintln!("Error: {:?}", e);
+            }
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+/// Start recording the events of the run for `--report`
+fn record() -> Rc<report::Recorder> {
+    let recorder = report::Recorder::start();
+    events::subscribe(recorder.clone());
+    recorder
+}
+
+fn run() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let settings = Settings::resolve(&cli, &matches, UserConfig::load()?);
+
+    let paths = platform::init_paths(&settings.path_overrides())?;
+    platform::init_env_mode(cli.env_mode);
+
+    // Initialize logging. RUST_LOG still applies; -v/-vv raise our own level
+    // on top of it. The log file records every run but the ones reading it.
+    let mut filter = EnvFilter::from_default_env();
+    match cli.verbose {
+        0 => {}
+        1 => filter = filter.add_directive("code_assist=debug".parse()?),
+        _ => filter = filter.add_directive("code_assist=trace".parse()?),
+    }
+    let ansi = !settings.no_color.value;
+    let log_file = match &cli.command {
+        Some(Commands::Logs { .. }) => None,
+        _ => logs::open(&paths, settings.log_max_size_mb.value),
+    };
+    let logging = log_file.is_some();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(ansi)
+                .with_writer(std::io::stderr)
+                .with_filter(filter),
+        )
+        .with(log_file.map(|file| {
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(logs::filter())
+        }))
         .init();
+    if logging {
+        logs::run_started();
+    }
 
-    let cli = Cli::parse();
+    let verbosity = if cli.quiet {
+        Verbosity::Quiet
+    } else if cli.verbose > 0 {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    // A report written to stdout leaves the console output to stderr
+    let report_to_stdout = matches!(
+        &cli.command,
+        Some(Commands::Install { report: Some(path), .. } | Commands::Uninstall { report: Some(path), .. })
+            if path.as_os_str() == "-"
+    );
+    output::init(
+        verbosity,
+        settings.no_color.value,
+        report_to_stdout,
+        settings.non_interactive.value,
+    );
+    cancel::install_handler();
+    self_update::remove_leftovers();
+    layout::migrate(&paths);
 
     // Check platform support - warn on Linux but allow for development
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
-        eprintln!(
-            "{} Warning: This platform is not officially supported. Some features may not work.",
-            style("!").yellow().bold()
+        reporter().notice(
+            "Warning: This platform is not officially supported. Some features may not work.",
         );
     }
 
-    match cli.command {
-        Commands::Check => cmd_check(),
-        Commands::Install { tool } => cmd_install(&tool, cli.yes),
-        Commands::Uninstall { tool } => cmd_uninstall(&tool, cli.yes),
-        Commands::Configure { tool } => cmd_configure(&tool),
-        Commands::List => cmd_list(),
+    if cli.dry_run {
+        reporter().notice("Dry run: no changes will be made\n");
+    }
+
+    // A dry run never changes anything, so there is nothing to confirm
+    let skip_confirm = settings.yes.value || cli.dry_run;
+
+    download::init(download::ClientOptions {
+        base_urls: settings.download_base_url.value.clone(),
+        proxy: settings.proxy.value.clone(),
+        retry: download::RetryPolicy {
+            attempts: settings.retries.value,
+            base_delay: settings.retry_delay.value,
+        },
+        connect_timeout: settings.timeout.value.map(Duration::from_secs),
+        read_timeout: settings.timeout.value.map(Duration::from_secs),
+        ca_certs: download::ca_cert_files(&cli.ca_cert, &paths.certs_dir),
+        manifest_key: settings.manifest_key.value.clone(),
+        allow_unsigned: cli.allow_unsigned,
+        auth_token: settings.auth_token.value.clone(),
+    })?;
+
+    let options = ToolOptions {
+        local_dir: tools::resolve_local_dir(settings.local_dir.value.as_deref())?,
+        offline: settings.offline.value,
+        skip_space_check: cli.skip_space_check,
+        force_extensions: cli.force_extensions,
+        force: cli.force,
+        edited_keys: if cli.force_settings {
+            config::EditedKeys::Overwrite
+        } else if cli.interactive {
+            config::EditedKeys::Ask
+        } else {
+            config::EditedKeys::Keep
+        },
+        link: cli.link,
+        skip_cert_import: cli.skip_cert_import,
+        force_path: cli.force_path,
+        editor: cli.editor,
+        profile: None,
+        keep_backups: settings.keep_backups.value,
+        node_version: settings.node_version.value.clone(),
+        system: false,
+        assume_yes: skip_confirm,
+        default_version: None,
+        default_channel: None,
+        dependencies: Vec::new(),
+        overrides: settings.tools.clone(),
+        paths,
+        host: Host::system(),
+    };
+
+    let Some(command) = cli.command else {
+        // Scripts that forget the subcommand get the usual help instead of a
+        // prompt that would hang waiting for input
+        if !output::is_interactive() || !std::io::stdout().is_terminal() {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::MissingSubcommand,
+                    "'code-assist' requires a subcommand but one was not provided",
+                )
+                .exit();
+        }
+        let _lock = (!cli.dry_run)
+            .then(|| lock::acquire(&options.paths, cli.wait))
+            .transpose()?;
+        return cmd_interactive(&options, skip_confirm, cli.dry_run);
+    };
+
+    // Commands that change the installation take turns; dry runs and
+    // checks only read it
+    let changes_installation = match &command {
+        Commands::Install { .. }
+        | Commands::Update { .. }
+        | Commands::Uninstall { .. }
+        | Commands::Rollback { .. }
+        | Commands::Restore { .. }
+        | Commands::Import { .. } => true,
+        Commands::Configure { check, .. } => !check,
+        _ => false,
+    };
+    let _lock = (changes_installation && !cli.dry_run)
+        .then(|| lock::acquire(&options.paths, cli.wait))
+        .transpose()?;
+
+    match command {
+        Commands::Check { tool, json } => cmd_check(&options, tool.as_deref(), json),
+        Commands::Install {
+            tool,
+            all,
+            version,
+            channel,
+            switch_channel,
+            profile,
+            system,
+            report,
+        } => {
+            let recorder = report.is_some().then(record);
+            let result = cmd_install(
+                &ToolOptions {
+                    profile,
+                    system,
+                    ..options
+                },
+                tool.as_deref(),
+                all,
+                version.as_deref(),
+                ChannelChoice {
+                    requested: channel,
+                    allow_switch: switch_channel,
+                },
+                skip_confirm,
+                cli.dry_run,
+            );
+            match (report, recorder) {
+                (Some(path), Some(recorder)) => recorder.save(&path, result),
+                _ => result,
+            }
+        }
+        Commands::Update {
+            tool,
+            channel,
+            switch_channel,
+        } => cmd_update(
+            &options,
+            tool.as_deref(),
+            ChannelChoice {
+                requested: channel,
+                allow_switch: switch_channel,
+            },
+            skip_confirm,
+            cli.dry_run,
+        ),
+        Commands::Uninstall {
+            tool,
+            purge,
+            system,
+            report,
+        } => {
+            let recorder = report.is_some().then(record);
+            let result = cmd_uninstall(
+                &ToolOptions { system, ..options },
+                &tool,
+                purge,
+                skip_confirm,
+                cli.dry_run,
+            );
+            match (report, recorder) {
+                (Some(path), Some(recorder)) => recorder.save(&path, result),
+                _ => result,
+            }
+        }
+        Commands::Rollback { tool, to } => {
+            cmd_rollback(&options, &tool, to.as_deref(), skip_confirm, cli.dry_run)
+        }
+        Commands::Restore { tool, timestamp } => cmd_restore(
+            &options,
+            &tool,
+            timestamp.as_deref(),
+            skip_confirm,
+            cli.dry_run,
+        ),
+        Commands::Configure {
+            tool,
+            profile,
+            check,
+            json,
+        } => {
+            let options = ToolOptions { profile, ..options };
+            if check {
+                cmd_configure_check(&options, &tool, json)
+            } else {
+                cmd_configure(&options, &tool, cli.dry_run)
+            }
+        }
+        Commands::Versions { tool, limit, json } => cmd_versions(&options, &tool, limit, json),
+        Commands::Verify { json } => cmd_verify(&options, json),
+        Commands::List { json } => cmd_list(&options, json),
+        Commands::Status { tool, json } => cmd_status(&options, tool.as_deref(), json),
+        Commands::Export { output } => cmd_export(&options, output.as_deref()),
+        Commands::Import { file } => cmd_import(&options, &file, skip_confirm, cli.dry_run),
+        Commands::Doctor => cmd_doctor(&options, &settings),
+        Commands::Env { tool, shell } => cmd_env(&options, tool.as_deref(), shell),
+        Commands::Logs {
+            tail,
+            last_run,
+            path,
+        } => cmd_logs(&options, tail, last_run, path),
+        Commands::SelfUpdate { check, url } => cmd_self_update(
+            &options,
+            url.as_deref().unwrap_or(&settings.self_update_url.value),
+            check,
+            skip_confirm,
+            cli.dry_run,
+        ),
+        Commands::Config {
+            action: ConfigAction::Show,
+        } => cmd_config_show(&settings, &options),
+        Commands::Cache { action } => cmd_cache(action, cli.dry_run),
+        Commands::Completions { shell } => cmd_completions(&options, shell),
     }
 }
 
-fn cmd_check() -> Result<()> {
-    println!(
-        "{} Checking prerequisites...\n",
-        style("→").cyan().bold()
-    );
+/// Check what `tool_name` needs, or every registered tool if none is given
+fn cmd_check(options: &ToolOptions, tool_name: Option<&str>, json: bool) -> Result<()> {
+    let required = match tool_name {
+        Some(name) => tools::get_tool(name, options)?.prerequisites(),
+        None => tools::prerequisites_of(&tools::list_tools(options)),
+    };
+
+    // The local fallback can stand in for the download server, so not
+    // reaching it is worth a warning but doesn't fail the check
+    let network = (!options.offline).then(download::check_reachability);
 
-    let vscode_ok = prerequisites::check_vscode();
-    let git_ok = prerequisites::check_git();
+    if json {
+        let prerequisites =
+            prerequisites::evaluate(&*options.host.runner, &required, &options.paths.home_dir);
+        let ok = prerequisites.iter().all(|p| p.installed);
+        let report = serde_json::json!({
+            "ok": ok,
+            "prerequisites": prerequisites,
+            "network": network,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if !ok {
+            return Err(Failure::MissingPrerequisites.into());
+        }
+        return Ok(());
+    }
 
-    println!();
+    let out = reporter();
+    out.heading("Checking prerequisites...");
+    out.blank();
 
-    if !vscode_ok || !git_ok {
-        println!(
-            "{} Some prerequisites are missing.\n",
-            style("✗").red().bold()
-        );
-        platform::print_install_instructions();
-        std::process::exit(1);
+    let missing = prerequisites::check(&*options.host.runner, &required, &options.paths.home_dir);
+    for result in network.iter().flatten() {
+        let diagnostic = doctor::reachability_diagnostic(result);
+        if diagnostic.passed {
+            diagnostic.print();
+        } else {
+            out.warn(format!("{} {}", diagnostic.name, diagnostic.detail));
+        }
     }
 
-    println!(
-        "{} All prerequisites satisfied!",
-        style("✓").green().bold()
-    );
+    out.blank();
+
+    if !missing.is_empty() {
+        out.finish_failure("Some prerequisites are missing.");
+        out.blank();
+        platform::print_install_instructions(&missing);
+        return Err(Failure::MissingPrerequisites.into());
+    }
+
+    out.finish_success("All prerequisites satisfied!");
     Ok(())
 }
 
-fn cmd_install(tool_name: &str, skip_confirm: bool) -> Result<()> {
-    // First check prerequisites
-    println!(
-        "{} Checking prerequisites...",
-        style("→").cyan().bold()
-    );
+fn cmd_install(
+    options: &ToolOptions,
+    tool_name: Option<&str>,
+    all: bool,
+    version: Option<&str>,
+    channel: ChannelChoice,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    check_elevated(options, dry_run)?;
 
-    let vscode_ok = prerequisites::check_vscode();
-    let git_ok = prerequisites::check_git();
+    if all {
+        return install_all(options, channel, skip_confirm, dry_run);
+    }
 
-    if !vscode_ok || !git_ok {
-        println!(
-            "\n{} Prerequisites not met.\n",
-            style("✗").red().bold()
-        );
-        platform::print_install_instructions();
-        std::process::exit(1);
+    // Get the tool, after the ones it depends on that aren't installed yet
+    let tool = tools::get_tool(tool_name.unwrap_or_default(), options)?;
+    let mut needed = Vec::new();
+    let mut present = Vec::new();
+    for dependency in tools::with_dependencies(&[tool.name()], options)? {
+        if dependency.name() != tool.name() && dependency.is_installed()? {
+            present.push(dependency);
+        } else {
+            needed.push(dependency);
+        }
     }
 
-    println!(
-        "{} Prerequisites satisfied.\n",
-        style("✓").green().bold()
-    );
+    require_prerequisites(&tools::prerequisites_of(&needed), options)?;
+    for dependency in &present {
+        out.skip(format!(
+            "{} is already installed",
+            dependency.display_name()
+        ));
+    }
 
-    // Get the tool
-    let tool = tools::get_tool(tool_name)?;
+    // Say what will change before asking; a dry run prints its own, more
+    // detailed, plan instead
+    if !dry_run {
+        for (i, dependency) in needed.iter().enumerate() {
+            let plan = if dependency.name() == tool.name() {
+                tool.plan(version, channel)?
+            } else {
+                dependency.plan(None, ChannelChoice::default())?
+            };
+            if i > 0 {
+                out.blank();
+            }
+            plan.print(dependency.display_name());
+        }
+        if !skip_confirm {
+            out.blank();
+        }
+    }
 
-    if !skip_confirm {
-        println!(
+    let names: Vec<String> = needed
+        .iter()
+        .map(|t| style(t.display_name()).cyan().to_string())
+        .collect();
+    let message = if options.system {
+        format!(
+            "This will install {} for every user of this machine.",
+            names.join(", ")
+        )
+    } else {
+        format!(
             "This will install {} and configure your environment.",
-            style(tool.display_name()).cyan()
-        );
-        print!("Continue? [Y/n] ");
-        std::io::Write::flush(&mut std::io::stdout())?;
+            names.join(", ")
+        )
+    };
+    if !skip_confirm && !confirm(&message)? {
+        return Err(Failure::Aborted.into());
+    }
 
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+    for dependency in &needed {
+        cancel::check()?;
+        out.blank();
+        events::tool(dependency.name());
+        if dependency.name() == tool.name() {
+            tool.install(version, channel, dry_run)?;
+        } else {
+            dependency
+                .install(None, ChannelChoice::default(), dry_run)
+                .with_context(|| {
+                    format!(
+                        "Failed to install {}, which {} depends on",
+                        dependency.display_name(),
+                        tool.display_name()
+                    )
+                })?;
+        }
+    }
 
-        if !input.is_empty() && input != "y" && input != "yes" {
-            println!("Aborted.");
-            return Ok(());
+    out.blank();
+    if dry_run {
+        out.finish_success(format!(
+            "Dry run complete, {} was not installed",
+            tool.display_name()
+        ));
+    } else {
+        out.finish_success(format!("{} installed successfully!", tool.display_name()));
+    }
+
+    Ok(())
+}
+
+/// Check `required`, stopping with install instructions if any is missing
+fn require_prerequisites(required: &[Prerequisite], options: &ToolOptions) -> Result<()> {
+    let out = reporter();
+    out.heading("Checking prerequisites...");
+
+    let missing = prerequisites::check(&*options.host.runner, required, &options.paths.home_dir);
+    if !missing.is_empty() {
+        out.blank();
+        out.finish_failure("Prerequisites not met.");
+        out.blank();
+        platform::print_install_instructions(&missing);
+        return Err(Failure::MissingPrerequisites.into());
+    }
+
+    out.done("Prerequisites satisfied.");
+    out.blank();
+    Ok(())
+}
+
+/// Stop before changing anything when `--system` was asked for without the
+/// rights machine-wide locations need
+fn check_elevated(options: &ToolOptions, dry_run: bool) -> Result<()> {
+    if !options.system || dry_run || platform::is_elevated() {
+        return Ok(());
+    }
+    let how = if cfg!(target_os = "windows") {
+        "from a terminal opened with 'Run as administrator'"
+    } else {
+        "with sudo"
+    };
+    anyhow::bail!(
+        "--system changes files for every user; run it again {}",
+        how
+    )
+}
+
+/// Install every registered tool in sequence, continuing past failures and
+/// summarizing the results at the end
+fn install_all(
+    options: &ToolOptions,
+    channel: ChannelChoice,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    let names: Vec<String> = tools::list_tools(options)
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let tools = tools::with_dependencies(&names, options)?;
+    require_prerequisites(&tools::prerequisites_of(&tools), options)?;
+    if !dry_run {
+        for (i, tool) in tools.iter().enumerate() {
+            if i > 0 {
+                out.blank();
+            }
+            tool.plan(None, channel)?.print(tool.display_name());
+        }
+        if !skip_confirm {
+            out.blank();
         }
     }
 
-    println!();
-    tool.install()?;
+    let names: Vec<String> = tools
+        .iter()
+        .map(|t| style(t.display_name()).cyan().to_string())
+        .collect();
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will install {} and configure your environment.",
+            names.join(", ")
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
 
-    println!(
-        "\n{} {} installed successfully!",
-        style("✓").green().bold(),
-        tool.display_name()
-    );
+    let mut results = Vec::new();
+    for tool in &tools {
+        cancel::check()?;
+        out.blank();
+        events::tool(tool.name());
+        let result = tool.install(None, channel, dry_run);
+        if let Err(e) = &result {
+            events::fail_step();
+            out.error(format!("{} failed: {:#}", tool.display_name(), e));
+        }
+        results.push((tool.display_name(), result));
+    }
+
+    let width = results.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    out.blank();
+    out.heading("Summary:");
+    for (name, result) in &results {
+        let status = match result {
+            Ok(()) if dry_run => style("planned".to_string()).green(),
+            Ok(()) => style("installed".to_string()).green(),
+            Err(_) => style("failed".to_string()).red(),
+        };
+        out.output(format!("  {:width$}  {}", name, status, width = width));
+    }
+
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+
+    out.blank();
+    if failed > 0 {
+        let message = format!("{} of {} tool(s) failed to install", failed, results.len());
+        out.finish_failure(&message);
+        return Err(Failure::Reported(message).into());
+    }
+
+    if dry_run {
+        out.finish_success("Dry run complete, nothing was installed");
+    } else {
+        out.finish_success(format!("All {} tool(s) installed successfully!", results.len()));
+    }
 
     Ok(())
 }
 
-fn cmd_uninstall(tool_name: &str, skip_confirm: bool) -> Result<()> {
-    let tool = tools::get_tool(tool_name)?;
+/// Update one tool, or every installed tool, to the latest version on its
+/// channel, continuing past failures and summarizing the results at the end
+fn cmd_update(
+    options: &ToolOptions,
+    tool_name: Option<&str>,
+    channel: ChannelChoice,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+
+    let tools = match tool_name {
+        Some(name) => vec![tools::get_tool(name, options)?],
+        None => {
+            let mut installed = Vec::new();
+            for tool in tools::list_tools(options) {
+                if tool.is_installed()? {
+                    installed.push(tool);
+                }
+            }
+            installed
+        }
+    };
+
+    if tools.is_empty() {
+        out.line("No tools are installed; nothing to update.");
+        return Ok(());
+    }
+    require_prerequisites(&tools::prerequisites_of(&tools), options)?;
+
+    let names: Vec<String> = tools
+        .iter()
+        .map(|t| style(t.display_name()).cyan().to_string())
+        .collect();
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will update {} and reconfigure your environment.",
+            names.join(", ")
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
 
-    if !skip_confirm {
-        println!(
-            "This will uninstall {} and remove its configuration.",
+    // A single tool's error goes straight to `main` for its exit code
+    if let [tool] = tools.as_slice() {
+        out.blank();
+        let updated = tool.update(channel, dry_run)?;
+        out.blank();
+        if dry_run {
+            out.finish_success("Dry run complete, nothing was updated");
+        } else if updated {
+            out.finish_success(format!("{} updated successfully!", tool.display_name()));
+        } else {
+            out.finish_success(format!("{} is up to date", tool.display_name()));
+        }
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    for tool in &tools {
+        out.blank();
+        let result = tool.update(channel, dry_run);
+        if let Err(e) = &result {
+            out.error(format!("{} failed: {:#}", tool.display_name(), e));
+        }
+        results.push((tool.display_name(), result));
+    }
+
+    let width = results
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+
+    out.blank();
+    out.heading("Summary:");
+    for (name, result) in &results {
+        let status = match result {
+            Ok(true) if dry_run => style("planned".to_string()).green(),
+            Ok(true) => style("updated".to_string()).green(),
+            Ok(false) => style("up to date".to_string()).dim(),
+            Err(_) => style("failed".to_string()).red(),
+        };
+        out.output(format!("  {:width$}  {}", name, status, width = width));
+    }
+
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+
+    out.blank();
+    if failed > 0 {
+        let message = format!("{} of {} tool(s) failed to update", failed, results.len());
+        out.finish_failure(&message);
+        return Err(Failure::Reported(message).into());
+    }
+
+    if dry_run {
+        out.finish_success("Dry run complete, nothing was updated");
+    } else if results.iter().any(|(_, r)| matches!(r, Ok(true))) {
+        out.finish_success("Update complete!");
+    } else {
+        out.finish_success("Everything is up to date");
+    }
+
+    Ok(())
+}
+
+/// Guided flow for running without a subcommand: check prerequisites, let the
+/// user pick tools, then install the missing ones and reconfigure the rest
+fn cmd_interactive(options: &ToolOptions, skip_confirm: bool, dry_run: bool) -> Result<()> {
+    let out = reporter();
+
+    let tools = tools::list_tools(options);
+    require_prerequisites(&tools::prerequisites_of(&tools), options)?;
+    let mut labels = Vec::new();
+    let mut installed = Vec::new();
+    for tool in &tools {
+        let is_installed = tool.is_installed()?;
+        let status = match (is_installed, tool.installed_version()?) {
+            (true, Some(version)) => format!("installed {}, reconfigure", version),
+            (true, None) => "installed, reconfigure".to_string(),
+            (false, _) => "not installed".to_string(),
+        };
+        labels.push(format!("{} [{}]", tool.display_name(), status));
+        installed.push(is_installed);
+    }
+
+    // Preselect what isn't installed yet; reconfiguring is opt-in
+    let defaults: Vec<bool> = installed.iter().map(|i| !i).collect();
+    output::require_interactive("a command, such as 'install --tool claude-code'")?;
+    let theme = out.prompt_theme();
+    let selected = dialoguer::MultiSelect::with_theme(theme.as_ref())
+        .with_prompt("Select tools to set up (space to toggle, enter to confirm)")
+        .items(&labels)
+        .defaults(&defaults)
+        .interact()?;
+
+    if selected.is_empty() {
+        out.line("Nothing selected.");
+        return Ok(());
+    }
+
+    let names: Vec<String> = selected
+        .iter()
+        .map(|&i| style(tools[i].display_name()).cyan().to_string())
+        .collect();
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will set up {} and configure your environment.",
+            names.join(", ")
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
+
+    for &i in &selected {
+        let tool = &tools[i];
+        out.blank();
+        if installed[i] {
+            out.heading(format!("Configuring {}...", tool.display_name()));
+            tool.configure(dry_run)?;
+        } else {
+            tool.install(None, ChannelChoice::default(), dry_run)?;
+        }
+    }
+
+    out.blank();
+    if dry_run {
+        out.finish_success("Dry run complete, nothing was changed");
+    } else {
+        out.finish_success("Setup complete!");
+    }
+
+    Ok(())
+}
+
+fn cmd_uninstall(
+    options: &ToolOptions,
+    tool_name: &str,
+    purge: bool,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    check_elevated(options, dry_run)?;
+    let tool = tools::get_tool(tool_name, options)?;
+
+    let dependents: Vec<String> = tools::installed_dependents(tool.as_ref(), options)
+        .iter()
+        .map(|t| t.display_name().to_string())
+        .collect();
+    if !dependents.is_empty() {
+        out.warn(format!(
+            "{} depend{} on {} and may stop working without it",
+            dependents.join(", "),
+            if dependents.len() == 1 { "s" } else { "" },
+            tool.display_name()
+        ));
+    }
+
+    let name = style(tool.display_name()).cyan();
+    let message = if options.system {
+        format!(
+            "This will uninstall the copy of {} installed for every user.",
+            name
+        )
+    } else if purge {
+        format!(
+            "This will uninstall {} and the extensions it installed, and remove its \
+             configuration, certificates, and environment changes.",
+            name
+        )
+    } else {
+        format!(
+            "This will uninstall {} and the extensions it installed.",
+            name
+        )
+    };
+    if !skip_confirm && !confirm(&message)? {
+        return Err(Failure::Aborted.into());
+    }
+
+    out.blank();
+    events::tool(tool.name());
+    tool.uninstall(purge, dry_run)?;
+
+    out.blank();
+    if dry_run {
+        out.finish_success(format!(
+            "Dry run complete, {} was not uninstalled",
+            tool.display_name()
+        ));
+    } else {
+        out.finish_success(format!("{} uninstalled successfully!", tool.display_name()));
+    }
+
+    Ok(())
+}
+
+fn cmd_rollback(
+    options: &ToolOptions,
+    tool_name: &str,
+    to: Option<&str>,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    let tool = tools::get_tool(tool_name, options)?;
+
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will replace the installed {} binary with a previous version.",
             style(tool.display_name()).cyan()
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
+
+    out.blank();
+    tool.rollback(to, dry_run)?;
+
+    out.blank();
+    if dry_run {
+        out.finish_success(format!(
+            "Dry run complete, {} was not rolled back",
+            tool.display_name()
+        ));
+    } else {
+        out.finish_success(format!("{} rolled back successfully!", tool.display_name()));
+    }
+
+    Ok(())
+}
+
+fn cmd_restore(
+    options: &ToolOptions,
+    tool_name: &str,
+    timestamp: Option<&str>,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    let tool = tools::get_tool(tool_name, options)?;
+    let backups = tool.settings_backups()?;
+    if backups.is_empty() {
+        return Err(
+            Failure::Reported(format!("No settings backups for {}", tool.display_name())).into(),
         );
-        print!("Continue? [Y/n] ");
-        std::io::Write::flush(&mut std::io::stdout())?;
+    }
+
+    let labels: Vec<String> = backups
+        .iter()
+        .map(|backup| {
+            let files: Vec<String> = backup
+                .files
+                .iter()
+                .map(|file| file.original.display().to_string())
+                .collect();
+            format!("{}  {}", backup.timestamp, files.join(", "))
+        })
+        .collect();
 
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
+    let backup = match timestamp {
+        Some(timestamp) => backups
+            .iter()
+            .find(|backup| backup.timestamp == timestamp)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No settings backup {}; available: {}",
+                    timestamp,
+                    backups
+                        .iter()
+                        .map(|backup| backup.timestamp.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?,
+        None if !skip_confirm && output::is_interactive() && std::io::stdout().is_terminal() => {
+            let theme = out.prompt_theme();
+            let selected = dialoguer::Select::with_theme(theme.as_ref())
+                .with_prompt("Select a backup to restore")
+                .items(&labels)
+                .default(0)
+                .interact()?;
+            &backups[selected]
+        }
+        None => {
+            out.heading(format!("Settings backups for {}:", tool.display_name()));
+            out.blank();
+            for label in &labels {
+                out.output(format!("  {}", label));
+            }
+            out.blank();
+            &backups[0]
+        }
+    };
 
-        if !input.is_empty() && input != "y" && input != "yes" {
-            println!("Aborted.");
-            return Ok(());
+    // Show what restoring would change, current file first
+    let mut changed = 0;
+    for file in &backup.files {
+        let current = std::fs::read_to_string(&file.original).unwrap_or_default();
+        let saved = std::fs::read_to_string(&file.copy)?;
+        let diff = config::backup::diff_lines(&current, &saved);
+        if diff.iter().all(|line| matches!(line, DiffLine::Same(_))) {
+            continue;
+        }
+        changed += 1;
+        out.info(format!(
+            "{} (current -> backup {}):",
+            style(file.original.display()).cyan(),
+            backup.timestamp
+        ));
+        for line in diff {
+            match line {
+                DiffLine::Same(_) => {}
+                DiffLine::Removed(text) => out.output(style(format!("    - {}", text)).red()),
+                DiffLine::Added(text) => out.output(style(format!("    + {}", text)).green()),
+            }
         }
+        out.blank();
     }
 
-    println!();
-    tool.uninstall()?;
+    if changed == 0 {
+        out.finish_success(format!(
+            "Settings already match the backup from {}",
+            backup.timestamp
+        ));
+        return Ok(());
+    }
 
-    println!(
-        "\n{} {} uninstalled successfully!",
-        style("✓").green().bold(),
-        tool.display_name()
-    );
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will replace {} settings file(s) with the backup from {}.",
+            changed, backup.timestamp
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
+
+    tool.restore_settings(backup, dry_run)?;
 
+    out.blank();
+    if dry_run {
+        out.finish_success("Dry run complete, no settings were restored");
+    } else {
+        out.finish_success(format!(
+            "Restored settings from the backup from {}",
+            backup.timestamp
+        ));
+    }
     Ok(())
 }
 
-fn cmd_configure(tool_name: &str) -> Result<()> {
-    let tool = tools::get_tool(tool_name)?;
+fn cmd_configure(options: &ToolOptions, tool_name: &str, dry_run: bool) -> Result<()> {
+    let out = reporter();
+    let tool = tools::get_tool(tool_name, options)?;
 
-    println!(
-        "{} Configuring {}...\n",
-        style("→").cyan().bold(),
-        tool.display_name()
-    );
+    out.heading(format!("Configuring {}...", tool.display_name()));
+    out.blank();
 
-    tool.configure()?;
+    tool.configure(dry_run)?;
 
-    println!(
-        "\n{} Configuration complete!",
-        style("✓").green().bold()
-    );
+    out.blank();
+    if dry_run {
+        out.finish_success("Dry run complete, configuration was not changed");
+    } else {
+        out.finish_success("Configuration complete!");
+    }
+
+    Ok(())
+}
+
+/// Report how a tool's configuration differs from its templates, failing
+/// with a distinct exit code if it does
+fn cmd_configure_check(options: &ToolOptions, tool_name: &str, json: bool) -> Result<()> {
+    let tool = tools::get_tool(tool_name, options)?;
+    let drift = tool.check_configuration()?;
+
+    if json {
+        let report = serde_json::json!({
+            "tool": tool.name(),
+            "compliant": drift.is_empty(),
+            "drift": drift,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let out = reporter();
+        out.heading(format!("Checking {} configuration...", tool.display_name()));
+        out.blank();
+        for item in &drift {
+            out.error(item);
+        }
+        if !drift.is_empty() {
+            out.blank();
+        }
+    }
+
+    if !drift.is_empty() {
+        let message = format!(
+            "{} difference(s) from the mandated configuration",
+            drift.len()
+        );
+        if !json {
+            reporter().finish_failure(&message);
+        }
+        return Err(Failure::Drifted(message).into());
+    }
 
+    if !json {
+        reporter().finish_success("Configuration matches the templates");
+    }
     Ok(())
 }
 
-fn cmd_list() -> Result<()> {
-    println!("{} Available tools:\n", style("→").cyan().bold());
+fn cmd_list(options: &ToolOptions, json: bool) -> Result<()> {
+    let profiles = config::list_profiles(&options.local_dir);
+
+    if json {
+        let mut entries = Vec::new();
+        for tool in tools::list_tools(options) {
+            let installed = tool.is_installed()?;
+            let installed_version = if installed {
+                tool.installed_version()?
+            } else {
+                None
+            };
+            entries.push(serde_json::json!({
+                "name": tool.name(),
+                "display_name": tool.display_name(),
+                "installed": installed,
+                "installed_version": installed_version,
+                "profile": tool.deployed_profile()?,
+            }));
+        }
+        let report = serde_json::json!({ "tools": entries, "profiles": profiles });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let out = reporter();
+    out.heading("Available tools:");
+    out.blank();
 
-    for tool in tools::list_tools() {
+    for tool in tools::list_tools(options) {
         let status = if tool.is_installed()? {
-            style("installed").green()
+            match tool.installed_version()? {
+                Some(version) => style(format!("installed {}", version)).green(),
+                None => style("installed".to_string()).green(),
+            }
         } else {
-            style("not installed").dim()
+            style("not installed".to_string()).dim()
+        };
+        let profile = match tool.deployed_profile()? {
+            Some(profile) => format!(" (profile {})", profile),
+            None => String::new(),
         };
 
-        println!("  {} - {} [{}]", tool.name(), tool.display_name(), status);
+        out.output(format!(
+            "  {} - {} [{}]{}",
+            tool.name(),
+            tool.display_name(),
+            status,
+            profile
+        ));
     }
 
+    out.blank();
+    out.output(format!("Profiles: {}", profiles.join(", ")));
+
+    Ok(())
+}
+
+fn cmd_status(options: &ToolOptions, tool_name: Option<&str>, json: bool) -> Result<()> {
+    let tools = match tool_name {
+        Some(name) => vec![tools::get_tool(name, options)?],
+        None => tools::list_tools(options),
+    };
+
+    let mut statuses = Vec::new();
+    for tool in &tools {
+        statuses.push(tool.status()?);
+    }
+
+    let paths = &options.paths;
+    let vscode_settings_dir =
+        paths.vscode_settings_dir(options.editor.unwrap_or(platform::Editor::Stable));
+
+    if json {
+        let report = serde_json::json!({
+            "process_arch": platform::process_arch(),
+            "paths": {
+                "home": paths.home_dir,
+                "claude_dir": paths.claude_config_dir,
+                "certs_dir": paths.certs_dir,
+                "vscode_settings_dir": vscode_settings_dir,
+            },
+            "tools": statuses,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let out = reporter();
+    let yes_no = |value: bool| {
+        if value {
+            style("yes".to_string()).green()
+        } else {
+            style("no".to_string()).yellow()
+        }
+    };
+
+    for (i, status) in statuses.iter().enumerate() {
+        if i > 0 {
+            out.output("");
+        }
+        out.output(format!(
+            "{} ({})",
+            style(&status.display_name).bold(),
+            status.name
+        ));
+
+        let installed = match (status.installed, &status.installed_version) {
+            (true, Some(version)) => style(version.clone()).green(),
+            (true, None) => style("installed".to_string()).green(),
+            (false, _) => style("not installed".to_string()).dim(),
+        };
+        out.output(format!("  Installed version:   {}", installed));
+        if let Some(channel) = status.channel {
+            out.output(format!("  Channel:             {}", channel));
+        }
+        if let Some(profile) = &status.profile {
+            out.output(format!("  Profile:             {}", profile));
+        }
+
+        if let Some(path) = &status.install_path {
+            out.output(format!("  Install path:        {}", path.display()));
+            out.output(format!("  On PATH:             {}", yes_no(status.on_path)));
+            if let Some(arch) = &status.binary_arch {
+                out.output(format!(
+                    "  Architecture:        {} {}",
+                    arch,
+                    style(format!("(code-assist: {})", platform::process_arch())).dim()
+                ));
+            }
+        }
+
+        let latest = match &status.latest_version {
+            Some(version) => version.clone(),
+            None if options.offline => "unknown (offline)".to_string(),
+            None => "unknown (unreachable)".to_string(),
+        };
+        out.output(format!("  Latest version:      {}", latest));
+
+        let cached = if status.cached_versions.is_empty() {
+            style("none".to_string()).dim()
+        } else {
+            style(status.cached_versions.join(", "))
+        };
+        out.output(format!("  Cached versions:     {}", cached));
+
+        let certs = match &status.node_extra_ca_certs {
+            Some(env) if env.exists => style(env.value.clone()).green(),
+            Some(env) => style(format!("{} (missing)", env.value)).red(),
+            None => style("not set".to_string()).dim(),
+        };
+        out.output(format!("  NODE_EXTRA_CA_CERTS: {}", certs));
+
+        if !status.extensions.is_empty() {
+            out.output("  VS Code extensions:");
+            for ext in &status.extensions {
+                let state = match (ext.installed, &ext.version) {
+                    (true, Some(version)) => style(version.clone()).green(),
+                    (true, None) => style("installed".to_string()).green(),
+                    (false, _) => style("not installed".to_string()).dim(),
+                };
+                out.output(format!("    {} {}", ext.name, state));
+            }
+        }
+
+        if !status.deployed_files.is_empty() {
+            out.output("  Deployed files:");
+            for file in &status.deployed_files {
+                let how = match &file.link {
+                    Some(target) => style(format!("linked to {}", target.display())).cyan(),
+                    None => style("copied".to_string()).dim(),
+                };
+                out.output(format!("    {} {}", file.path.display(), how));
+            }
+        }
+
+        if !status.config_drift.is_empty() {
+            out.output(format!("  {}", style("Configuration drift:").yellow()));
+            for drift in &status.config_drift {
+                out.output(format!("    {}", drift));
+            }
+        }
+    }
+
+    out.blank();
+    out.output(style("Paths").bold());
+    out.output(format!(
+        "  Home:                {}",
+        paths.home_dir.display()
+    ));
+    out.output(format!(
+        "  Claude settings:     {}",
+        paths.claude_config_dir.display()
+    ));
+    out.output(format!(
+        "  Certificates:        {}",
+        paths.certs_dir.display()
+    ));
+    out.output(format!(
+        "  VS Code settings:    {}",
+        vscode_settings_dir.display()
+    ));
+
+    Ok(())
+}
+
+/// Write what's installed and deployed to `path`, or stdout
+fn cmd_export(options: &ToolOptions, path: Option<&Path>) -> Result<()> {
+    let mut exported = Vec::new();
+    for tool in tools::list_tools(options) {
+        if let Some(captured) = setup::capture(tool.as_ref(), &options.paths, &options.host)
+            .with_context(|| format!("Failed to read the setup of {}", tool.display_name()))?
+        {
+            exported.push(captured);
+        }
+    }
+    let count = exported.len();
+    let setup = setup::Setup {
+        schema: setup::SCHEMA,
+        exported_by: env!("CARGO_PKG_VERSION").to_string(),
+        platform: platform::get_platform_id().to_string(),
+        tools: exported,
+    };
+    let json = serde_json::to_string_pretty(&setup)?;
+
+    let Some(path) = path else {
+        println!("{}", json);
+        return Ok(());
+    };
+    std::fs::write(path, json + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    reporter().finish_success(format!("Exported {} tool(s) to {}", count, path.display()));
+    Ok(())
+}
+
+/// Install and configure tools to match a file written by `export`, after
+/// showing how this machine differs from it
+fn cmd_import(options: &ToolOptions, path: &Path, skip_confirm: bool, dry_run: bool) -> Result<()> {
+    let out = reporter();
+    let wanted = setup::Setup::load(path)?;
+    if wanted.platform != platform::get_platform_id() {
+        out.warn(format!(
+            "{} was exported on {}; this machine is {}",
+            path.display(),
+            wanted.platform,
+            platform::get_platform_id()
+        ));
+    }
+
+    out.heading(format!("Comparing with {}...", path.display()));
+    out.blank();
+
+    // Each tool deploys the profile the file names
+    let mut changes = Vec::new();
+    for tool_setup in &wanted.tools {
+        let tool_options = ToolOptions {
+            profile: tool_setup.profile.clone(),
+            ..options.clone()
+        };
+        let tool = tools::get_tool(&tool_setup.name, &tool_options)?;
+        let current = setup::capture(tool.as_ref(), &options.paths, &options.host)?;
+        let differences = setup::differences(tool_setup, current.as_ref());
+        if differences.is_empty() {
+            out.skip(format!("{} already matches", tool.display_name()));
+            continue;
+        }
+        out.info(style(tool.display_name()).bold());
+        for difference in &differences {
+            out.output(format!("  {}", difference));
+        }
+        changes.push((tool_setup, tool, differences));
+    }
+    out.blank();
+
+    if changes.is_empty() {
+        out.finish_success(format!("This machine already matches {}", path.display()));
+        return Ok(());
+    }
+    if dry_run {
+        out.finish_success("Dry run complete, nothing was changed");
+        return Ok(());
+    }
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will change {} tool(s) to match {}.",
+            changes.len(),
+            path.display()
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
+
+    let installing: Vec<Box<dyn tools::Tool>> = changes
+        .iter()
+        .filter(|(_, _, differences)| {
+            differences
+                .iter()
+                .any(|d| matches!(d, setup::Difference::Install { .. }))
+        })
+        .map(|(tool_setup, _, _)| tools::get_tool(&tool_setup.name, options))
+        .collect::<Result<_>>()?;
+    if !installing.is_empty() {
+        require_prerequisites(&tools::prerequisites_of(&installing), options)?;
+    }
+
+    let mut remaining = 0;
+    for (tool_setup, tool, differences) in &changes {
+        cancel::check()?;
+        // Installing deploys the configuration too
+        if differences
+            .iter()
+            .any(|d| matches!(d, setup::Difference::Install { .. }))
+        {
+            tool.install(
+                tool_setup.version.as_deref(),
+                ChannelChoice {
+                    requested: tool_setup.channel,
+                    allow_switch: true,
+                },
+                false,
+            )?;
+            out.blank();
+        } else if differences.iter().any(|d| !d.is_extension()) {
+            out.heading(format!("Configuring {}...", tool.display_name()));
+            out.blank();
+            tool.configure(false)?;
+            out.blank();
+        }
+
+        // Then the extensions at the versions the file pins, which the
+        // install may have put at the latest
+        let current = setup::capture(tool.as_ref(), &options.paths, &options.host)?;
+        for editor in platform::Editor::ALL {
+            let pinned = setup::differences(tool_setup, current.as_ref())
+                .into_iter()
+                .filter_map(|difference| match difference {
+                    setup::Difference::Extension {
+                        editor: wanted_editor,
+                        id,
+                        wanted,
+                        ..
+                    } if wanted_editor == editor => Some(config::marketplace_extension(
+                        &options.local_dir,
+                        &id,
+                        wanted.as_deref(),
+                    )),
+                    _ => None,
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if pinned.is_empty() {
+                continue;
+            }
+            config::install_extension_list(
+                &pinned,
+                &options.local_dir,
+                &options.paths,
+                tool.name(),
+                &config::ExtensionOptions {
+                    editor: Some(editor),
+                    offline: options.offline,
+                    force: false,
+                    host: options.host.clone(),
+                },
+                false,
+            )?;
+            out.blank();
+        }
+
+        // Local templates may deploy something other than the file says
+        let current = setup::capture(tool.as_ref(), &options.paths, &options.host)?;
+        for difference in setup::differences(tool_setup, current.as_ref()) {
+            out.warn(format!(
+                "{} still differs: {}",
+                tool.display_name(),
+                difference
+            ));
+            remaining += 1;
+        }
+    }
+
+    if remaining > 0 {
+        out.blank();
+        out.finish_success(format!(
+            "Imported {}, with {} difference(s) left",
+            path.display(),
+            remaining
+        ));
+    } else {
+        out.finish_success(format!("This machine now matches {}", path.display()));
+    }
+    Ok(())
+}
+
+fn cmd_doctor(options: &ToolOptions, settings: &Settings) -> Result<()> {
+    let out = reporter();
+    out.heading("Diagnosing installation...");
+    out.blank();
+
+    let mut failures = 0;
+
+    out.info(style("Environment").bold());
+    let required = tools::prerequisites_of(&tools::list_tools(options));
+    for diagnostic in doctor::check_environment(&options.paths, &options.host, &required) {
+        diagnostic.print();
+        failures += usize::from(!diagnostic.passed);
+    }
+    doctor::proxy_diagnostic(settings.proxy.value.as_deref(), settings.proxy.source).print();
+    if !options.offline {
+        for result in download::check_reachability() {
+            let diagnostic = doctor::reachability_diagnostic(&result);
+            diagnostic.print();
+            failures += usize::from(!diagnostic.passed);
+        }
+    }
+
+    for tool in tools::list_tools(options) {
+        let diagnostics = tool.diagnose();
+        if diagnostics.is_empty() {
+            continue;
+        }
+
+        out.blank();
+        out.info(style(tool.display_name()).bold());
+        for diagnostic in diagnostics {
+            diagnostic.print();
+            failures += usize::from(!diagnostic.passed);
+        }
+    }
+
+    out.blank();
+
+    if failures > 0 {
+        let message = format!("{} check(s) failed", failures);
+        out.finish_failure(&message);
+        return Err(Failure::Reported(message).into());
+    }
+
+    out.finish_success("Everything looks good!");
+    Ok(())
+}
+
+/// Compare the environment variables and PATH entries code-assist set with
+/// what new shells and this process have, or with `shell` print statements
+/// that bring this process up to date
+fn cmd_env(
+    options: &ToolOptions,
+    tool_name: Option<&str>,
+    shell: Option<environment::Shell>,
+) -> Result<()> {
+    let tools = match tool_name {
+        Some(name) => vec![tools::get_tool(name, options)?],
+        None => tools::list_tools(options),
+    };
+
+    let mut audits = Vec::new();
+    for tool in &tools {
+        let Some(state) = state::State::load(&options.paths, tool.name())? else {
+            continue;
+        };
+        let audit = environment::audit(&state);
+        if !audit.is_empty() {
+            audits.push((tool.display_name(), audit));
+        }
+    }
+
+    if let Some(shell) = shell {
+        for (_, audit) in &audits {
+            for line in audit.exports(shell) {
+                println!("{}", line);
+            }
+        }
+        return Ok(());
+    }
+
+    let out = reporter();
+    if audits.is_empty() {
+        out.skip("code-assist hasn't set any environment variables or PATH entries");
+        return Ok(());
+    }
+
+    let shown = |value: &Option<String>| match value {
+        Some(value) => value.clone(),
+        None => style("not set".to_string()).dim().to_string(),
+    };
+    for (i, (display_name, audit)) in audits.iter().enumerate() {
+        if i > 0 {
+            out.blank();
+        }
+        out.info(style(display_name).bold());
+
+        for var in &audit.vars {
+            out.info(&var.name);
+            match &var.saved {
+                Some((value, location)) => {
+                    out.info(format!("  saved:      {} ({})", value, location))
+                }
+                None => out.info(format!("  saved:      {}", shown(&None))),
+            }
+            out.info(format!("  this shell: {}", shown(&var.current)));
+            if var.saved.is_none() {
+                out.warn("New shells won't get it: it isn't saved anywhere");
+            } else if var.agrees() {
+                out.success("This shell is up to date");
+            } else {
+                out.warn("This shell has a different value from new shells");
+            }
+            if var
+                .saved
+                .as_ref()
+                .is_some_and(|(value, _)| *value != var.recorded)
+            {
+                out.warn(format!(
+                    "Changed since code-assist set it to {}",
+                    var.recorded
+                ));
+            }
+            for value in var.dangling() {
+                out.warn(format!("{} doesn't exist", value));
+            }
+        }
+
+        for entry in &audit.path_entries {
+            out.info(format!("PATH entry {}", entry.dir));
+            out.info(format!(
+                "  saved:      {}",
+                entry
+                    .saved
+                    .clone()
+                    .unwrap_or_else(|| style("no".to_string()).dim().to_string())
+            ));
+            out.info(format!(
+                "  this shell: {}",
+                if entry.current { "yes" } else { "no" }
+            ));
+            if entry.saved.is_none() {
+                out.warn("New shells won't get it: it isn't saved anywhere");
+            } else if entry.current {
+                out.success("This shell is up to date");
+            } else {
+                out.warn("This shell doesn't have it on PATH");
+            }
+            if !entry.exists {
+                out.warn(format!("{} doesn't exist", entry.dir));
+            }
+        }
+    }
+
+    if audits.iter().any(|(_, audit)| audit.out_of_date()) {
+        out.blank();
+        out.info(format!(
+            "To bring this shell up to date, run: {}",
+            environment::Shell::detect().eval_command()
+        ));
+    }
+    Ok(())
+}
+
+/// Print the end of the log file, or where it is
+fn cmd_logs(options: &ToolOptions, tail: usize, last_run: bool, path: bool) -> Result<()> {
+    let log_path = logs::path(&options.paths);
+    if path {
+        println!("{}", log_path.display());
+        return Ok(());
+    }
+
+    let lines = logs::read(&options.paths, last_run)?;
+    if lines.is_empty() {
+        reporter().skip(format!("Nothing logged yet in {}", log_path.display()));
+        return Ok(());
+    }
+    for line in &lines[lines.len().saturating_sub(tail)..] {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Replace the running binary with the newest build published at `url`,
+/// or with `check` only say whether there is one
+fn cmd_self_update(
+    options: &ToolOptions,
+    url: &str,
+    check: bool,
+    skip_confirm: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let out = reporter();
+    if options.offline {
+        return Err(Failure::Download(
+            "self-update downloads the new build, so it can't run offline".to_string(),
+        )
+        .into());
+    }
+
+    let current = env!("CARGO_PKG_VERSION");
+    out.heading("Checking for a newer code-assist...");
+    out.blank();
+    let latest = self_update::latest_version(url)?;
+    if download::compare_versions(&latest, current) != std::cmp::Ordering::Greater {
+        out.finish_success(format!("code-assist {} is up to date", current));
+        return Ok(());
+    }
+    out.info(format!(
+        "code-assist {} is available (this is {})",
+        style(&latest).cyan(),
+        current
+    ));
+    if check {
+        out.detail("Run `code-assist self-update` to install it");
+        return Ok(());
+    }
+
+    let release = self_update::release(url, &latest)?;
+    let exe = self_update::current_exe()?;
+    if let Err(e) = self_update::check_writable(&exe) {
+        let dir = exe.parent().unwrap_or(&exe);
+        let message = format!(
+            "Can't replace {}: {} isn't writable ({})",
+            exe.display(),
+            dir.display(),
+            e
+        );
+        out.error(&message);
+        let how = if cfg!(target_os = "windows") {
+            "from a terminal opened with 'Run as administrator'"
+        } else {
+            "with sudo"
+        };
+        out.info(format!("  Run `code-assist self-update` again {}, or", how));
+        out.info(format!(
+            "  download {} and put it in place of {} yourself",
+            release.url,
+            exe.display()
+        ));
+        return Err(Failure::Reported(message).into());
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Download code-assist {} from {}",
+            latest, release.url
+        ));
+        plan::step(format!("Replace {}", exe.display()));
+        out.blank();
+        out.finish_success("Dry run complete, code-assist was not updated");
+        return Ok(());
+    }
+    if !skip_confirm
+        && !confirm(&format!(
+            "This will replace {} with code-assist {}.",
+            exe.display(),
+            latest
+        ))?
+    {
+        return Err(Failure::Aborted.into());
+    }
+
+    let staged = self_update::stage(&release, &exe)?;
+    // A build that doesn't start would leave nothing to update from
+    let runs = process::output_with_timeout(
+        std::process::Command::new(&staged).arg("--version"),
+        Duration::from_secs(10),
+    )
+    .is_ok_and(|output| output.status.success());
+    if !runs {
+        std::fs::remove_file(&staged).ok();
+        return Err(Failure::Download(format!(
+            "The downloaded code-assist {} doesn't run; keeping {}",
+            latest, current
+        ))
+        .into());
+    }
+    self_update::replace(&exe, &staged)?;
+
+    out.blank();
+    out.finish_success(format!(
+        "Updated code-assist from {} to {}",
+        current, latest
+    ));
+    Ok(())
+}
+
+/// List the versions published for `tool_name`, newest first
+fn cmd_versions(
+    options: &ToolOptions,
+    tool_name: &str,
+    limit: Option<usize>,
+    json: bool,
+) -> Result<()> {
+    let tool = tools::get_tool(tool_name, options)?;
+    let installed = if tool.is_installed()? {
+        tool.installed_version()?
+    } else {
+        None
+    };
+    let index = tool.available_versions()?;
+    let total = index.versions.len();
+    let shown = &index.versions[..limit.unwrap_or(total).min(total)];
+    let source = match index.source {
+        download::DownloadSource::Remote => "remote",
+        download::DownloadSource::LocalFallback => "local fallback",
+        download::DownloadSource::Cache => "cache",
+    };
+
+    if json {
+        let versions: Vec<_> = shown
+            .iter()
+            .map(|version| {
+                serde_json::json!({
+                    "version": version,
+                    "installed": installed.as_deref() == Some(version.as_str()),
+                    "channels": index.channels_at(version),
+                })
+            })
+            .collect();
+        let output = serde_json::json!({
+            "tool": tool.name(),
+            "source": source,
+            "installed_version": installed,
+            "total": total,
+            "versions": versions,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let out = reporter();
+    out.heading(format!("{} versions ({}):", tool.display_name(), source));
+    out.blank();
+
+    for version in shown {
+        let mut marks: Vec<String> = index
+            .channels_at(version)
+            .iter()
+            .map(|channel| {
+                if *channel == download::Channel::Stable {
+                    "latest".to_string()
+                } else {
+                    format!("latest {}", channel)
+                }
+            })
+            .collect();
+        if installed.as_deref() == Some(version.as_str()) {
+            marks.insert(0, "installed".to_string());
+        }
+        if marks.is_empty() {
+            out.output(format!("  {}", version));
+        } else {
+            out.output(format!(
+                "  {} {}",
+                version,
+                style(format!("[{}]", marks.join(", "))).green()
+            ));
+        }
+    }
+
+    if shown.len() < total {
+        out.blank();
+        out.info(format!(
+            "Showing {} of {} versions; raise --limit to see more",
+            shown.len(),
+            total
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check every file in the local artifacts directory, reporting each one
+fn cmd_verify(options: &ToolOptions, json: bool) -> Result<()> {
+    let report = verify::verify_local(&options.local_dir);
+    let failures = report.failures();
+
+    if json {
+        let output = serde_json::json!({
+            "ok": failures == 0,
+            "local_dir": options.local_dir,
+            "version": report.version,
+            "sections": report.sections,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        let out = reporter();
+        out.heading(format!(
+            "Verifying {}...",
+            style(options.local_dir.display()).cyan()
+        ));
+        for section in &report.sections {
+            out.blank();
+            out.info(style(section.title).bold());
+            for check in &section.checks {
+                check.print();
+            }
+        }
+        out.blank();
+    }
+
+    if failures > 0 {
+        let message = format!("{} file(s) missing or corrupt", failures);
+        if !json {
+            reporter().finish_failure(&message);
+        }
+        return Err(Failure::Reported(message).into());
+    }
+
+    if !json {
+        reporter().finish_success("Local artifacts are intact");
+    }
+    Ok(())
+}
+
+fn cmd_config_show(settings: &Settings, options: &ToolOptions) -> Result<()> {
+    let out = reporter();
+
+    match UserConfig::path() {
+        Some(path) if path.exists() => out.heading(format!("Config file: {}", path.display())),
+        Some(path) => out.heading(format!("Config file: {} (not found)", path.display())),
+        None => out.heading("Config file: none (no config directory on this platform)"),
+    }
+    out.blank();
+
+    let row = |key: &str, value: String, source: config::Source| {
+        out.output(format!(
+            "  {:<22} {}  {}",
+            key,
+            value,
+            style(format!("({})", source)).dim()
+        ));
+    };
+    let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_string());
+
+    row(
+        "local_dir",
+        options.local_dir.display().to_string(),
+        settings.local_dir.source,
+    );
+    row(
+        "offline",
+        settings.offline.value.to_string(),
+        settings.offline.source,
+    );
+    row(
+        "download_base_url",
+        download::base_urls().join(", "),
+        settings.download_base_url.source,
+    );
+    row(
+        "proxy",
+        or_none(&settings.proxy.value),
+        settings.proxy.source,
+    );
+    row(
+        "download.token",
+        match settings.auth_token.value {
+            Some(_) => "(set)".to_string(),
+            None => "none".to_string(),
+        },
+        settings.auth_token.source,
+    );
+    row(
+        "download.manifest_key",
+        or_none(&settings.manifest_key.value),
+        settings.manifest_key.source,
+    );
+    row(
+        "timeout",
+        match settings.timeout.value {
+            Some(secs) => format!("{}s", secs),
+            None => format!(
+                "{}s to connect, {}s between reads",
+                download::DEFAULT_CONNECT_TIMEOUT.as_secs(),
+                download::DEFAULT_READ_TIMEOUT.as_secs()
+            ),
+        },
+        settings.timeout.source,
+    );
+    row(
+        "retries",
+        settings.retries.value.to_string(),
+        settings.retries.source,
+    );
+    row(
+        "retry_delay",
+        format!("{:?}", settings.retry_delay.value),
+        settings.retry_delay.source,
+    );
+    row(
+        "backups.keep",
+        settings.keep_backups.value.to_string(),
+        settings.keep_backups.source,
+    );
+    row(
+        "prerequisites.node",
+        settings.node_version.value.clone(),
+        settings.node_version.source,
+    );
+    row(
+        "default_yes",
+        settings.yes.value.to_string(),
+        settings.yes.source,
+    );
+    row(
+        "non_interactive",
+        settings.non_interactive.value.to_string(),
+        settings.non_interactive.source,
+    );
+    row(
+        "no_color",
+        settings.no_color.value.to_string(),
+        settings.no_color.source,
+    );
+    row(
+        "logs.max_size_mb",
+        settings.log_max_size_mb.value.to_string(),
+        settings.log_max_size_mb.source,
+    );
+    row(
+        "self_update_url",
+        settings.self_update_url.value.clone(),
+        settings.self_update_url.source,
+    );
+    let or_default = |dir: &Option<PathBuf>| match dir {
+        Some(dir) => dir.display().to_string(),
+        None => "platform default".to_string(),
+    };
+    row(
+        "paths.claude_dir",
+        or_default(&settings.claude_dir.value),
+        settings.claude_dir.source,
+    );
+    row(
+        "paths.certs_dir",
+        or_default(&settings.certs_dir.value),
+        settings.certs_dir.source,
+    );
+    row(
+        "paths.vscode_user_dir",
+        or_default(&settings.vscode_user_dir.value),
+        settings.vscode_user_dir.source,
+    );
+
+    for (name, tool) in &settings.tools {
+        out.blank();
+        out.output(format!("  [tools.{}]", name));
+        row("version", or_none(&tool.version), config::Source::File);
+        row(
+            "channel",
+            or_none(&tool.channel.map(|channel| channel.to_string())),
+            config::Source::File,
+        );
+        if let Some(local_dir) = &tool.local_dir {
+            row(
+                "local_dir",
+                local_dir.display().to_string(),
+                config::Source::File,
+            );
+        }
+        if !tool.dependencies.is_empty() {
+            row(
+                "dependencies",
+                tool.dependencies.join(", "),
+                config::Source::File,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_cache(action: CacheAction, dry_run: bool) -> Result<()> {
+    let out = reporter();
+
+    match action {
+        CacheAction::Path => out.output(cache::dir()?.display()),
+        CacheAction::List => {
+            let entries = cache::entries()?;
+            if entries.is_empty() {
+                out.line(format!(
+                    "No cached downloads in {}",
+                    cache::dir()?.display()
+                ));
+                return Ok(());
+            }
+
+            out.heading(format!("Cached downloads in {}:", cache::dir()?.display()));
+            out.blank();
+            for entry in &entries {
+                out.output(format!(
+                    "  {:<12} {:<14} {:>10}  {} old{}",
+                    entry.version,
+                    entry.platform.as_deref().unwrap_or("manifest"),
+                    HumanBytes(entry.size).to_string(),
+                    HumanDuration(entry.age()),
+                    if entry.partial { " (partial)" } else { "" }
+                ));
+            }
+
+            let total: u64 = entries.iter().map(|e| e.size).sum();
+            out.blank();
+            out.line(format!("Total: {}", HumanBytes(total)));
+        }
+        CacheAction::Clean { older_than, all } => {
+            let stale: Vec<_> = cache::entries()?
+                .into_iter()
+                .filter(|e| all || e.age() >= older_than)
+                .collect();
+
+            let mut freed = 0;
+            for entry in &stale {
+                if dry_run {
+                    plan::step(format!("Remove {}", entry.path.display()));
+                    continue;
+                }
+                cache::remove(&entry.path)?;
+                out.detail(format!("Removed {}", entry.path.display()));
+                freed += entry.size;
+            }
+
+            if dry_run && !stale.is_empty() {
+                out.blank();
+            }
+            if dry_run {
+                out.finish_success(format!(
+                    "Dry run complete, {} file(s) were not removed",
+                    stale.len()
+                ));
+            } else {
+                out.finish_success(format!(
+                    "Removed {} file(s), freed {}",
+                    stale.len(),
+                    HumanBytes(freed)
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_completions(options: &ToolOptions, shell: Shell) -> Result<()> {
+    let tool_names: Vec<String> = tools::list_tools(options)
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect();
+
+    let mut command = cli::completion_command(&tool_names);
+    clap_complete::generate(shell, &mut command, "code-assist", &mut std::io::stdout());
+
     Ok(())
 }
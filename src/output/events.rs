@@ -0,0 +1,289 @@
+//! Typed events for what a run does, as opposed to how it's shown: which
+//! tool and step it's in, the files, settings, environment variables, and
+//! extensions it changes, and the warnings and errors it reports. Code that
+//! installs or deploys emits them here, and every subscriber of the run's
+//! `Bus` hears each one in order. `main` makes the bus and subscribes the
+//! console, which prints the lines the `Reporter` emits, and the install
+//! report when one was asked for.
+//!
+//! Tests use `capture` to collect the events of the code they run, so they
+//! can check what an install did without reading the console.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::download::{Channel, DownloadSource};
+
+/// Something a run did
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// What's emitted next belongs to `tool`
+    ToolStarted {
+        tool: String,
+    },
+    StepStarted {
+        step: String,
+    },
+    StepCompleted {
+        step: String,
+    },
+    StepFailed {
+        step: String,
+    },
+    VersionChosen {
+        version: String,
+        channel: Channel,
+    },
+    DownloadSource(DownloadSource),
+    /// A mirror or URL that couldn't give a verified download, before the
+    /// next one is tried
+    DownloadFailed {
+        url: String,
+        error: String,
+    },
+    /// A prerequisite was looked for; `problem` says why it isn't usable
+    PrerequisiteChecked {
+        name: String,
+        installed: bool,
+        problem: Option<String>,
+    },
+    /// A file was written in place, copied or merged
+    FileDeployed(PathBuf),
+    /// Top-level keys added, changed, or removed in a settings file
+    SettingsChanged {
+        file: PathBuf,
+        keys: Vec<String>,
+    },
+    EnvVarSet {
+        name: String,
+        value: String,
+    },
+    ExtensionInstalled {
+        editor: String,
+        id: String,
+    },
+    FileRemoved(PathBuf),
+    RemovalFailed {
+        path: PathBuf,
+        error: String,
+    },
+    /// A top-level warning shown to the user, such as an unsupported
+    /// platform
+    Notice(String),
+    /// A warning shown to the user
+    Warning(String),
+    /// An error shown to the user
+    Error(String),
+    /// A line for the console that records nothing
+    Line(Line),
+}
+
+impl Event {
+    pub fn warning(message: impl Display) -> Self {
+        Event::Warning(message.to_string())
+    }
+
+    pub fn error(message: impl Display) -> Self {
+        Event::Error(message.to_string())
+    }
+}
+
+/// Console output besides warnings and errors, in the forms the `Reporter`
+/// prints
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    /// Top-level step: `→ message`
+    Heading(String),
+    /// Top-level success that isn't the final result: `✓ message`
+    Done(String),
+    /// Nested success: `  ✓ message`
+    Success(String),
+    /// Nested step that had nothing to do: `  - message`
+    Skip(String),
+    /// Nested plain line: `  message`
+    Info(String),
+    /// Extra detail only shown with --verbose: `    message`
+    Detail(String),
+    /// Unindented plain line
+    Plain(String),
+    Blank,
+    /// Primary output of a command, shown even when quiet
+    Output(String),
+    /// Final result line, shown even when quiet
+    Finished {
+        success: bool,
+        message: String,
+    },
+}
+
+/// Something that wants to hear about every event
+pub trait Subscriber {
+    fn event(&self, event: &Event);
+}
+
+/// Where the events of a run go: each subscriber, in the order they
+/// subscribed, and the step in progress
+#[derive(Default)]
+pub struct Bus {
+    subscribers: RefCell<Vec<Rc<dyn Subscriber>>>,
+    /// The step in progress, which the next one completes
+    step: RefCell<Option<String>>,
+}
+
+impl Bus {
+    /// Have `subscriber` hear every event from now on
+    pub fn subscribe(&self, subscriber: Rc<dyn Subscriber>) {
+        self.subscribers.borrow_mut().push(subscriber);
+    }
+}
+
+thread_local! {
+    /// The bus `with_bus` is running code for on this thread
+    static BUS: RefCell<Option<Rc<Bus>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, sending the events it emits on this thread to `bus`
+pub fn with_bus<T>(bus: Rc<Bus>, f: impl FnOnce() -> T) -> T {
+    let previous = BUS.with(|current| current.replace(Some(bus)));
+    let result = f();
+    BUS.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+fn bus() -> Option<Rc<Bus>> {
+    BUS.with(|bus| bus.borrow().clone())
+}
+
+/// Have `subscriber` hear the rest of the events sent to the current bus
+pub fn subscribe(subscriber: Rc<dyn Subscriber>) {
+    if let Some(bus) = bus() {
+        bus.subscribe(subscriber);
+    }
+}
+
+/// Send `event` to every subscriber of the current bus; with no bus, as on
+/// a thread of `process::in_parallel`, nobody hears it
+pub fn emit(event: Event) {
+    let Some(bus) = bus() else {
+        return;
+    };
+    // Copied out so a subscriber can emit events of its own
+    let subscribers = bus.subscribers.borrow().clone();
+    for subscriber in subscribers {
+        subscriber.event(&event);
+    }
+}
+
+/// Complete the step in progress and start `tool`
+pub fn tool(name: &str) {
+    complete_step();
+    emit(Event::ToolStarted {
+        tool: name.to_string(),
+    });
+}
+
+/// Complete the step in progress and start `name`
+pub fn step(name: &str) {
+    complete_step();
+    if let Some(bus) = bus() {
+        *bus.step.borrow_mut() = Some(name.to_string());
+    }
+    emit(Event::StepStarted {
+        step: name.to_string(),
+    });
+}
+
+/// Mark the step in progress as failed, for a tool whose failure doesn't
+/// stop the install of the others
+pub fn fail_step() {
+    if let Some(step) = take_step() {
+        emit(Event::StepFailed { step });
+    }
+}
+
+fn complete_step() {
+    if let Some(step) = take_step() {
+        emit(Event::StepCompleted { step });
+    }
+}
+
+fn take_step() -> Option<String> {
+    bus()?.step.borrow_mut().take()
+}
+
+/// Run `f` on a bus of its own, collecting the events it emits on this
+/// thread other than the lines it prints
+#[cfg(test)]
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Event>) {
+    #[derive(Default)]
+    struct Capture(RefCell<Vec<Event>>);
+
+    impl Subscriber for Capture {
+        fn event(&self, event: &Event) {
+            if !matches!(event, Event::Line(_)) {
+                self.0.borrow_mut().push(event.clone());
+            }
+        }
+    }
+
+    let captured = Rc::new(Capture::default());
+    let bus = Bus::default();
+    bus.subscribe(captured.clone());
+    let result = with_bus(Rc::new(bus), f);
+    let events = captured.0.take();
+    (result, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_step_completes_the_one_before() {
+        let ((), events) = capture(|| {
+            tool("claude-code");
+            step("download");
+            step("setup");
+            fail_step();
+            tool("continue");
+        });
+
+        assert_eq!(
+            events,
+            [
+                Event::ToolStarted {
+                    tool: "claude-code".to_string()
+                },
+                Event::StepStarted {
+                    step: "download".to_string()
+                },
+                Event::StepCompleted {
+                    step: "download".to_string()
+                },
+                Event::StepStarted {
+                    step: "setup".to_string()
+                },
+                Event::StepFailed {
+                    step: "setup".to_string()
+                },
+                Event::ToolStarted {
+                    tool: "continue".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_bus_code_runs_with_hears_its_events() {
+        let ((), outer) = capture(|| {
+            emit(Event::warning("outer"));
+            let ((), inner) = capture(|| emit(Event::warning("inner")));
+            assert_eq!(inner, [Event::warning("inner")]);
+            emit(Event::Line(Line::Blank));
+        });
+
+        assert_eq!(outer, [Event::warning("outer")]);
+    }
+}
@@ -0,0 +1,363 @@
+pub mod events;
+
+use console::style;
+use indicatif::ProgressStyle;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use crate::error::Failure;
+use events::{Event, Line};
+
+/// How much decorative output to print
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors, primary command output, and the final result line
+    Quiet,
+    Normal,
+    /// Normal output plus extra detail lines
+    Verbose,
+}
+
+/// Status markers printed in front of messages
+#[derive(Debug, Clone, Copy)]
+pub enum Symbol {
+    Arrow,
+    Check,
+    Cross,
+    Bang,
+    Dash,
+}
+
+/// Central sink for user-facing console output, so verbosity, color, and
+/// glyphs are decided in one place instead of at every call site
+pub struct Reporter {
+    verbosity: Verbosity,
+    /// No color and ASCII-only symbols
+    plain: bool,
+    /// Print to stderr, leaving stdout to a report written there
+    stderr: bool,
+    /// Fail instead of prompting
+    non_interactive: bool,
+}
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+
+/// Install the global reporter. Only the first call has an effect.
+///
+/// Output is plain (no color, ASCII symbols) with `no_color`, when `NO_COLOR`
+/// is set, or when it isn't going to a terminal. With `stderr`, everything
+/// is printed there, so stdout holds nothing but what a command writes to it
+/// for another program to read. With `non_interactive`, every prompt fails
+/// instead of waiting for an answer.
+pub fn init(verbosity: Verbosity, no_color: bool, stderr: bool, non_interactive: bool) {
+    let terminal = if stderr {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdout().is_terminal()
+    };
+    let plain =
+        no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) || !terminal;
+
+    if plain {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
+    let _ = REPORTER.set(Reporter {
+        verbosity,
+        plain,
+        stderr,
+        non_interactive,
+    });
+}
+
+/// The global reporter, at normal verbosity if `init` was never called
+pub fn reporter() -> &'static Reporter {
+    REPORTER.get_or_init(|| Reporter {
+        verbosity: Verbosity::Normal,
+        plain: false,
+        stderr: false,
+        non_interactive: !std::io::stdin().is_terminal(),
+    })
+}
+
+impl Reporter {
+    fn normal(&self) -> bool {
+        self.verbosity >= Verbosity::Normal
+    }
+
+    fn print(&self, line: impl Display) {
+        if self.stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    /// The marker for `symbol`, falling back to ASCII in plain mode
+    pub fn symbol(&self, symbol: Symbol) -> &'static str {
+        match (symbol, self.plain) {
+            (Symbol::Arrow, false) => "→",
+            (Symbol::Arrow, true) => ">",
+            (Symbol::Check, false) => "✓",
+            (Symbol::Check, true) => "+",
+            (Symbol::Cross, false) => "✗",
+            (Symbol::Cross, true) => "x",
+            (Symbol::Bang, _) => "!",
+            (Symbol::Dash, _) => "-",
+        }
+    }
+
+    /// Style for the spinner shown while waiting on a download
+    pub fn spinner_style(&self) -> ProgressStyle {
+        let style = ProgressStyle::default_spinner()
+            .template("  {spinner:.cyan} {msg}")
+            .unwrap();
+        if self.plain {
+            style.tick_chars("|/-\\ ")
+        } else {
+            style
+        }
+    }
+
+    /// Style for a download progress bar with a known size
+    pub fn bar_style(&self) -> ProgressStyle {
+        let style = ProgressStyle::default_bar()
+            .template(
+                "  {spinner:.cyan} [{bar:30.cyan/dim}] {bytes}/{total_bytes} \
+                 {binary_bytes_per_sec}, {eta} left {msg}",
+            )
+            .unwrap();
+        if self.plain {
+            style.tick_chars("|/-\\ ").progress_chars("#>-")
+        } else {
+            style.progress_chars("█▓░")
+        }
+    }
+
+    /// Style for a download of unknown size: bytes so far and the rate
+    pub fn counter_style(&self) -> ProgressStyle {
+        let style = ProgressStyle::default_spinner()
+            .template("  {spinner:.cyan} {bytes} ({binary_bytes_per_sec}) {msg}")
+            .unwrap();
+        if self.plain {
+            style.tick_chars("|/-\\ ")
+        } else {
+            style
+        }
+    }
+
+    /// Theme for interactive prompts
+    pub fn prompt_theme(&self) -> Box<dyn dialoguer::theme::Theme> {
+        if self.plain {
+            Box::new(dialoguer::theme::SimpleTheme)
+        } else {
+            Box::new(dialoguer::theme::ColorfulTheme::default())
+        }
+    }
+
+    /// Whether spinners and progress bars should be drawn
+    pub fn show_progress(&self) -> bool {
+        self.normal() && !self.stderr && std::io::stdout().is_terminal()
+    }
+
+    /// Top-level step: `→ message`
+    pub fn heading(&self, message: impl Display) {
+        emit(Line::Heading(message.to_string()));
+    }
+
+    /// Top-level success that isn't the final result: `✓ message`
+    pub fn done(&self, message: impl Display) {
+        emit(Line::Done(message.to_string()));
+    }
+
+    /// Top-level warning on stderr: `! message`
+    pub fn notice(&self, message: impl Display) {
+        events::emit(Event::Notice(message.to_string()));
+    }
+
+    /// Nested success: `  ✓ message`
+    pub fn success(&self, message: impl Display) {
+        emit(Line::Success(message.to_string()));
+    }
+
+    /// Nested warning: `  ! message`
+    pub fn warn(&self, message: impl Display) {
+        events::emit(Event::warning(message));
+    }
+
+    /// Nested error: `  ✗ message`, shown even when quiet
+    pub fn error(&self, message: impl Display) {
+        events::emit(Event::error(message));
+    }
+
+    /// Nested step that had nothing to do: `  - message`
+    pub fn skip(&self, message: impl Display) {
+        emit(Line::Skip(message.to_string()));
+    }
+
+    /// Nested plain line: `  message`
+    pub fn info(&self, message: impl Display) {
+        emit(Line::Info(message.to_string()));
+    }
+
+    /// Extra detail only shown with --verbose: `    message`
+    pub fn detail(&self, message: impl Display) {
+        emit(Line::Detail(message.to_string()));
+    }
+
+    /// Unindented plain line
+    pub fn line(&self, message: impl Display) {
+        emit(Line::Plain(message.to_string()));
+    }
+
+    pub fn blank(&self) {
+        emit(Line::Blank);
+    }
+
+    /// Primary output of a command, such as list entries, shown even when quiet
+    pub fn output(&self, message: impl Display) {
+        emit(Line::Output(message.to_string()));
+    }
+
+    /// Final success line, shown even when quiet
+    pub fn finish_success(&self, message: impl Display) {
+        emit(Line::Finished {
+            success: true,
+            message: message.to_string(),
+        });
+    }
+
+    /// Final failure line, shown even when quiet
+    pub fn finish_failure(&self, message: impl Display) {
+        emit(Line::Finished {
+            success: false,
+            message: message.to_string(),
+        });
+    }
+
+    /// Print `event` the way it's shown on the console, if it's shown at all
+    /// at this verbosity
+    fn render(&self, event: &Event) {
+        let symbol = |symbol| style(self.symbol(symbol));
+        let line = match event {
+            Event::Notice(message) => {
+                if self.normal() {
+                    eprintln!("{} {}", symbol(Symbol::Bang).yellow().bold(), message);
+                }
+                return;
+            }
+            Event::Warning(message) if self.normal() => {
+                format!("  {} {}", symbol(Symbol::Bang).yellow().bold(), message)
+            }
+            Event::Error(message) => {
+                format!("  {} {}", symbol(Symbol::Cross).red().bold(), message)
+            }
+            Event::Line(line) => match line {
+                Line::Heading(message) if self.normal() => {
+                    format!("{} {}", symbol(Symbol::Arrow).cyan().bold(), message)
+                }
+                Line::Done(message) if self.normal() => {
+                    format!("{} {}", symbol(Symbol::Check).green().bold(), message)
+                }
+                Line::Success(message) if self.normal() => {
+                    format!("  {} {}", symbol(Symbol::Check).green().bold(), message)
+                }
+                Line::Skip(message) if self.normal() => {
+                    format!("  {} {}", symbol(Symbol::Dash).dim(), message)
+                }
+                Line::Info(message) if self.normal() => format!("  {}", message),
+                Line::Detail(message) if self.verbosity >= Verbosity::Verbose => {
+                    format!("    {}", style(message).dim())
+                }
+                Line::Plain(message) if self.normal() => message.clone(),
+                Line::Blank if self.normal() => String::new(),
+                Line::Output(message) => message.clone(),
+                Line::Finished {
+                    success: true,
+                    message,
+                } => {
+                    format!("{} {}", symbol(Symbol::Check).green().bold(), message)
+                }
+                Line::Finished {
+                    success: false,
+                    message,
+                } => {
+                    format!("{} {}", symbol(Symbol::Cross).red().bold(), message)
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+        self.print(line);
+    }
+}
+
+fn emit(line: Line) {
+    events::emit(Event::Line(line));
+}
+
+/// The console, as a subscriber: prints the lines, warnings, and errors of
+/// a run through the global reporter
+pub struct Console;
+
+impl events::Subscriber for Console {
+    fn event(&self, event: &Event) {
+        reporter().render(event);
+    }
+}
+
+/// Whether prompts may wait for an answer: not with `--non-interactive`,
+/// nor by default when stdin isn't a terminal
+pub fn is_interactive() -> bool {
+    !reporter().non_interactive
+}
+
+/// Fail unless prompts may wait for an answer. `answer` says what to pass
+/// instead of answering, such as `--yes`.
+pub fn require_interactive(answer: &str) -> anyhow::Result<()> {
+    if is_interactive() {
+        return Ok(());
+    }
+    Err(Failure::NeedsInput(format!(
+        "This needs an answer, but prompts are off (--non-interactive, or stdin isn't a terminal); pass {}",
+        answer
+    ))
+    .into())
+}
+
+/// Ask `question` and read the answer, a line from stdin. Every prompt that
+/// reads stdin goes through here, so none can hang a run without a terminal:
+/// when prompts are off it fails at once, naming `answer` as the way around
+/// it.
+pub fn prompt(question: &str, answer: &str) -> anyhow::Result<String> {
+    require_interactive(answer)?;
+    let out = reporter();
+    if out.stderr {
+        eprint!("{}", question);
+        std::io::Write::flush(&mut std::io::stderr())?;
+    } else {
+        print!("{}", question);
+        std::io::Write::flush(&mut std::io::stdout())?;
+    }
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input)
+}
+
+/// Ask the user to confirm `message`; anything but an empty answer or yes aborts
+pub fn confirm(message: &str) -> anyhow::Result<bool> {
+    let out = reporter();
+    out.print(message);
+    let input = prompt("Continue? [Y/n] ", "--yes to go ahead")?;
+    let input = input.trim().to_lowercase();
+
+    if !input.is_empty() && input != "y" && input != "yes" {
+        out.print("Aborted.");
+        return Ok(false);
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,17 @@
+use console::style;
+
+use crate::output::reporter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static STEP: AtomicUsize = AtomicUsize::new(0);
+
+/// Print the next numbered step of a dry-run plan in place of performing it.
+/// The plan is the whole point of a dry run, so it's printed even when quiet.
+pub fn step(description: impl std::fmt::Display) {
+    let n = STEP.fetch_add(1, Ordering::Relaxed) + 1;
+    reporter().output(format!(
+        "  {} {}",
+        style(format!("{}.", n)).cyan().bold(),
+        description
+    ));
+}
@@ -0,0 +1,128 @@
+//! CPU architectures of this process and of installed binaries, named the
+//! way platform identifiers name them (`x64`, `arm64`)
+//!
+//! Binaries are identified from their Mach-O, PE, or ELF header rather than
+//! by running them, so a binary this machine can't run is still described.
+
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a binary to read; enough for a PE header, which starts at an
+/// offset given in the DOS stub
+const HEADER_LEN: u64 = 4096;
+
+/// Architecture this process was built for
+pub fn of_process() -> &'static str {
+    name(std::env::consts::ARCH)
+}
+
+fn name(arch: &str) -> &str {
+    match arch {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => other,
+    }
+}
+
+/// Architecture of the executable at `path`, or `universal (x64, arm64)` for
+/// a macOS universal binary; `None` if it can't be read or isn't an
+/// executable format we know
+pub fn of_binary(path: &Path) -> Option<String> {
+    let mut header = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(HEADER_LEN).read_to_end(&mut header))
+        .ok()?;
+    from_header(&header)
+}
+
+fn from_header(bytes: &[u8]) -> Option<String> {
+    let u16_le = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let u32_be = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+    match bytes.get(..4)? {
+        // 64-bit Mach-O
+        [0xcf, 0xfa, 0xed, 0xfe] => mach_cpu(u32_le(4)?).map(str::to_string),
+        // Universal Mach-O: a big-endian list of the architectures inside
+        [0xca, 0xfe, 0xba, 0xbe] => {
+            let count = u32_be(4)? as usize;
+            let archs = (0..count)
+                .map(|i| mach_cpu(u32_be(8 + i * 20)?))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("universal ({})", archs.join(", ")))
+        }
+        // ELF, little-endian
+        [0x7f, b'E', b'L', b'F'] => match u16_le(18)? {
+            0x3e => Some("x64".to_string()),
+            0xb7 => Some("arm64".to_string()),
+            _ => None,
+        },
+        // PE, found through the DOS stub
+        [b'M', b'Z', ..] => {
+            let pe = u32_le(0x3c)? as usize;
+            if bytes.get(pe..pe + 4)? != b"PE\0\0" {
+                return None;
+            }
+            match u16_le(pe + 4)? {
+                0x8664 => Some("x64".to_string()),
+                0xaa64 => Some("arm64".to_string()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn mach_cpu(cpu_type: u32) -> Option<&'static str> {
+    match cpu_type {
+        0x0100_0007 => Some("x64"),
+        0x0100_000c => Some("arm64"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(mut bytes: Vec<u8>, at: usize, value: &[u8]) -> Vec<u8> {
+        if bytes.len() < at + value.len() {
+            bytes.resize(at + value.len(), 0);
+        }
+        bytes[at..at + value.len()].copy_from_slice(value);
+        bytes
+    }
+
+    #[test]
+    fn mach_o_binaries_name_their_cpu() {
+        let arm = with(
+            vec![0xcf, 0xfa, 0xed, 0xfe],
+            4,
+            &0x0100_000cu32.to_le_bytes(),
+        );
+        assert_eq!(from_header(&arm).as_deref(), Some("arm64"));
+
+        let mut fat = with(vec![0xca, 0xfe, 0xba, 0xbe], 4, &2u32.to_be_bytes());
+        fat = with(fat, 8, &0x0100_0007u32.to_be_bytes());
+        fat = with(fat, 28, &0x0100_000cu32.to_be_bytes());
+        assert_eq!(from_header(&fat).as_deref(), Some("universal (x64, arm64)"));
+    }
+
+    #[test]
+    fn pe_and_elf_binaries_name_their_machine() {
+        let mut pe = with(b"MZ".to_vec(), 0x3c, &0x80u32.to_le_bytes());
+        pe = with(pe, 0x80, b"PE\0\0");
+        pe = with(pe, 0x84, &0xaa64u16.to_le_bytes());
+        assert_eq!(from_header(&pe).as_deref(), Some("arm64"));
+
+        let elf = with(b"\x7fELF".to_vec(), 18, &0x3eu16.to_le_bytes());
+        assert_eq!(from_header(&elf).as_deref(), Some("x64"));
+    }
+
+    #[test]
+    fn truncated_or_unknown_files_have_no_architecture() {
+        assert_eq!(from_header(b"#!/bin/sh\n"), None);
+        assert_eq!(from_header(b"MZ"), None);
+        assert_eq!(from_header(&[0xcf, 0xfa, 0xed, 0xfe, 0x07]), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! VS Code and the editors built from the same source, which keep their
+//! settings and extensions separately
+
+use std::path::{Path, PathBuf};
+
+/// A VS Code build that settings and extensions can be deployed to
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Editor {
+    /// VS Code
+    Stable,
+    /// VS Code Insiders
+    Insiders,
+    /// VSCodium
+    Vscodium,
+}
+
+impl Editor {
+    pub const ALL: [Editor; 3] = [Editor::Stable, Editor::Insiders, Editor::Vscodium];
+
+    /// Name shown to the user
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Editor::Stable => "VS Code",
+            Editor::Insiders => "VS Code Insiders",
+            Editor::Vscodium => "VSCodium",
+        }
+    }
+
+    /// Command the editor's CLI is run as when it's on PATH
+    pub fn command(self) -> &'static str {
+        match self {
+            Editor::Stable => "code",
+            Editor::Insiders => "code-insiders",
+            Editor::Vscodium => "codium",
+        }
+    }
+
+    /// Directory in backups holding copies of this editor's settings
+    pub fn backup_label(self) -> &'static str {
+        match self {
+            Editor::Stable => "vscode",
+            Editor::Insiders => "vscode-insiders",
+            Editor::Vscodium => "vscodium",
+        }
+    }
+
+    /// Directory under the platform's application data directory that
+    /// holds the editor's `User` settings
+    pub(super) fn data_dir_name(self) -> &'static str {
+        match self {
+            Editor::Stable => "Code",
+            Editor::Insiders => "Code - Insiders",
+            Editor::Vscodium => "VSCodium",
+        }
+    }
+
+    /// Where the editor may be installed, whether or not its CLI is on
+    /// PATH: the application on macOS, the executable on Windows
+    pub fn install_locations(self) -> Vec<PathBuf> {
+        #[cfg(target_os = "windows")]
+        {
+            let (dir, exe, display_names) = match self {
+                Editor::Stable => (
+                    "Microsoft VS Code",
+                    "Code.exe",
+                    [
+                        "Microsoft Visual Studio Code",
+                        "Microsoft Visual Studio Code (User)",
+                    ],
+                ),
+                Editor::Insiders => (
+                    "Microsoft VS Code Insiders",
+                    "Code - Insiders.exe",
+                    [
+                        "Microsoft Visual Studio Code - Insiders",
+                        "Microsoft Visual Studio Code - Insiders (User)",
+                    ],
+                ),
+                Editor::Vscodium => ("VSCodium", "VSCodium.exe", ["VSCodium", "VSCodium (User)"]),
+            };
+
+            // The default locations (per user since VS Code's user
+            // installer, machine wide before), wherever the installers'
+            // uninstall entries say, and a portable copy that's running
+            let mut install_dirs: Vec<PathBuf> = windows_install_roots()
+                .into_iter()
+                .map(|root| root.join(dir))
+                .collect();
+            install_dirs.extend(super::windows::uninstall_locations(&display_names));
+            if let Some(data) = std::env::var_os("VSCODE_PORTABLE") {
+                install_dirs.extend(Path::new(&data).parent().map(Path::to_path_buf));
+            }
+
+            let mut locations: Vec<PathBuf> = Vec::new();
+            for location in install_dirs.into_iter().map(|dir| dir.join(exe)) {
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+            locations
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let app = match self {
+                Editor::Stable => "Visual Studio Code.app",
+                Editor::Insiders => "Visual Studio Code - Insiders.app",
+                Editor::Vscodium => "VSCodium.app",
+            };
+            let mut locations = vec![PathBuf::from("/Applications").join(app)];
+            if let Some(home) = dirs::home_dir() {
+                locations.push(home.join("Applications").join(app));
+            }
+            locations
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            Vec::new()
+        }
+    }
+
+    /// The editor's CLI as found on PATH
+    pub fn cli_on_path(self) -> Option<PathBuf> {
+        let path = std::env::var_os("PATH")?;
+        let names = if cfg!(target_os = "windows") {
+            vec![
+                format!("{}.cmd", self.command()),
+                format!("{}.exe", self.command()),
+            ]
+        } else {
+            vec![self.command().to_string()]
+        };
+
+        std::env::split_paths(&path)
+            .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+            .find(|cli| cli.is_file())
+    }
+
+    /// The CLI shipped inside an install location, which works whether or
+    /// not it's on PATH
+    pub fn bundled_cli(self, location: &Path) -> PathBuf {
+        #[cfg(target_os = "windows")]
+        {
+            location
+                .with_file_name("bin")
+                .join(format!("{}.cmd", self.command()))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            location
+                .join("Contents")
+                .join("Resources")
+                .join("app")
+                .join("bin")
+                .join(self.command())
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            location.join("bin").join(self.command())
+        }
+    }
+
+    /// The CLI to run for this editor: the one on PATH, or else the one in
+    /// an installed copy, as installers don't always put it on PATH
+    pub fn cli(self) -> PathBuf {
+        if let Some(cli) = self.cli_on_path() {
+            return cli;
+        }
+
+        self.install_locations()
+            .iter()
+            .filter(|location| location.exists())
+            .map(|location| self.bundled_cli(location))
+            .find(|cli| cli.is_file())
+            .unwrap_or_else(|| PathBuf::from(self.command()))
+    }
+}
+
+impl std::fmt::Display for Editor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.display_name())
+    }
+}
+
+/// Directories the Windows installers put editors in: per user, then
+/// machine wide
+#[cfg(target_os = "windows")]
+fn windows_install_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(local) = dirs::data_local_dir() {
+        roots.push(local.join("Programs"));
+    }
+    // ProgramW6432 is the native Program Files even when this runs as an
+    // emulated x64 process on ARM, and ProgramFiles(Arm) is where ARM
+    // builds go on older Windows on ARM
+    for var in [
+        "ProgramFiles",
+        "ProgramW6432",
+        "ProgramFiles(Arm)",
+        "ProgramFiles(x86)",
+    ] {
+        if let Some(dir) = std::env::var_os(var).map(PathBuf::from) {
+            if !roots.contains(&dir) {
+                roots.push(dir);
+            }
+        }
+    }
+    roots
+}
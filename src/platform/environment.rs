@@ -0,0 +1,206 @@
+//! The user's persistent environment: variables and PATH entries that new
+//! shells start with, and this process's PATH. Tools and the config code
+//! change them through the `Environment` in their `Host`, so tests can pass
+//! a fake in place of `System` and check what an install sets without
+//! touching the registry or shell profiles.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "macos")]
+use super::macos;
+#[cfg(target_os = "windows")]
+use super::{powershell, windows};
+
+pub trait Environment {
+    /// Set an environment variable persistently for the user
+    ///
+    /// With `dry_run`, print where it would be set instead.
+    fn set_user_env_var(&self, name: &str, value: &str, dry_run: bool) -> anyhow::Result<()>;
+
+    /// Its persistent value; `None` if it isn't set
+    fn get_user_env_var(&self, name: &str) -> Option<String>;
+
+    /// Remove a persistent variable; a no-op if it isn't set
+    ///
+    /// With `dry_run`, print where it would be removed from instead.
+    fn unset_user_env_var(&self, name: &str, dry_run: bool) -> anyhow::Result<()>;
+
+    /// Add a directory to the user's PATH
+    ///
+    /// On Windows, this refuses to push the user PATH past the length
+    /// Windows keeps whole unless `force` is set. With `dry_run`, print where
+    /// it would be added instead.
+    fn add_to_path(&self, dir: &str, force: bool, dry_run: bool) -> anyhow::Result<()>;
+
+    /// Remove a directory from the user's PATH; a no-op if it isn't there
+    ///
+    /// With `dry_run`, print where it would be removed from instead.
+    fn remove_from_path(&self, dir: &str, dry_run: bool) -> anyhow::Result<()>;
+
+    /// Whether the user's saved PATH has `dir`
+    fn is_on_user_path(&self, dir: &str) -> bool;
+
+    /// Put `dir` on this process's PATH, so later steps of the same run, and
+    /// anything they start, find what was installed there
+    fn add_to_process_path(&self, dir: &Path);
+
+    /// The directories on this process's PATH, in order
+    fn process_path(&self) -> Vec<PathBuf>;
+
+    /// This process's value of `name`; `None` if it isn't set
+    fn process_env_var(&self, name: &str) -> Option<String>;
+
+    /// Its persistent value, or this process's where that can't be read
+    fn current_env_var(&self, name: &str) -> Option<String> {
+        self.get_user_env_var(name).or_else(|| self.process_env_var(name))
+    }
+}
+
+/// The registry or PowerShell profile on Windows (see `EnvMode`) and the
+/// shell profiles on macOS
+pub struct System;
+
+impl Environment for System {
+    fn set_user_env_var(&self, name: &str, value: &str, dry_run: bool) -> anyhow::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            let mode = super::env_mode();
+            if mode.registry() {
+                windows::set_user_env_var(name, value, dry_run)?;
+            }
+            if mode.profile() {
+                powershell::set_user_env_var(name, value, dry_run)?;
+            }
+            return Ok(());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            return macos::set_user_env_var(name, value, dry_run);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = (name, value, dry_run);
+            anyhow::bail!("Linux is not supported")
+        }
+    }
+
+    fn get_user_env_var(&self, name: &str) -> Option<String> {
+        #[cfg(target_os = "windows")]
+        {
+            return windows::get_user_env_var(name)
+                .or_else(|| powershell::find_user_env_var(name).map(|(value, _)| value));
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            return macos::get_user_env_var(name);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = name;
+            None
+        }
+    }
+
+    fn unset_user_env_var(&self, name: &str, dry_run: bool) -> anyhow::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            // Both, whatever the mode, so switching modes leaves nothing
+            // behind
+            windows::unset_user_env_var(name, dry_run)?;
+            return powershell::unset_user_env_var(name, dry_run);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            return macos::unset_user_env_var(name, dry_run);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = (name, dry_run);
+            anyhow::bail!("Linux is not supported")
+        }
+    }
+
+    fn add_to_path(&self, dir: &str, force: bool, dry_run: bool) -> anyhow::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            return windows::add_to_path(dir, force, dry_run);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let _ = force;
+            return macos::add_to_path(dir, dry_run);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = (dir, force, dry_run);
+            anyhow::bail!("Linux is not supported")
+        }
+    }
+
+    fn remove_from_path(&self, dir: &str, dry_run: bool) -> anyhow::Result<()> {
+        #[cfg(target_os = "windows")]
+        {
+            return windows::remove_from_path(dir, dry_run);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            return macos::remove_from_path(dir, dry_run);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = (dir, dry_run);
+            anyhow::bail!("Linux is not supported")
+        }
+    }
+
+    fn is_on_user_path(&self, dir: &str) -> bool {
+        #[cfg(target_os = "windows")]
+        {
+            return windows::path_has(dir);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            return macos::path_has(dir);
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let _ = dir;
+            false
+        }
+    }
+
+    fn add_to_process_path(&self, dir: &Path) {
+        let current = std::env::var_os("PATH").unwrap_or_default();
+        let mut dirs: Vec<PathBuf> = std::env::split_paths(&current).collect();
+        if dirs.iter().any(|entry| entry == dir) {
+            return;
+        }
+        dirs.push(dir.to_path_buf());
+        match std::env::join_paths(dirs) {
+            Ok(path) => std::env::set_var("PATH", path),
+            Err(e) => tracing::debug!("could not add {} to PATH: {}", dir.display(), e),
+        }
+    }
+
+    fn process_path(&self) -> Vec<PathBuf> {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default()
+    }
+
+    fn process_env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
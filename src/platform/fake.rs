@@ -0,0 +1,84 @@
+//! An `Environment` that keeps the user's variables and PATH in memory and
+//! records each change in order
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::Environment;
+
+#[derive(Default)]
+pub struct FakeEnvironment {
+    vars: RefCell<BTreeMap<String, String>>,
+    path: RefCell<Vec<String>>,
+    process_path: RefCell<Vec<PathBuf>>,
+    changes: RefCell<Vec<String>>,
+}
+
+impl FakeEnvironment {
+    /// Every change made so far, such as `set NAME=value` or `add to PATH dir`
+    pub fn changes(&self) -> Vec<String> {
+        self.changes.borrow().clone()
+    }
+
+    fn record(&self, change: String) {
+        self.changes.borrow_mut().push(change);
+    }
+}
+
+impl Environment for FakeEnvironment {
+    fn set_user_env_var(&self, name: &str, value: &str, dry_run: bool) -> anyhow::Result<()> {
+        if !dry_run {
+            self.vars
+                .borrow_mut()
+                .insert(name.to_string(), value.to_string());
+            self.record(format!("set {}={}", name, value));
+        }
+        Ok(())
+    }
+
+    fn get_user_env_var(&self, name: &str) -> Option<String> {
+        self.vars.borrow().get(name).cloned()
+    }
+
+    fn unset_user_env_var(&self, name: &str, dry_run: bool) -> anyhow::Result<()> {
+        if !dry_run && self.vars.borrow_mut().remove(name).is_some() {
+            self.record(format!("unset {}", name));
+        }
+        Ok(())
+    }
+
+    fn add_to_path(&self, dir: &str, _force: bool, dry_run: bool) -> anyhow::Result<()> {
+        if !dry_run && !self.is_on_user_path(dir) {
+            self.path.borrow_mut().push(dir.to_string());
+            self.record(format!("add to PATH {}", dir));
+        }
+        Ok(())
+    }
+
+    fn remove_from_path(&self, dir: &str, dry_run: bool) -> anyhow::Result<()> {
+        if !dry_run && self.is_on_user_path(dir) {
+            self.path.borrow_mut().retain(|entry| entry != dir);
+            self.record(format!("remove from PATH {}", dir));
+        }
+        Ok(())
+    }
+
+    fn is_on_user_path(&self, dir: &str) -> bool {
+        self.path.borrow().iter().any(|entry| entry == dir)
+    }
+
+    fn add_to_process_path(&self, dir: &Path) {
+        self.process_path.borrow_mut().push(dir.to_path_buf());
+        self.record(format!("add to process PATH {}", dir.display()));
+    }
+
+    fn process_path(&self) -> Vec<PathBuf> {
+        self.process_path.borrow().clone()
+    }
+
+    fn process_env_var(&self, _name: &str) -> Option<String> {
+        // Only the user's variables are faked; the process has none of its own
+        None
+    }
+}
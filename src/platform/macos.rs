@@ -1,150 +1,659 @@
 use super::PlatformPaths;
+use crate::output::reporter;
+use crate::plan;
+use crate::process;
 use anyhow::{Context, Result};
 use console::style;
-use std::path::PathBuf;
-
-pub fn get_paths() -> PlatformPaths {
-    let home_dir = dirs::home_dir().expect("Could not determine home directory");
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
+pub fn get_paths(home_dir: PathBuf) -> PlatformPaths {
+    let app_data_dir = home_dir.join("Library").join("Application Support");
+    let support_dir = app_data_dir.join("code-assist");
     PlatformPaths {
-        home_dir: home_dir.clone(),
         claude_config_dir: home_dir.join(".claude"),
-        vscode_settings_dir: home_dir
-            .join("Library")
-            .join("Application Support")
-            .join("Code")
-            .join("User"),
         certs_dir: home_dir.join("certs"),
+        vscode_user_dir: None,
+        downloads_dir: home_dir
+            .join("Library")
+            .join("Caches")
+            .join("code-assist")
+            .join("downloads"),
+        state_dir: support_dir.join("state"),
+        backups_dir: support_dir.join("backups"),
+        app_data_dir,
+        home_dir,
     }
 }
 
-pub fn print_install_instructions() {
-    println!(
-        "{}\n",
-        style("Please install the missing software via Self-Service:").yellow()
-    );
-    println!("  1. Open Self-Service from your Applications folder or Dock");
-    println!("  2. Search for and install:");
-    println!("     - Visual Studio Code");
-    println!("     - Git (or Xcode Command Line Tools)");
-    println!("\nOnce installed, run this command again.");
+pub fn print_install_instructions(missing: &[String]) {
+    let software = super::software_to_install(missing, "Git (or Xcode Command Line Tools)");
+    if software.is_empty() {
+        return;
+    }
+    reporter().line(style("Please install the missing software via Self-Service:").yellow());
+    reporter().blank();
+    reporter().line("  1. Open Self-Service from your Applications folder or Dock");
+    reporter().line("  2. Search for and install:");
+    for name in &software {
+        reporter().line(format!("     - {}", name));
+    }
+    if super::needs_node(missing) {
+        reporter().blank();
+        reporter().line("Or install Node.js yourself with nvm (https://github.com/nvm-sh/nvm):");
+        reporter().line("  nvm install --lts");
+    }
+    reporter().blank();
+    reporter().line("Once installed, run this command again.");
 }
 
-pub fn set_user_env_var(name: &str, value: &str) -> Result<()> {
-    // On macOS, we add to shell config files
-    let home = dirs::home_dir().context("Could not determine home directory")?;
+/// The line each POSIX shell profile gets to pick up `~/.claude/env.sh`
+const SOURCE_LINE: &str = r#"[ -f "$HOME/.claude/env.sh" ] && . "$HOME/.claude/env.sh""#;
 
-    // Determine which shell config to use
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+/// Profiles that get the source line when they exist, covering login and
+/// interactive zsh and bash
+const PROFILES: [&str; 4] = [".zshrc", ".zprofile", ".bash_profile", ".profile"];
 
-    let config_file = if shell.contains("zsh") {
-        home.join(".zshrc")
-    } else if shell.contains("bash") {
-        // On macOS, .bash_profile is typically used for login shells
-        home.join(".bash_profile")
-    } else {
-        home.join(".profile")
-    };
+/// A file holding persistent variables and PATH entries
+struct ShellConfig {
+    path: PathBuf,
+    /// fish has its own syntax
+    fish: bool,
+    /// Files we create ourselves are deleted once nothing is left in them
+    owned: bool,
+}
 
-    let export_line = format!("export {}=\"{}\"", name, value);
+impl ShellConfig {
+    /// `~/.claude/env.sh`, which every POSIX shell profile sources
+    fn snippet(home: &Path) -> Self {
+        Self {
+            path: home.join(".claude").join("env.sh"),
+            fish: false,
+            owned: true,
+        }
+    }
 
-    // Read existing content
-    let existing = std::fs::read_to_string(&config_file).unwrap_or_default();
+    fn fish(home: &Path) -> Self {
+        Self {
+            path: home
+                .join(".config")
+                .join("fish")
+                .join("conf.d")
+                .join("code-assist.fish"),
+            fish: true,
+            owned: true,
+        }
+    }
 
-    // Check if already set
-    if existing.contains(&format!("export {}=", name)) {
-        // Update existing line
-        let updated: Vec<String> = existing
-            .lines()
-            .map(|line| {
-                if line.trim_start().starts_with(&format!("export {}=", name)) {
-                    export_line.clone()
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect();
-        std::fs::write(&config_file, updated.join("\n") + "\n")
-            .context("Failed to update shell config")?;
-    } else {
-        // Append new line
+    fn profile(path: PathBuf) -> Self {
+        Self {
+            path,
+            fish: false,
+            owned: false,
+        }
+    }
+
+    /// Where variables are written: the snippet, plus fish's own file when
+    /// fish is the login shell, since it can't read the snippet
+    fn targets() -> Result<Vec<Self>> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        let mut targets = vec![Self::snippet(&home)];
+        if login_shell().contains("fish") {
+            targets.push(Self::fish(&home));
+        }
+        Ok(targets)
+    }
+
+    /// Every file variables could have been written to, including each
+    /// profile, which held them directly before the snippet existed
+    fn all() -> Result<Vec<Self>> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        let mut all = vec![Self::snippet(&home), Self::fish(&home)];
+        all.extend(PROFILES.map(|name| Self::profile(home.join(name))));
+        Ok(all)
+    }
+
+    /// Start of a line that sets `name`
+    fn var_prefix(&self, name: &str) -> String {
+        if self.fish {
+            format!("set -gx {} ", name)
+        } else {
+            format!("export {}=", name)
+        }
+    }
+
+    fn var_line(&self, name: &str, value: &str) -> String {
+        format!("{}\"{}\"", self.var_prefix(name), value)
+    }
+
+    fn path_line(&self, dir: &str) -> String {
+        if self.fish {
+            // -g keeps it out of fish's universal variables, which would
+            // outlive this file
+            format!("fish_add_path -g \"{}\"", dir)
+        } else {
+            format!("export PATH=\"{}:$PATH\"", dir)
+        }
+    }
+
+    fn read(&self) -> String {
+        std::fs::read_to_string(&self.path).unwrap_or_default()
+    }
+
+    /// Append `line` under the "# Added by code-assist" marker
+    fn append(&self, line: &str) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create shell config directory")?;
+        }
         let mut file = std::fs::OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&config_file)
+            .open(&self.path)
             .context("Failed to open shell config")?;
 
         use std::io::Write;
         writeln!(file, "\n# Added by code-assist")?;
-        writeln!(file, "{}", export_line)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
     }
 
-    Ok(())
+    /// Remove lines matching `is_ours`, along with the "# Added by
+    /// code-assist" marker and blank line written before them, and return
+    /// whether an owned file was (or would be) left empty and deleted
+    fn remove_lines(
+        &self,
+        is_ours: impl Fn(&str) -> bool,
+        what: &str,
+        dry_run: bool,
+    ) -> Result<bool> {
+        let existing = self.read();
+        if !existing.lines().any(&is_ours) {
+            return Ok(false);
+        }
+
+        let mut kept: Vec<&str> = Vec::new();
+        for line in existing.lines() {
+            if !is_ours(line) {
+                kept.push(line);
+                continue;
+            }
+            if kept.last() == Some(&"# Added by code-assist") {
+                kept.pop();
+                if kept.last().is_some_and(|l| l.trim().is_empty()) {
+                    kept.pop();
+                }
+            }
+        }
+        let emptied = self.owned && kept.iter().all(|l| l.trim().is_empty());
+
+        if dry_run {
+            if emptied {
+                plan::step(format!("Remove {}", self.path.display()));
+            } else {
+                plan::step(format!("Remove {} from {}", what, self.path.display()));
+            }
+        } else if emptied {
+            tracing::debug!("removing {}", self.path.display());
+            std::fs::remove_file(&self.path).context("Failed to remove shell config")?;
+        } else {
+            tracing::debug!("removing {} from {}", what, self.path.display());
+            std::fs::write(&self.path, kept.join("\n") + "\n")
+                .context("Failed to update shell config")?;
+        }
+        Ok(emptied)
+    }
+}
+
+fn login_shell() -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string())
 }
 
-pub fn add_to_path(dir: &str) -> Result<()> {
+/// Make sure the shell profiles source the snippet: each one in `PROFILES`
+/// that exists, or the login shell's if none do
+fn source_snippet(dry_run: bool) -> Result<()> {
     let home = dirs::home_dir().context("Could not determine home directory")?;
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+    let mut profiles: Vec<PathBuf> = PROFILES
+        .iter()
+        .map(|name| home.join(name))
+        .filter(|path| path.exists())
+        .collect();
+    if profiles.is_empty() {
+        let shell = login_shell();
+        if shell.contains("fish") {
+            return Ok(());
+        }
+        profiles.push(home.join(if shell.contains("zsh") {
+            ".zshrc"
+        } else if shell.contains("bash") {
+            // On macOS, .bash_profile is typically used for login shells
+            ".bash_profile"
+        } else {
+            ".profile"
+        }));
+    }
+
+    for path in profiles {
+        let profile = ShellConfig::profile(path);
+        if profile
+            .read()
+            .lines()
+            .any(|line| line.trim() == SOURCE_LINE)
+        {
+            continue;
+        }
+        if dry_run {
+            plan::step(format!(
+                "Source ~/.claude/env.sh from {}",
+                profile.path.display()
+            ));
+            continue;
+        }
+        tracing::debug!("sourcing env.sh from {}", profile.path.display());
+        profile.append(SOURCE_LINE)?;
+    }
+    Ok(())
+}
+
+/// Remove lines matching `is_ours` from every file variables could have been
+/// written to, and stop sourcing the snippet once it's gone
+fn remove_added_lines(
+    is_ours: impl Fn(&ShellConfig, &str) -> bool,
+    what: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let mut snippet_removed = false;
+    for config in ShellConfig::all()? {
+        let emptied = config.remove_lines(|line| is_ours(&config, line), what, dry_run)?;
+        snippet_removed |= emptied && config.owned && !config.fish;
+    }
+
+    if snippet_removed {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        for name in PROFILES {
+            ShellConfig::profile(home.join(name)).remove_lines(
+                |line| line.trim() == SOURCE_LINE,
+                "source line for ~/.claude/env.sh",
+                dry_run,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `launchctl` so apps launched from the Dock or Spotlight, which don't
+/// read shell profiles, see the change without logging out
+fn launchctl(args: &[&str], dry_run: bool) {
+    if dry_run {
+        plan::step(format!("Run 'launchctl {}'", args.join(" ")));
+        return;
+    }
+    match process::output(std::process::Command::new("launchctl").args(args)) {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::debug!(
+            "launchctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => tracing::debug!("could not run launchctl: {}", e),
+    }
+}
+
+pub fn set_user_env_var(name: &str, value: &str, dry_run: bool) -> Result<()> {
+    // On macOS, we add to shell config files
+    for config in ShellConfig::targets()? {
+        let prefix = config.var_prefix(name);
+        let export_line = config.var_line(name, value);
+
+        if dry_run {
+            plan::step(format!(
+                "Write '{}' to {}",
+                export_line,
+                config.path.display()
+            ));
+            continue;
+        }
+
+        // Read existing content
+        let existing = config.read();
+
+        // Check if already set
+        if existing.contains(&prefix) {
+            // Update existing line
+            let updated: Vec<String> = existing
+                .lines()
+                .map(|line| {
+                    if line.trim_start().starts_with(&prefix) {
+                        export_line.clone()
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect();
+            tracing::debug!("updating {} in {}", name, config.path.display());
+            std::fs::write(&config.path, updated.join("\n") + "\n")
+                .context("Failed to update shell config")?;
+        } else {
+            // Append new line
+            tracing::debug!("appending {} to {}", name, config.path.display());
+            config.append(&export_line)?;
+        }
+    }
+    // Exports written straight into a profile by older versions come before
+    // the source line appended now, so the snippet's value wins
+    source_snippet(dry_run)?;
+
+    launchctl(&["setenv", name, value], dry_run);
+    Ok(())
+}
+
+/// The value of the last line setting `name` in the snippet, or in the
+/// login shell's own config for installs from before it, unquoted and with
+/// a leading `~` or `$HOME` expanded
+pub fn get_user_env_var(name: &str) -> Option<String> {
+    find_user_env_var(name).map(|(value, _)| value)
+}
 
-    let config_file = if shell.contains("zsh") {
-        home.join(".zshrc")
+/// The value of the last export of `name` and the file and line it's on
+pub fn find_user_env_var(name: &str) -> Option<(String, String)> {
+    let home = dirs::home_dir()?;
+    let shell = login_shell();
+    let own = if shell.contains("fish") {
+        ShellConfig::fish(&home)
+    } else if shell.contains("zsh") {
+        ShellConfig::profile(home.join(".zshrc"))
     } else if shell.contains("bash") {
-        home.join(".bash_profile")
+        ShellConfig::profile(home.join(".bash_profile"))
     } else {
-        home.join(".profile")
+        ShellConfig::profile(home.join(".profile"))
     };
 
-    let path_line = format!("export PATH=\"{}:$PATH\"", dir);
+    let (value, location) = [ShellConfig::snippet(&home), own]
+        .iter()
+        .find_map(|config| {
+            let prefix = config.var_prefix(name);
+            let existing = std::fs::read_to_string(&config.path).ok()?;
+            let (index, value) = existing
+                .lines()
+                .enumerate()
+                .filter_map(|(i, line)| Some((i, line.trim().strip_prefix(&prefix)?)))
+                .next_back()?;
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            Some((
+                value.to_string(),
+                format!("{}:{}", config.path.display(), index + 1),
+            ))
+        })?;
 
-    let existing = std::fs::read_to_string(&config_file).unwrap_or_default();
+    let home = home.to_string_lossy();
+    let value = match value
+        .strip_prefix('~')
+        .or_else(|| value.strip_prefix("$HOME"))
+    {
+        Some(rest) => format!("{}{}", home, rest),
+        None => value.replace("${HOME}", &home),
+    };
+    Some((value, location)).filter(|(v, _)| !v.is_empty())
+}
+
+pub fn add_to_path(dir: &str, dry_run: bool) -> Result<()> {
+    for config in ShellConfig::targets()? {
+        let path_line = config.path_line(dir);
+
+        // Check if this path is already added
+        if config.read().contains(dir) {
+            continue;
+        }
+
+        if dry_run {
+            plan::step(format!(
+                "Write '{}' to {}",
+                path_line,
+                config.path.display()
+            ));
+            continue;
+        }
+
+        tracing::debug!("appending {} to PATH in {}", dir, config.path.display());
+        config.append(&path_line)?;
+    }
+    source_snippet(dry_run)
+}
+
+/// The PATH entry of `--system` installs, which `path_helper` adds for every
+/// user's login shell
+const SYSTEM_PATHS_FILE: &str = "/etc/paths.d/claude";
 
-    // Check if this path is already added
-    if existing.contains(dir) {
+pub fn add_to_system_path(dir: &str, dry_run: bool) -> Result<()> {
+    let paths_file = Path::new(SYSTEM_PATHS_FILE);
+    let existing = std::fs::read_to_string(paths_file).unwrap_or_default();
+    if existing.lines().any(|line| line.trim() == dir) {
         return Ok(());
     }
 
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&config_file)
-        .context("Failed to open shell config")?;
+    if dry_run {
+        plan::step(format!("Write {} to {}", dir, SYSTEM_PATHS_FILE));
+        return Ok(());
+    }
+
+    tracing::debug!("writing {} to {}", dir, SYSTEM_PATHS_FILE);
+    std::fs::write(paths_file, format!("{}\n", dir))
+        .with_context(|| format!("Failed to write {}", SYSTEM_PATHS_FILE))
+}
 
-    use std::io::Write;
-    writeln!(file, "\n# Added by code-assist")?;
-    writeln!(file, "{}", path_line)?;
+/// Remove the `--system` PATH entry; the file holds nothing else, so it goes
+pub fn remove_from_system_path(dir: &str, dry_run: bool) -> Result<()> {
+    let paths_file = Path::new(SYSTEM_PATHS_FILE);
+    let existing = std::fs::read_to_string(paths_file).unwrap_or_default();
+    if !existing.lines().any(|line| line.trim() == dir) {
+        return Ok(());
+    }
 
+    if dry_run {
+        plan::step(format!("Remove {}", SYSTEM_PATHS_FILE));
+        return Ok(());
+    }
+
+    tracing::debug!("removing {}", SYSTEM_PATHS_FILE);
+    std::fs::remove_file(paths_file)
+        .with_context(|| format!("Failed to remove {}", SYSTEM_PATHS_FILE))
+}
+
+/// Whether a shell config adds `dir` to PATH, including a profile that did
+/// so directly before the snippet existed
+pub fn path_has(dir: &str) -> bool {
+    find_on_path(dir).is_some()
+}
+
+/// The file and line of the first shell config line adding `dir` to PATH
+pub fn find_on_path(dir: &str) -> Option<String> {
+    ShellConfig::all().ok()?.iter().find_map(|config| {
+        let path_line = config.path_line(dir);
+        let index = config
+            .read()
+            .lines()
+            .position(|line| line.trim() == path_line)?;
+        Some(format!("{}:{}", config.path.display(), index + 1))
+    })
+}
+
+/// How to pick up the new PATH in a terminal that was already open
+pub fn path_refresh_steps() -> Vec<String> {
+    let source = if login_shell().contains("fish") {
+        "source ~/.config/fish/conf.d/code-assist.fish"
+    } else {
+        ". ~/.claude/env.sh"
+    };
+    vec![
+        format!("In this terminal, run: {}", source),
+        "Or start a fresh login shell with 'exec $SHELL -l', or open a new terminal".to_string(),
+    ]
+}
+
+pub fn unset_user_env_var(name: &str, dry_run: bool) -> Result<()> {
+    remove_added_lines(
+        |config, line| line.trim_start().starts_with(&config.var_prefix(name)),
+        &format!("export of {}", name),
+        dry_run,
+    )?;
+    launchctl(&["unsetenv", name], dry_run);
     Ok(())
 }
 
-pub fn import_certificate(cert_path: &std::path::Path) -> Result<()> {
-    let home = dirs::home_dir().context("Could not determine home directory")?;
-    let keychain = home.join("Library/Keychains/login.keychain-db");
-
-    let output = std::process::Command::new("security")
-        .args([
-            "add-trusted-cert",
-            "-k",
-            keychain.to_str().unwrap(),
-            cert_path.to_str().unwrap(),
-        ])
-        .output()
-        .context("Failed to run security command")?;
+pub fn remove_from_path(dir: &str, dry_run: bool) -> Result<()> {
+    remove_added_lines(
+        |config, line| line.trim() == config.path_line(dir),
+        &format!("PATH entry for {}", dir),
+        dry_run,
+    )
+}
+
+pub fn import_certificate(
+    cert_path: &std::path::Path,
+    thumbprints: &[String],
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    // A certificate anywhere on the search list, such as one an MDM put in
+    // the System keychain, is already trusted; importing it again only
+    // prompts for the keychain password
+    let keychain = login_keychain()?;
+    let present = keychain_thumbprints(None);
+    let missing: Vec<String> = thumbprints
+        .iter()
+        .filter(|thumbprint| !present.contains(thumbprint))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Trust {} in {}",
+            cert_path.display(),
+            keychain.display()
+        ));
+        return Ok(missing);
+    }
+
+    let output = process::output(
+        std::process::Command::new("security")
+            .args(["add-trusted-cert", "-k"])
+            .arg(&keychain)
+            .arg(cert_path),
+    )
+    .context("Failed to run security command")?;
 
     if !output.status.success() {
         // If security command fails, try opening the cert for manual import
-        println!(
-            "{} Automatic certificate import failed. Opening certificate for manual import...",
-            style("!").yellow().bold()
-        );
+        reporter()
+            .warn("Automatic certificate import failed. Opening certificate for manual import...");
+        tracing::debug!("spawning open {}", cert_path.display());
         std::process::Command::new("open")
             .arg(cert_path)
             .spawn()
             .context("Failed to open certificate")?;
     }
 
+    Ok(missing)
+}
+
+pub fn remove_certificate(thumbprint: &str, dry_run: bool) -> Result<()> {
+    let keychain = login_keychain()?;
+    let present = keychain_thumbprints(Some(&keychain));
+    if !present.iter().any(|t| t == thumbprint) {
+        return Ok(());
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Remove certificate {} from {}",
+            thumbprint,
+            keychain.display()
+        ));
+        return Ok(());
+    }
+
+    let output = process::output(
+        std::process::Command::new("security")
+            .args(["delete-certificate", "-Z", thumbprint])
+            .arg(&keychain),
+    )
+    .context("Failed to run security command")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "security could not remove {}: {}",
+            thumbprint,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
     Ok(())
 }
 
+/// The user's default keychain, as `security` reports it; older accounts
+/// still have `login.keychain` rather than `login.keychain-db`
+fn login_keychain() -> Result<PathBuf> {
+    let reported = process::output(std::process::Command::new("security").arg("default-keychain"))
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .trim_matches('"')
+                .to_string()
+        })
+        .filter(|path| !path.is_empty());
+    if let Some(path) = reported {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    let keychains = home.join("Library").join("Keychains");
+    let legacy = keychains.join("login.keychain");
+    if legacy.exists() && !keychains.join("login.keychain-db").exists() {
+        return Ok(legacy);
+    }
+    Ok(keychains.join("login.keychain-db"))
+}
+
+/// SHA-1 thumbprints of the certificates in `keychain`, or in every keychain
+/// on the search list, including the System one, if `None`
+fn keychain_thumbprints(keychain: Option<&Path>) -> Vec<String> {
+    let mut command = std::process::Command::new("security");
+    command.args(["find-certificate", "-a", "-Z"]);
+    if let Some(keychain) = keychain {
+        command.arg(keychain);
+    }
+    let Ok(output) = process::output(&mut command) else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("SHA-1 hash:"))
+        .map(|hash| hash.trim().to_uppercase())
+        .collect()
+}
+
+/// Whether this process is an x64 build running under Rosetta; asked once,
+/// as it doesn't change while the process runs
+pub fn is_translated() -> bool {
+    static TRANSLATED: OnceLock<bool> = OnceLock::new();
+    *TRANSLATED.get_or_init(|| {
+        // Intel Macs don't have the key, so sysctl fails there
+        match process::output(
+            std::process::Command::new("sysctl").args(["-n", "sysctl.proc_translated"]),
+        ) {
+            Ok(output) => {
+                output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "1"
+            }
+            Err(e) => {
+                tracing::debug!("could not run sysctl: {}", e);
+                false
+            }
+        }
+    })
+}
+
 /// Check if VS Code is installed on macOS
 pub fn check_vscode_installed() -> bool {
     // Check Application folder
@@ -170,7 +679,22 @@ pub fn check_git_installed() -> bool {
         .unwrap_or(false)
 }
 
-/// Get the VS Code CLI path
-pub fn get_vscode_cli() -> &'static str {
-    "code"
+/// Remove the `com.apple.quarantine` attribute from `path`; a file without
+/// one is fine
+pub fn clear_quarantine(path: &Path) -> Result<()> {
+    let output = process::output(
+        std::process::Command::new("xattr")
+            .args(["-d", "com.apple.quarantine"])
+            .arg(path),
+    )
+    .context("Failed to run xattr")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !stderr.contains("No such xattr") {
+        anyhow::bail!(
+            "could not clear the quarantine on {}: {}",
+            path.display(),
+            stderr.trim()
+        );
+    }
+    Ok(())
 }
@@ -4,57 +4,337 @@ mod windows;
 #[cfg(target_os = "macos")]
 mod macos;
 
-use std::path::PathBuf;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod wsl;
+
+pub mod arch;
+mod editor;
+mod environment;
+#[cfg(test)]
+pub mod fake;
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+mod path_list;
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+mod powershell;
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+pub use editor::Editor;
+pub use environment::Environment;
+
+use crate::process::Runner;
+
+/// What tools and the config code change the machine through: the
+/// commands they run and the user's persistent environment. The binary
+/// uses `Host::system()`; tests pass fakes to see what an install would do.
+#[derive(Clone)]
+pub struct Host {
+    pub runner: Rc<dyn Runner>,
+    pub environment: Rc<dyn Environment>,
+}
+
+impl Host {
+    pub fn system() -> Self {
+        Host {
+            runner: Rc::new(crate::process::System),
+            environment: Rc::new(environment::System),
+        }
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Host::system()
+    }
+}
+
+impl std::fmt::Debug for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Host")
+    }
+}
 
 /// Platform-specific configuration paths
+#[derive(Debug, Clone)]
 pub struct PlatformPaths {
     pub home_dir: PathBuf,
     pub claude_config_dir: PathBuf,
-    pub vscode_settings_dir: PathBuf,
+    /// Where applications keep per-user data, such as each editor's
+    /// settings directory
+    pub app_data_dir: PathBuf,
     pub certs_dir: PathBuf,
+    /// A VS Code `User` directory to use in place of each editor's own
+    pub vscode_user_dir: Option<PathBuf>,
+    /// Downloaded binaries and manifests, kept out of roaming profiles
+    pub downloads_dir: PathBuf,
+    /// The per-tool records of what code-assist changed
+    pub state_dir: PathBuf,
+    /// Copies of settings files taken before a deploy changed them
+    pub backups_dir: PathBuf,
+}
+
+impl PlatformPaths {
+    /// Directory holding `editor`'s `settings.json`
+    pub fn vscode_settings_dir(&self, editor: Editor) -> PathBuf {
+        match &self.vscode_user_dir {
+            Some(dir) => dir.clone(),
+            None => self.app_data_dir.join(editor.data_dir_name()).join("User"),
+        }
+    }
+
+    /// Claude Code's own config file, which holds the user's MCP servers
+    pub fn claude_json(&self) -> PathBuf {
+        self.home_dir.join(".claude.json")
+    }
+}
+
+/// Directories moved away from where the platform keeps them, from
+/// `CODE_ASSIST_*_DIR` or the `[paths]` table of the config file
+#[derive(Debug, Default, Clone)]
+pub struct PathOverrides {
+    pub claude_dir: Option<PathBuf>,
+    pub certs_dir: Option<PathBuf>,
+    pub vscode_user_dir: Option<PathBuf>,
+}
+
+static PATHS: OnceLock<PlatformPaths> = OnceLock::new();
+
+/// Resolve the paths with `overrides` applied, and keep them for every
+/// later `get_paths`
+pub fn init_paths(overrides: &PathOverrides) -> anyhow::Result<PlatformPaths> {
+    let mut paths = default_paths()?;
+    if let Some(dir) = &overrides.claude_dir {
+        paths.claude_config_dir = dir.clone();
+    }
+    if let Some(dir) = &overrides.certs_dir {
+        paths.certs_dir = dir.clone();
+    }
+    paths.vscode_user_dir = overrides.vscode_user_dir.clone();
+    Ok(PATHS.get_or_init(|| paths).clone())
+}
+
+/// The paths `init_paths` resolved, or the platform's own before it runs
+pub fn get_paths() -> anyhow::Result<PlatformPaths> {
+    match PATHS.get() {
+        Some(paths) => Ok(paths.clone()),
+        None => default_paths(),
+    }
+}
+
+/// Point `get_paths` at a directory kept for the rest of the test run, so
+/// tests that reach the download cache never touch the real one
+#[cfg(test)]
+pub fn init_test_paths() -> PlatformPaths {
+    PATHS
+        .get_or_init(|| {
+            let root = tempfile::TempDir::new().unwrap().keep();
+            PlatformPaths {
+                claude_config_dir: root.join(".claude"),
+                app_data_dir: root.join("appdata"),
+                certs_dir: root.join("certs"),
+                vscode_user_dir: None,
+                downloads_dir: root.join("downloads"),
+                state_dir: root.join("state"),
+                backups_dir: root.join("backups"),
+                home_dir: root,
+            }
+        })
+        .clone()
+}
+
+/// Where Windows keeps the environment variables code-assist sets. PATH
+/// always stays in the registry, and macOS has its own shell profiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum EnvMode {
+    /// HKCU\Environment, which every new process sees
+    #[default]
+    Registry,
+    /// A marked block in the PowerShell `$PROFILE`, for machines where
+    /// policy locks the registry
+    Profile,
+    Both,
+}
+
+impl EnvMode {
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    fn registry(self) -> bool {
+        matches!(self, EnvMode::Registry | EnvMode::Both)
+    }
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    fn profile(self) -> bool {
+        matches!(self, EnvMode::Profile | EnvMode::Both)
+    }
+}
+
+static ENV_MODE: OnceLock<EnvMode> = OnceLock::new();
+
+/// Use `mode` for every later `set_user_env_var`
+pub fn init_env_mode(mode: EnvMode) {
+    let _ = ENV_MODE.set(mode);
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn env_mode() -> EnvMode {
+    ENV_MODE.get().copied().unwrap_or_default()
 }
 
 /// Get platform-specific paths
-pub fn get_paths() -> PlatformPaths {
+fn default_paths() -> anyhow::Result<PlatformPaths> {
+    let home_dir = home_dir()?;
+
     #[cfg(target_os = "windows")]
     {
-        return windows::get_paths();
+        return Ok(windows::get_paths(home_dir));
     }
 
     #[cfg(target_os = "macos")]
     {
-        return macos::get_paths();
+        return Ok(macos::get_paths(home_dir));
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
         // Linux/other - for development only
-        let home_dir = dirs::home_dir().expect("Could not determine home directory");
-        PlatformPaths {
-            home_dir: home_dir.clone(),
+        // Under WSL, VS Code runs on the Windows host and keeps its settings
+        // there; everything else stays in the Linux home, or under the XDG
+        // directories where the user moved them
+        let app_data_dir = match wsl::detect().then(wsl::host_app_data) {
+            Some(Ok(app_data)) => app_data.clone(),
+            _ => xdg_dir("XDG_CONFIG_HOME").unwrap_or_else(|| home_dir.join(".config")),
+        };
+        let cache_dir = xdg_dir("XDG_CACHE_HOME").unwrap_or_else(|| home_dir.join(".cache"));
+        let state_dir = xdg_dir("XDG_STATE_HOME")
+            .unwrap_or_else(|| home_dir.join(".local").join("state"))
+            .join("code-assist");
+        Ok(PlatformPaths {
             claude_config_dir: home_dir.join(".claude"),
-            vscode_settings_dir: home_dir.join(".config").join("Code").join("User"),
+            app_data_dir,
             certs_dir: home_dir.join("certs"),
+            vscode_user_dir: None,
+            downloads_dir: cache_dir.join("code-assist").join("downloads"),
+            backups_dir: state_dir.join("backups"),
+            state_dir,
+            home_dir,
+        })
+    }
+}
+
+/// An XDG base directory variable, which the spec ignores unless it's
+/// absolute
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn xdg_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+/// The directory everything is deployed under: `CODE_ASSIST_HOME` when set,
+/// for service accounts and locked-down profiles without a usable home,
+/// else the user's home directory
+fn home_dir() -> anyhow::Result<PathBuf> {
+    if let Some(home) = std::env::var_os("CODE_ASSIST_HOME").filter(|home| !home.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+    let variable = if cfg!(target_os = "windows") {
+        "USERPROFILE"
+    } else {
+        "HOME"
+    };
+    dirs::home_dir().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not determine the home directory; set {} or CODE_ASSIST_HOME",
+            variable
+        )
+    })
+}
+
+/// `path` as UTF-8, for the registry, shell profiles, and the state file,
+/// which can't hold anything else
+pub fn path_str(path: &Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow::anyhow!("{} is not valid UTF-8", path.display()))
+}
+
+/// Whether this is WSL, where VS Code runs on the Windows host
+pub fn is_wsl() -> bool {
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        false
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        wsl::detect()
+    }
+}
+
+/// Under WSL, why the Windows host's VS Code settings can't be reached, if
+/// they can't
+pub fn wsl_host_problem() -> Option<String> {
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        None
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        if !wsl::detect() {
+            return None;
         }
+        wsl::host_app_data().as_ref().err().cloned()
     }
 }
 
-/// Print platform-specific installation instructions for missing prerequisites
-pub fn print_install_instructions() {
+/// Print platform-specific installation instructions for the prerequisites
+/// named in `missing`
+pub fn print_install_instructions(missing: &[String]) {
     #[cfg(target_os = "windows")]
     {
-        windows::print_install_instructions();
+        windows::print_install_instructions(missing);
     }
 
     #[cfg(target_os = "macos")]
     {
-        macos::print_install_instructions();
+        macos::print_install_instructions(missing);
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
-        println!("Linux is not supported. Please use Windows or macOS.");
+        let _ = missing;
+        crate::output::reporter().line("Linux is not supported. Please use Windows or macOS.");
+    }
+}
+
+/// What to look for in the software catalog for the prerequisites named in
+/// `missing`, with `git` the platform's name for Git. Disk space can't be
+/// installed, so it's left out.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn software_to_install(missing: &[String], git: &str) -> Vec<String> {
+    let mut software: Vec<String> = Vec::new();
+    for name in missing {
+        let item = match name.as_str() {
+            "VS Code" => "Visual Studio Code",
+            "Git" => git,
+            "Node.js" | "npm" => "Node.js LTS (includes npm)",
+            "Disk space" => continue,
+            other => other,
+        };
+        if !software.iter().any(|known| known == item) {
+            software.push(item.to_string());
+        }
     }
+    software
+}
+
+/// Whether Node.js is among the prerequisites named in `missing`
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn needs_node(missing: &[String]) -> bool {
+    missing
+        .iter()
+        .any(|name| name == "Node.js" || name == "npm")
 }
 
 /// Get the platform identifier for downloads
@@ -64,6 +344,11 @@ pub fn get_platform_id() -> &'static str {
         return "win32-x64";
     }
 
+    #[cfg(all(target_os = "windows", target_arch = "aarch64"))]
+    {
+        return "win32-arm64";
+    }
+
     #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
     {
         return "darwin-x64";
@@ -76,6 +361,7 @@ pub fn get_platform_id() -> &'static str {
 
     #[cfg(not(any(
         all(target_os = "windows", target_arch = "x86_64"),
+        all(target_os = "windows", target_arch = "aarch64"),
         all(target_os = "macos", target_arch = "x86_64"),
         all(target_os = "macos", target_arch = "aarch64")
     )))]
@@ -85,6 +371,52 @@ pub fn get_platform_id() -> &'static str {
     }
 }
 
+/// Whether this process runs translated: an x64 build of code-assist under
+/// Rosetta on Apple Silicon
+pub fn is_translated() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::is_translated()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        false
+    }
+}
+
+/// Platform identifier of the machine itself, which differs from
+/// [`get_platform_id`] when this process runs translated
+pub fn get_native_platform_id() -> &'static str {
+    if is_translated() {
+        "darwin-arm64"
+    } else {
+        get_platform_id()
+    }
+}
+
+/// Platform whose builds this machine can run under emulation when there's
+/// no native one: x64 on Windows on ARM, and on Apple Silicon when this
+/// process runs under Rosetta
+pub fn get_emulated_platform_id() -> Option<&'static str> {
+    if cfg!(all(target_os = "windows", target_arch = "aarch64")) {
+        Some("win32-x64")
+    } else if is_translated() {
+        Some("darwin-x64")
+    } else {
+        None
+    }
+}
+
+/// This process's architecture, e.g. `x64 under Rosetta`
+pub fn process_arch() -> String {
+    if is_translated() {
+        format!("{} under Rosetta", arch::of_process())
+    } else {
+        arch::of_process().to_string()
+    }
+}
+
 /// Get the binary name for the platform
 pub fn get_binary_name() -> &'static str {
     #[cfg(target_os = "windows")]
@@ -94,63 +426,311 @@ pub fn get_binary_name() -> &'static str {
 
     #[cfg(not(target_os = "windows"))]
     {
-        return "claude";
+        "claude"
+    }
+}
+
+/// A user environment variable's persistent value and where it's saved,
+/// such as a shell file and line, for showing the user
+pub fn find_user_env_var(name: &str) -> Option<(String, String)> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::get_user_env_var(name)
+            .map(|value| (value, windows::ENVIRONMENT_KEY.to_string()))
+            .or_else(|| powershell::find_user_env_var(name));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        return macos::find_user_env_var(name);
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = name;
+        None
+    }
+}
+
+/// Where `--system` installs go, for every user of the machine
+pub fn system_install_dir() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::system_install_dir();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        PathBuf::from("/usr/local/claude/bin")
     }
 }
 
-/// Set an environment variable persistently for the user
-pub fn set_user_env_var(name: &str, value: &str) -> anyhow::Result<()> {
+/// Whether this process can change machine-wide locations: running as
+/// root, or from an elevated prompt on Windows
+pub fn is_elevated() -> bool {
     #[cfg(target_os = "windows")]
     {
-        return windows::set_user_env_var(name, value);
+        return windows::is_elevated();
+    }
+
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and can't fail
+        unsafe { libc::geteuid() == 0 }
+    }
+}
+
+/// Add a directory to the PATH every user of the machine gets
+///
+/// With `dry_run`, print where it would be added instead.
+pub fn add_to_system_path(dir: &str, dry_run: bool) -> anyhow::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::add_to_system_path(dir, dry_run);
     }
 
     #[cfg(target_os = "macos")]
     {
-        return macos::set_user_env_var(name, value);
+        return macos::add_to_system_path(dir, dry_run);
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
-        let _ = (name, value);
+        let _ = (dir, dry_run);
         anyhow::bail!("Linux is not supported")
     }
 }
 
-/// Add a directory to the user's PATH
-pub fn add_to_path(dir: &str) -> anyhow::Result<()> {
+/// Remove a directory from the PATH every user of the machine gets; a
+/// no-op if it isn't there
+///
+/// With `dry_run`, print where it would be removed from instead.
+pub fn remove_from_system_path(dir: &str, dry_run: bool) -> anyhow::Result<()> {
     #[cfg(target_os = "windows")]
     {
-        return windows::add_to_path(dir);
+        return windows::remove_from_system_path(dir, dry_run);
     }
 
     #[cfg(target_os = "macos")]
     {
-        return macos::add_to_path(dir);
+        return macos::remove_from_system_path(dir, dry_run);
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = (dir, dry_run);
+        anyhow::bail!("Linux is not supported")
+    }
+}
+
+/// Where the user's saved PATH gets `dir` from, if it has it
+pub fn find_on_user_path(dir: &str) -> Option<String> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::path_has(dir).then(|| format!("{}\\Path", windows::ENVIRONMENT_KEY));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        return macos::find_on_path(dir);
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
         let _ = dir;
+        None
+    }
+}
+
+/// How to bring a shell that was open before PATH changed up to date
+pub fn path_refresh_steps() -> Vec<String> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::path_refresh_steps();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        return macos::path_refresh_steps();
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        vec!["Open a new terminal".to_string()]
+    }
+}
+
+/// Put a directory first on the user's PATH, so its programs win over
+/// other copies
+///
+/// With `dry_run`, print the change instead.
+pub fn move_to_front_of_path(dir: &str, dry_run: bool) -> anyhow::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::move_to_front_of_path(dir, dry_run);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // The shell profiles decide the order, and they're the user's
+        let _ = (dir, dry_run);
+        anyhow::bail!(
+            "reorder it yourself: source ~/.claude/env.sh after anything else that \
+             changes PATH in your shell profile"
+        )
+    }
+}
+
+/// Whether removing a file failed because another process has it open,
+/// which on Windows is a running program or an editor holding it
+pub fn is_in_use(error: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    cfg!(target_os = "windows") && matches!(error.raw_os_error(), Some(32 | 33))
+}
+
+/// Whether a process running the binary `name` is running. Only Windows
+/// needs to know: elsewhere a running binary can be removed.
+pub fn is_process_running(name: &str) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::is_process_running(name);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = name;
+        false
+    }
+}
+
+/// Stop every process running the binary `name`
+pub fn stop_processes(name: &str) -> anyhow::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::stop_processes(name);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = name;
+        anyhow::bail!("stopping processes is only needed on Windows")
+    }
+}
+
+/// The trust store `import_certificate` adds to, as the plan shown before an
+/// install names it; `None` where certificates aren't imported
+pub fn trust_store_name() -> Option<&'static str> {
+    if cfg!(target_os = "windows") {
+        Some("the CurrentUser Root store")
+    } else if cfg!(target_os = "macos") {
+        Some("your default keychain")
+    } else {
+        None
+    }
+}
+
+/// Import the certificates in `cert_path`, identified by `thumbprints`, into
+/// the user's trust store, returning the thumbprints that weren't already
+/// there
+///
+/// With `dry_run`, print what would be imported instead.
+pub fn import_certificate(
+    cert_path: &std::path::Path,
+    thumbprints: &[String],
+    dry_run: bool,
+) -> anyhow::Result<Vec<String>> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::import_certificate(cert_path, thumbprints, dry_run);
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        return macos::import_certificate(cert_path, thumbprints, dry_run);
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = (cert_path, thumbprints, dry_run);
         anyhow::bail!("Linux is not supported")
     }
 }
 
-/// Import a certificate into the system trust store
-pub fn import_certificate(cert_path: &std::path::Path) -> anyhow::Result<()> {
+/// Remove the certificate with `thumbprint` from the user's trust store; a
+/// no-op if it isn't there
+///
+/// With `dry_run`, print what would be removed instead.
+pub fn remove_certificate(thumbprint: &str, dry_run: bool) -> anyhow::Result<()> {
     #[cfg(target_os = "windows")]
     {
-        return windows::import_certificate(cert_path);
+        return windows::remove_certificate(thumbprint, dry_run);
     }
 
     #[cfg(target_os = "macos")]
     {
-        return macos::import_certificate(cert_path);
+        return macos::remove_certificate(thumbprint, dry_run);
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
-        let _ = cert_path;
+        let _ = (thumbprint, dry_run);
         anyhow::bail!("Linux is not supported")
     }
 }
+
+/// Clear the quarantine Gatekeeper puts on downloaded files, which stops an
+/// unnotarized binary from running; nothing to do elsewhere
+pub fn clear_quarantine(path: &Path) -> anyhow::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::clear_quarantine(path)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Bytes available to the current user on the filesystem holding `path`,
+/// which must exist
+pub fn available_space(path: &std::path::Path) -> std::io::Result<u64> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::available_space(path);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+/// Whether two existing paths are on the same filesystem, so space needed in
+/// both comes out of the same pool
+pub fn same_filesystem(a: &std::path::Path, b: &std::path::Path) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::same_volume(a, b);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Editing the `;`-separated user PATH kept in the Windows registry, whose
+//! entries may hold `%VAR%` references that Windows expands when it builds
+//! each process's environment
+//!
+//! Lookups go through a function so the registry code stays a thin read and
+//! write around these.
+
+/// Longest user PATH Windows keeps whole; past it, the value is cut off when
+/// the environment is built and the entries at the end stop working
+pub const MAX_LEN: usize = 2047;
+
+/// Length as Windows counts it, in UTF-16 units
+pub fn len(path: &str) -> usize {
+    path.encode_utf16().count()
+}
+
+/// `value` with each `%NAME%` that `lookup` knows replaced by its value;
+/// unknown names are left as written, as Windows does
+pub fn expand(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) => match lookup(&after[..end]).filter(|_| end > 0) {
+                Some(found) => {
+                    expanded.push_str(&found);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    // Keep the second `%`, which may open a reference of its own
+                    expanded.push('%');
+                    expanded.push_str(&after[..end]);
+                    rest = &after[end..];
+                }
+            },
+            None => {
+                expanded.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Whether two PATH entries name the same directory once expanded, ignoring
+/// case and a trailing backslash
+fn same_dir(a: &str, b: &str, lookup: &impl Fn(&str) -> Option<String>) -> bool {
+    let normalize = |entry: &str| {
+        expand(entry.trim(), lookup)
+            .trim_end_matches('\\')
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Whether `path` already has an entry for `dir`
+pub fn contains(path: &str, dir: &str, lookup: impl Fn(&str) -> Option<String>) -> bool {
+    path.split(';').any(|entry| same_dir(entry, dir, &lookup))
+}
+
+/// `path` with `entry` added at the end
+pub fn append(path: &str, entry: &str) -> String {
+    let path = path.trim_end_matches(';');
+    if path.is_empty() {
+        entry.to_string()
+    } else {
+        format!("{};{}", path, entry)
+    }
+}
+
+/// `path` with `entry` first and any other entry for the same directory
+/// dropped, so it's searched before everything else
+pub fn move_to_front(path: &str, entry: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let rest = remove(path, entry, &lookup).unwrap_or_else(|| path.to_string());
+    let rest = rest.trim_start_matches(';');
+    if rest.is_empty() {
+        entry.to_string()
+    } else {
+        format!("{};{}", entry, rest)
+    }
+}
+
+/// `path` without its entries for `dir`, leaving every other entry as
+/// written; `None` if it has none
+pub fn remove(path: &str, dir: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let remaining: Vec<&str> = path
+        .split(';')
+        .filter(|entry| !same_dir(entry, dir, &lookup))
+        .collect();
+    if remaining.len() == path.split(';').count() {
+        return None;
+    }
+    Some(remaining.join(";"))
+}
+
+/// Entries naming a directory an earlier entry already names, as written
+pub fn duplicates(path: &str, lookup: impl Fn(&str) -> Option<String>) -> Vec<&str> {
+    let entries: Vec<&str> = path.split(';').filter(|e| !e.trim().is_empty()).collect();
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(i, entry)| {
+            entries[..*i]
+                .iter()
+                .any(|earlier| same_dir(earlier, entry, &lookup))
+        })
+        .map(|(_, entry)| *entry)
+        .collect()
+}
+
+/// `path` without empty or duplicate entries, and with entries inside
+/// `profile` written relative to `%USERPROFILE%`: the same directories in
+/// the same order, in fewer characters
+pub fn compact(path: &str, profile: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut kept: Vec<String> = Vec::new();
+    for entry in path.split(';').filter(|e| !e.trim().is_empty()) {
+        if !kept.iter().any(|k| same_dir(k, entry, &lookup)) {
+            kept.push(under_profile(entry, profile));
+        }
+    }
+    kept.join(";")
+}
+
+/// `dir` written relative to `%USERPROFILE%` when it's inside `profile`, so
+/// the entry follows the profile if it moves
+pub fn under_profile(dir: &str, profile: &str) -> String {
+    let profile = profile.trim_end_matches('\\');
+    if profile.is_empty() || dir.len() <= profile.len() {
+        return dir.to_string();
+    }
+    let (head, tail) = dir.split_at(profile.len());
+    if head.eq_ignore_ascii_case(profile) && tail.starts_with('\\') {
+        format!("%USERPROFILE%{}", tail)
+    } else {
+        dir.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name.to_uppercase().as_str() {
+            "USERPROFILE" => Some(r"C:\Users\ada".to_string()),
+            "LOCALAPPDATA" => Some(r"C:\Users\ada\AppData\Local".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn references_are_expanded_and_unknown_ones_kept() {
+        assert_eq!(expand(r"%USERPROFILE%\bin", lookup), r"C:\Users\ada\bin");
+        assert_eq!(
+            expand(r"%NOPE%\%LocalAppData%\x", lookup),
+            r"%NOPE%\C:\Users\ada\AppData\Local\x"
+        );
+        assert_eq!(expand("50%;%%", lookup), "50%;%%");
+    }
+
+    #[test]
+    fn an_expandable_entry_counts_as_the_directory_it_names() {
+        let path = r"C:\Tools;%USERPROFILE%\.claude\bin\;%LOCALAPPDATA%\Programs\x";
+        assert!(contains(path, r"c:\users\ada\.claude\bin", lookup));
+        assert!(contains(path, r"%USERPROFILE%\.claude\bin", lookup));
+        assert!(!contains(path, r"C:\Users\ada\bin", lookup));
+    }
+
+    #[test]
+    fn removing_leaves_other_entries_as_written() {
+        let path = r"%USERPROFILE%\bin;C:\Users\ada\.claude\bin;%NOPE%\x";
+        assert_eq!(
+            remove(path, r"%USERPROFILE%\.claude\bin", lookup).as_deref(),
+            Some(r"%USERPROFILE%\bin;%NOPE%\x")
+        );
+        assert_eq!(remove(path, r"C:\other", lookup), None);
+    }
+
+    #[test]
+    fn appending_skips_a_trailing_separator() {
+        assert_eq!(append("", r"C:\a"), r"C:\a");
+        assert_eq!(append(r"C:\a;", r"C:\b"), r"C:\a;C:\b");
+    }
+
+    #[test]
+    fn moving_to_the_front_drops_the_old_entry() {
+        let path = r"C:\npm;%USERPROFILE%\.claude\bin;C:\Other";
+        assert_eq!(
+            move_to_front(path, r"%USERPROFILE%\.claude\bin", lookup),
+            r"%USERPROFILE%\.claude\bin;C:\npm;C:\Other"
+        );
+        assert_eq!(move_to_front("", r"C:\a", lookup), r"C:\a");
+    }
+
+    #[test]
+    fn duplicates_are_later_entries_for_the_same_directory() {
+        let path = r"C:\Tools;%USERPROFILE%\bin;;c:\tools\;C:\Users\ada\bin;C:\Other";
+        assert_eq!(
+            duplicates(path, lookup),
+            vec![r"c:\tools\", r"C:\Users\ada\bin"]
+        );
+        assert!(duplicates(r"C:\a;C:\b", lookup).is_empty());
+    }
+
+    #[test]
+    fn compacting_keeps_each_directory_once_in_order() {
+        let path = r"C:\Users\ada\bin;;C:\Tools;%USERPROFILE%\bin;C:\tools;C:\Users\ada\go\bin";
+        let compacted = compact(path, r"C:\Users\ada", lookup);
+        assert_eq!(
+            compacted,
+            r"%USERPROFILE%\bin;C:\Tools;%USERPROFILE%\go\bin"
+        );
+        assert!(len(&compacted) < len(path));
+    }
+
+    #[test]
+    fn length_counts_utf16_units() {
+        assert_eq!(len(r"C:\a;C:\b"), 9);
+        assert_eq!(len("C:\\Users\\Zoë"), 12);
+    }
+
+    #[test]
+    fn directories_in_the_profile_use_the_profile_variable() {
+        let profile = r"C:\Users\ada";
+        assert_eq!(
+            under_profile(r"c:\users\ada\.claude\bin", profile),
+            r"%USERPROFILE%\.claude\bin"
+        );
+        assert_eq!(
+            under_profile(r"C:\Users\adam\bin", profile),
+            r"C:\Users\adam\bin"
+        );
+        assert_eq!(under_profile(r"C:\Users\ada", profile), r"C:\Users\ada");
+    }
+}
@@ -0,0 +1,285 @@
+//! Persistent variables kept in the user's PowerShell `$PROFILE`, for
+//! `--env-mode profile` on Windows: each one is a `$env:NAME = "value"` line
+//! in a block between marker comments, which is rewritten in place and
+//! taken out whole once it's empty, so nothing else in the profile is
+//! touched.
+//!
+//! The text handling is kept apart from the files so it can be tested on
+//! every platform.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::plan;
+
+const BEGIN: &str = "# >>> code-assist environment >>>";
+const END: &str = "# <<< code-assist environment <<<";
+
+/// `$PROFILE` for Windows PowerShell, and for PowerShell 7 once it has made
+/// its own directory under Documents
+pub fn profiles() -> Vec<PathBuf> {
+    let Some(documents) = dirs::document_dir() else {
+        return Vec::new();
+    };
+    let mut profiles = vec![documents.join("WindowsPowerShell")];
+    if documents.join("PowerShell").is_dir() {
+        profiles.push(documents.join("PowerShell"));
+    }
+    profiles
+        .into_iter()
+        .map(|dir| dir.join("Microsoft.PowerShell_profile.ps1"))
+        .collect()
+}
+
+/// Set `name` in every profile, creating any that are missing
+pub fn set_user_env_var(name: &str, value: &str, dry_run: bool) -> Result<()> {
+    for profile in profiles() {
+        let existing = std::fs::read_to_string(&profile).unwrap_or_default();
+        let updated = set_var(&existing, name, value);
+        if updated == existing {
+            continue;
+        }
+        if dry_run {
+            plan::step(format!(
+                "Write '{}' to {}",
+                var_line(name, value),
+                profile.display()
+            ));
+            continue;
+        }
+        tracing::debug!("setting {} in {}", name, profile.display());
+        write(&profile, &updated)?;
+    }
+    Ok(())
+}
+
+/// Remove `name` from every profile, and the block with it once it's empty
+pub fn unset_user_env_var(name: &str, dry_run: bool) -> Result<()> {
+    for profile in profiles() {
+        let Ok(existing) = std::fs::read_to_string(&profile) else {
+            continue;
+        };
+        let updated = unset_var(&existing, name);
+        if updated == existing {
+            continue;
+        }
+        if dry_run {
+            plan::step(format!("Remove {} from {}", name, profile.display()));
+            continue;
+        }
+        tracing::debug!("removing {} from {}", name, profile.display());
+        write(&profile, &updated)?;
+    }
+    Ok(())
+}
+
+/// The value of `name` in the first profile that sets it, and the file and
+/// line it's on
+pub fn find_user_env_var(name: &str) -> Option<(String, String)> {
+    profiles().iter().find_map(|profile| {
+        let content = std::fs::read_to_string(profile).ok()?;
+        let (value, index) = get_var(&content, name)?;
+        Some((value, format!("{}:{}", profile.display(), index + 1)))
+    })
+}
+
+fn write(profile: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = profile.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(profile, content)
+        .with_context(|| format!("Failed to write {}", profile.display()))
+}
+
+/// `value` as a double-quoted PowerShell string, with the backtick, `$`,
+/// and `"` escaped so nothing in it is expanded
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '`' | '$' | '"') {
+            quoted.push('`');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The value of a string written by `quote`
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        value.push(if c == '`' { chars.next()? } else { c });
+    }
+    Some(value)
+}
+
+fn var_prefix(name: &str) -> String {
+    format!("$env:{} = ", name)
+}
+
+fn var_line(name: &str, value: &str) -> String {
+    format!("{}{}", var_prefix(name), quote(value))
+}
+
+/// Lines of `content` and the indices of our block's markers
+fn split(content: &str) -> (Vec<&str>, Option<(usize, usize)>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let begin = lines.iter().position(|line| line.trim() == BEGIN);
+    let block = begin.and_then(|begin| {
+        let end = lines[begin..].iter().position(|line| line.trim() == END)?;
+        Some((begin, begin + end))
+    });
+    (lines, block)
+}
+
+fn join(lines: &[String]) -> String {
+    if lines.is_empty() {
+        String::new()
+    } else {
+        lines.join("\r\n") + "\r\n"
+    }
+}
+
+/// `content` with `name` set to `value` in our block, which is added at the
+/// end if there isn't one
+fn set_var(content: &str, name: &str, value: &str) -> String {
+    let (lines, block) = split(content);
+    let prefix = var_prefix(name);
+    let line = var_line(name, value);
+    let mut lines: Vec<String> = lines.into_iter().map(String::from).collect();
+
+    match block {
+        Some((begin, end)) => {
+            match (begin + 1..end).find(|&i| lines[i].trim_start().starts_with(&prefix)) {
+                Some(i) => lines[i] = line,
+                None => lines.insert(end, line),
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|last| !last.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend([BEGIN.to_string(), line, END.to_string()]);
+        }
+    }
+    join(&lines)
+}
+
+/// `content` without `name` in our block, and without the block, and the
+/// blank line `set_var` put before it, once nothing is left in it
+fn unset_var(content: &str, name: &str) -> String {
+    let (lines, block) = split(content);
+    let Some((begin, end)) = block else {
+        return content.to_string();
+    };
+    let prefix = var_prefix(name);
+    let inside: Vec<&str> = lines[begin + 1..end]
+        .iter()
+        .copied()
+        .filter(|line| !line.trim_start().starts_with(&prefix))
+        .collect();
+    if inside.len() == end - begin - 1 {
+        return content.to_string();
+    }
+
+    let mut kept: Vec<String> = lines[..begin].iter().map(|l| l.to_string()).collect();
+    if inside.is_empty() {
+        if kept.last().is_some_and(|l| l.trim().is_empty()) {
+            kept.pop();
+        }
+    } else {
+        kept.push(lines[begin].to_string());
+        kept.extend(inside.iter().map(|l| l.to_string()));
+        kept.push(lines[end].to_string());
+    }
+    kept.extend(lines[end + 1..].iter().map(|l| l.to_string()));
+    join(&kept)
+}
+
+/// The value `name` is set to in our block, and the index of its line
+fn get_var(content: &str, name: &str) -> Option<(String, usize)> {
+    let (lines, block) = split(content);
+    let (begin, end) = block?;
+    let prefix = var_prefix(name);
+    (begin + 1..end).find_map(|i| {
+        let quoted = lines[i].trim().strip_prefix(&prefix)?;
+        Some((unquote(quoted)?, i))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USERS_OWN: &str = "Set-PSReadLineOption -EditMode Emacs\r\n";
+
+    #[test]
+    fn a_block_is_added_once_and_updated_in_place() {
+        let content = set_var(USERS_OWN, "NODE_EXTRA_CA_CERTS", r"C:\certs\a.pem");
+        let content = set_var(&content, "NODE_EXTRA_CA_CERTS", r"C:\certs\b.pem");
+        let content = set_var(&content, "HTTPS_PROXY", "http://proxy:8080");
+
+        assert_eq!(
+            content.lines().collect::<Vec<_>>(),
+            [
+                USERS_OWN.trim_end(),
+                "",
+                BEGIN,
+                r#"$env:NODE_EXTRA_CA_CERTS = "C:\certs\b.pem""#,
+                r#"$env:HTTPS_PROXY = "http://proxy:8080""#,
+                END,
+            ]
+        );
+        assert_eq!(
+            set_var(&content, "HTTPS_PROXY", "http://proxy:8080"),
+            content
+        );
+    }
+
+    #[test]
+    fn a_missing_profile_gets_just_the_block() {
+        assert_eq!(
+            set_var("", "A", "1"),
+            format!("{}\r\n$env:A = \"1\"\r\n{}\r\n", BEGIN, END)
+        );
+    }
+
+    #[test]
+    fn unsetting_the_last_variable_takes_out_exactly_the_block() {
+        let after = "# the user's, after ours\r\n";
+        let content = set_var(USERS_OWN, "A", "1") + after;
+        let content = set_var(&content, "B", "2");
+
+        let content = unset_var(&content, "A");
+        assert_eq!(get_var(&content, "A"), None);
+        assert!(get_var(&content, "B").is_some());
+
+        assert_eq!(unset_var(&content, "B"), format!("{}{}", USERS_OWN, after));
+        assert_eq!(unset_var(USERS_OWN, "B"), USERS_OWN);
+    }
+
+    #[test]
+    fn values_are_escaped_so_powershell_expands_nothing() {
+        let value = r#"C:\Users\$me\"quoted"`tick"#;
+        let content = set_var("", "A", value);
+
+        assert!(
+            content.contains(r#"$env:A = "C:\Users\`$me\`"quoted`"``tick""#),
+            "{}",
+            content
+        );
+        assert_eq!(get_var(&content, "A"), Some((value.to_string(), 1)));
+    }
+
+    #[test]
+    fn lines_outside_the_block_are_never_read_as_ours() {
+        let content = "$env:A = \"theirs\"\r\n";
+
+        assert_eq!(get_var(content, "A"), None);
+        assert_eq!(unset_var(content, "A"), content);
+    }
+}
@@ -1,35 +1,68 @@
+use super::path_list;
 use super::PlatformPaths;
-use anyhow::{Context, Result};
+use crate::output::reporter;
+use crate::plan;
+use crate::process;
+use anyhow::{bail, Context, Result};
 use console::style;
 use std::path::PathBuf;
 
-pub fn get_paths() -> PlatformPaths {
-    let home_dir = dirs::home_dir().expect("Could not determine home directory");
+pub fn get_paths(home_dir: PathBuf) -> PlatformPaths {
     let appdata = std::env::var("APPDATA")
         .map(PathBuf::from)
         .unwrap_or_else(|_| home_dir.join("AppData").join("Roaming"));
 
+    // Kept out of the roaming profile, which is copied at every sign-in
+    let local = std::env::var("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home_dir.join("AppData").join("Local"))
+        .join("code-assist");
+
     PlatformPaths {
-        home_dir: home_dir.clone(),
         claude_config_dir: home_dir.join(".claude"),
-        vscode_settings_dir: appdata.join("Code").join("User"),
+        app_data_dir: appdata,
         certs_dir: home_dir.join(".continue").join("certs"),
+        vscode_user_dir: None,
+        downloads_dir: local.join("downloads"),
+        state_dir: local.join("state"),
+        backups_dir: local.join("backups"),
+        home_dir,
     }
 }
 
-pub fn print_install_instructions() {
-    println!(
-        "{}\n",
-        style("Please install the missing software via Software Center:").yellow()
-    );
-    println!("  1. Open Software Center from the Start menu");
-    println!("  2. Search for and install:");
-    println!("     - Visual Studio Code");
-    println!("     - Git for Windows");
-    println!("\nOnce installed, run this command again.");
+pub fn print_install_instructions(missing: &[String]) {
+    let software = super::software_to_install(missing, "Git for Windows");
+    if software.is_empty() {
+        return;
+    }
+    reporter().line(style("Please install the missing software via Software Center:").yellow());
+    reporter().blank();
+    reporter().line("  1. Open Software Center from the Start menu");
+    reporter().line("  2. Search for and install:");
+    for name in &software {
+        reporter().line(format!("     - {}", name));
+    }
+    if super::needs_node(missing) {
+        reporter().blank();
+        reporter().line(
+            "Or install Node.js yourself with nvm-windows \
+             (https://github.com/coreybutler/nvm-windows):",
+        );
+        reporter().line("  nvm install lts");
+    }
+    reporter().blank();
+    reporter().line("Once installed, run this command again.");
 }
 
-pub fn set_user_env_var(name: &str, value: &str) -> Result<()> {
+pub fn set_user_env_var(name: &str, value: &str, dry_run: bool) -> Result<()> {
+    if dry_run {
+        plan::step(format!(
+            "Set user environment variable {}={} (HKCU\\Environment)",
+            name, value
+        ));
+        return Ok(());
+    }
+
     use winreg::enums::*;
     use winreg::RegKey;
 
@@ -38,6 +71,7 @@ pub fn set_user_env_var(name: &str, value: &str) -> Result<()> {
         .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
         .context("Failed to open Environment registry key")?;
 
+    tracing::debug!("writing HKCU\\Environment\\{} = {}", name, value);
     env.set_value(name, &value)
         .context(format!("Failed to set environment variable {}", name))?;
 
@@ -47,46 +81,427 @@ pub fn set_user_env_var(name: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn add_to_path(dir: &str) -> Result<()> {
+pub fn get_user_env_var(name: &str) -> Option<String> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let env = hkcu.open_subkey_with_flags("Environment", KEY_READ).ok()?;
+    let (value, expandable) = read_string(&env, name)?;
+    if expandable {
+        Some(path_list::expand(&value, |var| std::env::var(var).ok()))
+    } else {
+        Some(value)
+    }
+}
+
+/// Where a user environment variable is saved, as shown to the user
+pub const ENVIRONMENT_KEY: &str = "HKCU\\Environment";
+
+/// A string value as written, and whether it's REG_EXPAND_SZ
+fn read_string(key: &winreg::RegKey, name: &str) -> Option<(String, bool)> {
+    use winreg::enums::*;
+    use winreg::types::FromRegValue;
+
+    let raw = key.get_raw_value(name).ok()?;
+    let value = String::from_reg_value(&raw).ok()?;
+    Some((value, raw.vtype == REG_EXPAND_SZ))
+}
+
+/// Write the Path in an environment key, as REG_EXPAND_SZ if it was
+/// already or if it now holds a `%VAR%` reference; `set_value` would always
+/// write REG_SZ, which stops Windows expanding the entries that rely on it
+fn write_path(env: &winreg::RegKey, path: &str, expandable: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegValue;
+
+    let vtype = if expandable || path.contains('%') {
+        REG_EXPAND_SZ
+    } else {
+        REG_SZ
+    };
+    let bytes = path
+        .encode_utf16()
+        .chain(Some(0))
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    tracing::debug!("writing Path = {} ({:?})", path, vtype);
+    env.set_raw_value("Path", &RegValue { bytes, vtype })
+        .context("Failed to update PATH")
+}
+
+pub fn add_to_path(dir: &str, force: bool, dry_run: bool) -> Result<()> {
     use winreg::enums::*;
     use winreg::RegKey;
 
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let env = hkcu
-        .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
+        .open_subkey_with_flags("Environment", flags)
         .context("Failed to open Environment registry key")?;
 
-    let current_path: String = env.get_value("Path").unwrap_or_default();
+    let (current_path, expandable) = read_string(&env, "Path").unwrap_or_default();
 
-    // Check if already in PATH
-    if current_path
-        .split(';')
-        .any(|p| p.eq_ignore_ascii_case(dir))
-    {
+    // Check if already in PATH, however the entry is written
+    let lookup = |var: &str| std::env::var(var).ok();
+    if path_list::contains(&current_path, dir, lookup) {
         return Ok(());
     }
 
-    let new_path = if current_path.is_empty() {
-        dir.to_string()
-    } else {
-        format!("{};{}", current_path, dir)
+    let profile = std::env::var("USERPROFILE").unwrap_or_default();
+    let entry = path_list::under_profile(dir, &profile);
+    let new_path = path_list::append(&current_path, &entry);
+
+    if path_list::len(&new_path) > path_list::MAX_LEN {
+        if !force {
+            bail!(path_too_long(&current_path, &new_path, &profile));
+        }
+        reporter().warn(format!(
+            "The user PATH will be {} characters, over the {} Windows keeps; \
+             entries at the end may stop working",
+            path_list::len(&new_path),
+            path_list::MAX_LEN
+        ));
+    }
+
+    if dry_run {
+        plan::step(format!("Append {} to user PATH (HKCU\\Environment)", entry));
+        return Ok(());
+    }
+
+    write_path(&env, &new_path, expandable)?;
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+/// Why appending to the user PATH was refused, and what would make room
+fn path_too_long(current: &str, new_path: &str, profile: &str) -> String {
+    let lookup = |var: &str| std::env::var(var).ok();
+    let mut message = format!(
+        "adding it would make the user PATH {} characters long, over the {} Windows \
+         keeps (it's {} now)",
+        path_list::len(new_path),
+        path_list::MAX_LEN,
+        path_list::len(current)
+    );
+
+    let duplicates = path_list::duplicates(current, lookup);
+    if !duplicates.is_empty() {
+        message.push_str("\n  Duplicate entries:");
+        for duplicate in &duplicates {
+            message.push_str(&format!("\n    {}", duplicate));
+        }
+    }
+    let compacted = path_list::len(&path_list::compact(current, profile, lookup));
+    if compacted < path_list::len(current) {
+        message.push_str(&format!(
+            "\n  Removing duplicates and writing paths in your profile as \
+             %USERPROFILE%\\... would bring it to {} characters",
+            compacted
+        ));
+    }
+    message.push_str(
+        "\n  Shorten it in System Properties > Environment Variables, or rerun with \
+         --force-path to add it anyway",
+    );
+    message
+}
+
+/// Whether the user PATH saved in the registry has an entry for `dir`
+pub fn path_has(dir: &str) -> bool {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let Ok(env) = hkcu.open_subkey_with_flags("Environment", KEY_READ) else {
+        return false;
+    };
+    read_string(&env, "Path")
+        .is_some_and(|(path, _)| path_list::contains(&path, dir, |var| std::env::var(var).ok()))
+}
+
+/// How to pick up the saved PATH in a console that was already open, which
+/// keeps the environment it started with despite the broadcast
+pub fn path_refresh_steps() -> Vec<String> {
+    vec![
+        "In PowerShell, run: $env:Path = [Environment]::GetEnvironmentVariable('Path', 'Machine') \
+         + ';' + [Environment]::GetEnvironmentVariable('Path', 'User')"
+            .to_string(),
+        "In Command Prompt, open a new window".to_string(),
+    ]
+}
+
+pub fn unset_user_env_var(name: &str, dry_run: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let env = hkcu
+        .open_subkey_with_flags("Environment", flags)
+        .context("Failed to open Environment registry key")?;
+
+    if env.get_raw_value(name).is_err() {
+        return Ok(());
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Remove user environment variable {} (HKCU\\Environment)",
+            name
+        ));
+        return Ok(());
+    }
+
+    tracing::debug!("deleting HKCU\\Environment\\{}", name);
+    env.delete_value(name)
+        .context(format!("Failed to remove environment variable {}", name))?;
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+pub fn remove_from_path(dir: &str, dry_run: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let env = hkcu
+        .open_subkey_with_flags("Environment", flags)
+        .context("Failed to open Environment registry key")?;
+
+    let (current_path, expandable) = read_string(&env, "Path").unwrap_or_default();
+
+    let lookup = |var: &str| std::env::var(var).ok();
+    let Some(new_path) = path_list::remove(&current_path, dir, lookup) else {
+        return Ok(());
     };
 
-    env.set_value("Path", &new_path)
-        .context("Failed to update PATH")?;
+    if dry_run {
+        plan::step(format!("Remove {} from user PATH (HKCU\\Environment)", dir));
+        return Ok(());
+    }
+
+    write_path(&env, &new_path, expandable)?;
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+/// Put `dir` first on the user PATH, so it wins over the user's other
+/// entries; the machine PATH still comes before it
+pub fn move_to_front_of_path(dir: &str, dry_run: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let env = hkcu
+        .open_subkey_with_flags("Environment", flags)
+        .context("Failed to open Environment registry key")?;
+
+    let (current_path, expandable) = read_string(&env, "Path").unwrap_or_default();
+    let profile = std::env::var("USERPROFILE").unwrap_or_default();
+    let entry = path_list::under_profile(dir, &profile);
+    let lookup = |var: &str| std::env::var(var).ok();
+    let new_path = path_list::move_to_front(&current_path, &entry, lookup);
+
+    if dry_run {
+        plan::step(format!(
+            "Move {} to the front of the user PATH (HKCU\\Environment)",
+            entry
+        ));
+        return Ok(());
+    }
+
+    write_path(&env, &new_path, expandable)?;
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+/// Registry key holding the environment every user gets
+const SYSTEM_ENVIRONMENT: &str = r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment";
+
+/// Where `--system` installs go: `ClaudeCode` under the 64-bit Program Files
+pub fn system_install_dir() -> PathBuf {
+    std::env::var_os("ProgramW6432")
+        .or_else(|| std::env::var_os("ProgramFiles"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\Program Files"))
+        .join("ClaudeCode")
+}
+
+/// Whether this is an elevated prompt, tested by opening the machine
+/// environment for writing, which is what `--system` needs
+pub fn is_elevated() -> bool {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(SYSTEM_ENVIRONMENT, KEY_READ | KEY_WRITE)
+        .is_ok()
+}
+
+pub fn add_to_system_path(dir: &str, dry_run: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+    let env = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(SYSTEM_ENVIRONMENT, flags)
+        .context("Failed to open the machine Environment registry key")?;
+
+    let (current_path, expandable) = read_string(&env, "Path").unwrap_or_default();
+    if path_list::contains(&current_path, dir, |var| std::env::var(var).ok()) {
+        return Ok(());
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Append {} to machine PATH (HKLM\\{})",
+            dir, SYSTEM_ENVIRONMENT
+        ));
+        return Ok(());
+    }
 
+    write_path(&env, &path_list::append(&current_path, dir), expandable)?;
     broadcast_environment_change();
+    Ok(())
+}
+
+pub fn remove_from_system_path(dir: &str, dry_run: bool) -> Result<()> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let flags = if dry_run { KEY_READ } else { KEY_READ | KEY_WRITE };
+    let env = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(SYSTEM_ENVIRONMENT, flags)
+        .context("Failed to open the machine Environment registry key")?;
+
+    let (current_path, expandable) = read_string(&env, "Path").unwrap_or_default();
+    let lookup = |var: &str| std::env::var(var).ok();
+    let Some(new_path) = path_list::remove(&current_path, dir, lookup) else {
+        return Ok(());
+    };
+
+    if dry_run {
+        plan::step(format!(
+            "Remove {} from machine PATH (HKLM\\{})",
+            dir, SYSTEM_ENVIRONMENT
+        ));
+        return Ok(());
+    }
 
+    write_path(&env, &new_path, expandable)?;
+    broadcast_environment_change();
     Ok(())
 }
 
-pub fn import_certificate(_cert_path: &std::path::Path) -> Result<()> {
-    // On Windows, we use NODE_EXTRA_CA_CERTS environment variable
-    // instead of importing to system store (which requires admin)
-    // The certificate path is set via set_user_env_var
+pub fn import_certificate(
+    cert_path: &std::path::Path,
+    thumbprints: &[String],
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let missing: Vec<String> = thumbprints
+        .iter()
+        .filter(|thumbprint| !in_root_store(thumbprint))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Import {} into the CurrentUser Root store",
+            cert_path.display()
+        ));
+        return Ok(missing);
+    }
+
+    // The CurrentUser store needs no elevation, though Windows asks the
+    // user to confirm each new root
+    let output = process::output(
+        std::process::Command::new("certutil")
+            .args(["-user", "-addstore", "Root"])
+            .arg(cert_path),
+    )
+    .context("Failed to run certutil")?;
+    if !output.status.success() {
+        bail!(
+            "certutil could not import {}: {}",
+            cert_path.display(),
+            certutil_message(&output)
+        );
+    }
+
+    Ok(missing)
+}
+
+pub fn remove_certificate(thumbprint: &str, dry_run: bool) -> Result<()> {
+    if !in_root_store(thumbprint) {
+        return Ok(());
+    }
+
+    if dry_run {
+        plan::step(format!(
+            "Remove certificate {} from the CurrentUser Root store",
+            thumbprint
+        ));
+        return Ok(());
+    }
+
+    let output = process::output(std::process::Command::new("certutil").args([
+        "-user",
+        "-delstore",
+        "Root",
+        thumbprint,
+    ]))
+    .context("Failed to run certutil")?;
+    if !output.status.success() {
+        bail!(
+            "certutil could not remove {}: {}",
+            thumbprint,
+            certutil_message(&output)
+        );
+    }
+
     Ok(())
 }
 
+/// Whether the CurrentUser Root store has a certificate with `thumbprint`
+fn in_root_store(thumbprint: &str) -> bool {
+    process::output(
+        std::process::Command::new("certutil").args(["-user", "-store", "Root", thumbprint]),
+    )
+    .is_ok_and(|output| output.status.success())
+}
+
+/// What certutil printed, which is where it explains a failure
+fn certutil_message(output: &std::process::Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = format!("{}\n{}", stdout.trim(), stderr.trim());
+    let message = message.trim();
+    if message.is_empty() {
+        format!("exited with {}", output.status)
+    } else {
+        message.to_string()
+    }
+}
+
 fn broadcast_environment_change() {
     // This notifies Windows Explorer and other applications that
     // environment variables have changed
@@ -130,6 +545,34 @@ fn broadcast_environment_change() {
     }
 }
 
+/// Whether a process running `image` (such as `claude.exe`) is running
+pub fn is_process_running(image: &str) -> bool {
+    let filter = format!("IMAGENAME eq {}", image);
+    process::output(
+        std::process::Command::new("tasklist").args(["/FI", &filter, "/NH", "/FO", "CSV"]),
+    )
+    .is_ok_and(|output| {
+        String::from_utf8_lossy(&output.stdout)
+            .to_ascii_lowercase()
+            .contains(&format!("\"{}\"", image.to_ascii_lowercase()))
+    })
+}
+
+/// Stop every process running `image`
+pub fn stop_processes(image: &str) -> Result<()> {
+    let output = process::output(
+        std::process::Command::new("taskkill").args(["/F", "/T", "/IM", image]),
+    )
+    .context("Failed to run taskkill")?;
+    if !output.status.success() {
+        bail!(
+            "taskkill failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 /// Check if VS Code is installed on Windows
 pub fn check_vscode_installed() -> bool {
     // Check common installation paths
@@ -161,7 +604,75 @@ pub fn check_git_installed() -> bool {
         .unwrap_or(false)
 }
 
-/// Get the VS Code CLI path
-pub fn get_vscode_cli() -> &'static str {
-    "code"
+/// Install directories of programs whose uninstall entry has one of
+/// `display_names`, from the per-user and machine-wide uninstall keys
+pub fn uninstall_locations(display_names: &[&str]) -> Vec<PathBuf> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    const UNINSTALL: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall";
+
+    let roots = [
+        (HKEY_CURRENT_USER, KEY_READ),
+        (HKEY_LOCAL_MACHINE, KEY_READ | KEY_WOW64_64KEY),
+        (HKEY_LOCAL_MACHINE, KEY_READ | KEY_WOW64_32KEY),
+    ];
+
+    let mut locations = Vec::new();
+    for (root, flags) in roots {
+        let Ok(uninstall) = RegKey::predef(root).open_subkey_with_flags(UNINSTALL, flags) else {
+            continue;
+        };
+        for name in uninstall.enum_keys().flatten() {
+            let Ok(entry) = uninstall.open_subkey_with_flags(&name, flags) else {
+                continue;
+            };
+            let display_name: String = entry.get_value("DisplayName").unwrap_or_default();
+            if !display_names.contains(&display_name.as_str()) {
+                continue;
+            }
+            let location: String = entry.get_value("InstallLocation").unwrap_or_default();
+            if !location.is_empty() {
+                tracing::debug!("{} is installed in {} ({})", display_name, location, name);
+                locations.push(PathBuf::from(location));
+            }
+        }
+    }
+    locations
+}
+
+/// Bytes available to the current user on the volume holding `path`
+pub fn available_space(path: &std::path::Path) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated and the out pointer is valid; the
+    // totals we don't need may be null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+/// Whether two paths are on the same drive or share
+pub fn same_volume(a: &std::path::Path, b: &std::path::Path) -> bool {
+    use std::path::Component;
+
+    let prefix = |path: &std::path::Path| match path.components().next() {
+        Some(Component::Prefix(prefix)) => {
+            Some(prefix.as_os_str().to_string_lossy().to_lowercase())
+        }
+        _ => None,
+    };
+    prefix(a).is_some() && prefix(a) == prefix(b)
 }
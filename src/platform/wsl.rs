@@ -0,0 +1,65 @@
+//! Windows Subsystem for Linux, where Claude Code runs in the Linux
+//! distribution but VS Code runs on the Windows host and reaches in with the
+//! WSL extension, so its settings live on the Windows side
+
+use crate::process;
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Where the Windows drive is mounted by default
+const WINDOWS_DRIVE: &str = "/mnt/c";
+
+/// Whether this is a WSL distribution
+pub fn detect() -> bool {
+    if std::env::var_os("WSL_DISTRO_NAME").is_some_and(|name| !name.is_empty()) {
+        return true;
+    }
+    std::fs::read_to_string("/proc/version")
+        .is_ok_and(|version| version.to_lowercase().contains("microsoft"))
+}
+
+/// The Windows user's roaming AppData directory, through the drive mount;
+/// looked up once, since it means starting `cmd.exe`
+pub fn host_app_data() -> &'static Result<PathBuf, String> {
+    static APP_DATA: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+    APP_DATA.get_or_init(|| find_host_app_data().map_err(|e| format!("{:#}", e)))
+}
+
+fn find_host_app_data() -> Result<PathBuf> {
+    let drive = Path::new(WINDOWS_DRIVE);
+    if !drive.is_dir() {
+        bail!("the Windows drive isn't mounted at {}", WINDOWS_DRIVE);
+    }
+
+    let user = windows_username()?;
+    let app_data = drive
+        .join("Users")
+        .join(&user)
+        .join("AppData")
+        .join("Roaming");
+    if !app_data.is_dir() {
+        bail!("{} doesn't exist", app_data.display());
+    }
+    Ok(app_data)
+}
+
+/// The Windows user name, from the host's own `cmd.exe`
+fn windows_username() -> Result<String> {
+    // Run from the Windows drive, as cmd.exe can't start in a Linux directory
+    let output = process::output(
+        std::process::Command::new("cmd.exe")
+            .args(["/c", "echo", "%USERNAME%"])
+            .current_dir(WINDOWS_DRIVE),
+    )
+    .context("could not run cmd.exe to find the Windows user name")?;
+    if !output.status.success() {
+        return Err(anyhow!("cmd.exe exited with {}", output.status));
+    }
+
+    let user = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if user.is_empty() || user == "%USERNAME%" {
+        bail!("cmd.exe didn't report a Windows user name");
+    }
+    Ok(user)
+}
@@ -1,79 +1,614 @@
 use console::style;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// Check if VS Code is installed
-pub fn check_vscode() -> bool {
-    let installed = is_vscode_installed();
+use crate::download::compare_versions;
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::platform::Editor;
+use crate::process::{self, Runner};
+use crate::space;
 
-    if installed {
-        println!(
-            "  {} VS Code",
-            style("✓").green().bold()
-        );
-    } else {
-        println!(
-            "  {} VS Code - {}",
-            style("✗").red().bold(),
-            style("not installed").red()
-        );
+/// Oldest Node.js that npm-installed tools accept unless the config file
+/// says otherwise
+pub const NODE_MIN_VERSION: &str = "18";
+
+/// Longest a version command may take before it counts as hung, as broken
+/// `code` shims sometimes do
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something a tool needs on the machine before it can be installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prerequisite {
+    /// VS Code or one of its variants, at least `min_version` if given
+    VsCode {
+        min_version: Option<String>,
+    },
+    Git,
+    /// Node.js, at least `min_version` if given
+    Node {
+        min_version: Option<String>,
+    },
+    /// npm, which comes with Node.js
+    Npm,
+    /// Any program on PATH, whose version is read from the output of
+    /// `name version_args`
+    Command {
+        name: String,
+        version_args: Vec<String>,
+        min_version: Option<String>,
+    },
+    /// Free space in the home directory
+    DiskSpace {
+        bytes: u64,
+    },
+}
+
+impl Prerequisite {
+    /// What every tool needed before tools could say otherwise
+    pub fn defaults() -> Vec<Prerequisite> {
+        vec![
+            Prerequisite::VsCode { min_version: None },
+            Prerequisite::Git,
+        ]
     }
 
-    installed
+    /// Fold `other` into this one if they require the same thing, keeping
+    /// the stricter of the two. Returns whether they did.
+    fn absorb(&mut self, other: &Prerequisite) -> bool {
+        match (self, other) {
+            (
+                Prerequisite::VsCode { min_version },
+                Prerequisite::VsCode {
+                    min_version: other_min,
+                },
+            )
+            | (
+                Prerequisite::Node { min_version },
+                Prerequisite::Node {
+                    min_version: other_min,
+                },
+            ) => {
+                raise(min_version, other_min);
+                true
+            }
+            (Prerequisite::Git, Prerequisite::Git) | (Prerequisite::Npm, Prerequisite::Npm) => true,
+            (
+                Prerequisite::Command {
+                    name, min_version, ..
+                },
+                Prerequisite::Command {
+                    name: other_name,
+                    min_version: other_min,
+                    ..
+                },
+            ) if name == other_name => {
+                raise(min_version, other_min);
+                true
+            }
+            (Prerequisite::DiskSpace { bytes }, Prerequisite::DiskSpace { bytes: other }) => {
+                *bytes = (*bytes).max(*other);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
-/// Check if Git is installed
-pub fn check_git() -> bool {
-    let installed = is_git_installed();
+/// Raise `min_version` to `other` if that's higher
+fn raise(min_version: &mut Option<String>, other: &Option<String>) {
+    if let Some(other) = other {
+        let higher = min_version
+            .as_deref()
+            .is_none_or(|current| compare_versions(other, current) == Ordering::Greater);
+        if higher {
+            *min_version = Some(other.clone());
+        }
+    }
+}
 
-    if installed {
-        println!(
-            "  {} Git",
-            style("✓").green().bold()
-        );
-    } else {
-        println!(
-            "  {} Git - {}",
-            style("✗").red().bold(),
-            style("not installed").red()
+/// Everything any of `lists` requires, each thing once at the strictest
+/// version asked for, in the order first seen
+pub fn union(lists: impl IntoIterator<Item = Vec<Prerequisite>>) -> Vec<Prerequisite> {
+    let mut all: Vec<Prerequisite> = Vec::new();
+    for prerequisite in lists.into_iter().flatten() {
+        if !all.iter_mut().any(|known| known.absorb(&prerequisite)) {
+            all.push(prerequisite);
+        }
+    }
+    all
+}
+
+/// Result of checking a single prerequisite
+#[derive(Debug, Serialize)]
+pub struct PrerequisiteStatus {
+    pub name: String,
+    /// Whether it's there, and new enough
+    pub installed: bool,
+    /// Where or which version was found, or why it wasn't
+    pub details: String,
+    /// Something found that works but isn't set up as expected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// Why a prerequisite that was found still isn't good enough
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+impl PrerequisiteStatus {
+    fn found(name: impl Into<String>, details: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            installed: true,
+            details: details.into(),
+            warning: None,
+            problem: None,
+        }
+    }
+
+    fn missing(name: impl Into<String>, details: impl Into<String>) -> Self {
+        Self {
+            installed: false,
+            ..Self::found(name, details)
+        }
+    }
+
+    /// Fail the check if `version` is older than `min_version`
+    fn require(mut self, version: Option<&str>, min_version: Option<&str>) -> Self {
+        let Some(min_version) = min_version else {
+            return self;
+        };
+        match version {
+            Some(version) if compare_versions(version, min_version) != Ordering::Less => {}
+            Some(version) => {
+                self.installed = false;
+                self.problem = Some(format!(
+                    "too old (found {}, need {} or later)",
+                    version, min_version
+                ));
+            }
+            None => {
+                self.warning = Some(format!(
+                    "could not tell its version; {} or later is required",
+                    min_version
+                ))
+            }
+        }
+        self
+    }
+}
+
+/// Check `prerequisites`, printing a line for each. Returns the names of
+/// the ones not satisfied, so empty when all are.
+pub fn check(runner: &dyn Runner, prerequisites: &[Prerequisite], home: &Path) -> Vec<String> {
+    let statuses = evaluate(runner, prerequisites, home);
+    statuses.iter().for_each(print_status);
+    statuses
+        .into_iter()
+        .filter(|status| !status.installed)
+        .map(|status| status.name)
+        .collect()
+}
+
+/// Check `prerequisites` without printing anything, all at once, with the
+/// results in the order they were asked for
+pub fn evaluate(
+    runner: &dyn Runner,
+    prerequisites: &[Prerequisite],
+    home: &Path,
+) -> Vec<PrerequisiteStatus> {
+    process::in_parallel(prerequisites, |prerequisite| {
+        status(runner, prerequisite, home)
+    })
+}
+
+fn status(runner: &dyn Runner, prerequisite: &Prerequisite, home: &Path) -> PrerequisiteStatus {
+    match prerequisite {
+        Prerequisite::VsCode { min_version } => vscode_status(runner, min_version.as_deref()),
+        Prerequisite::Git => git_status(runner),
+        Prerequisite::Node { min_version } => node_status(runner, min_version.as_deref()),
+        Prerequisite::Npm => npm_status(runner),
+        Prerequisite::Command {
+            name,
+            version_args,
+            min_version,
+        } => command_status(runner, name, name, version_args, min_version.as_deref()),
+        Prerequisite::DiskSpace { bytes } => disk_space_status(home, *bytes),
+    }
+}
+
+fn print_status(status: &PrerequisiteStatus) {
+    let problem = (!status.installed).then(|| status.problem.as_deref().unwrap_or("not installed"));
+    events::emit(Event::PrerequisiteChecked {
+        name: status.name.clone(),
+        installed: status.installed,
+        problem: problem.map(str::to_string),
+    });
+    match problem {
+        None => {
+            reporter().success(&status.name);
+            if let Some(warning) = &status.warning {
+                reporter().warn(warning);
+            }
+        }
+        Some(problem) => {
+            reporter().error(format!("{} - {}", status.name, style(problem).red()));
+        }
+    }
+}
+
+fn vscode_status(runner: &dyn Runner, min_version: Option<&str>) -> PrerequisiteStatus {
+    let editors = installed_editors(runner);
+    if editors.is_empty() {
+        return PrerequisiteStatus::missing(
+            "VS Code",
+            "not found in the standard install locations or on PATH",
         );
     }
 
-    installed
+    let mut warnings: Vec<String> = editors.iter().filter_map(cli_warning).collect();
+    // The newest of the installed editors has to be new enough
+    let version = min_version.and_then(|_| {
+        process::in_parallel(&editors, |found| editor_version(runner, found.editor))
+            .into_iter()
+            .flatten()
+            .max_by(|a, b| compare_versions(a, b))
+    });
+    let found: Vec<String> = editors.into_iter().map(|found| found.details).collect();
+    let mut status = PrerequisiteStatus::found("VS Code", found.join(", "))
+        .require(version.as_deref(), min_version);
+    warnings.extend(status.warning.take());
+    status.warning = (!warnings.is_empty()).then(|| warnings.join("; "));
+    status
 }
 
-fn is_vscode_installed() -> bool {
-    // Check if VS Code app exists (platform-specific paths)
-    #[cfg(target_os = "windows")]
-    {
-        let paths = [
-            r"C:\Program Files\Microsoft VS Code\Code.exe",
-            r"C:\Program Files (x86)\Microsoft VS Code\Code.exe",
-        ];
-        for path in &paths {
-            if std::path::Path::new(path).exists() {
-                return true;
+/// Node.js, which reports its version as `v20.11.0`
+pub fn node_status(runner: &dyn Runner, min_version: Option<&str>) -> PrerequisiteStatus {
+    command_status(runner, "Node.js", "node", &[], min_version)
+}
+
+fn npm_status(runner: &dyn Runner) -> PrerequisiteStatus {
+    // npm is a batch file on Windows, which only runs by its full name
+    let program = if cfg!(windows) { "npm.cmd" } else { "npm" };
+    command_status(runner, "npm", program, &[], None)
+}
+
+/// A program that reports its version when run with `version_args`
+fn command_status(
+    runner: &dyn Runner,
+    name: &str,
+    program: &str,
+    version_args: &[String],
+    min_version: Option<&str>,
+) -> PrerequisiteStatus {
+    let args: Vec<&str> = if version_args.is_empty() {
+        vec!["--version"]
+    } else {
+        version_args.iter().map(String::as_str).collect()
+    };
+    match command_output(runner, program, &args) {
+        Ok(details) => {
+            let version = extract_version(&details).map(str::to_string);
+            PrerequisiteStatus::found(name, details).require(version.as_deref(), min_version)
+        }
+        Err(CommandError::Failed) => PrerequisiteStatus::missing(
+            name,
+            format!(
+                "'{} {}' failed or {} is not on PATH",
+                program,
+                args.join(" "),
+                program
+            ),
+        ),
+        Err(CommandError::TimedOut) => {
+            let problem = format!(
+                "'{} {}' did not finish within {}s",
+                program,
+                args.join(" "),
+                COMMAND_TIMEOUT.as_secs()
+            );
+            PrerequisiteStatus {
+                problem: Some(problem.clone()),
+                ..PrerequisiteStatus::missing(name, problem)
             }
         }
     }
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        if std::path::Path::new("/Applications/Visual Studio Code.app").exists() {
-            return true;
+fn disk_space_status(home: &Path, bytes: u64) -> PrerequisiteStatus {
+    let usage = match space::usage(&[(home, bytes)]) {
+        Ok(usage) => usage,
+        // Not knowing isn't a reason to stop; the install checks again
+        Err(e) => {
+            let mut status = PrerequisiteStatus::found("Disk space", "could not be checked");
+            status.warning = Some(format!("{:#}", e));
+            return status;
         }
+    };
+    let Some(usage) = usage.first() else {
+        return PrerequisiteStatus::found("Disk space", "nothing required");
+    };
+    let details = format!(
+        "{} free on {}, {} required",
+        space::describe_bytes(usage.available),
+        usage.describe_dirs(),
+        space::describe_bytes(usage.required)
+    );
+    let mut status = PrerequisiteStatus::found("Disk space", details);
+    if !usage.is_enough() {
+        status.installed = false;
+        status.problem = Some(format!(
+            "{} required, {} free",
+            indicatif::HumanBytes(usage.required),
+            indicatif::HumanBytes(usage.available)
+        ));
     }
+    status
+}
+
+/// Why an installed editor's CLI needs attention, if it does
+fn cli_warning(found: &InstalledEditor) -> Option<String> {
+    if found.cli_on_path {
+        return None;
+    }
+    let command = found.editor.command();
+    Some(match &found.bundled_cli {
+        Some(cli) => format!(
+            "'{}' is not on PATH; extensions will be installed with {}",
+            command,
+            cli.display()
+        ),
+        None => format!(
+            "'{}' is not on PATH and {} has no CLI, so extensions can't be installed",
+            command, found.details
+        ),
+    })
+}
+
+/// The version `editor`'s CLI reports, such as `1.90.0`
+pub fn editor_version(runner: &dyn Runner, editor: Editor) -> Option<String> {
+    let output = command_version(runner, editor.cli()).ok()?;
+    extract_version(&output).map(str::to_string)
+}
+
+fn git_status(runner: &dyn Runner) -> PrerequisiteStatus {
+    command_status(runner, "Git", "git", &[], None)
+}
 
-    // Check if 'code' command is available (works on all platforms)
-    std::process::Command::new("code")
-        .arg("--version")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// An editor found on this machine
+#[derive(Debug)]
+pub struct InstalledEditor {
+    pub editor: Editor,
+    /// Where or which version was found
+    pub details: String,
+    /// Whether its CLI is on PATH
+    pub cli_on_path: bool,
+    /// The CLI inside the install, for when it isn't on PATH
+    pub bundled_cli: Option<PathBuf>,
 }
 
-fn is_git_installed() -> bool {
-    std::process::Command::new("git")
-        .arg("--version")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Editors installed on this machine, in the order of `Editor::ALL`
+pub fn installed_editors(runner: &dyn Runner) -> Vec<InstalledEditor> {
+    process::in_parallel(&Editor::ALL, |editor| find_editor(runner, *editor))
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Editors to deploy settings and extensions to: `selected` if given,
+/// otherwise every installed one, or VS Code if none are found
+pub fn target_editors(runner: &dyn Runner, selected: Option<Editor>) -> Vec<Editor> {
+    if let Some(editor) = selected {
+        return vec![editor];
+    }
+
+    let installed: Vec<Editor> = installed_editors(runner)
+        .into_iter()
+        .map(|found| found.editor)
+        .collect();
+    if installed.is_empty() {
+        vec![Editor::Stable]
+    } else {
+        installed
+    }
+}
+
+/// Locate an editor, in its install locations or by its CLI
+fn find_editor(runner: &dyn Runner, editor: Editor) -> Option<InstalledEditor> {
+    let cli_on_path = editor.cli_on_path().is_some();
+
+    // Check the platform-specific install locations
+    if let Some(location) = editor
+        .install_locations()
+        .into_iter()
+        .find(|location| location.exists())
+    {
+        let bundled_cli = Some(editor.bundled_cli(&location)).filter(|cli| cli.is_file());
+        return Some(InstalledEditor {
+            editor,
+            details: location.display().to_string(),
+            cli_on_path,
+            bundled_cli,
+        });
+    }
+
+    // Check if its CLI is available (works on all platforms)
+    let version = command_version(runner, editor.cli()).ok()?;
+    Some(InstalledEditor {
+        editor,
+        details: format!("{} {}", editor.command(), version),
+        cli_on_path,
+        bundled_cli: None,
+    })
+}
+
+/// First line of `<program> --version`, if it runs successfully
+fn command_version(
+    runner: &dyn Runner,
+    program: impl AsRef<OsStr>,
+) -> Result<String, CommandError> {
+    command_output(runner, program, &["--version"])
+}
+
+/// Why a version command gave no output to read
+enum CommandError {
+    /// It isn't on PATH or exited with an error
+    Failed,
+    /// It was killed after `COMMAND_TIMEOUT`
+    TimedOut,
+}
+
+/// First line `program` prints when `runner` runs it with `args`, if it
+/// runs successfully within `COMMAND_TIMEOUT`
+fn command_output(
+    runner: &dyn Runner,
+    program: impl AsRef<OsStr>,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    let output = match runner.output_with_timeout(&mut command, COMMAND_TIMEOUT) {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return Err(CommandError::TimedOut),
+        Err(_) => return Err(CommandError::Failed),
+    };
+
+    if !output.status.success() {
+        return Err(CommandError::Failed);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// The first version number in `text`, such as `2.43.0` in
+/// `git version 2.43.0.windows.1` or `20.11.0` in `v20.11.0`
+fn extract_version(text: &str) -> Option<&str> {
+    text.split_whitespace().find_map(|word| {
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let end = word
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(word.len());
+        let version = word[..end].trim_end_matches('.');
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then_some(version)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+
+    fn found(cli_on_path: bool, bundled_cli: Option<&str>) -> InstalledEditor {
+        InstalledEditor {
+            editor: Editor::Stable,
+            details: "Code.exe".to_string(),
+            cli_on_path,
+            bundled_cli: bundled_cli.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn each_prerequisite_checked_is_an_event() {
+        let missing = Prerequisite::Command {
+            name: "code-assist-no-such-command".to_string(),
+            version_args: Vec::new(),
+            min_version: None,
+        };
+        let runner = FakeRunner::new(|_| (1, String::new()));
+        let (missing, events) = events::capture(|| check(&runner, &[missing], Path::new(".")));
+
+        assert_eq!(missing, ["code-assist-no-such-command"]);
+        assert_eq!(runner.commands(), ["code-assist-no-such-command --version"]);
+        assert_eq!(
+            events,
+            [
+                Event::PrerequisiteChecked {
+                    name: "code-assist-no-such-command".to_string(),
+                    installed: false,
+                    problem: Some("not installed".to_string()),
+                },
+                Event::error(format!(
+                    "code-assist-no-such-command - {}",
+                    style("not installed").red()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn versions_are_found_in_command_output() {
+        assert_eq!(extract_version("v20.11.0"), Some("20.11.0"));
+        assert_eq!(
+            extract_version("git version 2.43.0.windows.1"),
+            Some("2.43.0")
+        );
+        assert_eq!(extract_version("1.90.2"), Some("1.90.2"));
+        assert_eq!(
+            extract_version("Docker version 24.0.7, build afdd53b"),
+            Some("24.0.7")
+        );
+        assert_eq!(extract_version("no version here"), None);
+    }
+
+    #[test]
+    fn union_keeps_the_strictest_requirement_once() {
+        let node = |min: Option<&str>| Prerequisite::Node {
+            min_version: min.map(str::to_string),
+        };
+        let all = union([
+            Prerequisite::defaults(),
+            vec![node(Some("18")), Prerequisite::DiskSpace { bytes: 10 }],
+            vec![
+                node(Some("20.1")),
+                node(None),
+                Prerequisite::Git,
+                Prerequisite::DiskSpace { bytes: 5 },
+            ],
+        ]);
+
+        assert_eq!(
+            all,
+            vec![
+                Prerequisite::VsCode { min_version: None },
+                Prerequisite::Git,
+                node(Some("20.1")),
+                Prerequisite::DiskSpace { bytes: 10 },
+            ]
+        );
+    }
+
+    #[test]
+    fn an_old_version_fails_the_check() {
+        let status = PrerequisiteStatus::found("Node.js", "v16.20.0");
+        let status = status.require(Some("16.20.0"), Some("18"));
+        assert!(!status.installed);
+        assert_eq!(
+            status.problem.as_deref(),
+            Some("too old (found 16.20.0, need 18 or later)")
+        );
+
+        let status = PrerequisiteStatus::found("Node.js", "v20.11.0");
+        assert!(status.require(Some("20.11.0"), Some("18")).installed);
+    }
+
+    #[test]
+    fn cli_on_path_needs_no_warning() {
+        assert_eq!(cli_warning(&found(true, None)), None);
+    }
+
+    #[test]
+    fn missing_cli_on_path_names_the_one_used_instead() {
+        let warning = cli_warning(&found(false, Some("bin/code.cmd"))).unwrap();
+        assert!(warning.contains("'code' is not on PATH"), "{}", warning);
+        assert!(warning.contains("bin/code.cmd"), "{}", warning);
+
+        let warning = cli_warning(&found(false, None)).unwrap();
+        assert!(warning.contains("can't be installed"), "{}", warning);
+    }
 }
@@ -0,0 +1,55 @@
+//! A `Runner` that records the commands it's given instead of running them
+
+use std::process::{Command, ExitStatus, Output};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{describe, Runner};
+
+type Respond = Box<dyn Fn(&str) -> (i32, String) + Sync>;
+
+/// Answers each command with the exit code and stdout `respond` gives for
+/// its command line, and keeps the command lines in order
+pub struct FakeRunner {
+    respond: Respond,
+    commands: Mutex<Vec<String>>,
+}
+
+impl FakeRunner {
+    pub fn new(respond: impl Fn(&str) -> (i32, String) + Sync + 'static) -> Self {
+        FakeRunner {
+            respond: Box::new(respond),
+            commands: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every command line run so far
+    pub fn commands(&self) -> Vec<String> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+impl Runner for FakeRunner {
+    fn run(&self, command: &mut Command, _timeout: Option<Duration>) -> std::io::Result<Output> {
+        let command_line = describe(command);
+        let (code, stdout) = (self.respond)(&command_line);
+        self.commands.lock().unwrap().push(command_line);
+        Ok(Output {
+            status: exit_status(code),
+            stdout: stdout.into_bytes(),
+            stderr: Vec::new(),
+        })
+    }
+}
+
+fn exit_status(code: i32) -> ExitStatus {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+    }
+
+    #[cfg(windows)]
+    {
+        std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+    }
+}
@@ -0,0 +1,205 @@
+#[cfg(test)]
+pub mod fake;
+
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::cancel;
+
+/// How often a running child is checked on, to kill it after Ctrl+C
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Run a command to completion with the real system. Code that tests
+/// should be able to see into takes a [`Runner`] instead.
+pub fn output(command: &mut Command) -> std::io::Result<Output> {
+    System.output(command)
+}
+
+/// Like [`output`], but kill the command and fail with
+/// [`std::io::ErrorKind::TimedOut`] if it's still running after `timeout`
+pub fn output_with_timeout(command: &mut Command, timeout: Duration) -> std::io::Result<Output> {
+    System.output_with_timeout(command, timeout)
+}
+
+fn logged(
+    runner: &(impl Runner + ?Sized),
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> std::io::Result<Output> {
+    let command_line = describe(command);
+    let span = tracing::debug_span!("exec", command = %command_line);
+    let _enter = span.enter();
+
+    tracing::debug!("running {}", command_line);
+    let result = runner.run(command, timeout);
+
+    match &result {
+        Ok(output) => {
+            tracing::debug!(status = %output.status, "finished");
+            tracing::trace!(
+                stdout = %String::from_utf8_lossy(&output.stdout).trim(),
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "output"
+            );
+        }
+        Err(e) => tracing::debug!(error = %e, "failed to start"),
+    }
+
+    result
+}
+
+/// What starts external commands. Tools and the config code take one, so
+/// tests can pass a fake in place of `System` and check the commands an
+/// install runs without running them. It's `Sync` so the prerequisite
+/// checks can share one across `in_parallel`.
+pub trait Runner: Sync {
+    fn run(&self, command: &mut Command, timeout: Option<Duration>) -> std::io::Result<Output>;
+
+    /// Run a command to completion, logging the full command line and its
+    /// exit status at debug level and its output at trace level. Ctrl+C
+    /// kills the command and fails with [`std::io::ErrorKind::Interrupted`].
+    fn output(&self, command: &mut Command) -> std::io::Result<Output> {
+        logged(self, command, None)
+    }
+
+    /// Like [`Runner::output`], but kill the command and fail with
+    /// [`std::io::ErrorKind::TimedOut`] if it's still running after
+    /// `timeout`
+    fn output_with_timeout(
+        &self,
+        command: &mut Command,
+        timeout: Duration,
+    ) -> std::io::Result<Output> {
+        logged(self, command, Some(timeout))
+    }
+}
+
+/// Starts real child processes
+pub struct System;
+
+impl Runner for System {
+    fn run(&self, command: &mut Command, timeout: Option<Duration>) -> std::io::Result<Output> {
+        run(command, timeout)
+    }
+}
+
+/// Like `Command::output`, but checking for Ctrl+C and `timeout` while the
+/// child runs
+fn run(command: &mut Command, timeout: Option<Duration>) -> std::io::Result<Output> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain both pipes as the child writes, so a full one can't stall it
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if cancel::is_cancelled() {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "stopped by Ctrl+C",
+            ));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            child.kill().ok();
+            child.wait().ok();
+            // A grandchild may still hold the pipes open, so leave the
+            // readers behind rather than wait on them
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!(
+                    "did not finish within {}s",
+                    timeout.unwrap_or_default().as_secs()
+                ),
+            ));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let collect = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        reader.and_then(|r| r.join().ok()).unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer).ok();
+        buffer
+    })
+}
+
+/// Render a command as a copy-pasteable command line
+pub fn describe(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| {
+            let part = part.to_string_lossy();
+            if part.is_empty() || part.contains(char::is_whitespace) {
+                format!("\"{}\"", part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `f` applied to each of `items`, each on its own thread, in the order of
+/// `items`. For checks that mostly wait on child processes, which are slow
+/// to start on Windows.
+pub fn in_parallel<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .iter()
+            .map(|item| scope.spawn(move || f(item)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_command_past_its_timeout_is_killed() {
+        let started = Instant::now();
+        let error =
+            output_with_timeout(Command::new("sleep").arg("10"), Duration::from_millis(200))
+                .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn parallel_results_keep_their_order() {
+        let doubled = in_parallel(&[3, 1, 2], |n| {
+            std::thread::sleep(Duration::from_millis(*n * 10));
+            n * 2
+        });
+        assert_eq!(doubled, [6, 2, 4]);
+    }
+}
@@ -0,0 +1,333 @@
+//! A machine-readable record of what `install` did, for inventory tools:
+//! the version, where the binary came from, how long each step took, and
+//! every file, settings key, environment variable, and extension it
+//! changed. `install --report` writes it as JSON, also when the install
+//! fails, with the step it failed in marked. `uninstall --report` writes
+//! the same record of what it removed and what it couldn't.
+//!
+//! It's gathered from the events the install emits, see `output::events`,
+//! by a `Recorder` that `main` subscribes when a report was asked for.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Instant, SystemTime};
+
+use crate::config;
+use crate::download::{Channel, DownloadSource};
+use crate::output::events::{Event, Subscriber};
+use crate::output::reporter;
+
+struct Recording {
+    report: InstallReport,
+    started: Instant,
+    /// When the step in progress, the last one of the last tool, started
+    step_started: Option<Instant>,
+}
+
+#[derive(Serialize)]
+pub struct InstallReport {
+    /// UTC, e.g. `20260131T235959Z`
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// `tool: step` the install failed in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_step: Option<String>,
+    pub tools: Vec<ToolReport>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ToolReport {
+    pub tool: String,
+    pub version: Option<String>,
+    pub channel: Option<Channel>,
+    pub download_source: Option<DownloadSource>,
+    pub steps: Vec<StepReport>,
+    pub files_written: Vec<PathBuf>,
+    pub settings_changed: Vec<SettingsChange>,
+    pub env_vars: Vec<EnvVar>,
+    pub extensions_installed: Vec<InstalledExtension>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files_removed: Vec<PathBuf>,
+    /// What an uninstall left behind, and why
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removal_failures: Vec<RemovalFailure>,
+}
+
+#[derive(Serialize)]
+pub struct StepReport {
+    pub name: String,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    /// Still going when the report was written, which only a crash leaves
+    Running,
+    Ok,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct SettingsChange {
+    pub file: PathBuf,
+    /// Top-level keys added, changed, or removed
+    pub keys: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize)]
+pub struct RemovalFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct InstalledExtension {
+    pub editor: String,
+    pub id: String,
+}
+
+/// Feeds a recording from the events of the run, until it's finished
+pub struct Recorder {
+    recording: RefCell<Option<Recording>>,
+}
+
+impl Recorder {
+    /// Start recording the events of the run
+    pub fn start() -> Rc<Recorder> {
+        Rc::new(Recorder {
+            recording: RefCell::new(Some(Recording {
+                report: InstallReport {
+                    started_at: config::utc_timestamp(SystemTime::now()),
+                    duration_ms: 0,
+                    success: false,
+                    error: None,
+                    failed_step: None,
+                    tools: Vec::new(),
+                    warnings: Vec::new(),
+                    errors: Vec::new(),
+                },
+                started: Instant::now(),
+                step_started: None,
+            })),
+        })
+    }
+
+    fn with(&self, f: impl FnOnce(&mut Recording)) {
+        if let Some(recording) = self.recording.borrow_mut().as_mut() {
+            f(recording);
+        }
+    }
+
+    fn with_tool(&self, f: impl FnOnce(&mut ToolReport)) {
+        self.with(|recording| {
+            if let Some(tool) = recording.report.tools.last_mut() {
+                f(tool);
+            }
+        });
+    }
+}
+
+impl Subscriber for Recorder {
+    fn event(&self, event: &Event) {
+        match event {
+            Event::ToolStarted { tool } => self.with(|recording| {
+                recording.report.tools.push(ToolReport {
+                    tool: tool.clone(),
+                    version: None,
+                    channel: None,
+                    download_source: None,
+                    steps: Vec::new(),
+                    files_written: Vec::new(),
+                    settings_changed: Vec::new(),
+                    env_vars: Vec::new(),
+                    extensions_installed: Vec::new(),
+                    files_removed: Vec::new(),
+                    removal_failures: Vec::new(),
+                });
+            }),
+            Event::StepStarted { step } => self.with(|recording| {
+                if let Some(tool) = recording.report.tools.last_mut() {
+                    tool.steps.push(StepReport {
+                        name: step.clone(),
+                        status: StepStatus::Running,
+                        duration_ms: 0,
+                    });
+                    recording.step_started = Some(Instant::now());
+                }
+            }),
+            Event::StepCompleted { .. } => self.with(|recording| {
+                recording.end_step(StepStatus::Ok);
+            }),
+            Event::StepFailed { .. } => self.with(|recording| {
+                recording.end_step(StepStatus::Failed);
+            }),
+            Event::VersionChosen { version, channel } => self.with_tool(|tool| {
+                tool.version = Some(version.clone());
+                tool.channel = Some(*channel);
+            }),
+            Event::DownloadSource(source) => {
+                self.with_tool(|tool| tool.download_source = Some(*source))
+            }
+            Event::FileDeployed(path) => {
+                self.with_tool(|tool| tool.files_written.push(path.clone()))
+            }
+            Event::SettingsChanged { file, keys } => self.with_tool(|tool| {
+                tool.settings_changed.push(SettingsChange {
+                    file: file.clone(),
+                    keys: keys.clone(),
+                })
+            }),
+            Event::EnvVarSet { name, value } => self.with_tool(|tool| {
+                tool.env_vars.push(EnvVar {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+            }),
+            Event::ExtensionInstalled { editor, id } => self.with_tool(|tool| {
+                tool.extensions_installed.push(InstalledExtension {
+                    editor: editor.clone(),
+                    id: id.clone(),
+                })
+            }),
+            Event::FileRemoved(path) => {
+                self.with_tool(|tool| tool.files_removed.push(path.clone()))
+            }
+            Event::RemovalFailed { path, error } => self.with_tool(|tool| {
+                tool.removal_failures.push(RemovalFailure {
+                    path: path.clone(),
+                    error: error.clone(),
+                })
+            }),
+            Event::Notice(message) | Event::Warning(message) => {
+                self.with(|recording| recording.report.warnings.push(plain(message)))
+            }
+            Event::Error(message) => {
+                self.with(|recording| recording.report.errors.push(plain(message)))
+            }
+            Event::DownloadFailed { .. } | Event::PrerequisiteChecked { .. } | Event::Line(_) => {}
+        }
+    }
+}
+
+/// A message as it's recorded, without the styling it's printed with
+fn plain(message: impl Display) -> String {
+    console::strip_ansi_codes(&message.to_string()).into_owned()
+}
+
+impl Recorder {
+    /// Finish the report with how the install ended and write it to `path`,
+    /// or to stdout for `-`, passing `result` on. Failing to write the report
+    /// fails a successful install, but doesn't hide why one failed.
+    pub fn save(&self, path: &Path, result: Result<()>) -> Result<()> {
+        let Some(report) = self.finish(&result) else {
+            return result;
+        };
+        let written = write(path, &report);
+        match (result, written) {
+            (Ok(()), written) => written,
+            (Err(e), Ok(())) => Err(e),
+            (Err(e), Err(written)) => {
+                reporter().error(format!("{:#}", written));
+                Err(e)
+            }
+        }
+    }
+
+    fn finish(&self, result: &Result<()>) -> Option<InstallReport> {
+        let mut recording = self.recording.borrow_mut().take()?;
+        match result {
+            Ok(()) => {
+                recording.end_step(StepStatus::Ok);
+                recording.report.success = true;
+            }
+            Err(e) => {
+                recording.report.failed_step = recording.end_step(StepStatus::Failed);
+                recording.report.error = Some(format!("{:#}", e));
+            }
+        }
+        recording.report.duration_ms = millis(recording.started);
+        Some(recording.report)
+    }
+}
+
+fn write(path: &Path, report: &InstallReport) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    if path == Path::new("-") {
+        println!("{}", json);
+        return Ok(());
+    }
+    std::fs::write(path, format!("{}\n", json))
+        .with_context(|| format!("Failed to write the report to {}", path.display()))
+}
+
+impl Recording {
+    /// End the step in progress, if any, as `status`, returning it as
+    /// `tool: step`
+    fn end_step(&mut self, status: StepStatus) -> Option<String> {
+        let started = self.step_started.take()?;
+        let tool = self.report.tools.last_mut()?;
+        let step = tool.steps.last_mut()?;
+        step.status = status;
+        step.duration_ms = millis(started);
+        Some(format!("{}: {}", tool.tool, step.name))
+    }
+}
+
+fn millis(since: Instant) -> u64 {
+    since.elapsed().as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::events::{self, Bus};
+
+    #[test]
+    fn a_failure_marks_the_step_in_progress() {
+        let recorder = Recorder::start();
+        let bus = Rc::new(Bus::default());
+        bus.subscribe(recorder.clone());
+        events::with_bus(bus, || {
+            events::tool("claude-code");
+            events::step("download");
+            events::emit(Event::DownloadSource(DownloadSource::Cache));
+            events::step("setup");
+            events::emit(Event::warning(
+                console::style("slow").yellow().force_styling(true),
+            ));
+        });
+
+        let report = recorder
+            .finish(&Err(anyhow::anyhow!("setup crashed")))
+            .unwrap();
+        assert!(!report.success);
+        assert_eq!(report.failed_step.as_deref(), Some("claude-code: setup"));
+        assert_eq!(report.error.as_deref(), Some("setup crashed"));
+        assert_eq!(report.warnings, ["slow"]);
+        let steps = &report.tools[0].steps;
+        assert_eq!(steps[0].status, StepStatus::Ok);
+        assert_eq!(steps[1].status, StepStatus::Failed);
+        assert_eq!(report.tools[0].download_source, Some(DownloadSource::Cache));
+
+        // Nothing is recorded once the report is finished
+        recorder.event(&Event::warning("after"));
+        assert!(recorder.finish(&Ok(())).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+//! `self-update`: replace the running code-assist with a newer build.
+//!
+//! The update URL is laid out like the release bucket: `latest` holds the
+//! newest version, and `<version>/manifest.json` (signed like release
+//! manifests) lists the build for each platform, downloaded from
+//! `<version>/<platform>/` unless the entry gives its own URL.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::download::{self, Manifest, PlatformEntry};
+use crate::error::Failure;
+
+/// Where builds of code-assist itself are published, next to the Claude
+/// Code releases in the same bucket
+pub const DEFAULT_URL: &str = "https://storage.googleapis.com/claude-code-dist-86c565f3-f756-42ad-8dfa-d59b1c096819/code-assist";
+
+/// Suffix of the file a Windows update moves the running binary aside to
+const OLD_SUFFIX: &str = ".old";
+
+/// Suffix of the new binary while it's staged next to the running one
+const NEW_SUFFIX: &str = ".new";
+
+/// A build of code-assist newer than the running one
+pub struct Release {
+    pub entry: PlatformEntry,
+    /// Where the build for this platform is downloaded from
+    pub url: String,
+}
+
+/// Name of the code-assist binary on this platform
+pub fn binary_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "code-assist.exe"
+    } else {
+        "code-assist"
+    }
+}
+
+/// Newest version published at `url`
+pub fn latest_version(url: &str) -> Result<String> {
+    let latest_url = format!("{}/latest", url.trim_end_matches('/'));
+    let text = download::fetch_text(&latest_url).with_context(|| {
+        Failure::Download(format!("Could not check {} for updates", latest_url))
+    })?;
+    Ok(text.trim().to_string())
+}
+
+/// The build of `version` for this platform
+pub fn release(url: &str, version: &str) -> Result<Release> {
+    let base = format!("{}/{}", url.trim_end_matches('/'), version);
+    let manifest: Manifest = download::fetch_manifest(&format!("{}/manifest.json", base), version)
+        .with_context(|| {
+            Failure::Download(format!(
+                "Could not read the manifest for code-assist {}",
+                version
+            ))
+        })?;
+    let platform = crate::platform::get_platform_id();
+    let entry = manifest
+        .platform(version, platform)
+        .map_err(|e| Failure::Download(format!("{:#}", e)))?
+        .clone();
+    let file_name = match entry.archive {
+        Some(kind) => kind.file_name(binary_name()),
+        None => binary_name().to_string(),
+    };
+    let url = entry
+        .download_url
+        .clone()
+        .unwrap_or_else(|| format!("{}/{}/{}", base, platform, file_name));
+    Ok(Release { entry, url })
+}
+
+/// The binary to replace: the running one, with symlinks followed so a
+/// link on PATH is left pointing at the new build
+pub fn current_exe() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("Failed to locate the running code-assist")?;
+    Ok(exe.canonicalize().unwrap_or(exe))
+}
+
+/// Check a new file can be created next to `exe`, which replacing it needs
+pub fn check_writable(exe: &Path) -> std::io::Result<()> {
+    let probe = with_suffix(exe, &format!(".write-test-{}", std::process::id()));
+    std::fs::File::create(&probe)?;
+    std::fs::remove_file(&probe)
+}
+
+/// Download `release` next to `exe`, ready for `replace`
+pub fn stage(release: &Release, exe: &Path) -> Result<PathBuf> {
+    let staged = with_suffix(exe, NEW_SUFFIX);
+    download::download_entry(&release.url, &release.entry, binary_name(), &staged)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", staged.display()))?;
+    }
+    Ok(staged)
+}
+
+/// Move `staged` into place of the running `exe`. A rename replaces a
+/// running binary on Unix, but Windows refuses to overwrite one while
+/// still allowing it to be renamed, so there it's moved aside first and
+/// removed by a later run.
+pub fn replace(exe: &Path, staged: &Path) -> Result<()> {
+    if cfg!(target_os = "windows") {
+        let old = with_suffix(exe, OLD_SUFFIX);
+        std::fs::remove_file(&old).ok();
+        std::fs::rename(exe, &old)
+            .with_context(|| format!("Failed to move {} aside", exe.display()))?;
+        if let Err(e) = std::fs::rename(staged, exe) {
+            // Put the running binary back rather than leave nothing there
+            std::fs::rename(&old, exe).ok();
+            return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+        }
+        return Ok(());
+    }
+
+    std::fs::rename(staged, exe).with_context(|| format!("Failed to replace {}", exe.display()))
+}
+
+/// Remove what an earlier update left behind: the binary a Windows update
+/// moved aside, which couldn't be deleted while it was running
+pub fn remove_leftovers() {
+    if let Ok(exe) = current_exe() {
+        std::fs::remove_file(with_suffix(&exe, OLD_SUFFIX)).ok();
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -0,0 +1,431 @@
+//! A machine's effective setup, for `export` and `import`: the installed
+//! tools and versions, the settings keys we deployed with their current
+//! values, the extensions we installed, the certificates we deployed, and
+//! the environment variables we set. Paths under the home directory are
+//! written with `~`, so a file exported on one machine compares equal on
+//! another.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::config;
+use crate::download::Channel;
+use crate::error::Failure;
+use crate::platform::{Editor, Host, PlatformPaths};
+use crate::state::State;
+use crate::tools::Tool;
+
+/// Version of the file format; files from a newer one are refused
+pub const SCHEMA: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Setup {
+    pub schema: u32,
+    /// Version of code-assist that exported it
+    pub exported_by: String,
+    /// Platform it was exported on, such as `win32-x64`
+    pub platform: String,
+    pub tools: Vec<ToolSetup>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ToolSetup {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub channel: Option<Channel>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub settings: Vec<SettingValue>,
+    #[serde(default)]
+    pub extensions: Vec<ExtensionVersion>,
+    #[serde(default)]
+    pub certificates: Vec<Certificate>,
+    #[serde(default)]
+    pub env_vars: BTreeMap<String, String>,
+}
+
+/// A settings key we deployed, with the value it has now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingValue {
+    pub file: String,
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionVersion {
+    pub editor: Editor,
+    pub id: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub file: String,
+    pub subject: String,
+    /// SHA-256 of the DER encoding, as colon-separated hex
+    pub fingerprint: String,
+}
+
+impl Setup {
+    /// Read an exported setup, refusing files from a newer format
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let setup: Setup = serde_json::from_str(&content).with_context(|| {
+            Failure::Configuration(format!("{} is not a valid setup file", path.display()))
+        })?;
+        if setup.schema > SCHEMA {
+            bail!(Failure::Configuration(format!(
+                "{} was exported by a newer code-assist ({}); update to import it",
+                path.display(),
+                setup.exported_by
+            )));
+        }
+        Ok(setup)
+    }
+}
+
+/// What `tool` looks like on this machine, as seen through `host`, or
+/// `None` if it isn't installed
+pub fn capture(tool: &dyn Tool, paths: &PlatformPaths, host: &Host) -> Result<Option<ToolSetup>> {
+    if !tool.is_installed()? {
+        return Ok(None);
+    }
+    let state = State::load(paths, tool.name())?.unwrap_or_default();
+    let home = &paths.home_dir;
+
+    let settings = state
+        .settings
+        .iter()
+        .filter_map(|entry| {
+            let current = config::read_json_object(&entry.file)?;
+            Some(SettingValue {
+                file: portable(&entry.file, home),
+                key: entry.key.clone(),
+                value: current.get(&entry.key)?.clone(),
+            })
+        })
+        .collect();
+
+    let mut extensions = Vec::new();
+    for editor in Editor::ALL {
+        let ours: Vec<&str> = state
+            .extensions
+            .iter()
+            .filter(|extension| extension.editor == editor)
+            .map(|extension| extension.id.as_str())
+            .collect();
+        if ours.is_empty() {
+            continue;
+        }
+        let installed =
+            config::list_installed_extensions(&*host.runner, editor).unwrap_or_default();
+        for id in ours {
+            if let Some(found) = installed.iter().find(|found| found.matches(id)) {
+                extensions.push(ExtensionVersion {
+                    editor,
+                    id: found.id.clone(),
+                    version: found.version.clone(),
+                });
+            }
+        }
+    }
+
+    let deployed = state
+        .files
+        .iter()
+        .chain(state.copied_files.iter().map(|file| &file.path))
+        .chain(state.linked_files.iter().map(|file| &file.path));
+    let mut certificates: Vec<Certificate> = Vec::new();
+    for file in deployed {
+        if !config::is_certificate_file(file) {
+            continue;
+        }
+        let Ok(content) = std::fs::read(file) else {
+            continue;
+        };
+        for info in config::read_certificates(&content).unwrap_or_default() {
+            if !certificates
+                .iter()
+                .any(|c| c.fingerprint == info.fingerprint)
+            {
+                certificates.push(Certificate {
+                    file: portable(file, home),
+                    subject: info.subject,
+                    fingerprint: info.fingerprint,
+                });
+            }
+        }
+    }
+
+    let env_vars = state
+        .env_vars
+        .iter()
+        .filter_map(|var| {
+            let value = host.environment.current_env_var(&var.name)?;
+            Some((var.name.clone(), portable(Path::new(&value), home)))
+        })
+        .collect();
+
+    Ok(Some(ToolSetup {
+        name: tool.name().to_string(),
+        version: tool.installed_version()?,
+        channel: tool.installed_channel()?,
+        profile: tool.deployed_profile()?,
+        settings,
+        extensions,
+        certificates,
+        env_vars,
+    }))
+}
+
+/// How this machine differs from a setup file
+#[derive(Debug)]
+pub enum Difference {
+    /// The tool isn't installed, or not at the wanted version
+    Install {
+        current: Option<String>,
+        wanted: Option<String>,
+    },
+    Profile {
+        current: Option<String>,
+        wanted: String,
+    },
+    Setting {
+        file: String,
+        key: String,
+        current: Option<serde_json::Value>,
+        wanted: serde_json::Value,
+    },
+    Extension {
+        editor: Editor,
+        id: String,
+        current: Option<String>,
+        wanted: Option<String>,
+    },
+    Certificate {
+        subject: String,
+        fingerprint: String,
+    },
+    EnvVar {
+        name: String,
+        current: Option<String>,
+        wanted: String,
+    },
+}
+
+impl Difference {
+    /// Whether an extension install, rather than an install or configure,
+    /// makes up this difference
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Difference::Extension { .. })
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_latest = |version: &Option<String>| version.clone().unwrap_or("latest".into());
+        match self {
+            Difference::Install {
+                current: None,
+                wanted,
+            } => write!(f, "install {}", or_latest(wanted)),
+            Difference::Install {
+                current: Some(current),
+                wanted,
+            } => write!(f, "install {} (now {})", or_latest(wanted), current),
+            Difference::Profile { current, wanted } => write!(
+                f,
+                "deploy profile {} (now {})",
+                wanted,
+                current.as_deref().unwrap_or("none")
+            ),
+            Difference::Setting {
+                file,
+                key,
+                current,
+                wanted,
+            } => match current {
+                Some(current) => write!(f, "{}: set {} to {} (now {})", file, key, wanted, current),
+                None => write!(f, "{}: set {} to {}", file, key, wanted),
+            },
+            Difference::Extension {
+                editor,
+                id,
+                current,
+                wanted,
+            } => {
+                write!(f, "{}: install extension {}", editor, id)?;
+                if let Some(wanted) = wanted {
+                    write!(f, "@{}", wanted)?;
+                }
+                if let Some(current) = current {
+                    write!(f, " (now {})", current)?;
+                }
+                Ok(())
+            }
+            Difference::Certificate {
+                subject,
+                fingerprint,
+            } => write!(
+                f,
+                "deploy certificate {} (SHA-256 {})",
+                subject, fingerprint
+            ),
+            Difference::EnvVar {
+                name,
+                current,
+                wanted,
+            } => match current {
+                Some(current) => write!(f, "set {} to {} (now {})", name, wanted, current),
+                None => write!(f, "set {} to {}", name, wanted),
+            },
+        }
+    }
+}
+
+/// What it takes to get from `current` (`None` if not installed) to `wanted`
+pub fn differences(wanted: &ToolSetup, current: Option<&ToolSetup>) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let empty = ToolSetup::default();
+    let installed = current.is_some();
+    let current = current.unwrap_or(&empty);
+
+    if !installed || (wanted.version.is_some() && wanted.version != current.version) {
+        differences.push(Difference::Install {
+            current: current.version.clone().filter(|_| installed),
+            wanted: wanted.version.clone(),
+        });
+    }
+
+    if let Some(profile) = &wanted.profile {
+        if current.profile.as_ref() != Some(profile) {
+            differences.push(Difference::Profile {
+                current: current.profile.clone(),
+                wanted: profile.clone(),
+            });
+        }
+    }
+
+    for setting in &wanted.settings {
+        let now = current
+            .settings
+            .iter()
+            .find(|s| s.file == setting.file && s.key == setting.key)
+            .map(|s| &s.value);
+        if now != Some(&setting.value) {
+            differences.push(Difference::Setting {
+                file: setting.file.clone(),
+                key: setting.key.clone(),
+                current: now.cloned(),
+                wanted: setting.value.clone(),
+            });
+        }
+    }
+
+    for extension in &wanted.extensions {
+        let now = current
+            .extensions
+            .iter()
+            .find(|e| e.editor == extension.editor && e.id.eq_ignore_ascii_case(&extension.id));
+        let matches =
+            now.is_some_and(|now| extension.version.is_none() || now.version == extension.version);
+        if !matches {
+            differences.push(Difference::Extension {
+                editor: extension.editor,
+                id: extension.id.clone(),
+                current: now.and_then(|now| now.version.clone()),
+                wanted: extension.version.clone(),
+            });
+        }
+    }
+
+    for certificate in &wanted.certificates {
+        if !current
+            .certificates
+            .iter()
+            .any(|c| c.fingerprint == certificate.fingerprint)
+        {
+            differences.push(Difference::Certificate {
+                subject: certificate.subject.clone(),
+                fingerprint: certificate.fingerprint.clone(),
+            });
+        }
+    }
+
+    for (name, value) in &wanted.env_vars {
+        let now = current.env_vars.get(name);
+        if now != Some(value) {
+            differences.push(Difference::EnvVar {
+                name: name.clone(),
+                current: now.cloned(),
+                wanted: value.clone(),
+            });
+        }
+    }
+
+    differences
+}
+
+/// `path` with the home directory written as `~`
+fn portable(path: &Path, home: &Path) -> String {
+    match path.strip_prefix(home) {
+        Ok(rest) => format!("~/{}", rest.to_string_lossy().replace('\\', "/")),
+        Err(_) => path.to_string_lossy().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(version: &str, setting: serde_json::Value) -> ToolSetup {
+        ToolSetup {
+            name: "claude-code".to_string(),
+            version: Some(version.to_string()),
+            settings: vec![SettingValue {
+                file: "~/.claude/settings.json".to_string(),
+                key: "model".to_string(),
+                value: setting,
+            }],
+            ..ToolSetup::default()
+        }
+    }
+
+    #[test]
+    fn differences_cover_version_and_settings() {
+        let wanted = setup("2.1.0", "opus".into());
+
+        let same = differences(&wanted, Some(&setup("2.1.0", "opus".into())));
+        assert!(same.is_empty(), "{:?}", same);
+
+        let changed = differences(&wanted, Some(&setup("2.0.0", "sonnet".into())));
+        let described: Vec<String> = changed.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            described,
+            [
+                "install 2.1.0 (now 2.0.0)",
+                "~/.claude/settings.json: set model to \"opus\" (now \"sonnet\")"
+            ]
+        );
+
+        let missing = differences(&wanted, None);
+        assert_eq!(missing[0].to_string(), "install 2.1.0");
+    }
+
+    #[test]
+    fn home_paths_are_portable() {
+        let home = Path::new("/home/dev");
+        let path = home.join(".claude").join("settings.json");
+        assert_eq!(portable(&path, home), "~/.claude/settings.json");
+        assert_eq!(portable(Path::new("/etc/hosts"), home), "/etc/hosts");
+    }
+}
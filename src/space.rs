@@ -0,0 +1,138 @@
+//! Free disk space checks, so an install fails before it starts writing
+//! rather than halfway through a download
+
+use anyhow::Result;
+use indicatif::HumanBytes;
+use std::path::{Path, PathBuf};
+
+use crate::error::Failure;
+use crate::platform;
+
+/// A filesystem and what's about to be written to it
+#[derive(Debug)]
+pub struct Usage {
+    /// The directories sharing the filesystem, in the order they were given
+    pub dirs: Vec<PathBuf>,
+    pub required: u64,
+    pub available: u64,
+}
+
+impl Usage {
+    pub fn is_enough(&self) -> bool {
+        self.available >= self.required
+    }
+
+    /// The directories, joined for a message
+    pub fn describe_dirs(&self) -> String {
+        self.dirs
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" and ")
+    }
+}
+
+/// Tally `needs` (bytes to be written under each directory) per filesystem,
+/// so directories on the same disk are checked against its free space
+/// together. Directories that don't exist yet are measured at their nearest
+/// existing ancestor.
+pub fn usage(needs: &[(&Path, u64)]) -> Result<Vec<Usage>> {
+    let mut usages: Vec<(PathBuf, Usage)> = Vec::new();
+    for &(dir, bytes) in needs {
+        let existing = nearest_existing(dir);
+        if let Some((_, usage)) = usages
+            .iter_mut()
+            .find(|(other, _)| platform::same_filesystem(other, &existing))
+        {
+            usage.dirs.push(dir.to_path_buf());
+            usage.required += bytes;
+            continue;
+        }
+
+        let available = platform::available_space(&existing).map_err(|e| {
+            anyhow::anyhow!(
+                "Could not check free space on {}: {}",
+                existing.display(),
+                e
+            )
+        })?;
+        usages.push((
+            existing,
+            Usage {
+                dirs: vec![dir.to_path_buf()],
+                required: bytes,
+                available,
+            },
+        ));
+    }
+    Ok(usages.into_iter().map(|(_, usage)| usage).collect())
+}
+
+/// Fail with `Failure::DiskSpace` unless every filesystem has room for what
+/// `needs` will write to it
+pub fn check(needs: &[(&Path, u64)]) -> Result<()> {
+    for usage in usage(needs)? {
+        if !usage.is_enough() {
+            return Err(Failure::DiskSpace(format!(
+                "Not enough free space for {}: {} required, {} available. \
+                 Free up space or pass --skip-space-check to try anyway.",
+                usage.describe_dirs(),
+                describe_bytes(usage.required),
+                describe_bytes(usage.available)
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Bytes in readable units, with the exact count
+pub fn describe_bytes(bytes: u64) -> String {
+    format!("{} ({} bytes)", HumanBytes(bytes), bytes)
+}
+
+/// `path` or its closest ancestor that exists, made absolute so paths on
+/// different drives can be told apart
+fn nearest_existing(path: &Path) -> PathBuf {
+    let mut current = path;
+    loop {
+        if let Ok(canonical) = current.canonicalize() {
+            return canonical;
+        }
+        match current.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => current = parent,
+            _ => return PathBuf::from("."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_directories_on_the_same_filesystem() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let missing = temp.path().join("not").join("yet");
+
+        let usages = usage(&[(temp.path(), 10), (&missing, 5)]).unwrap();
+
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].required, 15);
+        assert_eq!(usages[0].dirs.len(), 2);
+        assert!(usages[0].is_enough());
+    }
+
+    #[test]
+    fn fails_when_space_runs_short() {
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let error = check(&[(temp.path(), u64::MAX)]).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<Failure>(),
+            Some(Failure::DiskSpace(_))
+        ));
+        assert!(error.to_string().contains("required"), "{}", error);
+    }
+}
@@ -0,0 +1,512 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::platform::{Editor, PlatformPaths};
+
+/// Prefix of the files in the state directory recording what code-assist
+/// changed for each tool, `<prefix>.<tool>.json`
+const STATE_FILE_PREFIX: &str = ".code-assist-state";
+
+/// The one state file from before state was kept per tool
+const LEGACY_STATE_FILE: &str = ".code-assist-state.json";
+
+/// The only tool before state and config templates were kept per tool, so
+/// the one the legacy state file and the shared config layout belong to
+pub const LEGACY_TOOL: &str = "claude-code";
+
+/// Bumped when the layout changes incompatibly
+const SCHEMA_VERSION: u32 = 1;
+
+/// Everything code-assist changed on this machine, so uninstall can revert
+/// exactly that and `doctor`/`status` can spot drift
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct State {
+    pub schema: u32,
+
+    /// Inferred from the machine rather than recorded at deploy time, for
+    /// installs made before the state file existed
+    #[serde(default)]
+    pub migrated: bool,
+
+    /// Profile the configuration was last deployed from, re-applied by a
+    /// `configure` that doesn't name one
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Files copied in whole, such as certificates
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+
+    /// Settings files we created because they didn't exist yet
+    #[serde(default)]
+    pub created_settings: Vec<PathBuf>,
+
+    /// Keys we inserted or overwrote in JSON settings files
+    #[serde(default)]
+    pub settings: Vec<SettingsKey>,
+
+    /// Entries we appended to keybindings files
+    #[serde(default)]
+    pub keybindings: Vec<Keybinding>,
+
+    /// Template files copied in whole that the user may edit, such as
+    /// snippets, with a checksum of what was written so a later deploy can
+    /// tell whether they were changed since
+    #[serde(default)]
+    pub copied_files: Vec<CopiedFile>,
+
+    /// Template files deployed with `--link` as links back to the template
+    /// rather than copies
+    #[serde(default)]
+    pub linked_files: Vec<LinkedFile>,
+
+    /// MCP servers we added to Claude's config, with the definition written
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+
+    /// Persistent user environment variables we set
+    #[serde(default)]
+    pub env_vars: Vec<EnvVar>,
+
+    /// Directories we added to the user's PATH
+    #[serde(default)]
+    pub path_entries: Vec<String>,
+
+    /// Thumbprints of certificates we imported into the user's trust store
+    /// that weren't there before
+    #[serde(default)]
+    pub trusted_certificates: Vec<String>,
+
+    /// Copies of settings files taken before a deploy changed them, oldest
+    /// first
+    #[serde(default)]
+    pub backups: Vec<Backup>,
+
+    /// Editor extensions we installed that weren't there before
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+}
+
+/// A top-level key written into a JSON settings file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsKey {
+    pub file: PathBuf,
+    pub key: String,
+    pub value: serde_json::Value,
+
+    /// Value before we first wrote the key; `None` if it wasn't set
+    pub previous: Option<serde_json::Value>,
+
+    /// The previous value is unknown because the key was found rather than
+    /// recorded, so it can't be safely reverted
+    #[serde(default)]
+    pub inferred: bool,
+}
+
+/// An entry appended to a keybindings file's array
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keybinding {
+    pub file: PathBuf,
+    pub entry: serde_json::Value,
+}
+
+/// A server written into the `mcpServers` map of Claude's config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServer {
+    pub file: PathBuf,
+    pub name: String,
+    pub definition: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopiedFile {
+    pub path: PathBuf,
+    /// SHA-256 of the contents written, hex encoded
+    pub sha256: String,
+}
+
+/// A link at `path` to the template `target`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedFile {
+    pub path: PathBuf,
+    pub target: PathBuf,
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Symlink,
+    /// Made where symlinks need privileges the user lacks, as on Windows
+    /// without developer mode
+    Hardlink,
+}
+
+impl LinkedFile {
+    /// Whether the link is still in place. A hard link can't be told apart
+    /// from a copy, so one counts while it still reads as the template.
+    pub fn is_intact(&self) -> bool {
+        match self.kind {
+            LinkKind::Symlink => {
+                std::fs::read_link(&self.path).is_ok_and(|target| target == self.target)
+            }
+            LinkKind::Hardlink => {
+                !self.path.is_symlink()
+                    && matches!(
+                        (std::fs::read(&self.path), std::fs::read(&self.target)),
+                        (Ok(a), Ok(b)) if a == b
+                    )
+            }
+        }
+    }
+}
+
+/// Settings files copied aside in one deploy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    /// UTC time the deploy started, which also names its directory
+    pub timestamp: String,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub original: PathBuf,
+    pub copy: PathBuf,
+}
+
+/// An extension installed into one editor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extension {
+    pub editor: Editor,
+    /// Lowercased `publisher.name` identifier
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    /// What it was set to before we set it, restored on uninstall
+    #[serde(default)]
+    pub previous: Option<String>,
+}
+
+impl State {
+    /// Location of `tool`'s state file
+    pub fn path(paths: &PlatformPaths, tool: &str) -> PathBuf {
+        paths
+            .state_dir
+            .join(format!("{}.{}.json", STATE_FILE_PREFIX, tool))
+    }
+
+    /// The state file from before state was kept per tool, if `tool` owns it
+    fn legacy_path(paths: &PlatformPaths, tool: &str) -> Option<PathBuf> {
+        (tool == LEGACY_TOOL).then(|| paths.state_dir.join(LEGACY_STATE_FILE))
+    }
+
+    /// Load `tool`'s state file, or `None` if there isn't one
+    pub fn load(paths: &PlatformPaths, tool: &str) -> Result<Option<Self>> {
+        let path = Some(Self::path(paths, tool))
+            .into_iter()
+            .chain(Self::legacy_path(paths, tool))
+            .find(|path| path.exists());
+        let Some(path) = path else {
+            return Ok(None);
+        };
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let state = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid state file {}", path.display()))?;
+        Ok(Some(state))
+    }
+
+    /// Write `tool`'s state file, replacing a legacy one
+    pub fn save(&mut self, paths: &PlatformPaths, tool: &str) -> Result<()> {
+        self.schema = SCHEMA_VERSION;
+        let path = Self::path(paths, tool);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        if let Some(legacy) = Self::legacy_path(paths, tool) {
+            std::fs::remove_file(legacy).ok();
+        }
+        Ok(())
+    }
+
+    /// Delete `tool`'s state file once everything in it has been reverted
+    pub fn remove(paths: &PlatformPaths, tool: &str) -> Result<()> {
+        for path in Some(Self::path(paths, tool))
+            .into_iter()
+            .chain(Self::legacy_path(paths, tool))
+        {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every state file in `dir`, the legacy one included
+    pub fn files_in(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(STATE_FILE_PREFIX) && name.ends_with(".json")
+            })
+            .map(|entry| entry.path())
+            .collect()
+    }
+
+    /// Point backup copies recorded under `from` at the same files under
+    /// `to`, returning whether any moved
+    pub fn rebase_backups(&mut self, from: &Path, to: &Path) -> bool {
+        let mut moved = false;
+        for file in self.backups.iter_mut().flat_map(|backup| &mut backup.files) {
+            if let Ok(relative) = file.copy.strip_prefix(from) {
+                file.copy = to.join(relative);
+                moved = true;
+            }
+        }
+        moved
+    }
+
+    /// Whether `tool` has a state file
+    pub fn exists(paths: &PlatformPaths, tool: &str) -> bool {
+        Self::path(paths, tool).exists()
+            || Self::legacy_path(paths, tool).is_some_and(|path| path.exists())
+    }
+
+    pub fn record_file(&mut self, path: &Path) {
+        if !self.files.iter().any(|f| f == path) {
+            self.files.push(path.to_path_buf());
+        }
+    }
+
+    pub fn record_created_settings(&mut self, file: &Path) {
+        if !self.created_settings.iter().any(|f| f == file) {
+            self.created_settings.push(file.to_path_buf());
+        }
+    }
+
+    /// Record that `key` in `file` now holds `value`. The first recorded
+    /// previous value is kept, so reverting restores the user's original.
+    pub fn record_key(
+        &mut self,
+        file: &Path,
+        key: &str,
+        value: serde_json::Value,
+        previous: Option<serde_json::Value>,
+    ) {
+        match self
+            .settings
+            .iter_mut()
+            .find(|s| s.file == file && s.key == key)
+        {
+            Some(existing) => existing.value = value,
+            None => self.settings.push(SettingsKey {
+                file: file.to_path_buf(),
+                key: key.to_string(),
+                value,
+                previous,
+                inferred: false,
+            }),
+        }
+    }
+
+    pub fn record_keybinding(&mut self, file: &Path, entry: &serde_json::Value) {
+        if !self
+            .keybindings
+            .iter()
+            .any(|k| k.file == file && &k.entry == entry)
+        {
+            self.keybindings.push(Keybinding {
+                file: file.to_path_buf(),
+                entry: entry.clone(),
+            });
+        }
+    }
+
+    /// Record that `path` was written with contents hashing to `sha256`
+    pub fn record_copied_file(&mut self, path: &Path, sha256: &str) {
+        self.forget_linked_file(path);
+        self.copied_files.retain(|f| f.path != path);
+        self.copied_files.push(CopiedFile {
+            path: path.to_path_buf(),
+            sha256: sha256.to_string(),
+        });
+    }
+
+    /// Checksum of what was last written to `path`, if we copied it
+    pub fn copied_file_sha256(&self, path: &Path) -> Option<&str> {
+        self.copied_files
+            .iter()
+            .find(|f| f.path == path)
+            .map(|f| f.sha256.as_str())
+    }
+
+    /// Record that `path` was made a link of `kind` to `target`
+    pub fn record_linked_file(&mut self, path: &Path, target: &Path, kind: LinkKind) {
+        self.copied_files.retain(|f| f.path != path);
+        self.forget_linked_file(path);
+        self.linked_files.push(LinkedFile {
+            path: path.to_path_buf(),
+            target: target.to_path_buf(),
+            kind,
+        });
+    }
+
+    /// The link an earlier deploy made at `path`, if any
+    pub fn linked_file(&self, path: &Path) -> Option<&LinkedFile> {
+        self.linked_files.iter().find(|f| f.path == path)
+    }
+
+    /// Stop tracking a link at `path`, returning whether one was tracked
+    pub fn forget_linked_file(&mut self, path: &Path) -> bool {
+        let before = self.linked_files.len();
+        self.linked_files.retain(|f| f.path != path);
+        self.linked_files.len() != before
+    }
+
+    /// Record that server `name` in `file` was written as `definition`
+    pub fn record_mcp_server(&mut self, file: &Path, name: &str, definition: &serde_json::Value) {
+        self.mcp_servers
+            .retain(|s| s.file != file || s.name != name);
+        self.mcp_servers.push(McpServer {
+            file: file.to_path_buf(),
+            name: name.to_string(),
+            definition: definition.clone(),
+        });
+    }
+
+    /// Definition last written for server `name` in `file`, if we wrote it
+    pub fn mcp_server(&self, file: &Path, name: &str) -> Option<&serde_json::Value> {
+        self.mcp_servers
+            .iter()
+            .find(|s| s.file == file && s.name == name)
+            .map(|s| &s.definition)
+    }
+
+    pub fn record_env_var(&mut self, name: &str, value: &str, previous: Option<&str>) {
+        self.env_vars.retain(|v| v.name != name);
+        self.env_vars.push(EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+            previous: previous.map(str::to_string),
+        });
+    }
+
+    /// Value `name` had before we first set it, if it had one
+    pub fn env_var_previous(&self, name: &str) -> Option<&str> {
+        self.env_vars
+            .iter()
+            .find(|v| v.name == name)
+            .and_then(|v| v.previous.as_deref())
+    }
+
+    /// Record that `original` was copied to `copy` in the backup taken at
+    /// `timestamp`
+    pub fn record_backup(&mut self, timestamp: &str, original: &Path, copy: &Path) {
+        let file = BackupFile {
+            original: original.to_path_buf(),
+            copy: copy.to_path_buf(),
+        };
+        match self.backups.iter_mut().find(|b| b.timestamp == timestamp) {
+            Some(backup) => backup.files.push(file),
+            None => self.backups.push(Backup {
+                timestamp: timestamp.to_string(),
+                files: vec![file],
+            }),
+        }
+    }
+
+    pub fn record_extension(&mut self, editor: Editor, id: &str) {
+        let extension = Extension {
+            editor,
+            id: id.to_string(),
+        };
+        if !self.extensions.contains(&extension) {
+            self.extensions.push(extension);
+        }
+    }
+
+    pub fn record_path_entry(&mut self, dir: &str) {
+        if !self.path_entries.iter().any(|p| p == dir) {
+            self.path_entries.push(dir.to_string());
+        }
+    }
+
+    pub fn record_trusted_certificate(&mut self, thumbprint: &str) {
+        if !self.trusted_certificates.iter().any(|t| t == thumbprint) {
+            self.trusted_certificates.push(thumbprint.to_string());
+        }
+    }
+
+    /// Stop tracking `dir` as a PATH entry we added
+    pub fn forget_path_entry(&mut self, dir: &str) {
+        self.path_entries.retain(|p| p != dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(dir: &Path) -> PlatformPaths {
+        PlatformPaths {
+            home_dir: dir.to_path_buf(),
+            claude_config_dir: dir.join(".claude"),
+            app_data_dir: dir.join(".config"),
+            certs_dir: dir.join("certs"),
+            vscode_user_dir: None,
+            downloads_dir: dir.join("downloads"),
+            state_dir: dir.join("state"),
+            backups_dir: dir.join("backups"),
+        }
+    }
+
+    #[test]
+    fn each_tool_has_its_own_state() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = paths(dir.path());
+
+        let mut state = State::default();
+        state.record_path_entry("/opt/a");
+        state.save(&paths, "tool-a").unwrap();
+
+        assert!(State::load(&paths, "tool-b").unwrap().is_none());
+        let loaded = State::load(&paths, "tool-a").unwrap().unwrap();
+        assert_eq!(loaded.path_entries, ["/opt/a"]);
+
+        State::remove(&paths, "tool-b").unwrap();
+        assert!(State::exists(&paths, "tool-a"));
+    }
+
+    #[test]
+    fn the_legacy_state_file_belongs_to_the_legacy_tool() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = paths(dir.path());
+        let legacy = paths.state_dir.join(LEGACY_STATE_FILE);
+        std::fs::create_dir_all(&paths.state_dir).unwrap();
+        std::fs::write(&legacy, r#"{ "schema": 1, "path_entries": ["/bin"] }"#).unwrap();
+
+        assert!(State::load(&paths, "other").unwrap().is_none());
+        let mut state = State::load(&paths, LEGACY_TOOL).unwrap().unwrap();
+        assert_eq!(state.path_entries, ["/bin"]);
+
+        // Saving moves it to the tool's own file
+        state.save(&paths, LEGACY_TOOL).unwrap();
+        assert!(!legacy.exists());
+        assert!(State::path(&paths, LEGACY_TOOL).exists());
+    }
+}
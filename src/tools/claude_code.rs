@@ -1,231 +1,2084 @@
 use anyhow::{anyhow, Context, Result};
 use console::style;
-use std::path::PathBuf;
+use indicatif::HumanBytes;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
-use super::Tool;
-use crate::config;
-use crate::download;
-use crate::platform;
+use super::conflicts::{self, FoundBinary, Origin};
+use super::removal::Removals;
+use super::{
+    ChannelChoice, EnvFileStatus, ExtensionStatus, InstallPlan, Tool, ToolOptions, ToolStatus,
+};
+use crate::cache;
+use crate::cancel;
+use crate::config::{self, EditedKeys, WantedExtension};
+use crate::doctor::{self, Diagnostic};
+use crate::download::{self, Channel};
+use crate::error::Failure;
+use crate::output::events::{self, Event};
+use crate::output::{self, reporter};
+use crate::plan;
+use crate::platform::{self, Editor, Host, PlatformPaths};
+use crate::prerequisites::{self, Prerequisite};
+use crate::process::{self, Runner};
+use crate::space;
+use crate::state::{Backup, State};
+
+/// Marker file in the install dir recording which version was installed
+const VERSION_FILE: &str = ".code-assist-version";
+
+/// Marker file in the install dir recording which release channel the
+/// installed version came from. Installs that predate channels have none and
+/// are treated as stable.
+const CHANNEL_FILE: &str = ".code-assist-channel";
+
+/// Marker file in the install dir holding why the installed binary failed to
+/// run, kept for `doctor` until an install that runs replaces it
+const SMOKE_TEST_FILE: &str = ".code-assist-smoke-test";
+
+/// Subdirectory of the install dir holding binaries kept for rollback
+const VERSIONS_DIR: &str = "versions";
+
+/// Rough size of a Claude Code binary, for judging free space before one
+/// is installed to measure
+const TYPICAL_BINARY_SIZE: u64 = 250 * 1024 * 1024;
+
+/// How many previous binaries to keep around for rollback
+const MAX_RETAINED_VERSIONS: usize = 3;
+
+/// Oldest VS Code the Claude Code extension runs in
+const VSCODE_MIN_VERSION: &str = "1.85";
 
 pub struct ClaudeCode {
     local_dir: PathBuf,
+    offline: bool,
+    skip_space_check: bool,
+    force_extensions: bool,
+    force: bool,
+    edited_keys: EditedKeys,
+    link: bool,
+    skip_cert_import: bool,
+    force_path: bool,
+    system: bool,
+    paths: PlatformPaths,
+    editor: Option<Editor>,
+    profile: Option<String>,
+    keep_backups: usize,
+    assume_yes: bool,
+    default_version: Option<String>,
+    dependencies: Vec<String>,
+    default_channel: Option<Channel>,
+    host: Host,
 }
 
 impl ClaudeCode {
-    pub fn new() -> Self {
-        // Get the directory where the executable is located
-        let exe_dir = std::env::current_exe()
-            .ok()
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| std::env::current_dir().unwrap());
-
-        // Look for local directory relative to executable or current directory
-        let local_dir = if exe_dir.join("local").exists() {
-            exe_dir.join("local")
-        } else {
-            std::env::current_dir().unwrap().join("local")
-        };
+    pub fn new(options: &ToolOptions) -> Self {
+        Self {
+            local_dir: options.local_dir.clone(),
+            offline: options.offline,
+            skip_space_check: options.skip_space_check,
+            force_extensions: options.force_extensions,
+            force: options.force,
+            edited_keys: options.edited_keys,
+            link: options.link,
+            skip_cert_import: options.skip_cert_import,
+            force_path: options.force_path,
+            system: options.system,
+            paths: options.paths.clone(),
+            editor: options.editor,
+            profile: options.profile.clone(),
+            keep_backups: options.keep_backups,
+            assume_yes: options.assume_yes,
+            default_version: options.default_version.clone(),
+            dependencies: options.dependencies.clone(),
+            default_channel: options.default_channel,
+            host: options.host.clone(),
+        }
+    }
+
+    fn deploy_options(&self) -> Result<config::DeployOptions> {
+        Ok(config::DeployOptions {
+            keep_backups: self.keep_backups,
+            assume_yes: self.assume_yes,
+            force: self.force,
+            edited_keys: self.edited_keys,
+            editor: self.editor,
+            install_dir: self.get_install_dir(),
+            tool: self.name().to_string(),
+            profile: self.profile()?,
+            link: self.link,
+            skip_cert_import: self.skip_cert_import,
+            files: Vec::new(),
+            env: Vec::new(),
+            host: self.host.clone(),
+        })
+    }
+
+    /// Profile to deploy: the one asked for, else the one last deployed,
+    /// else the default
+    fn profile(&self) -> Result<String> {
+        if let Some(profile) = &self.profile {
+            return Ok(profile.clone());
+        }
+        Ok(self
+            .deployed_profile()?
+            .unwrap_or_else(|| config::DEFAULT_PROFILE.to_string()))
+    }
+
+    /// Directory holding the profile's platform configs and extensions
+    fn profile_dir(&self) -> Result<PathBuf> {
+        config::profile_dir(&self.local_dir, &self.profile()?)
+    }
+
+    /// The profile's directory for reports, which fall back to the flat
+    /// layout if the profile can't be found
+    fn config_source(&self) -> PathBuf {
+        self.profile_dir().unwrap_or_else(|e| {
+            tracing::debug!("could not resolve profile: {:#}", e);
+            self.local_dir.clone()
+        })
+    }
+
+    fn extension_options(&self) -> config::ExtensionOptions {
+        config::ExtensionOptions {
+            editor: self.editor,
+            offline: self.offline,
+            force: self.force_extensions,
+            host: self.host.clone(),
+        }
+    }
 
-        Self { local_dir }
+    /// Whether `dir` is on the PATH new commands run with
+    fn is_on_process_path(&self, dir: &Path) -> bool {
+        doctor::is_in(&self.host.environment.process_path(), dir)
     }
 
     fn get_install_dir(&self) -> PathBuf {
-        let paths = platform::get_paths();
-        paths.home_dir.join(".claude").join("bin")
+        if self.system {
+            return platform::system_install_dir();
+        }
+        self.paths.home_dir.join(".claude").join("bin")
     }
 
     fn get_binary_path(&self) -> PathBuf {
         self.get_install_dir().join(platform::get_binary_name())
     }
-}
 
-impl Tool for ClaudeCode {
-    fn name(&self) -> &str {
-        "claude-code"
+    fn get_version_file(&self) -> PathBuf {
+        self.get_install_dir().join(VERSION_FILE)
     }
 
-    fn display_name(&self) -> &str {
-        "Claude Code"
+    fn get_channel_file(&self) -> PathBuf {
+        self.get_install_dir().join(CHANNEL_FILE)
     }
 
-    fn is_installed(&self) -> Result<bool> {
+    fn get_smoke_test_file(&self) -> PathBuf {
+        self.get_install_dir().join(SMOKE_TEST_FILE)
+    }
+
+    fn get_versions_dir(&self) -> PathBuf {
+        self.get_install_dir().join(VERSIONS_DIR)
+    }
+
+    fn get_retained_binary_path(&self, version: &str) -> PathBuf {
+        self.get_versions_dir()
+            .join(version)
+            .join(platform::get_binary_name())
+    }
+
+    /// Versions with a retained binary, oldest first
+    fn retained_versions(&self) -> Result<Vec<String>> {
+        let versions_dir = self.get_versions_dir();
+        if !versions_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut versions = Vec::new();
+        for entry in std::fs::read_dir(&versions_dir)? {
+            let entry = entry?;
+            let version = entry.file_name().to_string_lossy().to_string();
+            if self.get_retained_binary_path(&version).exists() {
+                versions.push(version);
+            }
+        }
+
+        versions.sort_by(|a, b| download::compare_versions(a, b));
+        Ok(versions)
+    }
+
+    /// Take the install directory back off PATH, whether or not the state
+    /// file recorded adding it; installs from before the state file didn't
+    fn remove_from_path(&self, dry_run: bool) -> Result<()> {
+        let paths = &self.paths;
+        let install_dir = self.get_install_dir();
+        let install_dir = platform::path_str(&install_dir)?;
+
+        match self.host.environment.remove_from_path(install_dir, dry_run) {
+            Ok(()) if !dry_run => reporter().success("Removed from PATH"),
+            Ok(()) => {}
+            Err(e) => {
+                reporter().warn(format!("Could not remove from PATH: {}", e));
+                return Ok(());
+            }
+        }
+
+        if let Some(mut state) = State::load(paths, self.name())? {
+            if !dry_run && state.path_entries.iter().any(|p| p == install_dir) {
+                state.forget_path_entry(install_dir);
+                state.save(paths, self.name())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove everything else code-assist put on the machine for Claude Code:
+    /// retained versions, cached downloads, deployed configuration, and any
+    /// environment variables it set
+    fn purge(&self, removals: &mut Removals, dry_run: bool) -> Result<()> {
+        reporter().blank();
+        reporter().heading("Purging configuration...");
+        reporter().blank();
+
+        let leftovers = [
+            (self.get_versions_dir(), "retained versions"),
+            (cache::dir()?, "cached downloads"),
+        ];
+        for (dir, label) in leftovers {
+            if !dir.exists() {
+                continue;
+            }
+            if dry_run {
+                plan::step(format!("Remove {} {}", label, dir.display()));
+                continue;
+            }
+            if removals.remove_dir_all(&dir) {
+                reporter().success(format!("Removed {}", label));
+                reporter().detail(dir.display());
+            }
+        }
+        if !dry_run {
+            removals.remove_file(&self.get_version_file());
+        }
+
+        let paths = &self.paths;
+        let left_in_place = config::purge_configs(
+            &self.config_source(),
+            paths,
+            self.name(),
+            &self.host,
+            dry_run,
+        )
+        .context(Failure::Configuration(
+            "Failed to remove configuration".to_string(),
+        ))?;
+
+        if !left_in_place.is_empty() {
+            reporter().blank();
+            reporter().warn("Left in place (not set by code-assist, or changed since):");
+            for item in left_in_place {
+                reporter().info(format!("  - {}", item));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copy the currently installed binary into the versions directory so it
+    /// can be restored later, then prune the oldest retained versions
+    fn retain_current_binary(&self, incoming_version: &str, dry_run: bool) -> Result<()> {
         let binary_path = self.get_binary_path();
-        Ok(binary_path.exists())
+        let current_version = match self.installed_version()? {
+            Some(v) if binary_path.exists() && v != incoming_version => v,
+            _ => return Ok(()),
+        };
+
+        let retained = self.get_retained_binary_path(&current_version);
+        if dry_run {
+            plan::step(format!(
+                "Keep version {} for rollback at {}",
+                current_version,
+                retained.display()
+            ));
+            return Ok(());
+        }
+
+        if let Some(parent) = retained.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create versions directory")?;
+        }
+        std::fs::copy(&binary_path, &retained).context("Failed to retain previous binary")?;
+
+        reporter().success(format!("Kept version {} for rollback", style(&current_version).cyan()));
+
+        let versions = self.retained_versions()?;
+        if versions.len() > MAX_RETAINED_VERSIONS {
+            for old in &versions[..versions.len() - MAX_RETAINED_VERSIONS] {
+                std::fs::remove_dir_all(self.get_versions_dir().join(old)).ok();
+            }
+        }
+
+        Ok(())
     }
 
-    fn install(&self) -> Result<()> {
-        println!(
-            "{} Installing Claude Code...\n",
-            style("→").cyan().bold()
-        );
+    /// The channel to follow: the installed one unless `--channel` asks for
+    /// another, which takes `--switch-channel` too. New installs use the
+    /// requested channel, then the configured one, then stable.
+    fn resolve_channel(&self, choice: ChannelChoice) -> Result<Channel> {
+        let installed = if self.is_installed()? {
+            self.installed_channel()?
+        } else {
+            None
+        };
+        match (installed, choice.requested) {
+            (Some(current), Some(requested)) if current != requested && !choice.allow_switch => {
+                Err(anyhow!(
+                    "Claude Code follows the {} channel; pass --switch-channel to move it to {}",
+                    current,
+                    requested
+                ))
+            }
+            (_, Some(requested)) => Ok(requested),
+            (Some(current), None) => Ok(current),
+            (None, None) => Ok(self.default_channel.unwrap_or_default()),
+        }
+    }
 
-        // Step 1: Get version
-        println!("  Fetching latest version...");
-        let (version, source) = download::get_latest_version(&self.local_dir)?;
-        println!(
-            "  {} Version: {} ({})",
-            style("✓").green().bold(),
+    /// Look up the latest version on `channel`, reporting where it came from
+    fn latest_version(&self, channel: Channel) -> Result<String> {
+        if channel == Channel::Stable {
+            reporter().info("Fetching latest version...");
+        } else {
+            reporter().info(format!("Fetching latest {} version...", channel));
+        }
+        let (version, source) =
+            download::get_latest_version(&self.local_dir, channel, self.offline)?;
+        reporter().success(format!(
+            "Version: {} ({})",
             style(&version).cyan(),
             match source {
                 download::DownloadSource::Remote => "remote",
                 download::DownloadSource::LocalFallback => "local fallback",
+                download::DownloadSource::Cache => "cache",
             }
+        ));
+        Ok(version)
+    }
+
+    /// Make sure the download, and the binary installed from it, will fit
+    /// before anything is written. An archive counts twice in the cache, for
+    /// the binary extracted next to it, and its size stands in for the
+    /// binary's.
+    fn check_space(
+        &self,
+        version: &str,
+        platform_id: &str,
+        entry: &download::PlatformEntry,
+        binary_name: &str,
+        cached_binary: &Path,
+        dry_run: bool,
+    ) -> Result<()> {
+        let Some(size) = download::artifact_size(
+            version,
+            platform_id,
+            binary_name,
+            entry,
+            &self.local_dir,
+            self.offline,
+        ) else {
+            reporter().detail("Download size unknown, skipping the free space check");
+            return Ok(());
+        };
+
+        let download_dir = cache::dir()?;
+        let install_dir = self.get_install_dir();
+        let mut needs = vec![(install_dir.as_path(), size)];
+        if !cached_binary.exists() {
+            let copies = if entry.archive.is_some() { 2 } else { 1 };
+            needs.insert(0, (download_dir.as_path(), size * copies));
+        }
+
+        if !dry_run {
+            return space::check(&needs);
+        }
+        for usage in space::usage(&needs)? {
+            plan::step(format!(
+                "Check for {} free in {} ({} available)",
+                HumanBytes(usage.required),
+                usage.describe_dirs(),
+                HumanBytes(usage.available)
+            ));
+            if !usage.is_enough() {
+                reporter().warn(format!(
+                    "Not enough free space in {}",
+                    usage.describe_dirs()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check no other copy of `claude` on PATH, from npm or Homebrew, runs
+    /// instead of ours
+    fn diagnose_conflicts(&self) -> Option<Diagnostic> {
+        let name = "Other claude installs";
+        let found = conflicts::find_on_path(
+            platform::get_binary_name(),
+            &self.get_install_dir(),
+            &self.host,
         );
+        let others: Vec<String> = found
+            .iter()
+            .filter(|binary| binary.origin != Origin::Ours)
+            .map(|binary| binary.to_string())
+            .collect();
+        if others.is_empty() {
+            return Some(Diagnostic::pass(name, "none"));
+        }
+        let winner = found.first()?;
+        if winner.origin == Origin::Ours {
+            return Some(Diagnostic::pass(
+                name,
+                format!("ours runs first; also found {}", others.join(", ")),
+            ));
+        }
 
-        // Step 2: Get manifest
-        println!("\n  Fetching manifest...");
-        let (manifest, _) = download::get_manifest(&version, &self.local_dir)?;
+        let advice = match winner.origin.uninstall_command() {
+            Some((program, args)) => format!("run '{} {}' to remove it", program, args.join(" ")),
+            None => format!(
+                "remove it, or move {} to the front of your PATH",
+                self.get_install_dir().display()
+            ),
+        };
+        Some(Diagnostic::fail(
+            name,
+            format!("{} runs instead of ours; {}", winner, advice),
+        ))
+    }
+
+    /// Whether there's room to download and install an update about the size
+    /// of the installed binary
+    fn diagnose_space(&self) -> Diagnostic {
+        let name = "Free disk space";
+        let size = std::fs::metadata(self.get_binary_path())
+            .map(|metadata| metadata.len())
+            .unwrap_or(TYPICAL_BINARY_SIZE);
+        let download_dir = match cache::dir() {
+            Ok(dir) => dir,
+            Err(e) => return Diagnostic::fail(name, format!("{:#}", e)),
+        };
+        let install_dir = self.get_install_dir();
+        let needs = [
+            (download_dir.as_path(), size),
+            (install_dir.as_path(), size),
+        ];
+
+        let usages = match space::usage(&needs) {
+            Ok(usages) => usages,
+            Err(e) => return Diagnostic::fail(name, format!("{:#}", e)),
+        };
+        if let Some(short) = usages.iter().find(|usage| !usage.is_enough()) {
+            return Diagnostic::fail(
+                name,
+                format!(
+                    "Only {} free in {}, and an update needs about {}; free up space",
+                    HumanBytes(short.available),
+                    short.describe_dirs(),
+                    HumanBytes(short.required)
+                ),
+            );
+        }
+        let detail = usages
+            .iter()
+            .map(|usage| {
+                format!(
+                    "{} free in {}",
+                    HumanBytes(usage.available),
+                    usage.describe_dirs()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Diagnostic::pass(name, detail)
+    }
+
+    /// Check the installed binary is built for this machine, which an x64
+    /// code-assist running under Rosetta once didn't ensure; `None` when the
+    /// binary's architecture can't be read
+    fn diagnose_arch(&self) -> Option<Diagnostic> {
+        let name = "Binary architecture";
+        let binary = platform::arch::of_binary(&self.get_binary_path())?;
+        let arch_of = |id: &str| id.rsplit('-').next().unwrap_or(id).to_string();
+        let native = arch_of(platform::get_native_platform_id());
+        let detail = format!("{}; code-assist is {}", binary, platform::process_arch());
+
+        if binary == native || (binary.starts_with("universal") && binary.contains(&native)) {
+            return Some(Diagnostic::pass(name, detail));
+        }
+        if platform::get_emulated_platform_id().map(arch_of).as_deref() == Some(binary.as_str()) {
+            return Some(Diagnostic::pass(
+                name,
+                format!("{}, running under emulation on {}", detail, native),
+            ));
+        }
+        Some(Diagnostic::fail(
+            name,
+            format!(
+                "{} is built for {}, which this {} machine can't run; run \
+                 'code-assist install --tool claude-code' to install the {} build",
+                self.get_binary_path().display(),
+                binary,
+                native,
+                native
+            ),
+        ))
+    }
+
+    /// When files couldn't be removed because something has them open, say
+    /// what to close, and offer to stop a running claude and try again
+    fn free_locked_files(&self, removals: &mut Removals) -> Result<()> {
+        let image = platform::get_binary_name();
+        let running = platform::is_process_running(image);
+        if !running && !removals.failed.iter().any(|failed| failed.in_use()) {
+            return Ok(());
+        }
+
+        reporter().blank();
+        reporter().warn("Some files are in use. Close VS Code and any terminals running claude.");
+        if !running {
+            return Ok(());
+        }
+
+        let interactive =
+            !self.assume_yes && output::is_interactive() && std::io::stdout().is_terminal();
+        if !interactive {
+            reporter().info(format!(
+                "claude is still running; stop it (e.g. 'taskkill /IM {}') and run uninstall again",
+                image
+            ));
+            return Ok(());
+        }
+        if !output::confirm("This will stop every running claude process and try again.")? {
+            return Ok(());
+        }
+        match platform::stop_processes(image) {
+            Ok(()) => {
+                reporter().success("Stopped claude");
+                removals.retry();
+            }
+            Err(e) => reporter().warn(format!("Could not stop claude: {:#}", e)),
+        }
+        Ok(())
+    }
+
+    /// Remove a `--system` install and its machine PATH entry, leaving each
+    /// user's own install and configuration alone
+    fn uninstall_system(&self, dry_run: bool) -> Result<()> {
+        let install_dir = self.get_install_dir();
+        if !install_dir.exists() {
+            reporter().skip("Claude Code is not installed for every user");
+        } else if dry_run {
+            plan::step(format!("Remove {}", install_dir.display()));
+        } else {
+            std::fs::remove_dir_all(&install_dir)
+                .with_context(|| format!("Failed to remove {}", install_dir.display()))?;
+            reporter().success(format!("Removed {}", install_dir.display()));
+        }
 
-        let platform_id = platform::get_platform_id();
-        let binary_name = platform::get_binary_name();
+        let removed = platform::path_str(&install_dir)
+            .and_then(|dir| platform::remove_from_system_path(dir, dry_run));
+        if let Err(e) = removed {
+            reporter().warn(format!("Could not remove from the system PATH: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Platform to install `version` for: the machine's own, even when this
+    /// process runs under Rosetta, or, when the manifest has no native
+    /// build, one that runs under emulation if the user accepts the slowdown
+    fn choose_platform(
+        &self,
+        manifest: &download::Manifest,
+        version: &str,
+        dry_run: bool,
+    ) -> Result<&'static str> {
+        let native = platform::get_native_platform_id();
+        let missing = match manifest.platform(version, native) {
+            Ok(_) => {
+                if native != platform::get_platform_id() {
+                    reporter().info(format!(
+                        "code-assist is running under Rosetta; installing the native {} build",
+                        native
+                    ));
+                }
+                return Ok(native);
+            }
+            Err(e) => e,
+        };
+        let Some(emulated) = platform::get_emulated_platform_id()
+            .filter(|emulated| manifest.platform(version, emulated).is_ok())
+        else {
+            return Err(missing);
+        };
 
-        let checksum = manifest["platforms"][platform_id]["checksum"]
-            .as_str()
-            .ok_or_else(|| anyhow!("Platform {} not found in manifest", platform_id))?;
+        reporter().warn(format!(
+            "There's no {} build of {}; the {} build runs under emulation, more slowly",
+            native, version, emulated
+        ));
+        if !dry_run
+            && !self.assume_yes
+            && !output::confirm(&format!("This will install the {} build.", emulated))?
+        {
+            return Err(Failure::Aborted.into());
+        }
+        Ok(emulated)
+    }
 
-        println!(
-            "  {} Platform: {}",
-            style("✓").green().bold(),
-            style(platform_id).cyan()
+    /// Put a `--system` install on the machine PATH, and say what's left to
+    /// each user, as settings, extensions, and certificates are per user
+    fn finish_system_install(&self, dry_run: bool) {
+        events::step("path");
+        reporter().blank();
+        reporter().skip(
+            "Skipping VS Code extensions and settings, Claude configuration, and certificates: \
+             they belong to each user, who can run 'code-assist configure --tool claude-code'",
         );
 
+        let install_dir = self.get_install_dir();
+        let added = platform::path_str(&install_dir)
+            .and_then(|dir| platform::add_to_system_path(dir, dry_run));
+        match added {
+            Ok(()) if !dry_run => reporter().success(format!(
+                "Added to the system PATH: {}",
+                install_dir.display()
+            )),
+            Ok(()) => {}
+            Err(e) => reporter().warn(format!("Could not add to the system PATH: {}", e)),
+        }
+    }
+
+    /// Run the installed binary's `--version` and check it reports `version`,
+    /// which catches a binary Gatekeeper blocks or an antivirus product
+    /// mangled. A failure is left in the install dir for `doctor`.
+    fn smoke_test(&self, version: &str) -> Result<()> {
+        let binary_path = self.get_binary_path();
+        if let Err(e) = platform::clear_quarantine(&binary_path) {
+            reporter().warn(format!("{:#}", e));
+        }
+
+        let marker = self.get_smoke_test_file();
+        match run_version(&*self.host.runner, &binary_path, version) {
+            Ok(()) => {
+                if marker.exists() {
+                    std::fs::remove_file(&marker)
+                        .with_context(|| format!("Failed to remove {}", marker.display()))?;
+                }
+                reporter().success(format!(
+                    "'{} --version' reports {}",
+                    binary_path.display(),
+                    version
+                ));
+                Ok(())
+            }
+            Err(e) => {
+                // A check cut short by Ctrl+C says nothing about the binary
+                cancel::check()?;
+                let problem = format!("{:#}", e);
+                if let Err(write_error) = std::fs::write(&marker, &problem) {
+                    tracing::debug!("could not record the failed smoke test: {}", write_error);
+                }
+                Err(anyhow!(
+                    "{} failed its check after install: {}. If security software quarantined \
+                     it, allow it and install again",
+                    binary_path.display(),
+                    problem
+                ))
+            }
+        }
+    }
+
+    /// Whether `version` from `channel` is installed with a binary that
+    /// still matches the manifest and passed its check after install, so
+    /// installing it again would change nothing
+    fn is_current(&self, version: &str, channel: Channel) -> Result<bool> {
+        if self.installed_version()?.as_deref() != Some(version)
+            || self.installed_channel().ok().flatten() != Some(channel)
+            || self.get_smoke_test_file().exists()
+        {
+            return Ok(false);
+        }
+        let binary_path = self.get_binary_path();
+        if !binary_path.exists() {
+            return Ok(false);
+        }
+
+        let (manifest, _) = download::get_manifest(version, &self.local_dir, self.offline)?;
+        let actual = download::file_checksum(&binary_path)?;
+        let platforms = std::iter::once(platform::get_native_platform_id())
+            .chain(platform::get_emulated_platform_id());
+        for platform_id in platforms {
+            let Ok(entry) = manifest.platform(version, platform_id) else {
+                continue;
+            };
+            let expected = match entry.archive {
+                None => entry.checksum.clone(),
+                // The manifest has the archive's checksum, so compare with
+                // the binary extracted from it
+                Some(_) => {
+                    let cached = cache::binary_path(version, platform_id, &entry.checksum)?;
+                    if !cached.exists() {
+                        continue;
+                    }
+                    download::file_checksum(&cached)?
+                }
+            };
+            if actual == expected {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Download, verify, and set up `version`, recording it as installed
+    /// from `channel`
+    fn install_version(&self, version: &str, channel: Channel, dry_run: bool) -> Result<()> {
+        // An unknown profile should stop the install before the download
+        let profile_dir = self.profile_dir()?;
+
+        // Step 2: Get manifest
+        events::emit(Event::VersionChosen {
+            version: version.to_string(),
+            channel,
+        });
+        events::step("manifest");
+        reporter().blank();
+        reporter().info("Fetching manifest...");
+        let (manifest, _) = download::get_manifest(version, &self.local_dir, self.offline)?;
+
+        let platform_id = self.choose_platform(&manifest, version, dry_run)?;
+        let entry = manifest.platform(version, platform_id)?;
+        let binary_name = entry.binary_name(platform::get_binary_name());
+        let checksum = entry.checksum.as_str();
+
+        reporter().success(format!("Platform: {}", style(platform_id).cyan()));
+
         // Step 3: Download binary
-        println!("\n  Downloading binary...");
-        let download_dir = platform::get_paths().home_dir.join(".claude").join("downloads");
-        std::fs::create_dir_all(&download_dir)?;
+        events::step("download");
+        reporter().blank();
+        reporter().info("Downloading binary...");
+        let download_dir = cache::dir()?;
+        let cached_binary = cache::binary_path(version, platform_id, checksum)?;
+        let install_dir = self.get_install_dir();
+
+        if !self.skip_space_check {
+            self.check_space(
+                version,
+                platform_id,
+                entry,
+                binary_name,
+                &cached_binary,
+                dry_run,
+            )?;
+        }
+
+        if dry_run {
+            let file_name = match entry.archive {
+                Some(kind) => kind.file_name(binary_name),
+                None => binary_name.to_string(),
+            };
+            let local_binary =
+                download::local_binary_path(&self.local_dir, version, platform_id, &file_name);
+            if cached_binary.exists() {
+                plan::step(format!("Use cached {}", cached_binary.display()));
+            } else if self.offline {
+                plan::step(format!(
+                    "Copy {} to {}",
+                    local_binary.display(),
+                    cached_binary.display()
+                ));
+            } else {
+                plan::step(format!(
+                    "Download {} to {} (falling back to {})",
+                    entry.download_url.clone().unwrap_or_else(|| {
+                        download::binary_url(version, platform_id, &file_name)
+                    }),
+                    cached_binary.display(),
+                    local_binary.display()
+                ));
+            }
+            plan::step(format!("Verify SHA-256 checksum {}", checksum));
+            if entry.archive.is_some() {
+                plan::step(format!("Extract {} from {}", binary_name, file_name));
+            }
+            self.retain_current_binary(version, true)?;
+            if self.system {
+                plan::step(format!(
+                    "Copy {} to {}",
+                    cached_binary.display(),
+                    self.get_binary_path().display()
+                ));
+            } else {
+                plan::step(format!("Run '{} install'", cached_binary.display()));
+            }
+            plan::step(format!(
+                "Run '{} --version' and check it reports {}",
+                self.get_binary_path().display(),
+                version
+            ));
+            plan::step(format!(
+                "Record version {} in {}",
+                version,
+                self.get_version_file().display()
+            ));
+            plan::step(format!(
+                "Record channel {} in {}",
+                channel,
+                self.get_channel_file().display()
+            ));
+
+            if self.system {
+                self.finish_system_install(true);
+                return Ok(());
+            }
 
-        let temp_binary = download_dir.join(format!("claude-{}-{}", version, platform_id));
+            events::step("extensions");
+            reporter().blank();
+            reporter().heading("Installing VS Code extensions...");
+            reporter().blank();
+            config::install_extensions(
+                &profile_dir,
+                &self.paths,
+                self.name(),
+                &self.extension_options(),
+                true,
+            )?;
 
-        let _source = download::download_binary(
-            &version,
+            events::step("configuration");
+            reporter().blank();
+            reporter().heading("Deploying configurations...");
+            reporter().blank();
+            config::deploy_configs(&profile_dir, &self.paths, &self.deploy_options()?, true)?;
+
+            let added = platform::path_str(&install_dir).and_then(|dir| {
+                self.host
+                    .environment
+                    .add_to_path(dir, self.force_path, true)
+            });
+            if let Err(e) = added {
+                reporter().warn(format!("Could not add to PATH: {}", e));
+            }
+            self.report_conflicts(true)?;
+
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&download_dir)?;
+
+        download::download_binary(
+            version,
             platform_id,
             binary_name,
+            entry,
             &self.local_dir,
-            &temp_binary,
-            checksum,
+            &cached_binary,
+            self.offline,
         )?;
 
         // Step 4: Make executable (Unix only)
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            let mut perms = std::fs::metadata(&temp_binary)?.permissions();
+            let mut perms = std::fs::metadata(&cached_binary)?.permissions();
             perms.set_mode(0o755);
-            std::fs::set_permissions(&temp_binary, perms)?;
+            std::fs::set_permissions(&cached_binary, perms)?;
+        }
+
+        cancel::check()?;
+
+        // Gatekeeper won't run a quarantined binary that isn't notarized
+        if let Err(e) = platform::clear_quarantine(&cached_binary) {
+            reporter().warn(format!("{:#}", e));
         }
 
         // Step 5: Run claude install
-        println!(
-            "\n{} Running Claude Code setup...\n",
-            style("→").cyan().bold()
-        );
+        events::step("setup");
+        self.retain_current_binary(version, false)?;
 
-        let output = std::process::Command::new(&temp_binary)
-            .arg("install")
-            .output()
-            .context("Failed to run claude install")?;
+        if self.system {
+            // `claude install` sets up the current user, so place the binary
+            // ourselves
+            std::fs::create_dir_all(&install_dir)
+                .with_context(|| format!("Failed to create {}", install_dir.display()))?;
+            std::fs::copy(&cached_binary, self.get_binary_path())
+                .context("Failed to copy the binary into place")?;
+            reporter().success(format!(
+                "Installed to {}",
+                style(install_dir.display()).cyan()
+            ));
+        } else {
+            reporter().blank();
+            reporter().heading("Running Claude Code setup...");
+            reporter().blank();
+
+            let output = self
+                .host
+                .runner
+                .output(std::process::Command::new(&cached_binary).arg("install"))
+                .context("Failed to run claude install")?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Claude install failed: {}", stderr));
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!("Claude install failed: {}", stderr));
+            }
         }
 
-        // Clean up temp binary
-        std::fs::remove_file(&temp_binary).ok();
+        events::step("smoke test");
+        self.smoke_test(version)?;
 
-        // Step 6: Install VSIX extensions
-        println!(
-            "\n{} Installing VS Code extensions...\n",
-            style("→").cyan().bold()
-        );
-        let vsix_dir = self.local_dir.join("VSIX");
-        config::install_vsix_extensions(&vsix_dir)?;
+        // Record the installed version so `list` can report it
+        std::fs::create_dir_all(&install_dir)?;
+        std::fs::write(self.get_version_file(), version)
+            .context("Failed to record installed version")?;
+        std::fs::write(self.get_channel_file(), channel.name())
+            .context("Failed to record installed channel")?;
+        cancel::committed(format!(
+            "Installed Claude Code {} to {}",
+            version,
+            install_dir.display()
+        ));
+
+        if self.system {
+            self.finish_system_install(false);
+            return Ok(());
+        }
+
+        // Step 6: Install VS Code extensions
+        cancel::check()?;
+        events::step("extensions");
+        reporter().blank();
+        reporter().heading("Installing VS Code extensions...");
+        reporter().blank();
+        config::install_extensions(
+            &profile_dir,
+            &self.paths,
+            self.name(),
+            &self.extension_options(),
+            false,
+        )?;
 
         // Step 7: Deploy configurations
-        println!(
-            "\n{} Deploying configurations...\n",
-            style("→").cyan().bold()
-        );
-        let paths = platform::get_paths();
-        config::deploy_configs(&self.local_dir, &paths)?;
+        events::step("configuration");
+        reporter().blank();
+        reporter().heading("Deploying configurations...");
+        reporter().blank();
+        let paths = &self.paths;
+        config::deploy_configs(&profile_dir, paths, &self.deploy_options()?, false)?;
 
         // Step 8: Add to PATH
+        cancel::check()?;
+        events::step("path");
+        let added = platform::path_str(&install_dir).and_then(|dir| {
+            self.host
+                .environment
+                .add_to_path(dir, self.force_path, false)
+                .map(|()| dir)
+        });
+        match added {
+            Err(e) => reporter().warn(format!("Could not add to PATH: {}", e)),
+            Ok(entry) => {
+                reporter().success(format!("Added to PATH: {}", install_dir.display()));
+                cancel::committed(format!("Added {} to PATH", install_dir.display()));
+                let mut state = config::load_state(&profile_dir, paths, self.name())?;
+                state.record_path_entry(entry);
+                state.save(paths, self.name())?;
+
+                // The shell that ran us keeps its old PATH until it's refreshed
+                if !self.is_on_process_path(&install_dir) {
+                    reporter()
+                        .warn("'claude' won't be found in this terminal until PATH is refreshed:");
+                    for step in platform::path_refresh_steps() {
+                        reporter().line(format!("    {}", step));
+                    }
+                    self.host.environment.add_to_process_path(&install_dir);
+                }
+            }
+        }
+        self.report_conflicts(false)?;
+
+        Ok(())
+    }
+
+    /// Warn about other copies of `claude` on PATH, such as one from
+    /// `npm install -g` or Homebrew, and offer to remove them; then make sure
+    /// ours is the one that runs
+    fn report_conflicts(&self, dry_run: bool) -> Result<()> {
         let install_dir = self.get_install_dir();
-        if let Err(e) = platform::add_to_path(install_dir.to_str().unwrap()) {
-            println!(
-                "  {} Could not add to PATH: {}",
-                style("!").yellow().bold(),
-                e
-            );
-        } else {
-            println!(
-                "  {} Added to PATH: {}",
-                style("✓").green().bold(),
-                install_dir.display()
-            );
+        let found = conflicts::find_on_path(platform::get_binary_name(), &install_dir, &self.host);
+        let others: Vec<&FoundBinary> = found
+            .iter()
+            .filter(|binary| binary.origin != Origin::Ours)
+            .collect();
+        if others.is_empty() {
+            return Ok(());
+        }
+
+        reporter().blank();
+        reporter().warn("Found other copies of claude on PATH:");
+        for other in &others {
+            reporter().detail(other.to_string());
+        }
+
+        let interactive = !dry_run
+            && !self.assume_yes
+            && output::is_interactive()
+            && std::io::stdout().is_terminal();
+        for other in &others {
+            let Some((program, args)) = other.origin.uninstall_command() else {
+                continue;
+            };
+            let command_line = format!("{} {}", program, args.join(" "));
+            if dry_run {
+                plan::step(format!("Offer to run '{}'", command_line));
+                continue;
+            }
+            if !interactive {
+                reporter().info(format!(
+                    "To remove {}, run '{}'",
+                    other.path.display(),
+                    command_line
+                ));
+                continue;
+            }
+            if !output::confirm(&format!(
+                "This will remove {} by running '{}'.",
+                other.path.display(),
+                command_line
+            ))? {
+                continue;
+            }
+            match self
+                .host
+                .runner
+                .output(std::process::Command::new(program).args(&args))
+            {
+                Ok(result) if result.status.success() => {
+                    reporter().success(format!("Removed the {}", other.origin))
+                }
+                Ok(result) => reporter().warn(format!(
+                    "'{}' failed: {}",
+                    command_line,
+                    String::from_utf8_lossy(&result.stderr).trim()
+                )),
+                Err(e) => reporter().warn(format!("Could not run '{}': {}", command_line, e)),
+            }
         }
 
+        // Whatever is left, ours only helps if it comes first
+        let found = conflicts::find_on_path(platform::get_binary_name(), &install_dir, &self.host);
+        let Some(winner) = found.first().filter(|first| first.origin != Origin::Ours) else {
+            return Ok(());
+        };
+        reporter().warn(format!(
+            "'claude' runs {}, not the copy just installed",
+            winner
+        ));
+        let dir = platform::path_str(&install_dir)?;
+        if cfg!(windows)
+            && !dry_run
+            && (!interactive
+                || !output::confirm(&format!(
+                    "This will move {} to the front of your PATH.",
+                    install_dir.display()
+                ))?)
+        {
+            reporter().info(format!(
+                "Remove the other copy, or move {} to the front of your PATH",
+                install_dir.display()
+            ));
+            return Ok(());
+        }
+        match platform::move_to_front_of_path(dir, dry_run) {
+            Ok(()) if !dry_run => reporter().success(format!(
+                "Moved {} to the front of PATH",
+                install_dir.display()
+            )),
+            Ok(()) => {}
+            Err(e) => reporter().info(format!("To use ours, {}", e)),
+        }
         Ok(())
     }
+}
 
-    fn uninstall(&self) -> Result<()> {
-        println!(
-            "{} Uninstalling Claude Code...\n",
-            style("→").cyan().bold()
+impl Tool for ClaudeCode {
+    fn name(&self) -> &str {
+        "claude-code"
+    }
+
+    fn display_name(&self) -> &str {
+        "Claude Code"
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        vec!["claude"]
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        self.dependencies.iter().map(String::as_str).collect()
+    }
+
+    fn prerequisites(&self) -> Vec<Prerequisite> {
+        vec![
+            Prerequisite::VsCode {
+                min_version: Some(VSCODE_MIN_VERSION.to_string()),
+            },
+            Prerequisite::Git,
+        ]
+    }
+
+    fn is_installed(&self) -> Result<bool> {
+        let binary_path = self.get_binary_path();
+        Ok(binary_path.exists())
+    }
+
+    fn installed_version(&self) -> Result<Option<String>> {
+        let version_file = self.get_version_file();
+        if !version_file.exists() {
+            return Ok(None);
+        }
+
+        let version = std::fs::read_to_string(&version_file)
+            .context("Failed to read installed version file")?;
+        Ok(Some(version.trim().to_string()))
+    }
+
+    fn install(&self, version: Option<&str>, channel: ChannelChoice, dry_run: bool) -> Result<()> {
+        reporter().heading("Installing Claude Code...");
+        reporter().blank();
+        reporter().info(format!(
+            "Local artifacts: {}",
+            style(self.local_dir.display()).cyan()
+        ));
+        reporter().blank();
+
+        // Step 1: Get version
+        events::step("version");
+        let channel = self.resolve_channel(channel)?;
+        let version = match version.or(self.default_version.as_deref()) {
+            Some(pinned) => {
+                reporter().success(format!("Version: {} (pinned)", style(pinned).cyan()));
+                pinned.to_string()
+            }
+            None => self.latest_version(channel)?,
+        };
+
+        if !self.force && self.is_current(&version, channel)? {
+            events::emit(Event::VersionChosen {
+                version: version.clone(),
+                channel,
+            });
+            reporter().success(format!(
+                "Claude Code {} is already installed",
+                style(&version).cyan()
+            ));
+            reporter().detail("Pass --force to reinstall it");
+            if self.system {
+                return Ok(());
+            }
+            reporter().blank();
+            return self.configure(dry_run);
+        }
+
+        self.install_version(&version, channel, dry_run)
+    }
+
+    fn plan(&self, version: Option<&str>, channel: ChannelChoice) -> Result<InstallPlan> {
+        let channel = self.resolve_channel(channel)?;
+        let version = match version.or(self.default_version.as_deref()) {
+            Some(pinned) => pinned.to_string(),
+            None => download::get_latest_version(&self.local_dir, channel, self.offline)?.0,
+        };
+        // Only the recorded version: checking the binary against the
+        // manifest is left to the install
+        let up_to_date = !self.force
+            && self.installed_version()?.as_deref() == Some(version.as_str())
+            && self.installed_channel()? == Some(channel);
+        let install_dir = self.get_install_dir();
+        let mut plan = InstallPlan {
+            version: Some(version),
+            up_to_date,
+            binary: (!up_to_date).then(|| self.get_binary_path()),
+            ..InstallPlan::default()
+        };
+        if self.system {
+            if !up_to_date {
+                plan.path_entries.push(install_dir);
+            }
+            return Ok(plan);
+        }
+
+        let profile_dir = self.profile_dir()?;
+        let wanted = config::wanted_extensions(&profile_dir).context(Failure::Configuration(
+            "Failed to read the extensions to install".to_string(),
+        ))?;
+        plan.extensions = config::extensions_to_install(&wanted, &self.extension_options());
+        plan.deploy(
+            config::preview_deploy(&profile_dir, &self.paths, &self.deploy_options()?)?,
+            self.skip_cert_import,
         );
+        if !up_to_date && !self.is_on_process_path(&install_dir) {
+            plan.path_entries.push(install_dir);
+        }
+        Ok(plan)
+    }
+
+    fn installed_channel(&self) -> Result<Option<Channel>> {
+        let channel_file = self.get_channel_file();
+        if !channel_file.exists() {
+            return Ok(self.get_version_file().exists().then_some(Channel::Stable));
+        }
+
+        let name = std::fs::read_to_string(&channel_file)
+            .context("Failed to read installed channel file")?;
+        Channel::parse(name.trim()).map(Some).ok_or_else(|| {
+            anyhow!(
+                "Unknown channel '{}' in {}",
+                name.trim(),
+                channel_file.display()
+            )
+        })
+    }
+
+    fn settings_backups(&self) -> Result<Vec<Backup>> {
+        config::backup::list(&self.paths, self.name())
+    }
+
+    fn restore_settings(&self, backup: &Backup, dry_run: bool) -> Result<()> {
+        config::backup::restore(&self.paths, self.name(), backup, self.keep_backups, dry_run)
+    }
+
+    fn available_versions(&self) -> Result<download::VersionIndex> {
+        download::list_versions(&self.local_dir, self.offline)
+    }
+
+    fn update(&self, channel: ChannelChoice, dry_run: bool) -> Result<bool> {
+        if !self.is_installed()? {
+            return Err(anyhow!(
+                "Claude Code is not installed. Run 'code-assist install --tool claude-code' first."
+            ));
+        }
+        let current = self.installed_version()?;
+        let current_channel = self.installed_channel()?;
+
+        reporter().heading("Updating Claude Code...");
+        reporter().blank();
+        let channel = self.resolve_channel(channel)?;
+        let version = self.latest_version(channel)?;
+
+        if current.as_deref() == Some(version.as_str()) && current_channel == Some(channel) {
+            reporter().success(format!(
+                "Already up to date ({} on the {} channel)",
+                style(&version).cyan(),
+                channel
+            ));
+            return Ok(false);
+        }
+
+        reporter().info(format!(
+            "Updating {} -> {}",
+            style(current.as_deref().unwrap_or("unknown version")).cyan(),
+            style(&version).cyan()
+        ));
+        self.install_version(&version, channel, dry_run)?;
+        Ok(true)
+    }
+
+    fn uninstall(&self, purge: bool, dry_run: bool) -> Result<()> {
+        reporter().heading("Uninstalling Claude Code...");
+        reporter().blank();
+
+        if self.system {
+            return self.uninstall_system(dry_run);
+        }
 
         let binary_path = self.get_binary_path();
+        let mut removals = Removals::default();
 
-        // Try to run claude uninstall first
-        if binary_path.exists() {
-            println!("  Running Claude Code uninstaller...");
-            let output = std::process::Command::new(&binary_path)
-                .arg("uninstall")
-                .output();
+        if dry_run {
+            if binary_path.exists() {
+                plan::step(format!("Run '{} uninstall'", binary_path.display()));
+                plan::step(format!(
+                    "If that fails, remove {}",
+                    self.get_install_dir().display()
+                ));
+            } else {
+                reporter().skip("Claude Code is not installed");
+            }
+        } else if binary_path.exists() {
+            // Try to run claude uninstall first
+            reporter().info("Running Claude Code uninstaller...");
+            let output = self
+                .host
+                .runner
+                .output(std::process::Command::new(&binary_path).arg("uninstall"));
 
             match output {
                 Ok(o) if o.status.success() => {
-                    println!(
-                        "  {} Claude Code uninstalled",
-                        style("✓").green().bold()
-                    );
+                    removals.remove_file(&self.get_version_file());
+                    reporter().success("Claude Code uninstalled");
                 }
                 _ => {
                     // Manual cleanup
-                    println!("  {} Performing manual cleanup...", style("!").yellow().bold());
+                    reporter().warn("Performing manual cleanup...");
 
                     // Remove binary
-                    std::fs::remove_file(&binary_path).ok();
+                    removals.remove_file(&binary_path);
 
                     // Remove .claude directory (but keep downloads as backup)
-                    let claude_dir = platform::get_paths().claude_config_dir;
+                    let claude_dir = &self.paths.claude_config_dir;
                     if claude_dir.exists() {
                         // Only remove specific subdirectories, not the whole thing
-                        std::fs::remove_dir_all(claude_dir.join("bin")).ok();
+                        removals.remove_dir_all(&claude_dir.join("bin"));
                     }
                 }
             }
         } else {
-            println!(
-                "  {} Claude Code is not installed",
-                style("-").dim()
-            );
+            reporter().skip("Claude Code is not installed");
+        }
+
+        self.remove_from_path(dry_run)?;
+
+        config::uninstall_extensions(&*self.host.runner, &self.paths, self.name(), dry_run)
+            .context(Failure::Configuration(
+                "Failed to uninstall extensions".to_string(),
+            ))?;
+
+        if purge {
+            self.purge(&mut removals, dry_run)?;
+        }
+
+        if !removals.failed.is_empty() {
+            self.free_locked_files(&mut removals)?;
         }
+        removals.finish()
+    }
+
+    fn rollback(&self, to: Option<&str>, dry_run: bool) -> Result<()> {
+        reporter().heading("Rolling back Claude Code...");
+        reporter().blank();
+
+        let binary_path = self.get_binary_path();
+        if !binary_path.exists() {
+            return Err(anyhow!(
+                "Claude Code is not installed. Run 'code-assist install --tool claude-code' first."
+            ));
+        }
+
+        let current = self.installed_version()?;
+        let retained = self.retained_versions()?;
+
+        let target = match to {
+            Some(requested) => {
+                if !retained.iter().any(|v| v == requested) {
+                    return Err(anyhow!(
+                        "Version {} is not retained. Available: {}",
+                        requested,
+                        if retained.is_empty() {
+                            "none".to_string()
+                        } else {
+                            retained.join(", ")
+                        }
+                    ));
+                }
+                requested.to_string()
+            }
+            None => retained
+                .iter()
+                .rev()
+                .find(|v| match &current {
+                    Some(c) => download::compare_versions(v, c) == std::cmp::Ordering::Less,
+                    None => true,
+                })
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No previous version to roll back to. Previous binaries are kept \
+                         when a newer version is installed over an existing one."
+                    )
+                })?,
+        };
+
+        if current.as_deref() == Some(target.as_str()) {
+            reporter().skip(format!("Version {} is already installed", style(&target).cyan()));
+            return Ok(());
+        }
+
+        if dry_run {
+            if let Some(current) = &current {
+                plan::step(format!(
+                    "Keep version {} for rollback at {}",
+                    current,
+                    self.get_retained_binary_path(current).display()
+                ));
+            }
+            plan::step(format!(
+                "Copy {} to {}",
+                self.get_retained_binary_path(&target).display(),
+                binary_path.display()
+            ));
+            plan::step(format!(
+                "Record version {} in {}",
+                target,
+                self.get_version_file().display()
+            ));
+            return Ok(());
+        }
+
+        // Keep the version we're replacing so the rollback can itself be undone
+        if let Some(current) = &current {
+            let retained_current = self.get_retained_binary_path(current);
+            if let Some(parent) = retained_current.parent() {
+                std::fs::create_dir_all(parent).context("Failed to create versions directory")?;
+            }
+            std::fs::copy(&binary_path, &retained_current)
+                .context("Failed to retain current binary")?;
+        }
+
+        std::fs::copy(self.get_retained_binary_path(&target), &binary_path)
+            .context("Failed to restore previous binary")?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&binary_path)?.permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&binary_path, perms)?;
+        }
+
+        std::fs::write(self.get_version_file(), &target)
+            .context("Failed to record installed version")?;
+
+        reporter().success(format!(
+            "Restored version {}{}",
+            style(&target).cyan(),
+            match &current {
+                Some(c) => format!(" (was {})", c),
+                None => String::new(),
+            }
+        ));
 
         Ok(())
     }
 
-    fn configure(&self) -> Result<()> {
-        // Install VSIX extensions
-        println!("  Installing VS Code extensions...\n");
-        let vsix_dir = self.local_dir.join("VSIX");
-        config::install_vsix_extensions(&vsix_dir)?;
+    fn configure(&self, dry_run: bool) -> Result<()> {
+        // Install VS Code extensions
+        events::step("extensions");
+        reporter().info("Installing VS Code extensions...");
+        reporter().blank();
+        let profile_dir = self.profile_dir()?;
+        config::install_extensions(
+            &profile_dir,
+            &self.paths,
+            self.name(),
+            &self.extension_options(),
+            dry_run,
+        )?;
 
         // Deploy configurations
-        println!("\n  Deploying configurations...\n");
-        let paths = platform::get_paths();
-        config::deploy_configs(&self.local_dir, &paths)?;
+        events::step("configuration");
+        reporter().blank();
+        reporter().info("Deploying configurations...");
+        reporter().blank();
+        config::deploy_configs(&profile_dir, &self.paths, &self.deploy_options()?, dry_run)?;
 
         Ok(())
     }
+
+    fn deployed_profile(&self) -> Result<Option<String>> {
+        Ok(State::load(&self.paths, self.name())?.and_then(|state| state.profile))
+    }
+
+    fn check_configuration(&self) -> Result<Vec<config::Drift>> {
+        config::check_configs(&self.profile_dir()?, &self.paths, &self.deploy_options()?)
+    }
+
+    fn status(&self) -> Result<ToolStatus> {
+        let installed = self.is_installed()?;
+        let install_dir = self.get_install_dir();
+
+        let latest_version = if self.offline {
+            None
+        } else {
+            let channel = self.installed_channel()?.unwrap_or_default();
+            download::get_remote_latest_version(channel)
+        };
+
+        // Reported for the first editor targeted, VS Code unless it's missing
+        let wanted = config::wanted_extensions(&self.config_source()).unwrap_or_default();
+        let installed_extensions = if wanted.is_empty() {
+            Vec::new()
+        } else {
+            let editor = prerequisites::target_editors(&*self.host.runner, self.editor)[0];
+            config::list_installed_extensions(&*self.host.runner, editor).unwrap_or_else(|e| {
+                tracing::debug!("could not list {} extensions: {:#}", editor, e);
+                Vec::new()
+            })
+        };
+        let extensions = wanted
+            .into_iter()
+            .map(|want| {
+                let found = installed_extensions
+                    .iter()
+                    .find(|ext| ext.matches(&want.id));
+                ExtensionStatus {
+                    installed: found.is_some_and(|ext| want.is_satisfied_by(ext)),
+                    version: found.and_then(|ext| ext.version.clone()),
+                    name: want.id,
+                }
+            })
+            .collect();
+
+        let paths = &self.paths;
+        let config_drift = config::config_drift(paths, self.name(), &*self.host.environment)
+            .unwrap_or_else(|e| {
+                tracing::debug!("could not read deployment state: {:#}", e);
+                Vec::new()
+            });
+
+        let deployed_files = config::deployed_files(paths, self.name()).unwrap_or_else(|e| {
+            tracing::debug!("could not read deployment state: {:#}", e);
+            Vec::new()
+        });
+
+        let platform_id = platform::get_native_platform_id();
+        let mut cached_versions: Vec<String> = Vec::new();
+        for entry in cache::entries().unwrap_or_default() {
+            if !entry.partial
+                && (entry.platform.as_deref() == Some(platform_id)
+                    || entry.platform.as_deref() == platform::get_emulated_platform_id())
+                && !cached_versions.contains(&entry.version)
+            {
+                cached_versions.push(entry.version);
+            }
+        }
+
+        let node_extra_ca_certs = std::env::var_os("NODE_EXTRA_CA_CERTS")
+            .filter(|value| !value.is_empty())
+            .map(|value| EnvFileStatus {
+                exists: std::path::Path::new(&value).is_file(),
+                value: value.to_string_lossy().to_string(),
+            });
+
+        Ok(ToolStatus {
+            name: self.name().to_string(),
+            display_name: self.display_name().to_string(),
+            installed,
+            installed_version: if installed {
+                self.installed_version()?
+            } else {
+                None
+            },
+            install_path: installed.then(|| self.get_binary_path()),
+            binary_arch: installed
+                .then(|| platform::arch::of_binary(&self.get_binary_path()))
+                .flatten(),
+            on_path: self.is_on_process_path(&install_dir),
+            profile: self.deployed_profile().unwrap_or_default(),
+            latest_version,
+            extensions,
+            node_extra_ca_certs,
+            deployed_files,
+            config_drift,
+            cached_versions,
+            channel: if installed {
+                self.installed_channel()?
+            } else {
+                None
+            },
+        })
+    }
+
+    fn diagnose(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let binary_path = self.get_binary_path();
+        let install_dir = self.get_install_dir();
+
+        diagnostics.push(if !binary_path.exists() {
+            Diagnostic::fail(
+                "Claude Code binary",
+                format!(
+                    "{} is missing; run 'code-assist install --tool claude-code'",
+                    binary_path.display()
+                ),
+            )
+        } else if !is_executable(&binary_path) {
+            Diagnostic::fail(
+                "Claude Code binary",
+                format!(
+                    "{} is not executable; run 'chmod +x {}'",
+                    binary_path.display(),
+                    binary_path.display()
+                ),
+            )
+        } else {
+            Diagnostic::pass("Claude Code binary", binary_path.display().to_string())
+        });
+
+        diagnostics.push(if self.is_on_process_path(&install_dir) {
+            Diagnostic::pass("Install directory on PATH", install_dir.display().to_string())
+        } else if self
+            .host
+            .environment
+            .is_on_user_path(&install_dir.to_string_lossy())
+        {
+            Diagnostic::fail(
+                "Install directory on PATH",
+                format!(
+                    "{} is on the saved PATH but not in this shell. {}",
+                    install_dir.display(),
+                    platform::path_refresh_steps().join(". ")
+                ),
+            )
+        } else {
+            Diagnostic::fail(
+                "Install directory on PATH",
+                format!(
+                    "{} is not on PATH; run 'code-assist install --tool claude-code' to add it",
+                    install_dir.display()
+                ),
+            )
+        });
+
+        if let Ok(problem) = std::fs::read_to_string(self.get_smoke_test_file()) {
+            diagnostics.push(Diagnostic::fail(
+                "Claude Code runs",
+                format!(
+                    "{} failed its last check after install: {}. If security software \
+                     quarantined it, allow it and run 'code-assist install --tool claude-code'",
+                    binary_path.display(),
+                    problem.trim()
+                ),
+            ));
+        }
+        diagnostics.extend(self.diagnose_conflicts());
+        diagnostics.extend(self.diagnose_arch());
+        diagnostics.push(self.diagnose_space());
+
+        match config::wanted_extensions(&self.config_source()) {
+            Ok(wanted) if !wanted.is_empty() => {
+                let runner = &*self.host.runner;
+                let editors = prerequisites::target_editors(runner, self.editor);
+                let found = process::in_parallel(&editors, |editor| {
+                    diagnose_extensions(runner, *editor, &wanted)
+                });
+                diagnostics.extend(found.into_iter().flatten());
+            }
+            Ok(_) => {}
+            Err(e) => diagnostics.push(Diagnostic::fail("Extensions", format!("{:#}", e))),
+        }
+
+        // Nothing to compare against until configuration has been deployed
+        let paths = &self.paths;
+        if !State::exists(paths, self.name()) {
+            return diagnostics;
+        }
+        let drift = config::config_drift(paths, self.name(), &*self.host.environment);
+        diagnostics.push(match drift {
+            Ok(drift) if drift.is_empty() => {
+                Diagnostic::pass("Deployed configuration", "matches what was deployed")
+            }
+            Ok(drift) => Diagnostic::fail(
+                "Deployed configuration",
+                format!(
+                    "{}; run 'code-assist configure --tool claude-code' to redeploy",
+                    drift.join("; ")
+                ),
+            ),
+            Err(e) => Diagnostic::fail("Deployed configuration", format!("{:#}", e)),
+        });
+
+        diagnostics
+    }
+}
+
+/// Run `binary --version` and check its output names `version`
+fn run_version(runner: &dyn Runner, binary: &Path, version: &str) -> Result<()> {
+    let output = runner
+        .output(std::process::Command::new(binary).arg("--version"))
+        .with_context(|| format!("could not run {}", binary.display()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "'--version' exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !reports_version(&stdout, version) {
+        return Err(anyhow!(
+            "'--version' reported {:?}, not {}",
+            stdout.trim(),
+            version
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `--version` output such as `2.1.31 (Claude Code)` names `version`
+fn reports_version(output: &str, version: &str) -> bool {
+    output
+        .split_whitespace()
+        .any(|word| word.trim_start_matches('v') == version)
+}
+
+fn is_executable(path: &std::path::Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path)
+            .map(|m| m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Whether each wanted extension is installed in `editor`, at the version
+/// pinned if there is one
+fn diagnose_extensions(
+    runner: &dyn Runner,
+    editor: Editor,
+    wanted: &[WantedExtension],
+) -> Vec<Diagnostic> {
+    let installed = match config::list_installed_extensions(runner, editor) {
+        Ok(installed) => installed,
+        Err(e) => {
+            return vec![Diagnostic::fail(
+                format!("{} extensions", editor),
+                format!(
+                    "Could not list extensions ({}); is '{}' on PATH?",
+                    e,
+                    editor.command()
+                ),
+            )]
+        }
+    };
+
+    wanted
+        .iter()
+        .map(|want| {
+            let found = installed.iter().find(|ext| ext.matches(&want.id));
+            let label = format!("{} extension {}", editor, want.id);
+            match (found, &want.version) {
+                (Some(ext), _) if want.is_satisfied_by(ext) => Diagnostic::pass(label, "installed"),
+                (Some(ext), Some(version)) => Diagnostic::fail(
+                    label,
+                    format!(
+                        "{} installed, {} wanted; run 'code-assist configure --tool claude-code'",
+                        ext.version.as_deref().unwrap_or("another version"),
+                        version
+                    ),
+                ),
+                _ => Diagnostic::fail(
+                    label,
+                    "Not installed; run 'code-assist configure --tool claude-code'",
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fake::FakeEnvironment;
+    use crate::process::fake::FakeRunner;
+    use sha2::{Digest, Sha256};
+    use std::collections::BTreeMap;
+    use std::process::Command;
+    use std::rc::Rc;
+
+    /// Options for a Claude Code install into `root/home` from the
+    /// templates in `root/local`, running commands and changing the
+    /// environment through `host`
+    fn options(root: &Path, host: Host) -> ToolOptions {
+        let home = root.join("home");
+        ToolOptions {
+            local_dir: root.join("local"),
+            offline: false,
+            skip_space_check: true,
+            force_extensions: false,
+            force: false,
+            edited_keys: EditedKeys::Keep,
+            link: false,
+            skip_cert_import: true,
+            force_path: false,
+            editor: Some(Editor::Stable),
+            profile: None,
+            keep_backups: 0,
+            node_version: "18.0.0".to_string(),
+            system: false,
+            paths: PlatformPaths {
+                claude_config_dir: home.join(".claude"),
+                app_data_dir: home.join("appdata"),
+                certs_dir: home.join("certs"),
+                vscode_user_dir: None,
+                downloads_dir: home.join("downloads"),
+                state_dir: home.join("state"),
+                backups_dir: home.join("backups"),
+                home_dir: home,
+            },
+            assume_yes: true,
+            default_version: None,
+            default_channel: None,
+            dependencies: Vec::new(),
+            overrides: BTreeMap::new(),
+            host,
+        }
+    }
+
+    fn fake_host(runner: &Rc<FakeRunner>, environment: &Rc<FakeEnvironment>) -> Host {
+        Host {
+            runner: runner.clone(),
+            environment: environment.clone(),
+        }
+    }
+
+    fn platform_tree(local_dir: &Path) -> PathBuf {
+        let platform = if cfg!(target_os = "windows") {
+            "WIN"
+        } else if cfg!(target_os = "macos") {
+            "MACOS"
+        } else {
+            "LINUX"
+        };
+        local_dir.join(platform).join("USER-DIRECTORY")
+    }
+
+    fn editor_command(args: &[&str]) -> String {
+        process::describe(Command::new(Editor::Stable.cli()).args(args))
+    }
+
+    #[test]
+    fn configure_installs_extensions_then_deploys_settings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let runner = Rc::new(FakeRunner::new(|_| (0, String::new())));
+        let environment = Rc::new(FakeEnvironment::default());
+        let options = options(dir.path(), fake_host(&runner, &environment));
+        let templates = platform_tree(&options.local_dir).join(".claude");
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(
+            options.local_dir.join(config::EXTENSIONS_FILE),
+            r#"{ "extensions": ["anthropic.claude-code"] }"#,
+        )
+        .unwrap();
+        std::fs::write(templates.join("settings.json"), r#"{ "model": "opus" }"#).unwrap();
+        let tool = ClaudeCode::new(&options);
+
+        let (result, emitted) = events::capture(|| tool.configure(false));
+        result.unwrap();
+
+        assert_eq!(
+            runner.commands(),
+            [
+                editor_command(&["--list-extensions", "--show-versions"]),
+                editor_command(&["--install-extension", "anthropic.claude-code"]),
+                // Again for the full ID of what was added
+                editor_command(&["--list-extensions", "--show-versions"]),
+            ]
+        );
+        let settings = options.paths.claude_config_dir.join("settings.json");
+        assert_eq!(
+            emitted,
+            [
+                Event::StepStarted {
+                    step: "extensions".to_string()
+                },
+                Event::ExtensionInstalled {
+                    editor: Editor::Stable.to_string(),
+                    id: "anthropic.claude-code".to_string(),
+                },
+                Event::StepCompleted {
+                    step: "extensions".to_string()
+                },
+                Event::StepStarted {
+                    step: "configuration".to_string()
+                },
+                Event::SettingsChanged {
+                    file: settings.clone(),
+                    keys: vec!["model".to_string()],
+                },
+                Event::FileDeployed(settings),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_smoke_test_runs_the_installed_binary() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let runner = Rc::new(FakeRunner::new(|_| (0, "2.1.0 (Claude Code)".to_string())));
+        let environment = Rc::new(FakeEnvironment::default());
+        let tool = ClaudeCode::new(&options(dir.path(), fake_host(&runner, &environment)));
+        std::fs::create_dir_all(tool.get_install_dir()).unwrap();
+        let binary = tool.get_binary_path();
+
+        tool.smoke_test("2.1.0").unwrap();
+        assert_eq!(
+            runner.commands(),
+            [process::describe(Command::new(&binary).arg("--version"))]
+        );
+
+        let error = tool.smoke_test("2.2.0").unwrap_err();
+        assert!(error.to_string().contains("not 2.2.0"), "{:#}", error);
+        assert!(tool.get_smoke_test_file().exists());
+    }
+
+    #[test]
+    fn an_install_sets_up_the_binary_then_the_editor_settings_and_path() {
+        platform::init_test_paths();
+        let dir = tempfile::TempDir::new().unwrap();
+        let runner = Rc::new(FakeRunner::new(|command| {
+            let stdout = if command.starts_with("tar ") {
+                r#"{ "engines": { "vscode": "^1.90.0" } }"#
+            } else if command == editor_command(&["--version"]) {
+                "1.95.0"
+            } else if command.ends_with("claude --version") {
+                "2.1.0 (Claude Code)"
+            } else {
+                ""
+            };
+            (0, stdout.to_string())
+        }));
+        let environment = Rc::new(FakeEnvironment::default());
+        let options = ToolOptions {
+            offline: true,
+            ..options(dir.path(), fake_host(&runner, &environment))
+        };
+
+        // A local release of 2.1.0 with a settings template and the VSIX
+        // file of an extension
+        let local = &options.local_dir;
+        let platform_id = platform::get_native_platform_id();
+        let binary = b"#!/bin/sh\necho '2.1.0 (Claude Code)'\n";
+        let release = local.join("2.1.0");
+        std::fs::create_dir_all(release.join(platform_id)).unwrap();
+        std::fs::write(local.join("latest"), "2.1.0").unwrap();
+        std::fs::write(
+            release.join("manifest.json"),
+            serde_json::json!({
+                "platforms": { platform_id: { "checksum": hex::encode(Sha256::digest(binary)) } }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(release.join(platform_id).join("claude"), binary).unwrap();
+        std::fs::write(
+            local.join(config::EXTENSIONS_FILE),
+            r#"{ "extensions": ["anthropic.claude-code"] }"#,
+        )
+        .unwrap();
+        let vsix = local.join("VSIX").join("claude-code-2.1.0.vsix");
+        std::fs::create_dir_all(local.join("VSIX")).unwrap();
+        std::fs::write(&vsix, "").unwrap();
+        let templates = platform_tree(local).join(".claude");
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(templates.join("settings.json"), r#"{ "model": "opus" }"#).unwrap();
+        let tool = ClaudeCode::new(&options);
+
+        let (result, emitted) =
+            events::capture(|| tool.install(None, ChannelChoice::default(), false));
+        result.unwrap();
+
+        let checksum = hex::encode(Sha256::digest(binary));
+        let cached = cache::binary_path("2.1.0", platform_id, &checksum).unwrap();
+        let install_dir = tool.get_install_dir();
+        assert_eq!(
+            runner.commands(),
+            [
+                process::describe(Command::new(&cached).arg("install")),
+                process::describe(Command::new(tool.get_binary_path()).arg("--version")),
+                editor_command(&["--list-extensions", "--show-versions"]),
+                editor_command(&["--version"]),
+                process::describe(
+                    Command::new("tar")
+                        .arg("-xOf")
+                        .arg(&vsix)
+                        .arg("extension/package.json")
+                ),
+                editor_command(&["--install-extension", &vsix.to_string_lossy()]),
+                editor_command(&["--list-extensions", "--show-versions"]),
+            ]
+        );
+        assert_eq!(
+            environment.changes(),
+            [
+                format!("add to PATH {}", install_dir.display()),
+                format!("add to process PATH {}", install_dir.display()),
+            ]
+        );
+
+        assert_eq!(std::fs::read(&cached).unwrap(), binary);
+        assert_eq!(
+            std::fs::read_to_string(tool.get_version_file()).unwrap(),
+            "2.1.0"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tool.get_channel_file()).unwrap(),
+            Channel::Stable.name()
+        );
+        let settings = options.paths.claude_config_dir.join("settings.json");
+        let deployed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&settings).unwrap()).unwrap();
+        assert_eq!(deployed, serde_json::json!({ "model": "opus" }));
+        let state = State::load(&options.paths, tool.name()).unwrap().unwrap();
+        assert_eq!(
+            state.path_entries,
+            [install_dir.to_string_lossy().to_string()]
+        );
+
+        let step = |name: &str| Event::StepStarted {
+            step: name.to_string(),
+        };
+        let done = |name: &str| Event::StepCompleted {
+            step: name.to_string(),
+        };
+        assert_eq!(
+            emitted,
+            [
+                step("version"),
+                Event::VersionChosen {
+                    version: "2.1.0".to_string(),
+                    channel: Channel::Stable,
+                },
+                done("version"),
+                step("manifest"),
+                done("manifest"),
+                step("download"),
+                Event::DownloadSource(download::DownloadSource::LocalFallback),
+                done("download"),
+                step("setup"),
+                done("setup"),
+                step("smoke test"),
+                done("smoke test"),
+                step("extensions"),
+                Event::ExtensionInstalled {
+                    editor: Editor::Stable.to_string(),
+                    id: "anthropic.claude-code".to_string(),
+                },
+                done("extensions"),
+                step("configuration"),
+                Event::SettingsChanged {
+                    file: settings.clone(),
+                    keys: vec!["model".to_string()],
+                },
+                Event::FileDeployed(settings),
+                done("configuration"),
+                step("path"),
+                Event::Warning(
+                    "'claude' won't be found in this terminal until PATH is refreshed:".to_string()
+                ),
+            ]
+        );
+    }
 }
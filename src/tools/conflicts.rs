@@ -0,0 +1,275 @@
+//! Other copies of a tool's binary on PATH, such as one left by an earlier
+//! `npm install -g` or `brew install`. Whichever comes first on PATH is the
+//! one that runs, so a stale copy ahead of ours makes updates look like
+//! they didn't happen.
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::platform::Host;
+
+/// Where a copy of a binary came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The copy in our install directory
+    Ours,
+    /// A global npm package, such as `@anthropic-ai/claude-code`
+    Npm(String),
+    /// A Homebrew formula or cask
+    Homebrew(String),
+    Other,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Ours => f.write_str("installed by code-assist"),
+            Origin::Npm(package) => write!(f, "npm package {}", package),
+            Origin::Homebrew(package) => write!(f, "Homebrew package {}", package),
+            Origin::Other => f.write_str("installed some other way"),
+        }
+    }
+}
+
+impl Origin {
+    /// The program and arguments that uninstall this copy, if we know them
+    pub fn uninstall_command(&self) -> Option<(&'static str, Vec<String>)> {
+        match self {
+            Origin::Npm(package) => Some((
+                if cfg!(windows) { "npm.cmd" } else { "npm" },
+                vec![
+                    "uninstall".to_string(),
+                    "--global".to_string(),
+                    package.clone(),
+                ],
+            )),
+            Origin::Homebrew(package) => {
+                Some(("brew", vec!["uninstall".to_string(), package.clone()]))
+            }
+            Origin::Ours | Origin::Other => None,
+        }
+    }
+}
+
+/// A copy of the binary found on PATH
+#[derive(Debug)]
+pub struct FoundBinary {
+    pub path: PathBuf,
+    pub origin: Origin,
+    /// First line of its `--version` output
+    pub version: Option<String>,
+}
+
+impl fmt::Display for FoundBinary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.path.display(), self.origin)?;
+        if let Some(version) = &self.version {
+            write!(f, ", {}", version)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Every copy of `binary` on PATH, in the order the shell searches them,
+/// like `which -a` (or `where` on Windows). The first one is the one that
+/// runs.
+pub fn find_on_path(binary: &str, install_dir: &Path, host: &Host) -> Vec<FoundBinary> {
+    let mut found: Vec<FoundBinary> = Vec::new();
+    let mut seen: Vec<PathBuf> = Vec::new();
+    for dir in host.environment.process_path() {
+        for name in file_names(binary) {
+            let path = dir.join(&name);
+            if !is_executable_file(&path) {
+                continue;
+            }
+            // PATH often lists a directory twice, or a link to it
+            let resolved = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if seen.contains(&resolved) {
+                continue;
+            }
+            seen.push(resolved);
+            found.push(FoundBinary {
+                origin: origin(&path, install_dir),
+                version: version(host, &path),
+                path,
+            });
+        }
+    }
+    found
+}
+
+/// Names `binary` may have on disk: with each of PATHEXT's extensions on
+/// Windows, where npm installs a `.cmd` shim
+fn file_names(binary: &str) -> Vec<String> {
+    if !cfg!(windows) {
+        return vec![binary.to_string()];
+    }
+    let stem = binary.strip_suffix(".exe").unwrap_or(binary);
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    extensions
+        .split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| format!("{}{}", stem, ext.to_lowercase()))
+        .collect()
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Work out where the copy at `path` came from: our install directory,
+/// a global npm package (its link or shim leads into `node_modules`), or
+/// Homebrew (its link leads into the Cellar or Caskroom)
+fn origin(path: &Path, install_dir: &Path) -> Origin {
+    let same_dir =
+        path.parent().is_some_and(
+            |dir| match (dir.canonicalize(), install_dir.canonicalize()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => dir == install_dir,
+            },
+        );
+    if same_dir {
+        return Origin::Ours;
+    }
+
+    let resolved = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let parts: Vec<String> = resolved
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    if let Some(package) = package_after(&parts, &["node_modules"]) {
+        return Origin::Npm(package);
+    }
+    if let Some(package) = package_after(&parts, &["Cellar", "Caskroom"]) {
+        return Origin::Homebrew(package);
+    }
+
+    // npm's shims on Windows are scripts that name the package they run
+    if let Some(package) = shim_package(path) {
+        return Origin::Npm(package);
+    }
+    Origin::Other
+}
+
+/// The package in the path component after the last of `markers`, with its
+/// scope if it has one
+fn package_after(parts: &[String], markers: &[&str]) -> Option<String> {
+    let index = parts
+        .iter()
+        .rposition(|part| markers.contains(&part.as_str()))?;
+    let name = parts.get(index + 1)?;
+    if name.starts_with('@') {
+        let scoped = parts.get(index + 2)?;
+        return Some(format!("{}/{}", name, scoped));
+    }
+    Some(name.clone())
+}
+
+/// The package a small script at `path` runs out of `node_modules`, if it
+/// is one
+fn shim_package(path: &Path) -> Option<String> {
+    const MAX_SHIM_SIZE: u64 = 64 * 1024;
+    if std::fs::metadata(path).ok()?.len() > MAX_SHIM_SIZE {
+        return None;
+    }
+    let text = String::from_utf8(std::fs::read(path).ok()?).ok()?;
+    let start = text.find("node_modules")? + "node_modules".len();
+    let parts: Vec<String> = text[start..]
+        .split(['/', '\\'])
+        .skip_while(|part| part.is_empty())
+        .take(2)
+        .map(|part| {
+            part.split(|c: char| c == '"' || c == '\'' || c.is_whitespace())
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    let mut with_marker = vec!["node_modules".to_string()];
+    with_marker.extend(parts);
+    package_after(&with_marker, &["node_modules"]).filter(|package| !package.is_empty())
+}
+
+/// First line of `path --version`, if it runs
+fn version(host: &Host, path: &Path) -> Option<String> {
+    let output = host
+        .runner
+        .output(std::process::Command::new(path).arg("--version"))
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(path: &str) -> Vec<String> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn packages_are_read_from_where_the_binary_lives() {
+        assert_eq!(
+            package_after(
+                &parts("/usr/local/lib/node_modules/@anthropic-ai/claude-code/cli.js"),
+                &["node_modules"]
+            )
+            .as_deref(),
+            Some("@anthropic-ai/claude-code")
+        );
+        assert_eq!(
+            package_after(
+                &parts("/opt/homebrew/Caskroom/claude-code/2.1.31/claude"),
+                &["Cellar", "Caskroom"]
+            )
+            .as_deref(),
+            Some("claude-code")
+        );
+        assert_eq!(
+            package_after(&parts("/usr/local/bin/claude"), &["node_modules"]),
+            None
+        );
+    }
+
+    #[test]
+    fn npm_shims_name_their_package() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shim = dir.path().join("claude.cmd");
+        std::fs::write(
+            &shim,
+            "@ECHO off\r\n\"%_prog%\" \"%dp0%\\node_modules\\@anthropic-ai\\claude-code\\cli.js\" %*\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            shim_package(&shim).as_deref(),
+            Some("@anthropic-ai/claude-code")
+        );
+        assert_eq!(
+            origin(&shim, Path::new("/elsewhere")),
+            Origin::Npm("@anthropic-ai/claude-code".to_string())
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::path::PathBuf;
+
+use super::{ChannelChoice, ExtensionStatus, InstallPlan, Tool, ToolOptions, ToolStatus};
+use crate::config::{self, EditedKeys};
+use crate::error::Failure;
+use crate::output::events;
+use crate::output::reporter;
+use crate::platform::{Editor, Host, PlatformPaths};
+use crate::prerequisites::{self, Prerequisite};
+use crate::state::{Backup, State};
+
+/// Marketplace ID of the Continue extension, `Continue.continue`, lowercased
+/// as extension IDs are compared
+const EXTENSION_ID: &str = "continue.continue";
+
+/// Continue (continue.dev), an open-source assistant that lives entirely in
+/// the editor: installing it installs its VS Code extension and deploys
+/// `~/.continue`, with certificates in `~/.continue/certs`
+pub struct Continue {
+    local_dir: PathBuf,
+    offline: bool,
+    force_extensions: bool,
+    force: bool,
+    edited_keys: EditedKeys,
+    link: bool,
+    skip_cert_import: bool,
+    paths: PlatformPaths,
+    editor: Option<Editor>,
+    profile: Option<String>,
+    keep_backups: usize,
+    assume_yes: bool,
+    default_version: Option<String>,
+    dependencies: Vec<String>,
+    host: Host,
+}
+
+impl Continue {
+    pub fn new(options: &ToolOptions) -> Self {
+        let mut paths = options.paths.clone();
+        paths.certs_dir = paths.home_dir.join(".continue").join("certs");
+        Self {
+            local_dir: options.local_dir.clone(),
+            offline: options.offline,
+            force_extensions: options.force_extensions,
+            force: options.force,
+            edited_keys: options.edited_keys,
+            link: options.link,
+            skip_cert_import: options.skip_cert_import,
+            paths,
+            editor: options.editor,
+            profile: options.profile.clone(),
+            keep_backups: options.keep_backups,
+            assume_yes: options.assume_yes,
+            default_version: options.default_version.clone(),
+            dependencies: options.dependencies.clone(),
+            host: options.host.clone(),
+        }
+    }
+
+    fn extension_options(&self) -> config::ExtensionOptions {
+        config::ExtensionOptions {
+            editor: self.editor,
+            offline: self.offline,
+            force: self.force_extensions,
+            host: self.host.clone(),
+        }
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.paths.home_dir.join(".continue")
+    }
+
+    fn deploy_options(&self) -> Result<config::DeployOptions> {
+        Ok(config::DeployOptions {
+            keep_backups: self.keep_backups,
+            assume_yes: self.assume_yes,
+            force: self.force,
+            edited_keys: self.edited_keys,
+            editor: self.editor,
+            install_dir: self.config_dir(),
+            tool: self.name().to_string(),
+            profile: self.profile()?,
+            link: self.link,
+            skip_cert_import: self.skip_cert_import,
+            files: Vec::new(),
+            env: Vec::new(),
+            host: self.host.clone(),
+        })
+    }
+
+    /// Profile to deploy: the one asked for, else the one last deployed,
+    /// else the default
+    fn profile(&self) -> Result<String> {
+        if let Some(profile) = &self.profile {
+            return Ok(profile.clone());
+        }
+        Ok(self
+            .deployed_profile()?
+            .unwrap_or_else(|| config::DEFAULT_PROFILE.to_string()))
+    }
+
+    fn profile_dir(&self) -> Result<PathBuf> {
+        config::profile_dir(&self.local_dir, &self.profile()?)
+    }
+
+    fn config_source(&self) -> PathBuf {
+        self.profile_dir().unwrap_or_else(|e| {
+            tracing::debug!("could not resolve profile: {:#}", e);
+            self.local_dir.clone()
+        })
+    }
+
+    /// The installed extension in the first targeted editor that has it
+    fn installed_extension(&self) -> Option<config::InstalledExtension> {
+        prerequisites::target_editors(&*self.host.runner, self.editor)
+            .into_iter()
+            .find_map(|editor| {
+                let installed = config::list_installed_extensions(&*self.host.runner, editor)
+                    .unwrap_or_else(|e| {
+                        tracing::debug!("could not list {} extensions: {:#}", editor, e);
+                        Vec::new()
+                    });
+                installed.into_iter().find(|ext| ext.matches(EXTENSION_ID))
+            })
+    }
+
+    /// Install the extension, pinned to `version` if given, and deploy the
+    /// configuration. The extension is the tool itself, so there's nothing
+    /// else to install.
+    fn deploy(&self, version: Option<&str>, dry_run: bool) -> Result<()> {
+        let profile_dir = self.profile_dir()?;
+
+        events::step("extension");
+        reporter().info("Installing the Continue extension...");
+        reporter().blank();
+        let wanted = config::marketplace_extension(&profile_dir, EXTENSION_ID, version).context(
+            Failure::Configuration("Failed to read the VSIX files".to_string()),
+        )?;
+        let options = self.extension_options();
+        config::install_extension_list(
+            &[wanted],
+            &profile_dir,
+            &self.paths,
+            self.name(),
+            &options,
+            dry_run,
+        )?;
+
+        events::step("configuration");
+        reporter().blank();
+        reporter().info("Deploying configurations...");
+        reporter().blank();
+        config::deploy_configs(&profile_dir, &self.paths, &self.deploy_options()?, dry_run)
+    }
+}
+
+impl Tool for Continue {
+    fn name(&self) -> &str {
+        "continue"
+    }
+
+    fn display_name(&self) -> &str {
+        "Continue"
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        vec!["continue-dev", "continue.dev"]
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        self.dependencies.iter().map(String::as_str).collect()
+    }
+
+    /// Only an editor to install the extension into
+    fn prerequisites(&self) -> Vec<Prerequisite> {
+        vec![Prerequisite::VsCode { min_version: None }]
+    }
+
+    fn is_installed(&self) -> Result<bool> {
+        Ok(self.installed_extension().is_some())
+    }
+
+    fn installed_version(&self) -> Result<Option<String>> {
+        Ok(self.installed_extension().and_then(|ext| ext.version))
+    }
+
+    fn deployed_profile(&self) -> Result<Option<String>> {
+        Ok(State::load(&self.paths, self.name())?.and_then(|state| state.profile))
+    }
+
+    fn install(&self, version: Option<&str>, _channel: ChannelChoice, dry_run: bool) -> Result<()> {
+        reporter().heading("Installing Continue...");
+        reporter().blank();
+        reporter().info(format!(
+            "Local artifacts: {}",
+            style(self.local_dir.display()).cyan()
+        ));
+        reporter().blank();
+
+        self.deploy(version.or(self.default_version.as_deref()), dry_run)
+    }
+
+    fn plan(&self, version: Option<&str>, _channel: ChannelChoice) -> Result<InstallPlan> {
+        let profile_dir = self.profile_dir()?;
+        let version = version.or(self.default_version.as_deref());
+        let wanted = config::marketplace_extension(&profile_dir, EXTENSION_ID, version).context(
+            Failure::Configuration("Failed to read the VSIX files".to_string()),
+        )?;
+        let options = self.extension_options();
+        let mut plan = InstallPlan {
+            version: version.map(str::to_string),
+            extensions: config::extensions_to_install(&[wanted], &options),
+            ..InstallPlan::default()
+        };
+        plan.deploy(
+            config::preview_deploy(&profile_dir, &self.paths, &self.deploy_options()?)?,
+            self.skip_cert_import,
+        );
+        Ok(plan)
+    }
+
+    fn uninstall(&self, purge: bool, dry_run: bool) -> Result<()> {
+        reporter().heading("Uninstalling Continue...");
+        reporter().blank();
+
+        config::uninstall_extension(
+            &*self.host.runner,
+            &self.paths,
+            self.name(),
+            EXTENSION_ID,
+            self.editor,
+            dry_run,
+        )?;
+
+        if purge {
+            super::purge_configuration(
+                &self.config_source(),
+                &self.paths,
+                self.name(),
+                &self.host,
+                dry_run,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn configure(&self, dry_run: bool) -> Result<()> {
+        self.deploy(self.default_version.as_deref(), dry_run)
+    }
+    fn check_configuration(&self) -> Result<Vec<config::Drift>> {
+        config::check_configs(&self.profile_dir()?, &self.paths, &self.deploy_options()?)
+    }
+
+    fn settings_backups(&self) -> Result<Vec<Backup>> {
+        config::backup::list(&self.paths, self.name())
+    }
+
+    fn restore_settings(&self, backup: &Backup, dry_run: bool) -> Result<()> {
+        config::backup::restore(&self.paths, self.name(), backup, self.keep_backups, dry_run)
+    }
+
+    fn status(&self) -> Result<ToolStatus> {
+        let installed = self.installed_extension();
+        let paths = &self.paths;
+        Ok(ToolStatus {
+            name: self.name().to_string(),
+            display_name: self.display_name().to_string(),
+            installed: installed.is_some(),
+            installed_version: installed.as_ref().and_then(|ext| ext.version.clone()),
+            install_path: installed.is_some().then(|| self.config_dir()),
+            profile: self.deployed_profile().unwrap_or_default(),
+            extensions: vec![ExtensionStatus {
+                name: EXTENSION_ID.to_string(),
+                installed: installed.is_some(),
+                version: installed.and_then(|ext| ext.version),
+            }],
+            deployed_files: config::deployed_files(paths, self.name()).unwrap_or_else(|e| {
+                tracing::debug!("could not read deployment state: {:#}", e);
+                Vec::new()
+            }),
+            config_drift: config::config_drift(paths, self.name(), &*self.host.environment)
+                .unwrap_or_else(|e| {
+                    tracing::debug!("could not read deployment state: {:#}", e);
+                    Vec::new()
+                }),
+            ..ToolStatus::default()
+        })
+    }
+}
@@ -0,0 +1,1179 @@
+//! Tools declared in TOML rather than code. Each `tools.d/*.toml` in the
+//! local artifacts directory or the user config directory describes a tool
+//! that is either a single binary to download or an npm package, plus the
+//! config files, extensions, and environment variables to deploy with it.
+//! [`DeclaredTool`] installs one by interpreting its definition:
+//!
+//! ```toml
+//! name = "acme-cli"
+//! display_name = "Acme CLI"
+//! binary = "acme"
+//! version = "1.4.0"
+//!
+//! [download]
+//! url = "https://downloads.example.com/acme/${VERSION}/${PLATFORM}/${BINARY}"
+//! latest_url = "https://downloads.example.com/acme/latest"
+//!
+//! [[config]]
+//! source = "settings.json"
+//! dest = "${HOME}/.acme/settings.json"
+//! merge = "merge"
+//!
+//! [env]
+//! ACME_HOME = "${HOME}/.acme"
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::{ChannelChoice, InstallPlan, Tool, ToolOptions, ToolStatus};
+use crate::cancel;
+use crate::config::{self, EditedKeys, FileStrategy};
+use crate::doctor;
+use crate::download;
+use crate::error::Failure;
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::plan;
+use crate::platform::{self, Editor, Host, PlatformPaths};
+use crate::prerequisites::{self, Prerequisite};
+use crate::state::{Backup, State};
+
+/// Directory of tool definitions, in `local/` and the user config directory
+pub const TOOLS_DIR: &str = "tools.d";
+
+/// A `tools.d/*.toml` file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+
+    /// Tools to install before this one
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    /// Version to install when none is asked for
+    pub version: Option<String>,
+
+    /// Where a single-binary tool is downloaded from
+    pub download: Option<DownloadDefinition>,
+
+    /// npm package to install globally instead of downloading a binary
+    pub npm: Option<String>,
+
+    /// Name of the downloaded binary, without `.exe`
+    pub binary: Option<String>,
+
+    /// Where the binary is installed; `${HOME}/.<name>/bin` by default
+    pub install_dir: Option<String>,
+
+    /// Config files to deploy (`[[config]]`)
+    #[serde(default)]
+    pub config: Vec<ConfigDefinition>,
+
+    /// Marketplace extensions, as `publisher.name` or `publisher.name@1.2.3`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+
+    /// Environment variables to set, which may use placeholders
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// What must be on the machine besides what the tool implies: Node.js
+    /// for an npm package and VS Code for extensions
+    #[serde(default)]
+    pub requires: RequiresDefinition,
+}
+
+/// The `[requires]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequiresDefinition {
+    pub vscode: Option<Requirement>,
+    pub git: bool,
+    pub node: Option<Requirement>,
+    /// Free space in the home directory, in megabytes
+    pub disk_space_mb: Option<u64>,
+    /// Other programs (`[[requires.command]]`)
+    pub command: Vec<CommandRequirement>,
+}
+
+/// `true` for any version, or the minimum version as a string
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Requirement {
+    Any(bool),
+    AtLeast(String),
+}
+
+impl Requirement {
+    /// `None` if not required, otherwise the minimum version if there is one
+    fn min_version(&self) -> Option<Option<String>> {
+        match self {
+            Requirement::Any(false) => None,
+            Requirement::Any(true) => Some(None),
+            Requirement::AtLeast(version) => Some(Some(version.clone())),
+        }
+    }
+}
+
+/// A `[[requires.command]]` entry
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandRequirement {
+    pub name: String,
+    /// Arguments that make it print its version; `--version` by default
+    #[serde(default)]
+    pub version_args: Vec<String>,
+    pub min_version: Option<String>,
+}
+
+impl RequiresDefinition {
+    fn prerequisites(&self) -> Vec<Prerequisite> {
+        let mut prerequisites = Vec::new();
+        if let Some(min_version) = self.vscode.as_ref().and_then(Requirement::min_version) {
+            prerequisites.push(Prerequisite::VsCode { min_version });
+        }
+        if self.git {
+            prerequisites.push(Prerequisite::Git);
+        }
+        if let Some(min_version) = self.node.as_ref().and_then(Requirement::min_version) {
+            prerequisites.push(Prerequisite::Node { min_version });
+        }
+        for command in &self.command {
+            prerequisites.push(Prerequisite::Command {
+                name: command.name.clone(),
+                version_args: command.version_args.clone(),
+                min_version: command.min_version.clone(),
+            });
+        }
+        if let Some(megabytes) = self.disk_space_mb {
+            prerequisites.push(Prerequisite::DiskSpace {
+                bytes: megabytes * 1024 * 1024,
+            });
+        }
+        prerequisites
+    }
+}
+
+/// The `[download]` table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownloadDefinition {
+    /// URL of the binary, with `${VERSION}`, `${PLATFORM}` (e.g.
+    /// `darwin-arm64`), and `${BINARY}` filled in
+    pub url: String,
+
+    /// URL of a text file naming the latest version
+    pub latest_url: Option<String>,
+
+    /// URL of a file starting with the binary's SHA-256, filled in like `url`
+    pub sha256_url: Option<String>,
+}
+
+/// A `[[config]]` entry
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigDefinition {
+    /// Template, relative to `local/<name>/`
+    pub source: PathBuf,
+
+    /// Where it goes, which may use placeholders such as `${HOME}`
+    pub dest: String,
+
+    /// `merge` (JSON or YAML only), `copy`, or `keep`
+    #[serde(default)]
+    pub merge: FileStrategy,
+}
+
+/// Placeholders a download URL may use
+const URL_PLACEHOLDERS: [&str; 3] = ["${VERSION}", "${PLATFORM}", "${BINARY}"];
+
+impl ToolDefinition {
+    /// Read and check the definition at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let definition: ToolDefinition = toml::from_str(&content)
+            .with_context(|| format!("Invalid tool definition {}", path.display()))?;
+        definition
+            .validate()
+            .map_err(|e| anyhow!("Invalid tool definition {}: {}", path.display(), e))?;
+        Ok(definition)
+    }
+
+    /// What's wrong with the definition, naming the offending key
+    fn validate(&self) -> std::result::Result<(), String> {
+        for (key, name) in std::iter::once(("name".to_string(), &self.name)).chain(
+            self.aliases
+                .iter()
+                .enumerate()
+                .map(|(i, alias)| (format!("aliases[{}]", i), alias))
+                .chain(
+                    self.dependencies
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| (format!("dependencies[{}]", i), name)),
+                ),
+        ) {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                return Err(format!(
+                    "`{}` must be lowercase letters, digits, and dashes, not \"{}\"",
+                    key, name
+                ));
+            }
+        }
+
+        match (&self.download, &self.npm) {
+            (None, None) => return Err("set either `download` or `npm`".to_string()),
+            (Some(_), Some(_)) => return Err("`download` and `npm` can't both be set".to_string()),
+            (Some(download), None) => {
+                if self.binary.as_deref().is_none_or(str::is_empty) {
+                    return Err("`binary` is required with `download`".to_string());
+                }
+                check_url("download.url", &download.url)?;
+                if let Some(url) = &download.latest_url {
+                    check_url("download.latest_url", url)?;
+                }
+                if let Some(url) = &download.sha256_url {
+                    check_url("download.sha256_url", url)?;
+                }
+                if self.version.is_none() && download.latest_url.is_none() {
+                    return Err(
+                        "`download.latest_url` is required when `version` isn't set".to_string()
+                    );
+                }
+            }
+            (None, Some(package)) => {
+                if package.trim().is_empty() {
+                    return Err("`npm` must name a package".to_string());
+                }
+                if self.install_dir.is_some() {
+                    return Err("`install_dir` doesn't apply to `npm` packages".to_string());
+                }
+            }
+        }
+
+        if self
+            .install_dir
+            .as_deref()
+            .is_some_and(|dir| dir.contains("${INSTALL_DIR}"))
+        {
+            return Err("`install_dir` can't use ${INSTALL_DIR}".to_string());
+        }
+
+        for (i, file) in self.config.iter().enumerate() {
+            let relative = file
+                .source
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+            if file.source.as_os_str().is_empty() || !relative {
+                return Err(format!(
+                    "`config[{}].source` must be a path inside local/{}/",
+                    i, self.name
+                ));
+            }
+            if file.dest.trim().is_empty() {
+                return Err(format!("`config[{}].dest` must not be empty", i));
+            }
+            let mergeable = file
+                .source
+                .extension()
+                .is_some_and(|e| ["json", "yaml", "yml"].iter().any(|ext| e == *ext));
+            if file.merge == FileStrategy::Merge && !mergeable {
+                return Err(format!(
+                    "`config[{}].merge` can only be \"merge\" for JSON or YAML files; \
+                     use \"copy\" or \"keep\" for {}",
+                    i,
+                    file.source.display()
+                ));
+            }
+        }
+
+        for (i, extension) in self.extensions.iter().enumerate() {
+            let id = extension.split('@').next().unwrap_or_default();
+            if !id.contains('.') || id.starts_with('.') || id.ends_with('.') {
+                return Err(format!(
+                    "`extensions[{}]` must be a marketplace ID such as publisher.name, not \"{}\"",
+                    i, extension
+                ));
+            }
+        }
+
+        for (key, requirement) in [
+            ("vscode", &self.requires.vscode),
+            ("node", &self.requires.node),
+        ] {
+            if let Some(Requirement::AtLeast(version)) = requirement {
+                if !version.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(format!(
+                        "`requires.{}` must be true or a version such as \"18\", not \"{}\"",
+                        key, version
+                    ));
+                }
+            }
+        }
+        for (i, command) in self.requires.command.iter().enumerate() {
+            if command.name.trim().is_empty() {
+                return Err(format!("`requires.command[{}].name` must not be empty", i));
+            }
+        }
+
+        for name in self.env.keys() {
+            if name.is_empty() || name.contains('=') || name.contains(char::is_whitespace) {
+                return Err(format!("`env` has an invalid variable name \"{}\"", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that `url`, the value of `key`, is one downloads can fetch
+fn check_url(key: &str, url: &str) -> std::result::Result<(), String> {
+    let mut rest = url.to_string();
+    for placeholder in URL_PLACEHOLDERS {
+        rest = rest.replace(placeholder, "x");
+    }
+    if let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .map_or(rest.len(), |end| start + end + 1);
+        return Err(format!(
+            "`{}` uses unknown placeholder {}; only ${{VERSION}}, ${{PLATFORM}}, and \
+             ${{BINARY}} are filled in",
+            key,
+            &rest[start..end]
+        ));
+    }
+    match reqwest::Url::parse(&rest) {
+        Ok(parsed) if ["http", "https", "file"].contains(&parsed.scheme()) => Ok(()),
+        _ => Err(format!(
+            "`{}` must be an http, https, or file URL, not \"{}\"",
+            key, url
+        )),
+    }
+}
+
+/// The definitions in `dirs`, in order, with a later definition of the same
+/// name replacing an earlier one. Files that can't be read or fail
+/// validation are reported and skipped.
+pub fn discover(dirs: &[PathBuf]) -> Vec<ToolDefinition> {
+    let mut definitions: Vec<ToolDefinition> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+            .collect();
+        files.sort();
+
+        for path in files {
+            match ToolDefinition::load(&path) {
+                Ok(definition) => {
+                    definitions.retain(|d| d.name != definition.name);
+                    definitions.push(definition);
+                }
+                Err(e) => reporter().notice(format!("Skipping tool: {:#}", e)),
+            }
+        }
+    }
+    definitions
+}
+
+/// The definitions in `local_dir/tools.d` and then the user config
+/// directory's `tools.d`, read once per run
+pub fn definitions(local_dir: &Path) -> &'static [ToolDefinition] {
+    static DEFINITIONS: OnceLock<Vec<ToolDefinition>> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| {
+        let mut dirs = vec![local_dir.join(TOOLS_DIR)];
+        if let Some(config) = config::UserConfig::path() {
+            dirs.extend(config.parent().map(|dir| dir.join(TOOLS_DIR)));
+        }
+        discover(&dirs)
+    })
+}
+
+/// A tool installed by interpreting its [`ToolDefinition`]
+pub struct DeclaredTool {
+    definition: ToolDefinition,
+    local_dir: PathBuf,
+    offline: bool,
+    force_extensions: bool,
+    force: bool,
+    edited_keys: EditedKeys,
+    link: bool,
+    skip_cert_import: bool,
+    force_path: bool,
+    paths: PlatformPaths,
+    editor: Option<Editor>,
+    profile: Option<String>,
+    keep_backups: usize,
+    assume_yes: bool,
+    default_version: Option<String>,
+    dependencies: Vec<String>,
+    node_version: String,
+    host: Host,
+}
+
+impl DeclaredTool {
+    pub fn new(definition: ToolDefinition, options: &ToolOptions) -> Self {
+        let mut dependencies = definition.dependencies.clone();
+        dependencies.extend(options.dependencies.iter().cloned());
+        Self {
+            definition,
+            local_dir: options.local_dir.clone(),
+            offline: options.offline,
+            force_extensions: options.force_extensions,
+            force: options.force,
+            edited_keys: options.edited_keys,
+            link: options.link,
+            skip_cert_import: options.skip_cert_import,
+            force_path: options.force_path,
+            paths: options.paths.clone(),
+            editor: options.editor,
+            profile: options.profile.clone(),
+            keep_backups: options.keep_backups,
+            assume_yes: options.assume_yes,
+            default_version: options.default_version.clone(),
+            dependencies,
+            node_version: options.node_version.clone(),
+            host: options.host.clone(),
+        }
+    }
+
+    fn install_dir(&self) -> PathBuf {
+        let default = format!("${{HOME}}/.{}/bin", self.definition.name);
+        let dir = self.definition.install_dir.as_deref().unwrap_or(&default);
+        PathBuf::from(config::expand_placeholders(
+            &self.paths,
+            Path::new(""),
+            dir,
+            "install_dir",
+        ))
+    }
+
+    /// File name of the binary on this platform
+    fn binary_name(&self) -> String {
+        let binary = self.definition.binary.as_deref().unwrap_or_default();
+        if cfg!(windows) {
+            format!("{}.exe", binary)
+        } else {
+            binary.to_string()
+        }
+    }
+
+    fn binary_path(&self) -> PathBuf {
+        self.install_dir().join(self.binary_name())
+    }
+
+    /// Marker next to the binary recording the version installed, named for
+    /// the tool as the install directory may be shared
+    fn version_file(&self) -> PathBuf {
+        self.install_dir()
+            .join(format!(".code-assist-{}-version", self.definition.name))
+    }
+
+    fn deploy_options(&self) -> Result<config::DeployOptions> {
+        let tool_dir = self.profile_dir()?.join(&self.definition.name);
+        Ok(config::DeployOptions {
+            keep_backups: self.keep_backups,
+            assume_yes: self.assume_yes,
+            force: self.force,
+            edited_keys: self.edited_keys,
+            editor: self.editor,
+            install_dir: self.install_dir(),
+            tool: self.name().to_string(),
+            profile: self.profile()?,
+            link: self.link,
+            skip_cert_import: self.skip_cert_import,
+            files: self
+                .definition
+                .config
+                .iter()
+                .map(|file| config::ConfigFile {
+                    source: tool_dir.join(&file.source),
+                    dest: file.dest.clone(),
+                    strategy: file.merge,
+                })
+                .collect(),
+            env: self
+                .definition
+                .env
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            host: self.host.clone(),
+        })
+    }
+
+    /// Profile to deploy: the one asked for, else the one last deployed,
+    /// else the default
+    fn profile(&self) -> Result<String> {
+        if let Some(profile) = &self.profile {
+            return Ok(profile.clone());
+        }
+        Ok(self
+            .deployed_profile()?
+            .unwrap_or_else(|| config::DEFAULT_PROFILE.to_string()))
+    }
+
+    fn profile_dir(&self) -> Result<PathBuf> {
+        config::profile_dir(&self.local_dir, &self.profile()?)
+    }
+
+    fn config_source(&self) -> PathBuf {
+        self.profile_dir().unwrap_or_else(|e| {
+            tracing::debug!("could not resolve profile: {:#}", e);
+            self.local_dir.clone()
+        })
+    }
+
+    /// `template` from the `[download]` table with its placeholders filled in
+    fn download_url(&self, template: &str, version: &str) -> String {
+        template
+            .replace("${VERSION}", version)
+            .replace("${PLATFORM}", platform::get_platform_id())
+            .replace("${BINARY}", &self.binary_name())
+    }
+
+    /// The version to install: the one asked for, the one configured, the
+    /// definition's, or else the latest
+    fn resolve_version(&self, requested: Option<&str>) -> Result<Option<String>> {
+        let pinned = requested
+            .or(self.default_version.as_deref())
+            .or(self.definition.version.as_deref());
+        if let Some(version) = pinned {
+            reporter().success(format!("Version: {} (pinned)", style(version).cyan()));
+            return Ok(Some(version.to_string()));
+        }
+        let latest = self.latest_version()?;
+        if let Some(version) = &latest {
+            reporter().success(format!("Latest version: {}", style(version).cyan()));
+        }
+        Ok(latest)
+    }
+
+    /// The latest published version; `None` for an npm package, which npm
+    /// resolves itself
+    fn latest_version(&self) -> Result<Option<String>> {
+        let Some(download) = &self.definition.download else {
+            return Ok(None);
+        };
+        let local = self.local_dir.join(&self.definition.name).join("latest");
+        let remote = match (&download.latest_url, self.offline) {
+            (Some(url), false) => Some(download::fetch_text(url)),
+            _ => None,
+        };
+        match remote {
+            Some(Ok(text)) => return Ok(Some(text.trim().to_string())),
+            Some(Err(e)) if !local.exists() => {
+                return Err(e.context(Failure::Download(format!(
+                    "Could not get the latest {} version",
+                    self.display_name()
+                ))))
+            }
+            Some(Err(e)) => reporter().warn(format!("{:#}; using local fallback", e)),
+            None => {}
+        }
+        let text = std::fs::read_to_string(&local).map_err(|_| {
+            Failure::Download(format!(
+                "No version to install: pass --version, or put the latest version in {}",
+                local.display()
+            ))
+        })?;
+        Ok(Some(text.trim().to_string()))
+    }
+
+    fn npm_command() -> std::process::Command {
+        std::process::Command::new(if cfg!(windows) { "npm.cmd" } else { "npm" })
+    }
+
+    /// Version of the globally installed npm package, if it is installed
+    fn npm_installed_version(&self, package: &str) -> Option<String> {
+        let output = self
+            .host
+            .runner
+            .output(Self::npm_command().args(["ls", "--global", "--depth=0", "--json", package]))
+            .ok()?;
+        let listing: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+        listing["dependencies"][package]["version"]
+            .as_str()
+            .map(str::to_string)
+    }
+
+    /// Run npm with `args`, failing with what it printed
+    fn run_npm(&self, args: &[&str]) -> Result<()> {
+        let output = self
+            .host
+            .runner
+            .output(Self::npm_command().args(args))
+            .context("Failed to run npm; is Node.js installed?")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("npm {} failed: {}", args.join(" "), stderr.trim()));
+        }
+        Ok(())
+    }
+
+    fn install_npm(&self, package: &str, version: Option<&str>, dry_run: bool) -> Result<()> {
+        let spec = match version {
+            Some(version) => format!("{}@{}", package, version),
+            None => package.to_string(),
+        };
+        if dry_run {
+            plan::step(format!("Run 'npm install --global {}'", spec));
+            return Ok(());
+        }
+        reporter().info(format!("Installing {} with npm...", style(&spec).cyan()));
+        self.run_npm(&["install", "--global", &spec])?;
+        reporter().success(format!("Installed {}", spec));
+        cancel::committed(format!("Installed {} with npm", spec));
+        Ok(())
+    }
+
+    fn install_binary(
+        &self,
+        download: &DownloadDefinition,
+        version: &str,
+        dry_run: bool,
+    ) -> Result<()> {
+        let url = self.download_url(&download.url, version);
+        let binary_path = self.binary_path();
+        if dry_run {
+            plan::step(format!("Download {} to {}", url, binary_path.display()));
+            return Ok(());
+        }
+
+        let sha256 = match &download.sha256_url {
+            Some(template) if !self.offline => {
+                let text = download::fetch_text(&self.download_url(template, version))?;
+                let checksum = text.split_whitespace().next().unwrap_or_default();
+                Some(checksum.to_lowercase())
+            }
+            _ => None,
+        };
+        let local_path = download::local_binary_path(
+            &self.local_dir.join(&self.definition.name),
+            version,
+            platform::get_platform_id(),
+            &self.binary_name(),
+        );
+
+        let install_dir = self.install_dir();
+        std::fs::create_dir_all(&install_dir)
+            .with_context(|| format!("Failed to create {}", install_dir.display()))?;
+        download::download_file(
+            &url,
+            &local_path,
+            &binary_path,
+            sha256.as_deref(),
+            self.offline,
+        )?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = std::fs::metadata(&binary_path)?.permissions();
+            perms.set_mode(0o755);
+            std::fs::set_permissions(&binary_path, perms)?;
+        }
+        if let Err(e) = platform::clear_quarantine(&binary_path) {
+            reporter().warn(format!("{:#}", e));
+        }
+
+        std::fs::write(self.version_file(), version)
+            .context("Failed to record installed version")?;
+        reporter().success(format!(
+            "Installed to {}",
+            style(install_dir.display()).cyan()
+        ));
+        cancel::committed(format!(
+            "Installed {} {} to {}",
+            self.display_name(),
+            version,
+            install_dir.display()
+        ));
+        Ok(())
+    }
+
+    /// Put the install directory on PATH, recording it for uninstall
+    /// The extensions the definition lists, each `id` or `id@version`
+    fn wanted_extensions(&self, profile_dir: &Path) -> Result<Vec<config::WantedExtension>> {
+        let mut wanted = Vec::new();
+        for spec in &self.definition.extensions {
+            let (id, version) = match spec.split_once('@') {
+                Some((id, version)) => (id, Some(version)),
+                None => (spec.as_str(), None),
+            };
+            wanted.push(config::marketplace_extension(profile_dir, id, version)?);
+        }
+        Ok(wanted)
+    }
+
+    fn extension_options(&self) -> config::ExtensionOptions {
+        config::ExtensionOptions {
+            editor: self.editor,
+            offline: self.offline,
+            force: self.force_extensions,
+            host: self.host.clone(),
+        }
+    }
+
+    /// Whether `dir` is on the PATH new commands run with
+    fn is_on_process_path(&self, dir: &Path) -> bool {
+        doctor::is_in(&self.host.environment.process_path(), dir)
+    }
+
+    fn add_to_path(&self, dry_run: bool) -> Result<()> {
+        let install_dir = self.install_dir();
+        let added = platform::path_str(&install_dir).and_then(|dir| {
+            self.host
+                .environment
+                .add_to_path(dir, self.force_path, dry_run)
+                .map(|()| dir)
+        });
+        match added {
+            Err(e) => reporter().warn(format!("Could not add to PATH: {}", e)),
+            Ok(_) if dry_run => {}
+            Ok(entry) => {
+                reporter().success(format!("Added to PATH: {}", install_dir.display()));
+                cancel::committed(format!("Added {} to PATH", install_dir.display()));
+                let paths = &self.paths;
+                let mut state = config::load_state(&self.config_source(), paths, self.name())?;
+                state.record_path_entry(entry);
+                state.save(paths, self.name())?;
+                if !self.is_on_process_path(&install_dir) {
+                    self.host.environment.add_to_process_path(&install_dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn uninstall_binary(&self, dry_run: bool) -> Result<()> {
+        let binary_path = self.binary_path();
+        if !binary_path.exists() {
+            reporter().skip(format!("{} is not installed", self.display_name()));
+        } else if dry_run {
+            plan::step(format!("Remove {}", binary_path.display()));
+        } else {
+            std::fs::remove_file(&binary_path)
+                .with_context(|| format!("Failed to remove {}", binary_path.display()))?;
+            std::fs::remove_file(self.version_file()).ok();
+            // Only a directory left empty, which the default one will be
+            std::fs::remove_dir(self.install_dir()).ok();
+            reporter().success(format!("{} uninstalled", self.display_name()));
+        }
+
+        let install_dir = self.install_dir();
+        let install_dir = platform::path_str(&install_dir)?;
+        match self.host.environment.remove_from_path(install_dir, dry_run) {
+            Ok(()) if !dry_run => reporter().success("Removed from PATH"),
+            Ok(()) => {}
+            Err(e) => reporter().warn(format!("Could not remove from PATH: {}", e)),
+        }
+        if let Some(mut state) = State::load(&self.paths, self.name())? {
+            if !dry_run && state.path_entries.iter().any(|p| p == install_dir) {
+                state.forget_path_entry(install_dir);
+                state.save(&self.paths, self.name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Tool for DeclaredTool {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn display_name(&self) -> &str {
+        self.definition
+            .display_name
+            .as_deref()
+            .unwrap_or(&self.definition.name)
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        self.definition.aliases.iter().map(String::as_str).collect()
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        self.dependencies.iter().map(String::as_str).collect()
+    }
+
+    fn prerequisites(&self) -> Vec<Prerequisite> {
+        let mut implied = Vec::new();
+        if self.definition.npm.is_some() {
+            implied.push(Prerequisite::Node {
+                min_version: Some(self.node_version.clone()),
+            });
+            implied.push(Prerequisite::Npm);
+        }
+        if !self.definition.extensions.is_empty() {
+            implied.push(Prerequisite::VsCode { min_version: None });
+        }
+        prerequisites::union([implied, self.definition.requires.prerequisites()])
+    }
+
+    fn is_installed(&self) -> Result<bool> {
+        Ok(match &self.definition.npm {
+            Some(package) => self.npm_installed_version(package).is_some(),
+            None => self.binary_path().exists(),
+        })
+    }
+
+    fn installed_version(&self) -> Result<Option<String>> {
+        if let Some(package) = &self.definition.npm {
+            return Ok(self.npm_installed_version(package));
+        }
+        match std::fs::read_to_string(self.version_file()) {
+            Ok(version) => Ok(Some(version.trim().to_string())),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn deployed_profile(&self) -> Result<Option<String>> {
+        Ok(State::load(&self.paths, self.name())?.and_then(|state| state.profile))
+    }
+
+    fn install(&self, version: Option<&str>, _channel: ChannelChoice, dry_run: bool) -> Result<()> {
+        reporter().heading(format!("Installing {}...", self.display_name()));
+        reporter().blank();
+        reporter().info(format!(
+            "Definition: {} from {}",
+            style(self.name()).cyan(),
+            TOOLS_DIR
+        ));
+        reporter().blank();
+
+        events::step("version");
+        let version = self.resolve_version(version)?;
+        if let Some(version) = &version {
+            events::emit(Event::VersionChosen {
+                version: version.to_string(),
+                channel: download::Channel::Stable,
+            });
+        }
+
+        let current = self.installed_version()?;
+        let installed = self.is_installed()?;
+        if !self.force && installed && version.is_some() && current == version {
+            reporter().success(format!(
+                "{} {} is already installed",
+                self.display_name(),
+                style(version.as_deref().unwrap_or_default()).cyan()
+            ));
+            reporter().detail("Pass --force to reinstall it");
+        } else {
+            events::step("download");
+            match (&self.definition.download, &self.definition.npm) {
+                (Some(download), _) => {
+                    let version = version.as_deref().unwrap_or_default();
+                    self.install_binary(download, version, dry_run)?;
+                }
+                (None, Some(package)) => self.install_npm(package, version.as_deref(), dry_run)?,
+                (None, None) => unreachable!("validated definitions name a source"),
+            }
+        }
+
+        cancel::check()?;
+        reporter().blank();
+        self.configure(dry_run)?;
+
+        if self.definition.download.is_some() {
+            cancel::check()?;
+            events::step("path");
+            self.add_to_path(dry_run)?;
+        }
+        Ok(())
+    }
+
+    fn plan(&self, version: Option<&str>, _channel: ChannelChoice) -> Result<InstallPlan> {
+        let pinned = version
+            .or(self.default_version.as_deref())
+            .or(self.definition.version.as_deref());
+        let version = match pinned {
+            Some(version) => Some(version.to_string()),
+            None => self.latest_version()?,
+        };
+        let up_to_date = !self.force
+            && version.is_some()
+            && self.is_installed()?
+            && self.installed_version()? == version;
+        let has_binary = self.definition.download.is_some();
+        let mut plan = InstallPlan {
+            version,
+            up_to_date,
+            binary: (has_binary && !up_to_date).then(|| self.binary_path()),
+            ..InstallPlan::default()
+        };
+
+        let profile_dir = self.profile_dir()?;
+        plan.extensions = config::extensions_to_install(
+            &self.wanted_extensions(&profile_dir)?,
+            &self.extension_options(),
+        );
+        plan.deploy(
+            config::preview_deploy(&profile_dir, &self.paths, &self.deploy_options()?)?,
+            self.skip_cert_import,
+        );
+        let install_dir = self.install_dir();
+        if has_binary && !self.is_on_process_path(&install_dir) {
+            plan.path_entries.push(install_dir);
+        }
+        Ok(plan)
+    }
+
+    fn update(&self, _channel: ChannelChoice, dry_run: bool) -> Result<bool> {
+        if !self.is_installed()? {
+            return Err(anyhow!(
+                "{} is not installed. Run 'code-assist install --tool {}' first.",
+                self.display_name(),
+                self.name()
+            ));
+        }
+        reporter().heading(format!("Updating {}...", self.display_name()));
+        reporter().blank();
+
+        if let Some(package) = &self.definition.npm {
+            if dry_run {
+                plan::step(format!("Run 'npm update --global {}'", package));
+                return Ok(true);
+            }
+            let before = self.installed_version()?;
+            self.run_npm(&["update", "--global", package])?;
+            let after = self.installed_version()?;
+            return Ok(before != after);
+        }
+
+        let current = self.installed_version()?;
+        let Some(latest) = self.latest_version()? else {
+            return Ok(false);
+        };
+        if current.as_deref() == Some(latest.as_str()) {
+            reporter().success(format!("Already up to date ({})", style(&latest).cyan()));
+            return Ok(false);
+        }
+        self.install(Some(&latest), ChannelChoice::default(), dry_run)?;
+        Ok(true)
+    }
+
+    fn uninstall(&self, purge: bool, dry_run: bool) -> Result<()> {
+        reporter().heading(format!("Uninstalling {}...", self.display_name()));
+        reporter().blank();
+
+        match &self.definition.npm {
+            Some(package) if dry_run => {
+                plan::step(format!("Run 'npm uninstall --global {}'", package));
+            }
+            Some(package) if self.npm_installed_version(package).is_some() => {
+                self.run_npm(&["uninstall", "--global", package])?;
+                reporter().success(format!("{} uninstalled", self.display_name()));
+            }
+            Some(_) => reporter().skip(format!("{} is not installed", self.display_name())),
+            None => self.uninstall_binary(dry_run)?,
+        }
+
+        config::uninstall_extensions(&*self.host.runner, &self.paths, self.name(), dry_run)
+            .context(Failure::Configuration(
+                "Failed to uninstall extensions".to_string(),
+            ))?;
+
+        if purge {
+            super::purge_configuration(
+                &self.config_source(),
+                &self.paths,
+                self.name(),
+                &self.host,
+                dry_run,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn configure(&self, dry_run: bool) -> Result<()> {
+        let profile_dir = self.profile_dir()?;
+
+        if !self.definition.extensions.is_empty() {
+            events::step("extensions");
+            reporter().info("Installing VS Code extensions...");
+            reporter().blank();
+            config::install_extension_list(
+                &self.wanted_extensions(&profile_dir)?,
+                &profile_dir,
+                &self.paths,
+                self.name(),
+                &self.extension_options(),
+                dry_run,
+            )?;
+            reporter().blank();
+        }
+
+        events::step("configuration");
+        reporter().info("Deploying configurations...");
+        reporter().blank();
+        config::deploy_configs(&profile_dir, &self.paths, &self.deploy_options()?, dry_run)
+    }
+
+    fn check_configuration(&self) -> Result<Vec<config::Drift>> {
+        config::check_configs(&self.profile_dir()?, &self.paths, &self.deploy_options()?)
+    }
+
+    fn settings_backups(&self) -> Result<Vec<Backup>> {
+        config::backup::list(&self.paths, self.name())
+    }
+
+    fn restore_settings(&self, backup: &Backup, dry_run: bool) -> Result<()> {
+        config::backup::restore(&self.paths, self.name(), backup, self.keep_backups, dry_run)
+    }
+
+    fn status(&self) -> Result<ToolStatus> {
+        let installed = self.is_installed()?;
+        let paths = &self.paths;
+        Ok(ToolStatus {
+            name: self.name().to_string(),
+            display_name: self.display_name().to_string(),
+            installed,
+            installed_version: if installed {
+                self.installed_version()?
+            } else {
+                None
+            },
+            install_path: (installed && self.definition.download.is_some())
+                .then(|| self.binary_path()),
+            on_path: self.is_on_process_path(&self.install_dir()),
+            profile: self.deployed_profile().unwrap_or_default(),
+            deployed_files: config::deployed_files(paths, self.name()).unwrap_or_else(|e| {
+                tracing::debug!("could not read deployment state: {:#}", e);
+                Vec::new()
+            }),
+            config_drift: config::config_drift(paths, self.name(), &*self.host.environment)
+                .unwrap_or_else(|e| {
+                    tracing::debug!("could not read deployment state: {:#}", e);
+                    Vec::new()
+                }),
+            ..ToolStatus::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> std::result::Result<ToolDefinition, String> {
+        let definition: ToolDefinition = toml::from_str(toml).map_err(|e| e.to_string())?;
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    const BINARY: &str = r#"
+        name = "acme"
+        binary = "acme"
+        version = "1.0.0"
+        [download]
+        url = "https://example.com/${VERSION}/${PLATFORM}/${BINARY}"
+    "#;
+
+    #[test]
+    fn accepts_a_binary_and_an_npm_tool() {
+        let binary = parse(BINARY).unwrap();
+        assert_eq!(binary.config.len(), 0);
+
+        let npm = parse(
+            r#"
+            name = "acme-js"
+            npm = "@acme/cli"
+            extensions = ["acme.vscode@2.0.0"]
+            [[config]]
+            source = "config.yaml"
+            dest = "${HOME}/.acme/config.yaml"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(npm.config[0].merge, FileStrategy::Merge);
+    }
+
+    #[test]
+    fn unknown_keys_are_named_with_their_line() {
+        let error = parse(&format!("{}\nbinnary = \"x\"", BINARY)).unwrap_err();
+        assert!(error.contains("unknown field `binnary`"), "{}", error);
+        assert!(error.contains("line 8"), "{}", error);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_key() {
+        let cases = [
+            (
+                BINARY.replace("name = \"acme\"", "name = \"Acme\""),
+                "`name`",
+            ),
+            (BINARY.replace("binary = \"acme\"", ""), "`binary`"),
+            (
+                BINARY.replace("version = \"1.0.0\"", ""),
+                "`download.latest_url`",
+            ),
+            (BINARY.replace("https://", "ftp://"), "`download.url`"),
+            (
+                BINARY.replace("${BINARY}", "${ARCH}"),
+                "unknown placeholder ${ARCH}",
+            ),
+            (format!("npm = \"acme\"\n{}", BINARY), "`npm` can't both"),
+            (
+                format!("extensions = [\"acme\"]\n{}", BINARY),
+                "`extensions[0]`",
+            ),
+            (
+                format!(
+                    "{}\n[[config]]\nsource = \"CLAUDE.md\"\ndest = \"x\"",
+                    BINARY
+                ),
+                "`config[0].merge`",
+            ),
+            (
+                format!(
+                    "{}\n[[config]]\nsource = \"../secrets.json\"\ndest = \"x\"",
+                    BINARY
+                ),
+                "`config[0].source`",
+            ),
+        ];
+        for (toml, expected) in cases {
+            let error = parse(&toml).unwrap_err();
+            assert!(error.contains(expected), "{} in: {}", expected, error);
+        }
+    }
+
+    #[test]
+    fn later_directories_replace_earlier_definitions() {
+        let local = tempfile::TempDir::new().unwrap();
+        let user = tempfile::TempDir::new().unwrap();
+        std::fs::write(local.path().join("acme.toml"), BINARY).unwrap();
+        std::fs::write(local.path().join("broken.toml"), "name = ").unwrap();
+        std::fs::write(
+            user.path().join("mine.toml"),
+            BINARY.replace("1.0.0", "2.0.0"),
+        )
+        .unwrap();
+
+        let found = discover(&[local.path().to_path_buf(), user.path().to_path_buf()]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].version.as_deref(), Some("2.0.0"));
+    }
+}
@@ -1,31 +1,619 @@
 mod claude_code;
+mod conflicts;
+mod continue_dev;
+mod declared;
+mod removal;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    self, DeployPreview, DeployedFile, Drift, EditedKeys, PlannedFile, ToolConfig,
+};
+use crate::doctor::Diagnostic;
+use crate::download::{Channel, VersionIndex};
+use crate::error::Failure;
+use crate::output::reporter;
+use crate::platform::{self, Editor, Host, PlatformPaths};
+use crate::prerequisites::{self, Prerequisite};
+use crate::state::Backup;
 
 pub use claude_code::ClaudeCode;
+pub use continue_dev::Continue;
+
+/// Options shared by every tool, resolved from the command line
+#[derive(Debug, Clone)]
+pub struct ToolOptions {
+    /// Directory holding fallback binaries, VSIX files, and config templates
+    pub local_dir: PathBuf,
+
+    /// Skip all network access and use local artifacts only
+    pub offline: bool,
+
+    /// Don't check for free disk space before downloading
+    pub skip_space_check: bool,
+
+    /// Reinstall extensions that are already up to date
+    pub force_extensions: bool,
+
+    /// Overwrite deployed files that were changed since they were deployed,
+    /// and reinstall a version that is already installed
+    pub force: bool,
+
+    /// What a deploy does with settings keys changed since the last one
+    pub edited_keys: EditedKeys,
+
+    /// Link files that aren't merged to their templates instead of copying
+    pub link: bool,
+
+    /// Leave the system trust store alone when deploying certificates
+    pub skip_cert_import: bool,
+
+    /// Add to the Windows user PATH even past its length limit
+    pub force_path: bool,
+
+    /// Editor to target instead of every installed one
+    pub editor: Option<Editor>,
+
+    /// Configuration profile asked for with `--profile`; without one, the
+    /// profile last deployed is used again
+    pub profile: Option<String>,
+
+    /// Settings backups to keep; zero turns backups off
+    pub keep_backups: usize,
+
+    /// Oldest Node.js that npm-installed tools accept
+    pub node_version: String,
+
+    /// Install machine-wide for every user (`--system`)
+    pub system: bool,
+
+    /// Where per-user files go, resolved once up front
+    pub paths: PlatformPaths,
+
+    /// Don't ask before changing the user's settings (`--yes`)
+    pub assume_yes: bool,
+
+    /// Version to install when none is requested on the command line
+    pub default_version: Option<String>,
+
+    /// Channel for new installs when none is requested on the command line
+    pub default_channel: Option<Channel>,
+
+    /// Tools to install first, from the config file
+    pub dependencies: Vec<String>,
+
+    /// Per-tool overrides from the user config file, keyed by tool name
+    pub overrides: BTreeMap<String, ToolConfig>,
+
+    /// Runs commands and changes the user's environment
+    pub host: Host,
+}
+
+impl ToolOptions {
+    /// Options for one tool, with its config file overrides applied
+    pub fn for_tool(&self, name: &str) -> ToolOptions {
+        let mut options = self.clone();
+        if let Some(tool) = self.overrides.get(name) {
+            if let Some(local_dir) = &tool.local_dir {
+                options.local_dir = local_dir.clone();
+            }
+            options.default_version = tool.version.clone();
+            options.default_channel = tool.channel;
+            options.dependencies = tool.dependencies.clone();
+        }
+        options
+    }
+}
+
+/// Resolve the local artifacts directory. An explicit override must exist;
+/// otherwise look for `local/` next to the executable, then in the current
+/// directory.
+pub fn resolve_local_dir(override_dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = override_dir {
+        if !dir.is_dir() {
+            return Err(anyhow!(
+                "Local artifacts directory {} does not exist",
+                dir.display()
+            ));
+        }
+        return Ok(dir.to_path_buf());
+    }
+
+    // Look for local directory relative to executable or current directory
+    let beside_exe = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|dir| dir.join("local")));
+    if let Some(dir) = beside_exe.filter(|dir| dir.exists()) {
+        return Ok(dir);
+    }
+    let current_dir = std::env::current_dir().context(
+        "Could not determine the current directory; pass --local-dir to say where the artifacts are",
+    )?;
+    Ok(current_dir.join("local"))
+}
+
+/// The release channel asked for on the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelChoice {
+    /// Channel from `--channel`, if given
+    pub requested: Option<Channel>,
+
+    /// Whether `--switch-channel` allows moving an install to another channel
+    pub allow_switch: bool,
+}
+
+/// Detailed state of a tool, reported by `status`
+#[derive(Debug, Default, Serialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub display_name: String,
+    pub installed: bool,
+    pub installed_version: Option<String>,
+    pub install_path: Option<PathBuf>,
+
+    /// Architecture the installed binary was built for, read from its header
+    pub binary_arch: Option<String>,
+
+    /// Channel the install follows, if the tool tracks one
+    pub channel: Option<Channel>,
+
+    /// Whether the install directory is on PATH in the current shell
+    pub on_path: bool,
+
+    /// Configuration profile last deployed, if the tool deploys any
+    pub profile: Option<String>,
+
+    /// Latest published version; `None` when offline or unreachable
+    pub latest_version: Option<String>,
+
+    /// VS Code extensions the tool deploys from its VSIX files
+    pub extensions: Vec<ExtensionStatus>,
+
+    /// NODE_EXTRA_CA_CERTS as seen by this process, if set
+    pub node_extra_ca_certs: Option<EnvFileStatus>,
+
+    /// Files copied or linked into place by the last deploy
+    pub deployed_files: Vec<DeployedFile>,
+
+    /// Ways the deployed configuration no longer matches what was recorded
+    pub config_drift: Vec<String>,
+
+    /// Versions with a verified binary for this platform in the downloads
+    /// cache, oldest first
+    pub cached_versions: Vec<String>,
+}
+
+/// A VS Code extension deployed by a tool
+#[derive(Debug, Serialize)]
+pub struct ExtensionStatus {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+}
+
+/// An environment variable that should point at a file
+#[derive(Debug, Serialize)]
+pub struct EnvFileStatus {
+    pub value: String,
+    pub exists: bool,
+}
+
+/// What `install` would change, shown before asking to go ahead
+#[derive(Debug, Default)]
+pub struct InstallPlan {
+    /// Version that would be installed, if the tool resolves one up front
+    pub version: Option<String>,
+
+    /// Whether that version is installed already, so only the configuration
+    /// would be deployed
+    pub up_to_date: bool,
+
+    /// Where the binary would be installed
+    pub binary: Option<PathBuf>,
+
+    /// Settings files that would be merged into, or created
+    pub settings: Vec<PlannedFile>,
+
+    /// Where certificates would be copied
+    pub certificates: Vec<PathBuf>,
+
+    /// Trust store the certificates would be imported into
+    pub trust_store: Option<&'static str>,
+
+    /// Environment variables that would be set
+    pub env_vars: Vec<String>,
+
+    /// Directories that would be added to PATH
+    pub path_entries: Vec<PathBuf>,
+
+    /// Extensions that would be installed, as `id` or `id@version`
+    pub extensions: Vec<(Editor, String)>,
+}
+
+impl InstallPlan {
+    /// Add what deploying the configuration would change
+    fn deploy(&mut self, preview: DeployPreview, skip_cert_import: bool) {
+        self.settings = preview.settings;
+        if !preview.certificates.is_empty() && !skip_cert_import {
+            self.trust_store = platform::trust_store_name();
+        }
+        self.certificates = preview.certificates;
+        self.env_vars = preview.env_vars;
+    }
+
+    /// Print the plan for `display_name`, one change per line
+    pub fn print(&self, display_name: &str) {
+        let out = reporter();
+        match &self.version {
+            Some(version) => out.heading(format!("{} {}:", display_name, version)),
+            None => out.heading(format!("{}:", display_name)),
+        }
+        if self.up_to_date {
+            out.info("- keep the installed binary, which is up to date");
+        }
+        if let Some(binary) = &self.binary {
+            out.info(format!("- install the binary to {}", binary.display()));
+        }
+        for file in &self.settings {
+            let action = if file.exists {
+                "merge settings into"
+            } else {
+                "create"
+            };
+            out.info(format!("- {} {}", action, file.path.display()));
+        }
+        for certificate in &self.certificates {
+            match self.trust_store {
+                Some(store) => out.info(format!(
+                    "  - copy certificate {} and trust it in {}",
+                    certificate.display(),
+                    store
+                )),
+                None => out.info(format!("- copy certificate {}", certificate.display())),
+            }
+        }
+        for name in &self.env_vars {
+            out.info(format!("- set {}", name));
+        }
+        for entry in &self.path_entries {
+            out.info(format!("- add {} to PATH", entry.display()));
+        }
+        for (editor, extension) in &self.extensions {
+            out.info(format!(
+                "  - install extension {} into {}",
+                extension, editor
+            ));
+        }
+    }
+}
 
 /// Trait for installable tools
 pub trait Tool {
     fn name(&self) -> &str;
     fn display_name(&self) -> &str;
+
+    /// Alternative names that resolve to this tool (e.g. `claude`)
+    fn aliases(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Names of the tools that must be installed before this one
+    fn dependencies(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// What must be on the machine before the tool can be installed
+    fn prerequisites(&self) -> Vec<Prerequisite> {
+        Prerequisite::defaults()
+    }
+
     fn is_installed(&self) -> Result<bool>;
-    fn install(&self) -> Result<()>;
-    fn uninstall(&self) -> Result<()>;
-    fn configure(&self) -> Result<()>;
+
+    /// Version recorded at install time, if the tool tracks one
+    fn installed_version(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Release channel recorded at install time, if the tool tracks one
+    fn installed_channel(&self) -> Result<Option<Channel>> {
+        Ok(None)
+    }
+
+    /// Configuration profile last deployed, if the tool deploys any
+    fn deployed_profile(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Versions published for the tool, for `versions`
+    fn available_versions(&self) -> Result<VersionIndex> {
+        Err(anyhow!(
+            "{} does not publish a version list",
+            self.display_name()
+        ))
+    }
+
+    /// Install the tool, pinned to `version` if given, otherwise the latest
+    /// on its channel. With `dry_run`, print the plan instead of changing
+    /// anything.
+    fn install(&self, version: Option<&str>, channel: ChannelChoice, dry_run: bool) -> Result<()>;
+
+    /// What `install` with the same arguments would change, without
+    /// changing anything
+    fn plan(&self, version: Option<&str>, channel: ChannelChoice) -> Result<InstallPlan> {
+        let _ = (version, channel);
+        Ok(InstallPlan::default())
+    }
+
+    /// Install the latest version on the tool's channel if it isn't installed
+    /// already. Returns whether anything was (or, with `dry_run`, would be)
+    /// updated.
+    fn update(&self, channel: ChannelChoice, dry_run: bool) -> Result<bool> {
+        let _ = (channel, dry_run);
+        Err(anyhow!("{} does not support update", self.display_name()))
+    }
+
+    /// Uninstall the tool. With `purge`, also remove its configuration and
+    /// environment changes.
+    fn uninstall(&self, purge: bool, dry_run: bool) -> Result<()>;
+
+    /// Restore a retained previous version, or `to` if given
+    fn rollback(&self, to: Option<&str>, dry_run: bool) -> Result<()> {
+        let _ = (to, dry_run);
+        Err(anyhow!("{} does not support rollback", self.display_name()))
+    }
+
+    fn configure(&self, dry_run: bool) -> Result<()>;
+
+    /// How the machine differs from what `configure` would deploy, for
+    /// `configure --check`; changes nothing
+    fn check_configuration(&self) -> Result<Vec<Drift>> {
+        Err(anyhow!(
+            "{} does not support configuration checks",
+            self.display_name()
+        ))
+    }
+
+    /// Backups of the settings files the tool deploys, newest first
+    fn settings_backups(&self) -> Result<Vec<Backup>> {
+        Err(anyhow!("{} does not back up settings", self.display_name()))
+    }
+
+    /// Copy a settings backup back over the current files
+    fn restore_settings(&self, backup: &Backup, dry_run: bool) -> Result<()> {
+        let _ = (backup, dry_run);
+        Err(anyhow!("{} does not back up settings", self.display_name()))
+    }
+
+    /// Detailed state for `status`. The default reports only what the basic
+    /// trait methods know.
+    fn status(&self) -> Result<ToolStatus> {
+        let installed = self.is_installed()?;
+        Ok(ToolStatus {
+            name: self.name().to_string(),
+            display_name: self.display_name().to_string(),
+            installed,
+            installed_version: if installed {
+                self.installed_version()?
+            } else {
+                None
+            },
+            ..ToolStatus::default()
+        })
+    }
+
+    /// Tool-specific health checks for `doctor`
+    fn diagnose(&self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
 }
 
-/// Get a tool by name
-pub fn get_tool(name: &str) -> Result<Box<dyn Tool>> {
-    match name {
-        "claude-code" => Ok(Box::new(ClaudeCode::new())),
-        _ => Err(anyhow!(
+/// Get a tool by name or alias. Matching ignores case and treats `-`, `_`,
+/// and spaces as insignificant, so `Claude_Code` and `claudecode` both work.
+pub fn get_tool(name: &str, options: &ToolOptions) -> Result<Box<dyn Tool>> {
+    let wanted = normalize_name(name);
+    let tools = list_tools(options);
+
+    let mut closest: Option<(usize, String)> = None;
+    for tool in &tools {
+        for candidate in std::iter::once(tool.name()).chain(tool.aliases()) {
+            let distance = edit_distance(&wanted, &normalize_name(candidate));
+            if closest.as_ref().is_none_or(|(best, _)| distance < *best) {
+                closest = Some((distance, tool.name().to_string()));
+            }
+        }
+    }
+
+    if let Some((0, matched)) = &closest {
+        return Ok(tools
+            .into_iter()
+            .find(|t| t.name() == matched)
+            .expect("matched tool is registered"));
+    }
+
+    // Only suggest names that are plausibly typos, not unrelated words
+    let message = match closest {
+        Some((distance, suggestion)) if distance <= (wanted.len() / 3).max(2) => {
+            format!("Unknown tool '{}'. Did you mean '{}'?", name, suggestion)
+        }
+        _ => format!(
             "Unknown tool: '{}'. Run 'code-assist list' to see available tools.",
             name
-        )),
+        ),
+    };
+    Err(Failure::UnknownTool(message).into())
+}
+
+/// List all available tools: the built-in ones, then those declared in
+/// `tools.d`. A declaration can't replace a built-in tool.
+pub fn list_tools(options: &ToolOptions) -> Vec<Box<dyn Tool>> {
+    let mut tools: Vec<Box<dyn Tool>> = vec![
+        Box::new(ClaudeCode::new(&options.for_tool("claude-code"))),
+        Box::new(Continue::new(&options.for_tool("continue"))),
+    ];
+    let builtin: Vec<String> = tools
+        .iter()
+        .flat_map(|tool| std::iter::once(tool.name()).chain(tool.aliases()))
+        .map(normalize_name)
+        .collect();
+    for definition in declared::definitions(&options.local_dir) {
+        if builtin.contains(&normalize_name(&definition.name)) {
+            tracing::debug!("{} is built in; ignoring its declaration", definition.name);
+            continue;
+        }
+        let options = options.for_tool(&definition.name);
+        tools.push(Box::new(declared::DeclaredTool::new(
+            definition.clone(),
+            &options,
+        )));
     }
+    tools
 }
 
-/// List all available tools
-pub fn list_tools() -> Vec<Box<dyn Tool>> {
-    vec![Box::new(ClaudeCode::new())]
+/// Remove a tool's deployed configuration, saying what was left alone
+fn purge_configuration(
+    local_dir: &Path,
+    paths: &PlatformPaths,
+    tool: &str,
+    host: &Host,
+    dry_run: bool,
+) -> Result<()> {
+    reporter().blank();
+    reporter().heading("Purging configuration...");
+    reporter().blank();
+
+    let left_in_place = config::purge_configs(local_dir, paths, tool, host, dry_run).context(
+        Failure::Configuration("Failed to remove configuration".to_string()),
+    )?;
+
+    if !left_in_place.is_empty() {
+        reporter().blank();
+        reporter().warn("Left in place (not set by code-assist, or changed since):");
+        for item in left_in_place {
+            reporter().info(format!("- {}", item));
+        }
+    }
+    Ok(())
+}
+
+/// The tools named in `roots` and everything they depend on, each after its
+/// dependencies. Fails on a dependency that isn't a known tool or on a cycle.
+pub fn with_dependencies(roots: &[&str], options: &ToolOptions) -> Result<Vec<Box<dyn Tool>>> {
+    let tools = list_tools(options);
+    let mut order = Vec::new();
+    for root in roots {
+        let index = find_tool(&tools, root)
+            .ok_or_else(|| Failure::UnknownTool(format!("Unknown tool: '{}'", root)))?;
+        visit_dependencies(&tools, index, &mut Vec::new(), &mut order)?;
+    }
+
+    let mut tools: Vec<Option<Box<dyn Tool>>> = tools.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|index| tools[index].take())
+        .collect())
+}
+
+/// Add `index` to `order` after its dependencies; `path` is the chain of
+/// tools being resolved, to report a cycle
+fn visit_dependencies(
+    tools: &[Box<dyn Tool>],
+    index: usize,
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<()> {
+    if order.contains(&index) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|&i| i == index) {
+        let cycle: Vec<&str> = path[start..]
+            .iter()
+            .chain(std::iter::once(&index))
+            .map(|&i| tools[i].name())
+            .collect();
+        return Err(Failure::Configuration(format!(
+            "Tool dependencies form a cycle: {}",
+            cycle.join(" -> ")
+        ))
+        .into());
+    }
+
+    path.push(index);
+    for dependency in tools[index].dependencies() {
+        let found = find_tool(tools, dependency).ok_or_else(|| {
+            Failure::UnknownTool(format!(
+                "{} depends on '{}', which is not a known tool",
+                tools[index].display_name(),
+                dependency
+            ))
+        })?;
+        visit_dependencies(tools, found, path, order)?;
+    }
+    path.pop();
+    order.push(index);
+    Ok(())
+}
+
+/// What any of `tools` requires, each thing once
+pub fn prerequisites_of(tools: &[Box<dyn Tool>]) -> Vec<Prerequisite> {
+    prerequisites::union(tools.iter().map(|tool| tool.prerequisites()))
+}
+
+/// Installed tools that depend on `tool`
+pub fn installed_dependents(tool: &dyn Tool, options: &ToolOptions) -> Vec<Box<dyn Tool>> {
+    let names: Vec<String> = std::iter::once(tool.name())
+        .chain(tool.aliases())
+        .map(normalize_name)
+        .collect();
+    list_tools(options)
+        .into_iter()
+        .filter(|other| {
+            other
+                .dependencies()
+                .iter()
+                .any(|dependency| names.contains(&normalize_name(dependency)))
+        })
+        .filter(|other| {
+            other.is_installed().unwrap_or_else(|e| {
+                tracing::debug!("could not check {}: {:#}", other.name(), e);
+                false
+            })
+        })
+        .collect()
+}
+
+/// Index of the tool with `name` as its name or an alias
+fn find_tool(tools: &[Box<dyn Tool>], name: &str) -> Option<usize> {
+    let wanted = normalize_name(name);
+    tools.iter().position(|tool| {
+        std::iter::once(tool.name())
+            .chain(tool.aliases())
+            .any(|candidate| normalize_name(candidate) == wanted)
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
 }
@@ -0,0 +1,132 @@
+//! What an uninstall removed and what it couldn't, so a file left behind is
+//! reported instead of ending in "uninstalled successfully".
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+use crate::output::events::{self, Event};
+use crate::output::reporter;
+use crate::platform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    File,
+    Dir,
+}
+
+/// A file or directory that couldn't be removed
+#[derive(Debug)]
+pub struct Failed {
+    pub path: PathBuf,
+    kind: Kind,
+    pub error: std::io::Error,
+}
+
+impl Failed {
+    /// Whether another process had it open
+    pub fn in_use(&self) -> bool {
+        platform::is_in_use(&self.error)
+    }
+}
+
+/// Every removal attempted, in order. Paths that were already gone count
+/// as neither removed nor failed.
+#[derive(Debug, Default)]
+pub struct Removals {
+    pub removed: Vec<PathBuf>,
+    pub failed: Vec<Failed>,
+}
+
+impl Removals {
+    /// Remove a file, returning whether it's gone
+    pub fn remove_file(&mut self, path: &Path) -> bool {
+        self.attempt(path, Kind::File)
+    }
+
+    /// Remove a directory and everything in it, returning whether it's gone
+    pub fn remove_dir_all(&mut self, path: &Path) -> bool {
+        self.attempt(path, Kind::Dir)
+    }
+
+    fn attempt(&mut self, path: &Path, kind: Kind) -> bool {
+        let result = match kind {
+            Kind::File => std::fs::remove_file(path),
+            Kind::Dir => std::fs::remove_dir_all(path),
+        };
+        match result {
+            Ok(()) => {
+                tracing::debug!("removed {}", path.display());
+                self.removed.push(path.to_path_buf());
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(error) => {
+                tracing::debug!("could not remove {}: {}", path.display(), error);
+                self.failed.push(Failed {
+                    path: path.to_path_buf(),
+                    kind,
+                    error,
+                });
+                false
+            }
+        }
+    }
+
+    /// Try the failed removals again, such as after stopping the process
+    /// that held them
+    pub fn retry(&mut self) {
+        for failed in std::mem::take(&mut self.failed) {
+            self.attempt(&failed.path, failed.kind);
+        }
+    }
+
+    /// Print what was removed and what wasn't, record both in the report,
+    /// and fail if anything couldn't be removed
+    pub fn finish(&self) -> Result<()> {
+        for path in &self.removed {
+            events::emit(Event::FileRemoved(path.clone()));
+        }
+        for failed in &self.failed {
+            events::emit(Event::RemovalFailed {
+                path: failed.path.clone(),
+                error: failed.error.to_string(),
+            });
+        }
+        if self.removed.is_empty() && self.failed.is_empty() {
+            return Ok(());
+        }
+
+        reporter().blank();
+        if !self.removed.is_empty() {
+            reporter().success(format!("Removed {} item(s):", self.removed.len()));
+            for path in &self.removed {
+                reporter().detail(path.display());
+            }
+        }
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+
+        reporter().warn(format!("Could not remove {} item(s):", self.failed.len()));
+        for failed in &self.failed {
+            let reason = if failed.in_use() {
+                "in use by another program".to_string()
+            } else {
+                failed.error.to_string()
+            };
+            reporter().detail(format!("{} ({})", failed.path.display(), reason));
+        }
+        tracing::warn!(
+            "left behind: {}",
+            self.failed
+                .iter()
+                .map(|failed| failed.path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Err(anyhow!(
+            "{} item(s) could not be removed; remove them by hand or run uninstall again",
+            self.failed.len()
+        ))
+    }
+}
@@ -0,0 +1,234 @@
+//! Checking a local artifacts directory end to end, so a copy damaged in
+//! transit is caught before anyone installs from it
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config;
+use crate::doctor::Diagnostic;
+use crate::download::{self, Manifest, SignatureError};
+
+/// Platform config directories every shipped `local/` must have, one per
+/// supported platform
+const PLATFORM_CONFIG_DIRS: [&str; 2] = ["WIN/USER-DIRECTORY", "MACOS/USER-DIRECTORY"];
+
+/// Every file `local/` ships with, checked in groups
+pub struct Report {
+    /// Version named by `latest`, if it could be read
+    pub version: Option<String>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Serialize)]
+pub struct Section {
+    pub title: &'static str,
+    pub checks: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.sections
+            .iter()
+            .flat_map(|section| &section.checks)
+            .filter(|check| !check.passed)
+            .count()
+    }
+}
+
+/// Check the release `local_dir/latest` names, the VSIX files, and the
+/// platform config directories
+pub fn verify_local(local_dir: &Path) -> Report {
+    let mut release = Vec::new();
+    let mut binaries = Vec::new();
+
+    let version = std::fs::read_to_string(local_dir.join("latest"))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|version| !version.is_empty());
+    match &version {
+        Some(version) => {
+            release.push(Diagnostic::pass("latest", version.clone()));
+            if let Some(manifest) = check_manifest(local_dir, version, &mut release) {
+                binaries = check_binaries(local_dir, version, &manifest);
+            }
+        }
+        None => release.push(Diagnostic::fail("latest", "missing or empty")),
+    }
+
+    let mut sections = vec![
+        Section {
+            title: "Release",
+            checks: release,
+        },
+        Section {
+            title: "Binaries",
+            checks: binaries,
+        },
+        Section {
+            title: "VS Code extensions",
+            checks: check_extensions(local_dir),
+        },
+        Section {
+            title: "Platform configuration",
+            checks: PLATFORM_CONFIG_DIRS
+                .iter()
+                .map(|dir| check_config_dir(local_dir, dir))
+                .collect(),
+        },
+    ];
+    sections.retain(|section| !section.checks.is_empty());
+
+    Report { version, sections }
+}
+
+/// Check the manifest and its signature, returning it if it parses
+fn check_manifest(
+    local_dir: &Path,
+    version: &str,
+    checks: &mut Vec<Diagnostic>,
+) -> Option<Manifest> {
+    let name = format!("{}/manifest.json", version);
+    let path = local_dir.join(version).join("manifest.json");
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => {
+            checks.push(Diagnostic::fail(name, "missing"));
+            return None;
+        }
+    };
+    let manifest = match Manifest::parse(&content, version) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            checks.push(Diagnostic::fail(name, format!("{:#}", e)));
+            return None;
+        }
+    };
+    checks.push(Diagnostic::pass(
+        name.clone(),
+        format!("{} platform(s)", manifest.platforms.len()),
+    ));
+
+    let signature_name = format!("{}.minisig", name);
+    let mut signature_path = path.into_os_string();
+    signature_path.push(".minisig");
+    let signature = std::fs::read_to_string(&signature_path).ok();
+    checks.push(
+        match download::verify_manifest_signature(&content, signature.as_deref()) {
+            None => Diagnostic::pass(signature_name, "not checked: no manifest key configured"),
+            Some(Ok(())) => Diagnostic::pass(signature_name, "valid"),
+            Some(Err(e)) if download::allows_unsigned() => Diagnostic::pass(
+                signature_name,
+                format!(
+                    "{}; allowed by --allow-unsigned",
+                    describe_signature_error(&e)
+                ),
+            ),
+            Some(Err(e)) => Diagnostic::fail(signature_name, describe_signature_error(&e)),
+        },
+    );
+
+    Some(manifest)
+}
+
+fn describe_signature_error(error: &SignatureError) -> String {
+    match error {
+        SignatureError::Missing => "missing".to_string(),
+        SignatureError::Invalid(reason) => format!("invalid: {}", reason),
+    }
+}
+
+/// Check every platform's binary (or archive) against the manifest
+fn check_binaries(local_dir: &Path, version: &str, manifest: &Manifest) -> Vec<Diagnostic> {
+    manifest
+        .platforms
+        .iter()
+        .map(|(platform, entry)| {
+            let binary_name = entry.binary_name(default_binary_name(platform));
+            let file_name = match entry.archive {
+                Some(kind) => kind.file_name(binary_name),
+                None => binary_name.to_string(),
+            };
+            let name = format!("{}/{}/{}", version, platform, file_name);
+            let path = download::local_binary_path(local_dir, version, platform, &file_name);
+            if !path.exists() {
+                return Diagnostic::fail(name, "missing");
+            }
+            match download::verify_checksum(&path, &entry.checksum) {
+                Ok(true) => Diagnostic::pass(name, "checksum OK"),
+                Ok(false) => Diagnostic::fail(
+                    name,
+                    "checksum mismatch; the file is corrupt or from another release",
+                ),
+                Err(e) => Diagnostic::fail(name, format!("could not read: {}", e)),
+            }
+        })
+        .collect()
+}
+
+/// The binary name a platform uses when the manifest doesn't say
+fn default_binary_name(platform: &str) -> &'static str {
+    if platform.starts_with("win32") {
+        "claude.exe"
+    } else {
+        "claude"
+    }
+}
+
+/// Check that `extensions.json` parses if there is one, and that there are
+/// VSIX files, each at least a zip archive, if there isn't
+fn check_extensions(local_dir: &Path) -> Vec<Diagnostic> {
+    let mut checks = Vec::new();
+    let listed = local_dir.join(config::EXTENSIONS_FILE).exists();
+    if listed {
+        checks.push(match config::wanted_extensions(local_dir) {
+            Ok(wanted) => Diagnostic::pass(
+                config::EXTENSIONS_FILE,
+                format!(
+                    "{} marketplace extensions",
+                    wanted.iter().filter(|want| want.marketplace).count()
+                ),
+            ),
+            Err(e) => Diagnostic::fail(config::EXTENSIONS_FILE, format!("{:#}", e)),
+        });
+    }
+
+    let files = match config::vsix_files(&local_dir.join("VSIX")) {
+        Ok(files) => files,
+        Err(e) => {
+            checks.push(Diagnostic::fail("VSIX", format!("could not read: {}", e)));
+            return checks;
+        }
+    };
+    if files.is_empty() && !listed {
+        checks.push(Diagnostic::fail(
+            "VSIX",
+            format!("no .vsix files or {} found", config::EXTENSIONS_FILE),
+        ));
+    }
+
+    checks.extend(files.iter().map(|path| {
+        let name = format!(
+            "VSIX/{}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        match std::fs::read(path) {
+            // A VSIX is a zip archive, which starts with a local file header
+            Ok(bytes) if bytes.starts_with(b"PK\x03\x04") => Diagnostic::pass(name, "present"),
+            Ok(_) => Diagnostic::fail(name, "not a valid VSIX (zip) file"),
+            Err(e) => Diagnostic::fail(name, format!("could not read: {}", e)),
+        }
+    }));
+    checks
+}
+
+fn check_config_dir(local_dir: &Path, dir: &str) -> Diagnostic {
+    let path = local_dir.join(dir);
+    if !path.is_dir() {
+        return Diagnostic::fail(dir, "missing");
+    }
+    match std::fs::read_dir(&path).map(|entries| entries.count()) {
+        Ok(0) => Diagnostic::fail(dir, "empty"),
+        Ok(count) => Diagnostic::pass(dir, format!("{} entries", count)),
+        Err(e) => Diagnostic::fail(dir, format!("could not read: {}", e)),
+    }
+}
@@ -0,0 +1,110 @@
+//! Platform builds shipped as archives are verified, then the binary is
+//! extracted from them.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, platform_id, Sandbox};
+use sha2::{Digest, Sha256};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+const VERSION: &str = "9.9.9";
+
+/// Pack `dir` into `archive` with the system tar, passing `extra` through
+fn tar(dir: &Path, archive: &Path, extra: &[&str]) {
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(archive)
+        .args(extra)
+        .arg("-C")
+        .arg(dir)
+        .arg(".")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+/// Stage `archive` as the local fallback release, with a signed manifest
+fn stage_archive(sandbox: &Sandbox, archive: &Path) {
+    let checksum = hex::encode(Sha256::digest(std::fs::read(archive).unwrap()));
+    let manifest = serde_json::json!({
+        "platforms": {
+            platform_id(): { "checksum": checksum, "archive": "tar.gz", "binary": "claude" }
+        }
+    });
+    sandbox.stage_manifest(VERSION, manifest);
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(VERSION)
+            .join(platform_id())
+            .join("claude.tar.gz"),
+        std::fs::read(archive).unwrap(),
+    );
+}
+
+fn install(sandbox: &Sandbox) -> assert_cmd::assert::Assert {
+    // The sandbox PATH only has stubs, and extraction needs the real tar
+    // (and the gzip it runs)
+    sandbox.stub("tar", "PATH=/usr/bin:/bin exec tar \"$@\"");
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+}
+
+#[test]
+fn installs_binary_from_archive() {
+    let sandbox = Sandbox::new();
+    let package = tempfile::TempDir::new().unwrap();
+    sandbox.write(
+        &package.path().join("package/claude"),
+        claude_binary(VERSION),
+    );
+    sandbox.write(&package.path().join("package/README.md"), "docs");
+    let archive = package.path().join("claude.tar.gz");
+    tar(&package.path().join("package"), &archive, &[]);
+    stage_archive(&sandbox, &archive);
+
+    let assert = install(&sandbox).success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("Extracted claude"), "{}", stdout);
+    let cached: Vec<_> = std::fs::read_dir(sandbox.cache_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    let binary = cached
+        .iter()
+        .find(|path| !path.to_string_lossy().ends_with(".tar.gz"))
+        .expect("extracted binary in the cache");
+    let mode = std::fs::metadata(binary).unwrap().permissions().mode();
+    assert_eq!(mode & 0o111, 0o111, "{:o}", mode);
+    assert_eq!(cached.len(), 2, "staging left behind: {:?}", cached);
+}
+
+#[test]
+fn refuses_archive_entries_outside_the_staging_directory() {
+    let sandbox = Sandbox::new();
+    let package = tempfile::TempDir::new().unwrap();
+    sandbox.write(
+        &package.path().join("package/claude"),
+        "#!/bin/sh\nexit 0\n",
+    );
+    let archive = package.path().join("claude.tar.gz");
+    tar(
+        &package.path().join("package"),
+        &archive,
+        &["-P", "--transform", "s,^\\./claude,../claude,"],
+    );
+    stage_archive(&sandbox, &archive);
+
+    let assert = install(&sandbox).failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("points outside the archive"), "{}", stderr);
+    assert!(!sandbox.cache_dir().join("../claude").exists());
+}
@@ -0,0 +1,101 @@
+//! Requests identify the client, carry the download token to mirrors that
+//! need one, and a refused token is reported as such.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, output, platform_id, respond, sign, Sandbox, TestServer};
+use sha2::{Digest, Sha256};
+
+const VERSION: &str = "9.9.9";
+const TOKEN: &str = "s3cret-t0ken";
+
+/// Serve a release, but only to requests with `Bearer TOKEN`
+fn release_server() -> TestServer {
+    let checksum = hex::encode(Sha256::digest(claude_binary(VERSION)));
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": checksum } }
+    })
+    .to_string();
+
+    TestServer::start(move |request, stream| {
+        if request.header("authorization") != Some(&format!("Bearer {}", TOKEN)) {
+            respond(stream, "401 Unauthorized", &[], b"");
+        } else if request.path == "/latest" {
+            respond(stream, "200 OK", &[], VERSION.as_bytes());
+        } else if request.path == format!("/{}/manifest.json", VERSION) {
+            respond(stream, "200 OK", &[], manifest.as_bytes());
+        } else if request.path == format!("/{}/manifest.json.minisig", VERSION) {
+            respond(stream, "200 OK", &[], sign(&manifest).as_bytes());
+        } else if request.path == format!("/{}/{}/claude", VERSION, platform_id()) {
+            respond(stream, "200 OK", &[], claude_binary(VERSION).as_bytes());
+        } else {
+            respond(stream, "404 Not Found", &[], b"");
+        }
+    })
+}
+
+#[test]
+fn sends_user_agent_and_token() {
+    let sandbox = Sandbox::new();
+    let server = release_server();
+    sandbox.config(&format!(
+        "download_base_url = \"{}\"\n\n[download]\ntoken = \"{}\"\n",
+        server.url, TOKEN
+    ));
+
+    let assert = sandbox
+        .command()
+        .args(["-vv", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .success();
+
+    let requests = server.requests();
+    assert!(!requests.is_empty());
+    let user_agent = format!(
+        "code-assist/{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        platform_id()
+    );
+    for request in &requests {
+        assert_eq!(request.header("user-agent"), Some(user_agent.as_str()));
+    }
+    let output = output(&assert);
+    assert!(!output.contains(TOKEN), "{}", output);
+}
+
+#[test]
+fn missing_token_is_reported() {
+    let sandbox = Sandbox::new();
+    let server = release_server();
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(3);
+
+    let output = output(&assert);
+    assert!(output.contains("requires a download token"), "{}", output);
+    assert!(output.contains("CODE_ASSIST_DOWNLOAD_TOKEN"), "{}", output);
+}
+
+#[test]
+fn rejected_token_is_reported_without_echoing_it() {
+    let sandbox = Sandbox::new();
+    let server = release_server();
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+
+    let assert = sandbox
+        .command()
+        .env("CODE_ASSIST_DOWNLOAD_TOKEN", "wrong-token")
+        .args(["-vv", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(3);
+
+    let output = output(&assert);
+    assert!(output.contains("rejected the download token"), "{}", output);
+    assert!(!output.contains("wrong-token"), "{}", output);
+}
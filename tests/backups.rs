@@ -0,0 +1,179 @@
+//! Deploys back up the settings they change, `restore` puts a backup back,
+//! and only the newest backups are kept.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::PathBuf;
+
+const ORIGINAL: &str = "{\n  \"theme\": \"dark\"\n}";
+
+/// Stage a Claude settings template and the user's own settings
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY")
+            .join(".claude")
+            .join("settings.json"),
+        r#"{ "theme": "light" }"#,
+    );
+    sandbox.write(&settings_path(&sandbox), ORIGINAL);
+    sandbox
+}
+
+fn settings_path(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude").join("settings.json")
+}
+
+/// Backup timestamps on disk, oldest first
+fn backups(sandbox: &Sandbox) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(sandbox.backups_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+fn configure(sandbox: &Sandbox) {
+    sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn restore_undoes_a_deploy() {
+    let sandbox = sandbox();
+    configure(&sandbox);
+    assert!(fs::read_to_string(settings_path(&sandbox))
+        .unwrap()
+        .contains("light"));
+
+    let timestamps = backups(&sandbox);
+    assert_eq!(timestamps.len(), 1);
+    let copy = sandbox
+        .backups_dir()
+        .join(&timestamps[0])
+        .join("claude")
+        .join("settings.json");
+    assert_eq!(fs::read_to_string(copy).unwrap(), ORIGINAL);
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "restore", "--tool", "claude-code"])
+        .args(["--timestamp", &timestamps[0]])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("- ") && stdout.contains("+ "), "{}", stdout);
+
+    assert_eq!(
+        fs::read_to_string(settings_path(&sandbox)).unwrap(),
+        ORIGINAL
+    );
+}
+
+#[test]
+fn unknown_timestamp_lists_the_available_ones() {
+    let sandbox = sandbox();
+    configure(&sandbox);
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "restore", "--tool", "claude-code"])
+        .args(["--timestamp", "19700101T000000Z"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+
+    assert!(stderr.contains("available:"), "{}", stderr);
+    assert!(stderr.contains(&backups(&sandbox)[0]), "{}", stderr);
+}
+
+#[test]
+fn keeps_only_the_configured_number_of_backups() {
+    let sandbox = sandbox();
+    sandbox.config("[backups]\nkeep = 2\n");
+
+    // Two earlier backups, recorded as a previous deploy would have
+    let mut recorded = Vec::new();
+    for timestamp in ["20200101T000000Z", "20200102T000000Z"] {
+        let copy = sandbox
+            .backups_dir()
+            .join(timestamp)
+            .join("claude")
+            .join("settings.json");
+        sandbox.write(&copy, ORIGINAL);
+        recorded.push(serde_json::json!({
+            "timestamp": timestamp,
+            "files": [{ "original": settings_path(&sandbox), "copy": copy }],
+        }));
+    }
+    sandbox.write(
+        &sandbox.state_dir().join(".code-assist-state.json"),
+        serde_json::json!({ "schema": 1, "backups": recorded }).to_string(),
+    );
+
+    configure(&sandbox);
+
+    let timestamps = backups(&sandbox);
+    assert_eq!(timestamps.len(), 2, "{:?}", timestamps);
+    assert_eq!(timestamps[0], "20200102T000000Z");
+}
+
+#[test]
+fn backups_from_the_old_layout_can_still_be_restored() {
+    let sandbox = sandbox();
+    let timestamp = "20200101T000000Z";
+    let copy = sandbox
+        .home()
+        .join(".claude/backups")
+        .join(timestamp)
+        .join("claude/settings.json");
+    sandbox.write(&copy, ORIGINAL);
+    sandbox.write(&settings_path(&sandbox), "{}");
+    sandbox.write(
+        &sandbox.home().join(".claude/.code-assist-state.json"),
+        serde_json::json!({
+            "schema": 1,
+            "backups": [{
+                "timestamp": timestamp,
+                "files": [{ "original": settings_path(&sandbox), "copy": copy }],
+            }],
+        })
+        .to_string(),
+    );
+
+    sandbox
+        .command()
+        .args([
+            "-y",
+            "restore",
+            "--tool",
+            "claude-code",
+            "--timestamp",
+            timestamp,
+        ])
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(settings_path(&sandbox)).unwrap(),
+        ORIGINAL
+    );
+    assert!(sandbox.backups_dir().join(timestamp).exists());
+    assert!(!sandbox.home().join(".claude/backups").exists());
+}
@@ -0,0 +1,82 @@
+//! Certificates are checked before they're deployed, and `doctor` checks the
+//! deployed ones the same way.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{ca_certificate, output, Sandbox};
+use std::path::PathBuf;
+
+/// The certs directory in the platform config tree
+fn certs_template_dir(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox
+        .local()
+        .join(platform_dir)
+        .join("USER-DIRECTORY")
+        .join("certs")
+}
+
+#[test]
+fn expired_certificates_are_deployed_loudly_but_not_trusted_by_node() {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &certs_template_dir(&sandbox).join("old-root.pem"),
+        ca_certificate("Old Root", true),
+    );
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = output(&assert);
+
+    assert!(output.contains("CN=Old Root"), "{}", output);
+    assert!(output.contains("EXPIRED on 2001-01-01"), "{}", output);
+    assert!(
+        output.contains("NODE_EXTRA_CA_CERTS was not pointed at them"),
+        "{}",
+        output
+    );
+    assert!(sandbox.home().join("certs").join("old-root.pem").exists());
+}
+
+#[test]
+fn files_that_are_not_certificates_fail_the_deploy() {
+    let sandbox = Sandbox::new();
+    sandbox.write(&certs_template_dir(&sandbox).join("root.cer"), "not a cert");
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .failure();
+    let output = output(&assert);
+
+    assert!(output.contains("is not a valid certificate"), "{}", output);
+    assert!(!sandbox.home().join("certs").exists());
+}
+
+#[test]
+fn doctor_reports_expired_deployed_certificates() {
+    let sandbox = Sandbox::new();
+    let certs = sandbox.home().join("certs");
+    sandbox.write(
+        &certs.join("current.crt"),
+        ca_certificate("Current Root", false),
+    );
+    sandbox.write(&certs.join("old.crt"), ca_certificate("Old Root", true));
+
+    let assert = sandbox.command().arg("doctor").assert().failure();
+    let output = output(&assert);
+
+    assert!(output.contains("Certificate current.crt"), "{}", output);
+    assert!(output.contains("valid until 2099-01-01"), "{}", output);
+    assert!(output.contains("CN=Old Root EXPIRED"), "{}", output);
+}
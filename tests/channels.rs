@@ -0,0 +1,119 @@
+//! Installs follow a release channel, and `update` stays on it unless told
+//! to switch.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, output, platform_id, Sandbox};
+use sha2::{Digest, Sha256};
+use std::fs;
+
+/// Stage `version` in the local fallback as the latest on `latest_file`
+fn stage(sandbox: &Sandbox, latest_file: &str, version: &str) {
+    let local = sandbox.local();
+    let binary = claude_binary(version);
+    let checksum = hex::encode(Sha256::digest(&binary));
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": checksum } }
+    });
+    sandbox.write(&local.join(latest_file), version);
+    sandbox.write_signed(
+        &local.join(version).join("manifest.json"),
+        manifest.to_string(),
+    );
+    sandbox.write(
+        &local.join(version).join(platform_id()).join("claude"),
+        binary,
+    );
+}
+
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    stage(&sandbox, "latest", "1.0.0");
+    stage(&sandbox, "latest-beta", "1.1.0-beta.1");
+    sandbox
+}
+
+fn installed(sandbox: &Sandbox, file: &str) -> String {
+    fs::read_to_string(sandbox.home().join(".claude/bin").join(file)).unwrap()
+}
+
+#[test]
+fn records_the_channel_installed_from() {
+    let sandbox = sandbox();
+
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .args(["--channel", "beta"])
+        .assert()
+        .success();
+
+    assert_eq!(installed(&sandbox, ".code-assist-version"), "1.1.0-beta.1");
+    assert_eq!(installed(&sandbox, ".code-assist-channel"), "beta");
+}
+
+#[test]
+fn switching_channels_requires_the_flag() {
+    let sandbox = sandbox();
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .success();
+    assert_eq!(installed(&sandbox, ".code-assist-channel"), "stable");
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "update", "--channel", "beta"])
+        .assert()
+        .failure();
+    let output = output(&assert);
+    assert!(
+        output.contains("pass --switch-channel to move it to beta"),
+        "{}",
+        output
+    );
+    assert_eq!(installed(&sandbox, ".code-assist-version"), "1.0.0");
+
+    sandbox
+        .command()
+        .args(["--offline", "-y", "update", "--channel", "beta"])
+        .arg("--switch-channel")
+        .assert()
+        .success();
+    assert_eq!(installed(&sandbox, ".code-assist-version"), "1.1.0-beta.1");
+    assert_eq!(installed(&sandbox, ".code-assist-channel"), "beta");
+}
+
+#[test]
+fn update_stays_on_the_installed_channel() {
+    let sandbox = sandbox();
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .args(["--channel", "beta"])
+        .assert()
+        .success();
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "update"])
+        .assert()
+        .success();
+    let output = output(&assert);
+    assert!(
+        output.contains("Already up to date (1.1.0-beta.1 on the beta channel)"),
+        "{}",
+        output
+    );
+
+    stage(&sandbox, "latest-beta", "1.1.0-beta.2");
+    sandbox
+        .command()
+        .args(["--offline", "-y", "update"])
+        .assert()
+        .success();
+    assert_eq!(installed(&sandbox, ".code-assist-version"), "1.1.0-beta.2");
+}
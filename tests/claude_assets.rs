@@ -0,0 +1,171 @@
+//! Everything under `.claude/` in the platform config tree is deployed into
+//! `~/.claude`: JSON merged, other files copied without replacing ones the
+//! user changed.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::PathBuf;
+
+/// The `.claude` template directory in the platform config tree
+fn templates(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox
+        .local()
+        .join(platform_dir)
+        .join("USER-DIRECTORY")
+        .join(".claude")
+}
+
+fn claude_dir(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude")
+}
+
+fn configure(sandbox: &Sandbox) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+#[test]
+fn the_claude_tree_is_copied_with_its_subdirectories() {
+    let sandbox = Sandbox::new();
+    let templates = templates(&sandbox);
+    sandbox.write(&templates.join("CLAUDE.md"), "# Team rules\n");
+    sandbox.write(&templates.join("commands").join("review.md"), "Review\n");
+    sandbox.write(&templates.join("agents").join("tester.md"), "Test\n");
+    sandbox.write(&templates.join("agents").join("._tester.md"), "");
+    sandbox.write(&templates.join("settings.json"), r#"{ "model": "sonnet" }"#);
+    sandbox.write(
+        &claude_dir(&sandbox).join("settings.json"),
+        r#"{ "theme": "dark" }"#,
+    );
+
+    let output = configure(&sandbox);
+
+    let claude = claude_dir(&sandbox);
+    assert_eq!(
+        fs::read_to_string(claude.join("CLAUDE.md")).unwrap(),
+        "# Team rules\n"
+    );
+    assert_eq!(
+        fs::read_to_string(claude.join("commands").join("review.md")).unwrap(),
+        "Review\n"
+    );
+    assert_eq!(
+        fs::read_to_string(claude.join("agents").join("tester.md")).unwrap(),
+        "Test\n"
+    );
+    assert!(!claude.join("agents").join("._tester.md").exists());
+    let settings: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(claude.join("settings.json")).unwrap()).unwrap();
+    assert_eq!(
+        settings,
+        serde_json::json!({ "theme": "dark", "model": "sonnet" })
+    );
+    assert!(output.contains("Deployed Claude CLAUDE.md"), "{}", output);
+    assert!(
+        output.contains("Deployed Claude commands/review.md"),
+        "{}",
+        output
+    );
+
+    // Nothing to do the second time
+    let output = configure(&sandbox);
+    assert!(
+        output.contains("Claude templates already up to date"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn edited_markdown_gets_the_template_alongside() {
+    let sandbox = Sandbox::new();
+    let template = templates(&sandbox).join("CLAUDE.md");
+    let deployed = claude_dir(&sandbox).join("CLAUDE.md");
+    let alongside = claude_dir(&sandbox).join("CLAUDE.new.md");
+    sandbox.write(&template, "v1\n");
+    configure(&sandbox);
+
+    // Untouched copies follow the template
+    sandbox.write(&template, "v2\n");
+    configure(&sandbox);
+    assert_eq!(fs::read_to_string(&deployed).unwrap(), "v2\n");
+    assert!(!alongside.exists());
+
+    // Edited ones are kept, with the template next to them
+    fs::write(&deployed, "mine\n").unwrap();
+    sandbox.write(&template, "v3\n");
+    let output = configure(&sandbox);
+    assert!(output.contains("CLAUDE.new.md"), "{}", output);
+    assert_eq!(fs::read_to_string(&deployed).unwrap(), "mine\n");
+    assert_eq!(fs::read_to_string(&alongside).unwrap(), "v3\n");
+
+    // The user's own file is treated the same way
+    let sandbox = Sandbox::new();
+    sandbox.write(&templates(&sandbox).join("CLAUDE.md"), "team\n");
+    sandbox.write(&claude_dir(&sandbox).join("CLAUDE.md"), "mine\n");
+    configure(&sandbox);
+    assert_eq!(
+        fs::read_to_string(claude_dir(&sandbox).join("CLAUDE.md")).unwrap(),
+        "mine\n"
+    );
+    assert_eq!(
+        fs::read_to_string(claude_dir(&sandbox).join("CLAUDE.new.md")).unwrap(),
+        "team\n"
+    );
+}
+
+#[test]
+fn a_tool_directory_takes_the_place_of_the_shared_tree() {
+    let sandbox = Sandbox::new();
+    sandbox.write(&templates(&sandbox).join("CLAUDE.md"), "shared\n");
+    let platform_tree = templates(&sandbox)
+        .strip_prefix(sandbox.local())
+        .unwrap()
+        .to_path_buf();
+    let tool_templates = sandbox.local().join("claude-code").join(platform_tree);
+    sandbox.write(&tool_templates.join("CLAUDE.md"), "claude-code\n");
+
+    configure(&sandbox);
+    assert_eq!(
+        fs::read_to_string(claude_dir(&sandbox).join("CLAUDE.md")).unwrap(),
+        "claude-code\n"
+    );
+    assert!(sandbox
+        .state_dir()
+        .join(".code-assist-state.claude-code.json")
+        .exists());
+}
+
+#[test]
+fn code_assist_home_takes_the_place_of_the_home_directory() {
+    let sandbox = Sandbox::new();
+    sandbox.write(&templates(&sandbox).join("CLAUDE.md"), "# Team rules\n");
+    let elsewhere = sandbox.home().with_file_name("service-account");
+
+    sandbox
+        .command()
+        .env("CODE_ASSIST_HOME", &elsewhere)
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    assert!(elsewhere.join(".claude").join("CLAUDE.md").exists());
+    assert!(!claude_dir(&sandbox).join("CLAUDE.md").exists());
+}
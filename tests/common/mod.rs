@@ -0,0 +1,360 @@
+//! Shared fixtures for the integration tests: an isolated sandbox to run the
+//! binary in, and a minimal HTTP server to stand in for the release bucket.
+
+// Each test binary uses a different subset of these
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// Platform ids the manifest may be looked up under on a Unix host
+pub const PLATFORM_IDS: [&str; 3] = ["linux-x64", "darwin-x64", "darwin-arm64"];
+
+/// Platform id the binary under test downloads for
+pub fn platform_id() -> &'static str {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("macos", "x86_64") => "darwin-x64",
+        ("macos", "aarch64") => "darwin-arm64",
+        _ => "linux-x64",
+    }
+}
+
+/// A stand-in for the Claude binary: `install` puts it where the launcher is
+/// expected, and `--version` reports `version`
+pub fn claude_binary(version: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         if [ \"$1\" = --version ]; then echo '{} (Claude Code)'; exit 0; fi\n\
+         /bin/mkdir -p \"$HOME/.claude/bin\" && /bin/cp \"$0\" \"$HOME/.claude/bin/claude\"\n",
+        version
+    )
+}
+
+/// Everything a command printed, stdout then stderr
+pub fn output(assert: &assert_cmd::assert::Assert) -> String {
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// Key id of the test signing key
+const KEY_ID: [u8; 8] = *b"testkey1";
+
+fn signing_key() -> Ed25519KeyPair {
+    Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap()
+}
+
+/// Minisign public key for manifests signed with `sign`
+pub fn manifest_key() -> String {
+    let key = [
+        b"Ed".as_slice(),
+        &KEY_ID,
+        signing_key().public_key().as_ref(),
+    ]
+    .concat();
+    STANDARD.encode(key)
+}
+
+/// A minisign signature file over `content`
+pub fn sign(content: impl AsRef<[u8]>) -> String {
+    let key = signing_key();
+    let signature = key.sign(content.as_ref());
+    let comment = "timestamp:0\tfile:manifest.json";
+    let global = key.sign(&[signature.as_ref(), comment.as_bytes()].concat());
+    format!(
+        "untrusted comment: test signature\n{}\ntrusted comment: {}\n{}\n",
+        STANDARD.encode([b"Ed".as_slice(), &KEY_ID, signature.as_ref()].concat()),
+        comment,
+        STANDARD.encode(global)
+    )
+}
+
+/// A self-signed CA certificate in PEM, either long expired or valid for
+/// decades
+pub fn ca_certificate(name: &str, expired: bool) -> String {
+    let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, name);
+    params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+    params.not_after = if expired {
+        rcgen::date_time_ymd(2001, 1, 1)
+    } else {
+        rcgen::date_time_ymd(2099, 1, 1)
+    };
+    let key = rcgen::KeyPair::generate().unwrap();
+    params.self_signed(&key).unwrap().pem()
+}
+
+/// An isolated home directory, PATH with stub `code` and `git`, and an empty
+/// local artifacts directory
+pub struct Sandbox {
+    root: TempDir,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let sandbox = Sandbox {
+            root: TempDir::new().unwrap(),
+        };
+        fs::create_dir_all(sandbox.home()).unwrap();
+        fs::create_dir_all(sandbox.local()).unwrap();
+        fs::create_dir_all(sandbox.bin()).unwrap();
+        sandbox.stub("code", "echo 1.90.0");
+        sandbox.stub("git", "echo git version 2.45.0");
+        sandbox
+    }
+
+    pub fn home(&self) -> PathBuf {
+        self.root.path().join("home")
+    }
+
+    pub fn local(&self) -> PathBuf {
+        self.root.path().join("local")
+    }
+
+    pub fn bin(&self) -> PathBuf {
+        self.root.path().join("bin")
+    }
+
+    /// The downloads cache under the sandbox home
+    pub fn cache_dir(&self) -> PathBuf {
+        if cfg!(target_os = "macos") {
+            self.home().join("Library/Caches/code-assist/downloads")
+        } else {
+            self.home().join(".cache/code-assist/downloads")
+        }
+    }
+
+    /// Where the state files recording what code-assist changed are kept
+    pub fn state_dir(&self) -> PathBuf {
+        if cfg!(target_os = "macos") {
+            self.home()
+                .join("Library/Application Support/code-assist/state")
+        } else {
+            self.home().join(".local/state/code-assist")
+        }
+    }
+
+    /// Where settings files are copied before a deploy changes them
+    pub fn backups_dir(&self) -> PathBuf {
+        if cfg!(target_os = "macos") {
+            self.home()
+                .join("Library/Application Support/code-assist/backups")
+        } else {
+            self.state_dir().join("backups")
+        }
+    }
+
+    pub fn stub(&self, name: &str, body: &str) {
+        let path = self.bin().join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    pub fn write(&self, path: &Path, contents: impl AsRef<[u8]>) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Write a manifest with a valid signature next to it
+    pub fn write_signed(&self, path: &Path, contents: impl AsRef<[u8]>) {
+        let mut signature_path = path.as_os_str().to_owned();
+        signature_path.push(".minisig");
+        self.write(Path::new(&signature_path), sign(&contents));
+        self.write(path, contents);
+    }
+
+    /// Make `version` the latest local fallback release, with `manifest`
+    /// signed next to it
+    pub fn stage_manifest(&self, version: &str, manifest: serde_json::Value) {
+        let local = self.local();
+        self.write(&local.join("latest"), version);
+        self.write_signed(
+            &local.join(version).join("manifest.json"),
+            manifest.to_string(),
+        );
+    }
+
+    /// Stage `binary` as the latest local fallback release of `version`
+    /// for this platform
+    pub fn stage_binary(&self, version: &str, binary: impl AsRef<[u8]>) {
+        let manifest = serde_json::json!({
+            "platforms": {
+                platform_id(): { "checksum": hex::encode(Sha256::digest(binary.as_ref())) }
+            }
+        });
+        self.stage_manifest(version, manifest);
+        self.write(
+            &self
+                .local()
+                .join(version)
+                .join(platform_id())
+                .join("claude"),
+            binary,
+        );
+    }
+
+    /// Stage a working Claude binary as the latest local fallback release
+    /// of `version`
+    pub fn stage_release(&self, version: &str) {
+        self.stage_binary(version, claude_binary(version));
+    }
+
+    /// Write the user config file where the binary looks for it
+    pub fn config(&self, toml: &str) {
+        let config_dir = if cfg!(target_os = "macos") {
+            self.home().join("Library").join("Application Support")
+        } else {
+            self.home().join(".config")
+        };
+        self.write(&config_dir.join("code-assist").join("config.toml"), toml);
+    }
+
+    pub fn command(&self) -> Command {
+        Command::from_std(self.process())
+    }
+
+    /// The same command as a plain process, for tests that signal it while
+    /// it runs
+    pub fn process(&self) -> std::process::Command {
+        self.process_of(&assert_cmd::cargo::cargo_bin("code-assist"))
+    }
+
+    /// The same command run from a copy of the binary at `program`
+    pub fn process_of(&self, program: &Path) -> std::process::Command {
+        let mut command = std::process::Command::new(program);
+        command
+            .env_clear()
+            .env("HOME", self.home())
+            .env("PATH", self.bin())
+            .env("NO_COLOR", "1")
+            .env("CODE_ASSIST_MANIFEST_KEY", manifest_key())
+            .arg("--local-dir")
+            .arg(self.local());
+        command
+    }
+}
+
+/// A request as seen by `TestServer`
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// An HTTP/1.1 server on a random local port that answers each request,
+/// one connection at a time, with `handler` and records what it was asked
+pub struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    pub fn start(handler: impl Fn(&Request, &mut TcpStream) + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let Some(request) = read_request(&stream) else {
+                    continue;
+                };
+                recorded.lock().unwrap().push(request.clone());
+                handler(&request, &mut stream);
+            }
+        });
+
+        TestServer { url, requests }
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    Some(Request {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Write a response with `body`, closing the connection afterwards
+pub fn respond(stream: &mut TcpStream, status: &str, headers: &[(&str, String)], body: &[u8]) {
+    respond_truncated(stream, status, headers, body, body.len());
+}
+
+/// Like `respond`, but announce the full body length and hang up after
+/// `sent` bytes, as a dropped connection would
+pub fn respond_truncated(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+    sent: usize,
+) {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&body[..sent]);
+    let _ = stream.flush();
+}
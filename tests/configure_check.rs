@@ -0,0 +1,103 @@
+//! `configure --check` compares the machine with the templates without
+//! changing anything, and exits 9 when they differ.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+/// The platform config tree
+fn templates(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.local().join(platform_dir).join("USER-DIRECTORY")
+}
+
+fn check(sandbox: &Sandbox, json: bool) -> assert_cmd::assert::Assert {
+    let mut command = sandbox.command();
+    command.args(["configure", "--tool", "claude-code", "--check"]);
+    if json {
+        command.arg("--json");
+    }
+    command.assert()
+}
+
+#[test]
+fn reports_drift_without_changing_anything() {
+    let sandbox = Sandbox::new();
+    let templates = templates(&sandbox);
+    sandbox.write(
+        &templates.join(".claude").join("settings.json"),
+        r#"{ "model": "sonnet", "env": { "HTTPS_PROXY": "http://proxy:8080" } }"#,
+    );
+    sandbox.write(&templates.join(".claude").join("CLAUDE.md"), "# Rules\n");
+    sandbox.write(
+        &templates.join("mcp.json"),
+        json!({ "mcpServers": { "docs": { "command": "docs-mcp" } } }).to_string(),
+    );
+
+    // Nothing deployed yet
+    let assert = check(&sandbox, true).code(9);
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(report["compliant"], false);
+    let kinds: Vec<&str> = report["drift"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["file", "setting", "mcp-server"], "{}", report);
+    // Only the log the run appended to
+    let created: Vec<_> = std::fs::read_dir(sandbox.home().join(".claude"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(created, ["logs"]);
+
+    sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let assert = check(&sandbox, false).success();
+    let output = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(
+        output.contains("Configuration matches the templates"),
+        "{}",
+        output
+    );
+
+    let settings = sandbox.home().join(".claude").join("settings.json");
+    sandbox.write(
+        &settings,
+        r#"{ "model": "opus", "env": { "HTTPS_PROXY": "http://proxy:8080" } }"#,
+    );
+    let assert = check(&sandbox, true).code(9);
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(
+        report["drift"],
+        json!([{
+            "kind": "setting",
+            "target": settings.display().to_string(),
+            "problem": "\"model\" is \"opus\", not \"sonnet\""
+        }])
+    );
+    assert!(fs::read_to_string(&settings).unwrap().contains("opus"));
+}
+
+#[test]
+fn json_requires_check() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .command()
+        .args(["configure", "--tool", "claude-code", "--json"])
+        .assert()
+        .code(2);
+}
@@ -0,0 +1,54 @@
+//! Other copies of `claude` on PATH, such as an npm global install, are
+//! reported during install and by `doctor`.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{output, Sandbox};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+const VERSION: &str = "2.0.0";
+
+/// A sandbox with Claude Code available offline, and an older copy
+/// installed globally by npm, linked into the bin directory on PATH
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+
+    let cli = sandbox
+        .home()
+        .join("npm-global/lib/node_modules/@anthropic-ai/claude-code/cli.js");
+    sandbox.write(&cli, "#!/bin/sh\necho '1.0.0 (Claude Code)'\n");
+    fs::set_permissions(&cli, fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink(&cli, sandbox.bin().join("claude")).unwrap();
+    sandbox
+}
+
+#[test]
+fn an_npm_install_is_reported_with_how_to_remove_it() {
+    let sandbox = sandbox();
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let installed = output(&assert);
+    assert!(
+        installed.contains("npm package @anthropic-ai/claude-code, 1.0.0 (Claude Code)"),
+        "{}",
+        installed
+    );
+    assert!(
+        installed.contains("npm uninstall --global @anthropic-ai/claude-code"),
+        "{}",
+        installed
+    );
+
+    // The sandbox PATH doesn't include our install directory, so npm's wins
+    let assert = sandbox.command().arg("doctor").assert().failure();
+    let diagnosed = output(&assert);
+    assert!(diagnosed.contains("runs instead of ours"), "{}", diagnosed);
+}
@@ -0,0 +1,101 @@
+//! The Continue tool installs its VS Code extension and merges its YAML
+//! config into `~/.continue`, and uninstalling takes the extension away.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+/// A `code` that keeps its installed extensions in `$HOME/extensions`, with
+/// only shell builtins as the sandbox PATH has nothing else
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stub(
+        "code",
+        r#"list="$HOME/extensions"
+: >> "$list"
+case "$1" in
+  --list-extensions) while read -r ext; do echo "$ext"; done < "$list" ;;
+  --install-extension) echo "Continue.continue@1.2.0" >> "$list" ;;
+  --uninstall-extension) : > "$list" ;;
+  *) echo 1.90.0 ;;
+esac"#,
+    );
+    sandbox.write(
+        &sandbox
+            .local()
+            .join("continue/LINUX/USER-DIRECTORY/.continue/config.yaml"),
+        "requestOptions:\n  caBundlePath: ${CERTS_DIR}/ca-bundle.pem\n",
+    );
+    sandbox
+}
+
+#[test]
+fn merges_the_yaml_config_into_the_users() {
+    let sandbox = sandbox();
+    let config = sandbox.home().join(".continue/config.yaml");
+    sandbox.write(
+        &config,
+        "name: Mine\nmodels:\n  - name: Local\n    provider: ollama\nrequestOptions:\n  timeout: 30\n",
+    );
+
+    sandbox
+        .command()
+        .args(["-y", "install", "--tool", "continue"])
+        .assert()
+        .success();
+
+    assert!(fs::read_to_string(sandbox.home().join("extensions"))
+        .unwrap()
+        .contains("Continue.continue"));
+    let merged = fs::read_to_string(&config).unwrap();
+    assert!(merged.contains("name: Mine"), "{}", merged);
+    assert!(merged.contains("provider: ollama"), "{}", merged);
+    assert!(merged.contains("timeout: 30"), "{}", merged);
+    let certs = sandbox.home().join(".continue/certs/ca-bundle.pem");
+    assert!(
+        merged.contains(&format!("caBundlePath: {}", certs.display())),
+        "{}",
+        merged
+    );
+
+    let assert = sandbox
+        .command()
+        .args(["status", "--tool", "continue"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("1.2.0"), "{}", stdout);
+}
+
+#[test]
+fn uninstall_removes_the_extension_and_purge_the_config() {
+    let sandbox = sandbox();
+    sandbox
+        .command()
+        .args(["-y", "install", "--tool", "continue"])
+        .assert()
+        .success();
+    let config = sandbox.home().join(".continue/config.yaml");
+    assert!(config.exists());
+
+    sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "continue"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(sandbox.home().join("extensions")).unwrap(),
+        ""
+    );
+    assert!(config.exists());
+
+    sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "continue", "--purge"])
+        .assert()
+        .success();
+    assert!(!config.exists());
+}
@@ -0,0 +1,99 @@
+//! Tools declared in `tools.d` are listed, installed from their download
+//! URL or local fallback, and deploy their config files.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+/// A sandbox declaring `acme`, a binary served from a `file://` URL that
+/// merges `settings.json` into `~/.acme`
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let dist = sandbox.local().join("dist");
+    sandbox.write(
+        &sandbox.local().join("tools.d/acme.toml"),
+        format!(
+            r#"name = "acme"
+display_name = "Acme CLI"
+binary = "acme"
+version = "1.4.0"
+
+[download]
+url = "file://{}/${{VERSION}}/${{BINARY}}"
+
+[[config]]
+source = "settings.json"
+dest = "${{HOME}}/.acme/settings.json"
+"#,
+            dist.display()
+        ),
+    );
+    sandbox.write(&dist.join("1.4.0/acme"), "#!/bin/sh\necho acme 1.4.0\n");
+    sandbox.write(
+        &sandbox.local().join("acme/settings.json"),
+        r#"{"telemetry": false}"#,
+    );
+    sandbox
+}
+
+#[test]
+fn installs_a_declared_tool_and_merges_its_config() {
+    let sandbox = sandbox();
+    let settings = sandbox.home().join(".acme/settings.json");
+    sandbox.write(&settings, r#"{"theme": "dark"}"#);
+
+    let assert = sandbox.command().arg("list").assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("acme"), "{}", stdout);
+
+    sandbox
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .success();
+
+    let binary = sandbox.home().join(".acme/bin/acme");
+    assert_eq!(
+        fs::read_to_string(&binary).unwrap(),
+        "#!/bin/sh\necho acme 1.4.0\n"
+    );
+    let merged: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&settings).unwrap()).unwrap();
+    assert_eq!(merged["theme"], "dark");
+    assert_eq!(merged["telemetry"], false);
+
+    let assert = sandbox
+        .command()
+        .args(["status", "--tool", "acme"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("1.4.0"), "{}", stdout);
+
+    sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "acme", "--purge"])
+        .assert()
+        .success();
+    assert!(!binary.exists());
+}
+
+#[test]
+fn an_invalid_definition_is_reported_and_skipped() {
+    let sandbox = sandbox();
+    sandbox.write(
+        &sandbox.local().join("tools.d/broken.toml"),
+        "name = \"broken\"\nnpm = \"broken\"\n\n[[config]]\nsource = \"README.md\"\ndest = \"x\"\n",
+    );
+
+    let assert = sandbox.command().arg("list").assert().success();
+    let output = assert.get_output();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("acme"), "{}", stdout);
+    assert!(!stdout.contains("broken"), "{}", stdout);
+    assert!(stderr.contains("`config[0].merge`"), "{}", stderr);
+}
@@ -0,0 +1,92 @@
+//! Installing a tool installs the tools it depends on first, and
+//! uninstalling one warns about the tools that depend on it.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+
+/// Declare a binary tool served from `local/dist`, depending on `dependencies`
+fn declare(sandbox: &Sandbox, name: &str, display_name: &str, dependencies: &[&str]) {
+    let dist = sandbox.local().join("dist");
+    let dependencies: Vec<String> = dependencies.iter().map(|d| format!("{:?}", d)).collect();
+    sandbox.write(
+        &sandbox.local().join(format!("tools.d/{}.toml", name)),
+        format!(
+            r#"name = "{name}"
+display_name = "{display_name}"
+binary = "{name}"
+version = "1.0.0"
+dependencies = [{}]
+
+[download]
+url = "file://{}/${{BINARY}}"
+"#,
+            dependencies.join(", "),
+            dist.display()
+        ),
+    );
+    sandbox.write(&dist.join(name), "#!/bin/sh\n");
+}
+
+#[test]
+fn installs_dependencies_first_and_only_once() {
+    let sandbox = Sandbox::new();
+    declare(&sandbox, "corp-certs", "Corp Certs", &[]);
+    declare(&sandbox, "acme", "Acme", &["corp-certs"]);
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    let certs = stdout.find("Installing Corp Certs").expect(&stdout);
+    let acme = stdout.find("Installing Acme").expect(&stdout);
+    assert!(certs < acme, "{}", stdout);
+    assert!(sandbox.home().join(".corp-certs/bin/corp-certs").exists());
+    assert!(sandbox.home().join(".acme/bin/acme").exists());
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        stdout.contains("Corp Certs is already installed"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Installing Corp Certs"), "{}", stdout);
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "corp-certs"])
+        .assert()
+        .success();
+    let output = assert.get_output();
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(all.contains("Acme depends on Corp Certs"), "{}", all);
+}
+
+#[test]
+fn a_dependency_cycle_is_refused() {
+    let sandbox = Sandbox::new();
+    declare(&sandbox, "corp-certs", "Corp Certs", &["acme"]);
+    declare(&sandbox, "acme", "Acme", &["corp-certs"]);
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .code(5);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("acme -> corp-certs -> acme"), "{}", stderr);
+    assert!(!sandbox.home().join(".acme").exists());
+}
@@ -0,0 +1,122 @@
+//! A settings key the user changed after a deploy wrote it is kept by the
+//! next deploy and listed, unless `--force-settings` replaces it or the
+//! user says to when `--interactive` asks.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+fn template(sandbox: &Sandbox, content: serde_json::Value) {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY/.claude/settings.json"),
+        content.to_string(),
+    );
+}
+
+fn settings_path(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude/settings.json")
+}
+
+fn settings(sandbox: &Sandbox) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(settings_path(sandbox)).unwrap()).unwrap()
+}
+
+fn configure(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .args(args)
+        .assert()
+        .success();
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+/// A deploy of `maxTokens: 4000`, which the user then raised to 16000,
+/// followed by a template that wants 8000 and a new model
+fn edited_after_deploy() -> Sandbox {
+    let sandbox = Sandbox::new();
+    template(&sandbox, json!({ "maxTokens": 4000, "model": "sonnet" }));
+    configure(&sandbox, &[]);
+    sandbox.write(
+        &settings_path(&sandbox),
+        json!({ "maxTokens": 16000, "model": "sonnet" }).to_string(),
+    );
+    template(&sandbox, json!({ "maxTokens": 8000, "model": "opus" }));
+    sandbox
+}
+
+#[test]
+fn changed_keys_are_kept_and_listed() {
+    let sandbox = edited_after_deploy();
+
+    let output = configure(&sandbox, &[]);
+
+    assert_eq!(
+        settings(&sandbox),
+        json!({ "maxTokens": 16000, "model": "opus" })
+    );
+    assert!(
+        output.contains("\"maxTokens\": 16000 (template: 8000)"),
+        "{}",
+        output
+    );
+
+    // Still the user's on the next deploy
+    configure(&sandbox, &[]);
+    assert_eq!(settings(&sandbox)["maxTokens"], 16000);
+}
+
+#[test]
+fn force_settings_replaces_them() {
+    let sandbox = edited_after_deploy();
+
+    configure(&sandbox, &["--force-settings"]);
+
+    assert_eq!(
+        settings(&sandbox),
+        json!({ "maxTokens": 8000, "model": "opus" })
+    );
+}
+
+#[test]
+fn interactive_asks_about_each_one() {
+    let sandbox = edited_after_deploy();
+
+    sandbox
+        .command()
+        .args(["-y", "--interactive", "configure", "--tool", "claude-code"])
+        .env("CODE_ASSIST_NON_INTERACTIVE", "0")
+        .write_stdin("y\n")
+        .assert()
+        .success();
+
+    assert_eq!(settings(&sandbox)["maxTokens"], 8000);
+}
+
+#[test]
+fn interactive_without_a_terminal_names_the_flag() {
+    let sandbox = edited_after_deploy();
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "--interactive", "configure", "--tool", "claude-code"])
+        .assert()
+        .code(11);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("--force-settings"), "{}", stderr);
+    assert_eq!(settings(&sandbox)["maxTokens"], 16000);
+}
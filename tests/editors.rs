@@ -0,0 +1,101 @@
+//! Extensions and settings go to every installed VS Code variant, or just
+//! the one picked with `--editor`.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::PathBuf;
+
+/// A sandbox with VS Code and VS Code Insiders installed, a VS Code
+/// settings template, and an extension to install
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    for command in ["code", "code-insiders"] {
+        sandbox.stub(
+            command,
+            &format!("echo \"$@\" >> \"$HOME/{}.log\"\necho 1.90.0", command),
+        );
+    }
+
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    let config_dir = sandbox.local().join(platform_dir).join("USER-DIRECTORY");
+    sandbox.write(
+        &config_dir.join("vscode-settings.json"),
+        r#"{ "editor.fontSize": 14 }"#,
+    );
+    sandbox.write(&sandbox.local().join("VSIX").join("helper-1.0.0.vsix"), "");
+    sandbox
+}
+
+fn settings_path(sandbox: &Sandbox, data_dir: &str) -> PathBuf {
+    let app_data = if cfg!(target_os = "macos") {
+        sandbox.home().join("Library").join("Application Support")
+    } else {
+        sandbox.home().join(".config")
+    };
+    app_data.join(data_dir).join("User").join("settings.json")
+}
+
+/// Arguments each run of `command` was given
+fn calls(sandbox: &Sandbox, command: &str) -> String {
+    fs::read_to_string(sandbox.home().join(format!("{}.log", command))).unwrap_or_default()
+}
+
+#[test]
+fn deploys_to_every_installed_editor() {
+    let sandbox = sandbox();
+
+    sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+
+    assert!(settings_path(&sandbox, "Code").exists());
+    assert!(settings_path(&sandbox, "Code - Insiders").exists());
+    assert!(!settings_path(&sandbox, "VSCodium").exists());
+    assert!(calls(&sandbox, "code").contains("--install-extension"));
+    assert!(calls(&sandbox, "code-insiders").contains("--install-extension"));
+}
+
+#[test]
+fn editor_flag_picks_one_variant() {
+    let sandbox = sandbox();
+
+    sandbox
+        .command()
+        .args(["-y", "--editor", "insiders"])
+        .args(["configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+
+    assert!(!settings_path(&sandbox, "Code").exists());
+    assert!(settings_path(&sandbox, "Code - Insiders").exists());
+    assert!(!calls(&sandbox, "code").contains("--install-extension"));
+    assert!(calls(&sandbox, "code-insiders").contains("--install-extension"));
+}
+
+#[test]
+fn check_reports_the_variants_found() {
+    let sandbox = sandbox();
+
+    let assert = sandbox
+        .command()
+        .args(["check", "--json"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+
+    assert!(
+        stdout.contains("code 1.90.0, code-insiders 1.90.0"),
+        "{}",
+        stdout
+    );
+}
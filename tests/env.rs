@@ -0,0 +1,96 @@
+//! `env` lists what code-assist set in the environment and whether this
+//! shell has it, and with `--shell` prints statements to catch up.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+
+/// A Claude Code install that set a certificate variable and a PATH entry
+fn deployed(sandbox: &Sandbox, cert: &str) {
+    let home = sandbox.home();
+    let bin = home.join(".claude/bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let state = serde_json::json!({
+        "schema": 1,
+        "env_vars": [{ "name": "NODE_EXTRA_CA_CERTS", "value": cert }],
+        "path_entries": [bin],
+    });
+    sandbox.write(
+        &sandbox
+            .state_dir()
+            .join(".code-assist-state.claude-code.json"),
+        state.to_string(),
+    );
+}
+
+#[test]
+fn a_variable_naming_a_missing_file_is_flagged() {
+    let sandbox = Sandbox::new();
+    let cert = sandbox.home().join("certs/gone.pem");
+    deployed(&sandbox, &cert.to_string_lossy());
+
+    let assert = sandbox
+        .command()
+        .args(["env", "--tool", "claude-code"])
+        .env("NODE_EXTRA_CA_CERTS", &cert)
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("NODE_EXTRA_CA_CERTS"), "{}", stdout);
+    assert!(
+        stdout.contains(&format!("this shell: {}", cert.display())),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("{} doesn't exist", cert.display())),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!(
+            "PATH entry {}",
+            sandbox.home().join(".claude/bin").display()
+        )),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn shell_statements_are_quoted_for_that_shell() {
+    let sandbox = Sandbox::new();
+    deployed(&sandbox, "/etc/it's.pem");
+    let bin = sandbox.home().join(".claude/bin");
+
+    let assert = sandbox
+        .command()
+        .args(["env", "--shell", "fish"])
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "set -gx NODE_EXTRA_CA_CERTS '/etc/it\\'s.pem'".to_string(),
+            format!("set -gx PATH '{}' $PATH", bin.display()),
+        ]
+    );
+}
+
+#[test]
+fn nothing_to_show_without_an_install() {
+    let sandbox = Sandbox::new();
+
+    let assert = sandbox
+        .command()
+        .args(["env", "--shell", "bash"])
+        .assert()
+        .success();
+
+    assert!(assert.get_output().stdout.is_empty());
+}
@@ -0,0 +1,136 @@
+//! Each failure category maps to its documented exit code.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{Sandbox, PLATFORM_IDS};
+use std::fs;
+
+#[test]
+fn success_exits_0() {
+    let sandbox = Sandbox::new();
+    sandbox.command().arg("list").assert().code(0);
+}
+
+#[test]
+fn missing_prerequisites_exits_2() {
+    let sandbox = Sandbox::new();
+    fs::remove_file(sandbox.bin().join("git")).unwrap();
+    sandbox.command().arg("check").assert().code(2);
+}
+
+#[test]
+fn download_failure_exits_3() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn checksum_mismatch_exits_4() {
+    let sandbox = Sandbox::new();
+    let local = sandbox.local();
+    let wrong = "0".repeat(64);
+
+    let platforms: serde_json::Map<String, serde_json::Value> = PLATFORM_IDS
+        .iter()
+        .map(|id| (id.to_string(), serde_json::json!({ "checksum": wrong })))
+        .collect();
+    sandbox.write(&local.join("latest"), "1.0.0");
+    sandbox.write_signed(
+        &local.join("1.0.0").join("manifest.json"),
+        serde_json::json!({ "platforms": platforms }).to_string(),
+    );
+    for id in PLATFORM_IDS {
+        sandbox.write(
+            &local.join("1.0.0").join(id).join("claude"),
+            "not the real binary",
+        );
+    }
+
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn configuration_failure_exits_5() {
+    let sandbox = Sandbox::new();
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+
+    sandbox.write(&sandbox.home().join(".claude").join("settings.json"), "{}");
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY")
+            .join(".claude")
+            .join("settings.json"),
+        "not json",
+    );
+
+    sandbox
+        .command()
+        .args(["configure", "--tool", "claude-code"])
+        .assert()
+        .code(5);
+}
+
+#[test]
+fn unknown_tool_exits_6() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "no-such-tool"])
+        .assert()
+        .code(6);
+}
+
+#[test]
+fn insufficient_disk_space_exits_8() {
+    let sandbox = Sandbox::new();
+    let local = sandbox.local();
+    let checksum = "0".repeat(64);
+
+    // No disk has room for a binary this large
+    let platforms: serde_json::Map<String, serde_json::Value> = PLATFORM_IDS
+        .iter()
+        .map(|id| {
+            let entry = serde_json::json!({ "checksum": checksum, "size": u64::MAX / 4 });
+            (id.to_string(), entry)
+        })
+        .collect();
+    sandbox.write(&local.join("latest"), "1.0.0");
+    sandbox.write_signed(
+        &local.join("1.0.0").join("manifest.json"),
+        serde_json::json!({ "platforms": platforms }).to_string(),
+    );
+
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(8);
+}
+
+#[test]
+fn declined_confirmation_exits_130() {
+    let sandbox = Sandbox::new();
+    sandbox
+        .command()
+        .args(["uninstall", "--tool", "claude-code"])
+        .env("CODE_ASSIST_NON_INTERACTIVE", "0")
+        .write_stdin("n\n")
+        .assert()
+        .code(130);
+}
@@ -0,0 +1,279 @@
+//! Extensions listed by marketplace ID are installed unless already at the
+//! wanted version, with VSIX files as the fallback.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+/// A `code` that has two extensions installed, logs each install, and
+/// fails to install `redhat.vscode-yaml` from the marketplace
+fn sandbox(extensions: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stub(
+        "code",
+        r#"case "$1" in
+  --list-extensions)
+    echo ms-python.python@2024.1.0
+    echo anthropic.claude-code@2.0.0
+    ;;
+  --install-extension)
+    echo "$@" >> "$HOME/code.log"
+    case "$2" in redhat.vscode-yaml*) echo "marketplace unreachable" >&2; exit 1 ;; esac
+    ;;
+  *) echo 1.90.0 ;;
+esac"#,
+    );
+    sandbox.write(&sandbox.local().join("extensions.json"), extensions);
+    sandbox
+}
+
+/// Arguments each `code --install-extension` was given, one run per line
+fn installs(sandbox: &Sandbox) -> Vec<String> {
+    fs::read_to_string(sandbox.home().join("code.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn configure(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y"])
+        .args(args)
+        .args(["configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn installs_only_what_is_missing_or_outdated() {
+    let sandbox = sandbox(
+        r#"{ "extensions": [
+            "ms-python.python@2024.1.0",
+            "anthropic.claude-code@2.1.0",
+            { "id": "github.copilot" }
+        ] }"#,
+    );
+
+    let stdout = configure(&sandbox, &[]);
+
+    assert_eq!(
+        installs(&sandbox),
+        [
+            "--install-extension anthropic.claude-code@2.1.0 --force",
+            "--install-extension github.copilot",
+        ]
+    );
+    assert!(
+        stdout.contains("2 installed, 1 already up to date, 0 failed"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn falls_back_to_vsix_when_the_marketplace_fails() {
+    let sandbox = sandbox(r#"["redhat.vscode-yaml@1.15.0", "github.copilot"]"#);
+    let vsix = sandbox.local().join("VSIX").join("vscode-yaml-1.15.0.vsix");
+    sandbox.write(&vsix, "");
+
+    let stdout = configure(&sandbox, &[]);
+
+    assert_eq!(
+        installs(&sandbox),
+        [
+            "--install-extension redhat.vscode-yaml@1.15.0".to_string(),
+            format!("--install-extension {}", vsix.display()),
+            "--install-extension github.copilot".to_string(),
+        ]
+    );
+    assert!(
+        stdout.contains("2 installed, 0 already up to date, 0 failed"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn offline_installs_only_from_vsix_files() {
+    let sandbox = sandbox(r#"["redhat.vscode-yaml", "github.copilot"]"#);
+    let vsix = sandbox.local().join("VSIX").join("vscode-yaml-1.15.0.vsix");
+    sandbox.write(&vsix, "");
+
+    let stdout = configure(&sandbox, &["--offline"]);
+
+    assert_eq!(
+        installs(&sandbox),
+        [format!("--install-extension {}", vsix.display())]
+    );
+    assert!(
+        stdout.contains("1 installed, 0 already up to date, 1 failed"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn vsix_files_replace_only_older_versions() {
+    let sandbox = sandbox("[]");
+    let older = sandbox.local().join("VSIX").join("python-2023.9.0.vsix");
+    let newer = sandbox.local().join("VSIX").join("claude-code-2.0.1.vsix");
+    sandbox.write(&older, "");
+    sandbox.write(&newer, "");
+
+    let stdout = configure(&sandbox, &[]);
+
+    assert_eq!(
+        installs(&sandbox),
+        [format!("--install-extension {} --force", newer.display())]
+    );
+    assert!(
+        stdout.contains("python 2024.1.0 is up to date"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn force_extensions_reinstalls_everything() {
+    let sandbox = sandbox(r#"["ms-python.python@2024.1.0"]"#);
+
+    configure(&sandbox, &["--force-extensions"]);
+
+    assert_eq!(
+        installs(&sandbox),
+        ["--install-extension ms-python.python@2024.1.0 --force"]
+    );
+}
+
+/// A `code` that keeps its extensions in a file, so installs and uninstalls
+/// show up in later listings. A VSIX file installs as `acme.helper`. Only
+/// shell builtins are on the sandbox PATH.
+fn stateful_sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stub(
+        "code",
+        r#"list="$HOME/code-extensions"
+case "$1" in
+  --list-extensions) while read -r ext; do echo "$ext"; done < "$list" ;;
+  --install-extension)
+    case "$2" in *.vsix) id=acme.helper ;; *) id="${2%@*}" ;; esac
+    echo "$id@1.0.0" >> "$list"
+    ;;
+  --uninstall-extension)
+    echo "$2" >> "$HOME/uninstalled.log"
+    kept=""
+    while read -r ext; do
+      case "$ext" in "$2@"*) ;; *) kept="$kept$ext
+" ;; esac
+    done < "$list"
+    printf %s "$kept" > "$list"
+    ;;
+  *) echo 1.90.0 ;;
+esac"#,
+    );
+    // Installed by the user before code-assist ran
+    sandbox.write(
+        &sandbox.home().join("code-extensions"),
+        "ms-python.python@2024.1.0\n",
+    );
+    sandbox.write(
+        &sandbox.local().join("extensions.json"),
+        r#"["ms-python.python", "github.copilot"]"#,
+    );
+    sandbox.write(&sandbox.local().join("VSIX").join("helper-1.0.0.vsix"), "");
+    sandbox
+}
+
+fn uninstall(sandbox: &Sandbox) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "claude-code"])
+        .assert()
+        .success();
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn uninstall_removes_only_the_extensions_we_installed() {
+    let sandbox = stateful_sandbox();
+    configure(&sandbox, &[]);
+
+    let stdout = uninstall(&sandbox);
+
+    assert_eq!(
+        fs::read_to_string(sandbox.home().join("uninstalled.log")).unwrap(),
+        "github.copilot\nacme.helper\n"
+    );
+    assert_eq!(
+        fs::read_to_string(sandbox.home().join("code-extensions")).unwrap(),
+        "ms-python.python@2024.1.0\n"
+    );
+    assert!(
+        stdout.contains("2 removed, 0 already gone, 0 failed"),
+        "{}",
+        stdout
+    );
+
+    // Nothing is left recorded to remove again
+    let state_file = sandbox
+        .state_dir()
+        .join(".code-assist-state.claude-code.json");
+    let state = fs::read_to_string(state_file).unwrap_or_default();
+    assert!(!state.contains("copilot"), "{}", state);
+}
+
+#[test]
+fn uninstall_reports_extensions_already_removed() {
+    let sandbox = stateful_sandbox();
+    configure(&sandbox, &[]);
+    // The user removed one themselves
+    let list = sandbox.home().join("code-extensions");
+    let remaining = fs::read_to_string(&list)
+        .unwrap()
+        .replace("github.copilot@1.0.0\n", "");
+    fs::write(&list, remaining).unwrap();
+
+    let stdout = uninstall(&sandbox);
+
+    assert_eq!(
+        fs::read_to_string(sandbox.home().join("uninstalled.log")).unwrap(),
+        "acme.helper\n"
+    );
+    assert!(
+        stdout.contains("1 removed, 1 already gone, 0 failed"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn a_vsix_needing_a_newer_editor_is_skipped() {
+    let sandbox = sandbox(r#"["redhat.vscode-yaml"]"#);
+    let vsix = sandbox.local().join("VSIX").join("vscode-yaml-1.15.0.vsix");
+    sandbox.write(&vsix, "");
+    // Stands in for reading extension/package.json out of the VSIX
+    sandbox.stub("tar", r#"echo '{"engines": {"vscode": "^1.95.0"}}'"#);
+
+    let stdout = configure(&sandbox, &["--offline"]);
+
+    assert!(installs(&sandbox).is_empty());
+    assert!(
+        stdout.contains(
+            "vscode-yaml-1.15.0.vsix needs VS Code 1.95.0 or later (engines.vscode is \"^1.95.0\"), \
+             but 1.90.0 is installed"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("0 installed, 0 already up to date, 1 failed"),
+        "{}",
+        stdout
+    );
+}
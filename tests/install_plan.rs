@@ -0,0 +1,102 @@
+//! Before asking to go ahead, `install` lists what it will change.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{ca_certificate, Sandbox};
+
+const VERSION: &str = "2.0.0";
+
+/// A local release of Claude Code with a settings template and a
+/// certificate to deploy
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    let tree = sandbox.local().join(platform_dir).join("USER-DIRECTORY");
+    sandbox.write(
+        &tree.join(".claude").join("settings.json"),
+        r#"{ "model": "opus" }"#,
+    );
+    sandbox.write(
+        &tree.join("certs").join("corp.crt"),
+        ca_certificate("Corp Root", false),
+    );
+    sandbox
+}
+
+#[test]
+fn the_plan_is_printed_even_with_yes() {
+    let sandbox = sandbox();
+    let home = sandbox.home();
+
+    // Only the plan matters here: where environment variables can't be set,
+    // the certificate fails the install after it
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    let plan_end = stdout
+        .find("Installing Claude Code...")
+        .expect("install output");
+    let plan = &stdout[..plan_end];
+    for expected in [
+        format!("Claude Code {}:", VERSION),
+        format!(
+            "install the binary to {}",
+            home.join(".claude/bin/claude").display()
+        ),
+        format!("create {}", home.join(".claude/settings.json").display()),
+        format!("copy certificate {}", home.join("certs/corp.crt").display()),
+        "set NODE_EXTRA_CA_CERTS".to_string(),
+        format!("add {} to PATH", home.join(".claude/bin").display()),
+    ] {
+        assert!(
+            plan.contains(&expected),
+            "{} missing from:\n{}",
+            expected,
+            plan
+        );
+    }
+}
+
+#[test]
+fn existing_settings_are_merged() {
+    let sandbox = sandbox();
+    let settings = sandbox.home().join(".claude/settings.json");
+    sandbox.write(&settings, r#"{ "theme": "dark" }"#);
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        stdout.contains(&format!("merge settings into {}", settings.display())),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn a_dry_run_shows_its_own_plan_instead() {
+    let sandbox = sandbox();
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "--dry-run", "install", "--tool", "claude-code"])
+        .assert();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("Verify SHA-256 checksum"), "{}", stdout);
+    assert!(!stdout.contains("install the binary to"), "{}", stdout);
+}
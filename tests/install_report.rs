@@ -0,0 +1,109 @@
+//! `install --report` writes a JSON record of what the install did, also
+//! when it fails.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, Sandbox};
+
+const VERSION: &str = "2.0.0";
+
+fn steps(report: &serde_json::Value) -> Vec<(String, String)> {
+    report["tools"][0]["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| {
+            (
+                step["name"].as_str().unwrap().to_string(),
+                step["status"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn a_successful_install_is_recorded() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+    let path = sandbox.home().join("report.json");
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .arg("--report")
+        .arg(&path)
+        .assert()
+        .success();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(report["success"], true, "{}", report);
+    let tool = &report["tools"][0];
+    assert_eq!(tool["tool"], "claude-code");
+    assert_eq!(tool["version"], VERSION);
+    assert_eq!(tool["channel"], "stable");
+    assert_eq!(tool["download_source"], "local");
+    let steps = steps(&report);
+    assert!(steps.iter().all(|(_, status)| status == "ok"), "{:?}", steps);
+    assert!(
+        steps.iter().any(|(name, _)| name == "download"),
+        "{:?}",
+        steps
+    );
+}
+
+#[test]
+fn a_failed_install_marks_the_step_it_failed_in() {
+    let crashing = claude_binary(VERSION).replace(
+        "echo '2.0.0 (Claude Code)'; exit 0",
+        "echo 'Killed: 9' >&2; exit 137",
+    );
+    let sandbox = Sandbox::new();
+    sandbox.stage_binary(VERSION, crashing);
+    let path = sandbox.home().join("report.json");
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .arg("--report")
+        .arg(&path)
+        .assert()
+        .failure();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(report["success"], false);
+    assert_eq!(report["failed_step"], "claude-code: smoke test");
+    assert!(
+        report["error"].as_str().unwrap().contains("Killed: 9"),
+        "{}",
+        report
+    );
+    let steps = steps(&report);
+    assert_eq!(
+        steps.last().unwrap(),
+        &("smoke test".to_string(), "failed".to_string())
+    );
+}
+
+#[test]
+fn a_report_on_stdout_leaves_the_console_output_to_stderr() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .args(["--report", "-"])
+        .assert()
+        .success();
+    let output = assert.get_output();
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["success"], true);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Claude Code installed successfully"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,102 @@
+//! Ctrl+C stops an install cleanly: the download stops, nothing is put in
+//! place, and the exit code says it was interrupted.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{platform_id, respond, respond_truncated, sign, Sandbox, TestServer};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const VERSION: &str = "9.9.9";
+
+/// A binary big enough that trickling it out takes far longer than the test
+fn binary() -> Vec<u8> {
+    vec![b'#'; 4 * 1024 * 1024]
+}
+
+/// Serve a release whose binary arrives a little at a time
+fn slow_server() -> TestServer {
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": hex::encode(Sha256::digest(binary())) } }
+    })
+    .to_string();
+
+    TestServer::start(move |request, stream| {
+        if request.path == "/latest" {
+            respond(stream, "200 OK", &[], VERSION.as_bytes());
+        } else if request.path.ends_with("/manifest.json") {
+            respond(stream, "200 OK", &[], manifest.as_bytes());
+        } else if request.path.ends_with("/manifest.json.minisig") {
+            respond(stream, "200 OK", &[], sign(&manifest).as_bytes());
+        } else {
+            respond_truncated(stream, "200 OK", &[], &binary(), 0);
+            if request.method == "HEAD" {
+                return;
+            }
+            for _ in 0..1500 {
+                if stream.write_all(&[b'#'; 1024]).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+    })
+}
+
+/// Wait up to ten seconds for `done`
+fn wait_for(done: impl Fn() -> bool) {
+    let started = Instant::now();
+    while !done() {
+        assert!(started.elapsed() < Duration::from_secs(10), "timed out");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn ctrl_c_during_a_download_stops_without_changing_anything() {
+    let sandbox = Sandbox::new();
+    let server = slow_server();
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+
+    let child = sandbox
+        .process()
+        .args(["-y", "install", "--tool", "claude-code"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    wait_for(|| {
+        std::fs::read_dir(sandbox.cache_dir()).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().ends_with(".part"))
+        })
+    });
+
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(130), "{}", stdout);
+    assert!(
+        stdout.contains("Aborted — no changes were committed"),
+        "{}",
+        stdout
+    );
+    assert!(!sandbox.home().join(".claude").join("bin").exists());
+    let leftovers: Vec<String> = std::fs::read_dir(sandbox.cache_dir())
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.contains(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+}
@@ -0,0 +1,115 @@
+//! `--link` deploys files that aren't merged as symlinks to their templates,
+//! so a shared local directory stays the one copy to maintain.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::PathBuf;
+
+/// The `.claude` template directory in the platform config tree
+fn templates(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox
+        .local()
+        .join(platform_dir)
+        .join("USER-DIRECTORY")
+        .join(".claude")
+}
+
+fn claude_dir(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude")
+}
+
+fn run(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox.command().arg("-y").args(args).assert().success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+#[test]
+fn linked_templates_follow_the_source_and_settings_are_still_merged() {
+    let sandbox = Sandbox::new();
+    let template = templates(&sandbox).join("CLAUDE.md");
+    let deployed = claude_dir(&sandbox).join("CLAUDE.md");
+    let settings = claude_dir(&sandbox).join("settings.json");
+    sandbox.write(&template, "v1\n");
+    sandbox.write(
+        &templates(&sandbox).join("settings.json"),
+        r#"{ "model": "sonnet" }"#,
+    );
+
+    let output = run(&sandbox, &["--link", "configure", "--tool", "claude-code"]);
+    assert!(output.contains("Linked Claude CLAUDE.md"), "{}", output);
+    assert_eq!(fs::read_link(&deployed).unwrap(), template);
+    assert!(!settings.is_symlink());
+
+    // Changes to the template show up without another deploy
+    fs::write(&template, "v2\n").unwrap();
+    assert_eq!(fs::read_to_string(&deployed).unwrap(), "v2\n");
+    let output = run(&sandbox, &["--link", "configure", "--tool", "claude-code"]);
+    assert!(
+        output.contains("Claude templates already up to date"),
+        "{}",
+        output
+    );
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "status", "--tool", "claude-code", "--json"])
+        .assert()
+        .success();
+    let status: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let files = status["tools"][0]["deployed_files"].as_array().unwrap();
+    let entry = files
+        .iter()
+        .find(|f| f["path"] == deployed.to_str().unwrap())
+        .unwrap();
+    assert_eq!(entry["link"], template.to_str().unwrap());
+}
+
+#[test]
+fn deploying_without_link_replaces_the_link_with_a_copy() {
+    let sandbox = Sandbox::new();
+    let template = templates(&sandbox).join("CLAUDE.md");
+    let deployed = claude_dir(&sandbox).join("CLAUDE.md");
+    sandbox.write(&template, "team\n");
+
+    run(&sandbox, &["--link", "configure", "--tool", "claude-code"]);
+    assert!(deployed.is_symlink());
+
+    run(&sandbox, &["configure", "--tool", "claude-code"]);
+    assert!(!deployed.is_symlink());
+    assert_eq!(fs::read_to_string(&deployed).unwrap(), "team\n");
+
+    // Editing the copy leaves the template alone
+    fs::write(&deployed, "mine\n").unwrap();
+    assert_eq!(fs::read_to_string(&template).unwrap(), "team\n");
+}
+
+#[test]
+fn purging_removes_links_but_not_their_templates() {
+    let sandbox = Sandbox::new();
+    let template = templates(&sandbox).join("commands").join("review.md");
+    let deployed = claude_dir(&sandbox).join("commands").join("review.md");
+    sandbox.write(&template, "Review\n");
+
+    run(&sandbox, &["--link", "configure", "--tool", "claude-code"]);
+    assert!(deployed.is_symlink());
+
+    let output = run(&sandbox, &["uninstall", "--tool", "claude-code", "--purge"]);
+    assert!(output.contains("Removed link"), "{}", output);
+    assert!(!deployed.is_symlink());
+    assert!(!claude_dir(&sandbox).join("commands").exists());
+    assert_eq!(fs::read_to_string(&template).unwrap(), "Review\n");
+}
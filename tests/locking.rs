@@ -0,0 +1,92 @@
+//! Runs that change the installation take turns through a lock in
+//! `~/.claude`, and a lock left by a run that crashed doesn't get in the way.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{output, Sandbox};
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn lock_path(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude").join(".code-assist.lock")
+}
+
+fn pid_path(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude").join(".code-assist.pid")
+}
+
+/// Take the lock as another run would, recording `pid` as its holder
+fn hold_lock(sandbox: &Sandbox, pid: &str) -> File {
+    sandbox.write(&pid_path(sandbox), pid);
+    let file = File::create(lock_path(sandbox)).unwrap();
+    file.try_lock().unwrap();
+    file
+}
+
+#[test]
+fn a_held_lock_names_its_holder_and_exits_with_its_own_code() {
+    let sandbox = Sandbox::new();
+    let _held = hold_lock(&sandbox, "4242");
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .code(10);
+    let output = output(&assert);
+    assert!(output.contains("process 4242"), "{}", output);
+    assert!(output.contains("--wait"), "{}", output);
+
+    // Reading the installation needs no lock
+    let checked = sandbox
+        .command()
+        .args(["configure", "--check", "--tool", "claude-code"])
+        .output()
+        .unwrap();
+    assert_ne!(checked.status.code(), Some(10));
+}
+
+#[test]
+fn wait_runs_once_the_lock_is_released() {
+    let sandbox = Sandbox::new();
+    let held = hold_lock(&sandbox, "4242");
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(500));
+        drop(held);
+    });
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "--wait", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    release.join().unwrap();
+    let output = output(&assert);
+    assert!(
+        output.contains("Waiting for another code-assist run (process 4242)"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn a_lock_left_by_a_crashed_run_is_cleared() {
+    let sandbox = Sandbox::new();
+    sandbox.write(&pid_path(&sandbox), "4242");
+
+    let assert = sandbox
+        .command()
+        .args(["-v", "-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = output(&assert);
+    assert!(
+        output.contains("Cleared a stale lock left by process 4242"),
+        "{}",
+        output
+    );
+    assert!(!pid_path(&sandbox).exists());
+}
@@ -0,0 +1,79 @@
+//! Every run appends to `~/.claude/logs/code-assist.log`, which `logs`
+//! prints, and the file is rotated to stay within its size limit.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+fn stdout(assert: &assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn a_failed_run_is_logged_with_its_error() {
+    let sandbox = Sandbox::new();
+    sandbox.command().arg("list").assert().success();
+    sandbox
+        .command()
+        .args(["install", "--tool", "nonexistent"])
+        .assert()
+        .failure()
+        .code(6);
+
+    let assert = sandbox
+        .command()
+        .args(["logs", "--last-run"])
+        .assert()
+        .success();
+    let logged = stdout(&assert);
+    assert!(logged.contains("run started: code-assist"), "{}", logged);
+    assert!(logged.contains("install --tool nonexistent"), "{}", logged);
+    assert!(logged.contains("failed with exit code 6"), "{}", logged);
+    let ran_list = |logged: &str| logged.lines().any(|line| line.ends_with(" list"));
+    assert!(!ran_list(&logged), "{}", logged);
+
+    // Without --last-run, earlier runs are included
+    let assert = sandbox.command().arg("logs").assert().success();
+    assert!(ran_list(&stdout(&assert)), "{}", stdout(&assert));
+
+    let assert = sandbox
+        .command()
+        .args(["logs", "--path"])
+        .assert()
+        .success();
+    assert_eq!(
+        stdout(&assert).trim(),
+        sandbox
+            .home()
+            .join(".claude/logs/code-assist.log")
+            .display()
+            .to_string()
+    );
+}
+
+#[test]
+fn the_log_is_rotated_at_half_the_limit() {
+    let sandbox = Sandbox::new();
+    sandbox.config("[logs]\nmax_size_mb = 1\n");
+    let log = sandbox.home().join(".claude/logs/code-assist.log");
+    sandbox.write(&log, "old entry\n".repeat(60_000));
+
+    sandbox.command().arg("list").assert().success();
+
+    let previous = fs::read_to_string(log.with_extension("log.1")).unwrap();
+    assert!(previous.starts_with("old entry"), "{}", &previous[..40]);
+    let current = fs::read_to_string(&log).unwrap();
+    assert!(current.contains("run started"), "{}", current);
+    assert!(!current.contains("old entry"), "{}", current);
+}
+
+#[test]
+fn logging_can_be_turned_off() {
+    let sandbox = Sandbox::new();
+    sandbox.config("[logs]\nmax_size_mb = 0\n");
+    sandbox.command().arg("list").assert().success();
+    assert!(!sandbox.home().join(".claude/logs/code-assist.log").exists());
+}
@@ -0,0 +1,132 @@
+//! MCP servers from `mcp.json` are merged into `~/.claude.json` by name,
+//! leaving the user's own servers and the rest of the file alone.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+use std::fs;
+
+fn template(sandbox: &Sandbox, servers: serde_json::Value) {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY")
+            .join("mcp.json"),
+        json!({ "mcpServers": servers }).to_string(),
+    );
+}
+
+fn claude_json(sandbox: &Sandbox) -> serde_json::Value {
+    let content = fs::read_to_string(sandbox.home().join(".claude.json")).unwrap();
+    serde_json::from_str(&content).unwrap()
+}
+
+fn run(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox.command().arg("-y").args(args).assert().success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+fn configure(sandbox: &Sandbox, args: &[&str]) -> String {
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .chain(["configure", "--tool", "claude-code"])
+        .collect();
+    run(sandbox, &args)
+}
+
+#[test]
+fn servers_are_merged_by_name_with_paths_expanded() {
+    let sandbox = Sandbox::new();
+    template(
+        &sandbox,
+        json!({
+            "docs": { "command": "${HOME}/bin/docs-mcp", "env": { "CA": "${CERTS_DIR}/ca.crt" } },
+            "jira": { "command": "jira-mcp" }
+        }),
+    );
+    sandbox.write(
+        &sandbox.home().join(".claude.json"),
+        r#"{
+            "numStartups": 3,
+            "mcpServers": {
+                "mine": { "command": "my-mcp" },
+                "jira": { "command": "my-jira" }
+            }
+        }"#,
+    );
+
+    let output = configure(&sandbox, &[]);
+
+    let home = sandbox.home().to_string_lossy().to_string();
+    let config = claude_json(&sandbox);
+    assert_eq!(config["numStartups"], 3);
+    assert_eq!(config["mcpServers"]["mine"], json!({ "command": "my-mcp" }));
+    assert_eq!(
+        config["mcpServers"]["jira"],
+        json!({ "command": "my-jira" })
+    );
+    assert_eq!(
+        config["mcpServers"]["docs"]["command"],
+        format!("{}/bin/docs-mcp", home)
+    );
+    assert!(
+        config["mcpServers"]["docs"]["env"]["CA"]
+            .as_str()
+            .unwrap()
+            .ends_with("/ca.crt"),
+        "{}",
+        config
+    );
+    assert!(output.contains("Kept your MCP server jira"), "{}", output);
+
+    configure(&sandbox, &["--force"]);
+    assert_eq!(
+        claude_json(&sandbox)["mcpServers"]["jira"],
+        json!({ "command": "jira-mcp" })
+    );
+
+    run(&sandbox, &["uninstall", "--tool", "claude-code", "--purge"]);
+    let config = claude_json(&sandbox);
+    assert_eq!(config["numStartups"], 3);
+    assert_eq!(
+        config["mcpServers"],
+        json!({ "mine": { "command": "my-mcp" } })
+    );
+}
+
+#[test]
+fn deployed_servers_follow_the_template() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, json!({ "docs": { "command": "docs-v1" } }));
+    configure(&sandbox, &[]);
+
+    template(&sandbox, json!({ "docs": { "command": "docs-v2" } }));
+    let output = configure(&sandbox, &[]);
+    assert!(output.contains("Updated MCP server docs"), "{}", output);
+    assert_eq!(
+        claude_json(&sandbox)["mcpServers"]["docs"],
+        json!({ "command": "docs-v2" })
+    );
+
+    let output = configure(&sandbox, &[]);
+    assert!(
+        output.contains("MCP servers already up to date"),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,62 @@
+//! `check` and `doctor` try the download server and say why it can't be
+//! reached, along with the proxy in use.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{output, respond, Sandbox, TestServer};
+
+#[test]
+fn a_proxy_wanting_credentials_is_named() {
+    let sandbox = Sandbox::new();
+    let proxy = TestServer::start(|_, stream| {
+        respond(stream, "407 Proxy Authentication Required", &[], b"")
+    });
+
+    let assert = sandbox
+        .command()
+        .args(["--proxy", &proxy.url])
+        .args(["--download-url", "http://mirror.example.invalid/releases"])
+        .arg("doctor")
+        .assert()
+        .failure();
+    let diagnosed = output(&assert);
+    assert!(
+        diagnosed.contains(&format!(
+            "http://mirror.example.invalid/releases: proxy authentication required (HTTP 407), \
+             through proxy {}",
+            proxy.url
+        )),
+        "{}",
+        diagnosed
+    );
+}
+
+#[test]
+fn check_reports_the_server_in_its_json_without_failing() {
+    let sandbox = Sandbox::new();
+    let mirror = TestServer::start(|_, stream| respond(stream, "404 Not Found", &[], b""));
+
+    let assert = sandbox
+        .command()
+        .args(["--download-url", &mirror.url])
+        .args(["check", "--json"])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let network = &report["network"][0];
+    assert_eq!(network["url"], mirror.url.as_str());
+    assert_eq!(network["reachable"], false);
+    assert_eq!(network["problem"], "not_found");
+    assert_eq!(network["proxy"], serde_json::Value::Null);
+
+    // Offline, the server isn't tried
+    let assert = sandbox
+        .command()
+        .args(["--offline", "check", "--json"])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(report["network"], serde_json::Value::Null);
+}
@@ -0,0 +1,69 @@
+//! Without a terminal, or with `--non-interactive`, a prompt fails at once
+//! and names the flag that answers it, instead of waiting for input.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+
+fn settings_template(sandbox: &Sandbox) {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY/.claude/settings.json"),
+        r#"{ "model": "opus" }"#,
+    );
+}
+
+#[test]
+fn a_confirmation_without_a_terminal_fails_naming_yes() {
+    let sandbox = Sandbox::new();
+
+    let assert = sandbox
+        .command()
+        .args(["uninstall", "--tool", "claude-code"])
+        .assert()
+        .code(11);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("pass --yes"), "{}", stderr);
+}
+
+#[test]
+fn the_flag_wins_over_answers_from_a_pipe() {
+    let sandbox = Sandbox::new();
+    settings_template(&sandbox);
+
+    let assert = sandbox
+        .command()
+        .args(["--non-interactive", "configure", "--tool", "claude-code"])
+        .env("CODE_ASSIST_NON_INTERACTIVE", "0")
+        .write_stdin("y\n")
+        .assert()
+        .code(11);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("pass --yes"), "{}", stderr);
+    assert!(!sandbox.home().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn yes_answers_ahead_of_time() {
+    let sandbox = Sandbox::new();
+    settings_template(&sandbox);
+
+    sandbox
+        .command()
+        .args(["--non-interactive", "-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+
+    assert!(sandbox.home().join(".claude/settings.json").exists());
+}
@@ -0,0 +1,85 @@
+//! Every directory code-assist deploys to can be moved, with a
+//! `CODE_ASSIST_*_DIR` variable or the `[paths]` table, and `status` shows
+//! where they ended up.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::Sandbox;
+
+fn status_paths(sandbox: &Sandbox, envs: &[(&str, PathBuf)]) -> serde_json::Value {
+    let assert = sandbox
+        .command()
+        .args(["--offline", "status", "--tool", "claude-code", "--json"])
+        .envs(envs.iter().map(|(name, value)| (name, value)))
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    report["paths"].clone()
+}
+
+#[test]
+fn settings_follow_a_moved_claude_dir() {
+    let sandbox = Sandbox::new();
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY/.claude/settings.json"),
+        r#"{ "model": "opus" }"#,
+    );
+    let claude_dir = sandbox.home().join("profiles/claude");
+
+    sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .env("CODE_ASSIST_CLAUDE_DIR", &claude_dir)
+        .assert()
+        .success();
+
+    assert!(claude_dir.join("settings.json").exists());
+    assert!(!sandbox.home().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn the_environment_wins_over_the_config_file() {
+    let sandbox = Sandbox::new();
+    let certs = sandbox.home().join("pki");
+    let vscode = sandbox.home().join("portable/data/user-data/User");
+    sandbox.config(&format!(
+        "[paths]\ncerts_dir = \"{}\"\nvscode_user_dir = \"{}\"\n",
+        sandbox.home().join("from-file").display(),
+        vscode.display()
+    ));
+
+    let paths = status_paths(&sandbox, &[("CODE_ASSIST_CERTS_DIR", certs.clone())]);
+
+    assert_eq!(paths["certs_dir"], certs.to_str().unwrap());
+    assert_eq!(paths["vscode_settings_dir"], vscode.to_str().unwrap());
+    assert_eq!(
+        paths["claude_dir"],
+        sandbox.home().join(".claude").to_str().unwrap()
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn vscode_settings_live_under_xdg_config_home() {
+    let sandbox = Sandbox::new();
+    let xdg = sandbox.home().join("xdg");
+
+    let paths = status_paths(&sandbox, &[("XDG_CONFIG_HOME", xdg.clone())]);
+
+    assert_eq!(
+        paths["vscode_settings_dir"],
+        xdg.join("Code/User").to_str().unwrap()
+    );
+}
@@ -0,0 +1,116 @@
+//! Each tool checks only the prerequisites it declares, and `check` without
+//! a tool checks what any of them needs.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+
+/// A sandbox declaring `acme`, an npm package, so it needs Node.js 18 and npm
+fn sandbox(node_version: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &sandbox.local().join("tools.d/acme.toml"),
+        "name = \"acme\"\nnpm = \"@acme/cli\"\n",
+    );
+    sandbox.stub("node", &format!("echo v{}", node_version));
+    sandbox.stub("npm", "echo 10.2.4");
+    sandbox
+}
+
+#[test]
+fn checks_only_what_the_tool_needs() {
+    let sandbox = sandbox("20.11.0");
+    sandbox.stub("git", "exit 1");
+
+    let assert = sandbox
+        .command()
+        .args(["check", "--tool", "acme", "--json"])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let names: Vec<&str> = report["prerequisites"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Node.js", "npm"]);
+
+    // Claude Code still needs Git, so checking everything fails
+    sandbox.command().arg("check").assert().code(2);
+}
+
+#[test]
+fn an_old_version_fails_the_check() {
+    let sandbox = sandbox("16.20.0");
+
+    let assert = sandbox
+        .command()
+        .args(["check", "--tool", "acme"])
+        .assert()
+        .code(2);
+    let output = assert.get_output();
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        all.contains("too old (found 16.20.0, need 18 or later)"),
+        "{}",
+        all
+    );
+}
+
+#[test]
+fn the_minimum_node_version_is_configurable() {
+    let sandbox = sandbox("16.20.0");
+    sandbox.config("[prerequisites]\nnode = \"16\"\n");
+
+    sandbox
+        .command()
+        .args(["check", "--tool", "acme"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn missing_npm_is_reported_as_not_installed() {
+    let sandbox = sandbox("20.11.0");
+    sandbox.stub("npm", "exit 127");
+
+    let assert = sandbox
+        .command()
+        .args(["check", "--tool", "acme"])
+        .assert()
+        .code(2);
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    let all = format!("{}{}", stdout, stderr);
+    assert!(all.contains("npm - not installed"), "{}", all);
+}
+
+#[test]
+fn claude_code_needs_a_recent_vs_code() {
+    let sandbox = Sandbox::new();
+    sandbox.stub("code", "echo 1.80.0");
+
+    let assert = sandbox
+        .command()
+        .args(["check", "--tool", "claude-code"])
+        .assert()
+        .code(2);
+    let output = assert.get_output();
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        all.contains("VS Code - too old (found 1.80.0, need 1.85 or later)"),
+        "{}",
+        all
+    );
+}
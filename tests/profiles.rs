@@ -0,0 +1,117 @@
+//! Profiles in `local/profiles/<name>/` hold their own config trees; the
+//! one deployed is remembered, and the flat layout is the default profile.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn platform_tree(root: &Path) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    root.join(platform_dir).join("USER-DIRECTORY")
+}
+
+fn run(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox.command().arg("-y").args(args).assert().success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+fn model(sandbox: &Sandbox) -> serde_json::Value {
+    let path = sandbox.home().join(".claude").join("settings.json");
+    let settings: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    settings["model"].clone()
+}
+
+#[test]
+fn the_chosen_profile_is_remembered() {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &platform_tree(&sandbox.local())
+            .join(".claude")
+            .join("settings.json"),
+        r#"{ "model": "sonnet" }"#,
+    );
+    let data_science = sandbox.local().join("profiles").join("data-science");
+    sandbox.write(
+        &platform_tree(&data_science)
+            .join(".claude")
+            .join("settings.json"),
+        r#"{ "model": "opus" }"#,
+    );
+
+    run(&sandbox, &["configure", "--tool", "claude-code"]);
+    assert_eq!(model(&sandbox), "sonnet");
+
+    run(
+        &sandbox,
+        &[
+            "configure",
+            "--tool",
+            "claude-code",
+            "--profile",
+            "data-science",
+        ],
+    );
+    assert_eq!(model(&sandbox), "opus");
+
+    // A bare configure re-applies the same profile, over the hand edit
+    sandbox.write(
+        &sandbox.home().join(".claude").join("settings.json"),
+        r#"{ "model": "haiku" }"#,
+    );
+    run(
+        &sandbox,
+        &["--force-settings", "configure", "--tool", "claude-code"],
+    );
+    assert_eq!(model(&sandbox), "opus");
+
+    let list = run(&sandbox, &["list"]);
+    assert!(list.contains("(profile data-science)"), "{}", list);
+    assert!(list.contains("Profiles: data-science, default"), "{}", list);
+
+    let assert = sandbox
+        .command()
+        .args(["status", "--tool", "claude-code", "--json"])
+        .assert()
+        .success();
+    let status: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(status["tools"][0]["profile"], "data-science");
+}
+
+#[test]
+fn unknown_profiles_fail_with_the_available_ones() {
+    let sandbox = Sandbox::new();
+    fs::create_dir_all(sandbox.local().join("profiles").join("standard")).unwrap();
+
+    let assert = sandbox
+        .command()
+        .args([
+            "-y",
+            "configure",
+            "--tool",
+            "claude-code",
+            "--profile",
+            "finance",
+        ])
+        .assert()
+        .code(5);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(
+        stderr.contains("Unknown profile 'finance' (available: default, standard)"),
+        "{}",
+        stderr
+    );
+}
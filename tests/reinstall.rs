@@ -0,0 +1,62 @@
+//! Installing the version that's already installed skips the download and
+//! setup and only re-applies the configuration.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, output, Sandbox};
+
+const VERSION: &str = "2.0.0";
+
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+    sandbox
+}
+
+fn install(sandbox: &Sandbox, force: bool) -> String {
+    let mut command = sandbox.command();
+    command.args(["--offline", "-y", "install", "--tool", "claude-code"]);
+    if force {
+        command.arg("--force");
+    }
+    output(&command.assert().success())
+}
+
+#[test]
+fn installing_the_installed_version_again_skips_setup() {
+    let sandbox = sandbox();
+    install(&sandbox, false);
+
+    let output = install(&sandbox, false);
+    assert!(
+        output.contains("Claude Code 2.0.0 is already installed"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("Running Claude Code setup"), "{}", output);
+    assert!(output.contains("Deploying configurations"), "{}", output);
+}
+
+#[test]
+fn a_changed_binary_is_installed_again() {
+    let sandbox = sandbox();
+    install(&sandbox, false);
+    let binary = sandbox.home().join(".claude").join("bin").join("claude");
+    sandbox.write(&binary, format!("{}# changed\n", claude_binary(VERSION)));
+
+    let output = install(&sandbox, false);
+    assert!(!output.contains("is already installed"), "{}", output);
+    assert!(output.contains("Running Claude Code setup"), "{}", output);
+}
+
+#[test]
+fn force_installs_the_installed_version_again() {
+    let sandbox = sandbox();
+    install(&sandbox, false);
+
+    let output = install(&sandbox, true);
+    assert!(!output.contains("is already installed"), "{}", output);
+    assert!(output.contains("Running Claude Code setup"), "{}", output);
+}
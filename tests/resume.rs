@@ -0,0 +1,199 @@
+//! Interrupted binary downloads are resumed with HTTP range requests.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{
+    claude_binary, platform_id, respond, respond_truncated, sign, Request, Sandbox, TestServer,
+};
+use sha2::{Digest, Sha256};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const VERSION: &str = "9.9.9";
+
+/// A stand-in for the Claude binary that `install` can run, padded so a
+/// truncated response is clearly partial
+fn binary() -> Vec<u8> {
+    let mut body = claude_binary(VERSION).into_bytes();
+    body.extend(std::iter::repeat_n(b"# padding\n".as_slice(), 4000).flatten());
+    body
+}
+
+fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Serve `latest` and the manifest, passing binary GET requests to
+/// `serve_binary` along with the number of them before this one. HEAD
+/// requests for the binary's size are answered here.
+fn release_server(
+    serve_binary: impl Fn(&Request, &mut TcpStream, usize) + Send + 'static,
+) -> TestServer {
+    let binary_requests = Arc::new(AtomicUsize::new(0));
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": checksum(&binary()) } }
+    })
+    .to_string();
+
+    TestServer::start(move |request, stream| {
+        let binary_path = format!("/{}/{}/claude", VERSION, platform_id());
+        if request.path == "/latest" {
+            respond(stream, "200 OK", &[], VERSION.as_bytes());
+        } else if request.path == format!("/{}/manifest.json", VERSION) {
+            respond(stream, "200 OK", &[], manifest.as_bytes());
+        } else if request.path == format!("/{}/manifest.json.minisig", VERSION) {
+            respond(stream, "200 OK", &[], sign(&manifest).as_bytes());
+        } else if request.path == binary_path && request.method == "HEAD" {
+            respond_truncated(stream, "200 OK", &[], &binary(), 0);
+        } else if request.path == binary_path {
+            serve_binary(
+                request,
+                stream,
+                binary_requests.fetch_add(1, Ordering::SeqCst),
+            );
+        } else {
+            respond(stream, "404 Not Found", &[], b"");
+        }
+    })
+}
+
+/// Answer a `Range: bytes=N-` request with 206 and the rest of `body`, or
+/// anything else with the whole body
+fn serve_range(request: &Request, stream: &mut TcpStream, body: &[u8]) {
+    let start = request
+        .header("range")
+        .and_then(|r| r.strip_prefix("bytes="))
+        .and_then(|r| r.strip_suffix('-'))
+        .and_then(|n| n.parse::<usize>().ok());
+
+    match start {
+        Some(start) if start < body.len() => respond(
+            stream,
+            "206 Partial Content",
+            &[(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, body.len() - 1, body.len()),
+            )],
+            &body[start..],
+        ),
+        Some(_) => respond(stream, "416 Range Not Satisfiable", &[], b""),
+        None => respond(stream, "200 OK", &[], body),
+    }
+}
+
+/// Binary requests made to `server`, in order, with their `Range` headers
+fn binary_ranges(server: &TestServer) -> Vec<Option<String>> {
+    server
+        .requests()
+        .iter()
+        .filter(|r| r.method == "GET" && r.path.ends_with("/claude"))
+        .map(|r| r.header("range").map(str::to_string))
+        .collect()
+}
+
+/// Where the sandbox caches the verified binary, and its `.part` file
+fn cached_binary(sandbox: &Sandbox) -> (std::path::PathBuf, std::path::PathBuf) {
+    let name = format!(
+        "claude-{}-{}-{}",
+        VERSION,
+        platform_id(),
+        &checksum(&binary())[..16]
+    );
+    let path = sandbox.cache_dir().join(&name);
+    (path, sandbox.cache_dir().join(format!("{}.part", name)))
+}
+
+fn install(sandbox: &Sandbox, server: &TestServer) -> assert_cmd::assert::Assert {
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+    sandbox
+        .command()
+        .args([
+            "--retry-delay",
+            "10ms",
+            "-y",
+            "install",
+            "--tool",
+            "claude-code",
+        ])
+        .assert()
+}
+
+#[test]
+fn resumes_after_connection_drops() {
+    let sandbox = Sandbox::new();
+    let server = release_server(|request, stream, n| {
+        let body = binary();
+        if n == 0 {
+            respond_truncated(stream, "200 OK", &[], &body, body.len() / 2);
+        } else {
+            serve_range(request, stream, &body);
+        }
+    });
+
+    install(&sandbox, &server).success();
+
+    let half = binary().len() / 2;
+    assert_eq!(
+        binary_ranges(&server),
+        vec![None, Some(format!("bytes={}-", half))]
+    );
+    let (path, part) = cached_binary(&sandbox);
+    assert_eq!(std::fs::read(path).unwrap(), binary());
+    assert!(!part.exists());
+}
+
+#[test]
+fn restarts_when_server_ignores_range() {
+    let sandbox = Sandbox::new();
+    let server = release_server(|_, stream, n| {
+        let body = binary();
+        if n == 0 {
+            respond_truncated(stream, "200 OK", &[], &body, body.len() / 2);
+        } else {
+            respond(stream, "200 OK", &[], &body);
+        }
+    });
+
+    install(&sandbox, &server).success();
+
+    assert_eq!(binary_ranges(&server).len(), 2);
+    let (path, _) = cached_binary(&sandbox);
+    assert_eq!(std::fs::read(path).unwrap(), binary());
+}
+
+#[test]
+fn resumes_partial_file_from_earlier_run() {
+    let sandbox = Sandbox::new();
+    let (path, part) = cached_binary(&sandbox);
+    sandbox.write(&part, &binary()[..1000]);
+    let server = release_server(|request, stream, _| serve_range(request, stream, &binary()));
+
+    install(&sandbox, &server).success();
+
+    assert_eq!(
+        binary_ranges(&server),
+        vec![Some("bytes=1000-".to_string())]
+    );
+    assert_eq!(std::fs::read(path).unwrap(), binary());
+}
+
+#[test]
+fn corrupt_partial_file_is_discarded() {
+    let sandbox = Sandbox::new();
+    let (path, part) = cached_binary(&sandbox);
+    sandbox.write(&part, vec![b'x'; 1000]);
+    let server = release_server(|request, stream, _| serve_range(request, stream, &binary()));
+
+    install(&sandbox, &server).success();
+
+    // The resumed file fails its checksum, so it's downloaded again in full
+    assert_eq!(
+        binary_ranges(&server),
+        vec![Some("bytes=1000-".to_string()), None]
+    );
+    assert_eq!(std::fs::read(path).unwrap(), binary());
+    assert!(!part.exists());
+}
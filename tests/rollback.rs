@@ -0,0 +1,134 @@
+//! A deploy that fails partway puts back every file it had already changed.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{ca_certificate, Sandbox};
+use std::fs;
+use std::path::PathBuf;
+
+const ORIGINAL: &str = r#"{ "theme": "dark" }"#;
+
+/// The platform config tree in the local artifacts directory
+fn config_dir(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.local().join(platform_dir).join("USER-DIRECTORY")
+}
+
+fn claude_settings(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude").join("settings.json")
+}
+
+fn vscode_settings(sandbox: &Sandbox) -> PathBuf {
+    let user_dir = if cfg!(target_os = "macos") {
+        sandbox
+            .home()
+            .join("Library")
+            .join("Application Support")
+            .join("Code")
+            .join("User")
+    } else {
+        sandbox.home().join(".config").join("Code").join("User")
+    };
+    user_dir.join("settings.json")
+}
+
+/// A Claude settings template and the user's own settings
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &config_dir(&sandbox).join(".claude").join("settings.json"),
+        r#"{ "theme": "light" }"#,
+    );
+    sandbox.write(&claude_settings(&sandbox), ORIGINAL);
+    sandbox
+}
+
+/// Run a deploy that must fail with a configuration error, returning stderr
+fn configure_fails(sandbox: &Sandbox) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .code(5);
+    String::from_utf8_lossy(&assert.get_output().stderr).to_string()
+}
+
+/// Files in `dir` that a transaction staged or set aside
+fn leftovers(dir: PathBuf) -> Vec<String> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".code-assist-new") || name.ends_with(".code-assist-old"))
+        .collect()
+}
+
+#[test]
+fn failed_settings_write_restores_earlier_files() {
+    let sandbox = sandbox();
+    sandbox.write(
+        &config_dir(&sandbox).join("certs").join("corp.crt"),
+        ca_certificate("Corp Root", false),
+    );
+    sandbox.write(
+        &config_dir(&sandbox).join("vscode-settings.json"),
+        r#"{ "editor.fontSize": 14 }"#,
+    );
+    // A directory where the VS Code settings file should be
+    sandbox.write(&vscode_settings(&sandbox).join("keep"), "");
+
+    let stderr = configure_fails(&sandbox);
+
+    assert!(
+        stderr.contains("Failed to deploy configuration"),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        fs::read_to_string(claude_settings(&sandbox)).unwrap(),
+        ORIGINAL
+    );
+    assert!(!sandbox.home().join("certs").exists());
+    assert!(vscode_settings(&sandbox).join("keep").exists());
+    assert!(leftovers(sandbox.home().join(".claude")).is_empty());
+}
+
+/// Environment variables can't be set on Linux, so that step always fails
+/// there once there's a certificate to point at
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_environment_step_rolls_back_files() {
+    let sandbox = sandbox();
+    sandbox.write(
+        &config_dir(&sandbox)
+            .join("certs")
+            .join("ZscalerRootCertificate-2048-SHA256.crt"),
+        ca_certificate("Zscaler Root CA", false),
+    );
+
+    let stderr = configure_fails(&sandbox);
+
+    assert!(
+        stderr.contains("Failed to set environment variables"),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        fs::read_to_string(claude_settings(&sandbox)).unwrap(),
+        ORIGINAL
+    );
+    assert!(!sandbox.home().join("certs").exists());
+    assert!(leftovers(sandbox.home().join(".claude")).is_empty());
+
+    // Nothing was deployed, so nothing is recorded for uninstall to revert
+    let state_file = sandbox
+        .state_dir()
+        .join(".code-assist-state.claude-code.json");
+    let state = fs::read_to_string(state_file).unwrap_or_default();
+    assert!(!state.contains("certs"), "{}", state);
+}
@@ -0,0 +1,99 @@
+//! `self-update` replaces the running binary with a newer verified build
+//! from the update URL, or with `--check` only reports one.
+
+#![cfg(unix)]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{platform_id, Sandbox};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+const NEW_BUILD: &str = "#!/bin/sh\necho code-assist 99.0.0\n";
+
+/// A copy of the binary under test to update, and a `file://` update URL
+/// publishing 99.0.0 with `checksum` in its manifest
+fn publish(sandbox: &Sandbox, checksum: &str) -> (PathBuf, String) {
+    let app = sandbox.home().join("app/code-assist");
+    fs::create_dir_all(app.parent().unwrap()).unwrap();
+    fs::copy(assert_cmd::cargo::cargo_bin("code-assist"), &app).unwrap();
+
+    let updates = sandbox.home().join("updates");
+    sandbox.write(&updates.join("latest"), "99.0.0\n");
+    sandbox.write_signed(
+        &updates.join("99.0.0/manifest.json"),
+        format!(
+            r#"{{ "version": "99.0.0", "platforms": {{ "{}": {{ "checksum": "{}" }} }} }}"#,
+            platform_id(),
+            checksum
+        ),
+    );
+    sandbox.write(
+        &updates.join(format!("99.0.0/{}/code-assist", platform_id())),
+        NEW_BUILD,
+    );
+    (app, format!("file://{}", updates.display()))
+}
+
+fn checksum(content: &str) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+#[test]
+fn check_reports_a_newer_build_without_installing_it() {
+    let sandbox = Sandbox::new();
+    let (app, url) = publish(&sandbox, &checksum(NEW_BUILD));
+    let before = fs::read(&app).unwrap();
+
+    let assert = Command::from_std(sandbox.process_of(&app))
+        .args(["self-update", "--check", "--url", &url])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        stdout.contains("code-assist 99.0.0 is available"),
+        "{}",
+        stdout
+    );
+    assert_eq!(fs::read(&app).unwrap(), before);
+}
+
+#[test]
+fn replaces_the_running_binary_with_the_verified_build() {
+    let sandbox = Sandbox::new();
+    let (app, url) = publish(&sandbox, &checksum(NEW_BUILD));
+
+    Command::from_std(sandbox.process_of(&app))
+        .env("CODE_ASSIST_SELF_UPDATE_URL", &url)
+        .args(["-y", "self-update"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&app).unwrap(), NEW_BUILD);
+    assert!(!app.with_file_name("code-assist.new").exists());
+}
+
+#[test]
+fn a_build_failing_verification_leaves_the_binary_alone() {
+    let sandbox = Sandbox::new();
+    let (app, url) = publish(&sandbox, &checksum("something else"));
+    let before = fs::read(&app).unwrap();
+
+    let assert = Command::from_std(sandbox.process_of(&app))
+        .args(["-y", "self-update", "--url", &url])
+        .assert()
+        .failure();
+    let output = assert.get_output();
+    let reported = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        reported.contains("does not match the manifest's checksum"),
+        "{}",
+        reported
+    );
+    assert_eq!(fs::read(&app).unwrap(), before);
+}
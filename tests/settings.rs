@@ -0,0 +1,191 @@
+//! Settings merges show their changes before writing, and accept comments
+//! and trailing commas as VS Code writes them.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::path::PathBuf;
+
+/// Stage a Claude settings template in the platform config tree
+fn template(sandbox: &Sandbox, content: &str) {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.write(
+        &sandbox
+            .local()
+            .join(platform_dir)
+            .join("USER-DIRECTORY")
+            .join(".claude")
+            .join("settings.json"),
+        content,
+    );
+}
+
+fn settings_path(sandbox: &Sandbox) -> PathBuf {
+    sandbox.home().join(".claude").join("settings.json")
+}
+
+/// Backups made next to the settings file
+fn backups(sandbox: &Sandbox) -> Vec<PathBuf> {
+    fs::read_dir(sandbox.home().join(".claude"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().ends_with(".bak"))
+        .collect()
+}
+
+fn configure(sandbox: &Sandbox) -> String {
+    let assert = sandbox
+        .command()
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+fn settings(sandbox: &Sandbox) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(settings_path(sandbox)).unwrap()).unwrap()
+}
+
+#[test]
+fn merges_commented_settings_and_keeps_a_backup() {
+    let sandbox = Sandbox::new();
+    template(
+        &sandbox,
+        "{\n  // Company defaults\n  \"model\": \"sonnet\",\n}\n",
+    );
+    let original =
+        "{\n  // My theme\n  \"theme\": \"dark\", /* keep */\n  \"env\": { \"A\": \"1\", },\n}\n";
+    sandbox.write(&settings_path(&sandbox), original);
+
+    let output = configure(&sandbox);
+
+    assert_eq!(
+        settings(&sandbox),
+        serde_json::json!({ "theme": "dark", "env": { "A": "1" }, "model": "sonnet" })
+    );
+    let backups = backups(&sandbox);
+    assert_eq!(backups.len(), 1, "{:?}", backups);
+    assert_eq!(fs::read_to_string(&backups[0]).unwrap(), original);
+    assert!(
+        output.contains("Comments in") && output.contains("could not be preserved"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn trailing_commas_alone_need_no_backup() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, r#"{ "model": "sonnet" }"#);
+    sandbox.write(&settings_path(&sandbox), "{ \"theme\": \"dark\", }");
+
+    configure(&sandbox);
+
+    assert_eq!(
+        settings(&sandbox),
+        serde_json::json!({ "theme": "dark", "model": "sonnet" })
+    );
+    assert!(backups(&sandbox).is_empty());
+}
+
+#[test]
+fn empty_settings_file_is_treated_as_empty() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, r#"{ "model": "sonnet" }"#);
+    sandbox.write(&settings_path(&sandbox), "");
+
+    configure(&sandbox);
+
+    assert_eq!(settings(&sandbox), serde_json::json!({ "model": "sonnet" }));
+}
+
+#[test]
+fn corrupted_settings_are_backed_up_and_the_deploy_goes_on() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, r#"{ "model": "sonnet" }"#);
+    sandbox.write(&settings_path(&sandbox), r#"{ "theme": "da"#);
+
+    let output = configure(&sandbox);
+
+    assert_eq!(settings(&sandbox), serde_json::json!({ "model": "sonnet" }));
+    assert_eq!(output.matches("can't be merged into").count(), 1, "{}", output);
+    assert!(output.contains("Saved the original as"), "{}", output);
+    let broken: Vec<PathBuf> = fs::read_dir(sandbox.home().join(".claude"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().contains("settings.json.broken-"))
+        .collect();
+    assert_eq!(broken.len(), 1);
+    assert_eq!(fs::read_to_string(&broken[0]).unwrap(), r#"{ "theme": "da"#);
+}
+
+#[test]
+fn dry_run_shows_the_changes_without_writing() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, r#"{ "model": "sonnet", "theme": "light" }"#);
+    let original = r#"{ "model": "opus", "keep": true }"#;
+    sandbox.write(&settings_path(&sandbox), original);
+
+    let assert = sandbox
+        .command()
+        .args(["--dry-run", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+
+    assert!(
+        stdout.contains(r#"~ "model": "opus" -> "sonnet""#),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(r#"+ "theme": "light""#), "{}", stdout);
+    assert!(
+        stdout.contains("1 other key(s) left untouched"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        fs::read_to_string(settings_path(&sandbox)).unwrap(),
+        original
+    );
+}
+
+#[test]
+fn declining_the_changes_leaves_settings_alone() {
+    let sandbox = Sandbox::new();
+    template(&sandbox, r#"{ "model": "sonnet" }"#);
+    let original = r#"{ "model": "opus" }"#;
+    sandbox.write(&settings_path(&sandbox), original);
+
+    let assert = sandbox
+        .command()
+        .args(["configure", "--tool", "claude-code"])
+        // Answer the prompt from a pipe
+        .env("CODE_ASSIST_NON_INTERACTIVE", "0")
+        .write_stdin("n\n")
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+
+    assert!(
+        stdout.contains("Left Claude settings unchanged"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        fs::read_to_string(settings_path(&sandbox)).unwrap(),
+        original
+    );
+}
@@ -0,0 +1,146 @@
+//! `export` writes the installed tools and what was deployed for them, and
+//! `import` makes another machine match it after showing what differs.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{output, Sandbox};
+use std::fs;
+
+/// A sandbox declaring `acme`, a binary served from a `file://` URL that
+/// merges `settings.json` into `~/.acme`
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let dist = sandbox.local().join("dist");
+    sandbox.write(
+        &sandbox.local().join("tools.d/acme.toml"),
+        format!(
+            r#"name = "acme"
+display_name = "Acme CLI"
+binary = "acme"
+version = "1.4.0"
+
+[download]
+url = "file://{}/${{VERSION}}/${{BINARY}}"
+
+[[config]]
+source = "settings.json"
+dest = "${{HOME}}/.acme/settings.json"
+"#,
+            dist.display()
+        ),
+    );
+    sandbox.write(&dist.join("1.4.0/acme"), "#!/bin/sh\necho acme 1.4.0\n");
+    sandbox.write(
+        &sandbox.local().join("acme/settings.json"),
+        r#"{"telemetry": false}"#,
+    );
+    sandbox
+}
+
+#[test]
+fn export_records_versions_and_deployed_settings() {
+    let sandbox = sandbox();
+    sandbox
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .success();
+
+    let assert = sandbox.command().arg("export").assert().success();
+    let setup: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(setup["schema"], 1);
+    let acme = setup["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == "acme")
+        .expect("acme is exported");
+    assert_eq!(acme["version"], "1.4.0");
+    assert_eq!(
+        acme["settings"],
+        serde_json::json!([{
+            "file": "~/.acme/settings.json",
+            "key": "telemetry",
+            "value": false
+        }])
+    );
+}
+
+#[test]
+fn import_shows_the_differences_and_applies_them() {
+    let exported = sandbox();
+    exported
+        .command()
+        .args(["-y", "install", "--tool", "acme"])
+        .assert()
+        .success();
+    let file = exported.home().join("setup.json");
+    exported
+        .command()
+        .args(["export", "--output"])
+        .arg(&file)
+        .assert()
+        .success();
+
+    let assert = exported
+        .command()
+        .arg("import")
+        .arg(&file)
+        .assert()
+        .success();
+    assert!(
+        output(&assert).contains("already matches"),
+        "{}",
+        output(&assert)
+    );
+
+    // Another machine without acme gets it installed
+    let other = sandbox();
+    let assert = other
+        .command()
+        .args(["--dry-run", "import"])
+        .arg(&file)
+        .assert()
+        .success();
+    let planned = output(&assert);
+    assert!(planned.contains("install 1.4.0"), "{}", planned);
+    assert!(!other.home().join(".acme/bin/acme").exists());
+
+    let assert = other
+        .command()
+        .args(["-y", "import"])
+        .arg(&file)
+        .assert()
+        .success();
+    assert!(
+        output(&assert).contains("This machine now matches"),
+        "{}",
+        output(&assert)
+    );
+    assert!(other.home().join(".acme/bin/acme").exists());
+    let settings: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(other.home().join(".acme/settings.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(settings["telemetry"], false);
+}
+
+#[test]
+fn a_file_from_a_newer_format_is_refused() {
+    let sandbox = Sandbox::new();
+    let file = sandbox.home().join("setup.json");
+    sandbox.write(
+        &file,
+        r#"{"schema": 99, "exported_by": "9.0.0", "platform": "darwin-arm64", "tools": []}"#,
+    );
+
+    sandbox
+        .command()
+        .arg("import")
+        .arg(&file)
+        .assert()
+        .failure()
+        .code(5);
+}
@@ -0,0 +1,162 @@
+//! With a manifest key configured, manifests must carry a valid signature
+//! from it before their checksums are trusted, unless `--allow-unsigned` is
+//! passed. Without one, they're used as served.
+
+#![cfg(unix)]
+
+mod common;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use common::{
+    claude_binary, manifest_key, output, platform_id, respond, sign, Sandbox, TestServer,
+};
+use sha2::{Digest, Sha256};
+
+const VERSION: &str = "9.9.9";
+
+fn manifest() -> String {
+    let checksum = hex::encode(Sha256::digest(claude_binary(VERSION)));
+    serde_json::json!({
+        "platforms": { platform_id(): { "checksum": checksum } }
+    })
+    .to_string()
+}
+
+/// Stage a release in the local fallback, with the manifest unsigned
+fn stage_local_release(sandbox: &Sandbox) {
+    sandbox.stage_release(VERSION);
+    let manifest_path = sandbox.local().join(VERSION).join("manifest.json");
+    std::fs::remove_file(manifest_path.with_extension("json.minisig")).unwrap();
+}
+
+#[test]
+fn unsigned_manifest_is_refused() {
+    let sandbox = Sandbox::new();
+    stage_local_release(&sandbox);
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(7);
+
+    let output = output(&assert);
+    assert!(
+        output.contains("No signature found for the 9.9.9 manifest"),
+        "{}",
+        output
+    );
+    assert!(output.contains("--allow-unsigned"), "{}", output);
+}
+
+#[test]
+fn tampered_manifest_is_refused() {
+    let sandbox = Sandbox::new();
+    stage_local_release(&sandbox);
+    let manifest_path = sandbox.local().join(VERSION).join("manifest.json");
+    sandbox.write_signed(&manifest_path, manifest());
+    sandbox.write(
+        &manifest_path,
+        manifest().replace("platforms", "platforms "),
+    );
+
+    let assert = sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .code(7);
+
+    let output = output(&assert);
+    assert!(
+        output.contains("Invalid signature on the 9.9.9 manifest"),
+        "{}",
+        output
+    );
+    assert!(output.contains("does not match the manifest"), "{}", output);
+}
+
+#[test]
+fn allow_unsigned_installs_with_a_warning() {
+    let sandbox = Sandbox::new();
+    stage_local_release(&sandbox);
+
+    let assert = sandbox
+        .command()
+        .args([
+            "--offline",
+            "--allow-unsigned",
+            "-y",
+            "install",
+            "--tool",
+            "claude-code",
+        ])
+        .assert()
+        .success();
+
+    let output = output(&assert);
+    assert!(
+        output.contains("continuing because of --allow-unsigned"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn without_a_key_unsigned_manifests_are_used() {
+    let sandbox = Sandbox::new();
+    stage_local_release(&sandbox);
+
+    let assert = sandbox
+        .command()
+        .env_remove("CODE_ASSIST_MANIFEST_KEY")
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+        .success();
+
+    let output = output(&assert);
+    assert!(!output.contains("signature"), "{}", output);
+}
+
+#[test]
+fn remote_manifest_not_signed_by_the_configured_key_is_refused() {
+    let sandbox = Sandbox::new();
+    let server = TestServer::start(|request, stream| {
+        let manifest = manifest();
+        if request.path == "/latest" {
+            respond(stream, "200 OK", &[], VERSION.as_bytes());
+        } else if request.path.ends_with("/manifest.json") {
+            respond(stream, "200 OK", &[], manifest.as_bytes());
+        } else if request.path.ends_with("/manifest.json.minisig") {
+            respond(stream, "200 OK", &[], sign(&manifest).as_bytes());
+        } else {
+            respond(stream, "200 OK", &[], claude_binary(VERSION).as_bytes());
+        }
+    });
+
+    // The test key under another key id, from the config file
+    let mut other_key = STANDARD.decode(manifest_key()).unwrap();
+    other_key[2] ^= 0xff;
+    sandbox.config(&format!(
+        "[download]\nmanifest_key = \"{}\"\n",
+        STANDARD.encode(other_key)
+    ));
+
+    let assert = sandbox
+        .command()
+        .env_remove("CODE_ASSIST_MANIFEST_KEY")
+        .args([
+            "--download-url",
+            &server.url,
+            "-y",
+            "install",
+            "--tool",
+            "claude-code",
+        ])
+        .assert()
+        .code(7);
+
+    let output = output(&assert);
+    assert!(output.contains("Invalid signature"), "{}", output);
+    assert!(output.contains("made with another key"), "{}", output);
+}
@@ -0,0 +1,61 @@
+//! An install only succeeds once the installed binary runs and reports the
+//! version that was installed.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, output, Sandbox};
+
+const VERSION: &str = "2.0.0";
+
+/// Stage `binary` as the local release of `VERSION`
+fn sandbox(binary: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.stage_binary(VERSION, binary);
+    sandbox
+}
+
+fn install(sandbox: &Sandbox) -> assert_cmd::assert::Assert {
+    sandbox
+        .command()
+        .args(["--offline", "-y", "install", "--tool", "claude-code"])
+        .assert()
+}
+
+#[test]
+fn a_binary_that_runs_and_reports_the_version_passes() {
+    let sandbox = sandbox(&claude_binary(VERSION));
+    let output = output(&install(&sandbox).success());
+    assert!(output.contains("--version' reports 2.0.0"), "{}", output);
+}
+
+#[test]
+fn a_binary_that_crashes_fails_the_install_with_its_stderr() {
+    let crashing = claude_binary(VERSION).replace(
+        "echo '2.0.0 (Claude Code)'; exit 0",
+        "echo 'Killed: 9' >&2; exit 137",
+    );
+    let sandbox = sandbox(&crashing);
+
+    let installed = output(&install(&sandbox).failure());
+    assert!(installed.contains("Killed: 9"), "{}", installed);
+    let install_dir = sandbox.home().join(".claude").join("bin");
+    assert!(!install_dir.join(".code-assist-version").exists());
+
+    let assert = sandbox.command().arg("doctor").assert();
+    let output = output(&assert);
+    assert!(
+        output.contains("failed its last check after install"),
+        "{}",
+        output
+    );
+    assert!(output.contains("Killed: 9"), "{}", output);
+}
+
+#[test]
+fn a_binary_reporting_another_version_fails_the_install() {
+    let sandbox = sandbox(&claude_binary("1.0.0"));
+    let output = output(&install(&sandbox).failure());
+    assert!(output.contains("not 2.0.0"), "{}", output);
+}
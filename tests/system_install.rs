@@ -0,0 +1,72 @@
+//! `--system` installs put the binary where every user finds it and leave
+//! per-user configuration alone.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{output, platform_id, Sandbox};
+use sha2::{Digest, Sha256};
+
+const BINARY: &str = "#!/bin/sh\n";
+
+fn sandbox() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let local = sandbox.local();
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": hex::encode(Sha256::digest(BINARY)) } }
+    });
+    sandbox.write(&local.join("latest"), "1.0.0");
+    sandbox.write_signed(
+        &local.join("1.0.0").join("manifest.json"),
+        manifest.to_string(),
+    );
+    sandbox.write(
+        &local.join("1.0.0").join(platform_id()).join("claude"),
+        BINARY,
+    );
+    sandbox
+}
+
+#[test]
+fn a_system_install_skips_per_user_setup() {
+    let sandbox = sandbox();
+    let assert = sandbox
+        .command()
+        .args(["--offline", "--dry-run", "-y", "install", "--system"])
+        .args(["--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = output(&assert);
+
+    assert!(
+        output.contains("to /usr/local/claude/bin/claude"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("install'"), "{}", output);
+    assert!(
+        output.contains("Skipping VS Code extensions and settings"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("Deploying configurations"), "{}", output);
+    assert!(!sandbox.home().join(".claude").join("bin").exists());
+}
+
+#[test]
+fn system_mode_takes_no_profile_or_purge() {
+    let sandbox = sandbox();
+    sandbox
+        .command()
+        .args(["-y", "install", "--system", "--profile", "lab"])
+        .args(["--tool", "claude-code"])
+        .assert()
+        .failure();
+    sandbox
+        .command()
+        .args(["-y", "uninstall", "--system", "--purge"])
+        .args(["--tool", "claude-code"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,94 @@
+//! A remote that accepts connections but stops responding times out and
+//! falls back to the local artifacts instead of hanging the installer.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{claude_binary, platform_id, respond, respond_truncated, sign, Sandbox, TestServer};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+const VERSION: &str = "9.9.9";
+
+fn install(sandbox: &Sandbox, server: &TestServer) -> (assert_cmd::assert::Assert, Duration) {
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+    let started = Instant::now();
+    let assert = sandbox
+        .command()
+        .args([
+            "--timeout",
+            "1",
+            "--retries",
+            "1",
+            "-y",
+            "install",
+            "--tool",
+            "claude-code",
+        ])
+        .assert();
+    (assert, started.elapsed())
+}
+
+/// Hold the connection open without ever answering
+fn hang() {
+    std::thread::sleep(Duration::from_secs(600));
+}
+
+#[test]
+fn unresponsive_remote_falls_back_to_local() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+    let server = TestServer::start(|_, _| hang());
+
+    let (assert, elapsed) = install(&sandbox, &server);
+
+    let output = assert.success().get_output().stdout.clone();
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(
+        stdout.contains("Remote timed out after 1s, falling back to local"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Using local fallback (verified)"),
+        "{}",
+        stdout
+    );
+    assert!(elapsed < Duration::from_secs(20), "took {:?}", elapsed);
+}
+
+#[test]
+fn stalled_download_falls_back_to_local() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_release(VERSION);
+    let checksum = hex::encode(Sha256::digest(claude_binary(VERSION)));
+    let manifest = serde_json::json!({
+        "platforms": { platform_id(): { "checksum": checksum } }
+    })
+    .to_string();
+    let server = TestServer::start(move |request, stream| {
+        if request.path == "/latest" {
+            respond(stream, "200 OK", &[], VERSION.as_bytes());
+        } else if request.path.ends_with("/manifest.json") {
+            respond(stream, "200 OK", &[], manifest.as_bytes());
+        } else if request.path.ends_with("/manifest.json.minisig") {
+            respond(stream, "200 OK", &[], sign(&manifest).as_bytes());
+        } else {
+            respond_truncated(stream, "200 OK", &[], claude_binary(VERSION).as_bytes(), 4);
+            hang();
+        }
+    });
+
+    let (assert, elapsed) = install(&sandbox, &server);
+
+    let output = assert.success().get_output().stdout.clone();
+    let stdout = String::from_utf8_lossy(&output);
+    assert!(stdout.contains("Download timed out after 1s"), "{}", stdout);
+    assert!(
+        stdout.contains("Remote timed out after 1s, falling back to local"),
+        "{}",
+        stdout
+    );
+    assert!(elapsed < Duration::from_secs(20), "took {:?}", elapsed);
+}
@@ -0,0 +1,71 @@
+//! Uninstall says what it removed and what it couldn't, rather than
+//! reporting success with files left behind.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+/// Claude installed, with an uninstaller that fails so code-assist cleans
+/// up by hand
+fn installed() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let binary = sandbox.home().join(".claude/bin/claude");
+    sandbox.write(
+        &binary,
+        "#!/bin/sh\nif [ \"$1\" = --version ]; then echo '2.1.0 (Claude Code)'; exit 0; fi\nexit 1\n",
+    );
+    fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+    sandbox
+}
+
+#[test]
+fn report_lists_the_removed_files() {
+    let sandbox = installed();
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "claude-code", "--report", "-"])
+        .assert()
+        .success();
+
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let removed = report["tools"][0]["files_removed"].as_array().unwrap();
+    assert!(
+        removed
+            .iter()
+            .any(|path| path.as_str().unwrap().ends_with(".claude/bin/claude")),
+        "{}",
+        report
+    );
+    assert!(report["tools"][0].get("removal_failures").is_none());
+    assert!(!sandbox.home().join(".claude/bin").exists());
+}
+
+#[test]
+fn files_left_behind_fail_the_uninstall() {
+    let sandbox = installed();
+    // Something the purge can't remove as a directory
+    sandbox.write(&sandbox.cache_dir(), "not a directory");
+
+    let assert = sandbox
+        .command()
+        .args(["-y", "uninstall", "--tool", "claude-code", "--purge"])
+        .assert()
+        .failure();
+
+    let output = assert.get_output();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("Removed 2 item(s):"), "{}", stdout);
+    assert!(stdout.contains("Could not remove 1 item(s):"), "{}", stdout);
+    assert!(
+        stderr.contains("1 item(s) could not be removed"),
+        "{}",
+        stderr
+    );
+    assert!(!stdout.contains("uninstalled successfully"), "{}", stdout);
+}
@@ -0,0 +1,84 @@
+//! `verify` checks every file a local artifacts directory ships with.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use sha2::{Digest, Sha256};
+
+const VERSION: &str = "9.9.9";
+const BINARY: &[u8] = b"#!/bin/sh\nexit 0\n";
+
+/// Stage a complete local artifacts directory
+fn stage(sandbox: &Sandbox) {
+    let local = sandbox.local();
+    let checksum = hex::encode(Sha256::digest(BINARY));
+    let manifest = serde_json::json!({
+        "platforms": {
+            "darwin-arm64": { "checksum": checksum },
+            "win32-x64": { "checksum": checksum },
+        }
+    });
+    sandbox.stage_manifest(VERSION, manifest);
+    sandbox.write(&local.join(VERSION).join("darwin-arm64/claude"), BINARY);
+    sandbox.write(&local.join(VERSION).join("win32-x64/claude.exe"), BINARY);
+    sandbox.write(&local.join("VSIX/extension-1.0.0.vsix"), b"PK\x03\x04rest");
+    for dir in ["WIN/USER-DIRECTORY", "MACOS/USER-DIRECTORY"] {
+        sandbox.write(&local.join(dir).join(".claude/settings.json"), "{}");
+    }
+}
+
+fn verify_json(sandbox: &Sandbox) -> (assert_cmd::assert::Assert, serde_json::Value) {
+    let assert = sandbox.command().args(["verify", "--json"]).assert();
+    let report = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    (assert, report)
+}
+
+/// Names of the checks that failed
+fn failed(report: &serde_json::Value) -> Vec<String> {
+    report["sections"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|section| section["checks"].as_array().unwrap())
+        .filter(|check| check["passed"] == false)
+        .map(|check| check["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn intact_directory_passes() {
+    let sandbox = Sandbox::new();
+    stage(&sandbox);
+
+    let (assert, report) = verify_json(&sandbox);
+
+    assert.success();
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["version"], VERSION);
+    assert!(failed(&report).is_empty(), "{}", report);
+}
+
+#[test]
+fn corrupt_and_missing_files_fail() {
+    let sandbox = Sandbox::new();
+    stage(&sandbox);
+    let local = sandbox.local();
+    sandbox.write(&local.join(VERSION).join("darwin-arm64/claude"), "garbage");
+    std::fs::remove_file(local.join(VERSION).join("win32-x64/claude.exe")).unwrap();
+    std::fs::remove_dir_all(local.join("MACOS")).unwrap();
+
+    let (assert, report) = verify_json(&sandbox);
+
+    assert.code(1);
+    assert_eq!(report["ok"], false);
+    assert_eq!(
+        failed(&report),
+        vec![
+            "9.9.9/darwin-arm64/claude",
+            "9.9.9/win32-x64/claude.exe",
+            "MACOS/USER-DIRECTORY",
+        ]
+    );
+}
@@ -0,0 +1,86 @@
+//! `versions` lists what can be installed, newest first, from the mirror's
+//! index or else the local fallback.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{respond, Sandbox, TestServer};
+
+fn versions_json(sandbox: &Sandbox, args: &[&str]) -> serde_json::Value {
+    let assert = sandbox
+        .command()
+        .args(["versions", "--tool", "claude-code", "--json"])
+        .args(args)
+        .assert()
+        .success();
+    serde_json::from_slice(&assert.get_output().stdout).unwrap()
+}
+
+fn listed(report: &serde_json::Value) -> Vec<&str> {
+    report["versions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["version"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn lists_the_mirror_index_with_channel_heads() {
+    let sandbox = Sandbox::new();
+    let server = TestServer::start(|request, stream| match request.path.as_str() {
+        "/versions.json" => respond(
+            stream,
+            "200 OK",
+            &[],
+            br#"{"versions": ["1.9.0", "1.10.0", "1.2.3"]}"#,
+        ),
+        "/latest" => respond(stream, "200 OK", &[], b"1.10.0"),
+        "/latest-beta" => respond(stream, "200 OK", &[], b"1.11.0-beta.1"),
+        _ => respond(stream, "404 Not Found", &[], b""),
+    });
+    sandbox.config(&format!("download_base_url = \"{}\"", server.url));
+
+    let report = versions_json(&sandbox, &[]);
+
+    assert_eq!(report["source"], "remote");
+    assert_eq!(
+        listed(&report),
+        vec!["1.11.0-beta.1", "1.10.0", "1.9.0", "1.2.3"]
+    );
+    assert_eq!(
+        report["versions"][0]["channels"],
+        serde_json::json!(["beta"])
+    );
+    assert_eq!(
+        report["versions"][1]["channels"],
+        serde_json::json!(["stable"])
+    );
+}
+
+#[test]
+fn falls_back_to_local_version_directories() {
+    let sandbox = Sandbox::new();
+    let local = sandbox.local();
+    for version in ["2.0.9", "2.0.10", "2.1.0"] {
+        sandbox.write(&local.join(version).join("manifest.json"), "{}");
+    }
+    sandbox.write(&local.join("latest"), "2.1.0");
+    sandbox.write(
+        &sandbox.home().join(".claude/bin/.code-assist-version"),
+        "2.0.10",
+    );
+    sandbox.write(&sandbox.home().join(".claude/bin/claude"), "");
+
+    let report = versions_json(&sandbox, &["--offline", "--limit", "2"]);
+
+    assert_eq!(report["source"], "local fallback");
+    assert_eq!(report["total"], 3);
+    assert_eq!(listed(&report), vec!["2.1.0", "2.0.10"]);
+    assert_eq!(
+        report["versions"][0]["channels"],
+        serde_json::json!(["stable"])
+    );
+    assert_eq!(report["versions"][1]["installed"], true);
+}
@@ -0,0 +1,219 @@
+//! VS Code keybindings are merged entry by entry, and snippets are copied
+//! without replacing ones the user changed.
+
+#![cfg(unix)]
+
+mod common;
+
+use common::Sandbox;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+/// The platform config tree in the local artifacts directory
+fn config_dir(sandbox: &Sandbox) -> PathBuf {
+    let platform_dir = if cfg!(target_os = "macos") {
+        "MACOS"
+    } else {
+        "LINUX"
+    };
+    sandbox.local().join(platform_dir).join("USER-DIRECTORY")
+}
+
+/// VS Code's `User` directory, in the template tree when `template`
+fn user_dir(sandbox: &Sandbox, template: bool) -> PathBuf {
+    let root = if template {
+        config_dir(sandbox)
+    } else {
+        sandbox.home()
+    };
+    let app_data = if cfg!(target_os = "macos") {
+        root.join("Library").join("Application Support")
+    } else {
+        root.join(".config")
+    };
+    app_data.join("Code").join("User")
+}
+
+fn snippet(sandbox: &Sandbox) -> PathBuf {
+    user_dir(sandbox, false)
+        .join("snippets")
+        .join("python.json")
+}
+
+fn run(sandbox: &Sandbox, args: &[&str]) -> String {
+    let assert = sandbox.command().arg("-y").args(args).assert().success();
+    let output = assert.get_output();
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+fn configure(sandbox: &Sandbox, args: &[&str]) -> String {
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .chain(["configure", "--tool", "claude-code"])
+        .collect();
+    run(sandbox, &args)
+}
+
+fn purge(sandbox: &Sandbox) -> String {
+    run(sandbox, &["uninstall", "--tool", "claude-code", "--purge"])
+}
+
+fn keybindings(sandbox: &Sandbox) -> serde_json::Value {
+    let path = user_dir(sandbox, false).join("keybindings.json");
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn keybindings_are_appended_unless_already_bound() {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &user_dir(&sandbox, true).join("keybindings.json"),
+        r#"[
+            { "key": "ctrl+shift+c", "command": "claude.open" },
+            { "key": "ctrl+alt+c", "command": "claude.chat" }
+        ]"#,
+    );
+    sandbox.write(
+        &user_dir(&sandbox, false).join("keybindings.json"),
+        r#"// Mine
+        [
+            { "key": "ctrl+k", "command": "editor.fold" },
+            { "key": "Ctrl+Shift+C", "command": "claude.open", "when": "editorFocus" }
+        ]"#,
+    );
+
+    configure(&sandbox, &[]);
+
+    let user = json!([
+        { "key": "ctrl+k", "command": "editor.fold" },
+        { "key": "Ctrl+Shift+C", "command": "claude.open", "when": "editorFocus" }
+    ]);
+    let mut merged = user.as_array().unwrap().clone();
+    merged.push(json!({ "key": "ctrl+alt+c", "command": "claude.chat" }));
+    assert_eq!(keybindings(&sandbox), json!(merged));
+
+    // A second deploy has nothing to add
+    let output = configure(&sandbox, &[]);
+    assert!(
+        output.contains("VS Code keybindings already up to date"),
+        "{}",
+        output
+    );
+
+    purge(&sandbox);
+    assert_eq!(keybindings(&sandbox), user);
+}
+
+#[test]
+fn snippets_changed_by_the_user_are_kept_unless_forced() {
+    let sandbox = Sandbox::new();
+    let template = config_dir(&sandbox)
+        .join("vscode-snippets")
+        .join("python.json");
+    sandbox.write(&template, r#"{ "main": { "prefix": "main" } }"#);
+    sandbox.write(
+        &config_dir(&sandbox)
+            .join("vscode-snippets")
+            .join("._python.json"),
+        "",
+    );
+
+    configure(&sandbox, &[]);
+    assert_eq!(
+        fs::read_to_string(snippet(&sandbox)).unwrap(),
+        r#"{ "main": { "prefix": "main" } }"#
+    );
+    assert!(!snippet(&sandbox).with_file_name("._python.json").exists());
+
+    // Untouched snippets follow the template
+    sandbox.write(&template, r#"{ "main": { "prefix": "mn" } }"#);
+    configure(&sandbox, &[]);
+    assert_eq!(
+        fs::read_to_string(snippet(&sandbox)).unwrap(),
+        r#"{ "main": { "prefix": "mn" } }"#
+    );
+
+    // Edited ones don't, without --force
+    let edited = r#"{ "main": { "prefix": "mine" } }"#;
+    fs::write(snippet(&sandbox), edited).unwrap();
+    sandbox.write(&template, r#"{ "main": { "prefix": "m" } }"#);
+    let output = configure(&sandbox, &[]);
+    assert!(output.contains("use --force"), "{}", output);
+    assert_eq!(fs::read_to_string(snippet(&sandbox)).unwrap(), edited);
+
+    configure(&sandbox, &["--force"]);
+    assert_eq!(
+        fs::read_to_string(snippet(&sandbox)).unwrap(),
+        r#"{ "main": { "prefix": "m" } }"#
+    );
+    let backups: Vec<PathBuf> = fs::read_dir(sandbox.backups_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("vscode/snippets/python.json"))
+        .filter(|copy| copy.exists())
+        .collect();
+    assert_eq!(backups.len(), 1, "{:?}", backups);
+    assert_eq!(fs::read_to_string(&backups[0]).unwrap(), edited);
+}
+
+#[test]
+fn purge_removes_only_unchanged_snippets() {
+    let sandbox = Sandbox::new();
+    let templates = config_dir(&sandbox).join("vscode-snippets");
+    sandbox.write(&templates.join("python.json"), "{}");
+    sandbox.write(&templates.join("rust.json"), "{}");
+    configure(&sandbox, &[]);
+    fs::write(
+        snippet(&sandbox).with_file_name("rust.json"),
+        r#"{ "a": {} }"#,
+    )
+    .unwrap();
+
+    let output = purge(&sandbox);
+
+    assert!(!snippet(&sandbox).exists());
+    assert!(snippet(&sandbox).with_file_name("rust.json").exists());
+    assert!(
+        output.contains("rust.json (changed since it was deployed)"),
+        "{}",
+        output
+    );
+}
+
+/// Under WSL without the Windows drive, VS Code's settings can't be reached,
+/// but Claude's still land in the Linux home
+#[cfg(target_os = "linux")]
+#[test]
+fn wsl_without_the_windows_host_skips_only_vscode_settings() {
+    let sandbox = Sandbox::new();
+    sandbox.write(
+        &user_dir(&sandbox, true).join("settings.json"),
+        r#"{ "editor.tabSize": 2 }"#,
+    );
+    sandbox.write(
+        &config_dir(&sandbox).join(".claude").join("CLAUDE.md"),
+        "# Team rules\n",
+    );
+
+    let assert = sandbox
+        .command()
+        .env("WSL_DISTRO_NAME", "Ubuntu")
+        .args(["-y", "configure", "--tool", "claude-code"])
+        .assert()
+        .success();
+    let output = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+
+    assert!(output.contains("Skipping VS Code settings"), "{}", output);
+    assert!(
+        output.contains("settings.json as settings.json"),
+        "{}",
+        output
+    );
+    assert!(!user_dir(&sandbox, false).join("settings.json").exists());
+    assert!(sandbox.home().join(".claude").join("CLAUDE.md").exists());
+}